@@ -0,0 +1,246 @@
+//! Bindings of the core wallet operations for mobile apps, generated with
+//! [uniffi](https://mozilla.github.io/uniffi-rs/).
+//!
+//! The bindings cover mnemonic handling, reading and writing encrypted wallet
+//! files in the format of the `helium-wallet` CLI, and building signed
+//! transfer and claim transactions. Transactions are returned bincode
+//! serialized, ready to be submitted by the app.
+use helium_lib::{
+    client,
+    entity_key::EncodedEntityKey,
+    keypair::{self, Keypair, Signer},
+    reward::{self, ClaimableToken},
+    solana_sdk::transaction::VersionedTransaction,
+    token::{self, Token, TokenAmount},
+    TransactionOpts,
+};
+use helium_wallet_core::{format::Format, pwhash::PwHash, wallet::Wallet};
+use std::sync::Arc;
+
+uniffi::setup_scaffolding!();
+
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum FfiError {
+    #[error("mnemonic: {0}")]
+    Mnemonic(String),
+    #[error("wallet: {0}")]
+    Wallet(String),
+    #[error("transaction: {0}")]
+    Transaction(String),
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+}
+
+impl FfiError {
+    fn wallet(err: impl std::fmt::Display) -> Self {
+        Self::Wallet(err.to_string())
+    }
+
+    fn transaction(err: impl std::fmt::Display) -> Self {
+        Self::Transaction(err.to_string())
+    }
+}
+
+/// Tokens that rewards can be claimed in
+#[derive(Debug, Clone, Copy, uniffi::Enum)]
+pub enum RewardToken {
+    Hnt,
+    Iot,
+    Mobile,
+}
+
+impl From<RewardToken> for ClaimableToken {
+    fn from(value: RewardToken) -> Self {
+        match value {
+            RewardToken::Hnt => Self::Hnt,
+            RewardToken::Iot => Self::Iot,
+            RewardToken::Mobile => Self::Mobile,
+        }
+    }
+}
+
+/// A wallet keypair. The secret never leaves the Rust side other than
+/// encrypted with [`encrypt_wallet`] or as mnemonic words.
+#[derive(uniffi::Object)]
+pub struct WalletKeypair(Arc<Keypair>);
+
+#[uniffi::export]
+impl WalletKeypair {
+    #[uniffi::constructor]
+    pub fn generate() -> Arc<Self> {
+        Arc::new(Self(Arc::new(Keypair::generate())))
+    }
+
+    /// Restore a keypair from its 12 or 24 mnemonic words
+    #[uniffi::constructor]
+    pub fn from_words(words: Vec<String>) -> Result<Arc<Self>, FfiError> {
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        Keypair::from_words(&words)
+            .map(|keypair| Arc::new(Self(keypair)))
+            .map_err(|err| FfiError::Mnemonic(err.to_string()))
+    }
+
+    /// Solana address of the keypair
+    pub fn address(&self) -> String {
+        self.0.pubkey().to_string()
+    }
+
+    /// Helium address of the keypair
+    pub fn helium_address(&self) -> Result<String, FfiError> {
+        keypair::to_helium_pubkey(&self.0.pubkey())
+            .map(|key| key.to_string())
+            .map_err(FfiError::wallet)
+    }
+
+    /// Mnemonic words that restore the keypair
+    pub fn words(&self) -> Result<Vec<String>, FfiError> {
+        self.0
+            .phrase()
+            .map(|phrase| phrase.split(' ').map(str::to_string).collect())
+            .map_err(|err| FfiError::Mnemonic(err.to_string()))
+    }
+
+    /// Sign a message with the keypair
+    pub fn sign(&self, message: Vec<u8>) -> Result<Vec<u8>, FfiError> {
+        self.0
+            .sign(&message)
+            .map(|signature| signature.as_ref().to_vec())
+            .map_err(FfiError::wallet)
+    }
+}
+
+/// Encrypt a keypair with a password into the contents of a wallet file, the
+/// same as `helium-wallet create basic` writes
+#[uniffi::export]
+pub fn encrypt_wallet(keypair: Arc<WalletKeypair>, password: String) -> Result<Vec<u8>, FfiError> {
+    let wallet = Wallet::encrypt(
+        &keypair.0,
+        password.as_bytes(),
+        Format::basic(PwHash::argon2id13_default()),
+    )
+    .map_err(FfiError::wallet)?;
+    let mut bytes = vec![];
+    wallet.write(&mut bytes).map_err(FfiError::wallet)?;
+    Ok(bytes)
+}
+
+/// Decrypt the contents of a wallet file with its password. Sharded wallets
+/// are not supported
+#[uniffi::export]
+pub fn decrypt_wallet(wallet: Vec<u8>, password: String) -> Result<Arc<WalletKeypair>, FfiError> {
+    let wallet = read_wallet(&wallet)?;
+    if wallet.is_sharded() {
+        return Err(FfiError::Wallet("sharded wallets are not supported".into()));
+    }
+    let keypair = wallet
+        .decrypt(password.as_bytes())
+        .map_err(FfiError::wallet)?;
+    Ok(Arc::new(WalletKeypair(keypair)))
+}
+
+/// Solana address of a wallet file, read without decrypting it
+#[uniffi::export]
+pub fn wallet_address(wallet: Vec<u8>) -> Result<String, FfiError> {
+    read_wallet(&wallet)?.address().map_err(FfiError::wallet)
+}
+
+fn read_wallet(bytes: &[u8]) -> Result<Wallet, FfiError> {
+    Wallet::read(&mut &bytes[..]).map_err(FfiError::wallet)
+}
+
+fn client(url: &str) -> Result<client::Client, FfiError> {
+    client::Client::try_from(url).map_err(FfiError::transaction)
+}
+
+fn serialize(txn: &VersionedTransaction) -> Result<Vec<u8>, FfiError> {
+    bincode::serialize(txn).map_err(FfiError::transaction)
+}
+
+/// Build a signed transaction transferring an amount of a token, for example
+/// "hnt" or "sol", to a recipient.
+///
+/// The url is a Solana RPC url, or "m" and "d" for mainnet and devnet.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn transfer_transaction(
+    url: String,
+    keypair: Arc<WalletKeypair>,
+    recipient: String,
+    token: String,
+    amount: f64,
+) -> Result<Vec<u8>, FfiError> {
+    let token: Token = token
+        .parse()
+        .map_err(|err: token::TokenError| FfiError::InvalidArgument(err.to_string()))?;
+    let recipient = recipient
+        .parse()
+        .map_err(|_| FfiError::InvalidArgument(format!("invalid recipient {recipient}")))?;
+    let client = client(&url)?;
+    let (txn, _) = token::transfer(
+        &client,
+        &[(recipient, TokenAmount::from_f64(token, amount))],
+        None,
+        &[],
+        &keypair.0,
+        &TransactionOpts::default(),
+    )
+    .await
+    .map_err(FfiError::transaction)?;
+    serialize(&txn)
+}
+
+/// Build a signed transaction claiming the pending rewards of a Hotspot,
+/// given its Helium key. Returns nothing when there are no rewards to claim.
+///
+/// The url is a Solana RPC url, or "m" and "d" for mainnet and devnet.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn claim_transaction(
+    url: String,
+    keypair: Arc<WalletKeypair>,
+    hotspot: String,
+    token: RewardToken,
+) -> Result<Option<Vec<u8>>, FfiError> {
+    let hotspot: helium_crypto::PublicKey = hotspot
+        .parse()
+        .map_err(|_| FfiError::InvalidArgument(format!("invalid hotspot {hotspot}")))?;
+    let client = client(&url)?;
+    reward::claim(
+        &client,
+        token.into(),
+        None,
+        &EncodedEntityKey::from(&hotspot),
+        &keypair.0,
+        &TransactionOpts::default(),
+    )
+    .await
+    .map_err(FfiError::transaction)?
+    .map(|(txn, _)| serialize(&txn))
+    .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wallet_roundtrip() {
+        let keypair = WalletKeypair::generate();
+        let wallet = encrypt_wallet(keypair.clone(), "pass".into()).expect("encrypt");
+        assert_eq!(
+            wallet_address(wallet.clone()).expect("address"),
+            keypair.address()
+        );
+
+        let decrypted = decrypt_wallet(wallet.clone(), "pass".into()).expect("decrypt");
+        assert_eq!(decrypted.address(), keypair.address());
+        assert!(decrypt_wallet(wallet, "wrong".into()).is_err());
+    }
+
+    #[test]
+    fn words_roundtrip() {
+        let keypair = WalletKeypair::generate();
+        let words = keypair.words().expect("words");
+        let restored = WalletKeypair::from_words(words).expect("restore");
+        assert_eq!(restored.address(), keypair.address());
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     bs58,
-    client::{DasClient, DasSearchAssetsParams, SolanaRpcClient},
+    client::{DasClient, DasSearchAssetsParams, GetAsset, SolanaRpcClient, DAS_MAX_PAGE_SIZE},
     dao::Dao,
     entity_key::{self, AsEntityKey},
     error::{DecodeError, Error},
@@ -9,47 +9,77 @@ use crate::{
     kta, message,
     priority_fee::{compute_budget_instruction, compute_price_instruction_for_accounts},
     programs::{SPL_ACCOUNT_COMPRESSION_PROGRAM_ID, SPL_NOOP_PROGRAM_ID},
-    solana_sdk::{instruction::AccountMeta, transaction::VersionedTransaction},
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        transaction::VersionedTransaction,
+    },
     TransactionOpts,
 };
+use futures::{stream, Stream, TryStreamExt};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, result::Result as StdResult, str::FromStr};
 
-pub async fn for_entity_key<E, C: AsRef<DasClient>>(
+pub async fn for_entity_key<E, C: GetAsset>(client: &C, entity_key: &E) -> Result<Asset, Error>
+where
+    E: AsEntityKey,
+{
+    let kta = kta::for_entity_key(entity_key).await?;
+    for_kta(client, &kta).await
+}
+
+/// Time between checks for a newly issued asset
+const ASSET_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Wait for the asset of an entity key to appear, for example right after it
+/// was issued. Both the key to asset account and the DAS index of the asset
+/// trail the issuing transaction, so they are polled until the asset is found
+/// or the timeout passes, when the last lookup error is returned.
+pub async fn wait_for_entity_key<E, C: GetAsset>(
     client: &C,
     entity_key: &E,
+    timeout: std::time::Duration,
 ) -> Result<Asset, Error>
 where
     E: AsEntityKey,
 {
-    let kta = kta::for_entity_key(entity_key).await?;
-    for_kta(client, &kta).await
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        match for_entity_key(client, entity_key).await {
+            Ok(asset) => return Ok(asset),
+            Err(err) if tokio::time::Instant::now() + ASSET_POLL_INTERVAL > deadline => {
+                return Err(err)
+            }
+            Err(_) => tokio::time::sleep(ASSET_POLL_INTERVAL).await,
+        }
+    }
 }
 
-pub async fn for_kta<C: AsRef<DasClient>>(
+pub async fn for_kta<C: GetAsset>(
     client: &C,
     kta: &helium_entity_manager::KeyToAssetV0,
 ) -> Result<Asset, Error> {
     get(client, &kta.asset).await
 }
 
-pub async fn for_kta_with_proof<C: AsRef<DasClient>>(
+pub async fn for_kta_with_proof<C: GetAsset>(
     client: &C,
     kta: &helium_entity_manager::KeyToAssetV0,
 ) -> Result<(Asset, AssetProof), Error> {
     get_with_proof(client, &kta.asset).await
 }
 
-pub async fn get<C: AsRef<DasClient>>(client: &C, pubkey: &Pubkey) -> Result<Asset, Error> {
-    let asset_response: Asset = client.as_ref().get_asset(pubkey).await?;
-    Ok(asset_response)
+pub async fn get<C: GetAsset>(client: &C, pubkey: &Pubkey) -> Result<Asset, Error> {
+    client.asset(pubkey).await
 }
 
-pub async fn get_with_proof<C: AsRef<DasClient>>(
+/// Get an asset along with its proof. The asset is never served from the
+/// cache so it matches the proof.
+pub async fn get_with_proof<C: GetAsset>(
     client: &C,
     pubkey: &Pubkey,
 ) -> Result<(Asset, AssetProof), Error> {
+    cache::invalidate(pubkey);
     let (asset, asset_proof) = futures::try_join!(get(client, pubkey), proof::get(client, pubkey))?;
     Ok((asset, asset_proof))
 }
@@ -59,7 +89,7 @@ pub mod canopy {
 
     async fn get_heights() -> Result<HashMap<Pubkey, usize>, Error> {
         const KNOWN_CANOPY_HEIGHT_URL: &str = "https://shdw-drive.genesysgo.net/6tcnBSybPG7piEDShBcrVtYJDPSvGrDbVvXmXKpzBvWP/merkles.json";
-        let client = reqwest::Client::new();
+        let client = crate::client::http_client();
         let map: HashMap<String, usize> = client
             .get(KNOWN_CANOPY_HEIGHT_URL)
             .send()
@@ -81,32 +111,403 @@ pub mod canopy {
         client: &C,
         tree: &Pubkey,
     ) -> Result<usize, Error> {
-        use helium_anchor_gen::anchor_lang::AnchorDeserialize;
         if let Some(height) = get_heights().await?.get(tree) {
             return Ok(*height);
         }
         let tree_account = client.as_ref().get_account(tree).await?;
-        let header = ConcurrentMerkleTreeHeader::deserialize(&mut &tree_account.data[..])
+        Ok(height_from_account(&tree_account.data)?)
+    }
+
+    /// The canopy height of the given merkle tree account data
+    pub(crate) fn height_from_account(data: &[u8]) -> Result<usize, DecodeError> {
+        use helium_anchor_gen::anchor_lang::AnchorDeserialize;
+        let header = ConcurrentMerkleTreeHeader::deserialize(&mut &data[..])
             .map_err(|_| DecodeError::other("invalid merkle tree header"))?;
         let merkle_tree_size = merkle_tree_get_size(&header)
             .map_err(|_| DecodeError::other("invalid merkle tree header"))?;
-        let canopy_size = tree_account.data.len()
-            - std::mem::size_of::<ConcurrentMerkleTreeHeader>()
-            - merkle_tree_size;
+        let canopy_size = data
+            .len()
+            .checked_sub(std::mem::size_of::<ConcurrentMerkleTreeHeader>() + merkle_tree_size)
+            .ok_or_else(|| DecodeError::other("merkle tree account too small"))?;
         let canopy_depth = (canopy_size / 32 + 1).ilog2();
         Ok(canopy_depth as usize)
     }
 }
 
-pub mod proof {
+/// In memory cache of the assets fetched from the DAS provider, keyed by
+/// asset id.
+///
+/// Listing the assets of a wallet and then acting on each of them would
+/// otherwise fetch every asset twice. Assets are served from the cache for
+/// the configured time to live, and the least recently used asset is evicted
+/// once the cache is full. Proofs are never cached since they change with
+/// every update of their tree, and assets fetched along with a proof bypass
+/// the cache so the two match.
+pub mod cache {
+    use super::*;
+    use crate::client::das_limits;
+    use std::{
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Mutex, MutexGuard, OnceLock,
+        },
+        time::{Duration, Instant},
+    };
+
+    /// Size and hit rate of the asset cache
+    #[derive(Debug, Default, Clone, Copy, Serialize)]
+    pub struct CacheStats {
+        pub entries: usize,
+        pub capacity: usize,
+        pub ttl_secs: u64,
+        pub hits: u64,
+        pub misses: u64,
+        pub evictions: u64,
+    }
+
+    impl CacheStats {
+        /// Fraction of lookups served from the cache, if there were any
+        pub fn hit_rate(&self) -> Option<f64> {
+            let lookups = self.hits + self.misses;
+            (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+        }
+    }
+
+    /// The cached asset with the given id, unless it expired
+    pub fn get(id: &Pubkey) -> Option<Asset> {
+        cache().get(id)
+    }
+
+    /// Cache the given assets, replacing earlier versions
+    pub fn insert<'a>(assets: impl IntoIterator<Item = &'a Asset>) {
+        let cache = cache();
+        for asset in assets {
+            cache.insert(asset.id, asset.clone());
+        }
+    }
+
+    /// Drop a cached asset so it is fetched again
+    pub fn invalidate(id: &Pubkey) {
+        cache().lru().remove(id);
+    }
+
+    /// Statistics of the asset cache of this process
+    pub fn stats() -> CacheStats {
+        cache().stats()
+    }
+
+    static CACHE: OnceLock<AssetCache> = OnceLock::new();
+
+    fn cache() -> &'static AssetCache {
+        CACHE.get_or_init(|| {
+            let limits = das_limits();
+            AssetCache {
+                lru: Mutex::new(Lru::new(limits.cache_capacity, limits.cache_ttl)),
+                hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+            }
+        })
+    }
+
+    struct AssetCache {
+        lru: Mutex<Lru<Asset>>,
+        hits: AtomicU64,
+        misses: AtomicU64,
+    }
+
+    impl AssetCache {
+        fn lru(&self) -> MutexGuard<'_, Lru<Asset>> {
+            self.lru.lock().expect("asset cache lock poisoned")
+        }
+
+        fn get(&self, id: &Pubkey) -> Option<Asset> {
+            let asset = self.lru().get(id, Instant::now());
+            let counter = if asset.is_some() {
+                &self.hits
+            } else {
+                &self.misses
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+            asset
+        }
+
+        fn insert(&self, id: Pubkey, asset: Asset) {
+            self.lru().insert(id, asset, Instant::now());
+        }
+
+        fn stats(&self) -> CacheStats {
+            let lru = self.lru();
+            CacheStats {
+                entries: lru.entries.len(),
+                capacity: lru.capacity,
+                ttl_secs: lru.ttl.as_secs(),
+                hits: self.hits.load(Ordering::Relaxed),
+                misses: self.misses.load(Ordering::Relaxed),
+                evictions: lru.evictions,
+            }
+        }
+    }
+
+    struct Entry<V> {
+        value: V,
+        inserted: Instant,
+        used: u64,
+    }
+
+    /// A least recently used map with expiring entries. Eviction scans the
+    /// entries, which is cheap at the sizes asset listings reach.
+    struct Lru<V> {
+        entries: HashMap<Pubkey, Entry<V>>,
+        capacity: usize,
+        ttl: Duration,
+        clock: u64,
+        evictions: u64,
+    }
+
+    impl<V: Clone> Lru<V> {
+        fn new(capacity: usize, ttl: Duration) -> Self {
+            Self {
+                entries: HashMap::new(),
+                capacity,
+                ttl,
+                clock: 0,
+                evictions: 0,
+            }
+        }
+
+        fn get(&mut self, key: &Pubkey, now: Instant) -> Option<V> {
+            let ttl = self.ttl;
+            let entry = self.entries.get_mut(key)?;
+            if now.duration_since(entry.inserted) >= ttl {
+                self.entries.remove(key);
+                return None;
+            }
+            self.clock += 1;
+            entry.used = self.clock;
+            Some(entry.value.clone())
+        }
+
+        fn insert(&mut self, key: Pubkey, value: V, now: Instant) {
+            if self.capacity == 0 || self.ttl.is_zero() {
+                return;
+            }
+            if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+                let ttl = self.ttl;
+                self.entries
+                    .retain(|_, entry| now.duration_since(entry.inserted) < ttl);
+            }
+            if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+                if let Some(oldest) = self
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.used)
+                    .map(|(key, _)| *key)
+                {
+                    self.entries.remove(&oldest);
+                    self.evictions += 1;
+                }
+            }
+            self.clock += 1;
+            self.entries.insert(
+                key,
+                Entry {
+                    value,
+                    inserted: now,
+                    used: self.clock,
+                },
+            );
+        }
+
+        fn remove(&mut self, key: &Pubkey) {
+            self.entries.remove(key);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn lru() {
+            let now = Instant::now();
+            let mut lru = Lru::new(2, Duration::from_secs(60));
+            let (a, b, c) = (
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+            );
+            lru.insert(a, "a", now);
+            lru.insert(b, "b", now);
+            // Using a makes b the least recently used
+            assert_eq!(lru.get(&a, now), Some("a"));
+            lru.insert(c, "c", now);
+            assert_eq!(lru.get(&b, now), None);
+            assert_eq!(lru.get(&c, now), Some("c"));
+            assert_eq!(lru.evictions, 1);
+
+            let later = now + Duration::from_secs(60);
+            assert_eq!(lru.get(&a, later), None);
+            assert_eq!(lru.entries.len(), 1);
+
+            let mut disabled = Lru::new(2, Duration::ZERO);
+            disabled.insert(a, "a", now);
+            assert_eq!(disabled.get(&a, now), None);
+        }
+    }
+}
+
+/// Diagnostics of the on-chain merkle trees that compressed assets live in.
+///
+/// Transfers and claims of a compressed asset carry a proof from the DAS
+/// indexer, which the compression program checks against the roots in the
+/// changelog buffer of the tree. A proof for an older root than the buffer
+/// holds means the indexer is behind and the transaction will fail.
+pub mod tree {
     use super::*;
+    use spl_account_compression::state::ConcurrentMerkleTreeHeader;
+
+    /// Size of a node of the tree
+    const NODE_SIZE: usize = 32;
 
-    pub async fn get<C: AsRef<DasClient>>(
+    /// State of a merkle tree as stored on chain
+    #[derive(Debug, Serialize, Clone)]
+    pub struct TreeState {
+        #[serde(with = "serde_pubkey")]
+        pub tree: Pubkey,
+        pub max_depth: u32,
+        pub max_buffer_size: u32,
+        pub canopy_depth: usize,
+        /// Number of changes made to the tree
+        pub seq: u64,
+        /// The current root of the tree
+        #[serde(with = "serde_pubkey")]
+        pub root: Pubkey,
+        /// Roots in the changelog buffer of the tree, which proofs are
+        /// accepted for
+        #[serde(skip)]
+        pub recent_roots: Vec<Pubkey>,
+    }
+
+    /// How a DAS proof relates to the on-chain tree
+    #[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+    #[serde(rename_all = "snake_case")]
+    pub enum ProofFreshness {
+        /// The proof is for the current root of the tree
+        Current,
+        /// The proof is for an older root still in the changelog buffer, so
+        /// it is accepted
+        Recent,
+        /// The proof is for a root the tree no longer accepts
+        Stale,
+    }
+
+    pub async fn state<C: AsRef<SolanaRpcClient>>(
         client: &C,
-        pubkey: &Pubkey,
-    ) -> Result<AssetProof, Error> {
-        let asset_proof_response: AssetProof = client.as_ref().get_asset_proof(pubkey).await?;
-        Ok(asset_proof_response)
+        tree: &Pubkey,
+    ) -> Result<TreeState, Error> {
+        let account = client.as_ref().get_account(tree).await?;
+        Ok(TreeState::from_account(tree, &account.data)?)
+    }
+
+    impl TreeState {
+        fn from_account(tree: &Pubkey, data: &[u8]) -> Result<Self, DecodeError> {
+            use helium_anchor_gen::anchor_lang::AnchorDeserialize;
+            let header = ConcurrentMerkleTreeHeader::deserialize(&mut &data[..])
+                .map_err(|_| DecodeError::other("invalid merkle tree header"))?;
+            let max_depth = header.get_max_depth();
+            let max_buffer_size = header.get_max_buffer_size();
+            // The tree starts with the sequence number, the active index and
+            // the used size of the changelog buffer, followed by the buffer,
+            // each entry of which starts with its root
+            let body = &data[std::mem::size_of::<ConcurrentMerkleTreeHeader>().min(data.len())..];
+            let read_u64 = |offset: usize| {
+                body.get(offset..offset + 8)
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .map(u64::from_le_bytes)
+                    .ok_or_else(|| DecodeError::other("merkle tree account too small"))
+            };
+            let seq = read_u64(0)?;
+            let active_index = usize::try_from(read_u64(8)?).map_err(DecodeError::from)?;
+            let buffer_size = usize::try_from(read_u64(16)?).map_err(DecodeError::from)?;
+            let changelog_size = NODE_SIZE * (max_depth as usize + 1) + 8;
+            let root_at = |index: usize| {
+                let offset = 24 + index * changelog_size;
+                body.get(offset..offset + NODE_SIZE)
+                    .map(|bytes| Pubkey::try_from(bytes).expect("node size"))
+                    .ok_or_else(|| DecodeError::other("merkle tree account too small"))
+            };
+            let buffer_len = max_buffer_size as usize;
+            let recent_roots = (0..buffer_size.min(buffer_len))
+                .map(|age| root_at((active_index + buffer_len - age) % buffer_len))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Self {
+                tree: *tree,
+                max_depth,
+                max_buffer_size,
+                canopy_depth: canopy::height_from_account(data)?,
+                seq,
+                root: root_at(active_index)?,
+                recent_roots,
+            })
+        }
+
+        pub fn proof_freshness(&self, proof: &AssetProof) -> ProofFreshness {
+            if proof.root == self.root {
+                ProofFreshness::Current
+            } else if self.recent_roots.contains(&proof.root) {
+                ProofFreshness::Recent
+            } else {
+                ProofFreshness::Stale
+            }
+        }
+
+        /// Number of proof nodes a transaction has to pass for this tree
+        pub fn proof_len(&self) -> usize {
+            (self.max_depth as usize).saturating_sub(self.canopy_depth)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn freshness() {
+            let (current, older, stale) = (
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+            );
+            let state = TreeState {
+                tree: Pubkey::new_unique(),
+                max_depth: 20,
+                max_buffer_size: 64,
+                canopy_depth: 14,
+                seq: 100,
+                root: current,
+                recent_roots: vec![current, older],
+            };
+            let proof = |root| AssetProof {
+                proof: vec![],
+                root,
+                tree_id: state.tree,
+            };
+            assert_eq!(
+                state.proof_freshness(&proof(current)),
+                ProofFreshness::Current
+            );
+            assert_eq!(state.proof_freshness(&proof(older)), ProofFreshness::Recent);
+            assert_eq!(state.proof_freshness(&proof(stale)), ProofFreshness::Stale);
+            assert_eq!(state.proof_len(), 6);
+        }
+    }
+}
+
+pub mod proof {
+    use super::*;
+
+    pub async fn get<C: GetAsset>(client: &C, pubkey: &Pubkey) -> Result<AssetProof, Error> {
+        client.asset_proof(pubkey).await
     }
 }
 
@@ -122,25 +523,54 @@ pub async fn for_owner<C: AsRef<DasClient>>(
     creator: &Pubkey,
     owner: &Pubkey,
 ) -> Result<Vec<Asset>, Error> {
-    let mut params = DasSearchAssetsParams::for_owner(*owner, *creator);
-    // Set to maximum documented limit
-    params.limit = 1000;
-    let mut results = vec![];
-    loop {
-        let page = client
-            .as_ref()
-            .search_assets(params.clone())
-            .await
-            .map_err(Error::from)?;
-        let fetch_count = page.items.len();
-        results.extend(page.items);
-        if fetch_count < params.limit as usize {
-            break;
-        }
-        params.page += 1;
-    }
+    search_all(client, DasSearchAssetsParams::for_owner(*owner, *creator)).await
+}
+
+/// Get all compressed assets of the given owner, by any creator.
+///
+/// Uncompressed assets, like staked positions, are left out since they carry
+/// no compression data to build transactions with.
+pub async fn all_for_owner<C: AsRef<DasClient>>(
+    client: &C,
+    owner: &Pubkey,
+) -> Result<Vec<Asset>, Error> {
+    let params = DasSearchAssetsParams {
+        owner_address: Some(*owner),
+        compressed: true,
+        page: 1,
+        ..Default::default()
+    };
+    search_all(client, params).await
+}
+
+/// Get every page of results of a search
+async fn search_all<C: AsRef<DasClient>>(
+    client: &C,
+    params: DasSearchAssetsParams,
+) -> Result<Vec<Asset>, Error> {
+    search_pages(client, params).try_concat().await
+}
 
-    Ok(results)
+/// Stream the results of a search one page at a time, so large results can be
+/// processed as they are fetched
+pub fn search_pages<'a, C: AsRef<DasClient>>(
+    client: &'a C,
+    mut params: DasSearchAssetsParams,
+) -> impl Stream<Item = Result<Vec<Asset>, Error>> + 'a {
+    // Searches beyond the DAS page size are split by the client
+    params.limit = DAS_MAX_PAGE_SIZE;
+    params.page = params.page.max(1);
+    stream::try_unfold(Some(params), move |params| async move {
+        let Some(mut params) = params else {
+            return Ok(None);
+        };
+        let page = search(client, params.clone()).await?;
+        let next = (page.items.len() >= params.limit as usize).then(|| {
+            params.page += 1;
+            params
+        });
+        Ok(Some((page.items, next)))
+    })
 }
 
 /// Get an unsigned transaction for an asset transfer
@@ -148,20 +578,40 @@ pub async fn for_owner<C: AsRef<DasClient>>(
 /// The asset is transferred from the owner to the given recipient
 /// Note that the owner is currently expected to sign this transaction and pay for
 /// transaction fees.
-pub async fn transfer_message<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
+pub async fn transfer_message<C: AsRef<SolanaRpcClient> + GetAsset>(
     client: &C,
     pubkey: &Pubkey,
     recipient: &Pubkey,
     opts: &TransactionOpts,
 ) -> Result<(message::VersionedMessage, u64), Error> {
     let (asset, asset_proof) = get_with_proof(client, pubkey).await?;
+    let transfer_ix = transfer_instruction(client, &asset, &asset_proof, recipient).await?;
+
+    let ixs = &[
+        compute_budget_instruction(200_000),
+        compute_price_instruction_for_accounts(client, &transfer_ix.accounts, opts.fee_range())
+            .await?,
+        transfer_ix,
+    ];
+
+    message::mk_message(client, ixs, &opts.lut_addresses, &asset.ownership.owner).await
+}
 
+/// The instruction transferring the given asset from its current owner, who
+/// signs it, to the given recipient. The proof accounts needed for the tree of
+/// the asset are included.
+pub async fn transfer_instruction<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    asset: &Asset,
+    asset_proof: &AssetProof,
+    recipient: &Pubkey,
+) -> Result<Instruction, Error> {
     let leaf_delegate = asset.ownership.delegate.unwrap_or(asset.ownership.owner);
     let merkle_tree = asset_proof.tree_id;
     let remaining_accounts = asset_proof.proof_for_tree(client, &merkle_tree).await?;
 
     let transfer = mpl_bubblegum::instructions::Transfer {
-        leaf_owner: (asset.ownership.owner, false),
+        leaf_owner: (asset.ownership.owner, true),
         leaf_delegate: (leaf_delegate, false),
         new_leaf_owner: *recipient,
         tree_config: mpl_bubblegum::accounts::TreeConfig::find_pda(&merkle_tree).0,
@@ -178,21 +628,10 @@ pub async fn transfer_message<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
         nonce: asset.compression.leaf_id,
     };
 
-    let transfer_ix = transfer.instruction_with_remaining_accounts(args, &remaining_accounts);
-    let mut priority_fee_accounts = transfer_ix.accounts.clone();
-    priority_fee_accounts.extend_from_slice(&remaining_accounts);
-
-    let ixs = &[
-        compute_budget_instruction(200_000),
-        compute_price_instruction_for_accounts(client, &priority_fee_accounts, opts.fee_range())
-            .await?,
-        transfer_ix,
-    ];
-
-    message::mk_message(client, ixs, &opts.lut_addresses, &asset.ownership.owner).await
+    Ok(transfer.instruction_with_remaining_accounts(args, &remaining_accounts))
 }
 
-pub async fn transfer<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
+pub async fn transfer<C: AsRef<SolanaRpcClient> + GetAsset>(
     client: &C,
     pubkey: &Pubkey,
     recipient: &Pubkey,
@@ -204,14 +643,86 @@ pub async fn transfer<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
     Ok((txn, block_height))
 }
 
+/// Compute units for a single transfer or burn in a batch
+const BATCH_INSTRUCTION_BUDGET: u32 = 100_000;
+
+/// Pack transfers of the given assets, all owned by the given owner, to the
+/// recipient into as few transactions as possible.
+///
+/// How many transfers fit in a transaction depends on the proof each asset
+/// needs, which is shorter for trees with a larger canopy.
+pub async fn transfer_batches<C: AsRef<SolanaRpcClient> + GetAsset>(
+    client: &C,
+    assets: &[Pubkey],
+    owner: &Pubkey,
+    recipient: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<Vec<message::Batch>, Error> {
+    let lut_accounts = message::get_lut_accounts(client, &opts.lut_addresses).await?;
+    let mut instructions = Vec::with_capacity(assets.len());
+    for pubkey in assets {
+        let (asset, asset_proof) = get_owned_with_proof(client, pubkey, owner).await?;
+        let ix = transfer_instruction(client, &asset, &asset_proof, recipient).await?;
+        instructions.push((vec![ix], BATCH_INSTRUCTION_BUDGET));
+    }
+    Ok(message::pack(instructions, &lut_accounts, owner))
+}
+
+/// Pack burns of the given assets, all owned by the given owner, into as few
+/// transactions as possible
+pub async fn burn_batches<C: AsRef<SolanaRpcClient> + GetAsset>(
+    client: &C,
+    assets: &[Pubkey],
+    owner: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<Vec<message::Batch>, Error> {
+    let lut_accounts = message::get_lut_accounts(client, &opts.lut_addresses).await?;
+    let mut instructions = Vec::with_capacity(assets.len());
+    for pubkey in assets {
+        let (asset, asset_proof) = get_owned_with_proof(client, pubkey, owner).await?;
+        let ix = burn_instruction(client, &asset, &asset_proof).await?;
+        instructions.push((vec![ix], BATCH_INSTRUCTION_BUDGET));
+    }
+    Ok(message::pack(instructions, &lut_accounts, owner))
+}
+
+async fn get_owned_with_proof<C: GetAsset>(
+    client: &C,
+    pubkey: &Pubkey,
+    owner: &Pubkey,
+) -> Result<(Asset, AssetProof), Error> {
+    let (asset, asset_proof) = get_with_proof(client, pubkey).await?;
+    if asset.ownership.owner != *owner {
+        return Err(DecodeError::other(format!("asset {pubkey} not owned by {owner}")).into());
+    }
+    Ok((asset, asset_proof))
+}
+
 /// Get an unsigned burn transaction for an asset
-pub async fn burn_message<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
+pub async fn burn_message<C: AsRef<SolanaRpcClient> + GetAsset>(
     client: &C,
     pubkey: &Pubkey,
     opts: &TransactionOpts,
 ) -> Result<(message::VersionedMessage, u64), Error> {
     let (asset, asset_proof) = get_with_proof(client, pubkey).await?;
+    let ix = burn_instruction(client, &asset, &asset_proof).await?;
+
+    let ixs = &[
+        compute_budget_instruction(100_000),
+        compute_price_instruction_for_accounts(client, &ix.accounts, opts.fee_range()).await?,
+        ix,
+    ];
 
+    message::mk_message(client, ixs, &opts.lut_addresses, &asset.ownership.owner).await
+}
+
+/// The instruction burning the given asset of its current owner. The proof
+/// accounts needed for the tree of the asset are included.
+pub async fn burn_instruction<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    asset: &Asset,
+    asset_proof: &AssetProof,
+) -> Result<Instruction, Error> {
     let leaf_delegate = asset.ownership.delegate.unwrap_or(asset.ownership.owner);
     let merkle_tree = asset_proof.tree_id;
     let remaining_accounts = asset_proof.proof_for_tree(client, &merkle_tree).await?;
@@ -234,21 +745,10 @@ pub async fn burn_message<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
         nonce: asset.compression.leaf_id,
     };
 
-    let ix = burn.instruction_with_remaining_accounts(args, &remaining_accounts);
-    let mut priority_fee_accounts = ix.accounts.clone();
-    priority_fee_accounts.extend_from_slice(&remaining_accounts);
-
-    let ixs = &[
-        compute_budget_instruction(100_000),
-        compute_price_instruction_for_accounts(client, &priority_fee_accounts, opts.fee_range())
-            .await?,
-        ix,
-    ];
-
-    message::mk_message(client, ixs, &opts.lut_addresses, &asset.ownership.owner).await
+    Ok(burn.instruction_with_remaining_accounts(args, &remaining_accounts))
 }
 
-pub async fn burn<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
+pub async fn burn<C: AsRef<SolanaRpcClient> + GetAsset>(
     client: &C,
     pubkey: &Pubkey,
     keypair: &Keypair,
@@ -361,6 +861,66 @@ impl Asset {
     pub fn is_symbol(&self, symbol: &str) -> bool {
         self.content.metadata.symbol == symbol
     }
+
+    /// Whether the asset is a Hotspot, or another entity issued by the
+    /// Helium entity manager
+    pub fn is_hotspot(&self) -> bool {
+        self.creators
+            .iter()
+            .any(|creator| creator.verified && creator.address == crate::hotspot::HOTSPOT_CREATOR)
+    }
+
+    /// Collection the asset is grouped in, if any
+    pub fn collection(&self) -> Option<Pubkey> {
+        self.grouping
+            .iter()
+            .find(|group| group.group_key == "collection")
+            .map(|group| group.group_value)
+    }
+}
+
+/// Selects assets by a property, parsed from `symbol=<symbol>`,
+/// `collection=<address>` or `creator=<address>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetFilter {
+    /// Symbol of the asset, ignoring case
+    Symbol(String),
+    Collection(Pubkey),
+    /// Any of the creators of the asset, verified or not
+    Creator(Pubkey),
+}
+
+impl AssetFilter {
+    pub fn matches(&self, asset: &Asset) -> bool {
+        match self {
+            Self::Symbol(symbol) => asset.content.metadata.symbol.eq_ignore_ascii_case(symbol),
+            Self::Collection(collection) => asset.collection().as_ref() == Some(collection),
+            Self::Creator(creator) => asset
+                .creators
+                .iter()
+                .any(|asset_creator| asset_creator.address == *creator),
+        }
+    }
+}
+
+impl FromStr for AssetFilter {
+    type Err = DecodeError;
+
+    fn from_str(s: &str) -> StdResult<Self, Self::Err> {
+        let Some((key, value)) = s.split_once('=') else {
+            return Err(DecodeError::other(format!(
+                "expected <key>=<value> filter, got {s}"
+            )));
+        };
+        match key {
+            "symbol" => Ok(Self::Symbol(value.to_string())),
+            "collection" => Ok(Self::Collection(Pubkey::from_str(value)?)),
+            "creator" => Ok(Self::Creator(Pubkey::from_str(value)?)),
+            _ => Err(DecodeError::other(format!(
+                "unknown filter {key}, expected symbol, collection or creator"
+            ))),
+        }
+    }
 }
 
 impl AssetProof {
@@ -450,3 +1010,24 @@ pub mod serde_hash {
             .map_err(|_| de::Error::custom("invalid hash"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter() {
+        assert_eq!(
+            "symbol=SPAM".parse::<AssetFilter>().unwrap(),
+            AssetFilter::Symbol("SPAM".to_string())
+        );
+        let creator = crate::hotspot::HOTSPOT_CREATOR;
+        assert_eq!(
+            format!("creator={creator}").parse::<AssetFilter>().unwrap(),
+            AssetFilter::Creator(creator)
+        );
+        assert!("collection=invalid".parse::<AssetFilter>().is_err());
+        assert!("name=SPAM".parse::<AssetFilter>().is_err());
+        assert!("SPAM".parse::<AssetFilter>().is_err());
+    }
+}
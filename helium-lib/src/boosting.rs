@@ -1,15 +1,127 @@
 use crate::{
-    anchor_lang::{InstructionData, ToAccountMetas},
-    client::SolanaRpcClient,
+    anchor_client::solana_client::{
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+        rpc_filter::{Memcmp, RpcFilterType},
+    },
+    anchor_lang::{AccountDeserialize, Discriminator, InstructionData, ToAccountMetas},
+    client::{GetAnchorAccount, SolanaRpcClient},
+    dao::SubDao,
     error::Error,
     hexboosting,
-    hexboosting::accounts::StartBoostV0,
-    keypair::{Keypair, Pubkey},
+    hexboosting::accounts::{CloseBoostV0, StartBoostV0},
+    keypair::{serde_pubkey, Keypair, Pubkey},
     message, priority_fee,
     solana_sdk::{instruction::Instruction, signer::Signer, transaction::VersionedTransaction},
     TransactionOpts,
 };
 use chrono::{DateTime, Utc};
+use serde::Serialize;
+use solana_account_decoder::UiAccountEncoding;
+
+/// The h3 resolution hexes are boosted at
+pub const BOOSTED_HEX_RESOLUTION: h3o::Resolution = h3o::Resolution::Ten;
+
+/// Device types a hex can be boosted for, with their names
+const BOOSTED_DEVICE_TYPES: [(hexboosting::DeviceTypeV0, &str); 4] = [
+    (hexboosting::DeviceTypeV0::CbrsIndoor, "cbrs_indoor"),
+    (hexboosting::DeviceTypeV0::CbrsOutdoor, "cbrs_outdoor"),
+    (hexboosting::DeviceTypeV0::WifiIndoor, "wifi_indoor"),
+    (hexboosting::DeviceTypeV0::WifiOutdoor, "wifi_outdoor"),
+];
+
+/// The boost of a hex for a device type
+#[derive(Debug, Serialize, Clone)]
+pub struct HexBoost {
+    #[serde(with = "serde_pubkey")]
+    pub address: Pubkey,
+    pub hex: String,
+    pub device_type: &'static str,
+    /// Reward multiplier for the current period, if the boost is active
+    pub multiplier: Option<u8>,
+    /// Time the boost started, if it has started
+    pub start: Option<DateTime<Utc>>,
+    /// Time the last boosted period ends, if the boost has started
+    pub expiry: Option<DateTime<Utc>>,
+}
+
+pub fn boost_config_key(subdao: SubDao) -> Pubkey {
+    let (key, _) = Pubkey::find_program_address(
+        &[b"boost_config", subdao.token().mint().as_ref()],
+        &hexboosting::id(),
+    );
+    key
+}
+
+pub fn boosted_hex_key(
+    boost_config: &Pubkey,
+    device_type: hexboosting::DeviceTypeV0,
+    hex: u64,
+) -> Pubkey {
+    let (key, _) = Pubkey::find_program_address(
+        &[
+            b"boosted_hex",
+            boost_config.as_ref(),
+            &[device_type as u8],
+            &hex.to_le_bytes(),
+        ],
+        &hexboosting::id(),
+    );
+    key
+}
+
+/// Get the boosts for the mobile hex containing the given location.
+///
+/// Locations finer than the boosted hex resolution are mapped to their parent
+/// hex. Only device types the hex is boosted for are returned.
+pub async fn for_location<C: GetAnchorAccount>(
+    client: &C,
+    location: h3o::CellIndex,
+) -> Result<Vec<HexBoost>, Error> {
+    let Some(hex) = location.parent(BOOSTED_HEX_RESOLUTION) else {
+        return Ok(vec![]);
+    };
+    let boost_config_key = boost_config_key(SubDao::Mobile);
+    let boosted_hex_keys: Vec<Pubkey> = BOOSTED_DEVICE_TYPES
+        .iter()
+        .map(|(device_type, _)| boosted_hex_key(&boost_config_key, *device_type, hex.into()))
+        .collect();
+    let boosted_hexes = client
+        .anchor_accounts::<hexboosting::BoostedHexV1>(&boosted_hex_keys)
+        .await?;
+    if boosted_hexes.iter().all(Option::is_none) {
+        return Ok(vec![]);
+    }
+    let boost_config: hexboosting::BoostConfigV0 = client.anchor_account(&boost_config_key).await?;
+    let period_length = i64::from(boost_config.period_length);
+    let now = Utc::now().timestamp();
+
+    let boosts = boosted_hex_keys
+        .into_iter()
+        .zip(boosted_hexes)
+        .zip(BOOSTED_DEVICE_TYPES)
+        .filter_map(|((address, boosted_hex), (_, device_type))| {
+            let boosted_hex = boosted_hex?;
+            let started = boosted_hex.start_ts > 0;
+            let expiry_ts =
+                boosted_hex.start_ts + boosted_hex.boosts_by_period.len() as i64 * period_length;
+            let multiplier = if started && now < expiry_ts && period_length > 0 {
+                let period = (now - boosted_hex.start_ts) / period_length;
+                boosted_hex.boosts_by_period.get(period as usize).copied()
+            } else {
+                None
+            };
+            Some(HexBoost {
+                address,
+                hex: hex.to_string(),
+                device_type,
+                multiplier,
+                start: DateTime::from_timestamp(boosted_hex.start_ts, 0).filter(|_| started),
+                expiry: DateTime::from_timestamp(expiry_ts, 0).filter(|_| started),
+            })
+        })
+        .collect();
+    Ok(boosts)
+}
 
 pub trait StartBoostingHex {
     fn start_authority(&self) -> Pubkey;
@@ -84,3 +196,155 @@ pub async fn start_boost<C: AsRef<SolanaRpcClient>>(
     let txn = VersionedTransaction::try_new(msg, &[keypair])?;
     Ok((txn, block_height))
 }
+
+/// A boost whose boosted periods have all passed, so the program allows its
+/// account to be closed
+#[derive(Debug, Serialize, Clone)]
+pub struct ClosableBoost {
+    #[serde(with = "serde_pubkey")]
+    pub address: Pubkey,
+    pub hex: String,
+    pub device_type: &'static str,
+    pub expiry: DateTime<Utc>,
+    /// Rent in lamports returned to the rent reclaim authority on close
+    pub rent: u64,
+}
+
+/// Whether all boosted periods of a boost have passed. Boosts that have not
+/// started never expire.
+fn is_expired(start_ts: i64, periods: usize, period_length: u32, now: i64) -> bool {
+    start_ts > 0 && now >= start_ts + periods as i64 * i64::from(period_length)
+}
+
+/// Get the mobile boosts whose accounts the given wallet can close.
+///
+/// The hex boosting program does not record who paid for a boost, and
+/// unused boosted periods are not refunded. What can be recovered is the rent
+/// of expired boost accounts, which goes to the rent reclaim authority of the
+/// boost config. Nothing is closable for any other wallet.
+pub async fn closable<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    authority: &Pubkey,
+) -> Result<Vec<ClosableBoost>, Error> {
+    let boost_config_key = boost_config_key(SubDao::Mobile);
+    let boost_config: hexboosting::BoostConfigV0 =
+        client.as_ref().anchor_account(&boost_config_key).await?;
+    if boost_config.rent_reclaim_authority != *authority {
+        return Ok(vec![]);
+    }
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            0,
+            hexboosting::BoostedHexV1::DISCRIMINATOR.to_vec(),
+        ))]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let accounts = client
+        .as_ref()
+        .get_program_accounts_with_config(&hexboosting::id(), config)
+        .await?;
+
+    let now = Utc::now().timestamp();
+    let mut closable = vec![];
+    for (address, account) in accounts {
+        let boosted_hex = hexboosting::BoostedHexV1::try_deserialize(&mut account.data.as_slice())?;
+        if boosted_hex.boost_config != boost_config_key
+            || !is_expired(
+                boosted_hex.start_ts,
+                boosted_hex.boosts_by_period.len(),
+                boost_config.period_length,
+                now,
+            )
+        {
+            continue;
+        }
+        let expiry_ts = boosted_hex.start_ts
+            + boosted_hex.boosts_by_period.len() as i64 * i64::from(boost_config.period_length);
+        let device_type = BOOSTED_DEVICE_TYPES
+            .iter()
+            .find(|(device_type, _)| *device_type as u8 == boosted_hex.device_type as u8)
+            .map(|(_, name)| *name)
+            .unwrap_or("unknown");
+        let hex = h3o::CellIndex::try_from(boosted_hex.location)
+            .map(|cell| cell.to_string())
+            .unwrap_or_else(|_| format!("{:x}", boosted_hex.location));
+        closable.push(ClosableBoost {
+            address,
+            hex,
+            device_type,
+            expiry: DateTime::from_timestamp(expiry_ts, 0).unwrap_or_default(),
+            rent: account.lamports,
+        });
+    }
+    Ok(closable)
+}
+
+pub async fn close_boost_message<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    boosted_hexes: &[Pubkey],
+    authority: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<(message::VersionedMessage, u64), Error> {
+    let boost_config = boost_config_key(SubDao::Mobile);
+    let mut ix_accounts = vec![];
+    let mut close_ixs = vec![];
+    for boosted_hex in boosted_hexes {
+        let accounts = CloseBoostV0 {
+            rent_reclaim_authority: *authority,
+            boost_config,
+            boosted_hex: *boosted_hex,
+        }
+        .to_account_metas(None);
+        ix_accounts.extend_from_slice(&accounts);
+        close_ixs.push(Instruction {
+            program_id: hexboosting::id(),
+            accounts,
+            data: hexboosting::instruction::CloseBoostV0 {}.data(),
+        });
+    }
+    let ixs = [
+        &[
+            priority_fee::compute_budget_instruction(
+                20_000 * u32::try_from(close_ixs.len()).unwrap_or(u32::MAX).max(1),
+            ),
+            priority_fee::compute_price_instruction_for_accounts(
+                client,
+                &ix_accounts,
+                opts.fee_range(),
+            )
+            .await?,
+        ],
+        close_ixs.as_slice(),
+    ]
+    .concat();
+    message::mk_message(client, &ixs, &opts.lut_addresses, authority).await
+}
+
+pub async fn close_boost<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    boosted_hexes: &[Pubkey],
+    keypair: &Keypair,
+    opts: &TransactionOpts,
+) -> Result<(VersionedTransaction, u64), Error> {
+    let (msg, block_height) =
+        close_boost_message(client, boosted_hexes, &keypair.pubkey(), opts).await?;
+    let txn = VersionedTransaction::try_new(msg, &[keypair])?;
+    Ok((txn, block_height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expired() {
+        let day = 86_400;
+        assert!(!is_expired(0, 3, day, 10 * i64::from(day)));
+        assert!(!is_expired(1_000, 3, day, 1_000 + 2 * i64::from(day)));
+        assert!(is_expired(1_000, 3, day, 1_000 + 3 * i64::from(day)));
+    }
+}
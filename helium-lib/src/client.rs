@@ -4,12 +4,25 @@ use crate::{
     error::{DecodeError, Error},
     is_zero,
     keypair::{self, Pubkey},
-    solana_client,
+    network,
+    solana_client::{
+        self,
+        client_error::Result as ClientResult,
+        http_sender::HttpSender,
+        rpc_client::RpcClientConfig,
+        rpc_request::RpcRequest,
+        rpc_sender::{RpcSender, RpcTransportStats},
+    },
+    solana_sdk::commitment_config::CommitmentConfig,
 };
 use futures::{stream, StreamExt, TryStreamExt};
 use itertools::Itertools;
 use jsonrpc_client::{JsonRpcError, SendRequest};
-use std::{marker::Send, sync::Arc};
+use std::{
+    marker::Send,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
 use tracing::instrument;
 
 pub static ONBOARDING_URL_MAINNET: &str = "https://onboarding.dewi.org/api/v3";
@@ -18,6 +31,9 @@ pub static ONBOARDING_URL_DEVNET: &str = "https://onboarding.web.test-helium.com
 pub static VERIFIER_URL_MAINNET: &str = "https://ecc-verifier.web.helium.io";
 pub static VERIFIER_URL_DEVNET: &str = "https://ecc-verifier.web.test-helium.com";
 
+pub static IOT_CONFIG_URL_MAINNET: &str = "http://mainnet-config.helium.io:6080";
+pub static IOT_CONFIG_KEY_MAINNET: &str = "137oJzq1qZpSbzHawaysTGGsRCYaHmzSStxBzyVvYdzhDYcDhs7";
+
 pub static SOLANA_URL_MAINNET: &str = "https://solana-rpc.web.helium.io:443?session-key=Pluto";
 pub static SOLANA_URL_DEVNET: &str = "https://solana-rpc.web.test-helium.com?session-key=Pluto";
 pub static SOLANA_URL_MAINNET_ENV: &str = "SOLANA_MAINNET_URL";
@@ -37,6 +53,157 @@ pub fn is_devnet(url: &str) -> bool {
     url == "d" || url.starts_with("devnet") || url.contains("test-helium")
 }
 
+/// Whether the given url is that of a node on the local machine, like a
+/// solana-test-validator
+pub fn is_localhost(url: &str) -> bool {
+    match url::Url::parse(url).ok().as_ref().and_then(url::Url::host) {
+        Some(url::Host::Domain(domain)) => domain == "localhost",
+        Some(url::Host::Ipv4(addr)) => addr.is_loopback(),
+        Some(url::Host::Ipv6(addr)) => addr.is_loopback(),
+        None => false,
+    }
+}
+
+/// The websocket url of the RPC node at the given url. Like the Solana CLI,
+/// an explicit port is incremented by one, which is the default websocket
+/// port of a node.
+pub fn ws_url(rpc_url: &str) -> Result<String, DecodeError> {
+    let mut url = url::Url::parse(rpc_url)?;
+    let scheme = match url.scheme() {
+        "https" => "wss",
+        "http" => "ws",
+        _ => return Err(DecodeError::InvalidUrl(rpc_url.to_string())),
+    };
+    url.set_scheme(scheme)
+        .map_err(|_| DecodeError::InvalidUrl(rpc_url.to_string()))?;
+    if let Some(port) = url.port() {
+        url.set_port(port.checked_add(1))
+            .map_err(|_| DecodeError::InvalidUrl(rpc_url.to_string()))?;
+    }
+    Ok(url.to_string())
+}
+
+static TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// Set the timeout used for all network requests made by clients constructed
+/// after this call. Without a timeout set, the defaults of the underlying
+/// clients apply.
+pub fn set_timeout(timeout: Duration) {
+    let _ = TIMEOUT.set(timeout);
+}
+
+pub fn timeout() -> Option<Duration> {
+    TIMEOUT.get().copied()
+}
+
+/// Largest page size accepted by the DAS providers in use
+pub const DAS_MAX_PAGE_SIZE: u32 = 1000;
+
+static DAS_PAGE_SIZE: OnceLock<u32> = OnceLock::new();
+
+/// Set the number of assets requested per DAS search request. Some providers
+/// cap pages below the documented maximum, so searches asking for more assets
+/// than this are split into multiple requests. The size is clamped to
+/// `1..=DAS_MAX_PAGE_SIZE`.
+pub fn set_das_page_size(page_size: u32) {
+    let _ = DAS_PAGE_SIZE.set(page_size.clamp(1, DAS_MAX_PAGE_SIZE));
+}
+
+pub fn das_page_size() -> u32 {
+    DAS_PAGE_SIZE.get().copied().unwrap_or(DAS_MAX_PAGE_SIZE)
+}
+
+/// Limits on the requests made to the DAS provider, and on the caching of
+/// the assets it returns
+#[derive(Debug, Clone, Copy)]
+pub struct DasLimits {
+    /// Maximum number of requests in flight at once
+    pub concurrency: usize,
+    /// Maximum number of requests started per second, unlimited when `None`
+    pub rate: Option<u32>,
+    /// Time a fetched asset is served from the cache, assets are not cached
+    /// when zero. Zero by default, since a cached asset does not reflect
+    /// transfers made after it was fetched
+    pub cache_ttl: Duration,
+    /// Maximum number of cached assets
+    pub cache_capacity: usize,
+}
+
+impl Default for DasLimits {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            rate: None,
+            cache_ttl: Duration::ZERO,
+            cache_capacity: 10_000,
+        }
+    }
+}
+
+static DAS_LIMITS: OnceLock<DasLimits> = OnceLock::new();
+
+/// Set the limits for DAS requests made by clients constructed after this
+/// call, and for the asset cache if it is not in use yet
+pub fn set_das_limits(limits: DasLimits) {
+    let _ = DAS_LIMITS.set(limits);
+}
+
+pub fn das_limits() -> DasLimits {
+    DAS_LIMITS.get().copied().unwrap_or_default()
+}
+
+/// Limits the number of concurrent requests and the rate they are started at
+#[derive(Debug)]
+struct RequestLimiter {
+    permits: tokio::sync::Semaphore,
+    interval: Option<Duration>,
+    next: Mutex<tokio::time::Instant>,
+}
+
+impl RequestLimiter {
+    fn new(limits: &DasLimits) -> Self {
+        Self {
+            permits: tokio::sync::Semaphore::new(limits.concurrency.max(1)),
+            interval: limits
+                .rate
+                .filter(|rate| *rate > 0)
+                .map(|rate| Duration::from_secs(1) / rate),
+            next: Mutex::new(tokio::time::Instant::now()),
+        }
+    }
+
+    /// Wait for a free request slot and the next start time the rate allows.
+    /// The request may be made while the returned permit is held.
+    async fn acquire(&self) -> tokio::sync::SemaphorePermit<'_> {
+        // The semaphore is never closed
+        let permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("request limiter closed");
+        if let Some(interval) = self.interval {
+            let start = {
+                let mut next = self.next.lock().expect("request limiter lock poisoned");
+                let start = (*next).max(tokio::time::Instant::now());
+                *next = start + interval;
+                start
+            };
+            tokio::time::sleep_until(start).await;
+        }
+        permit
+    }
+}
+
+/// Construct an http client which honors the configured request timeout,
+/// proxy and root certificates
+pub(crate) fn http_client() -> reqwest::Client {
+    let mut builder = network::apply(reqwest::Client::builder().user_agent(USER_AGENT));
+    if let Some(timeout) = timeout() {
+        builder = builder.timeout(timeout);
+    }
+    builder.build().unwrap_or_default()
+}
+
 #[derive(Clone)]
 pub struct Client {
     pub solana_client: Arc<SolanaRpcClient>,
@@ -110,6 +277,35 @@ impl GetAnchorAccount for Client {
     }
 }
 
+/// Fetch compressed assets and their proofs, as served by a DAS provider
+#[async_trait::async_trait]
+pub trait GetAsset {
+    async fn asset(&self, pubkey: &Pubkey) -> Result<asset::Asset, Error>;
+    async fn asset_proof(&self, pubkey: &Pubkey) -> Result<asset::AssetProof, Error>;
+}
+
+#[async_trait::async_trait]
+impl GetAsset for DasClient {
+    async fn asset(&self, pubkey: &Pubkey) -> Result<asset::Asset, Error> {
+        Ok(self.get_asset(pubkey).await?)
+    }
+
+    async fn asset_proof(&self, pubkey: &Pubkey) -> Result<asset::AssetProof, Error> {
+        Ok(self.get_asset_proof(pubkey).await?)
+    }
+}
+
+#[async_trait::async_trait]
+impl GetAsset for Client {
+    async fn asset(&self, pubkey: &Pubkey) -> Result<asset::Asset, Error> {
+        self.das_client.asset(pubkey).await
+    }
+
+    async fn asset_proof(&self, pubkey: &Pubkey) -> Result<asset::AssetProof, Error> {
+        self.das_client.asset_proof(pubkey).await
+    }
+}
+
 impl TryFrom<&str> for Client {
     type Error = Error;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
@@ -136,7 +332,19 @@ impl TryFrom<&str> for Client {
             _url => (env_or(CERT_URL_MAINNET_ENV, CERT_URL_MAINNET), None),
         };
         let das_client = Arc::new(DasClient::with_base_url(&rpc_url)?);
-        let solana_client = Arc::new(SolanaRpcClient::new(rpc_url));
+        let config = RpcClientConfig::with_commitment(CommitmentConfig::default());
+        let solana_client = Arc::new(match timeout() {
+            // The sender's http client applies the timeout itself
+            _ if network::is_customized() => SolanaRpcClient::new_sender(
+                LoggingSender(network::ProxySender::new(rpc_url)),
+                config,
+            ),
+            Some(timeout) => SolanaRpcClient::new_sender(
+                LoggingSender(HttpSender::new_with_timeout(rpc_url, timeout)),
+                config,
+            ),
+            None => SolanaRpcClient::new_sender(LoggingSender(HttpSender::new(rpc_url)), config),
+        });
         let cert_client = Arc::new(CertClient::new(&cert_url, cert_token)?);
         Ok(Self {
             solana_client,
@@ -146,6 +354,45 @@ impl TryFrom<&str> for Client {
     }
 }
 
+/// Log the outcome and duration of a request to a service at debug level
+pub(crate) fn log_request<T, E: std::fmt::Display>(
+    service: &str,
+    request: &str,
+    start: Instant,
+    result: &Result<T, E>,
+) {
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+    match result {
+        Ok(_) => tracing::debug!(service, request, elapsed_ms, "request"),
+        Err(err) => tracing::debug!(service, request, elapsed_ms, %err, "request failed"),
+    }
+}
+
+/// Logs the Solana RPC requests of the wrapped sender
+struct LoggingSender<S>(S);
+
+#[async_trait::async_trait]
+impl<S: RpcSender + Send + Sync> RpcSender for LoggingSender<S> {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> ClientResult<serde_json::Value> {
+        let start = Instant::now();
+        let result = self.0.send(request, params).await;
+        log_request("rpc", &request.to_string(), start, &result);
+        result
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.0.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.0.url()
+    }
+}
+
 impl AsRef<SolanaRpcClient> for Client {
     fn as_ref(&self) -> &SolanaRpcClient {
         &self.solana_client
@@ -181,6 +428,9 @@ pub struct DasSearchAssetsParams {
         skip_serializing_if = "Option::is_none"
     )]
     pub owner_address: Option<Pubkey>,
+    /// Only return compressed assets
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub compressed: bool,
     #[serde(skip_serializing_if = "is_zero")]
     pub page: u32,
     #[serde(skip_serializing_if = "is_zero")]
@@ -241,6 +491,7 @@ static USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_V
 pub struct DasClient {
     inner: reqwest::Client,
     base_url: reqwest::Url,
+    limiter: Arc<RequestLimiter>,
 }
 
 impl Default for DasClient {
@@ -252,16 +503,21 @@ impl Default for DasClient {
 
 impl DasClient {
     pub fn with_base_url(url: &str) -> Result<Self, Error> {
-        let client = reqwest::Client::new();
+        let client = http_client();
         let base_url = url.parse().map_err(DecodeError::from)?;
         Ok(Self {
             inner: client,
             base_url,
+            limiter: Arc::new(RequestLimiter::new(&das_limits())),
         })
     }
 
+    /// Get an asset, from the asset cache if it was fetched recently
     #[instrument(skip(self), level = "trace")]
     pub async fn get_asset(&self, address: &Pubkey) -> Result<asset::Asset, DasClientError> {
+        if let Some(asset) = asset::cache::get(address) {
+            return Ok(asset);
+        }
         let body = jsonrpc_client::Request::new_v2("getAsset")
             .with_argument("id".to_string(), address.to_string())?
             .serialize()?;
@@ -271,6 +527,7 @@ impl DasClient {
                 .await?
                 .payload,
         )?;
+        asset::cache::insert([&response]);
         Ok(response)
     }
 
@@ -291,10 +548,71 @@ impl DasClient {
         Ok(response)
     }
 
+    /// Search for assets. A search for more assets than the configured DAS
+    /// page size, or than the provider returns in a single response, is
+    /// split into multiple requests and returned as a single page.
     #[instrument(skip(self, params), level = "trace")]
     pub async fn search_assets(
         &self,
         params: DasSearchAssetsParams,
+    ) -> Result<asset::AssetPage, DasClientError> {
+        let page_size = das_page_size();
+        let limit = if params.limit == 0 {
+            page_size
+        } else {
+            params.limit
+        };
+        let params = DasSearchAssetsParams {
+            page: params.page.max(1),
+            limit,
+            ..params
+        };
+        if limit > page_size {
+            return self.search_assets_split(params, page_size).await;
+        }
+        let page = self.search_assets_page(params.clone()).await?;
+        // Providers with a lower page cap report the limit they applied
+        if page.limit > 0 && page.limit < limit {
+            return self.search_assets_split(params, page.limit).await;
+        }
+        Ok(page)
+    }
+
+    async fn search_assets_split(
+        &self,
+        params: DasSearchAssetsParams,
+        page_size: u32,
+    ) -> Result<asset::AssetPage, DasClientError> {
+        let limit = params.limit as usize;
+        let (first_page, mut skip) = split_page_start(params.page, params.limit, page_size);
+        let mut split_params = DasSearchAssetsParams {
+            page: first_page,
+            limit: page_size,
+            ..params.clone()
+        };
+        let mut items = Vec::with_capacity(limit);
+        loop {
+            let page = self.search_assets_page(split_params.clone()).await?;
+            let fetch_count = page.items.len();
+            items.extend(page.items.into_iter().skip(skip));
+            skip = 0;
+            if items.len() >= limit || fetch_count < page_size as usize {
+                break;
+            }
+            split_params.page += 1;
+        }
+        items.truncate(limit);
+        Ok(asset::AssetPage {
+            total: items.len() as u32,
+            limit: params.limit,
+            page: params.page,
+            items,
+        })
+    }
+
+    async fn search_assets_page(
+        &self,
+        params: DasSearchAssetsParams,
     ) -> Result<asset::AssetPage, DasClientError> {
         let params =
             serde_json::to_value(params).map(|value| value.as_object().unwrap().to_owned())?;
@@ -309,10 +627,22 @@ impl DasClient {
             .await?
             .payload,
         )?;
+        asset::cache::insert(&response.items);
         Ok(response)
     }
 }
 
+/// The first page to request at the given page size, and the number of assets
+/// to skip in it, to fetch the given page of a search with a larger limit
+fn split_page_start(page: u32, limit: u32, page_size: u32) -> (u32, usize) {
+    let offset = u64::from(page.saturating_sub(1)) * u64::from(limit);
+    let page_size = u64::from(page_size);
+    (
+        (offset / page_size) as u32 + 1,
+        (offset % page_size) as usize,
+    )
+}
+
 #[async_trait::async_trait]
 impl jsonrpc_client::SendRequest for DasClient {
     type Error = reqwest::Error;
@@ -324,15 +654,26 @@ impl jsonrpc_client::SendRequest for DasClient {
     where
         P: serde::de::DeserializeOwned,
     {
-        self.inner
-            .post(endpoint)
-            .header(reqwest::header::CONTENT_TYPE, "application/json")
-            .header(reqwest::header::USER_AGENT, USER_AGENT)
-            .body(body)
-            .send()
-            .await?
-            .json()
-            .await
+        let _permit = self.limiter.acquire().await;
+        let method = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|request| request["method"].as_str().map(str::to_string))
+            .unwrap_or_default();
+        let start = Instant::now();
+        let result = async {
+            self.inner
+                .post(endpoint)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(reqwest::header::USER_AGENT, USER_AGENT)
+                .body(body)
+                .send()
+                .await?
+                .json()
+                .await
+        }
+        .await;
+        log_request("das", &method, start, &result);
+        result
     }
 }
 
@@ -348,6 +689,7 @@ pub mod config {
     };
     use std::{collections::HashMap, time::Duration};
     use stream::BoxStream;
+    use tonic::transport::{Certificate, ClientTlsConfig};
 
     pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
     pub const RPC_TIMEOUT: Duration = Duration::from_secs(5);
@@ -394,13 +736,21 @@ pub mod config {
     fn channel_for_uri(uri: &str) -> Result<Channel, DecodeError> {
         let uri: Uri = uri
             .parse()
-            .map_err(|_| DecodeError::other(format!("invalid config url: {uri}")))?;
-        let channel = Endpoint::from(uri)
+            .map_err(|_| DecodeError::InvalidUrl(uri.to_string()))?;
+        let mut endpoint = Endpoint::from(uri)
             .connect_timeout(CONNECT_TIMEOUT)
             .timeout(RPC_TIMEOUT)
-            .tcp_keepalive(Some(RPC_TCP_KEEPALIVE))
-            .connect_lazy();
-        Ok(channel)
+            .tcp_keepalive(Some(RPC_TCP_KEEPALIVE));
+        if let Some(pem) = network::root_pem() {
+            let tls = ClientTlsConfig::new().ca_certificate(Certificate::from_pem(pem));
+            endpoint = endpoint
+                .tls_config(tls)
+                .map_err(|err| DecodeError::other(format!("invalid tls config: {err}")))?;
+        }
+        match network::grpc_connector()? {
+            Some(connector) => Ok(endpoint.connect_with_connector_lazy(connector)),
+            None => Ok(endpoint.connect_lazy()),
+        }
     }
 
     #[derive(Clone)]
@@ -456,15 +806,21 @@ pub mod config {
 
     pub mod iot {
         use super::*;
-        use helium_proto::services::iot_config::{
-            GatewayClient, GatewayInfo, GatewayInfoReqV1, GatewayInfoResV1, GatewayInfoStreamReqV1,
-            GatewayInfoStreamResV1,
+        use helium_proto::{
+            services::iot_config::{
+                GatewayClient, GatewayInfo, GatewayInfoReqV1, GatewayInfoResV1,
+                GatewayInfoStreamReqV1, GatewayInfoStreamResV1, GatewayRegionParamsReqV1,
+                GatewayRegionParamsResV1,
+            },
+            BlockchainRegionParamsV1, Region,
         };
 
         impl_message_sign!(GatewayInfoReqV1);
         impl_message_sign!(GatewayInfoStreamReqV1);
+        impl_message_sign!(GatewayRegionParamsReqV1);
         impl_message_verify!(GatewayInfoResV1);
         impl_message_verify!(GatewayInfoStreamResV1);
+        impl_message_verify!(GatewayRegionParamsResV1);
 
         #[derive(Clone)]
         pub struct Client {
@@ -488,10 +844,10 @@ pub mod config {
                 })
             }
 
-            pub async fn info(
+            async fn info_res(
                 &mut self,
                 address: &helium_crypto::PublicKey,
-            ) -> Result<Option<HotspotInfo>, Error> {
+            ) -> Result<Option<GatewayInfoResV1>, Error> {
                 let mut req = GatewayInfoReqV1 {
                     signer: self.keypair.public_key().into(),
                     address: address.into(),
@@ -502,7 +858,56 @@ pub mod config {
                     Ok(resp) => {
                         let inner = resp.into_inner();
                         inner.verify(&self.address)?;
-                        info_from_res(inner)
+                        Ok(Some(inner))
+                    }
+                    Err(status) if status.code() == tonic::Code::NotFound => Ok(None),
+                    Err(err) => Err(err.into()),
+                }
+            }
+
+            pub async fn info(
+                &mut self,
+                address: &helium_crypto::PublicKey,
+            ) -> Result<Option<HotspotInfo>, Error> {
+                match self.info_res(address).await? {
+                    Some(res) => info_from_res(res),
+                    None => Ok(None),
+                }
+            }
+
+            /// The region of the asserted location of the given Hotspot, if
+            /// known to the config service
+            pub async fn region(
+                &mut self,
+                address: &helium_crypto::PublicKey,
+            ) -> Result<Option<Region>, Error> {
+                let region = self
+                    .info_res(address)
+                    .await?
+                    .and_then(|res| res.info)
+                    .and_then(|info| info.metadata)
+                    .and_then(|metadata| Region::try_from(metadata.region).ok());
+                Ok(region)
+            }
+
+            /// The LoRa parameters of the given region, including the maximum
+            /// EIRP of its channels, as served to gateways. The request is made
+            /// for the key this client signs with.
+            pub async fn region_params(
+                &mut self,
+                region: Region,
+            ) -> Result<Option<BlockchainRegionParamsV1>, Error> {
+                let mut req = GatewayRegionParamsReqV1 {
+                    region: region.into(),
+                    address: self.keypair.public_key().into(),
+                    signature: vec![],
+                };
+                req.sign(&self.keypair)?;
+                match self.client.region_params(req).await {
+                    Ok(resp) => {
+                        let inner = resp.into_inner();
+                        inner.verify(&self.address)?;
+                        Ok(inner.params)
                     }
                     Err(status) if status.code() == tonic::Code::NotFound => Ok(None),
                     Err(err) => Err(err.into()),
@@ -747,3 +1152,39 @@ pub mod config {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_page() {
+        assert_eq!(split_page_start(1, 1000, 500), (1, 0));
+        assert_eq!(split_page_start(2, 1000, 500), (3, 0));
+        assert_eq!(split_page_start(2, 1000, 300), (4, 100));
+        assert_eq!(split_page_start(3, 250, 100), (6, 0));
+    }
+
+    #[test]
+    fn ws_urls() {
+        assert_eq!(
+            ws_url(SOLANA_URL_MAINNET).expect("mainnet"),
+            "wss://solana-rpc.web.helium.io/?session-key=Pluto"
+        );
+        assert_eq!(
+            ws_url("http://localhost:8899").expect("local"),
+            "ws://localhost:8900/"
+        );
+        assert!(ws_url("ftp://localhost").is_err());
+    }
+
+    #[test]
+    fn localhost_urls() {
+        assert!(is_localhost("http://localhost:8899"));
+        assert!(is_localhost("http://127.0.0.1:8899"));
+        assert!(is_localhost("http://[::1]:8899"));
+        assert!(!is_localhost("m"));
+        assert!(!is_localhost(SOLANA_URL_MAINNET));
+        assert!(!is_localhost("http://localhost.example.com"));
+    }
+}
@@ -0,0 +1,212 @@
+//! Confirmation of submitted transactions.
+//!
+//! [`wait`] subscribes to the status of a transaction signature over the
+//! websocket of the RPC node and returns once the transaction reaches the
+//! requested commitment. The subscription is backed by a status check right
+//! after subscribing, since a transaction that landed before the
+//! subscription was made is not notified, and by periodic checks of the
+//! transaction blockhash so a transaction that was dropped is reported as
+//! expired instead of waiting for the full timeout.
+//!
+//! The websocket can not use the proxy and root certificates of
+//! [`crate::network`], so with those configured the status is polled over
+//! the RPC client instead.
+use crate::{
+    client::{self, SolanaRpcClient},
+    error::Error,
+    keypair::Signature,
+    network,
+    solana_client::{
+        nonblocking::pubsub_client::{PubsubClient, PubsubClientError},
+        rpc_config::RpcSignatureSubscribeConfig,
+        rpc_response::RpcSignatureResult,
+    },
+    solana_sdk::{
+        commitment_config::{CommitmentConfig, CommitmentLevel},
+        transaction::{TransactionError, VersionedTransaction},
+    },
+};
+use futures::StreamExt;
+use serde::Serialize;
+use std::time::Duration;
+
+/// Interval between checks of the transaction blockhash while waiting
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfirmError {
+    #[error("transaction {0} expired before it was confirmed")]
+    Expired(Signature),
+    #[error("transaction {0} was not confirmed within {1:?}")]
+    Timeout(Signature, Duration),
+    #[error("transaction {0} failed: {1}")]
+    Failed(Signature, TransactionError),
+    #[error("subscription: {0}")]
+    Subscription(#[from] PubsubClientError),
+}
+
+/// A transaction that reached a commitment level
+#[derive(Debug, Serialize, Clone)]
+pub struct Confirmation {
+    pub signature: String,
+    /// Slot the transaction was processed in
+    pub slot: u64,
+    pub commitment: CommitmentLevel,
+}
+
+impl Confirmation {
+    pub fn is_finalized(&self) -> bool {
+        self.commitment == CommitmentLevel::Finalized
+    }
+}
+
+/// Wait for the given submitted transaction to reach the given commitment,
+/// for at most the given time.
+pub async fn wait<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    tx: &VersionedTransaction,
+    commitment: CommitmentLevel,
+    timeout: Duration,
+) -> Result<Confirmation, Error> {
+    let signature = tx.signatures[0];
+    match tokio::time::timeout(timeout, wait_for(client, tx, commitment)).await {
+        Ok(result) => result,
+        Err(_) => Err(ConfirmError::Timeout(signature, timeout).into()),
+    }
+}
+
+async fn wait_for<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    tx: &VersionedTransaction,
+    commitment: CommitmentLevel,
+) -> Result<Confirmation, Error> {
+    let solana_client = client.as_ref();
+    let signature = tx.signatures[0];
+    let commitment_config = CommitmentConfig { commitment };
+    if network::is_customized() {
+        return poll(client, tx, commitment_config).await;
+    }
+    let pubsub = PubsubClient::new(&client::ws_url(&solana_client.url())?)
+        .await
+        .map_err(ConfirmError::from)?;
+    let (mut notifications, unsubscribe) = pubsub
+        .signature_subscribe(
+            &signature,
+            Some(RpcSignatureSubscribeConfig {
+                commitment: Some(commitment_config),
+                enable_received_notification: Some(false),
+            }),
+        )
+        .await
+        .map_err(ConfirmError::from)?;
+
+    let result = async {
+        if let Some(confirmation) = status(client, &signature, commitment_config).await? {
+            return Ok(confirmation);
+        }
+        let mut expiry_check = tokio::time::interval(EXPIRY_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                notification = notifications.next() => {
+                    let Some(notification) = notification else {
+                        // The subscription closed, fall back to polling
+                        return poll(client, tx, commitment_config).await;
+                    };
+                    if let RpcSignatureResult::ProcessedSignature(processed) = notification.value {
+                        if let Some(err) = processed.err {
+                            return Err(ConfirmError::Failed(signature, err).into());
+                        }
+                        return Ok(Confirmation {
+                            signature: signature.to_string(),
+                            slot: notification.context.slot,
+                            commitment,
+                        });
+                    }
+                }
+                _ = expiry_check.tick() => {
+                    if is_expired(client, tx).await? {
+                        // The transaction may have landed just before its
+                        // blockhash expired
+                        return status(client, &signature, commitment_config)
+                            .await?
+                            .ok_or_else(|| ConfirmError::Expired(signature).into());
+                    }
+                }
+            }
+        }
+    }
+    .await;
+    unsubscribe().await;
+    result
+}
+
+/// Poll the status of the given transaction until it reaches the given
+/// commitment or its blockhash expires
+async fn poll<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    tx: &VersionedTransaction,
+    commitment: CommitmentConfig,
+) -> Result<Confirmation, Error> {
+    let signature = tx.signatures[0];
+    loop {
+        if let Some(confirmation) = status(client, &signature, commitment).await? {
+            return Ok(confirmation);
+        }
+        if is_expired(client, tx).await? {
+            return status(client, &signature, commitment)
+                .await?
+                .ok_or_else(|| ConfirmError::Expired(signature).into());
+        }
+        tokio::time::sleep(EXPIRY_CHECK_INTERVAL).await;
+    }
+}
+
+/// The confirmation of the given transaction if it reached the given
+/// commitment
+async fn status<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    signature: &Signature,
+    commitment: CommitmentConfig,
+) -> Result<Option<Confirmation>, Error> {
+    let statuses = client
+        .as_ref()
+        .get_signature_statuses(&[*signature])
+        .await?
+        .value;
+    let Some(Some(status)) = statuses.into_iter().next() else {
+        return Ok(None);
+    };
+    if let Some(err) = status.err.clone() {
+        return Err(ConfirmError::Failed(*signature, err).into());
+    }
+    if !status.satisfies_commitment(commitment) {
+        return Ok(None);
+    }
+    let commitment = match status.confirmation_status() {
+        solana_transaction_status::TransactionConfirmationStatus::Processed => {
+            CommitmentLevel::Processed
+        }
+        solana_transaction_status::TransactionConfirmationStatus::Confirmed => {
+            CommitmentLevel::Confirmed
+        }
+        solana_transaction_status::TransactionConfirmationStatus::Finalized => {
+            CommitmentLevel::Finalized
+        }
+    };
+    Ok(Some(Confirmation {
+        signature: signature.to_string(),
+        slot: status.slot,
+        commitment,
+    }))
+}
+
+async fn is_expired<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    tx: &VersionedTransaction,
+) -> Result<bool, Error> {
+    let valid = client
+        .as_ref()
+        .is_blockhash_valid(tx.message.recent_blockhash(), CommitmentConfig::processed())
+        .await?;
+    Ok(!valid)
+}
@@ -1,6 +1,11 @@
 use crate::{
-    data_credits, entity_key::AsEntityKey, helium_entity_manager, helium_sub_daos, keypair::Pubkey,
-    programs::TOKEN_METADATA_PROGRAM_ID, rewards_oracle, token::Token,
+    data_credits,
+    entity_key::AsEntityKey,
+    helium_entity_manager, helium_sub_daos,
+    keypair::Pubkey,
+    programs::{TOKEN_METADATA_PROGRAM_ID, VOTER_STAKE_REGISTRY_PROGRAM_ID},
+    rewards_oracle,
+    token::Token,
 };
 use chrono::Timelike;
 use sha2::{Digest, Sha256};
@@ -116,6 +121,15 @@ impl Dao {
             Pubkey::find_program_address(&[b"dc", Token::Dc.mint().as_ref()], &data_credits::id());
         key
     }
+
+    /// The staking position backed by the position NFT with the given mint
+    pub fn position_key(mint: &Pubkey) -> Pubkey {
+        let (key, _) = Pubkey::find_program_address(
+            &[b"position", mint.as_ref()],
+            &VOTER_STAKE_REGISTRY_PROGRAM_ID,
+        );
+        key
+    }
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
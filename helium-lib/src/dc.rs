@@ -17,13 +17,14 @@ use helium_anchor_gen::{
     helium_sub_daos::{self, DaoV0, SubDaoV0},
 };
 
-pub async fn mint_message<C: AsRef<SolanaRpcClient>>(
-    client: &C,
+/// Build the instruction minting data credits for the given payee from
+/// either an HNT or DC amount
+pub fn mint_instruction(
     amount: TokenAmount,
     payee: &Pubkey,
-    payer: &Pubkey,
-    opts: &TransactionOpts,
-) -> Result<(message::VersionedMessage, u64), Error> {
+    owner: &Pubkey,
+    hnt_price_oracle: Pubkey,
+) -> Result<Instruction, Error> {
     fn token_amount_to_mint_args(
         amount: TokenAmount,
     ) -> Result<data_credits::MintDataCreditsArgsV0, DecodeError> {
@@ -61,20 +62,30 @@ pub async fn mint_message<C: AsRef<SolanaRpcClient>>(
         }
     }
 
+    Ok(Instruction {
+        program_id: data_credits::id(),
+        accounts: mk_accounts(owner, *payee, hnt_price_oracle).to_account_metas(None),
+        data: data_credits::instruction::MintDataCreditsV0 {
+            _args: token_amount_to_mint_args(amount)?,
+        }
+        .data(),
+    })
+}
+
+pub async fn mint_message<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    amount: TokenAmount,
+    payee: &Pubkey,
+    payer: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<(message::VersionedMessage, u64), Error> {
     let hnt_price_oracle = client
         .as_ref()
         .anchor_account::<data_credits::DataCreditsV0>(&Dao::dc_key())
         .await?
         .hnt_price_oracle;
 
-    let ix = Instruction {
-        program_id: data_credits::id(),
-        accounts: mk_accounts(payer, *payee, hnt_price_oracle).to_account_metas(None),
-        data: data_credits::instruction::MintDataCreditsV0 {
-            _args: token_amount_to_mint_args(amount)?,
-        }
-        .data(),
-    };
+    let ix = mint_instruction(amount, payee, payer, hnt_price_oracle)?;
 
     let ixs = &[
         priority_fee::compute_budget_instruction(300_000),
@@ -102,14 +113,14 @@ pub async fn mint<C: AsRef<SolanaRpcClient>>(
     Ok((txn, block_height))
 }
 
-pub async fn delegate_message<C: AsRef<SolanaRpcClient>>(
-    client: &C,
+/// Build the instruction delegating the given amount of data credits from
+/// the owner to the given router payer key in a subdao
+pub fn delegate_instruction(
     subdao: SubDao,
     payer_key: &str,
     amount: u64,
     owner: &Pubkey,
-    opts: &TransactionOpts,
-) -> Result<(message::VersionedMessage, u64), Error> {
+) -> Instruction {
     fn mk_accounts(delegated_dc_key: Pubkey, subdao: SubDao, owner: Pubkey) -> impl ToAccountMetas {
         data_credits::accounts::DelegateDataCreditsV0 {
             delegated_data_credits: delegated_dc_key,
@@ -128,7 +139,7 @@ pub async fn delegate_message<C: AsRef<SolanaRpcClient>>(
     }
 
     let delegated_dc_key = subdao.delegated_dc_key(payer_key);
-    let ix = Instruction {
+    Instruction {
         program_id: data_credits::id(),
         accounts: mk_accounts(delegated_dc_key, subdao, *owner).to_account_metas(None),
         data: data_credits::instruction::DelegateDataCreditsV0 {
@@ -138,7 +149,20 @@ pub async fn delegate_message<C: AsRef<SolanaRpcClient>>(
             },
         }
         .data(),
-    };
+    }
+}
+
+/// Construct a message delegating the given amount of data credits to the
+/// given router payer key in a subdao. The amount has to be in data credits.
+pub async fn delegate_message<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    subdao: SubDao,
+    payer_key: &str,
+    amount: TokenAmount,
+    owner: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<(message::VersionedMessage, u64), Error> {
+    let ix = delegate_instruction(subdao, payer_key, amount.amount_for(Token::Dc)?, owner);
 
     let ixs = &[
         priority_fee::compute_budget_instruction(150_000),
@@ -157,7 +181,7 @@ pub async fn delegate<C: AsRef<SolanaRpcClient>>(
     client: &C,
     subdao: SubDao,
     payer_key: &str,
-    amount: u64,
+    amount: TokenAmount,
     keypair: &Keypair,
     opts: &TransactionOpts,
 ) -> Result<(VersionedTransaction, u64), Error> {
@@ -167,12 +191,51 @@ pub async fn delegate<C: AsRef<SolanaRpcClient>>(
     Ok((txn, block_height))
 }
 
-pub async fn burn_message<C: AsRef<SolanaRpcClient>>(
+/// Like [`delegate_message`], with the amount in data credits
+#[deprecated(note = "use delegate_message with a TokenAmount")]
+pub async fn delegate_message_u64<C: AsRef<SolanaRpcClient>>(
     client: &C,
+    subdao: SubDao,
+    payer_key: &str,
     amount: u64,
     owner: &Pubkey,
     opts: &TransactionOpts,
 ) -> Result<(message::VersionedMessage, u64), Error> {
+    delegate_message(
+        client,
+        subdao,
+        payer_key,
+        Token::Dc.amount(amount),
+        owner,
+        opts,
+    )
+    .await
+}
+
+/// Like [`delegate`], with the amount in data credits
+#[deprecated(note = "use delegate with a TokenAmount")]
+pub async fn delegate_u64<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    subdao: SubDao,
+    payer_key: &str,
+    amount: u64,
+    keypair: &Keypair,
+    opts: &TransactionOpts,
+) -> Result<(VersionedTransaction, u64), Error> {
+    delegate(
+        client,
+        subdao,
+        payer_key,
+        Token::Dc.amount(amount),
+        keypair,
+        opts,
+    )
+    .await
+}
+
+/// Build the instruction burning the given amount of data credits held by
+/// the owner
+pub fn burn_instruction(amount: u64, owner: &Pubkey) -> Instruction {
     fn mk_accounts(owner: Pubkey) -> impl ToAccountMetas {
         data_credits::accounts::BurnWithoutTrackingV0 {
             BurnWithoutTrackingV0burn_accounts:
@@ -188,14 +251,23 @@ pub async fn burn_message<C: AsRef<SolanaRpcClient>>(
         }
     }
 
-    let ix = Instruction {
+    Instruction {
         program_id: data_credits::id(),
         accounts: mk_accounts(*owner).to_account_metas(None),
         data: data_credits::instruction::BurnWithoutTrackingV0 {
             _args: data_credits::BurnWithoutTrackingArgsV0 { amount },
         }
         .data(),
-    };
+    }
+}
+
+pub async fn burn_message<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    amount: u64,
+    owner: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<(message::VersionedMessage, u64), Error> {
+    let ix = burn_instruction(amount, owner);
 
     let ixs = &[
         priority_fee::compute_budget_instruction(150_000),
@@ -221,21 +293,22 @@ pub async fn burn<C: AsRef<SolanaRpcClient>>(
     Ok((txn, block_height))
 }
 
-pub async fn burn_delegated_message<C: AsRef<SolanaRpcClient>>(
-    client: &C,
+/// Build the instruction burning the given amount of data credits delegated
+/// to the given router key in a subdao
+pub fn burn_delegated_instruction(
     sub_dao: SubDao,
     amount: u64,
-    router_key: Pubkey,
-    payer: &Pubkey,
-    opts: &TransactionOpts,
-) -> Result<(message::VersionedMessage, u64), Error> {
+    router_key: &str,
+    dc_burn_authority: Pubkey,
+    registrar: Pubkey,
+) -> Instruction {
     fn mk_accounts(
         sub_dao: SubDao,
-        router_key: Pubkey,
+        router_key: &str,
         dc_burn_authority: Pubkey,
         registrar: Pubkey,
     ) -> BurnDelegatedDataCreditsV0 {
-        let delegated_data_credits = sub_dao.delegated_dc_key(&router_key.to_string());
+        let delegated_data_credits = sub_dao.delegated_dc_key(router_key);
         let escrow_account = sub_dao.escrow_key(&delegated_data_credits);
 
         BurnDelegatedDataCreditsV0 {
@@ -258,25 +331,43 @@ pub async fn burn_delegated_message<C: AsRef<SolanaRpcClient>>(
         }
     }
 
-    let (dc_burn_authority, registrar) = {
-        let account_data = client.as_ref().get_account_data(&sub_dao.key()).await?;
-        let sub_dao = SubDaoV0::try_deserialize(&mut account_data.as_ref())?;
-
-        let account_data = client.as_ref().get_account_data(&Dao::Hnt.key()).await?;
-        let dao = DaoV0::try_deserialize(&mut account_data.as_ref())?;
-
-        (sub_dao.dc_burn_authority, dao.registrar)
-    };
-
     let accounts = mk_accounts(sub_dao, router_key, dc_burn_authority, registrar);
-    let burn_ix = solana_sdk::instruction::Instruction {
+    Instruction {
         program_id: data_credits::id(),
         accounts: accounts.to_account_metas(None),
         data: data_credits::instruction::BurnDelegatedDataCreditsV0 {
             _args: data_credits::BurnDelegatedDataCreditsArgsV0 { amount },
         }
         .data(),
-    };
+    }
+}
+
+/// Get the DC burn authority of a subdao and the registrar of the DAO, the
+/// accounts needed to burn delegated data credits
+pub async fn burn_authority<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    sub_dao: SubDao,
+) -> Result<(Pubkey, Pubkey), Error> {
+    let account_data = client.as_ref().get_account_data(&sub_dao.key()).await?;
+    let sub_dao = SubDaoV0::try_deserialize(&mut account_data.as_ref())?;
+
+    let account_data = client.as_ref().get_account_data(&Dao::Hnt.key()).await?;
+    let dao = DaoV0::try_deserialize(&mut account_data.as_ref())?;
+
+    Ok((sub_dao.dc_burn_authority, dao.registrar))
+}
+
+pub async fn burn_delegated_message<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    sub_dao: SubDao,
+    amount: u64,
+    router_key: &str,
+    payer: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<(message::VersionedMessage, u64), Error> {
+    let (dc_burn_authority, registrar) = burn_authority(client, sub_dao).await?;
+    let burn_ix =
+        burn_delegated_instruction(sub_dao, amount, router_key, dc_burn_authority, registrar);
 
     let ixs = &[
         priority_fee::compute_budget_instruction(150_000),
@@ -296,7 +387,7 @@ pub async fn burn_delegated<C: AsRef<SolanaRpcClient>>(
     sub_dao: SubDao,
     keypair: &Keypair,
     amount: u64,
-    router_key: Pubkey,
+    router_key: &str,
     opts: &TransactionOpts,
 ) -> Result<(VersionedTransaction, u64), Error> {
     let (msg, block_height) =
@@ -305,3 +396,80 @@ pub async fn burn_delegated<C: AsRef<SolanaRpcClient>>(
     let txn = VersionedTransaction::try_new(msg, &[keypair])?;
     Ok((txn, block_height))
 }
+
+/// A delegation of data credits to a router key in a subdao
+#[derive(Debug, Clone, Copy)]
+pub struct Delegation<'a> {
+    pub subdao: SubDao,
+    pub router_key: &'a str,
+}
+
+/// Build the instructions moving delegated data credits from one router key
+/// to another, see [`migrate_delegation_message`].
+///
+/// The escrow of the old router key is only burned from when the DC burn
+/// authority of its subdao and the registrar of the DAO, as returned by
+/// [`burn_authority`], are given.
+pub fn migrate_delegation_instructions(
+    from: Delegation<'_>,
+    to: Delegation<'_>,
+    amount: u64,
+    burn_accounts: Option<(Pubkey, Pubkey)>,
+    owner: &Pubkey,
+) -> Vec<Instruction> {
+    let mut ixs = vec![];
+    if let Some((dc_burn_authority, registrar)) = burn_accounts {
+        ixs.push(burn_delegated_instruction(
+            from.subdao,
+            amount,
+            from.router_key,
+            dc_burn_authority,
+            registrar,
+        ));
+    }
+    ixs.push(delegate_instruction(
+        to.subdao,
+        to.router_key,
+        amount,
+        owner,
+    ));
+    ixs
+}
+
+/// Build a message moving delegated data credits from one router key to
+/// another.
+///
+/// Delegated data credits can not be undelegated, the data credits program
+/// has no instruction returning escrowed data credits or closing a
+/// delegation. The owner delegates the given amount of its own data credits
+/// to the new router key and, when `burn_old` is set, the same amount is
+/// burned from the escrow of the old router key in the same transaction.
+/// Burning requires the owner to be the DC burn authority of the old subdao.
+pub async fn migrate_delegation_message<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    from: Delegation<'_>,
+    to: Delegation<'_>,
+    amount: u64,
+    burn_old: bool,
+    owner: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<(message::VersionedMessage, u64), Error> {
+    let burn_accounts = if burn_old {
+        Some(burn_authority(client, from.subdao).await?)
+    } else {
+        None
+    };
+    let migrate_ixs = migrate_delegation_instructions(from, to, amount, burn_accounts, owner);
+
+    let accounts: Vec<_> = migrate_ixs
+        .iter()
+        .flat_map(|ix| ix.accounts.clone())
+        .collect();
+    let mut ixs = vec![
+        priority_fee::compute_budget_instruction(150_000 * migrate_ixs.len() as u32),
+        priority_fee::compute_price_instruction_for_accounts(client, &accounts, opts.fee_range())
+            .await?,
+    ];
+    ixs.extend(migrate_ixs);
+    message::mk_message(client, &ixs, &opts.lut_addresses, owner).await
+}
@@ -1,4 +1,8 @@
-use crate::{anchor_client, client, hotspot::cert, onboarding, solana_client, token};
+use crate::{
+    anchor_client, client, confirm,
+    hotspot::{self, cert, region},
+    onboarding, payer, reward, solana_client, swap, token,
+};
 use std::{array::TryFromSliceError, num::TryFromIntError};
 use thiserror::Error;
 
@@ -21,10 +25,26 @@ pub enum Error {
     Grpc(#[from] tonic::Status),
     #[error("service: {0}")]
     Service(#[from] helium_proto::services::Error),
+    #[error("payer: {0}")]
+    Payer(#[from] payer::PayerError),
+    #[error("gain: {0}")]
+    Gain(#[from] region::GainError),
+    #[error("token: {0}")]
+    Token(#[from] token::TokenError),
+    #[error("reward: {0}")]
+    Reward(#[from] reward::RewardError),
+    #[error("hotspot: {0}")]
+    Hotspot(#[from] hotspot::HotspotError),
     #[error("price client: {0}")]
     Price(#[from] token::price::PriceError),
+    #[error("swap: {0}")]
+    Swap(#[from] swap::SwapError),
     #[error("rest client: {0}")]
     Rest(#[from] reqwest::Error),
+    #[error("confirm: {0}")]
+    Confirm(#[from] confirm::ConfirmError),
+    #[error("oracle: {0}")]
+    Oracle(#[from] OracleError),
     #[error("system time: {0}")]
     Time(#[from] std::time::SystemTimeError),
     #[error("program: {0}")]
@@ -74,6 +94,293 @@ impl Error {
             _ => false,
         }
     }
+
+    /// The kind of service a failed request was made to
+    pub fn service(&self) -> Service {
+        match self {
+            Self::Solana(_)
+            | Self::Anchor(_)
+            | Self::Confirm(confirm::ConfirmError::Subscription(_)) => Service::Rpc,
+            Self::Das(_) => Service::Das,
+            Self::Oracle(_) => Service::Oracle,
+            Self::Onboarding(_)
+            | Self::Cert(_)
+            | Self::Rest(_)
+            | Self::Swap(swap::SwapError::Api(_)) => Service::Rest,
+            Self::Grpc(_) | Self::Service(_) => Service::Grpc,
+            _ => Service::Local,
+        }
+    }
+
+    /// The url of the endpoint a failed request was made to, if known
+    pub fn endpoint(&self) -> Option<String> {
+        match self {
+            Self::Oracle(err) => Some(err.endpoint.clone()),
+            Self::Rest(err) => err.url().map(ToString::to_string),
+            Self::Onboarding(onboarding::OnboardingError::Client(err)) => {
+                err.url().map(ToString::to_string)
+            }
+            Self::Das(client::DasClientError::Rpc(jsonrpc_client::Error::Client(err))) => {
+                err.url().map(ToString::to_string)
+            }
+            Self::Solana(err) => match &err.kind {
+                solana_client::client_error::ClientErrorKind::Reqwest(err) => {
+                    err.url().map(ToString::to_string)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The HTTP status code of a failed request, if any
+    pub fn http_status(&self) -> Option<u16> {
+        match self {
+            Self::Oracle(err) => err.status(),
+            Self::Rest(err) => err.status().map(|status| status.as_u16()),
+            Self::Onboarding(err) => err.status(),
+            Self::Das(client::DasClientError::Rpc(jsonrpc_client::Error::Client(err))) => {
+                err.status().map(|status| status.as_u16())
+            }
+            Self::Solana(err) => match &err.kind {
+                solana_client::client_error::ClientErrorKind::Reqwest(err) => {
+                    err.status().map(|status| status.as_u16())
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The JSON-RPC error code of a failed request, if any
+    pub fn rpc_code(&self) -> Option<i64> {
+        match self {
+            Self::Das(client::DasClientError::Rpc(jsonrpc_client::Error::JsonRpc(err))) => {
+                Some(err.code)
+            }
+            Self::Solana(err) => match &err.kind {
+                solana_client::client_error::ClientErrorKind::RpcError(
+                    solana_client::rpc_request::RpcError::RpcResponseError { code, .. },
+                ) => Some(*code),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Why the request or transaction failed, for causes callers commonly
+    /// need to handle differently. `None` for failures that are not
+    /// classified, which are best reported as is.
+    pub fn cause(&self) -> Option<FailureCause> {
+        if self.is_account_not_found() {
+            return Some(FailureCause::AccountNotFound);
+        }
+        let cause = match self {
+            Self::Solana(err) => FailureCause::for_client_error(err),
+            Self::Anchor(anchor_client::ClientError::SolanaClientError(err)) => {
+                FailureCause::for_client_error(err)
+            }
+            Self::Confirm(confirm::ConfirmError::Expired(_)) => {
+                Some(FailureCause::BlockhashExpired)
+            }
+            Self::Confirm(confirm::ConfirmError::Failed(_, err)) => Some(FailureCause::from(err)),
+            Self::Program(solana_program::program_error::ProgramError::Custom(code)) => {
+                Some(FailureCause::ProgramError {
+                    instruction: None,
+                    code: *code,
+                })
+            }
+            _ => None,
+        };
+        cause.or_else(|| (self.http_status() == Some(429)).then_some(FailureCause::RateLimited))
+    }
+
+    /// Whether the failed request may succeed if retried later, as opposed to
+    /// being rejected as invalid.
+    pub fn is_retryable(&self) -> bool {
+        use solana_client::{
+            client_error::ClientErrorKind, rpc_custom_error, rpc_request::RpcError,
+        };
+        fn retryable_status(status: Option<u16>) -> bool {
+            status.is_some_and(|status| status == 429 || (500..600).contains(&status))
+        }
+        let retryable = match self {
+            Self::Oracle(err) => err.source.is_timeout() || err.source.is_connect(),
+            Self::Rest(err) => err.is_timeout() || err.is_connect(),
+            Self::Onboarding(err) => err.is_retryable(),
+            Self::Das(client::DasClientError::Rpc(jsonrpc_client::Error::Client(err))) => {
+                err.is_timeout() || err.is_connect()
+            }
+            Self::Das(client::DasClientError::Rpc(jsonrpc_client::Error::JsonRpc(err))) => {
+                // JSON-RPC internal error
+                err.code == -32603
+            }
+            Self::Solana(err) => match &err.kind {
+                ClientErrorKind::Io(_) => true,
+                ClientErrorKind::Reqwest(err) => err.is_timeout() || err.is_connect(),
+                ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => matches!(
+                    *code,
+                    rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+                        | rpc_custom_error::JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE
+                        | rpc_custom_error::JSON_RPC_SERVER_ERROR_BLOCK_STATUS_NOT_AVAILABLE_YET
+                        | rpc_custom_error::JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED
+                ),
+                _ => false,
+            },
+            Self::Grpc(status) => matches!(
+                status.code(),
+                tonic::Code::Unavailable
+                    | tonic::Code::DeadlineExceeded
+                    | tonic::Code::ResourceExhausted
+            ),
+            _ => false,
+        };
+        retryable || retryable_status(self.http_status())
+    }
+}
+
+/// Common causes of failed requests and transactions, see [`Error::cause`]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "cause", rename_all = "snake_case")]
+pub enum FailureCause {
+    /// An account expected to exist, like the fee payer or a key to asset
+    /// account, does not
+    AccountNotFound,
+    /// The fee payer can not pay the transaction fee
+    InsufficientFundsForFee,
+    /// An instruction tried to move more lamports than an account holds
+    InsufficientFunds { instruction: u8 },
+    /// An account would be left with less than its rent exempt minimum
+    InsufficientFundsForRent { account_index: u8 },
+    /// The blockhash of the transaction expired, or is unknown to the node
+    BlockhashExpired,
+    /// The transaction was processed before
+    AlreadyProcessed,
+    /// An instruction failed with a custom program error. The instruction is
+    /// not known for errors raised before a transaction was sent. Custom
+    /// codes are program specific, for example code 1 of the token program
+    /// is insufficient token funds.
+    ProgramError { instruction: Option<u8>, code: u32 },
+    /// An instruction failed with any other error
+    InstructionError { instruction: u8, error: String },
+    /// The transaction was rejected for any other reason
+    TransactionError { error: String },
+    /// The node is unhealthy or behind the cluster
+    NodeUnhealthy,
+    /// The service rejected the request for exceeding its rate limit
+    RateLimited,
+}
+
+impl FailureCause {
+    fn for_client_error(err: &solana_client::client_error::ClientError) -> Option<Self> {
+        use solana_client::{
+            client_error::ClientErrorKind,
+            rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+            rpc_request::{RpcError, RpcResponseErrorData},
+        };
+        if let Some(err) = err.get_transaction_error() {
+            return Some(Self::from(&err));
+        }
+        match &err.kind {
+            ClientErrorKind::RpcError(RpcError::RpcResponseError { code, data, .. })
+                if matches!(data, RpcResponseErrorData::NodeUnhealthy { .. })
+                    || *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY =>
+            {
+                Some(Self::NodeUnhealthy)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<&solana_sdk::transaction::TransactionError> for FailureCause {
+    fn from(value: &solana_sdk::transaction::TransactionError) -> Self {
+        use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+        match value {
+            TransactionError::AccountNotFound => Self::AccountNotFound,
+            TransactionError::InsufficientFundsForFee => Self::InsufficientFundsForFee,
+            TransactionError::InsufficientFundsForRent { account_index } => {
+                Self::InsufficientFundsForRent {
+                    account_index: *account_index,
+                }
+            }
+            TransactionError::BlockhashNotFound => Self::BlockhashExpired,
+            TransactionError::AlreadyProcessed => Self::AlreadyProcessed,
+            TransactionError::InstructionError(instruction, InstructionError::Custom(code)) => {
+                Self::ProgramError {
+                    instruction: Some(*instruction),
+                    code: *code,
+                }
+            }
+            TransactionError::InstructionError(
+                instruction,
+                InstructionError::InsufficientFunds,
+            ) => Self::InsufficientFunds {
+                instruction: *instruction,
+            },
+            TransactionError::InstructionError(instruction, err) => Self::InstructionError {
+                instruction: *instruction,
+                error: err.to_string(),
+            },
+            err => Self::TransactionError {
+                error: err.to_string(),
+            },
+        }
+    }
+}
+
+/// The kind of service an error originated from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Service {
+    /// The Solana RPC node
+    Rpc,
+    /// The DAS asset API
+    Das,
+    /// A lazy distributor oracle or signature verifier
+    Oracle,
+    /// Any other REST service, like onboarding or certificates
+    Rest,
+    /// The config service
+    Grpc,
+    /// Not a remote request failure
+    Local,
+}
+
+impl std::fmt::Display for Service {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::Rpc => "solana rpc",
+            Self::Das => "das api",
+            Self::Oracle => "oracle",
+            Self::Rest => "rest service",
+            Self::Grpc => "config service",
+            Self::Local => "local",
+        };
+        f.write_str(str)
+    }
+}
+
+/// A failed request to an oracle or signature verifier
+#[derive(Debug, Error)]
+#[error("{endpoint}: {source}")]
+pub struct OracleError {
+    pub endpoint: String,
+    #[source]
+    pub source: reqwest::Error,
+}
+
+impl OracleError {
+    pub fn new<S: ToString>(endpoint: S, source: reqwest::Error) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            source,
+        }
+    }
+
+    pub fn status(&self) -> Option<u16> {
+        self.source.status().map(|status| status.as_u16())
+    }
 }
 
 #[derive(Debug, Error)]
@@ -122,6 +429,8 @@ pub enum DecodeError {
     Slice(#[from] TryFromSliceError),
     #[error("crypto: {0}")]
     Crypto(#[from] helium_crypto::Error),
+    #[error("invalid url: {0}")]
+    InvalidUrl(String),
     #[error("decode: {0}")]
     Decode(String),
 }
@@ -131,3 +440,48 @@ impl DecodeError {
         Self::Decode(reason.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    #[test]
+    fn failure_causes() {
+        assert_eq!(
+            FailureCause::from(&TransactionError::InstructionError(
+                2,
+                InstructionError::Custom(6004)
+            )),
+            FailureCause::ProgramError {
+                instruction: Some(2),
+                code: 6004
+            }
+        );
+        assert_eq!(
+            FailureCause::from(&TransactionError::BlockhashNotFound),
+            FailureCause::BlockhashExpired
+        );
+        assert_eq!(
+            Error::account_not_found().cause(),
+            Some(FailureCause::AccountNotFound)
+        );
+        assert_eq!(
+            Error::from(confirm::ConfirmError::Failed(
+                Default::default(),
+                TransactionError::InsufficientFundsForFee
+            ))
+            .cause(),
+            Some(FailureCause::InsufficientFundsForFee)
+        );
+        assert_eq!(Error::from(DecodeError::other("bad")).cause(), None);
+        assert!(matches!(
+            Error::from(token::TokenError::NotMint(Default::default())),
+            Error::Token(token::TokenError::NotMint(_))
+        ));
+        assert_eq!(
+            serde_json::to_value(FailureCause::InsufficientFunds { instruction: 1 }).unwrap(),
+            serde_json::json!({"cause": "insufficient_funds", "instruction": 1})
+        );
+    }
+}
@@ -161,7 +161,7 @@ impl Default for Client {
 
 impl Client {
     pub fn new(url: &str, token: Option<String>) -> Result<Self, Error> {
-        let inner = reqwest::Client::new();
+        let inner = crate::client::http_client();
         let base_url = url.parse().map_err(DecodeError::from)?;
         Ok(Self {
             inner,
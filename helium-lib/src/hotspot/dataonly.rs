@@ -1,11 +1,11 @@
 use crate::{
     anchor_lang::{InstructionData, ToAccountMetas},
     asset, b64,
-    client::{DasClient, GetAnchorAccount, SolanaRpcClient},
+    client::{GetAnchorAccount, GetAsset, SolanaRpcClient},
     dao::{Dao, SubDao},
     data_credits,
     entity_key::AsEntityKey,
-    error::{DecodeError, EncodeError, Error},
+    error::{DecodeError, EncodeError, Error, OracleError},
     helium_entity_manager, helium_sub_daos, hotspot,
     hotspot::{HotspotInfoUpdate, ECC_VERIFIER},
     keypair::{Keypair, Pubkey},
@@ -13,7 +13,9 @@ use crate::{
     programs::{
         SPL_ACCOUNT_COMPRESSION_PROGRAM_ID, SPL_NOOP_PROGRAM_ID, TOKEN_METADATA_PROGRAM_ID,
     },
-    solana_sdk::{instruction::Instruction, signature::Signer, transaction::Transaction},
+    solana_sdk::{
+        instruction::Instruction, signature::Signer, system_instruction, transaction::Transaction,
+    },
     token::Token,
     TransactionOpts,
 };
@@ -25,9 +27,7 @@ use solana_sdk::transaction::VersionedTransaction;
 mod iot {
     use super::*;
 
-    pub async fn onboard_message<
-        C: AsRef<DasClient> + AsRef<SolanaRpcClient> + GetAnchorAccount,
-    >(
+    pub async fn onboard_message<C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount>(
         client: &C,
         hotspot_key: &helium_crypto::PublicKey,
         assertion: HotspotInfoUpdate,
@@ -112,9 +112,7 @@ mod iot {
 mod mobile {
     use super::*;
 
-    pub async fn onboard_message<
-        C: AsRef<DasClient> + AsRef<SolanaRpcClient> + GetAnchorAccount,
-    >(
+    pub async fn onboard_message<C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount>(
         client: &C,
         hotspot_key: &helium_crypto::PublicKey,
         assertion: HotspotInfoUpdate,
@@ -197,7 +195,7 @@ mod mobile {
     }
 }
 
-pub async fn onboard_message<C: AsRef<DasClient> + AsRef<SolanaRpcClient> + GetAnchorAccount>(
+pub async fn onboard_message<C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount>(
     client: &C,
     subdao: SubDao,
     hotspot_key: &helium_crypto::PublicKey,
@@ -213,7 +211,7 @@ pub async fn onboard_message<C: AsRef<DasClient> + AsRef<SolanaRpcClient> + GetA
     }
 }
 
-pub async fn onboard<C: AsRef<DasClient> + AsRef<SolanaRpcClient> + GetAnchorAccount>(
+pub async fn onboard<C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount>(
     client: &C,
     subdao: SubDao,
     hotspot_key: &helium_crypto::PublicKey,
@@ -234,6 +232,112 @@ pub async fn onboard<C: AsRef<DasClient> + AsRef<SolanaRpcClient> + GetAnchorAcc
     Ok((txn, block_height))
 }
 
+/// Capacity of the merkle tree data-only Hotspots are currently issued into
+#[derive(Debug, Serialize, Clone)]
+pub struct TreeCapacity {
+    #[serde(with = "crate::keypair::serde_pubkey")]
+    pub merkle_tree: Pubkey,
+    pub total_mint_capacity: u64,
+    pub num_minted: u64,
+}
+
+impl TreeCapacity {
+    pub fn remaining(&self) -> u64 {
+        self.total_mint_capacity.saturating_sub(self.num_minted)
+    }
+}
+
+/// Look up how many more data-only Hotspots fit in the current tree
+pub async fn tree_capacity<C: AsRef<SolanaRpcClient> + GetAnchorAccount>(
+    client: &C,
+) -> Result<TreeCapacity, Error> {
+    let config = client
+        .anchor_account::<helium_entity_manager::DataOnlyConfigV0>(&Dao::Hnt.dataonly_config_key())
+        .await?;
+    let tree_config_key = Dao::Hnt.merkle_tree_authority(&config.merkle_tree);
+    let data = client.as_ref().get_account_data(&tree_config_key).await?;
+    let tree_config = mpl_bubblegum::accounts::TreeConfig::from_bytes(&data)
+        .map_err(|err| DecodeError::other(format!("tree config {tree_config_key}: {err}")))?;
+    Ok(TreeCapacity {
+        merkle_tree: config.merkle_tree,
+        total_mint_capacity: tree_config.total_mint_capacity,
+        num_minted: tree_config.num_minted,
+    })
+}
+
+/// Construct a message that replaces the data-only merkle tree with a new,
+/// empty tree at the given address, sized by the data-only config.
+///
+/// The payer funds the rent of the new tree account, the entity manager
+/// checks whether the current tree may be replaced.
+pub async fn create_tree_message<C: AsRef<SolanaRpcClient> + GetAnchorAccount>(
+    client: &C,
+    new_merkle_tree: &Pubkey,
+    payer: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<(message::VersionedMessage, u64), Error> {
+    let dao = Dao::Hnt;
+    let data_only_config = dao.dataonly_config_key();
+    let config = client
+        .anchor_account::<helium_entity_manager::DataOnlyConfigV0>(&data_only_config)
+        .await?;
+    let space = usize::try_from(config.new_tree_space).map_err(DecodeError::from)?;
+    let rent = client
+        .as_ref()
+        .get_minimum_balance_for_rent_exemption(space)
+        .await?;
+    let create_ix = system_instruction::create_account(
+        payer,
+        new_merkle_tree,
+        rent,
+        config.new_tree_space,
+        &SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+    );
+
+    let accounts = helium_entity_manager::accounts::UpdateDataOnlyTreeV0 {
+        payer: *payer,
+        data_only_config,
+        old_tree_authority: dao.merkle_tree_authority(&config.merkle_tree),
+        new_tree_authority: dao.merkle_tree_authority(new_merkle_tree),
+        data_only_escrow: dao.dataonly_escrow_key(),
+        new_merkle_tree: *new_merkle_tree,
+        log_wrapper: SPL_NOOP_PROGRAM_ID,
+        system_program: solana_sdk::system_program::id(),
+        bubblegum_program: mpl_bubblegum::ID,
+        compression_program: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+    }
+    .to_account_metas(None);
+    let update_ix = Instruction {
+        program_id: helium_entity_manager::id(),
+        accounts: accounts.clone(),
+        data: helium_entity_manager::instruction::UpdateDataOnlyTreeV0 {}.data(),
+    };
+
+    let ixs = &[
+        priority_fee::compute_budget_instruction(300_000),
+        priority_fee::compute_price_instruction_for_accounts(client, &accounts, opts.fee_range())
+            .await?,
+        create_ix,
+        update_ix,
+    ];
+    message::mk_message(client, ixs, &opts.lut_addresses, payer).await
+}
+
+/// Replace the data-only merkle tree with a new tree, returning the signed
+/// transaction and the address of the new tree.
+pub async fn create_tree<C: AsRef<SolanaRpcClient> + GetAnchorAccount>(
+    client: &C,
+    keypair: &Keypair,
+    opts: &TransactionOpts,
+) -> Result<(VersionedTransaction, u64, Pubkey), Error> {
+    let tree_keypair = Keypair::generate();
+    let new_merkle_tree = tree_keypair.pubkey();
+    let (msg, block_height) =
+        create_tree_message(client, &new_merkle_tree, &keypair.pubkey(), opts).await?;
+    let txn = VersionedTransaction::try_new(msg, &[keypair, &tree_keypair])?;
+    Ok((txn, block_height, new_merkle_tree))
+}
+
 pub async fn issue_transaction<C: AsRef<SolanaRpcClient> + GetAnchorAccount>(
     client: &C,
     verifier: &str,
@@ -241,8 +345,26 @@ pub async fn issue_transaction<C: AsRef<SolanaRpcClient> + GetAnchorAccount>(
     owner: Pubkey,
     opts: &TransactionOpts,
 ) -> Result<(Transaction, u64), Error> {
+    let hotspot_key = helium_crypto::PublicKey::from_bytes(&add_tx.gateway)?;
+    let sig = add_tx.gateway_signature.clone();
+    add_tx.gateway_signature = vec![];
+    let msg = add_tx.encode_to_vec();
+
+    issue_entity_transaction(client, verifier, &hotspot_key, &msg, &sig, owner, opts).await
+}
+
+/// Construct the instruction issuing a data-only entity with the given raw
+/// entity key into the given collection and merkle tree of the data-only
+/// config.
+pub fn issue_entity_instruction(
+    collection: Pubkey,
+    merkle_tree: Pubkey,
+    owner: Pubkey,
+    entity_key: Vec<u8>,
+) -> Instruction {
     fn mk_accounts(
-        config_account: helium_entity_manager::DataOnlyConfigV0,
+        collection: Pubkey,
+        merkle_tree: Pubkey,
         owner: Pubkey,
         entity_key: &[u8],
     ) -> impl ToAccountMetas {
@@ -252,17 +374,16 @@ pub async fn issue_transaction<C: AsRef<SolanaRpcClient> + GetAnchorAccount>(
         IssueDataOnlyEntityV0 {
             payer: owner,
             ecc_verifier: ECC_VERIFIER,
-            collection: config_account.collection,
-            collection_metadata: dao.collection_metadata_key(&config_account.collection),
-            collection_master_edition: dao
-                .collection_master_edition_key(&config_account.collection),
+            collection,
+            collection_metadata: dao.collection_metadata_key(&collection),
+            collection_master_edition: dao.collection_master_edition_key(&collection),
             data_only_config: dataonly_config_key,
             entity_creator: dao.entity_creator_key(),
             dao: dao.key(),
             key_to_asset: dao.entity_key_to_kta_key(&entity_key),
-            tree_authority: dao.merkle_tree_authority(&config_account.merkle_tree),
+            tree_authority: dao.merkle_tree_authority(&merkle_tree),
             recipient: owner,
-            merkle_tree: config_account.merkle_tree,
+            merkle_tree,
             data_only_escrow: dao.dataonly_escrow_key(),
             bubblegum_signer: dao.bubblegum_signer(),
             token_metadata_program: TOKEN_METADATA_PROGRAM_ID,
@@ -273,39 +394,84 @@ pub async fn issue_transaction<C: AsRef<SolanaRpcClient> + GetAnchorAccount>(
         }
     }
 
-    let config_account = client
-        .anchor_account::<helium_entity_manager::DataOnlyConfigV0>(&Dao::Hnt.dataonly_config_key())
-        .await?;
-    let hotspot_key = helium_crypto::PublicKey::from_bytes(&add_tx.gateway)?;
-    let entity_key = hotspot_key.as_entity_key();
-    let accounts = mk_accounts(config_account, owner, &entity_key);
-
-    let issue_ix = Instruction {
+    let accounts = mk_accounts(collection, merkle_tree, owner, &entity_key);
+    Instruction {
         program_id: helium_entity_manager::id(),
         accounts: accounts.to_account_metas(None),
         data: helium_entity_manager::instruction::IssueDataOnlyEntityV0 {
             _args: helium_entity_manager::IssueDataOnlyEntityArgsV0 { entity_key },
         }
         .data(),
-    };
+    }
+}
+
+/// Construct a transaction issuing a data-only entity for the given entity key.
+///
+/// The data-only program records every entity key with a b58 serialization,
+/// so the entity key is the helium public key of the entity. The given
+/// signature of `msg` by that key is checked by the ecc verifier before it
+/// co-signs the returned transaction. This allows entities other
+/// than Hotspots, like service provider keys, to be issued as long as the
+/// verifier permits it.
+pub async fn issue_entity_transaction<C: AsRef<SolanaRpcClient> + GetAnchorAccount>(
+    client: &C,
+    verifier: &str,
+    entity_key: &helium_crypto::PublicKey,
+    msg: &[u8],
+    signature: &[u8],
+    owner: Pubkey,
+    opts: &TransactionOpts,
+) -> Result<(Transaction, u64), Error> {
+    let config_account = client
+        .anchor_account::<helium_entity_manager::DataOnlyConfigV0>(&Dao::Hnt.dataonly_config_key())
+        .await?;
+    let issue_ix = issue_entity_instruction(
+        config_account.collection,
+        config_account.merkle_tree,
+        owner,
+        entity_key.as_entity_key(),
+    );
 
     let ixs = &[
         priority_fee::compute_budget_instruction(300_000),
-        priority_fee::compute_price_instruction_for_accounts(client, &accounts, opts.fee_range())
-            .await?,
+        priority_fee::compute_price_instruction_for_accounts(
+            client,
+            &issue_ix.accounts,
+            opts.fee_range(),
+        )
+        .await?,
         issue_ix,
     ];
 
     let (txn, latest_block_height) = mk_transaction_with_blockhash(client, ixs, &owner).await?;
-
-    let sig = add_tx.gateway_signature.clone();
-    add_tx.gateway_signature = vec![];
-    let msg = add_tx.encode_to_vec();
-
-    let signed_txn = verify_helium_key(verifier, &msg, &sig, txn).await?;
+    let signed_txn = verify_helium_key(verifier, msg, signature, txn).await?;
     Ok((signed_txn, latest_block_height))
 }
 
+pub async fn issue_entity<C: AsRef<SolanaRpcClient> + GetAnchorAccount>(
+    client: &C,
+    verifier: &str,
+    entity_key: &helium_crypto::PublicKey,
+    msg: &[u8],
+    signature: &[u8],
+    keypair: &Keypair,
+    opts: &TransactionOpts,
+) -> Result<(Transaction, u64), Error> {
+    let (mut txn, block_height) = issue_entity_transaction(
+        client,
+        verifier,
+        entity_key,
+        msg,
+        signature,
+        keypair.pubkey(),
+        opts,
+    )
+    .await?;
+    let blockhash = txn.message.recent_blockhash;
+    txn.try_partial_sign(&[keypair], blockhash)?;
+    Ok((txn, block_height))
+}
+
 #[derive(Debug, serde::Serialize)]
 pub struct IssueHotspot {
     key: PublicKey,
@@ -391,7 +557,7 @@ async fn verify_helium_key(
         // hex encoded solana transaction
         pub transaction: String,
     }
-    let client = reqwest::Client::new();
+    let client = crate::client::http_client();
     let serialized_tx = hex::encode(bincode::serialize(&tx).map_err(EncodeError::from)?);
     let response = client
         .post(format!("{}/verify", verifier))
@@ -401,9 +567,12 @@ async fn verify_helium_key(
             signature: &hex::encode(signature),
         })
         .send()
-        .await?
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|err| OracleError::new(verifier, err))?
         .json::<VerifyResponse>()
-        .await?;
+        .await
+        .map_err(|err| OracleError::new(verifier, err))?;
     let signed_tx =
         bincode::deserialize(&hex::decode(response.transaction).map_err(DecodeError::from)?)
             .map_err(DecodeError::from)?;
@@ -0,0 +1,322 @@
+//! Two-party Hotspot sales.
+//!
+//! A sale is a single transaction that transfers the Hotspot from the seller
+//! to the buyer and pays the price from the buyer to the seller, so neither
+//! leg can execute without the other. The seller builds the transaction with
+//! the buyer as fee payer, signs it and hands the resulting [`SaleOffer`] to
+//! the buyer. The buyer checks that the transaction does what the offer says,
+//! adds their signature and submits it.
+//!
+//! The transaction uses a recent blockhash, so the buyer has to accept the
+//! offer before it expires, typically within a minute or two.
+use crate::{
+    asset, b64,
+    client::{GetAsset, SolanaRpcClient},
+    error::{DecodeError, Error},
+    hotspot::HotspotError,
+    keypair::{serde_pubkey, Keypair, Pubkey, Signature, Signer},
+    kta, message,
+    priority_fee::{compute_budget_instruction, compute_price_instruction_for_accounts},
+    solana_sdk::{
+        compute_budget,
+        instruction::{AccountMeta, Instruction},
+        message::VersionedMessage,
+        transaction::VersionedTransaction,
+    },
+    token::{self, Token, TokenAmount},
+    TransactionOpts,
+};
+use serde::{Deserialize, Serialize};
+
+/// Tokens a Hotspot can be sold for
+pub const SALE_TOKENS: [Token; 2] = [Token::Hnt, Token::Usdc];
+
+/// Anchor discriminator of the bubblegum transfer instruction
+const BUBBLEGUM_TRANSFER: [u8; 8] = [163, 52, 200, 231, 140, 3, 69, 186];
+/// Instruction tag of an spl token transfer with a checked amount
+const TOKEN_TRANSFER_CHECKED: u8 = 12;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SaleOffer {
+    pub hotspot: helium_crypto::PublicKey,
+    #[serde(with = "serde_pubkey")]
+    pub asset: Pubkey,
+    #[serde(with = "serde_pubkey")]
+    pub seller: Pubkey,
+    #[serde(with = "serde_pubkey")]
+    pub buyer: Pubkey,
+    pub token: Token,
+    /// Price in the smallest unit of the token
+    pub amount: u64,
+    /// The offer expires when the chain passes this block height
+    pub last_valid_block_height: u64,
+    /// Base64 encoded transaction, signed by the seller
+    pub transaction: String,
+}
+
+impl SaleOffer {
+    pub fn price(&self) -> TokenAmount {
+        self.token.amount(self.amount)
+    }
+
+    pub fn decode_transaction(&self) -> Result<VersionedTransaction, Error> {
+        let bytes = b64::decode(&self.transaction)?;
+        Ok(bincode::deserialize(&bytes).map_err(DecodeError::from)?)
+    }
+}
+
+/// Create an offer to sell the given Hotspot to the given buyer for the given
+/// price, signed by the seller.
+pub async fn offer<C: AsRef<SolanaRpcClient> + GetAsset>(
+    client: &C,
+    hotspot_key: &helium_crypto::PublicKey,
+    buyer: &Pubkey,
+    price: TokenAmount,
+    keypair: &Keypair,
+    opts: &TransactionOpts,
+) -> Result<SaleOffer, Error> {
+    if !SALE_TOKENS.contains(&price.token) {
+        return Err(HotspotError::UnsupportedPrice(price.token).into());
+    }
+    let seller = keypair.pubkey();
+    let kta = kta::for_entity_key(hotspot_key).await?;
+    let (asset, asset_proof) = asset::get_with_proof(client, &kta.asset).await?;
+    if asset.ownership.owner != seller {
+        return Err(HotspotError::NotOwned {
+            asset: asset.id,
+            owner: seller,
+        }
+        .into());
+    }
+
+    let transfer_ix = asset::transfer_instruction(client, &asset, &asset_proof, buyer).await?;
+    let payment_ixs = token::transfer_instructions(&[(seller, price)], None, &[], buyer)?;
+    let mut ixs = vec![
+        compute_budget_instruction(300_000),
+        compute_price_instruction_for_accounts(client, &transfer_ix.accounts, opts.fee_range())
+            .await?,
+        transfer_ix,
+    ];
+    ixs.extend(payment_ixs);
+
+    let (msg, last_valid_block_height) =
+        message::mk_message(client, &ixs, &opts.lut_addresses, buyer).await?;
+    let mut txn = VersionedTransaction {
+        signatures: vec![Signature::default(); msg.header().num_required_signatures.into()],
+        message: msg,
+    };
+    sign_for(&mut txn, keypair)?;
+
+    Ok(SaleOffer {
+        hotspot: hotspot_key.clone(),
+        asset: asset.id,
+        seller,
+        buyer: *buyer,
+        token: price.token,
+        amount: price.amount,
+        last_valid_block_height,
+        transaction: b64::encode(bincode::serialize(&txn).map_err(DecodeError::from)?),
+    })
+}
+
+/// Check that the transaction of the given offer only transfers the Hotspot
+/// to the buyer and pays the price to the seller, and sign it as the buyer.
+///
+/// Priority fees in the offer are paid by the buyer and are refused above the
+/// given maximum, in lamports.
+pub async fn accept<C: AsRef<SolanaRpcClient> + GetAsset>(
+    client: &C,
+    offer: &SaleOffer,
+    max_priority_fee: u64,
+    keypair: &Keypair,
+) -> Result<VersionedTransaction, Error> {
+    if keypair.pubkey() != offer.buyer {
+        return Err(invalid_offer("offer is for a different buyer"));
+    }
+    let kta = kta::for_entity_key(&offer.hotspot).await?;
+    if kta.asset != offer.asset {
+        return Err(invalid_offer("offer asset does not match the hotspot"));
+    }
+    let asset = asset::get(client, &offer.asset).await?;
+    if asset.ownership.owner != offer.seller {
+        return Err(HotspotError::NotOwned {
+            asset: asset.id,
+            owner: offer.seller,
+        }
+        .into());
+    }
+
+    let mut txn = offer.decode_transaction()?;
+    let account_keys = resolve_account_keys(client, &txn.message).await?;
+    let ixs = decompile(&txn.message, &account_keys)?;
+    check_offer(offer, &asset, &account_keys, &ixs, max_priority_fee)?;
+
+    let seller_index = signer_index(&txn.message, &offer.seller)?;
+    if !txn.signatures[seller_index].verify(offer.seller.as_ref(), &txn.message.serialize()) {
+        return Err(invalid_offer("offer is not signed by the seller"));
+    }
+    sign_for(&mut txn, keypair)?;
+    Ok(txn)
+}
+
+fn check_offer(
+    offer: &SaleOffer,
+    asset: &asset::Asset,
+    account_keys: &[Pubkey],
+    ixs: &[Instruction],
+    max_priority_fee: u64,
+) -> Result<(), Error> {
+    let refuse = invalid_offer;
+    if account_keys.first() != Some(&offer.buyer) {
+        return Err(refuse("the buyer does not pay the transaction fee"));
+    }
+
+    let mint = offer.token.mint();
+    let token_program = offer.token.token_program();
+    let buyer_account = offer.token.associated_token_adress(&offer.buyer);
+    let seller_account = offer.token.associated_token_adress(&offer.seller);
+    let mut transfers = 0;
+    let mut payments = 0;
+    let mut unit_limit: u64 = 0;
+    let mut unit_price: u64 = 0;
+    for ix in ixs {
+        let account = |index: usize| ix.accounts.get(index).map(|meta| meta.pubkey);
+        if ix.program_id == compute_budget::ID {
+            match ix.data.split_first() {
+                Some((2, limit)) if limit.len() == 4 => {
+                    unit_limit =
+                        u32::from_le_bytes(limit.try_into().map_err(DecodeError::from)?).into()
+                }
+                Some((3, price)) if price.len() == 8 => {
+                    unit_price = u64::from_le_bytes(price.try_into().map_err(DecodeError::from)?)
+                }
+                _ => return Err(refuse("unexpected compute budget instruction")),
+            }
+        } else if ix.program_id == mpl_bubblegum::ID {
+            let data = &ix.data;
+            let is_asset_transfer = data.len() == 8 + 32 * 3 + 8 + 4
+                && data[..8] == BUBBLEGUM_TRANSFER
+                && data[40..72] == asset.compression.data_hash
+                && data[104..112] == asset.compression.leaf_id.to_le_bytes()
+                && account(1) == Some(offer.seller)
+                && account(3) == Some(offer.buyer)
+                && account(4) == Some(asset.compression.tree);
+            if !is_asset_transfer {
+                return Err(refuse("unexpected asset instruction"));
+            }
+            transfers += 1;
+        } else if ix.program_id == spl_associated_token_account::ID {
+            if account(2) != Some(offer.seller) || account(3) != Some(*mint) {
+                return Err(refuse("unexpected token account creation"));
+            }
+        } else if ix.program_id == token_program {
+            let is_payment = ix.data.len() == 10
+                && ix.data[0] == TOKEN_TRANSFER_CHECKED
+                && ix.data[1..9] == offer.amount.to_le_bytes()
+                && account(0) == Some(buyer_account)
+                && account(1) == Some(*mint)
+                && account(2) == Some(seller_account)
+                && account(3) == Some(offer.buyer);
+            if !is_payment {
+                return Err(refuse("unexpected token instruction"));
+            }
+            payments += 1;
+        } else {
+            return Err(refuse(&format!("unexpected program {}", ix.program_id)));
+        }
+    }
+    if transfers != 1 || payments != 1 {
+        return Err(refuse("expected a single hotspot transfer and payment"));
+    }
+    let priority_fee = unit_limit.saturating_mul(unit_price) / 1_000_000;
+    if priority_fee > max_priority_fee {
+        return Err(refuse(&format!(
+            "priority fee of {priority_fee} lamports exceeds {max_priority_fee}"
+        )));
+    }
+    Ok(())
+}
+
+/// All account keys of a message, including the ones loaded from address
+/// lookup tables, in the order instructions refer to them
+async fn resolve_account_keys<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    msg: &VersionedMessage,
+) -> Result<Vec<Pubkey>, Error> {
+    let mut keys = msg.static_account_keys().to_vec();
+    let Some(lookups) = msg.address_table_lookups() else {
+        return Ok(keys);
+    };
+    let table_keys: Vec<Pubkey> = lookups.iter().map(|lookup| lookup.account_key).collect();
+    let tables = message::get_lut_accounts(client, &table_keys).await?;
+    let table = |key: &Pubkey| {
+        tables
+            .iter()
+            .find(|table| table.key == *key)
+            .ok_or_else(|| DecodeError::other(format!("missing lookup table {key}")))
+    };
+    let lookup_address = |addresses: &[Pubkey], index: &u8| {
+        addresses
+            .get(usize::from(*index))
+            .copied()
+            .ok_or_else(|| DecodeError::other("invalid lookup table index"))
+    };
+    for lookup in lookups {
+        let table = table(&lookup.account_key)?;
+        for index in &lookup.writable_indexes {
+            keys.push(lookup_address(&table.addresses, index)?);
+        }
+    }
+    for lookup in lookups {
+        let table = table(&lookup.account_key)?;
+        for index in &lookup.readonly_indexes {
+            keys.push(lookup_address(&table.addresses, index)?);
+        }
+    }
+    Ok(keys)
+}
+
+/// The instructions of a message with their program and account keys
+/// resolved. Signer and writable flags are not restored.
+fn decompile(msg: &VersionedMessage, account_keys: &[Pubkey]) -> Result<Vec<Instruction>, Error> {
+    let key = |index: u8| {
+        account_keys
+            .get(usize::from(index))
+            .copied()
+            .ok_or_else(|| DecodeError::other("invalid account index"))
+    };
+    msg.instructions()
+        .iter()
+        .map(|ix| {
+            let accounts = ix
+                .accounts
+                .iter()
+                .map(|index| Ok(AccountMeta::new_readonly(key(*index)?, false)))
+                .collect::<Result<Vec<_>, DecodeError>>()?;
+            Ok(Instruction {
+                program_id: key(ix.program_id_index)?,
+                accounts,
+                data: ix.data.clone(),
+            })
+        })
+        .collect()
+}
+
+fn signer_index(msg: &VersionedMessage, signer: &Pubkey) -> Result<usize, Error> {
+    msg.static_account_keys()
+        .iter()
+        .take(msg.header().num_required_signatures.into())
+        .position(|key| key == signer)
+        .ok_or_else(|| invalid_offer(&format!("{signer} is not a signer of the offer")))
+}
+
+fn invalid_offer(reason: &str) -> Error {
+    HotspotError::InvalidOffer(reason.to_string()).into()
+}
+
+/// Add the signature of the given keypair to a transaction with more signers
+fn sign_for(txn: &mut VersionedTransaction, keypair: &Keypair) -> Result<(), Error> {
+    let index = signer_index(&txn.message, &keypair.pubkey())?;
+    txn.signatures[index] = keypair.sign(&txn.message.serialize())?;
+    Ok(())
+}
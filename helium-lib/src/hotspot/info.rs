@@ -1,20 +1,22 @@
 use crate::{
     anchor_client::solana_client::{
-        rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_config::RpcTransactionConfig,
+        rpc_client::GetConfirmedSignaturesForAddress2Config,
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionConfig},
+        rpc_filter::{Memcmp, RpcFilterType},
     },
-    anchor_lang::{AnchorDeserialize, Discriminator},
+    anchor_lang::{AccountDeserialize, AnchorDeserialize, Discriminator},
     client::{GetAnchorAccount, SolanaRpcClient},
     dao::SubDao,
     entity_key::AsEntityKey,
     error::{DecodeError, Error},
     helium_entity_manager,
-    hotspot::{CommittedHotspotInfoUpdate, HotspotInfo, HotspotInfoUpdate},
+    hotspot::{CommittedHotspotInfoUpdate, HotspotError, HotspotInfo, HotspotInfoUpdate},
     keypair::Pubkey,
     solana_sdk::{commitment_config::CommitmentConfig, signature::Signature},
 };
 use chrono::DateTime;
 use futures::{
-    stream::{self, StreamExt, TryStreamExt},
+    stream::{self, Stream, StreamExt, TryStreamExt},
     TryFutureExt,
 };
 use helium_anchor_gen::helium_entity_manager::{
@@ -26,6 +28,7 @@ use helium_anchor_gen::helium_entity_manager::{
     OnboardMobileHotspotArgsV0, UpdateIotInfoArgsV0, UpdateMobileInfoArgsV0,
 };
 use serde::{Deserialize, Serialize};
+use solana_account_decoder::UiAccountEncoding;
 use solana_transaction_status::{
     EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiInstruction, UiMessage,
     UiParsedInstruction, UiTransactionEncoding,
@@ -103,6 +106,111 @@ pub async fn for_entity_key<C: GetAnchorAccount>(
         .await
 }
 
+/// Resolution of asserted Hotspot locations. Only locations at this
+/// resolution are found by [`in_cell_in_subdao`].
+pub const LOCATION_RESOLUTION: h3o::Resolution = h3o::Resolution::Twelve;
+
+/// Offset of the optional location in both the IOT and MOBILE info accounts,
+/// following the account discriminator, the asset and the bump seed
+const LOCATION_OFFSET: usize = 8 + 32 + 1;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HotspotInCell {
+    pub subdao: SubDao,
+    #[serde(with = "crate::keypair::serde_pubkey")]
+    pub info_key: Pubkey,
+    #[serde(with = "crate::keypair::serde_pubkey")]
+    pub asset: Pubkey,
+    pub info: HotspotInfo,
+}
+
+/// Get all Hotspots of a subdao asserted within the given cell. See
+/// [`crate::hotspot::in_cell`] to search all subdaos.
+///
+/// The resolution of the cell sets the size of the area searched. Info
+/// accounts are selected on-chain by the part of their location shared by all
+/// children of the cell, and the result is narrowed down to the exact cell
+/// locally. Searches with large cells return many accounts and may be refused
+/// by RPC providers that limit program account queries.
+pub async fn in_cell_in_subdao<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    subdao: SubDao,
+    cell: h3o::CellIndex,
+) -> Result<Vec<HotspotInCell>, Error> {
+    let Some(child) = cell.center_child(LOCATION_RESOLUTION) else {
+        return Err(HotspotError::InvalidResolution {
+            resolution: cell.resolution(),
+            cell: LOCATION_RESOLUTION,
+        }
+        .into());
+    };
+    let discriminator = match subdao {
+        SubDao::Iot => helium_entity_manager::IotHotspotInfoV0::DISCRIMINATOR,
+        SubDao::Mobile => helium_entity_manager::MobileHotspotInfoV0::DISCRIMINATOR,
+    };
+    let (prefix_offset, prefix) = location_prefix(child, cell.resolution());
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, discriminator.to_vec())),
+            // Some(location)
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(LOCATION_OFFSET, vec![1])),
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(prefix_offset, prefix)),
+        ]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let accounts = client
+        .as_ref()
+        .get_program_accounts_with_config(&helium_entity_manager::ID, config)
+        .await?;
+
+    let mut hotspots = vec![];
+    for (info_key, account) in accounts {
+        let data = &mut account.data.as_slice();
+        let (asset, info) = match subdao {
+            SubDao::Iot => {
+                let info = helium_entity_manager::IotHotspotInfoV0::try_deserialize(data)?;
+                (info.asset, HotspotInfo::from(info))
+            }
+            SubDao::Mobile => {
+                let info = helium_entity_manager::MobileHotspotInfoV0::try_deserialize(data)?;
+                (info.asset, HotspotInfo::from(info))
+            }
+        };
+        let in_cell = info
+            .location()
+            .is_some_and(|location| location.location.parent(cell.resolution()) == Some(cell));
+        if in_cell {
+            hotspots.push(HotspotInCell {
+                subdao,
+                info_key,
+                asset,
+                info,
+            });
+        }
+    }
+    Ok(hotspots)
+}
+
+/// The account offset and bytes of the high bits of a location index that
+/// all locations within the parent of the given resolution share.
+///
+/// H3 indices store the mode, resolution and base cell in their high bits,
+/// followed by three bits per resolution digit. Locations are stored little
+/// endian, so the shared high bytes are at the end of the location.
+fn location_prefix(location: h3o::CellIndex, resolution: h3o::Resolution) -> (usize, Vec<u8>) {
+    let shared_bits = 19 + 3 * u8::from(resolution) as usize;
+    let shared_bytes = shared_bits / 8;
+    let bytes = u64::from(location).to_le_bytes();
+    (
+        LOCATION_OFFSET + 1 + bytes.len() - shared_bytes,
+        bytes[bytes.len() - shared_bytes..].to_vec(),
+    )
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct HotspotInfoUpdateParams {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -129,6 +237,34 @@ pub async fn updates<C: AsRef<SolanaRpcClient>>(
     account: &Pubkey,
     params: HotspotInfoUpdateParams,
 ) -> Result<Vec<CommittedHotspotInfoUpdate>, Error> {
+    updates_stream(client, account, params)
+        .await?
+        .try_collect::<Vec<CommittedHotspotInfoUpdate>>()
+        .await
+}
+
+/// The most recent info update of the given account. Only the latest
+/// transaction of the account is looked at, so this is `None` both for
+/// accounts that were never updated and for accounts whose latest
+/// transaction is not an info update.
+pub async fn last_update<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    account: &Pubkey,
+) -> Result<Option<CommittedHotspotInfoUpdate>, Error> {
+    let params = HotspotInfoUpdateParams {
+        limit: Some(1),
+        ..Default::default()
+    };
+    Ok(updates(client, account, params).await?.into_iter().next())
+}
+
+/// Stream the info updates for the given account, newest first, as their
+/// transactions are fetched
+pub async fn updates_stream<'a, C: AsRef<SolanaRpcClient>>(
+    client: &'a C,
+    account: &Pubkey,
+    params: HotspotInfoUpdateParams,
+) -> Result<impl Stream<Item = Result<CommittedHotspotInfoUpdate, Error>> + 'a, Error> {
     let signatures = client
         .as_ref()
         .get_signatures_for_address_with_config(account, params.into())
@@ -143,7 +279,7 @@ pub async fn updates<C: AsRef<SolanaRpcClient>>(
                 .map_err(Error::from)
         });
     let updates = stream::iter(signature_iter)
-        .map_ok(|signature| async move {
+        .map_ok(move |signature| async move {
             client
                 .as_ref()
                 .get_transaction_with_config(
@@ -160,9 +296,7 @@ pub async fn updates<C: AsRef<SolanaRpcClient>>(
         .try_buffered(5)
         .try_filter_map(|txn| async move {
             CommittedHotspotInfoUpdate::from_transaction(txn).map_err(Error::from)
-        })
-        .try_collect::<Vec<CommittedHotspotInfoUpdate>>()
-        .await?;
+        });
 
     Ok(updates)
 }
@@ -287,3 +421,20 @@ impl HotspotInfoUpdate {
         .map_err(DecodeError::from)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location_prefix_shared_by_children() {
+        let location: h3o::CellIndex = "8c2a1072b59ffff".parse().expect("cell");
+        let parent = location.parent(h3o::Resolution::Eight).expect("parent");
+        let (offset, prefix) = location_prefix(location, parent.resolution());
+        assert_eq!(offset, LOCATION_OFFSET + 1 + 3);
+        assert_eq!(prefix.len(), 5);
+        for child in parent.children(LOCATION_RESOLUTION) {
+            assert_eq!(location_prefix(child, parent.resolution()).1, prefix);
+        }
+    }
+}
@@ -1,7 +1,10 @@
 use crate::{
     anchor_lang::{InstructionData, ToAccountMetas},
     anchor_spl, asset, bs58,
-    client::{DasClient, DasSearchAssetsParams, GetAnchorAccount, SolanaRpcClient},
+    client::{
+        DasClient, DasSearchAssetsParams, GetAnchorAccount, GetAsset, SolanaRpcClient,
+        DAS_MAX_PAGE_SIZE,
+    },
     dao::{Dao, SubDao},
     data_credits,
     error::{DecodeError, EncodeError, Error},
@@ -19,7 +22,7 @@ use crate::{
 };
 use angry_purple_tiger::AnimalName;
 use chrono::Utc;
-use futures::TryFutureExt;
+use futures::{stream, Stream, TryFutureExt};
 use itertools::{izip, Itertools};
 use rust_decimal::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -27,7 +30,26 @@ use std::{collections::HashMap, hash::Hash, str::FromStr};
 
 pub mod cert;
 pub mod dataonly;
+pub mod escrow;
 pub mod info;
+pub mod region;
+
+#[derive(Debug, thiserror::Error)]
+pub enum HotspotError {
+    #[error("hotspot {asset} not owned by {owner}")]
+    NotOwned { asset: Pubkey, owner: Pubkey },
+    #[error("invalid entity key string")]
+    InvalidEntityKey,
+    #[error("resolution {resolution} is finer than the cell resolution {cell}")]
+    InvalidResolution {
+        resolution: h3o::Resolution,
+        cell: h3o::Resolution,
+    },
+    #[error("hotspots can not be sold for {0}")]
+    UnsupportedPrice(Token),
+    #[error("invalid offer: {0}")]
+    InvalidOffer(String),
+}
 
 pub const HOTSPOT_CREATOR: Pubkey = pubkey!("Fv5hf1Fg58htfC7YEXKNEfkpuogUUQDDTLgjGWxxv48H");
 pub const ECC_VERIFIER: Pubkey = pubkey!("eccSAJM3tq7nQSpQTm8roxv4FPoipCkMsGizW2KBhqZ");
@@ -38,7 +60,7 @@ pub fn entity_key_from_kta(
     let key_str = match kta.key_serialization {
         helium_entity_manager::KeySerialization::B58 => bs58::encode(&kta.entity_key).into_string(),
         helium_entity_manager::KeySerialization::UTF8 => String::from_utf8(kta.entity_key.to_vec())
-            .map_err(|_| DecodeError::other("invalid entity key string"))?,
+            .map_err(|_| HotspotError::InvalidEntityKey)?,
     };
     Ok(helium_crypto::PublicKey::from_str(&key_str)?)
 }
@@ -64,6 +86,86 @@ pub async fn for_owner<C: AsRef<DasClient>>(
         .try_collect()
 }
 
+/// Stream the Hotspots of the given owner one page of assets at a time, so
+/// large holdings can be processed as they are fetched.
+pub fn for_owner_pages<'a, C: AsRef<DasClient>>(
+    client: &'a C,
+    owner: &Pubkey,
+) -> impl Stream<Item = Result<Vec<Hotspot>, Error>> + 'a {
+    let mut params = DasSearchAssetsParams::for_owner(*owner, HOTSPOT_CREATOR);
+    // Searches beyond the DAS page size are split by the client
+    params.limit = DAS_MAX_PAGE_SIZE;
+    stream::try_unfold(Some(params), move |params| async move {
+        let Some(mut params) = params else {
+            return Ok(None);
+        };
+        let page = search(client, params.clone()).await?;
+        // The page total counts assets before non-Hotspot assets are dropped
+        let next = (page.total >= params.limit).then(|| {
+            params.page += 1;
+            params
+        });
+        Ok(Some((page.items, next)))
+    })
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim().to_lowercase().replace([' ', '_'], "-")
+}
+
+/// Find the Hotspots with the given animal name, like "tall-cinnamon-dolphin".
+///
+/// Names are not guaranteed to be unique, so all matching Hotspots are
+/// returned. When no owner is given all Hotspots in the network are searched,
+/// which requires paging through every Hotspot asset and is slow. The network
+/// search stops after `max_pages` pages of assets when given.
+pub async fn for_name<C: AsRef<DasClient>>(
+    client: &C,
+    name: &str,
+    owner: Option<&Pubkey>,
+    max_pages: Option<u32>,
+) -> Result<Vec<Hotspot>, Error> {
+    let name = normalize_name(name);
+    if let Some(owner) = owner {
+        let hotspots = for_owner(client, owner)
+            .await?
+            .into_iter()
+            .filter(|hotspot| normalize_name(&hotspot.name) == name)
+            .collect();
+        return Ok(hotspots);
+    }
+
+    let mut params = DasSearchAssetsParams {
+        creator_address: Some(HOTSPOT_CREATOR),
+        creator_verified: true,
+        page: 1,
+        limit: DAS_MAX_PAGE_SIZE,
+        ..Default::default()
+    };
+    let mut assets = vec![];
+    loop {
+        let page = asset::search(client, params.clone()).await?;
+        let fetch_count = page.items.len();
+        assets.extend(page.items.into_iter().filter(|asset| {
+            asset.is_symbol("HOTSPOT") && normalize_name(&asset.content.metadata.name) == name
+        }));
+        if fetch_count < params.limit as usize
+            || max_pages.is_some_and(|max_pages| params.page >= max_pages)
+        {
+            break;
+        }
+        params.page += 1;
+    }
+    let mut hotspots = vec![];
+    for asset in assets {
+        hotspots.push(Hotspot::from_asset(asset).await?);
+    }
+    // Asset metadata names are informational, so confirm against the name
+    // derived from the entity key
+    hotspots.retain(|hotspot| normalize_name(&hotspot.name) == name);
+    Ok(hotspots)
+}
+
 pub async fn search<C: AsRef<DasClient>>(
     client: &C,
     params: DasSearchAssetsParams,
@@ -85,7 +187,33 @@ pub fn name(hotspot_key: &helium_crypto::PublicKey) -> String {
         .to_string()
 }
 
-pub async fn get<C: AsRef<DasClient>>(
+/// Get all Hotspots, in any subdao, asserted within the parent of the given
+/// H3 cell at the given resolution.
+///
+/// The resolution sets the size of the area searched and can be at most the
+/// resolution of the cell. Searches with coarse resolutions return many
+/// accounts and may be refused by RPC providers that limit program account
+/// queries.
+pub async fn in_cell<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    h3_cell: h3o::CellIndex,
+    resolution: h3o::Resolution,
+) -> Result<Vec<info::HotspotInCell>, Error> {
+    let Some(cell) = h3_cell.parent(resolution) else {
+        return Err(HotspotError::InvalidResolution {
+            resolution,
+            cell: h3_cell.resolution(),
+        }
+        .into());
+    };
+    let mut hotspots = vec![];
+    for subdao in SubDao::all() {
+        hotspots.extend(info::in_cell_in_subdao(client, subdao, cell).await?);
+    }
+    Ok(hotspots)
+}
+
+pub async fn get<C: GetAsset>(
     client: &C,
     hotspot_key: &helium_crypto::PublicKey,
 ) -> Result<Hotspot, Error> {
@@ -94,7 +222,7 @@ pub async fn get<C: AsRef<DasClient>>(
     Hotspot::from_asset(asset).await
 }
 
-pub async fn get_with_info<C: AsRef<DasClient> + GetAnchorAccount>(
+pub async fn get_with_info<C: GetAsset + GetAnchorAccount>(
     client: &C,
     subdaos: &[SubDao],
     hotspot_key: &helium_crypto::PublicKey,
@@ -109,13 +237,15 @@ pub async fn get_with_info<C: AsRef<DasClient> + GetAnchorAccount>(
     Ok(hotspot)
 }
 
-pub async fn direct_update_message<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
-    client: &C,
-    hotspot: &helium_crypto::PublicKey,
-    update: HotspotInfoUpdate,
+/// Construct the instruction applying the given info update to the Hotspot
+/// with the given key to asset account, compressed asset and asset proof.
+pub fn update_instruction(
+    kta: &helium_entity_manager::KeyToAssetV0,
+    asset: &asset::Asset,
+    asset_proof: &asset::AssetProof,
+    update: &HotspotInfoUpdate,
     owner: &Pubkey,
-    opts: &TransactionOpts,
-) -> Result<(message::VersionedMessage, u64), Error> {
+) -> Result<Instruction, Error> {
     fn mk_accounts(
         subdao: SubDao,
         kta: &helium_entity_manager::KeyToAssetV0,
@@ -154,9 +284,6 @@ pub async fn direct_update_message<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>
         }
     }
 
-    let kta = kta::for_entity_key(hotspot).await?;
-    let (asset, asset_proof) = asset::for_kta_with_proof(&client, &kta).await?;
-
     macro_rules! mk_update_data {
         ($ix_struct:ident, $arg_struct:ident, $($manual_fields:tt)*) => {
             $ix_struct {
@@ -172,7 +299,7 @@ pub async fn direct_update_message<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>
         };
     }
 
-    let mut accounts = mk_accounts(update.subdao(), &kta, &asset, owner);
+    let mut accounts = mk_accounts(update.subdao(), kta, asset, owner);
     accounts.extend_from_slice(&asset_proof.proof(Some(3))?);
 
     use helium_entity_manager::{
@@ -195,23 +322,39 @@ pub async fn direct_update_message<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>
             )
         }
     };
-    let ix = Instruction {
+    Ok(Instruction {
         program_id: helium_entity_manager::id(),
-        accounts: accounts.to_account_metas(None),
+        accounts,
         data,
-    };
+    })
+}
+
+pub async fn direct_update_message<C: AsRef<SolanaRpcClient> + GetAsset>(
+    client: &C,
+    hotspot: &helium_crypto::PublicKey,
+    update: HotspotInfoUpdate,
+    owner: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<(message::VersionedMessage, u64), Error> {
+    let kta = kta::for_entity_key(hotspot).await?;
+    let (asset, asset_proof) = asset::for_kta_with_proof(&client, &kta).await?;
+    let ix = update_instruction(&kta, &asset, &asset_proof, &update, owner)?;
 
     let ixs = &[
         priority_fee::compute_budget_instruction(200_000),
-        priority_fee::compute_price_instruction_for_accounts(client, &accounts, opts.fee_range())
-            .await?,
+        priority_fee::compute_price_instruction_for_accounts(
+            client,
+            &ix.accounts,
+            opts.fee_range(),
+        )
+        .await?,
         ix,
     ];
 
     message::mk_message(client, ixs, &opts.lut_addresses, owner).await
 }
 
-pub async fn direct_update<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
+pub async fn direct_update<C: AsRef<SolanaRpcClient> + GetAsset>(
     client: &C,
     hotspot: &helium_crypto::PublicKey,
     update: HotspotInfoUpdate,
@@ -224,7 +367,7 @@ pub async fn direct_update<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
     Ok((txn, block_height))
 }
 
-pub async fn update<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
+pub async fn update<C: AsRef<SolanaRpcClient> + GetAsset>(
     client: &C,
     onboarding_server: Option<String>,
     hotspot: &helium_crypto::PublicKey,
@@ -250,7 +393,7 @@ pub async fn update<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
 /// The Hotspot is transferred from the owner of the Hotspot to the given recipient
 /// Note that the owner is currently expected to sign this transaction and pay for
 /// transaction fees.
-pub async fn transfer_message<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
+pub async fn transfer_message<C: AsRef<SolanaRpcClient> + GetAsset>(
     client: &C,
     hotspot_key: &helium_crypto::PublicKey,
     recipient: &Pubkey,
@@ -260,7 +403,7 @@ pub async fn transfer_message<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
     asset::transfer_message(client, &kta.asset, recipient, opts).await
 }
 
-pub async fn transfer<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
+pub async fn transfer<C: AsRef<SolanaRpcClient> + GetAsset>(
     client: &C,
     hotspot_key: &helium_crypto::PublicKey,
     recipient: &Pubkey,
@@ -271,7 +414,7 @@ pub async fn transfer<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
     asset::transfer(client, &kta.asset, recipient, keypair, opts).await
 }
 
-pub async fn burn_message<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
+pub async fn burn_message<C: AsRef<SolanaRpcClient> + GetAsset>(
     client: &C,
     hotspot_key: &helium_crypto::PublicKey,
     opts: &TransactionOpts,
@@ -280,7 +423,7 @@ pub async fn burn_message<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
     asset::burn_message(client, &kta.asset, opts).await
 }
 
-pub async fn burn<C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
+pub async fn burn<C: AsRef<SolanaRpcClient> + GetAsset>(
     client: &C,
     hotspot_key: &helium_crypto::PublicKey,
     keypair: &Keypair,
@@ -676,6 +819,21 @@ impl HotspotInfoUpdate {
         }
         self
     }
+
+    /// Whether every value set in this update already matches the given
+    /// Hotspot info, meaning the update has no effect
+    pub fn is_applied(&self, info: &HotspotInfo) -> bool {
+        fn matches<T: PartialEq>(update: &Option<T>, current: &Option<T>) -> bool {
+            update.is_none() || update == current
+        }
+        self.subdao() == info.subdao()
+            && matches(
+                &self.location().as_ref().map(|location| location.location),
+                &info.location().as_ref().map(|location| location.location),
+            )
+            && matches(self.gain(), info.gain())
+            && matches(self.elevation(), info.elevation())
+    }
 }
 
 #[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Default, Hash, Deserialize)]
@@ -731,6 +889,13 @@ impl HotspotInfo {
         value.map(Into::into)
     }
 
+    pub fn subdao(&self) -> SubDao {
+        match self {
+            Self::Iot { .. } => SubDao::Iot,
+            Self::Mobile { .. } => SubDao::Mobile,
+        }
+    }
+
     pub fn location(&self) -> &Option<HotspotLocation> {
         match self {
             Self::Iot { location, .. } => location,
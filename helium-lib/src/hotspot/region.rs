@@ -0,0 +1,153 @@
+use crate::{
+    client::{config::iot, GetAnchorAccount},
+    dao::SubDao,
+    error::{DecodeError, Error},
+    helium_entity_manager,
+};
+use helium_proto::{BlockchainRegionParamsV1, Region};
+use rust_decimal::prelude::*;
+use serde::Serialize;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GainError {
+    #[error("gain {gain} dBi outside of allowed range {min} to {max} dBi")]
+    OutOfRange {
+        gain: Decimal,
+        min: Decimal,
+        max: Decimal,
+    },
+    #[error("gain {gain} dBi reaches the maximum EIRP of {max_eirp} dBm for region {region}")]
+    ExceedsMaxEirp {
+        gain: Decimal,
+        max_eirp: Decimal,
+        region: String,
+    },
+}
+
+/// The antenna gain range in dBi accepted for IoT Hotspots
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct GainLimits {
+    pub min: Decimal,
+    pub max: Decimal,
+}
+
+/// Parse a region by its name, like "US915" or "eu868"
+pub fn region_from_str(s: &str) -> Result<Region, DecodeError> {
+    Region::from_str_name(&s.to_uppercase())
+        .ok_or_else(|| DecodeError::other(format!("invalid region: {s}")))
+}
+
+/// The maximum EIRP in dBm a Hotspot is allowed to transmit at in the given
+/// region, as served by the IoT config service to gateways, which derive
+/// their transmit power from the maximum EIRP and the asserted antenna gain.
+///
+/// Returns None if the config service has no parameters for the region.
+pub async fn max_eirp(config: &mut iot::Client, region: Region) -> Result<Option<Decimal>, Error> {
+    Ok(config
+        .region_params(region)
+        .await?
+        .as_ref()
+        .and_then(max_eirp_from_params))
+}
+
+/// The highest maximum EIRP in dBm of the channels in the given region
+/// parameters. The parameters carry it in tenths of a dBm.
+pub fn max_eirp_from_params(params: &BlockchainRegionParamsV1) -> Option<Decimal> {
+    params
+        .region_params
+        .iter()
+        .map(|param| param.max_eirp)
+        .max()
+        .map(|deci_dbm| Decimal::new(deci_dbm.into(), 1))
+}
+
+/// Get the antenna gain range accepted for IoT Hotspots by the IoT rewardable
+/// entity configuration
+pub async fn gain_limits<C: GetAnchorAccount>(client: &C) -> Result<GainLimits, Error> {
+    let config: helium_entity_manager::RewardableEntityConfigV0 = client
+        .anchor_account(&SubDao::Iot.rewardable_entity_config_key())
+        .await?;
+    match config.settings {
+        helium_entity_manager::ConfigSettingsV0::IotConfig {
+            min_gain, max_gain, ..
+        } => Ok(GainLimits {
+            min: Decimal::new(min_gain.into(), 1),
+            max: Decimal::new(max_gain.into(), 1),
+        }),
+        _ => Err(DecodeError::other("invalid iot rewardable entity config").into()),
+    }
+}
+
+/// Check the given antenna gain against the accepted gain range and, if
+/// given, the maximum EIRP in dBm of a region.
+pub fn check_gain(
+    gain: Decimal,
+    limits: &GainLimits,
+    max_eirp: Option<(Region, Decimal)>,
+) -> Result<(), GainError> {
+    if gain < limits.min || gain > limits.max {
+        return Err(GainError::OutOfRange {
+            gain,
+            min: limits.min,
+            max: limits.max,
+        });
+    }
+    if let Some((region, max_eirp)) = max_eirp {
+        if gain >= max_eirp {
+            return Err(GainError::ExceedsMaxEirp {
+                gain,
+                max_eirp,
+                region: region.as_str_name().to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helium_proto::BlockchainRegionParamV1;
+
+    const LIMITS: GainLimits = GainLimits {
+        min: Decimal::from_parts(10, 0, 0, false, 1),
+        max: Decimal::from_parts(150, 0, 0, false, 1),
+    };
+
+    #[test]
+    fn check_gain_range() {
+        assert!(check_gain(Decimal::new(12, 1), &LIMITS, None).is_ok());
+        assert!(matches!(
+            check_gain(Decimal::new(5, 1), &LIMITS, None),
+            Err(GainError::OutOfRange { .. })
+        ));
+        assert!(matches!(
+            check_gain(Decimal::new(151, 1), &LIMITS, None),
+            Err(GainError::OutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn check_gain_region() {
+        let region = region_from_str("eu433").expect("region");
+        let max_eirp = Some((region, Decimal::new(121, 1)));
+        assert!(check_gain(Decimal::new(80, 1), &LIMITS, max_eirp).is_ok());
+        assert!(matches!(
+            check_gain(Decimal::new(130, 1), &LIMITS, max_eirp),
+            Err(GainError::ExceedsMaxEirp { .. })
+        ));
+    }
+
+    #[test]
+    fn max_eirp_params() {
+        let param = |max_eirp| BlockchainRegionParamV1 {
+            max_eirp,
+            ..Default::default()
+        };
+        let params = BlockchainRegionParamsV1 {
+            region_params: vec![param(160), param(360), param(300)],
+        };
+        assert_eq!(Some(Decimal::new(360, 1)), max_eirp_from_params(&params));
+        assert_eq!(None, max_eirp_from_params(&Default::default()));
+    }
+}
@@ -5,9 +5,13 @@ use crate::{
 };
 use futures::{stream, StreamExt, TryFutureExt, TryStreamExt};
 use itertools::Itertools;
+use serde::Serialize;
 use std::{
     collections::HashMap,
-    sync::{Arc, OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
 };
 
 pub fn init(solana_client: Arc<SolanaRpcClient>) -> Result<(), Error> {
@@ -25,6 +29,13 @@ pub async fn get_many(kta_keys: &[Pubkey]) -> Result<Vec<KeyToAssetV0>, Error> {
     cache.get_many(kta_keys).await
 }
 
+/// Get the key to asset accounts for the given keys, with `None` for keys
+/// that have no key to asset account
+pub async fn try_get_many(kta_keys: &[Pubkey]) -> Result<Vec<Option<KeyToAssetV0>>, Error> {
+    let cache = CACHE.get().ok_or_else(Error::account_not_found)?;
+    cache.try_get_many(kta_keys).await
+}
+
 pub async fn for_entity_key<E>(entity_key: &E) -> Result<KeyToAssetV0, Error>
 where
     E: AsEntityKey,
@@ -44,12 +55,57 @@ where
     get_many(&kta_keys).await
 }
 
+/// Resolve the asset ids for the given entity keys, in the order given.
+///
+/// Key to asset accounts are fetched in chunks, so this is suitable for
+/// resolving large numbers of keys. Entity keys without an asset resolve to
+/// `None` rather than failing the whole batch.
+pub async fn asset_ids_for_entity_keys<E>(entity_keys: &[E]) -> Result<Vec<Option<Pubkey>>, Error>
+where
+    E: AsEntityKey,
+{
+    let kta_keys = entity_keys
+        .iter()
+        .map(|entity_key| Dao::Hnt.entity_key_to_kta_key(entity_key))
+        .collect::<Vec<Pubkey>>();
+    let ktas = try_get_many(&kta_keys).await?;
+    Ok(ktas
+        .into_iter()
+        .map(|kta| kta.map(|kta| kta.asset))
+        .collect())
+}
+
+/// Size and hit rate of the key to asset cache
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    /// Approximate memory used by the cached accounts, in bytes
+    pub bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Fraction of lookups served from the cache, if there were any
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+}
+
+/// Statistics of the key to asset cache of this process
+pub fn stats() -> CacheStats {
+    CACHE.get().map(KtaCache::stats).unwrap_or_default()
+}
+
 static CACHE: OnceLock<KtaCache> = OnceLock::new();
 
 type KtaCacheMap = HashMap<Pubkey, KeyToAssetV0>;
 struct KtaCache {
     solana_client: Arc<SolanaRpcClient>,
     cache: RwLock<KtaCacheMap>,
+    hits: AtomicU64,
+    misses: AtomicU64,
 }
 
 impl KtaCache {
@@ -58,9 +114,29 @@ impl KtaCache {
         Ok(Self {
             solana_client,
             cache,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
         })
     }
 
+    fn stats(&self) -> CacheStats {
+        let cache = self.cache_read();
+        let bytes = cache
+            .values()
+            .map(|kta| {
+                std::mem::size_of::<Pubkey>()
+                    + std::mem::size_of::<KeyToAssetV0>()
+                    + kta.entity_key.capacity()
+            })
+            .sum();
+        CacheStats {
+            entries: cache.len(),
+            bytes,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
     fn cache_read(&self) -> RwLockReadGuard<'_, KtaCacheMap> {
         self.cache.read().expect("cache read lock poisoned")
     }
@@ -71,8 +147,10 @@ impl KtaCache {
 
     async fn get(&self, kta_key: &Pubkey) -> Result<KeyToAssetV0, Error> {
         if let Some(account) = self.cache_read().get(kta_key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
             return Ok(account.clone());
         }
+        self.misses.fetch_add(1, Ordering::Relaxed);
 
         let kta = self
             .solana_client
@@ -89,17 +167,24 @@ impl KtaCache {
         Ok(kta)
     }
 
-    async fn get_many(&self, kta_keys: &[Pubkey]) -> Result<Vec<KeyToAssetV0>, Error> {
+    async fn try_get_many(&self, kta_keys: &[Pubkey]) -> Result<Vec<Option<KeyToAssetV0>>, Error> {
         let missing_keys: Vec<Pubkey> = {
             let cache = self.cache_read();
             kta_keys
                 .iter()
                 .filter(|key| !cache.contains_key(key))
+                .unique()
                 .copied()
                 .collect()
         };
+        let missing = missing_keys.len() as u64;
+        self.misses.fetch_add(missing, Ordering::Relaxed);
+        self.hits.fetch_add(
+            (kta_keys.len() as u64).saturating_sub(missing),
+            Ordering::Relaxed,
+        );
 
-        let mut missing_accounts = stream::iter(missing_keys.clone())
+        let missing_accounts = stream::iter(missing_keys.clone())
             // Chunk into documented max keys to pass to getMultipleAccounts
             .chunks(100)
             .map(|key_chunk| async move {
@@ -115,28 +200,23 @@ impl KtaCache {
             .collect_vec();
         {
             let mut cache = self.cache_write();
-            missing_keys
-                .into_iter()
-                .zip(missing_accounts.iter_mut())
-                .map(|(key, maybe_account)| {
-                    let Some(account) = maybe_account.as_mut() else {
-                        return Err(Error::account_not_found());
-                    };
-                    KeyToAssetV0::try_deserialize(&mut account.data.as_ref())
-                        .map_err(Error::from)
-                        .map(|kta| (key, kta))
-                })
-                .map_ok(|(key, kta)| {
-                    cache.insert(key, kta);
-                })
-                .try_collect::<_, (), _>()?;
-        }
-        {
-            let cache = self.cache_read();
-            kta_keys
-                .iter()
-                .map(|key| cache.get(key).cloned().ok_or(Error::account_not_found()))
-                .try_collect()
+            for (key, maybe_account) in missing_keys.into_iter().zip(missing_accounts) {
+                let Some(account) = maybe_account else {
+                    continue;
+                };
+                let kta = KeyToAssetV0::try_deserialize(&mut account.data.as_ref())?;
+                cache.insert(key, kta);
+            }
         }
+        let cache = self.cache_read();
+        Ok(kta_keys.iter().map(|key| cache.get(key).cloned()).collect())
+    }
+
+    async fn get_many(&self, kta_keys: &[Pubkey]) -> Result<Vec<KeyToAssetV0>, Error> {
+        self.try_get_many(kta_keys)
+            .await?
+            .into_iter()
+            .map(|kta| kta.ok_or_else(Error::account_not_found))
+            .try_collect()
     }
 }
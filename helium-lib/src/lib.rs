@@ -3,6 +3,7 @@ pub mod b64;
 pub mod client;
 
 pub mod boosting;
+pub mod confirm;
 pub mod dao;
 pub mod dc;
 pub mod entity_key;
@@ -12,11 +13,23 @@ pub mod keypair;
 pub mod kta;
 pub mod memo;
 pub mod message;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod mock;
+pub mod multisig;
+pub mod network;
 pub mod onboarding;
+pub mod payer;
+pub mod pda;
+pub mod preflight;
 pub mod priority_fee;
+pub mod program_error;
 pub mod programs;
 pub mod reward;
+pub mod simulation;
+pub mod swap;
+pub mod test_vectors;
 pub mod token;
+pub mod transaction;
 
 pub use anchor_client;
 pub use anchor_client::solana_client;
@@ -68,6 +81,9 @@ pub fn init(solana_client: Arc<client::SolanaRpcClient>) -> Result<(), error::Er
 pub struct TransactionOpts {
     pub min_priority_fee: u64,
     pub max_priority_fee: u64,
+    /// Compute unit price in micro lamports to use as is. Skips priority fee
+    /// estimation when set.
+    pub exact_priority_fee: Option<u64>,
     pub lut_addresses: Vec<Pubkey>,
 }
 
@@ -76,6 +92,7 @@ impl Default for TransactionOpts {
         Self {
             min_priority_fee: priority_fee::MIN_PRIORITY_FEE,
             max_priority_fee: priority_fee::MAX_PRIORITY_FEE,
+            exact_priority_fee: None,
             lut_addresses: vec![message::COMMON_LUT],
         }
     }
@@ -83,6 +100,9 @@ impl Default for TransactionOpts {
 
 impl TransactionOpts {
     fn fee_range(&self) -> RangeInclusive<u64> {
+        if let Some(fee) = self.exact_priority_fee {
+            return RangeInclusive::new(fee, fee);
+        }
         RangeInclusive::new(self.min_priority_fee, self.max_priority_fee)
     }
 }
@@ -100,3 +120,44 @@ pub async fn mk_transaction_with_blockhash<C: AsRef<SolanaRpcClient>>(
     txn.message.recent_blockhash = latest_blockhash;
     Ok((txn, latest_block_height))
 }
+
+/// Build a transaction on the current value of the given durable nonce
+/// account instead of a recent blockhash. The transaction advances the nonce
+/// as its first instruction, signed by the payer which has to be the
+/// authority of the nonce account, and stays valid until the nonce is
+/// advanced.
+pub async fn mk_transaction_with_nonce<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    ixs: &[Instruction],
+    payer: &Pubkey,
+    nonce_account: &Pubkey,
+) -> Result<Transaction, Error> {
+    use solana_sdk::{
+        nonce::state::{State, Versions},
+        system_instruction,
+    };
+    let solana_client = AsRef::<SolanaRpcClient>::as_ref(client);
+    let account = solana_client.get_account(nonce_account).await?;
+    let versions: Versions =
+        bincode::deserialize(&account.data).map_err(error::DecodeError::from)?;
+    let State::Initialized(data) = versions.state() else {
+        return Err(error::DecodeError::other(format!(
+            "nonce account {nonce_account} is not initialized"
+        ))
+        .into());
+    };
+    if data.authority != *payer {
+        return Err(error::DecodeError::other(format!(
+            "{payer} is not the authority of nonce account {nonce_account}"
+        ))
+        .into());
+    }
+    let mut nonce_ixs = vec![system_instruction::advance_nonce_account(
+        nonce_account,
+        payer,
+    )];
+    nonce_ixs.extend_from_slice(ixs);
+    let mut txn = Transaction::new_with_payer(&nonce_ixs, Some(payer));
+    txn.message.recent_blockhash = data.blockhash();
+    Ok(txn)
+}
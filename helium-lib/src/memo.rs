@@ -3,17 +3,22 @@ use crate::{
     error::Error,
     keypair::{Keypair, Pubkey},
     message, priority_fee,
-    solana_sdk::{signer::Signer, transaction::VersionedTransaction},
+    solana_sdk::{instruction::Instruction, signer::Signer, transaction::VersionedTransaction},
     TransactionOpts,
 };
 
+/// Build a memo instruction signed by the given key
+pub fn memo_instruction(data: &str, pubkey: &Pubkey) -> Instruction {
+    spl_memo::build_memo(data.as_bytes(), &[pubkey])
+}
+
 pub async fn memo_message<C: AsRef<SolanaRpcClient>>(
     client: &C,
     data: &str,
     pubkey: &Pubkey,
     opts: &TransactionOpts,
 ) -> Result<(message::VersionedMessage, u64), Error> {
-    let ix = spl_memo::build_memo(data.as_bytes(), &[pubkey]);
+    let ix = memo_instruction(data, pubkey);
     let ixs = &[
         priority_fee::compute_budget_instruction(200_000),
         priority_fee::compute_price_instruction_for_accounts(
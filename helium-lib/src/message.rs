@@ -1,12 +1,20 @@
 use crate::{
     client::SolanaRpcClient,
     keypair::pubkey,
+    priority_fee::{
+        compute_budget_instruction, compute_price_instruction,
+        compute_price_instruction_for_accounts,
+    },
     solana_sdk::{
         address_lookup_table::{state::AddressLookupTable, AddressLookupTableAccount},
-        instruction::Instruction,
+        hash::Hash,
+        instruction::{AccountMeta, Instruction},
         message::v0,
+        packet::PACKET_DATA_SIZE,
+        signature::Signature,
+        transaction::VersionedTransaction,
     },
-    Error, Pubkey,
+    Error, Pubkey, TransactionOpts,
 };
 use itertools::Itertools;
 
@@ -55,3 +63,159 @@ pub async fn mk_message<C: AsRef<SolanaRpcClient>>(
     )?);
     Ok((msg, recent_blockheight))
 }
+
+/// Serialized size in bytes of a signed transaction with the given
+/// instructions, or `None` if they can not be compiled into a message
+pub fn size(
+    ixs: &[Instruction],
+    lut_accounts: &[AddressLookupTableAccount],
+    payer: &Pubkey,
+) -> Option<u64> {
+    let msg = v0::Message::try_compile(payer, ixs, lut_accounts, Hash::default()).ok()?;
+    let msg = VersionedMessage::V0(msg);
+    let txn = VersionedTransaction {
+        signatures: vec![Signature::default(); msg.header().num_required_signatures.into()],
+        message: msg,
+    };
+    bincode::serialized_size(&txn).ok()
+}
+
+/// Whether a transaction with the given instructions, signed by the payer
+/// only, fits in a single packet
+pub fn fits(
+    ixs: &[Instruction],
+    lut_accounts: &[AddressLookupTableAccount],
+    payer: &Pubkey,
+) -> bool {
+    size(ixs, lut_accounts, payer).is_some_and(|size| size <= PACKET_DATA_SIZE as u64)
+}
+
+/// Items packed into a single transaction, see [`pack`]
+#[derive(Debug, Clone, Default)]
+pub struct Batch {
+    instructions: Vec<Instruction>,
+    budget: u32,
+    /// Indices of the items in the batch
+    pub items: Vec<usize>,
+}
+
+impl Batch {
+    /// Build the transaction message for the batch, with a fresh blockhash
+    pub async fn message<C: AsRef<SolanaRpcClient>>(
+        &self,
+        client: &C,
+        payer: &Pubkey,
+        opts: &TransactionOpts,
+    ) -> Result<(VersionedMessage, u64), Error> {
+        let accounts: Vec<AccountMeta> = self
+            .instructions
+            .iter()
+            .flat_map(|ix| ix.accounts.clone())
+            .collect();
+        let mut ixs = vec![
+            compute_budget_instruction(self.budget),
+            compute_price_instruction_for_accounts(client, &accounts, opts.fee_range()).await?,
+        ];
+        ixs.extend_from_slice(&self.instructions);
+        mk_message(client, &ixs, &opts.lut_addresses, payer).await
+    }
+
+    /// Whether the instructions of the given item still fit in the batch
+    fn fits(
+        &self,
+        ixs: &[Instruction],
+        budget: u32,
+        lut_accounts: &[AddressLookupTableAccount],
+        payer: &Pubkey,
+    ) -> bool {
+        // The price instruction is sized like any other, so a default one
+        // stands in for it while packing
+        let mut candidate = vec![
+            compute_budget_instruction(self.budget.saturating_add(budget)),
+            compute_price_instruction(0),
+        ];
+        candidate.extend_from_slice(&self.instructions);
+        candidate.extend_from_slice(ixs);
+        fits(&candidate, lut_accounts, payer)
+    }
+}
+
+/// Pack items, each the instructions and compute budget of one operation,
+/// in order into as few transactions paid by the payer as possible. The
+/// instructions of an item always go into the same transaction.
+pub fn pack<I>(items: I, lut_accounts: &[AddressLookupTableAccount], payer: &Pubkey) -> Vec<Batch>
+where
+    I: IntoIterator<Item = (Vec<Instruction>, u32)>,
+{
+    let mut batches = vec![];
+    let mut batch = Batch::default();
+    for (index, (ixs, budget)) in items.into_iter().enumerate() {
+        if !batch.items.is_empty() && !batch.fits(&ixs, budget, lut_accounts, payer) {
+            batches.push(std::mem::take(&mut batch));
+        }
+        batch.instructions.extend(ixs);
+        batch.budget = batch.budget.saturating_add(budget);
+        batch.items.push(index);
+    }
+    if !batch.items.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::{Payment, Token};
+
+    #[test]
+    fn fits_packet() {
+        let payer = Pubkey::new_unique();
+        let ixs = |count: usize| -> Vec<Instruction> {
+            (0..count)
+                .flat_map(|_| {
+                    Payment {
+                        payee: Pubkey::new_unique(),
+                        amount: Token::Hnt.amount(1),
+                        memo: None,
+                    }
+                    .instructions(&[], &payer)
+                    .expect("instructions")
+                })
+                .collect()
+        };
+        assert!(fits(&ixs(1), &[], &payer));
+        assert!(!fits(&ixs(40), &[], &payer));
+    }
+
+    #[test]
+    fn pack_items() {
+        let payer = Pubkey::new_unique();
+        let payment = || {
+            Payment {
+                payee: Pubkey::new_unique(),
+                amount: Token::Hnt.amount(1),
+                memo: None,
+            }
+            .instructions(&[], &payer)
+            .expect("instructions")
+        };
+        let batches = pack((0..40).map(|_| (payment(), 1_000)), &[], &payer);
+        assert!(batches.len() > 1);
+        let items: Vec<usize> = batches
+            .iter()
+            .flat_map(|batch| batch.items.clone())
+            .collect();
+        assert_eq!(items, (0..40).collect::<Vec<_>>());
+        for batch in &batches {
+            assert_eq!(batch.budget, 1_000 * batch.items.len() as u32);
+            let mut ixs = vec![
+                compute_budget_instruction(batch.budget),
+                compute_price_instruction(0),
+            ];
+            ixs.extend_from_slice(&batch.instructions);
+            assert!(fits(&ixs, &[], &payer));
+        }
+        assert!(pack(std::iter::empty(), &[], &payer).is_empty());
+    }
+}
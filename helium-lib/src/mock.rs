@@ -0,0 +1,151 @@
+//! An in-memory client for tests.
+//!
+//! [`MockClient`] implements the [`GetAnchorAccount`] and [`GetAsset`] traits
+//! from accounts, assets and asset proofs preloaded by a test, so code written
+//! against those traits can be exercised without an RPC or DAS provider.
+//! Lookups of entries that were not loaded fail like a missing account does
+//! against a real provider.
+//!
+//! Functions that look up key to asset accounts by entity key still go through
+//! the client passed to [`crate::init`].
+use crate::{
+    anchor_lang::{AccountDeserialize, AccountSerialize},
+    asset::{Asset, AssetProof},
+    client::{GetAnchorAccount, GetAsset},
+    error::{EncodeError, Error},
+    keypair::Pubkey,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+#[derive(Debug, Default, Clone)]
+pub struct MockClient {
+    accounts: Arc<RwLock<HashMap<Pubkey, Vec<u8>>>>,
+    assets: Arc<RwLock<HashMap<Pubkey, Asset>>>,
+    proofs: Arc<RwLock<HashMap<Pubkey, AssetProof>>>,
+}
+
+impl MockClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store the raw data of an account, including its anchor discriminator
+    pub fn add_account_data(&self, pubkey: Pubkey, data: Vec<u8>) {
+        self.accounts.write().unwrap().insert(pubkey, data);
+    }
+
+    /// Store an anchor account, serialized with its discriminator
+    pub fn add_anchor_account<T: AccountSerialize>(
+        &self,
+        pubkey: Pubkey,
+        account: &T,
+    ) -> Result<(), Error> {
+        let mut data = vec![];
+        account
+            .try_serialize(&mut data)
+            .map_err(|err| EncodeError::other(format!("account {pubkey}: {err}")))?;
+        self.add_account_data(pubkey, data);
+        Ok(())
+    }
+
+    /// Store an asset under its id
+    pub fn add_asset(&self, asset: Asset) {
+        self.assets.write().unwrap().insert(asset.id, asset);
+    }
+
+    /// Store the proof for the asset with the given id
+    pub fn add_asset_proof(&self, pubkey: Pubkey, proof: AssetProof) {
+        self.proofs.write().unwrap().insert(pubkey, proof);
+    }
+
+    pub fn remove_account(&self, pubkey: &Pubkey) {
+        self.accounts.write().unwrap().remove(pubkey);
+    }
+
+    fn account_data(&self, pubkey: &Pubkey) -> Option<Vec<u8>> {
+        self.accounts.read().unwrap().get(pubkey).cloned()
+    }
+}
+
+#[async_trait::async_trait]
+impl GetAnchorAccount for MockClient {
+    async fn anchor_account<T: AccountDeserialize>(&self, pubkey: &Pubkey) -> Result<T, Error> {
+        let data = self
+            .account_data(pubkey)
+            .ok_or_else(Error::account_not_found)?;
+        Ok(T::try_deserialize(&mut data.as_slice())?)
+    }
+
+    async fn anchor_accounts<T: AccountDeserialize + Send>(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<T>>, Error> {
+        pubkeys
+            .iter()
+            .map(|pubkey| {
+                self.account_data(pubkey)
+                    .map(|data| T::try_deserialize(&mut data.as_slice()))
+                    .transpose()
+                    .map_err(Error::from)
+            })
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl GetAsset for MockClient {
+    async fn asset(&self, pubkey: &Pubkey) -> Result<Asset, Error> {
+        self.assets
+            .read()
+            .unwrap()
+            .get(pubkey)
+            .cloned()
+            .ok_or_else(Error::account_not_found)
+    }
+
+    async fn asset_proof(&self, pubkey: &Pubkey) -> Result<AssetProof, Error> {
+        self.proofs
+            .read()
+            .unwrap()
+            .get(pubkey)
+            .cloned()
+            .ok_or_else(Error::account_not_found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{asset, dao::SubDao, hotspot};
+    use futures::executor::block_on;
+
+    #[test]
+    fn mock_lookups() {
+        let client = MockClient::new();
+        let asset_key = Pubkey::new_unique();
+        let proof = AssetProof {
+            proof: vec![Pubkey::new_unique().to_string()],
+            root: Pubkey::new_unique(),
+            tree_id: Pubkey::new_unique(),
+        };
+        client.add_asset_proof(asset_key, proof.clone());
+
+        let fetched = block_on(asset::proof::get(&client, &asset_key)).expect("proof");
+        assert_eq!(fetched.root, proof.root);
+        assert_eq!(fetched.proof, proof.proof);
+        assert!(block_on(asset::get(&client, &asset_key))
+            .expect_err("missing asset")
+            .is_account_not_found());
+
+        let infos = block_on(hotspot::info::get_many(
+            &client,
+            SubDao::Iot,
+            &[Pubkey::new_unique()],
+        ))
+        .expect("infos");
+        assert!(infos[0].is_none());
+    }
+}
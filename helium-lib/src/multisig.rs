@@ -0,0 +1,509 @@
+//! Proposals for a Squads v4 multisig.
+//!
+//! Wallets held by a multisig can not sign for themselves. Instead a member
+//! wraps the instructions in a vault transaction of the multisig:
+//! [`proposal_message`] builds a single transaction that creates the vault
+//! transaction, opens a proposal for it and approves the proposal with the
+//! vote of the member. The other members then approve it and one of them
+//! executes it, for example from the Squads app.
+//!
+//! The wrapped instructions act on behalf of the vault of the multisig, so
+//! they have to be built with the vault as the owner and payer.
+//!
+//! New multisigs are created with [`create_message`], giving every member
+//! permission to propose, vote on and execute transactions.
+use crate::{
+    client::SolanaRpcClient,
+    error::{DecodeError, Error},
+    keypair::{pubkey, serde_pubkey, Pubkey},
+    message::{self, VersionedMessage},
+    priority_fee,
+    solana_sdk::{
+        address_lookup_table::AddressLookupTableAccount,
+        compute_budget,
+        hash::{self, Hash},
+        instruction::{AccountMeta, Instruction},
+        message::v0,
+        system_program,
+    },
+    TransactionOpts,
+};
+
+pub const SQUADS_PROGRAM_ID: Pubkey = pubkey!("SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf");
+
+/// Offset of the index of the last transaction in a multisig account, after
+/// the discriminator, create key, config authority, threshold and time lock
+const TRANSACTION_INDEX_OFFSET: usize = 8 + 32 + 32 + 2 + 4;
+
+/// Offset of the treasury in the program config account, after the
+/// discriminator, authority and multisig creation fee
+const TREASURY_OFFSET: usize = 8 + 32 + 8;
+
+/// Compute units for creating, proposing and approving a vault transaction
+const PROPOSAL_COMPUTE_LIMIT: u32 = 300_000;
+/// Compute units for creating a multisig
+const CREATE_COMPUTE_LIMIT: u32 = 100_000;
+
+/// Permission mask allowing a member to initiate, vote on and execute
+/// transactions
+const ALL_PERMISSIONS: u8 = 0b111;
+
+pub fn program_config_key() -> Pubkey {
+    let (key, _) =
+        Pubkey::find_program_address(&[b"multisig", b"program_config"], &SQUADS_PROGRAM_ID);
+    key
+}
+
+/// The multisig created with the given create key
+pub fn multisig_key(create_key: &Pubkey) -> Pubkey {
+    let (key, _) = Pubkey::find_program_address(
+        &[b"multisig", b"multisig", create_key.as_ref()],
+        &SQUADS_PROGRAM_ID,
+    );
+    key
+}
+
+pub fn vault_key(multisig: &Pubkey, vault_index: u8) -> Pubkey {
+    let (key, _) = Pubkey::find_program_address(
+        &[b"multisig", multisig.as_ref(), b"vault", &[vault_index]],
+        &SQUADS_PROGRAM_ID,
+    );
+    key
+}
+
+pub fn transaction_key(multisig: &Pubkey, transaction_index: u64) -> Pubkey {
+    let (key, _) = Pubkey::find_program_address(
+        &[
+            b"multisig",
+            multisig.as_ref(),
+            b"transaction",
+            &transaction_index.to_le_bytes(),
+        ],
+        &SQUADS_PROGRAM_ID,
+    );
+    key
+}
+
+pub fn proposal_key(multisig: &Pubkey, transaction_index: u64) -> Pubkey {
+    let (key, _) = Pubkey::find_program_address(
+        &[
+            b"multisig",
+            multisig.as_ref(),
+            b"transaction",
+            &transaction_index.to_le_bytes(),
+            b"proposal",
+        ],
+        &SQUADS_PROGRAM_ID,
+    );
+    key
+}
+
+/// A vault transaction proposed to a multisig
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Proposal {
+    #[serde(with = "serde_pubkey")]
+    pub multisig: Pubkey,
+    #[serde(with = "serde_pubkey")]
+    pub vault: Pubkey,
+    pub transaction_index: u64,
+    #[serde(with = "serde_pubkey")]
+    pub transaction: Pubkey,
+    #[serde(with = "serde_pubkey")]
+    pub proposal: Pubkey,
+}
+
+/// The index of the last transaction created in the given multisig
+pub async fn transaction_index<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    multisig: &Pubkey,
+) -> Result<u64, Error> {
+    let account = client.as_ref().get_account(multisig).await?;
+    if account.owner != SQUADS_PROGRAM_ID {
+        return Err(DecodeError::other(format!("{multisig} is not a squads multisig")).into());
+    }
+    let bytes = account
+        .data
+        .get(TRANSACTION_INDEX_OFFSET..TRANSACTION_INDEX_OFFSET + 8)
+        .ok_or_else(|| DecodeError::other("multisig account too short"))?;
+    Ok(u64::from_le_bytes(
+        bytes.try_into().map_err(DecodeError::from)?,
+    ))
+}
+
+/// Build a transaction creating a multisig with the given members and
+/// approval threshold, without a config authority or time lock.
+///
+/// The multisig address is derived from the create key, which has to sign
+/// the transaction along with the creator. A fresh keypair is the usual
+/// create key, it is not needed after the multisig is created.
+pub async fn create_message<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    create_key: &Pubkey,
+    members: &[Pubkey],
+    threshold: u16,
+    creator: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<(VersionedMessage, u64), Error> {
+    let program_config = client.as_ref().get_account(&program_config_key()).await?;
+    let treasury = program_config
+        .data
+        .get(TREASURY_OFFSET..TREASURY_OFFSET + 32)
+        .ok_or_else(|| DecodeError::other("program config account too short"))
+        .and_then(|bytes| Pubkey::try_from(bytes).map_err(DecodeError::from))?;
+    let create_ix = create_instruction(create_key, &treasury, members, threshold, creator)?;
+    let ixs = [
+        priority_fee::compute_budget_instruction(CREATE_COMPUTE_LIMIT),
+        priority_fee::compute_price_instruction_for_accounts(
+            client,
+            &create_ix.accounts,
+            opts.fee_range(),
+        )
+        .await?,
+        create_ix,
+    ];
+    message::mk_message(client, &ixs, &opts.lut_addresses, creator).await
+}
+
+fn create_instruction(
+    create_key: &Pubkey,
+    treasury: &Pubkey,
+    members: &[Pubkey],
+    threshold: u16,
+    creator: &Pubkey,
+) -> Result<Instruction, DecodeError> {
+    let mut unique = members.to_vec();
+    unique.sort();
+    unique.dedup();
+    if unique.len() != members.len() {
+        return Err(DecodeError::other("duplicate multisig member"));
+    }
+    if threshold == 0 || usize::from(threshold) > members.len() {
+        return Err(DecodeError::other(format!(
+            "threshold must be between 1 and the {} members",
+            members.len()
+        )));
+    }
+
+    // No config authority, so the multisig can only change itself
+    let mut args = vec![0];
+    args.extend(threshold.to_le_bytes());
+    args.extend(u32::try_from(members.len())?.to_le_bytes());
+    for member in members {
+        args.extend_from_slice(member.as_ref());
+        args.push(ALL_PERMISSIONS);
+    }
+    // No time lock, rent collector or memo
+    args.extend(0u32.to_le_bytes());
+    args.extend([0, 0]);
+
+    Ok(Instruction {
+        program_id: SQUADS_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(program_config_key(), false),
+            AccountMeta::new(*treasury, false),
+            AccountMeta::new(multisig_key(create_key), false),
+            AccountMeta::new_readonly(*create_key, true),
+            AccountMeta::new(*creator, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: [discriminator("multisig_create_v2").as_slice(), &args].concat(),
+    })
+}
+
+/// Build a transaction for the given member that proposes the instructions
+/// of the given message to the multisig and approves the proposal.
+///
+/// The message has to be built with the vault of the multisig as payer.
+/// Compute budget instructions are dropped from it, since they have no
+/// effect inside a vault transaction.
+pub async fn proposal_message<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    multisig: &Pubkey,
+    vault_index: u8,
+    inner: &VersionedMessage,
+    member: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<(VersionedMessage, u64, Proposal), Error> {
+    let vault = vault_key(multisig, vault_index);
+    if inner.static_account_keys().first() != Some(&vault) {
+        return Err(DecodeError::other("proposed instructions must be paid by the vault").into());
+    }
+    let lookup_keys: Vec<Pubkey> = inner
+        .address_table_lookups()
+        .map(|lookups| lookups.iter().map(|lookup| lookup.account_key).collect())
+        .unwrap_or_default();
+    let lut_accounts = message::get_lut_accounts(client, &lookup_keys).await?;
+    let ixs: Vec<Instruction> = instructions(inner, &lut_accounts)?
+        .into_iter()
+        .filter(|ix| ix.program_id != compute_budget::id())
+        .collect();
+    let vault_message = v0::Message::try_compile(&vault, &ixs, &lut_accounts, Hash::default())?;
+
+    let transaction_index = transaction_index(client, multisig).await? + 1;
+    let proposal = Proposal {
+        multisig: *multisig,
+        vault,
+        transaction_index,
+        transaction: transaction_key(multisig, transaction_index),
+        proposal: proposal_key(multisig, transaction_index),
+    };
+    let proposal_ixs = proposal_instructions(&proposal, vault_index, &vault_message, member)?;
+    let accounts: Vec<AccountMeta> = proposal_ixs
+        .iter()
+        .flat_map(|ix| ix.accounts.clone())
+        .collect();
+    let mut ixs = vec![
+        priority_fee::compute_budget_instruction(PROPOSAL_COMPUTE_LIMIT),
+        priority_fee::compute_price_instruction_for_accounts(client, &accounts, opts.fee_range())
+            .await?,
+    ];
+    ixs.extend(proposal_ixs);
+
+    let outer_luts = message::get_lut_accounts(client, &opts.lut_addresses).await?;
+    if !message::fits(&ixs, &outer_luts, member) {
+        return Err(DecodeError::other("instructions too large for a single proposal").into());
+    }
+    let (msg, block_height) =
+        message::mk_message(client, &ixs, &opts.lut_addresses, member).await?;
+    Ok((msg, block_height, proposal))
+}
+
+/// The instructions creating, proposing and approving a vault transaction
+fn proposal_instructions(
+    proposal: &Proposal,
+    vault_index: u8,
+    vault_message: &v0::Message,
+    member: &Pubkey,
+) -> Result<Vec<Instruction>, DecodeError> {
+    let mut create_args = vec![vault_index, 0];
+    let transaction_message = transaction_message(vault_message)?;
+    create_args.extend(u32::try_from(transaction_message.len())?.to_le_bytes());
+    create_args.extend(transaction_message);
+    // No memo
+    create_args.push(0);
+
+    let mut propose_args = proposal.transaction_index.to_le_bytes().to_vec();
+    // Not a draft, so it can be voted on right away
+    propose_args.push(0);
+
+    Ok(vec![
+        Instruction {
+            program_id: SQUADS_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(proposal.multisig, false),
+                AccountMeta::new(proposal.transaction, false),
+                AccountMeta::new_readonly(*member, true),
+                AccountMeta::new(*member, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+            data: [
+                discriminator("vault_transaction_create").as_slice(),
+                &create_args,
+            ]
+            .concat(),
+        },
+        Instruction {
+            program_id: SQUADS_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(proposal.multisig, false),
+                AccountMeta::new(proposal.proposal, false),
+                AccountMeta::new_readonly(*member, true),
+                AccountMeta::new(*member, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+            data: [discriminator("proposal_create").as_slice(), &propose_args].concat(),
+        },
+        Instruction {
+            program_id: SQUADS_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(proposal.multisig, false),
+                AccountMeta::new(*member, true),
+                AccountMeta::new(proposal.proposal, false),
+            ],
+            // No memo
+            data: [discriminator("proposal_approve").as_slice(), &[0]].concat(),
+        },
+    ])
+}
+
+/// Anchor instruction discriminator for the given instruction name
+fn discriminator(name: &str) -> [u8; 8] {
+    let hash = hash::hash(format!("global:{name}").as_bytes());
+    let mut discriminator = [0; 8];
+    discriminator.copy_from_slice(&hash.as_ref()[..8]);
+    discriminator
+}
+
+/// Serialize a message in the compact format Squads stores vault
+/// transactions in. Account keys are ordered the same way as in a Solana
+/// message, so the header translates to counts of each kind of account.
+fn transaction_message(message: &v0::Message) -> Result<Vec<u8>, DecodeError> {
+    let header = message.header;
+    let num_writable_non_signers = message
+        .account_keys
+        .len()
+        .checked_sub(
+            usize::from(header.num_required_signatures)
+                + usize::from(header.num_readonly_unsigned_accounts),
+        )
+        .ok_or_else(|| DecodeError::other("invalid message header"))?;
+    let mut data = vec![
+        header.num_required_signatures,
+        header.num_required_signatures - header.num_readonly_signed_accounts,
+        u8::try_from(num_writable_non_signers)?,
+    ];
+    data.push(u8::try_from(message.account_keys.len())?);
+    for key in &message.account_keys {
+        data.extend_from_slice(key.as_ref());
+    }
+    data.push(u8::try_from(message.instructions.len())?);
+    for ix in &message.instructions {
+        data.push(ix.program_id_index);
+        data.push(u8::try_from(ix.accounts.len())?);
+        data.extend_from_slice(&ix.accounts);
+        data.extend(u16::try_from(ix.data.len())?.to_le_bytes());
+        data.extend_from_slice(&ix.data);
+    }
+    data.push(u8::try_from(message.address_table_lookups.len())?);
+    for lookup in &message.address_table_lookups {
+        data.extend_from_slice(lookup.account_key.as_ref());
+        data.push(u8::try_from(lookup.writable_indexes.len())?);
+        data.extend_from_slice(&lookup.writable_indexes);
+        data.push(u8::try_from(lookup.readonly_indexes.len())?);
+        data.extend_from_slice(&lookup.readonly_indexes);
+    }
+    Ok(data)
+}
+
+/// The instructions of a message, with the accounts loaded from the given
+/// lookup tables resolved
+fn instructions(
+    message: &VersionedMessage,
+    lut_accounts: &[AddressLookupTableAccount],
+) -> Result<Vec<Instruction>, DecodeError> {
+    let header = message.header();
+    let static_keys = message.static_account_keys();
+    let num_signed = usize::from(header.num_required_signatures);
+    let num_writable_signed = num_signed - usize::from(header.num_readonly_signed_accounts);
+    let num_writable_unsigned =
+        static_keys.len() - usize::from(header.num_readonly_unsigned_accounts);
+    let mut accounts: Vec<AccountMeta> = static_keys
+        .iter()
+        .enumerate()
+        .map(|(index, key)| AccountMeta {
+            pubkey: *key,
+            is_signer: index < num_signed,
+            is_writable: index < num_writable_signed
+                || (index >= num_signed && index < num_writable_unsigned),
+        })
+        .collect();
+
+    let lookups = message.address_table_lookups().unwrap_or_default();
+    let lookup_address = |key: &Pubkey, index: u8| {
+        lut_accounts
+            .iter()
+            .find(|lut| lut.key == *key)
+            .and_then(|lut| lut.addresses.get(usize::from(index)))
+            .copied()
+            .ok_or_else(|| DecodeError::other(format!("missing lookup table entry in {key}")))
+    };
+    for lookup in lookups {
+        for index in &lookup.writable_indexes {
+            accounts.push(AccountMeta::new(
+                lookup_address(&lookup.account_key, *index)?,
+                false,
+            ));
+        }
+    }
+    for lookup in lookups {
+        for index in &lookup.readonly_indexes {
+            accounts.push(AccountMeta::new_readonly(
+                lookup_address(&lookup.account_key, *index)?,
+                false,
+            ));
+        }
+    }
+
+    let account = |index: u8| {
+        accounts
+            .get(usize::from(index))
+            .cloned()
+            .ok_or_else(|| DecodeError::other("invalid account index"))
+    };
+    message
+        .instructions()
+        .iter()
+        .map(|ix| {
+            Ok(Instruction {
+                program_id: account(ix.program_id_index)?.pubkey,
+                accounts: ix
+                    .accounts
+                    .iter()
+                    .map(|index| account(*index))
+                    .collect::<Result<_, _>>()?,
+                data: ix.data.clone(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vault_transaction_message() {
+        let multisig = Pubkey::new_unique();
+        let vault = vault_key(&multisig, 0);
+        let (recipient, program) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ixs = vec![
+            priority_fee::compute_budget_instruction(100_000),
+            Instruction {
+                program_id: program,
+                accounts: vec![
+                    AccountMeta::new(vault, true),
+                    AccountMeta::new(recipient, false),
+                ],
+                data: vec![1, 2, 3],
+            },
+        ];
+        let inner = VersionedMessage::V0(
+            v0::Message::try_compile(&vault, &ixs, &[], Hash::default()).expect("message"),
+        );
+        let decompiled = instructions(&inner, &[]).expect("instructions");
+        assert_eq!(decompiled, ixs);
+
+        let vault_message =
+            v0::Message::try_compile(&vault, &decompiled[1..], &[], Hash::default())
+                .expect("vault message");
+        let data = transaction_message(&vault_message).expect("transaction message");
+        // One writable signer, one writable account and the read only program
+        assert_eq!(&data[..4], &[1, 1, 1, 3]);
+        assert_eq!(&data[4..36], vault.as_ref());
+        // One instruction of the program at index 2 with accounts 0 and 1
+        assert_eq!(&data[100..], &[1, 2, 2, 0, 1, 3, 0, 1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn create_args() {
+        let (create_key, treasury, creator) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let members = [creator, Pubkey::new_unique()];
+        let ix =
+            create_instruction(&create_key, &treasury, &members, 2, &creator).expect("instruction");
+        let args = &ix.data[8..];
+        assert_eq!(&args[..7], &[0, 2, 0, 2, 0, 0, 0]);
+        assert_eq!(&args[7..39], creator.as_ref());
+        assert_eq!(args[39], ALL_PERMISSIONS);
+        assert_eq!(&args[73..], &[0, 0, 0, 0, 0, 0]);
+        assert_eq!(ix.accounts[2].pubkey, multisig_key(&create_key));
+
+        assert!(create_instruction(&create_key, &treasury, &members, 3, &creator).is_err());
+        assert!(
+            create_instruction(&create_key, &treasury, &[creator, creator], 1, &creator).is_err()
+        );
+    }
+}
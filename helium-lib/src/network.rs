@@ -0,0 +1,375 @@
+//! Proxy and TLS settings shared by all network clients.
+//!
+//! Settings are made once with [`set`], before any client is constructed,
+//! and apply to the http clients used for REST and DAS requests, to the
+//! Solana RPC client and to the gRPC channels of the config services. Extra
+//! root certificates are trusted in addition to the built in ones.
+//!
+//! gRPC channels are tunneled through the configured proxy with http
+//! CONNECT, so they require an http proxy. The websocket used to wait for
+//! transaction confirmations can not use these settings, so once any are
+//! made confirmations are polled over the Solana RPC client instead.
+//!
+//! Without a configured proxy the http clients use the proxy given in the
+//! `HTTPS_PROXY`, `HTTP_PROXY` or `ALL_PROXY` environment variables, skipping
+//! hosts listed in `NO_PROXY`. gRPC channels only use a configured proxy.
+use crate::{
+    error::{DecodeError, Error},
+    solana_client::{
+        client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+        rpc_custom_error::{
+            JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+            JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE,
+        },
+        rpc_request::{RpcError, RpcRequest, RpcResponseErrorData},
+        rpc_response::RpcSimulateTransactionResult,
+        rpc_sender::{RpcSender, RpcTransportStats},
+    },
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use helium_proto::services::Uri;
+use serde::Deserialize;
+use std::{
+    future::Future,
+    io,
+    path::Path,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use tonic::codegen::Service;
+
+const PEM_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_END: &str = "-----END CERTIFICATE-----";
+/// Number of times a rate limited Solana RPC request is retried
+const MAX_TOO_MANY_REQUESTS_RETRIES: usize = 5;
+/// Delay before retrying a rate limited Solana RPC request without a
+/// Retry-After header
+const TOO_MANY_REQUESTS_DELAY: Duration = Duration::from_millis(500);
+/// Maximum size of the response of a proxy to a CONNECT request
+const MAX_CONNECT_RESPONSE: usize = 8192;
+
+#[derive(Default)]
+struct Settings {
+    proxy: Option<reqwest::Proxy>,
+    /// The proxy url, for the gRPC channels
+    proxy_url: Option<url::Url>,
+    root_certificates: Vec<reqwest::Certificate>,
+    /// The root certificates as PEM, for the gRPC channels
+    root_pem: Vec<String>,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Set the proxy url and a PEM file of extra root certificates to use for
+/// all clients constructed after this call. The proxy url may use the http,
+/// https or socks5 scheme and include credentials.
+pub fn set(proxy: Option<&str>, ca_bundle: Option<&Path>) -> Result<(), Error> {
+    let proxy_url = proxy
+        .map(url::Url::parse)
+        .transpose()
+        .map_err(|err| DecodeError::other(format!("invalid proxy url: {err}")))?;
+    let proxy = proxy.map(reqwest::Proxy::all).transpose()?;
+    let root_pem = match ca_bundle {
+        Some(path) => {
+            let bundle = std::fs::read_to_string(path).map_err(DecodeError::from)?;
+            let certificates = pem_certificates(&bundle);
+            if certificates.is_empty() {
+                return Err(DecodeError::other(format!(
+                    "no certificates found in {}",
+                    path.display()
+                ))
+                .into());
+            }
+            certificates
+        }
+        None => vec![],
+    };
+    let root_certificates = root_pem
+        .iter()
+        .map(|pem| reqwest::Certificate::from_pem(pem.as_bytes()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let _ = SETTINGS.set(Settings {
+        proxy,
+        proxy_url,
+        root_certificates,
+        root_pem,
+    });
+    Ok(())
+}
+
+/// Whether a proxy or extra root certificates were configured
+pub fn is_customized() -> bool {
+    SETTINGS
+        .get()
+        .is_some_and(|settings| settings.proxy.is_some() || !settings.root_pem.is_empty())
+}
+
+/// Apply the configured proxy and root certificates to an http client
+pub fn apply(mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+    let Some(settings) = SETTINGS.get() else {
+        return builder;
+    };
+    if let Some(proxy) = &settings.proxy {
+        builder = builder.proxy(proxy.clone());
+    }
+    for certificate in &settings.root_certificates {
+        builder = builder.add_root_certificate(certificate.clone());
+    }
+    builder
+}
+
+/// The configured root certificates as a single PEM bundle, if any
+pub(crate) fn root_pem() -> Option<String> {
+    SETTINGS
+        .get()
+        .filter(|settings| !settings.root_pem.is_empty())
+        .map(|settings| settings.root_pem.join("\n"))
+}
+
+/// A connector that tunnels gRPC channels through the configured proxy, if
+/// any. Only http proxies are supported for gRPC.
+pub(crate) fn grpc_connector() -> Result<Option<ProxyConnector>, DecodeError> {
+    let Some(proxy) = SETTINGS
+        .get()
+        .and_then(|settings| settings.proxy_url.clone())
+    else {
+        return Ok(None);
+    };
+    if proxy.scheme() != "http" {
+        return Err(DecodeError::other(format!(
+            "gRPC connections require an http proxy, not {}",
+            proxy.scheme()
+        )));
+    }
+    Ok(Some(ProxyConnector { proxy }))
+}
+
+/// Connects to a gRPC endpoint through an http CONNECT tunnel of a proxy.
+/// TLS to the endpoint is layered on top of the tunnel by the channel.
+#[derive(Clone)]
+pub(crate) struct ProxyConnector {
+    proxy: url::Url,
+}
+
+impl Service<Uri> for ProxyConnector {
+    type Response = TcpStream;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<TcpStream>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let proxy = self.proxy.clone();
+        Box::pin(async move { connect_tunnel(&proxy, &uri).await })
+    }
+}
+
+async fn connect_tunnel(proxy: &url::Url, uri: &Uri) -> io::Result<TcpStream> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+    let host = uri
+        .host()
+        .ok_or_else(|| invalid(format!("no host in {uri}")))?;
+    let port = uri
+        .port_u16()
+        .unwrap_or(if uri.scheme_str() == Some("https") {
+            443
+        } else {
+            80
+        });
+    let proxy_host = proxy
+        .host_str()
+        .ok_or_else(|| invalid(format!("no host in proxy url {proxy}")))?;
+    let proxy_port = proxy.port_or_known_default().unwrap_or(80);
+
+    let mut stream = TcpStream::connect((proxy_host, proxy_port)).await?;
+    let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if !proxy.username().is_empty() {
+        let credentials = format!("{}:{}", proxy.username(), proxy.password().unwrap_or(""));
+        request.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            STANDARD.encode(credentials)
+        ));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // Read the response a byte at a time so nothing sent through the tunnel
+    // after it is consumed
+    let mut response = vec![];
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_CONNECT_RESPONSE {
+            return Err(io::Error::other("proxy response too large"));
+        }
+        response.push(stream.read_u8().await?);
+    }
+    let response = String::from_utf8_lossy(&response);
+    let status = response.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some("200") => Ok(stream),
+        _ => Err(io::Error::other(format!(
+            "proxy refused tunnel to {host}:{port}: {status}"
+        ))),
+    }
+}
+
+/// Split a PEM bundle into its certificates. Anything outside of certificate
+/// blocks, like comments or keys, is skipped.
+fn pem_certificates(bundle: &str) -> Vec<String> {
+    let mut certificates = vec![];
+    let mut rest = bundle;
+    while let Some(start) = rest.find(PEM_BEGIN) {
+        let Some(len) = rest[start..].find(PEM_END) else {
+            break;
+        };
+        let end = start + len + PEM_END.len();
+        certificates.push(format!("{}\n", &rest[start..end]));
+        rest = &rest[end..];
+    }
+    certificates
+}
+
+/// Sends Solana RPC requests with an http client that uses the configured
+/// proxy and root certificates, since the Solana RPC client does not expose
+/// those settings
+pub(crate) struct ProxySender {
+    client: reqwest::Client,
+    url: String,
+    request_id: AtomicU64,
+}
+
+impl ProxySender {
+    pub(crate) fn new(url: String) -> Self {
+        Self {
+            client: crate::client::http_client(),
+            url,
+            request_id: AtomicU64::new(0),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcErrorObject {
+    code: i64,
+    message: String,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeUnhealthyData {
+    num_slots_behind: Option<u64>,
+}
+
+fn transport_error(err: reqwest::Error) -> ClientError {
+    ClientErrorKind::Custom(err.to_string()).into()
+}
+
+#[async_trait::async_trait]
+impl RpcSender for ProxySender {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> ClientResult<serde_json::Value> {
+        let id = self.request_id.fetch_add(1, Ordering::Relaxed);
+        let body = request.build_request_json(id, params);
+        // Rate limited requests are retried like the default Solana RPC
+        // sender does, honoring the Retry-After header of the node
+        let mut retries = MAX_TOO_MANY_REQUESTS_RETRIES;
+        let response = loop {
+            let response = self
+                .client
+                .post(&self.url)
+                .json(&body)
+                .send()
+                .await
+                .map_err(transport_error)?;
+            if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS || retries == 0 {
+                break response;
+            }
+            retries -= 1;
+            let delay = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(TOO_MANY_REQUESTS_DELAY);
+            tokio::time::sleep(delay).await;
+        };
+        let mut response = response
+            .error_for_status()
+            .map_err(transport_error)?
+            .json::<serde_json::Value>()
+            .await
+            .map_err(transport_error)?;
+        let error = response["error"].take();
+        if error.is_null() {
+            return Ok(response["result"].take());
+        }
+        let error: RpcErrorObject = serde_json::from_value(error).map_err(|err| {
+            RpcError::RpcRequestError(format!("invalid rpc error response: {err}"))
+        })?;
+        let data = match error.code {
+            JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE => {
+                serde_json::from_value::<RpcSimulateTransactionResult>(error.data)
+                    .map(RpcResponseErrorData::SendTransactionPreflightFailure)
+                    .unwrap_or(RpcResponseErrorData::Empty)
+            }
+            JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY => {
+                serde_json::from_value::<NodeUnhealthyData>(error.data)
+                    .map(|data| RpcResponseErrorData::NodeUnhealthy {
+                        num_slots_behind: data.num_slots_behind,
+                    })
+                    .unwrap_or(RpcResponseErrorData::Empty)
+            }
+            _ => RpcResponseErrorData::Empty,
+        };
+        Err(RpcError::RpcResponseError {
+            code: error.code,
+            message: error.message,
+            data,
+        }
+        .into())
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pem_bundle() {
+        let cert = |body: &str| format!("{PEM_BEGIN}\n{body}\n{PEM_END}");
+        let bundle = format!(
+            "# Example CA\n{}\n\n# Other CA\n{}\ntrailing",
+            cert("AAAA"),
+            cert("BBBB")
+        );
+        assert_eq!(
+            pem_certificates(&bundle),
+            vec![format!("{}\n", cert("AAAA")), format!("{}\n", cert("BBBB"))]
+        );
+        assert!(pem_certificates(&format!("{PEM_BEGIN}\nAAAA")).is_empty());
+    }
+}
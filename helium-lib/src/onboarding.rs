@@ -1,18 +1,88 @@
-use crate::{hotspot::HotspotInfoUpdate, keypair};
-use futures::TryFutureExt;
+use crate::{
+    client::{GetAnchorAccount, SolanaRpcClient},
+    confirm::ConfirmError,
+    dao::SubDao,
+    error::{DecodeError, Error},
+    helium_entity_manager,
+    hotspot::{self, HotspotInfoUpdate},
+    keypair::{self, Signature},
+    solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel},
+    token::{self, Token, TokenAmount},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use std::marker::Send;
+use std::{
+    collections::HashMap,
+    marker::Send,
+    time::{Duration, Instant},
+};
 
+/// Number of times a request that failed with a retryable error is retried
+const MAX_RETRIES: u32 = 3;
+/// Delay before the first retry of a request, doubled for every following
+/// retry
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+/// Longest delay between checks of the status of a transaction
+const MAX_POLL_DELAY: Duration = Duration::from_secs(8);
+/// Time allowed for a transaction of the onboarding server to land
+pub const TRANSACTION_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Client for the v3 API of the DeWi onboarding server.
+///
+/// Requests that only read from the server and fail to connect, time out or
+/// are answered with a 429 or 5xx status are retried with exponential
+/// backoff. Requests that create records on the server are only retried
+/// when they failed to connect, since they may have been handled otherwise.
 pub struct Client {
     base_url: String,
     inner: reqwest::Client,
+    api_key: Option<String>,
 }
 
 impl Client {
     pub fn new(base_url: &str) -> Self {
         Self {
             base_url: base_url.to_string(),
-            inner: reqwest::Client::new(),
+            inner: crate::client::http_client(),
+            api_key: None,
+        }
+    }
+
+    /// Authenticate requests with the given maker api key, which creating
+    /// Hotspot records requires
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    async fn send<T, F>(&self, request: F, idempotent: bool) -> Result<T, OnboardingError>
+    where
+        T: DeserializeOwned,
+        F: Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+    {
+        let mut delay = RETRY_DELAY;
+        let mut retries = 0;
+        loop {
+            let mut builder = request(&self.inner);
+            if let Some(api_key) = &self.api_key {
+                builder = builder.header(reqwest::header::AUTHORIZATION, api_key);
+            }
+            let result = match builder.send().await {
+                Ok(resp) => parse_response(resp).await,
+                Err(err) => Err(err.into()),
+            };
+            match result {
+                Err(err)
+                    if retries < MAX_RETRIES
+                        && (err.is_connect() || (idempotent && err.is_retryable())) =>
+                {
+                    retries += 1;
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                result => return result,
+            }
         }
     }
 
@@ -21,26 +91,30 @@ impl Client {
         T: 'static + DeserializeOwned + Send,
     {
         let url = format!("{}{}", &self.base_url, path);
-        let resp = self.inner.get(&url).send().await?;
-        let onboarding_resp = resp.json::<OnboardingResponse<T>>().await?;
-        if !onboarding_resp.success {
-            return Err(OnboardingError::from(onboarding_resp));
-        }
-        onboarding_resp.data.ok_or_else(|| OnboardingError::NoData)
+        self.send(|client| client.get(&url), true).await
     }
 
+    /// Post a request that changes state on the server
     pub async fn post<T, P>(&self, path: &str, params: &P) -> Result<T, OnboardingError>
     where
         T: 'static + DeserializeOwned + Send,
         P: Serialize + ?Sized,
     {
         let url = format!("{}{}", &self.base_url, path);
-        let resp = self.inner.post(&url).json(&params).send().await?;
-        let onboarding_resp = resp.json::<OnboardingResponse<T>>().await?;
-        if !onboarding_resp.success {
-            return Err(OnboardingError::from(onboarding_resp));
-        }
-        onboarding_resp.data.ok_or_else(|| OnboardingError::NoData)
+        self.send(|client| client.post(&url).json(params), false)
+            .await
+    }
+
+    /// Post a request that only builds a response, like the transactions
+    /// the server returns, and so is safe to send again
+    async fn post_idempotent<T, P>(&self, path: &str, params: &P) -> Result<T, OnboardingError>
+    where
+        T: 'static + DeserializeOwned + Send,
+        P: Serialize + ?Sized,
+    {
+        let url = format!("{}{}", &self.base_url, path);
+        self.send(|client| client.post(&url).json(params), true)
+            .await
     }
 
     pub async fn get_hotspot(
@@ -50,6 +124,73 @@ impl Client {
         self.get::<Hotspot>(&format!("/hotspots/{}", hotspot)).await
     }
 
+    /// Get a page of the onboarding records on the server, optionally only
+    /// those of the given maker. Pages start at 1.
+    pub async fn get_hotspots(
+        &self,
+        maker_id: Option<u32>,
+        page: u32,
+    ) -> Result<Vec<Hotspot>, OnboardingError> {
+        let path = match maker_id {
+            Some(maker_id) => format!("/hotspots?makerId={maker_id}&page={page}"),
+            None => format!("/hotspots?page={page}"),
+        };
+        self.get(&path).await
+    }
+
+    /// Create the onboarding record of a Hotspot for the maker of the api
+    /// key of the client
+    pub async fn create_hotspot(&self, hotspot: &NewHotspot) -> Result<Hotspot, OnboardingError> {
+        if self.api_key.is_none() {
+            return Err(OnboardingError::Unauthorized(
+                "creating a hotspot needs a maker api key".to_string(),
+            ));
+        }
+        self.post("/hotspots", hotspot).await
+    }
+
+    /// Get the transactions issuing the Hotspot of the given add gateway
+    /// transaction, paid for by its maker. The add gateway transaction is
+    /// given as its encoded envelope.
+    pub async fn get_create_hotspot_txns(
+        &self,
+        add_gateway_txn: &[u8],
+    ) -> Result<Vec<solana_sdk::transaction::Transaction>, OnboardingError> {
+        #[derive(Serialize)]
+        struct CreateParams {
+            transaction: String,
+        }
+
+        let params = CreateParams {
+            transaction: STANDARD.encode(add_gateway_txn),
+        };
+        let resp = self
+            .post_idempotent::<OnboardingResponseTransactions, _>(
+                "/transactions/create-hotspot",
+                &params,
+            )
+            .await?;
+        resp.transactions()
+    }
+
+    /// Get the transaction onboarding an issued Hotspot to the subdao of the
+    /// given update, with its onboarding fees paid by the maker. The owner of
+    /// the Hotspot still needs to sign the transaction.
+    pub async fn get_onboard_txn(
+        &self,
+        hotspot: &helium_crypto::PublicKey,
+        update: HotspotInfoUpdate,
+    ) -> Result<solana_sdk::transaction::Transaction, OnboardingError> {
+        let params = InfoParams::new(hotspot, &update);
+        let resp = self
+            .post_idempotent::<OnboardingResponseTransactions, _>(
+                &format!("/transactions/{}/onboard", update.subdao()),
+                &params,
+            )
+            .await?;
+        resp.transaction()
+    }
+
     pub async fn get_update_txn(
         &self,
         hotspot: &helium_crypto::PublicKey,
@@ -59,36 +200,232 @@ impl Client {
         #[derive(Serialize)]
         #[serde(rename_all = "camelCase")]
         struct UpdateParams {
-            entity_key: helium_crypto::PublicKey,
+            #[serde(flatten)]
+            info: InfoParams,
             #[serde(with = "keypair::serde_pubkey")]
             wallet: keypair::Pubkey,
-            location: Option<String>,
-            gain: Option<i32>,
-            elevation: Option<i32>,
         }
 
         let params = UpdateParams {
-            entity_key: hotspot.clone(),
+            info: InfoParams::new(hotspot, &update),
             wallet: *signer,
+        };
+
+        self.post_idempotent::<OnboardingResponseTransactions, _>(
+            &format!("/transactions/{}/update-metadata", update.subdao()),
+            &params,
+        )
+        .await?
+        .transaction()
+    }
+}
+
+/// Poll the status of a submitted transaction of the onboarding server until
+/// it reaches the given commitment, backing off between checks, for at most
+/// the given time. Returns the slot the transaction landed in.
+pub async fn wait_for_transaction<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    signature: &Signature,
+    commitment: CommitmentLevel,
+    timeout: Duration,
+) -> Result<u64, Error> {
+    let start = Instant::now();
+    let mut delay = RETRY_DELAY;
+    loop {
+        let statuses = client
+            .as_ref()
+            .get_signature_statuses(&[*signature])
+            .await?
+            .value;
+        if let Some(Some(status)) = statuses.into_iter().next() {
+            if let Some(err) = status.err.clone() {
+                return Err(ConfirmError::Failed(*signature, err).into());
+            }
+            if status.satisfies_commitment(CommitmentConfig { commitment }) {
+                return Ok(status.slot);
+            }
+        }
+        if start.elapsed() >= timeout {
+            return Err(ConfirmError::Timeout(*signature, timeout).into());
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_POLL_DELAY);
+    }
+}
+
+/// The Hotspot and asserted details sent with onboard and update requests
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InfoParams {
+    entity_key: helium_crypto::PublicKey,
+    location: Option<String>,
+    gain: Option<i32>,
+    elevation: Option<i32>,
+}
+
+impl InfoParams {
+    fn new(hotspot: &helium_crypto::PublicKey, update: &HotspotInfoUpdate) -> Self {
+        Self {
+            entity_key: hotspot.clone(),
             location: update
                 .location()
                 .map(|location| u64::from(location).to_string()),
             gain: update.gain_i32(),
             elevation: update.elevation().to_owned(),
-        };
+        }
+    }
+}
 
-        self.post::<OnboardingResponseTransactions, _>(
-            &format!("/transactions/{}/update-metadata", update.subdao()),
-            &params,
-        )
-        .and_then(|resp_data| async move {
-            bincode::deserialize::<solana_sdk::transaction::Transaction>(
-                &resp_data.solana_transactions[0].data,
-            )
-            .map_err(|_| OnboardingError::InvalidData)
-        })
-        .await
+async fn parse_response<T: DeserializeOwned>(
+    resp: reqwest::Response,
+) -> Result<T, OnboardingError> {
+    let status = resp.status();
+    match resp.json::<OnboardingResponse<T>>().await {
+        Ok(onboarding_resp) if onboarding_resp.success => {
+            onboarding_resp.data.ok_or(OnboardingError::NoData)
+        }
+        Ok(onboarding_resp) => Err(OnboardingError::from(onboarding_resp)),
+        // Errors from proxies in front of the server are not in the format
+        // of the onboarding server
+        Err(_) if !status.is_success() => Err(OnboardingError::from_code(
+            u32::from(status.as_u16()),
+            status.canonical_reason().unwrap_or("unknown").to_string(),
+        )),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Onboarding record to create for a Hotspot
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NewHotspot {
+    pub onboarding_key: helium_crypto::PublicKey,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mac_wlan0: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mac_eth0: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpi_serial: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub helium_serial: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch: Option<String>,
+}
+
+/// The onboarding record of a Hotspot, with the subdaos it has been
+/// onboarded to on chain
+#[derive(Serialize, Debug)]
+pub struct OnboardingRecord {
+    #[serde(flatten)]
+    pub hotspot: Hotspot,
+    /// The maker wallet that pays for onboarding the Hotspot
+    #[serde(with = "crate::keypair::serde_pubkey")]
+    pub payer: keypair::Pubkey,
+    pub onboarded: HashMap<SubDao, bool>,
+}
+
+/// Look up the onboarding record of a Hotspot on the onboarding server and
+/// check which subdaos it is onboarded to.
+pub async fn record<C: GetAnchorAccount>(
+    client: &C,
+    onboarding_client: &Client,
+    hotspot: &helium_crypto::PublicKey,
+) -> Result<OnboardingRecord, Error> {
+    let record = onboarding_client.get_hotspot(hotspot).await?;
+    let mut onboarded = HashMap::new();
+    for subdao in SubDao::all() {
+        let info = hotspot::info::get(client, subdao, &subdao.info_key(hotspot)).await?;
+        onboarded.insert(subdao, info.is_some());
+    }
+    Ok(OnboardingRecord {
+        payer: record.maker.address,
+        hotspot: record,
+        onboarded,
+    })
+}
+
+/// Minimum SOL balance, in lamports, a maker needs to pay for onboarding
+/// transaction fees and rent
+pub const MIN_MAKER_SOL: u64 = 10_000_000;
+
+/// The balances of the maker paying for an onboarding server transaction
+#[derive(Serialize, Debug)]
+pub struct MakerFunding {
+    pub name: String,
+    #[serde(with = "crate::keypair::serde_pubkey")]
+    pub address: keypair::Pubkey,
+    pub dc: TokenAmount,
+    pub sol: TokenAmount,
+}
+
+/// The data credits the maker is charged for the given update, if known.
+///
+/// Location changes of IoT Hotspots cost at least the data-only location
+/// staking fee. Other fees are not known ahead of time.
+async fn required_dc<C: GetAnchorAccount>(
+    client: &C,
+    update: &HotspotInfoUpdate,
+) -> Result<u64, Error> {
+    if update.location().is_none() || update.subdao() != SubDao::Iot {
+        return Ok(0);
+    }
+    let config: helium_entity_manager::RewardableEntityConfigV0 = client
+        .anchor_account(&SubDao::Iot.rewardable_entity_config_key())
+        .await?;
+    match config.settings {
+        helium_entity_manager::ConfigSettingsV0::IotConfig {
+            dataonly_location_staking_fee,
+            ..
+        } => Ok(dataonly_location_staking_fee),
+        _ => Err(DecodeError::other("invalid iot rewardable entity config").into()),
+    }
+}
+
+/// Check that the maker of the given Hotspot can pay for the given update
+/// through the onboarding server.
+///
+/// This looks up the onboarding record of the Hotspot and the DC and SOL
+/// balances of its maker, and fails with a clear error when the maker is out
+/// of funds, rather than have the onboarding server fail the request later.
+pub async fn preflight<C: AsRef<SolanaRpcClient> + GetAnchorAccount>(
+    client: &C,
+    onboarding_client: &Client,
+    hotspot: &helium_crypto::PublicKey,
+    update: &HotspotInfoUpdate,
+) -> Result<MakerFunding, Error> {
+    let Maker { name, address, .. } = onboarding_client.get_hotspot(hotspot).await?.maker;
+    let dc = token::balance_for_address(client, &Token::Dc.associated_token_adress(&address))
+        .await?
+        .map(|balance| balance.amount)
+        .unwrap_or_else(|| Token::Dc.amount(0));
+    let sol = token::balance_for_address(client, &address)
+        .await?
+        .map(|balance| balance.amount)
+        .unwrap_or_else(|| Token::Sol.amount(0));
+
+    let required = required_dc(client, update).await?;
+    if dc.amount < required {
+        return Err(OnboardingError::MakerOutOfDc {
+            maker: name,
+            balance: dc.amount,
+            required,
+        }
+        .into());
+    }
+    if sol.amount < MIN_MAKER_SOL {
+        return Err(OnboardingError::MakerOutOfSol {
+            maker: name,
+            balance: sol.amount,
+            required: MIN_MAKER_SOL,
+        }
+        .into());
     }
+    Ok(MakerFunding {
+        name,
+        address,
+        dc,
+        sol,
+    })
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -97,6 +434,10 @@ pub struct Hotspot {
     pub maker: Maker,
     pub onboarding_key: helium_crypto::PublicKey,
     pub public_address: helium_crypto::PublicKey,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -115,18 +456,69 @@ pub enum OnboardingError {
     Client(#[from] reqwest::Error),
     #[error("onboarding response: {code} {reason}")]
     Server { code: u32, reason: String },
+    #[error("onboarding record not found: {0}")]
+    NotFound(String),
+    #[error("onboarding request not authorized: {0}")]
+    Unauthorized(String),
     #[error("no data in response")]
     NoData,
     #[error("invalid data in response")]
     InvalidData,
+    #[error("maker {maker} out of DC: balance {balance}, required {required}")]
+    MakerOutOfDc {
+        maker: String,
+        balance: u64,
+        required: u64,
+    },
+    #[error("maker {maker} out of SOL: balance {balance} lamports, required {required} lamports")]
+    MakerOutOfSol {
+        maker: String,
+        balance: u64,
+        required: u64,
+    },
+}
+
+impl OnboardingError {
+    fn from_code(code: u32, reason: String) -> Self {
+        match code {
+            401 | 403 => Self::Unauthorized(reason),
+            404 => Self::NotFound(reason),
+            code => Self::Server { code, reason },
+        }
+    }
+
+    /// The HTTP status of a failed request, if any
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::Client(err) => err.status().map(|status| status.as_u16()),
+            Self::Server { code, .. } => u16::try_from(*code).ok(),
+            Self::NotFound(_) => Some(404),
+            _ => None,
+        }
+    }
+
+    /// Whether the request failed to connect, so it never reached the server
+    pub fn is_connect(&self) -> bool {
+        matches!(self, Self::Client(err) if err.is_connect())
+    }
+
+    /// Whether the request may succeed if sent again
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Client(err) if err.is_timeout() || err.is_connect() => true,
+            _ => self
+                .status()
+                .is_some_and(|status| status == 429 || (500..600).contains(&status)),
+        }
+    }
 }
 
 impl<T> From<OnboardingResponse<T>> for OnboardingError {
     fn from(value: OnboardingResponse<T>) -> Self {
-        Self::Server {
-            code: value.code,
-            reason: value.error_message.unwrap_or("unknown".to_string()),
-        }
+        Self::from_code(
+            value.code,
+            value.error_message.unwrap_or("unknown".to_string()),
+        )
     }
 }
 
@@ -145,7 +537,50 @@ struct OnboardingResponseTransactions {
     solana_transactions: Vec<OnboardingResponseTransaction>,
 }
 
+impl OnboardingResponseTransactions {
+    fn transactions(self) -> Result<Vec<solana_sdk::transaction::Transaction>, OnboardingError> {
+        self.solana_transactions
+            .iter()
+            .map(|txn| bincode::deserialize(&txn.data).map_err(|_| OnboardingError::InvalidData))
+            .collect()
+    }
+
+    fn transaction(self) -> Result<solana_sdk::transaction::Transaction, OnboardingError> {
+        self.transactions()?
+            .into_iter()
+            .next()
+            .ok_or(OnboardingError::NoData)
+    }
+}
+
 #[derive(Deserialize)]
 struct OnboardingResponseTransaction {
     data: Vec<u8>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors() {
+        let response = |code| OnboardingResponse::<()> {
+            code,
+            success: false,
+            error_message: Some("reason".to_string()),
+            data: None,
+        };
+        assert!(matches!(
+            OnboardingError::from(response(404)),
+            OnboardingError::NotFound(_)
+        ));
+        assert!(matches!(
+            OnboardingError::from(response(401)),
+            OnboardingError::Unauthorized(_)
+        ));
+        assert!(OnboardingError::from(response(503)).is_retryable());
+        assert!(OnboardingError::from(response(429)).is_retryable());
+        assert!(!OnboardingError::from(response(400)).is_retryable());
+        assert!(!OnboardingError::NoData.is_retryable());
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{
+    client::SolanaRpcClient,
+    error::Error,
+    keypair::{serde_pubkey, Keypair, Pubkey, Signer},
+    simulation,
+    solana_sdk::transaction::VersionedTransaction,
+};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PayerError {
+    #[error("no payers in payer pool")]
+    Empty,
+    #[error("no payer with at least {min_balance} lamports available")]
+    Underfunded { min_balance: u64 },
+}
+
+/// The last known balance of a payer in a [`PayerPool`]
+#[derive(Debug, Clone, Serialize)]
+pub struct PayerBalance {
+    #[serde(with = "serde_pubkey")]
+    pub address: Pubkey,
+    /// Balance in lamports, if it has been fetched
+    pub balance: Option<u64>,
+    /// Whether the payer is excluded from rotation for being underfunded
+    pub excluded: bool,
+}
+
+/// A pool of fee payer keypairs that are rotated per transaction.
+///
+/// Spreading transactions over several payers spreads fee spend and lets
+/// transactions for different payers be built and submitted in parallel.
+/// Balances are fetched with [`PayerPool::refresh`] and reduced by the fees of
+/// the transactions recorded with [`PayerPool::record_transaction`]. Payers
+/// whose balance drops below the minimum balance of the pool are skipped until
+/// a refresh shows they have been topped up.
+pub struct PayerPool {
+    payers: Vec<Arc<Keypair>>,
+    min_balance: u64,
+    next: AtomicUsize,
+    balances: RwLock<HashMap<Pubkey, u64>>,
+}
+
+impl PayerPool {
+    pub fn new(payers: Vec<Arc<Keypair>>, min_balance: u64) -> Result<Self, PayerError> {
+        if payers.is_empty() {
+            return Err(PayerError::Empty);
+        }
+        Ok(Self {
+            payers,
+            min_balance,
+            next: AtomicUsize::new(0),
+            balances: RwLock::new(HashMap::new()),
+        })
+    }
+
+    pub fn min_balance(&self) -> u64 {
+        self.min_balance
+    }
+
+    /// Fetch the current balances of all payers in the pool
+    pub async fn refresh<C: AsRef<SolanaRpcClient>>(&self, client: &C) -> Result<(), Error> {
+        let addresses = self
+            .payers
+            .iter()
+            .map(|payer| payer.pubkey())
+            .collect::<Vec<Pubkey>>();
+        let mut balances = HashMap::with_capacity(addresses.len());
+        // Chunk into documented max keys to pass to getMultipleAccounts
+        for chunk in addresses.chunks(100) {
+            let accounts = client.as_ref().get_multiple_accounts(chunk).await?;
+            for (address, account) in chunk.iter().zip(accounts) {
+                let lamports = account.map(|account| account.lamports).unwrap_or_default();
+                balances.insert(*address, lamports);
+            }
+        }
+        *self.balances.write().expect("payer balances lock poisoned") = balances;
+        Ok(())
+    }
+
+    /// The last known balance of the given payer in lamports
+    pub fn balance(&self, payer: &Pubkey) -> Option<u64> {
+        self.balances
+            .read()
+            .expect("payer balances lock poisoned")
+            .get(payer)
+            .copied()
+    }
+
+    pub fn balances(&self) -> Vec<PayerBalance> {
+        let balances = self.balances.read().expect("payer balances lock poisoned");
+        self.payers
+            .iter()
+            .map(|payer| {
+                let address = payer.pubkey();
+                let balance = balances.get(&address).copied();
+                PayerBalance {
+                    address,
+                    balance,
+                    excluded: !self.is_funded(balance),
+                }
+            })
+            .collect()
+    }
+
+    /// A payer with an unknown balance is assumed to be funded until the
+    /// pool is refreshed
+    fn is_funded(&self, balance: Option<u64>) -> bool {
+        !matches!(balance, Some(balance) if balance < self.min_balance)
+    }
+
+    /// The next funded payer in rotation
+    pub fn next_payer(&self) -> Result<Arc<Keypair>, PayerError> {
+        let balances = self.balances.read().expect("payer balances lock poisoned");
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..self.payers.len())
+            .map(|offset| &self.payers[(start + offset) % self.payers.len()])
+            .find(|payer| self.is_funded(balances.get(&payer.pubkey()).copied()))
+            .cloned()
+            .ok_or(PayerError::Underfunded {
+                min_balance: self.min_balance,
+            })
+    }
+
+    /// Deduct the given fee in lamports from the known balance of a payer
+    pub fn record_fee(&self, payer: &Pubkey, lamports: u64) {
+        let mut balances = self.balances.write().expect("payer balances lock poisoned");
+        if let Some(balance) = balances.get_mut(payer) {
+            *balance = balance.saturating_sub(lamports);
+        }
+    }
+
+    /// Deduct the expected fee of a submitted transaction from the known
+    /// balance of its fee payer
+    pub fn record_transaction(&self, txn: &VersionedTransaction) {
+        if let Some(payer) = txn.message.static_account_keys().first() {
+            self.record_fee(payer, simulation::fee(&txn.message));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(count: usize, min_balance: u64) -> PayerPool {
+        let payers = (0..count).map(|_| Arc::new(Keypair::generate())).collect();
+        PayerPool::new(payers, min_balance).expect("payer pool")
+    }
+
+    fn set_balance(pool: &PayerPool, payer: &Pubkey, lamports: u64) {
+        pool.balances.write().unwrap().insert(*payer, lamports);
+    }
+
+    #[test]
+    fn rotates_payers() {
+        let pool = pool(3, 0);
+        let picked = (0..6)
+            .map(|_| pool.next_payer().unwrap().pubkey())
+            .collect::<Vec<_>>();
+        assert_eq!(picked[..3], picked[3..]);
+        assert_ne!(picked[0], picked[1]);
+        assert_ne!(picked[1], picked[2]);
+    }
+
+    #[test]
+    fn skips_underfunded_payers() {
+        let pool = pool(2, 10_000);
+        let addresses = pool.payers.iter().map(|p| p.pubkey()).collect::<Vec<_>>();
+        set_balance(&pool, &addresses[0], 5_000);
+        set_balance(&pool, &addresses[1], 15_000);
+        for _ in 0..3 {
+            assert_eq!(addresses[1], pool.next_payer().unwrap().pubkey());
+        }
+        pool.record_fee(&addresses[1], 10_000);
+        assert!(matches!(
+            pool.next_payer(),
+            Err(PayerError::Underfunded { .. })
+        ));
+    }
+}
@@ -0,0 +1,118 @@
+//! Addresses of Helium program accounts, computed from public keys alone.
+//!
+//! Every function here is a pure derivation that needs no client or network
+//! access, so it can be used offline. The seeds of each program derived
+//! address (PDA) are listed with its function for implementations in other
+//! languages. Hashed seeds are the SHA-256 digest of
+//! the value. Mints are the mints of the given token, and unless noted the
+//! `dao` seed is the HNT DAO returned by [`dao_key`].
+use crate::{
+    boosting,
+    dao::{Dao, SubDao},
+    entity_key::AsEntityKey,
+    hexboosting,
+    keypair::Pubkey,
+    lazy_distributor,
+    reward::ClaimableToken,
+    token::Token,
+};
+
+/// The HNT DAO: `["dao", hnt mint]` in helium-sub-daos
+pub fn dao_key() -> Pubkey {
+    Dao::Hnt.key()
+}
+
+/// A subdao: `["sub_dao", subdao mint]` in helium-sub-daos
+pub fn subdao_key(subdao: SubDao) -> Pubkey {
+    subdao.key()
+}
+
+/// The data credits config: `["dc", dc mint]` in data-credits
+pub fn dc_key() -> Pubkey {
+    Dao::dc_key()
+}
+
+/// The associated token account holding the given token for an owner. SOL
+/// has no token account, the owner address is returned as is.
+pub fn associated_token_address(token: Token, owner: &Pubkey) -> Pubkey {
+    token.associated_token_adress(owner)
+}
+
+/// The key to asset account of an entity:
+/// `["key_to_asset", dao, hashed entity key]` in helium-entity-manager
+pub fn key_to_asset_key<E: AsEntityKey + ?Sized>(entity_key: &E) -> Pubkey {
+    Dao::Hnt.entity_key_to_kta_key(entity_key)
+}
+
+/// The rewardable entity config of a subdao:
+/// `["rewardable_entity_config", subdao, "IOT" | "MOBILE"]` in
+/// helium-entity-manager
+pub fn rewardable_entity_config_key(subdao: SubDao) -> Pubkey {
+    subdao.rewardable_entity_config_key()
+}
+
+/// The Hotspot info of an entity in a subdao:
+/// `["iot_info" | "mobile_info", rewardable entity config, hashed entity key]`
+/// in helium-entity-manager
+pub fn info_key<E: AsEntityKey>(subdao: SubDao, entity_key: &E) -> Pubkey {
+    subdao.info_key(entity_key)
+}
+
+/// The data-only config: `["data_only_config", dao]` in helium-entity-manager
+pub fn dataonly_config_key() -> Pubkey {
+    Dao::Hnt.dataonly_config_key()
+}
+
+/// The bubblegum tree config of a merkle tree: `[merkle tree]` in bubblegum
+pub fn tree_config_key(merkle_tree: &Pubkey) -> Pubkey {
+    Dao::Hnt.merkle_tree_authority(merkle_tree)
+}
+
+/// The lazy distributor paying out rewards in a token:
+/// `["lazy_distributor", mint]` in lazy-distributor
+pub fn lazy_distributor_key(token: ClaimableToken) -> Pubkey {
+    token.lazy_distributor_key()
+}
+
+/// The reward recipient of an asset:
+/// `["recipient", lazy distributor, asset]` in lazy-distributor
+pub fn recipient_key(token: ClaimableToken, asset: &Pubkey) -> Pubkey {
+    let (key, _) = Pubkey::find_program_address(
+        &[
+            b"recipient",
+            token.lazy_distributor_key().as_ref(),
+            asset.as_ref(),
+        ],
+        &lazy_distributor::id(),
+    );
+    key
+}
+
+/// The delegated data credits of a router key in a subdao:
+/// `["delegated_data_credits", subdao, hashed router key]` in data-credits
+pub fn delegated_dc_key(subdao: SubDao, router_key: &str) -> Pubkey {
+    subdao.delegated_dc_key(router_key)
+}
+
+/// The escrow token account of delegated data credits:
+/// `["escrow_dc_account", delegated data credits]` in data-credits
+pub fn delegated_dc_escrow_key(subdao: SubDao, delegated_dc_key: &Pubkey) -> Pubkey {
+    subdao.escrow_key(delegated_dc_key)
+}
+
+/// The hex boost config of a subdao: `["boost_config", subdao mint]` in
+/// hexboosting
+pub fn boost_config_key(subdao: SubDao) -> Pubkey {
+    boosting::boost_config_key(subdao)
+}
+
+/// The boost of a hex for a device type:
+/// `["boosted_hex", boost config, device type, little endian hex]` in
+/// hexboosting
+pub fn boosted_hex_key(
+    boost_config: &Pubkey,
+    device_type: hexboosting::DeviceTypeV0,
+    hex: u64,
+) -> Pubkey {
+    boosting::boosted_hex_key(boost_config, device_type, hex)
+}
@@ -0,0 +1,142 @@
+use crate::{
+    anchor_spl,
+    client::SolanaRpcClient,
+    error::Error,
+    keypair::{serde_pubkey, Pubkey},
+    simulation,
+    solana_sdk::{
+        message::VersionedMessage, program_pack::Pack, system_instruction::SystemInstruction,
+        system_program,
+    },
+};
+use serde::Serialize;
+
+/// The SOL a transaction takes from its fee payer, and what the fee payer
+/// holds. All amounts are in lamports.
+#[derive(Debug, Serialize, Clone)]
+pub struct Preflight {
+    #[serde(with = "serde_pubkey")]
+    pub payer: Pubkey,
+    pub balance: u64,
+    /// Signature and priority fees
+    pub fee: u64,
+    /// Rent of the associated token accounts the transaction creates
+    pub rent: u64,
+    /// SOL transferred or used to fund new accounts by the payer
+    pub transfers: u64,
+    /// Least balance a system account can be left with, other than none
+    pub min_balance: u64,
+}
+
+impl Preflight {
+    /// SOL the transaction takes from the payer
+    pub fn required(&self) -> u64 {
+        self.fee
+            .saturating_add(self.rent)
+            .saturating_add(self.transfers)
+    }
+
+    /// SOL the payer is missing for the transaction to succeed. A payer
+    /// that would be left with less than the rent exempt minimum fails the
+    /// transaction as well, unless it is left with nothing.
+    pub fn shortfall(&self) -> u64 {
+        let required = self.required();
+        match self.balance.checked_sub(required) {
+            None => required - self.balance,
+            Some(0) => 0,
+            Some(left) => self.min_balance.saturating_sub(left),
+        }
+    }
+}
+
+/// Estimate the SOL the given message takes from its fee payer.
+///
+/// Only instructions whose accounts are not loaded from lookup tables are
+/// inspected, which covers the payer funded instructions this crate builds.
+pub async fn check<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    message: &VersionedMessage,
+) -> Result<Preflight, Error> {
+    let keys = message.static_account_keys();
+    let payer = keys[0];
+    let account = |ix_index: usize, position: usize| -> Option<Pubkey> {
+        let ix = &message.instructions()[ix_index];
+        let index = ix.accounts.get(position)?;
+        keys.get(usize::from(*index)).copied()
+    };
+
+    let mut new_token_accounts = vec![];
+    let mut transfers = 0u64;
+    for (index, ix) in message.instructions().iter().enumerate() {
+        let Some(program_id) = keys.get(usize::from(ix.program_id_index)) else {
+            continue;
+        };
+        if *program_id == anchor_spl::associated_token::ID {
+            // Create and CreateIdempotent take the funding account first and
+            // the new token account second
+            if account(index, 0) == Some(payer) {
+                if let Some(token_account) = account(index, 1) {
+                    new_token_accounts.push(token_account);
+                }
+            }
+        } else if *program_id == system_program::ID && account(index, 0) == Some(payer) {
+            match bincode::deserialize::<SystemInstruction>(&ix.data) {
+                Ok(SystemInstruction::Transfer { lamports })
+                | Ok(SystemInstruction::CreateAccount { lamports, .. }) => {
+                    transfers = transfers.saturating_add(lamports)
+                }
+                _ => (),
+            }
+        }
+    }
+
+    let solana_client = client.as_ref();
+    let balance = solana_client.get_balance(&payer).await?;
+    let min_balance = solana_client
+        .get_minimum_balance_for_rent_exemption(0)
+        .await?;
+    let rent = if new_token_accounts.is_empty() {
+        0
+    } else {
+        let existing = solana_client
+            .get_multiple_accounts(&new_token_accounts)
+            .await?;
+        let missing = existing.iter().filter(|account| account.is_none()).count() as u64;
+        let token_account_rent = solana_client
+            .get_minimum_balance_for_rent_exemption(
+                anchor_spl::token::spl_token::state::Account::LEN,
+            )
+            .await?;
+        missing * token_account_rent
+    };
+
+    Ok(Preflight {
+        payer,
+        balance,
+        fee: simulation::fee(message),
+        rent,
+        transfers,
+        min_balance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortfall() {
+        let preflight = |balance| Preflight {
+            payer: Pubkey::new_unique(),
+            balance,
+            fee: 5_000,
+            rent: 2_000_000,
+            transfers: 0,
+            min_balance: 890_880,
+        };
+        assert_eq!(preflight(1_000_000).shortfall(), 1_005_000);
+        assert_eq!(preflight(2_005_000).shortfall(), 0);
+        assert_eq!(preflight(2_105_000).shortfall(), 790_880);
+        assert_eq!(preflight(3_000_000).shortfall(), 0);
+    }
+}
@@ -15,6 +15,10 @@ pub async fn get_estimate<C: AsRef<SolanaRpcClient>>(
     accounts: &impl ToAccountMetas,
     fee_range: RangeInclusive<u64>,
 ) -> Result<u64, Error> {
+    // Any estimate would be clamped to the single fee in the range
+    if fee_range.start() == fee_range.end() {
+        return Ok(*fee_range.start());
+    }
     let client_url = client.as_ref().url();
     if client_url.contains("mainnet.helius") {
         helius::get_estimate(client, accounts, fee_range).await
@@ -0,0 +1,366 @@
+//! Decoding of custom program errors into named errors.
+//!
+//! A failed instruction only reports a numeric custom error code. The errors
+//! of the Helium programs are named from the error table of the program's
+//! Anchor IDL, which the programs publish on chain, and errors raised by the
+//! Anchor framework itself from its fixed table. Codes neither table knows
+//! are named from the error log line Anchor programs write, when the logs
+//! are available.
+use crate::{
+    anchor_lang::{idl::IdlAccount, AccountDeserialize},
+    circuit_breaker,
+    client::SolanaRpcClient,
+    data_credits,
+    error::{DecodeError, Error},
+    helium_entity_manager,
+    keypair::{serde_pubkey, Pubkey},
+    lazy_distributor,
+    message::VersionedMessage,
+    solana_sdk::{instruction::InstructionError, transaction::TransactionError},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io::Read,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+/// Errors of the Anchor framework shared by all Anchor programs, by code
+const ANCHOR_ERRORS: &[(u32, &str, &str)] = &[
+    (2000, "ConstraintMut", "A mut constraint was violated"),
+    (
+        2001,
+        "ConstraintHasOne",
+        "A has one constraint was violated",
+    ),
+    (2002, "ConstraintSigner", "A signer constraint was violated"),
+    (2003, "ConstraintRaw", "A raw constraint was violated"),
+    (2004, "ConstraintOwner", "An owner constraint was violated"),
+    (2006, "ConstraintSeeds", "A seeds constraint was violated"),
+    (
+        2012,
+        "ConstraintAddress",
+        "An address constraint was violated",
+    ),
+    (
+        2014,
+        "ConstraintTokenMint",
+        "A token mint constraint was violated",
+    ),
+    (
+        2015,
+        "ConstraintTokenOwner",
+        "A token owner constraint was violated",
+    ),
+    (
+        3001,
+        "AccountDiscriminatorNotFound",
+        "No 8 byte discriminator was found on the account",
+    ),
+    (
+        3002,
+        "AccountDiscriminatorMismatch",
+        "8 byte discriminator did not match what was expected",
+    ),
+    (
+        3003,
+        "AccountDidNotDeserialize",
+        "Failed to deserialize the account",
+    ),
+    (
+        3007,
+        "AccountOwnedByWrongProgram",
+        "The given account is owned by a different program than expected",
+    ),
+    (3010, "AccountNotSigner", "The given account did not sign"),
+    (
+        3012,
+        "AccountNotInitialized",
+        "The program expected this account to be already initialized",
+    ),
+];
+
+/// Name of the given Helium program, if it is one
+pub fn program_name(program_id: &Pubkey) -> Option<&'static str> {
+    [
+        (helium_entity_manager::id(), "helium-entity-manager"),
+        (data_credits::id(), "data-credits"),
+        (lazy_distributor::id(), "lazy-distributor"),
+        (circuit_breaker::id(), "circuit-breaker"),
+    ]
+    .into_iter()
+    .find_map(|(id, name)| (id == *program_id).then_some(name))
+}
+
+/// The custom errors of a program, by code, as listed in its IDL
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProgramErrors(HashMap<u32, (String, String)>);
+
+impl ProgramErrors {
+    /// Read the error table from the JSON of an Anchor IDL
+    pub fn from_idl_json(json: &[u8]) -> Result<Self, DecodeError> {
+        #[derive(Deserialize)]
+        struct Idl {
+            #[serde(default)]
+            errors: Vec<IdlError>,
+        }
+        #[derive(Deserialize)]
+        struct IdlError {
+            code: u32,
+            name: String,
+            #[serde(default)]
+            msg: Option<String>,
+        }
+        let idl: Idl = serde_json::from_slice(json).map_err(DecodeError::other)?;
+        Ok(Self(
+            idl.errors
+                .into_iter()
+                .map(|error| {
+                    let message = error.msg.unwrap_or_default();
+                    (error.code, (error.name, message))
+                })
+                .collect(),
+        ))
+    }
+
+    /// Read the error table from the data of an on chain Anchor IDL account,
+    /// which holds the zlib compressed JSON of the IDL
+    pub fn from_idl_account(data: &[u8]) -> Result<Self, DecodeError> {
+        let account = IdlAccount::try_deserialize(&mut &data[..]).map_err(DecodeError::other)?;
+        // Discriminator, authority and data length precede the IDL
+        let compressed = data
+            .get(44..)
+            .and_then(|idl| idl.get(..usize::try_from(account.data_len).ok()?))
+            .ok_or_else(|| DecodeError::other("idl account too short"))?;
+        let mut json = vec![];
+        flate2::read::ZlibDecoder::new(compressed).read_to_end(&mut json)?;
+        Self::from_idl_json(&json)
+    }
+
+    pub fn get(&self, code: u32) -> Option<(&str, &str)> {
+        self.0
+            .get(&code)
+            .map(|(name, message)| (name.as_str(), message.as_str()))
+    }
+}
+
+/// Error tables of the Helium programs fetched so far, by program
+fn error_tables() -> &'static Mutex<HashMap<Pubkey, Arc<ProgramErrors>>> {
+    static TABLES: OnceLock<Mutex<HashMap<Pubkey, Arc<ProgramErrors>>>> = OnceLock::new();
+    TABLES.get_or_init(Default::default)
+}
+
+/// The error table of the given Helium program, read from the IDL account
+/// the program published. Tables are fetched once per process. Programs
+/// that are not Helium programs have no table.
+pub async fn program_errors<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    program_id: &Pubkey,
+) -> Result<Option<Arc<ProgramErrors>>, Error> {
+    if program_name(program_id).is_none() {
+        return Ok(None);
+    }
+    if let Some(errors) = error_tables()
+        .lock()
+        .expect("error tables lock")
+        .get(program_id)
+    {
+        return Ok(Some(errors.clone()));
+    }
+    let account = client
+        .as_ref()
+        .get_account(&IdlAccount::address(program_id))
+        .await?;
+    let errors = Arc::new(ProgramErrors::from_idl_account(&account.data)?);
+    error_tables()
+        .lock()
+        .expect("error tables lock")
+        .insert(*program_id, errors.clone());
+    Ok(Some(errors))
+}
+
+/// The program of the instruction the given transaction error is a custom
+/// program error of
+pub fn failed_program(message: &VersionedMessage, err: &TransactionError) -> Option<Pubkey> {
+    let TransactionError::InstructionError(instruction, InstructionError::Custom(_)) = err else {
+        return None;
+    };
+    let compiled = message.instructions().get(usize::from(*instruction))?;
+    message
+        .static_account_keys()
+        .get(usize::from(compiled.program_id_index))
+        .copied()
+}
+
+/// A custom program error an instruction of a transaction failed with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DecodedError {
+    #[serde(with = "serde_pubkey")]
+    pub program_id: Pubkey,
+    /// Name of the program, for Helium programs
+    pub program: Option<&'static str>,
+    /// Index of the failed instruction in the transaction
+    pub instruction: u8,
+    pub code: u32,
+    pub name: Option<String>,
+    pub message: Option<String>,
+}
+
+impl std::fmt::Display for DecodedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.program {
+            Some(program) => write!(f, "{program}")?,
+            None => write!(f, "program {}", self.program_id)?,
+        }
+        write!(f, " instruction {} failed with ", self.instruction)?;
+        match &self.name {
+            Some(name) => write!(f, "{name} ({})", self.code)?,
+            None => write!(f, "custom error {}", self.code)?,
+        }
+        match &self.message {
+            Some(message) => write!(f, ": {message}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for DecodedError {}
+
+/// Decode the custom program error the given transaction error is for, using
+/// the transaction message to find the failed program. The error is named
+/// from the given error table of the program, the Anchor framework errors
+/// or, for codes neither knows, the given logs. Other transaction errors are
+/// not decoded.
+pub fn decode(
+    message: &VersionedMessage,
+    err: &TransactionError,
+    errors: Option<&ProgramErrors>,
+    logs: &[String],
+) -> Option<DecodedError> {
+    let TransactionError::InstructionError(instruction, InstructionError::Custom(code)) = err
+    else {
+        return None;
+    };
+    let program_id = failed_program(message, err)?;
+    let (name, error_message) = errors
+        .and_then(|errors| errors.get(*code))
+        .or_else(|| {
+            ANCHOR_ERRORS
+                .iter()
+                .find(|(anchor_code, _, _)| anchor_code == code)
+                .map(|(_, name, message)| (*name, *message))
+        })
+        .map(|(name, message)| (name.to_string(), message.to_string()))
+        .or_else(|| from_logs(*code, logs))
+        .map(|(name, message)| (Some(name), Some(message).filter(|msg| !msg.is_empty())))
+        .unwrap_or_default();
+    Some(DecodedError {
+        program_id,
+        program: program_name(&program_id),
+        instruction: *instruction,
+        code: *code,
+        name,
+        message: error_message,
+    })
+}
+
+/// Find the name and message of the given error code in an Anchor error
+/// log line, like "Program log: AnchorError occurred. Error Code: NotEnoughDc.
+/// Error Number: 6003. Error Message: Not enough DC."
+fn from_logs(code: u32, logs: &[String]) -> Option<(String, String)> {
+    let number = format!(". Error Number: {code}. Error Message: ");
+    logs.iter()
+        .filter(|log| log.contains("AnchorError"))
+        .find_map(|log| {
+            let (head, message) = log.split_once(&number)?;
+            let (_, name) = head.rsplit_once("Error Code: ")?;
+            Some((
+                name.to_string(),
+                message.trim_end().trim_end_matches('.').to_string(),
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        message::legacy,
+    };
+
+    #[test]
+    fn decode_errors() {
+        let payer = Pubkey::new_unique();
+        let ix = Instruction::new_with_bytes(
+            data_credits::id(),
+            &[],
+            vec![AccountMeta::new(payer, true)],
+        );
+        let message = VersionedMessage::Legacy(legacy::Message::new(&[ix], Some(&payer)));
+        let err = TransactionError::InstructionError(0, InstructionError::Custom(6004));
+        let logs = vec![
+            "Program log: Instruction: MintDataCreditsV0".to_string(),
+            "Program log: AnchorError thrown in programs/data-credits/src/lib.rs:10. Error Code: PythPriceNotFound. Error Number: 6004. Error Message: Pyth price is not available.".to_string(),
+        ];
+        let decoded = decode(&message, &err, None, &logs).expect("decoded");
+        assert_eq!(decoded.program, Some("data-credits"));
+        assert_eq!(decoded.name.as_deref(), Some("PythPriceNotFound"));
+        assert_eq!(
+            decoded.to_string(),
+            "data-credits instruction 0 failed with PythPriceNotFound (6004): Pyth price is not available"
+        );
+
+        let decoded = decode(&message, &err, None, &[]).expect("decoded");
+        assert_eq!(decoded.name, None);
+        assert_eq!(
+            decoded.to_string(),
+            "data-credits instruction 0 failed with custom error 6004"
+        );
+
+        // The IDL table takes precedence over the logs
+        let errors = ProgramErrors::from_idl_json(
+            br#"{"errors": [{"code": 6004, "name": "PriceNotFound", "msg": "No price"}]}"#,
+        )
+        .expect("errors");
+        let decoded = decode(&message, &err, Some(&errors), &logs).expect("decoded");
+        assert_eq!(decoded.name.as_deref(), Some("PriceNotFound"));
+        assert_eq!(decoded.message.as_deref(), Some("No price"));
+
+        let unknown = TransactionError::InstructionError(0, InstructionError::Custom(6005));
+        let decoded = decode(&message, &unknown, Some(&errors), &[]).expect("decoded");
+        assert_eq!(decoded.name, None);
+
+        let seeds = TransactionError::InstructionError(0, InstructionError::Custom(2006));
+        let decoded = decode(&message, &seeds, Some(&errors), &[]).expect("decoded");
+        assert_eq!(decoded.name.as_deref(), Some("ConstraintSeeds"));
+
+        assert!(decode(&message, &TransactionError::AccountNotFound, None, &logs).is_none());
+    }
+
+    #[test]
+    fn idl_account() {
+        use crate::anchor_lang::Discriminator;
+        use flate2::{write::ZlibEncoder, Compression};
+        use std::io::Write;
+
+        let json =
+            br#"{"name": "data_credits", "errors": [{"code": 6000, "name": "BumpNotAvailable"}]}"#;
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(json).expect("compressed");
+        let compressed = encoder.finish().expect("compressed");
+
+        let mut data = IdlAccount::discriminator().to_vec();
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        data.extend_from_slice(&compressed);
+        // Accounts are allocated larger than the IDL they hold
+        data.extend_from_slice(&[0; 16]);
+
+        let errors = ProgramErrors::from_idl_account(&data).expect("errors");
+        assert_eq!(errors.get(6000), Some(("BumpNotAvailable", "")));
+        assert_eq!(errors.get(6001), None);
+
+        assert!(ProgramErrors::from_idl_account(&data[..50]).is_err());
+    }
+}
@@ -7,3 +7,6 @@ pub const SPL_ACCOUNT_COMPRESSION_PROGRAM_ID: Pubkey =
     pubkey!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
 
 pub const SPL_NOOP_PROGRAM_ID: Pubkey = pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
+pub const VOTER_STAKE_REGISTRY_PROGRAM_ID: Pubkey =
+    pubkey!("hvsrNC3NKbcryqDs2DocYHZ9yPKEVzdSjQG6RVtK1s8");
@@ -1,18 +1,20 @@
 use crate::{
     anchor_lang::{InstructionData, ToAccountMetas},
     asset, circuit_breaker,
-    client::{DasClient, GetAnchorAccount, SolanaRpcClient},
+    client::{DasClient, GetAnchorAccount, GetAsset, SolanaRpcClient},
     dao::Dao,
     entity_key::{self, AsEntityKey, KeySerialization},
-    error::{DecodeError, EncodeError, Error},
-    helium_entity_manager,
+    error::{DecodeError, EncodeError, Error, OracleError},
+    helium_entity_manager, hotspot,
     keypair::{Keypair, Pubkey},
-    kta, lazy_distributor, message, mk_transaction_with_blockhash, priority_fee,
+    kta, lazy_distributor, message, mk_transaction_with_blockhash, mk_transaction_with_nonce,
+    payer::PayerPool,
+    priority_fee,
     programs::SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
     rewards_oracle,
     solana_client::rpc_client::SerializableTransaction,
     solana_sdk::{
-        instruction::Instruction,
+        instruction::{AccountMeta, Instruction},
         signer::Signer,
         transaction::{Transaction, VersionedTransaction},
     },
@@ -22,12 +24,30 @@ use crate::{
 use chrono::Utc;
 use futures::{
     stream::{self, StreamExt, TryStreamExt},
-    TryFutureExt,
+    Stream, TryFutureExt,
 };
 use itertools::{izip, Itertools};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+#[derive(Debug, thiserror::Error)]
+pub enum RewardError {
+    #[error("asset {asset} not owned by {owner}")]
+    NotOwned { asset: Pubkey, owner: Pubkey },
+    #[error("claim requires the asset owner to sign")]
+    OwnerSignatureRequired,
+    #[error("claim does not pay out to destination account {0}")]
+    UnexpectedDestination(Pubkey),
+    #[error("split claims must be paid out to the paying wallet")]
+    SplitNotToPayer,
+    #[error("invalid split: {0}")]
+    InvalidSplit(&'static str),
+    #[error("percent max claim threshold not supported")]
+    UnsupportedThreshold,
+    #[error("invalid reward value {0}")]
+    InvalidValue(String),
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct Oracle {
     #[serde(with = "crate::keypair::serde_pubkey")]
@@ -44,6 +64,18 @@ impl From<lazy_distributor::OracleConfigV0> for Oracle {
     }
 }
 
+/// The result of probing an oracle of a lazy distributor
+#[derive(Debug, Serialize, Clone)]
+pub struct OracleHealth {
+    #[serde(flatten)]
+    pub oracle: Oracle,
+    pub index: u16,
+    pub healthy: bool,
+    /// Time taken by the probe request in milliseconds
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct OracleReward {
     pub oracle: Oracle,
@@ -86,15 +118,7 @@ impl ClaimableToken {
         key
     }
     pub fn receipient_key_from_kta(&self, kta: &helium_entity_manager::KeyToAssetV0) -> Pubkey {
-        let (key, _) = Pubkey::find_program_address(
-            &[
-                b"recipient",
-                self.lazy_distributor_key().as_ref(),
-                kta.asset.as_ref(),
-            ],
-            &lazy_distributor::id(),
-        );
-        key
+        crate::pda::recipient_key(*self, &kta.asset)
     }
 }
 
@@ -155,7 +179,7 @@ pub async fn max_claim<C: GetAnchorAccount>(
             threshold,
             ..
         } => threshold,
-        _ => return Err(DecodeError::other("percent max claim threshold not supported").into()),
+        _ => return Err(RewardError::UnsupportedThreshold.into()),
     };
     let remaining = time_decay_previous_value(
         &circuit_breaker_account.config,
@@ -166,7 +190,7 @@ pub async fn max_claim<C: GetAnchorAccount>(
     Ok(Token::from(token).amount(threshold - remaining))
 }
 
-async fn set_current_rewards_instruction(
+fn set_current_rewards_instruction(
     token: ClaimableToken,
     kta_key: Pubkey,
     kta: &helium_entity_manager::KeyToAssetV0,
@@ -197,7 +221,7 @@ async fn set_current_rewards_instruction(
     Ok(ix)
 }
 
-pub async fn distribute_rewards_instruction<C: AsRef<DasClient> + GetAnchorAccount>(
+pub async fn distribute_rewards_instruction<C: GetAsset + GetAnchorAccount>(
     client: &C,
     token: ClaimableToken,
     kta: &helium_entity_manager::KeyToAssetV0,
@@ -207,6 +231,28 @@ pub async fn distribute_rewards_instruction<C: AsRef<DasClient> + GetAnchorAccou
     payer: Pubkey,
 ) -> Result<Instruction, Error> {
     let ld_account = lazy_distributor(client, token).await?;
+    distribute_instruction(
+        token,
+        &ld_account,
+        kta,
+        destination_account,
+        asset,
+        asset_proof,
+        payer,
+    )
+}
+
+/// The instruction distributing the rewards of a compressed asset, to the
+/// owner of the asset or the given custom destination
+fn distribute_instruction(
+    token: ClaimableToken,
+    ld_account: &lazy_distributor::LazyDistributorV0,
+    kta: &helium_entity_manager::KeyToAssetV0,
+    destination_account: Option<Pubkey>,
+    asset: &asset::Asset,
+    asset_proof: &asset::AssetProof,
+    payer: Pubkey,
+) -> Result<Instruction, Error> {
     macro_rules! mk_common {
         ($name: ident, $dest_account: expr) => {
             $name {
@@ -219,7 +265,7 @@ pub async fn distribute_rewards_instruction<C: AsRef<DasClient> + GetAnchorAccou
                 token_program: anchor_spl::token::ID,
                 circuit_breaker_program: circuit_breaker::id(),
                 owner: asset.ownership.owner,
-                circuit_breaker: lazy_distributor_circuit_breaker(&ld_account),
+                circuit_breaker: lazy_distributor_circuit_breaker(ld_account),
                 recipient: token.receipient_key_from_kta(kta),
                 destination_account: Token::from(token).associated_token_adress($dest_account),
             }
@@ -262,23 +308,370 @@ pub async fn distribute_rewards_instruction<C: AsRef<DasClient> + GetAnchorAccou
         .data(),
     };
 
-    ix.accounts.extend_from_slice(&asset_proof.proof(Some(3))?);
+    ix.accounts
+        .extend_from_slice(&asset_proof.proof(Some(size::CLAIM_PROOF_LEN))?);
     Ok(ix)
 }
 
-pub async fn claim<C: AsRef<DasClient> + AsRef<SolanaRpcClient> + GetAnchorAccount>(
+/// The instructions claiming the given reward of an asset: initializing the
+/// recipient of the asset, if asked to, setting the current rewards of the
+/// recipient and distributing them
+#[allow(clippy::too_many_arguments)]
+fn claim_instructions(
+    token: ClaimableToken,
+    ld_account: &lazy_distributor::LazyDistributorV0,
+    kta_key: Pubkey,
+    kta: &helium_entity_manager::KeyToAssetV0,
+    reward: &OracleReward,
+    asset: &asset::Asset,
+    asset_proof: &asset::AssetProof,
+    init_recipient: bool,
+    destination: Option<Pubkey>,
+    payer: &Pubkey,
+) -> Result<Vec<Instruction>, Error> {
+    let mut ixs = vec![];
+    if init_recipient {
+        ixs.push(recipient::mk_init_instruction(
+            token,
+            kta,
+            asset,
+            asset_proof,
+            payer,
+        )?);
+    }
+    ixs.push(set_current_rewards_instruction(
+        token, kta_key, kta, reward,
+    )?);
+    ixs.push(distribute_instruction(
+        token,
+        ld_account,
+        kta,
+        destination,
+        asset,
+        asset_proof,
+        *payer,
+    )?);
+    Ok(ixs)
+}
+
+/// Where the rewards of an asset are paid to when claimed
+#[derive(Debug, Serialize, Clone)]
+pub struct ClaimRouting {
+    #[serde(with = "crate::keypair::serde_pubkey")]
+    pub owner: Pubkey,
+    /// The wallet receiving the rewards: the owner, or the custom destination
+    /// set for the asset
+    #[serde(with = "crate::keypair::serde_pubkey")]
+    pub destination: Pubkey,
+    /// The token account of the destination wallet the rewards are paid into
+    #[serde(with = "crate::keypair::serde_pubkey")]
+    pub destination_account: Pubkey,
+    pub custom_destination: bool,
+    /// Whether the rewards recipient account of the asset exists. A claim
+    /// initializes missing recipients at the expense of the payer.
+    pub recipient_initialized: bool,
+}
+
+impl ClaimRouting {
+    /// Check that the given claim transaction can be submitted by the given
+    /// payer alone and pays out to the expected destination account
+    pub fn check_transaction(&self, txn: &Transaction, payer: &Pubkey) -> Result<(), Error> {
+        let message = &txn.message;
+        let signers = &message.account_keys[..usize::from(message.header.num_required_signatures)];
+        if self.owner != *payer && signers.contains(&self.owner) {
+            return Err(RewardError::OwnerSignatureRequired.into());
+        }
+        if !message.account_keys.contains(&self.destination_account) {
+            return Err(RewardError::UnexpectedDestination(self.destination_account).into());
+        }
+        Ok(())
+    }
+}
+
+/// Look up where the rewards for the given entity would be paid to by a claim
+pub async fn claim_routing<C: GetAsset + GetAnchorAccount>(
     client: &C,
     token: ClaimableToken,
-    amount: Option<u64>,
+    encoded_entity_key: &entity_key::EncodedEntityKey,
+) -> Result<ClaimRouting, Error> {
+    let entity_key = encoded_entity_key.as_entity_key()?;
+    let kta = kta::for_entity_key(&entity_key).await?;
+    let (asset, recipient) = futures::try_join!(
+        asset::for_kta(client, &kta),
+        recipient::for_kta(client, token, &kta)
+    )?;
+    let owner = asset.ownership.owner;
+    let custom_destination = recipient
+        .as_ref()
+        .map(|recipient| recipient.destination)
+        .filter(|destination| *destination != Pubkey::default());
+    let destination = custom_destination.unwrap_or(owner);
+    Ok(ClaimRouting {
+        owner,
+        destination,
+        destination_account: Token::from(token).associated_token_adress(&destination),
+        custom_destination: custom_destination.is_some(),
+        recipient_initialized: recipient.is_some(),
+    })
+}
+
+/// A reward claim decoded from its transaction
+#[derive(Debug, Serialize, Clone)]
+pub struct ClaimSummary {
+    #[serde(with = "crate::keypair::serde_pubkey")]
+    pub recipient: Pubkey,
+    /// Lifetime rewards of the recipient as signed by the oracle. The claim
+    /// pays out the difference to the rewards already claimed.
+    pub lifetime_rewards: u64,
+    /// The token account the rewards are paid into
+    #[serde(with = "crate::keypair::serde_pubkey")]
+    pub destination_account: Pubkey,
+    /// Whether the claim initializes the recipient account
+    pub init_recipient: bool,
+    /// The durable nonce account the claim is built on
+    #[serde(with = "crate::keypair::serde_opt_pubkey")]
+    pub nonce_account: Option<Pubkey>,
+    /// Expected fee in lamports, including the priority fee
+    pub fee: u64,
+}
+
+/// Index of the destination token account in the accounts of the distribute
+/// instructions, which share their leading accounts
+fn distribute_destination_index() -> usize {
+    let destination_account = Pubkey::new_from_array([1; 32]);
+    lazy_distributor::accounts::DistributeCompressionRewardsV0Common {
+        payer: Pubkey::default(),
+        lazy_distributor: Pubkey::default(),
+        associated_token_program: Pubkey::default(),
+        rewards_mint: Pubkey::default(),
+        rewards_escrow: Pubkey::default(),
+        system_program: Pubkey::default(),
+        token_program: Pubkey::default(),
+        circuit_breaker_program: Pubkey::default(),
+        owner: Pubkey::default(),
+        circuit_breaker: Pubkey::default(),
+        recipient: Pubkey::default(),
+        destination_account,
+    }
+    .to_account_metas(None)
+    .iter()
+    .position(|meta| meta.pubkey == destination_account)
+    .unwrap_or_default()
+}
+
+/// Decode a claim transaction built by [`claim_split_nonce_transaction`] or
+/// [`claim_transaction`] without a split.
+///
+/// Fails for any instruction other than compute budget instructions, an
+/// advance of the durable nonce as the first instruction and the lazy
+/// distributor claim instructions, so a transaction that does anything else
+/// with the signature of the owner is rejected.
+pub fn decode_claim(message: &message::VersionedMessage) -> Result<ClaimSummary, Error> {
+    use crate::{
+        anchor_lang::{AnchorDeserialize, Discriminator},
+        solana_sdk::{
+            borsh1::try_from_slice_unchecked, compute_budget,
+            compute_budget::ComputeBudgetInstruction, system_instruction::SystemInstruction,
+            system_program,
+        },
+    };
+    use lazy_distributor::instruction::{
+        DistributeCompressionRewardsV0, DistributeCustomDestinationV0,
+        InitializeCompressionRecipientV0,
+    };
+    use rewards_oracle::instruction::SetCurrentRewardsWrapperV1;
+
+    let unexpected = |reason: String| -> Error { DecodeError::other(reason).into() };
+    if message
+        .address_table_lookups()
+        .is_some_and(|lookups| !lookups.is_empty())
+    {
+        return Err(unexpected(
+            "claim loads accounts from lookup tables".to_string(),
+        ));
+    }
+    let keys = message.static_account_keys();
+    let account = |ix: &solana_sdk::instruction::CompiledInstruction, index: usize| {
+        ix.accounts
+            .get(index)
+            .and_then(|key_index| keys.get(usize::from(*key_index)))
+            .copied()
+            .ok_or_else(|| unexpected(format!("claim instruction is missing account {index}")))
+    };
+
+    let mut nonce_account = None;
+    let mut init_recipient = false;
+    let mut current_rewards = None;
+    let mut distribution = None;
+    for (index, ix) in message.instructions().iter().enumerate() {
+        let program_id = keys
+            .get(usize::from(ix.program_id_index))
+            .ok_or_else(|| unexpected("claim instruction has no program".to_string()))?;
+        let discriminator = ix.data.get(..8).unwrap_or_default();
+        if *program_id == compute_budget::id() {
+            match try_from_slice_unchecked::<ComputeBudgetInstruction>(&ix.data) {
+                Ok(
+                    ComputeBudgetInstruction::SetComputeUnitLimit(_)
+                    | ComputeBudgetInstruction::SetComputeUnitPrice(_),
+                ) => continue,
+                _ => {
+                    return Err(unexpected(
+                        "unexpected compute budget instruction".to_string(),
+                    ))
+                }
+            }
+        } else if *program_id == system_program::id() && index == 0 {
+            match bincode::deserialize::<SystemInstruction>(&ix.data) {
+                Ok(SystemInstruction::AdvanceNonceAccount) => nonce_account = Some(account(ix, 0)?),
+                _ => return Err(unexpected("unexpected system instruction".to_string())),
+            }
+        } else if *program_id == rewards_oracle::id()
+            && discriminator == SetCurrentRewardsWrapperV1::DISCRIMINATOR
+            && current_rewards.is_none()
+        {
+            let args =
+                rewards_oracle::SetCurrentRewardsWrapperArgsV1::deserialize(&mut &ix.data[8..])
+                    .map_err(DecodeError::from)?;
+            // The recipient is the third account of the instruction
+            current_rewards = Some((account(ix, 2)?, args.current_rewards));
+        } else if *program_id == lazy_distributor::id()
+            && discriminator == InitializeCompressionRecipientV0::DISCRIMINATOR
+        {
+            init_recipient = true;
+        } else if *program_id == lazy_distributor::id()
+            && (discriminator == DistributeCompressionRewardsV0::DISCRIMINATOR
+                || discriminator == DistributeCustomDestinationV0::DISCRIMINATOR)
+            && distribution.is_none()
+        {
+            distribution = Some(account(ix, distribute_destination_index())?);
+        } else {
+            return Err(unexpected(format!(
+                "claim has an unexpected instruction for program {program_id}"
+            )));
+        }
+    }
+    let (Some((recipient, lifetime_rewards)), Some(destination_account)) =
+        (current_rewards, distribution)
+    else {
+        return Err(unexpected("transaction is not a reward claim".to_string()));
+    };
+    Ok(ClaimSummary {
+        recipient,
+        lifetime_rewards,
+        destination_account,
+        init_recipient,
+        nonce_account,
+        fee: crate::simulation::fee(message),
+    })
+}
+
+/// Basis points making up the whole of a claimed amount
+pub const SPLIT_BASIS_POINTS: u16 = 10_000;
+/// Compute units budgeted for paying out a single share of a split claim
+const SPLIT_SHARE_BUDGET: u32 = 40_000;
+/// Compute units budgeted for a claim, without initializing its recipient or
+/// paying out splits
+const CLAIM_BUDGET: u32 = 200_000;
+
+/// A share of claimed rewards that is passed on to another wallet
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct RewardShare {
+    #[serde(with = "crate::keypair::serde_pubkey")]
+    pub destination: Pubkey,
+    /// Share of the claimed amount, in basis points
+    pub basis_points: u16,
+}
+
+/// Divides claimed rewards between the claiming wallet and other wallets.
+///
+/// Each share is transferred out of the claimed amount in the same
+/// transaction as the claim. Whatever is left after the shares stays with the
+/// claiming wallet.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct RewardSplit {
+    pub shares: Vec<RewardShare>,
+}
+
+impl RewardSplit {
+    pub fn new(shares: Vec<RewardShare>) -> Result<Self, Error> {
+        if shares.iter().any(|share| share.basis_points == 0) {
+            return Err(RewardError::InvalidSplit("shares must not be empty").into());
+        }
+        if shares
+            .iter()
+            .map(|share| share.destination)
+            .unique()
+            .count()
+            != shares.len()
+        {
+            return Err(RewardError::InvalidSplit("destinations must be unique").into());
+        }
+        let total: u32 = shares
+            .iter()
+            .map(|share| u32::from(share.basis_points))
+            .sum();
+        if total > u32::from(SPLIT_BASIS_POINTS) {
+            return Err(RewardError::InvalidSplit("shares exceed the claimed amount").into());
+        }
+        Ok(Self { shares })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shares.is_empty()
+    }
+
+    /// The transfers paying out the shares of the given claimed amount.
+    /// Shares are rounded down, shares that round to nothing are left out.
+    pub fn transfers(&self, token: Token, amount: u64) -> Vec<(Pubkey, TokenAmount)> {
+        self.shares
+            .iter()
+            .filter_map(|share| {
+                let share_amount = u128::from(amount) * u128::from(share.basis_points)
+                    / u128::from(SPLIT_BASIS_POINTS);
+                let share_amount = u64::try_from(share_amount).ok()?;
+                (share_amount > 0).then(|| (share.destination, token.amount(share_amount)))
+            })
+            .collect()
+    }
+}
+
+pub async fn claim<C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount>(
+    client: &C,
+    token: ClaimableToken,
+    amount: Option<TokenAmount>,
     encoded_entity_key: &entity_key::EncodedEntityKey,
     keypair: &Keypair,
     opts: &TransactionOpts,
 ) -> Result<Option<(VersionedTransaction, u64)>, Error> {
-    let Some((mut txn, block_height)) = claim_transaction(
+    claim_split(
+        client,
+        token,
+        amount,
+        encoded_entity_key,
+        &RewardSplit::default(),
+        keypair,
+        opts,
+    )
+    .await
+}
+
+/// Claim rewards and pay out the shares of the given split from the claimed
+/// amount in the same transaction.
+pub async fn claim_split<C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount>(
+    client: &C,
+    token: ClaimableToken,
+    amount: Option<TokenAmount>,
+    encoded_entity_key: &entity_key::EncodedEntityKey,
+    split: &RewardSplit,
+    keypair: &Keypair,
+    opts: &TransactionOpts,
+) -> Result<Option<(VersionedTransaction, u64)>, Error> {
+    let Some((mut txn, block_height)) = claim_split_transaction(
         client,
         token,
         amount,
         encoded_entity_key,
+        split,
         &keypair.pubkey(),
         opts,
     )
@@ -291,12 +684,126 @@ pub async fn claim<C: AsRef<DasClient> + AsRef<SolanaRpcClient> + GetAnchorAccou
     Ok(Some((txn.into(), block_height)))
 }
 
-pub async fn claim_transaction<C: AsRef<DasClient> + AsRef<SolanaRpcClient> + GetAnchorAccount>(
+/// Claim rewards with the fees paid by the next funded payer of the given
+/// pool. The owner keypair only signs the claim if it needs its signature.
+pub async fn claim_with_payers<C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount>(
     client: &C,
     token: ClaimableToken,
-    amount: Option<u64>,
+    amount: Option<TokenAmount>,
+    encoded_entity_key: &entity_key::EncodedEntityKey,
+    keypair: &Keypair,
+    payers: &PayerPool,
+    opts: &TransactionOpts,
+) -> Result<Option<(VersionedTransaction, u64)>, Error> {
+    let payer = payers.next_payer()?;
+    let Some((mut txn, block_height)) = claim_transaction(
+        client,
+        token,
+        amount,
+        encoded_entity_key,
+        &payer.pubkey(),
+        opts,
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let blockhash = *txn.get_recent_blockhash();
+    txn.try_partial_sign(&[payer.as_ref()], blockhash)?;
+    let signers = &txn.message.account_keys[..txn.message.header.num_required_signatures as usize];
+    if signers.contains(&keypair.pubkey()) {
+        txn.try_partial_sign(&[keypair], blockhash)?;
+    }
+    Ok(Some((txn.into(), block_height)))
+}
+
+pub async fn claim_transaction<C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount>(
+    client: &C,
+    token: ClaimableToken,
+    amount: Option<TokenAmount>,
+    encoded_entity_key: &entity_key::EncodedEntityKey,
+    payer: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<Option<(Transaction, u64)>, Error> {
+    claim_split_transaction(
+        client,
+        token,
+        amount,
+        encoded_entity_key,
+        &RewardSplit::default(),
+        payer,
+        opts,
+    )
+    .await
+}
+
+/// Build a claim transaction that pays out the shares of the given split from
+/// the claimed amount.
+///
+/// The shares are transferred by the wallet the rewards are paid out to, so
+/// a split claim has to be paid for by that wallet.
+pub async fn claim_split_transaction<C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount>(
+    client: &C,
+    token: ClaimableToken,
+    amount: Option<TokenAmount>,
+    encoded_entity_key: &entity_key::EncodedEntityKey,
+    split: &RewardSplit,
+    payer: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<Option<(Transaction, u64)>, Error> {
+    claim_split_transaction_on(
+        client,
+        token,
+        amount,
+        encoded_entity_key,
+        split,
+        payer,
+        None,
+        opts,
+    )
+    .await
+}
+
+/// Like [`claim_split_transaction`], built on the given durable nonce account
+/// instead of a recent blockhash so the claim can be signed long after it was
+/// built. The payer has to be the authority of the nonce account.
+#[allow(clippy::too_many_arguments)]
+pub async fn claim_split_nonce_transaction<
+    C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount,
+>(
+    client: &C,
+    token: ClaimableToken,
+    amount: Option<TokenAmount>,
+    encoded_entity_key: &entity_key::EncodedEntityKey,
+    split: &RewardSplit,
+    payer: &Pubkey,
+    nonce_account: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<Option<Transaction>, Error> {
+    let txn = claim_split_transaction_on(
+        client,
+        token,
+        amount,
+        encoded_entity_key,
+        split,
+        payer,
+        Some(nonce_account),
+        opts,
+    )
+    .await?;
+    Ok(txn.map(|(txn, _)| txn))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn claim_split_transaction_on<C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount>(
+    client: &C,
+    token: ClaimableToken,
+    amount: Option<TokenAmount>,
     encoded_entity_key: &entity_key::EncodedEntityKey,
+    split: &RewardSplit,
     payer: &Pubkey,
+    nonce_account: Option<&Pubkey>,
     opts: &TransactionOpts,
 ) -> Result<Option<(Transaction, u64)>, Error> {
     let entity_key_string = encoded_entity_key.to_string();
@@ -308,6 +815,9 @@ pub async fn claim_transaction<C: AsRef<DasClient> + AsRef<SolanaRpcClient> + Ge
     )
     .await?;
 
+    let amount = amount
+        .map(|amount| amount.amount_for(token.into()))
+        .transpose()?;
     if let Some(0) = amount {
         return Ok(None);
     }
@@ -336,38 +846,44 @@ pub async fn claim_transaction<C: AsRef<DasClient> + AsRef<SolanaRpcClient> + Ge
     let kta = kta::for_entity_key(&entity_key).await?;
     let (asset, asset_proof) = asset::for_kta_with_proof(client, &kta).await?;
 
-    let (init_ix, init_budget, destination) =
-        if let Some(recipient) = recipient::for_kta(client, token, &kta).await? {
-            (
-                None,
-                1,
-                (recipient.destination != Pubkey::default()).then_some(recipient.destination),
-            )
-        } else {
-            let ix = recipient::init_instruction(token, &kta, &asset, &asset_proof, payer).await?;
-            (Some(ix), recipient::INIT_INSTRUCTION_BUDGET, None)
-        };
-    let set_current_ix =
-        set_current_rewards_instruction(token, kta_key, &kta, &lifetime_rewards).await?;
-    let distribute_ix = distribute_rewards_instruction(
-        client,
+    let recipient = recipient::for_kta(client, token, &kta).await?;
+    let (init_budget, destination) = match &recipient {
+        Some(recipient) => (1, recipient::custom_destination(recipient)),
+        None => (recipient::INIT_INSTRUCTION_BUDGET, None),
+    };
+    let split_ixs = if split.is_empty() {
+        vec![]
+    } else {
+        if destination.unwrap_or(asset.ownership.owner) != *payer {
+            return Err(RewardError::SplitNotToPayer.into());
+        }
+        let transfers = split.transfers(token.into(), to_claim);
+        crate::token::transfer_instructions(&transfers, None, &[], payer)?
+    };
+    let split_budget = SPLIT_SHARE_BUDGET * u32::try_from(split.shares.len()).unwrap_or(u32::MAX);
+    let ld_account = lazy_distributor(client, token).await?;
+    let mut claim_ixs = claim_instructions(
         token,
+        &ld_account,
+        kta_key,
         &kta,
-        destination,
+        &lifetime_rewards,
         &asset,
         &asset_proof,
-        *payer,
-    )
-    .await?;
-    let mut ixs_accounts = vec![];
-    if let Some(ix) = &init_ix {
-        ixs_accounts.extend_from_slice(&ix.accounts);
-    }
-    ixs_accounts.extend_from_slice(&set_current_ix.accounts);
-    ixs_accounts.extend_from_slice(&distribute_ix.accounts);
+        recipient.is_none(),
+        destination,
+        payer,
+    )?;
+    claim_ixs.extend(split_ixs);
+    let ixs_accounts: Vec<AccountMeta> = claim_ixs
+        .iter()
+        .flat_map(|ix| ix.accounts.clone())
+        .collect();
 
     let mut ixs = vec![
-        priority_fee::compute_budget_instruction(init_budget + 200_000),
+        priority_fee::compute_budget_instruction(
+            (init_budget + CLAIM_BUDGET).saturating_add(split_budget),
+        ),
         priority_fee::compute_price_instruction_for_accounts(
             client,
             &ixs_accounts,
@@ -375,16 +891,100 @@ pub async fn claim_transaction<C: AsRef<DasClient> + AsRef<SolanaRpcClient> + Ge
         )
         .await?,
     ];
-    if let Some(ix) = init_ix {
-        ixs.push(ix);
-    }
-    ixs.extend_from_slice(&[set_current_ix, distribute_ix]);
+    ixs.extend(claim_ixs);
 
-    let (txn, latest_block_height) = mk_transaction_with_blockhash(client, &ixs, payer).await?;
+    let (txn, latest_block_height) = match nonce_account {
+        // Nonce transactions do not expire at a block height
+        Some(nonce_account) => (
+            mk_transaction_with_nonce(client, &ixs, payer, nonce_account).await?,
+            u64::MAX,
+        ),
+        None => mk_transaction_with_blockhash(client, &ixs, payer).await?,
+    };
     let signed_txn = oracle_sign(&lifetime_rewards.oracle.url, txn).await?;
     Ok(Some((signed_txn, latest_block_height)))
 }
 
+/// Like [`claim`], with the amount in the smallest unit of the token
+#[deprecated(note = "use claim with a TokenAmount")]
+pub async fn claim_u64<C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount>(
+    client: &C,
+    token: ClaimableToken,
+    amount: Option<u64>,
+    encoded_entity_key: &entity_key::EncodedEntityKey,
+    keypair: &Keypair,
+    opts: &TransactionOpts,
+) -> Result<Option<(VersionedTransaction, u64)>, Error> {
+    let amount = amount.map(|amount| Token::from(token).amount(amount));
+    claim(client, token, amount, encoded_entity_key, keypair, opts).await
+}
+
+/// Like [`claim_transaction`], with the amount in the smallest unit of the
+/// token
+#[deprecated(note = "use claim_transaction with a TokenAmount")]
+pub async fn claim_transaction_u64<C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount>(
+    client: &C,
+    token: ClaimableToken,
+    amount: Option<u64>,
+    encoded_entity_key: &entity_key::EncodedEntityKey,
+    payer: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<Option<(Transaction, u64)>, Error> {
+    let amount = amount.map(|amount| Token::from(token).amount(amount));
+    claim_transaction(client, token, amount, encoded_entity_key, payer, opts).await
+}
+
+/// Like [`claim_split`], with the amount in the smallest unit of the token
+#[deprecated(note = "use claim_split with a TokenAmount")]
+pub async fn claim_split_u64<C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount>(
+    client: &C,
+    token: ClaimableToken,
+    amount: Option<u64>,
+    encoded_entity_key: &entity_key::EncodedEntityKey,
+    split: &RewardSplit,
+    keypair: &Keypair,
+    opts: &TransactionOpts,
+) -> Result<Option<(VersionedTransaction, u64)>, Error> {
+    let amount = amount.map(|amount| Token::from(token).amount(amount));
+    claim_split(
+        client,
+        token,
+        amount,
+        encoded_entity_key,
+        split,
+        keypair,
+        opts,
+    )
+    .await
+}
+
+/// Like [`claim_split_transaction`], with the amount in the smallest unit of
+/// the token
+#[deprecated(note = "use claim_split_transaction with a TokenAmount")]
+pub async fn claim_split_transaction_u64<
+    C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount,
+>(
+    client: &C,
+    token: ClaimableToken,
+    amount: Option<u64>,
+    encoded_entity_key: &entity_key::EncodedEntityKey,
+    split: &RewardSplit,
+    payer: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<Option<(Transaction, u64)>, Error> {
+    let amount = amount.map(|amount| Token::from(token).amount(amount));
+    claim_split_transaction(
+        client,
+        token,
+        amount,
+        encoded_entity_key,
+        split,
+        payer,
+        opts,
+    )
+    .await
+}
+
 pub async fn pending<C: GetAnchorAccount>(
     client: &C,
     token: ClaimableToken,
@@ -441,12 +1041,162 @@ pub async fn pending<C: GetAnchorAccount>(
     Ok(entity_key_rewards)
 }
 
-pub async fn lifetime<C: GetAnchorAccount>(
+/// A pending reward of a Hotspot, to be claimed for its owner
+#[derive(Debug, Clone, Serialize)]
+pub struct OwnerReward {
+    pub token: ClaimableToken,
+    pub hotspot: helium_crypto::PublicKey,
+    pub name: String,
+    pub pending: TokenAmount,
+}
+
+/// The pending rewards in the given tokens of every Hotspot owned by the
+/// given owner, largest first within each token
+pub async fn pending_for_owner<C: AsRef<DasClient> + GetAnchorAccount>(
     client: &C,
-    token: ClaimableToken,
-    entity_key_strings: &[String],
-) -> Result<HashMap<String, Vec<OracleReward>>, Error> {
-    let ld_account = lazy_distributor(client, token).await?;
+    owner: &Pubkey,
+    tokens: &[ClaimableToken],
+) -> Result<Vec<OwnerReward>, Error> {
+    let hotspots = hotspot::for_owner(client, owner).await?;
+    if hotspots.is_empty() {
+        return Ok(vec![]);
+    }
+    let entity_key_strings: Vec<String> = hotspots
+        .iter()
+        .map(|hotspot| hotspot.key.to_string())
+        .collect();
+    let mut rewards = vec![];
+    for token in tokens {
+        let mut pending =
+            pending(client, *token, &entity_key_strings, KeySerialization::B58).await?;
+        let mut token_rewards: Vec<OwnerReward> = hotspots
+            .iter()
+            .filter_map(|hotspot| {
+                pending
+                    .remove(&hotspot.key.to_string())
+                    .map(|reward| OwnerReward {
+                        token: *token,
+                        hotspot: hotspot.key.clone(),
+                        name: hotspot.name.clone(),
+                        pending: reward.reward,
+                    })
+            })
+            .collect();
+        token_rewards.sort_by(|a, b| b.pending.cmp(&a.pending));
+        rewards.extend(token_rewards);
+    }
+    Ok(rewards)
+}
+
+/// Claim the given rewards, paying out the split returned by `split_for`
+/// for each Hotspot.
+///
+/// Claims without a split have their fees paid by the given payer pool, if
+/// any. Claims with one are paid for by the keypair, which transfers the
+/// shares.
+///
+/// Each claim is built, and signed by the oracles, only when the stream is
+/// polled for it, so that its blockhash is still fresh when it is
+/// submitted. Claims for which nothing is left to claim yield `None`.
+pub fn claim_for_owner<'a, C, F>(
+    client: &'a C,
+    rewards: &'a [OwnerReward],
+    split_for: F,
+    keypair: &'a Keypair,
+    payers: Option<&'a PayerPool>,
+    opts: &'a TransactionOpts,
+) -> impl Stream<
+    Item = (
+        &'a OwnerReward,
+        Result<Option<(VersionedTransaction, u64)>, Error>,
+    ),
+> + 'a
+where
+    C: GetAsset + AsRef<SolanaRpcClient> + GetAnchorAccount,
+    F: Fn(&helium_crypto::PublicKey) -> RewardSplit + 'a,
+{
+    stream::iter(rewards).then(move |reward| {
+        let split = split_for(&reward.hotspot);
+        async move {
+            let encoded_entity_key = entity_key::EncodedEntityKey::from(&reward.hotspot);
+            let claim = match payers.filter(|_| split.is_empty()) {
+                Some(payers) => {
+                    claim_with_payers(
+                        client,
+                        reward.token,
+                        None,
+                        &encoded_entity_key,
+                        keypair,
+                        payers,
+                        opts,
+                    )
+                    .await
+                }
+                None => {
+                    claim_split(
+                        client,
+                        reward.token,
+                        None,
+                        &encoded_entity_key,
+                        &split,
+                        keypair,
+                        opts,
+                    )
+                    .await
+                }
+            };
+            (reward, claim)
+        }
+    })
+}
+
+/// Entity key used to probe oracles. The probe only checks that an oracle
+/// answers bulk reward requests, not what it answers for this key.
+const ORACLE_PROBE_ENTITY_KEY: &str = "oracle-health-probe";
+
+/// The oracles configured for the lazy distributor of the given token
+pub async fn oracles<C: GetAnchorAccount>(
+    client: &C,
+    token: ClaimableToken,
+) -> Result<Vec<Oracle>, Error> {
+    let ld_account = lazy_distributor(client, token).await?;
+    Ok(ld_account.oracles.into_iter().map(Oracle::from).collect())
+}
+
+/// Probe every oracle of the lazy distributor for the given token with a
+/// bulk rewards request, bypassing the reward cache.
+///
+/// A claim needs a signature from every oracle, so a single unhealthy oracle
+/// fails all claims for the token.
+pub async fn oracle_health<C: GetAnchorAccount>(
+    client: &C,
+    token: ClaimableToken,
+) -> Result<Vec<OracleHealth>, Error> {
+    let oracles = oracles(client, token).await?;
+    let probes = oracles
+        .into_iter()
+        .enumerate()
+        .map(|(index, oracle)| async move {
+            let start = std::time::Instant::now();
+            let result =
+                request_bulk_rewards(&oracle.url, vec![ORACLE_PROBE_ENTITY_KEY.to_string()]).await;
+            OracleHealth {
+                oracle,
+                index: index as u16,
+                healthy: result.is_ok(),
+                latency_ms: start.elapsed().as_millis() as u64,
+                error: result.err().map(|err| err.to_string()),
+            }
+        });
+    Ok(futures::future::join_all(probes).await)
+}
+
+pub async fn lifetime<C: GetAnchorAccount>(
+    client: &C,
+    token: ClaimableToken,
+    entity_key_strings: &[String],
+) -> Result<HashMap<String, Vec<OracleReward>>, Error> {
+    let ld_account = lazy_distributor(client, token).await?;
     stream::iter(ld_account.oracles)
         .enumerate()
         .map(Ok)
@@ -484,26 +1234,32 @@ async fn oracle_sign(oracle: &str, txn: Transaction) -> Result<Transaction, Erro
     struct OracleSignResponse {
         pub transaction: Data,
     }
-    let client = reqwest::Client::new();
+    let client = crate::client::http_client();
     let transaction = Data {
         data: bincode::serialize(&txn).map_err(EncodeError::from)?,
     };
-    let response = client
-        .post(oracle.to_string())
-        .json(&OracleSignRequest { transaction })
-        .send()
-        .await?
-        .json::<OracleSignResponse>()
-        .await?;
+    let start = std::time::Instant::now();
+    let response = async {
+        client
+            .post(oracle.to_string())
+            .json(&OracleSignRequest { transaction })
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)?
+            .json::<OracleSignResponse>()
+            .await
+    }
+    .await;
+    crate::client::log_request("oracle", oracle, start, &response);
+    let response = response.map_err(|err| OracleError::new(oracle, err))?;
     let signed_tx = bincode::deserialize(&response.transaction.data).map_err(DecodeError::from)?;
     Ok(signed_tx)
 }
 
-async fn bulk_from_oracle(
-    token: ClaimableToken,
+async fn request_bulk_rewards(
     oracle: &str,
-    entity_keys: &[String],
-) -> Result<HashMap<String, TokenAmount>, Error> {
+    entity_keys: Vec<String>,
+) -> Result<HashMap<String, serde_json::Value>, Error> {
     #[derive(Debug, Serialize, Deserialize)]
     #[serde(rename_all = "camelCase")]
     struct OracleBulkRewardRequest {
@@ -516,23 +1272,574 @@ async fn bulk_from_oracle(
         current_rewards: HashMap<String, serde_json::Value>,
     }
 
-    let client = reqwest::Client::new();
-    let oracle_rewards_response = client
-        .post(format!("{oracle}/bulk-rewards"))
-        .json(&OracleBulkRewardRequest {
-            entity_keys: entity_keys.into(),
+    let client = crate::client::http_client();
+    let url = format!("{oracle}/bulk-rewards");
+    let start = std::time::Instant::now();
+    let response = async {
+        client
+            .post(&url)
+            .json(&OracleBulkRewardRequest { entity_keys })
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)?
+            .json::<OracleBulkRewardResponse>()
+            .await
+    }
+    .await;
+    crate::client::log_request("oracle", &url, start, &response);
+    let response = response.map_err(|err| OracleError::new(oracle, err))?;
+    Ok(response.current_rewards)
+}
+
+async fn bulk_from_oracle(
+    token: ClaimableToken,
+    oracle: &str,
+    entity_keys: &[String],
+) -> Result<HashMap<String, TokenAmount>, Error> {
+    let (cached, missing) = cache::get(oracle, entity_keys);
+    let mut result: HashMap<String, TokenAmount> = cached
+        .into_iter()
+        .map(|(entity_key_string, amount)| {
+            (
+                entity_key_string,
+                TokenAmount::from_u64(token.into(), amount),
+            )
         })
-        .send()
+        .collect();
+    if missing.is_empty() {
+        return Ok(result);
+    }
+
+    let fetched: HashMap<String, TokenAmount> = request_bulk_rewards(oracle, missing)
         .await?
-        .json::<OracleBulkRewardResponse>()
-        .await?;
-    oracle_rewards_response
-        .current_rewards
         .into_iter()
         .map(|(entity_key_string, value)| {
             value_to_token_amount(token, value).map(|amount| (entity_key_string, amount))
         })
-        .try_collect()
+        .try_collect()?;
+    cache::put(oracle, &fetched);
+    result.extend(fetched);
+    Ok(result)
+}
+
+/// An optional on-disk cache of oracle bulk reward responses, keyed by oracle
+/// and entity key.
+///
+/// Oracle rewards only change once per epoch, so repeated lookups within a
+/// short time, like a `pending` followed by a `claim`, are served from the
+/// cache instead of hitting every oracle again. The cache is disabled unless
+/// [`init`] is called.
+pub mod cache {
+    use super::*;
+    use std::{
+        fs, io,
+        path::PathBuf,
+        sync::{Mutex, OnceLock},
+        time::Duration,
+    };
+
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(120);
+
+    static CACHE: OnceLock<RewardCache> = OnceLock::new();
+
+    pub fn init(path: PathBuf, ttl: Duration) -> Result<(), Error> {
+        let _ = CACHE.set(RewardCache::new(path, ttl));
+        Ok(())
+    }
+
+    pub(super) fn get(oracle: &str, entity_keys: &[String]) -> (HashMap<String, u64>, Vec<String>) {
+        match CACHE.get() {
+            Some(cache) => cache.get(oracle, entity_keys),
+            None => (HashMap::new(), entity_keys.to_vec()),
+        }
+    }
+
+    pub(super) fn put(oracle: &str, rewards: &HashMap<String, TokenAmount>) {
+        if let Some(cache) = CACHE.get() {
+            if let Err(err) = cache.put(oracle, rewards) {
+                tracing::warn!(?err, "failed to write reward cache");
+            }
+        }
+    }
+
+    /// Contents of the on-disk reward cache
+    #[derive(Debug, Clone, Serialize)]
+    pub struct CacheStats {
+        pub path: PathBuf,
+        pub entries: usize,
+        /// Entries still young enough to be served
+        pub fresh: usize,
+        /// Size of the cache file, in bytes
+        pub bytes: u64,
+        pub ttl_secs: u64,
+    }
+
+    /// Statistics of the reward cache, or `None` when it is not enabled
+    pub fn stats() -> Option<CacheStats> {
+        CACHE.get().map(RewardCache::stats)
+    }
+
+    /// Remove the reward cache file, returning whether there was one. Does
+    /// nothing when the cache is not enabled.
+    pub fn clear() -> io::Result<bool> {
+        let Some(cache) = CACHE.get() else {
+            return Ok(false);
+        };
+        let _guard = cache.lock.lock().expect("reward cache lock poisoned");
+        match fs::remove_file(&cache.path) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+    struct CachedReward {
+        timestamp: i64,
+        amount: u64,
+    }
+
+    type RewardCacheMap = HashMap<String, CachedReward>;
+
+    struct RewardCache {
+        path: PathBuf,
+        ttl: Duration,
+        lock: Mutex<()>,
+    }
+
+    fn cache_key(oracle: &str, entity_key: &str) -> String {
+        format!("{oracle}#{entity_key}")
+    }
+
+    impl RewardCache {
+        fn new(path: PathBuf, ttl: Duration) -> Self {
+            Self {
+                path,
+                ttl,
+                lock: Mutex::new(()),
+            }
+        }
+
+        fn is_fresh(&self, cached: &CachedReward, now: i64) -> bool {
+            now.saturating_sub(cached.timestamp) < self.ttl.as_secs() as i64
+        }
+
+        fn stats(&self) -> CacheStats {
+            let _guard = self.lock.lock().expect("reward cache lock poisoned");
+            let cached = self.load();
+            let now = Utc::now().timestamp();
+            CacheStats {
+                path: self.path.clone(),
+                entries: cached.len(),
+                fresh: cached
+                    .values()
+                    .filter(|reward| self.is_fresh(reward, now))
+                    .count(),
+                bytes: fs::metadata(&self.path).map_or(0, |meta| meta.len()),
+                ttl_secs: self.ttl.as_secs(),
+            }
+        }
+
+        fn load(&self) -> RewardCacheMap {
+            fs::File::open(&self.path)
+                .ok()
+                .and_then(|file| serde_json::from_reader(io::BufReader::new(file)).ok())
+                .unwrap_or_default()
+        }
+
+        fn get(&self, oracle: &str, entity_keys: &[String]) -> (HashMap<String, u64>, Vec<String>) {
+            let _guard = self.lock.lock().expect("reward cache lock poisoned");
+            let cached = self.load();
+            let now = Utc::now().timestamp();
+            let mut found = HashMap::new();
+            let mut missing = vec![];
+            for entity_key in entity_keys {
+                match cached.get(&cache_key(oracle, entity_key)) {
+                    Some(reward) if self.is_fresh(reward, now) => {
+                        found.insert(entity_key.clone(), reward.amount);
+                    }
+                    _ => missing.push(entity_key.clone()),
+                }
+            }
+            (found, missing)
+        }
+
+        fn put(&self, oracle: &str, rewards: &HashMap<String, TokenAmount>) -> io::Result<()> {
+            let _guard = self.lock.lock().expect("reward cache lock poisoned");
+            let now = Utc::now().timestamp();
+            let mut cached = self.load();
+            cached.retain(|_, reward| self.is_fresh(reward, now));
+            cached.extend(rewards.iter().map(|(entity_key, amount)| {
+                (
+                    cache_key(oracle, entity_key),
+                    CachedReward {
+                        timestamp: now,
+                        amount: amount.amount,
+                    },
+                )
+            }));
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // Write to a temporary file and rename to avoid readers seeing a
+            // partially written cache
+            let tmp_path = self.path.with_extension("tmp");
+            let file = fs::File::create(&tmp_path)?;
+            serde_json::to_writer(io::BufWriter::new(file), &cached)?;
+            fs::rename(tmp_path, &self.path)
+        }
+    }
+}
+
+pub mod history {
+    use super::*;
+    use crate::{
+        anchor_lang::{AnchorDeserialize, Discriminator},
+        solana_client::{
+            rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_config::RpcTransactionConfig,
+        },
+        solana_sdk::{commitment_config::CommitmentConfig, signature::Signature},
+    };
+    use chrono::DateTime;
+    use lazy_distributor::instruction::SetCurrentRewardsV0;
+    use rewards_oracle::instruction::SetCurrentRewardsWrapperV1;
+    use solana_transaction_status::{
+        EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiInstruction, UiMessage,
+        UiParsedInstruction, UiTransactionEncoding, UiTransactionTokenBalance,
+    };
+    use std::str::FromStr;
+
+    /// Length of a reward epoch in seconds
+    pub const EPOCH_SECONDS: i64 = 60 * 60 * 24;
+    const SIGNATURE_PAGE_SIZE: usize = 1000;
+
+    /// Rewards distributed to the recipient of an entity in a single
+    /// transaction.
+    ///
+    /// Rewards accumulate in the oracles until they are claimed, so a
+    /// distribution covers all rewards earned since the previous one and is
+    /// attributed to the epoch it was made in.
+    #[derive(Debug, Serialize, Clone)]
+    pub struct EpochReward {
+        pub epoch: u64,
+        pub timestamp: DateTime<Utc>,
+        pub signature: String,
+        /// Rewards distributed since the previous distribution
+        pub amount: TokenAmount,
+        /// Lifetime rewards set by the oracles in this distribution
+        pub lifetime: TokenAmount,
+    }
+
+    /// The reward epoch of a given time
+    pub fn epoch(timestamp: DateTime<Utc>) -> u64 {
+        timestamp.timestamp().div_euclid(EPOCH_SECONDS) as u64
+    }
+
+    struct Distribution {
+        timestamp: DateTime<Utc>,
+        signature: String,
+        lifetime: u64,
+    }
+
+    /// Reconstruct the rewards distributed to the given entity in the given
+    /// number of most recent epochs.
+    ///
+    /// The distribution transactions of the entity's recipient account are
+    /// read back from the chain, and the lifetime rewards the oracles set in
+    /// each are compared to the previous distribution. Results are ordered
+    /// oldest first.
+    pub async fn for_entity_key<E: AsEntityKey, C: AsRef<SolanaRpcClient>>(
+        client: &C,
+        token: ClaimableToken,
+        entity_key: &E,
+        epochs: u64,
+    ) -> Result<Vec<EpochReward>, Error> {
+        let kta = kta::for_entity_key(entity_key).await?;
+        let recipient = token.receipient_key_from_kta(&kta);
+        let since_epoch = epoch(Utc::now()).saturating_sub(epochs.saturating_sub(1));
+        let since =
+            DateTime::from_timestamp(since_epoch as i64 * EPOCH_SECONDS, 0).unwrap_or_default();
+
+        // Collect distributions newest first until one from before the
+        // requested epochs is found to use as the baseline
+        let mut distributions = vec![];
+        let mut before = None;
+        'pages: loop {
+            let signatures = client
+                .as_ref()
+                .get_signatures_for_address_with_config(
+                    &recipient,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        limit: Some(SIGNATURE_PAGE_SIZE),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            let page_len = signatures.len();
+            for status in signatures {
+                before = Some(Signature::from_str(&status.signature).map_err(DecodeError::from)?);
+                if status.err.is_some() {
+                    continue;
+                }
+                let txn = client
+                    .as_ref()
+                    .get_transaction_with_config(
+                        &Signature::from_str(&status.signature).map_err(DecodeError::from)?,
+                        RpcTransactionConfig {
+                            encoding: Some(UiTransactionEncoding::JsonParsed),
+                            commitment: Some(CommitmentConfig::finalized()),
+                            max_supported_transaction_version: Some(0),
+                        },
+                    )
+                    .await?;
+                let Some(distribution) = Distribution::from_transaction(txn, &recipient)? else {
+                    continue;
+                };
+                let is_baseline = distribution.timestamp < since;
+                distributions.push(distribution);
+                if is_baseline {
+                    break 'pages;
+                }
+            }
+            if page_len < SIGNATURE_PAGE_SIZE {
+                break;
+            }
+        }
+
+        let token = Token::from(token);
+        let mut previous = 0;
+        let rewards = distributions
+            .into_iter()
+            .rev()
+            .filter_map(|distribution| {
+                let amount = distribution.lifetime.saturating_sub(previous);
+                previous = distribution.lifetime;
+                (distribution.timestamp >= since).then(|| EpochReward {
+                    epoch: epoch(distribution.timestamp),
+                    timestamp: distribution.timestamp,
+                    signature: distribution.signature,
+                    amount: token.amount(amount),
+                    lifetime: token.amount(distribution.lifetime),
+                })
+            })
+            .collect();
+        Ok(rewards)
+    }
+
+    /// Rewards received by a wallet in a single claim transaction
+    #[derive(Debug, Serialize, Clone)]
+    pub struct ClaimReceipt {
+        pub timestamp: DateTime<Utc>,
+        pub signature: String,
+        /// Increase of the owner's token balance in the claim transaction
+        pub amount: TokenAmount,
+    }
+
+    /// Read back the claims of the given token received by the given owner
+    /// between two times.
+    ///
+    /// Claims are the transactions of the owner's token account that run the
+    /// lazy distributor and increase the owner's balance. Results are ordered
+    /// oldest first.
+    pub async fn claims_for_owner<C: AsRef<SolanaRpcClient>>(
+        client: &C,
+        token: ClaimableToken,
+        owner: &Pubkey,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<ClaimReceipt>, Error> {
+        let token = Token::from(token);
+        let token_account = token.associated_token_adress(owner);
+        let mut receipts = vec![];
+        let mut before = None;
+        'pages: loop {
+            let signatures = client
+                .as_ref()
+                .get_signatures_for_address_with_config(
+                    &token_account,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        limit: Some(SIGNATURE_PAGE_SIZE),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            let page_len = signatures.len();
+            for status in signatures {
+                let signature =
+                    Signature::from_str(&status.signature).map_err(DecodeError::from)?;
+                before = Some(signature);
+                let block_time = status
+                    .block_time
+                    .and_then(|block_time| DateTime::from_timestamp(block_time, 0));
+                match block_time {
+                    Some(block_time) if block_time < since => break 'pages,
+                    Some(block_time) if block_time >= until => continue,
+                    _ => (),
+                }
+                if status.err.is_some() {
+                    continue;
+                }
+                let txn = client
+                    .as_ref()
+                    .get_transaction_with_config(
+                        &signature,
+                        RpcTransactionConfig {
+                            encoding: Some(UiTransactionEncoding::JsonParsed),
+                            commitment: Some(CommitmentConfig::finalized()),
+                            max_supported_transaction_version: Some(0),
+                        },
+                    )
+                    .await?;
+                if let Some(receipt) = ClaimReceipt::from_transaction(txn, token, owner)? {
+                    receipts.push(receipt);
+                }
+            }
+            if page_len < SIGNATURE_PAGE_SIZE {
+                break;
+            }
+        }
+        receipts.reverse();
+        Ok(receipts)
+    }
+
+    impl ClaimReceipt {
+        fn from_transaction(
+            txn: EncodedConfirmedTransactionWithStatusMeta,
+            token: Token,
+            owner: &Pubkey,
+        ) -> Result<Option<Self>, DecodeError> {
+            let Some(meta) = txn.transaction.meta else {
+                return Ok(None);
+            };
+            if meta.err.is_some() {
+                return Ok(None);
+            }
+            let EncodedTransaction::Json(ui_txn) = txn.transaction.transaction else {
+                return Err(DecodeError::other("not a json encoded transaction"));
+            };
+            let UiMessage::Parsed(ui_msg) = ui_txn.message else {
+                return Err(DecodeError::other("not a parsed transaction message"));
+            };
+            let distributor = lazy_distributor::id().to_string();
+            if !ui_msg
+                .account_keys
+                .iter()
+                .any(|account| account.pubkey == distributor)
+            {
+                return Ok(None);
+            }
+            let Some(timestamp) = txn
+                .block_time
+                .and_then(|block_time| DateTime::from_timestamp(block_time, 0))
+            else {
+                return Err(DecodeError::other("no valid block time found"));
+            };
+            let mint = token.mint().to_string();
+            let owner = owner.to_string();
+            let balance = |balances: Option<Vec<UiTransactionTokenBalance>>| {
+                balances
+                    .unwrap_or_default()
+                    .iter()
+                    .filter(|balance| {
+                        balance.mint == mint
+                            && Option::<&String>::from(balance.owner.as_ref()) == Some(&owner)
+                    })
+                    .map(|balance| balance.ui_token_amount.amount.parse::<u64>())
+                    .sum::<Result<u64, _>>()
+                    .map_err(|err| DecodeError::other(format!("invalid token amount: {err}")))
+            };
+            let pre = balance(meta.pre_token_balances.into())?;
+            let post = balance(meta.post_token_balances.into())?;
+            if post <= pre {
+                return Ok(None);
+            }
+            Ok(Some(Self {
+                timestamp,
+                signature: ui_txn.signatures[0].clone(),
+                amount: token.amount(post - pre),
+            }))
+        }
+    }
+
+    impl Distribution {
+        fn from_transaction(
+            txn: EncodedConfirmedTransactionWithStatusMeta,
+            recipient: &Pubkey,
+        ) -> Result<Option<Self>, DecodeError> {
+            if let Some(meta) = &txn.transaction.meta {
+                if meta.err.is_some() {
+                    return Ok(None);
+                }
+            }
+            let EncodedTransaction::Json(ui_txn) = txn.transaction.transaction else {
+                return Err(DecodeError::other("not a json encoded transaction"));
+            };
+            let UiMessage::Parsed(ui_msg) = ui_txn.message else {
+                return Err(DecodeError::other("not a parsed transaction message"));
+            };
+            let Some(timestamp) = txn
+                .block_time
+                .and_then(|block_time| DateTime::from_timestamp(block_time, 0))
+            else {
+                return Err(DecodeError::other("no valid block time found"));
+            };
+            // Lifetime rewards by oracle index
+            let oracle_rewards: HashMap<u16, u64> = ui_msg
+                .instructions
+                .iter()
+                .map(|ix| set_current_rewards(ix, recipient))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .flatten()
+                .collect();
+            if oracle_rewards.is_empty() {
+                return Ok(None);
+            }
+            let mut lifetimes = oracle_rewards.into_values().collect_vec();
+            lifetimes.sort_unstable();
+            Ok(Some(Self {
+                timestamp,
+                signature: ui_txn.signatures[0].clone(),
+                lifetime: lifetimes[lifetimes.len() / 2],
+            }))
+        }
+    }
+
+    /// The oracle index and lifetime rewards of an instruction setting the
+    /// current rewards of the given recipient
+    fn set_current_rewards(
+        ix: &UiInstruction,
+        recipient: &Pubkey,
+    ) -> Result<Option<(u16, u64)>, DecodeError> {
+        let UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(decoded)) = ix else {
+            return Ok(None);
+        };
+        let is_wrapper = decoded.program_id == rewards_oracle::id().to_string();
+        if !is_wrapper && decoded.program_id != lazy_distributor::id().to_string() {
+            return Ok(None);
+        }
+        // The recipient is the third account of both instructions
+        if decoded.accounts.get(2) != Some(&recipient.to_string()) {
+            return Ok(None);
+        }
+        let data = solana_sdk::bs58::decode(&decoded.data).into_vec()?;
+        if data.len() < 8 {
+            return Ok(None);
+        }
+        let (discriminator, mut args) = data.split_at(8);
+        let rewards = if is_wrapper && discriminator == SetCurrentRewardsWrapperV1::DISCRIMINATOR {
+            let args = rewards_oracle::SetCurrentRewardsWrapperArgsV1::deserialize(&mut args)?;
+            (args.oracle_index, args.current_rewards)
+        } else if !is_wrapper && discriminator == SetCurrentRewardsV0::DISCRIMINATOR {
+            let args = lazy_distributor::SetCurrentRewardsArgsV0::deserialize(&mut args)?;
+            (args.oracle_index, args.current_rewards)
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(rewards))
+    }
 }
 
 pub mod recipient {
@@ -565,6 +1872,16 @@ pub mod recipient {
         asset: &asset::Asset,
         asset_proof: &asset::AssetProof,
         payer: &Pubkey,
+    ) -> Result<Instruction, Error> {
+        mk_init_instruction(token, kta, asset, asset_proof, payer)
+    }
+
+    pub(crate) fn mk_init_instruction(
+        token: ClaimableToken,
+        kta: &helium_entity_manager::KeyToAssetV0,
+        asset: &asset::Asset,
+        asset_proof: &asset::AssetProof,
+        payer: &Pubkey,
     ) -> Result<Instruction, Error> {
         fn mk_accounts(
             payer: Pubkey,
@@ -593,7 +1910,7 @@ pub mod recipient {
             kta,
         )
         .to_account_metas(None);
-        accounts.extend_from_slice(&asset_proof.proof(Some(3))?);
+        accounts.extend_from_slice(&asset_proof.proof(Some(size::CLAIM_PROOF_LEN))?);
 
         let ix = Instruction {
             program_id: lazy_distributor::id(),
@@ -613,7 +1930,7 @@ pub mod recipient {
 
     pub const INIT_INSTRUCTION_BUDGET: u32 = 150_000;
 
-    pub async fn init_message<E: AsEntityKey, C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
+    pub async fn init_message<E: AsEntityKey, C: AsRef<SolanaRpcClient> + GetAsset>(
         client: &C,
         token: ClaimableToken,
         entity_key: &E,
@@ -637,7 +1954,7 @@ pub mod recipient {
         message::mk_message(client, ixs, &opts.lut_addresses, payer).await
     }
 
-    pub async fn init<E: AsEntityKey, C: AsRef<SolanaRpcClient> + AsRef<DasClient>>(
+    pub async fn init<E: AsEntityKey, C: AsRef<SolanaRpcClient> + GetAsset>(
         client: &C,
         token: ClaimableToken,
         entity_key: &E,
@@ -649,6 +1966,157 @@ pub mod recipient {
         let txn = VersionedTransaction::try_new(msg, &[keypair])?;
         Ok((txn, block_height))
     }
+
+    /// Pack the initializations of the recipients missing for the given
+    /// entities into as few transactions as possible.
+    ///
+    /// A claim for an entity without a recipient initializes it in the claim
+    /// transaction, which leaves less room for the claim itself. Initializing
+    /// the recipients of many entities up front takes fewer transactions.
+    pub async fn init_batches<E: AsEntityKey, C: AsRef<SolanaRpcClient> + GetAsset>(
+        client: &C,
+        token: ClaimableToken,
+        entity_keys: &[E],
+        payer: &Pubkey,
+        opts: &TransactionOpts,
+    ) -> Result<Vec<message::Batch>, Error> {
+        let ktas = kta::for_entity_keys(entity_keys).await?;
+        let recipients = for_ktas(client, token, &ktas).await?;
+        let lut_accounts = message::get_lut_accounts(client, &opts.lut_addresses).await?;
+        let mut items = vec![];
+        let mut indices = vec![];
+        for (index, (kta, recipient)) in ktas.iter().zip(recipients).enumerate() {
+            if recipient.is_some() {
+                continue;
+            }
+            let (asset, asset_proof) = asset::for_kta_with_proof(client, kta).await?;
+            let ix = init_instruction(token, kta, &asset, &asset_proof, payer).await?;
+            items.push((vec![ix], INIT_INSTRUCTION_BUDGET));
+            indices.push(index);
+        }
+        Ok(with_indices(
+            message::pack(items, &lut_accounts, payer),
+            &indices,
+        ))
+    }
+
+    /// Map the items of packed batches to the given indices of the entity
+    /// keys they were built for
+    fn with_indices(mut batches: Vec<message::Batch>, indices: &[usize]) -> Vec<message::Batch> {
+        for batch in &mut batches {
+            for item in &mut batch.items {
+                *item = indices[*item];
+            }
+        }
+        batches
+    }
+
+    pub const DESTINATION_INSTRUCTION_BUDGET: u32 = 100_000;
+
+    /// The custom reward destination of a recipient, if one is set
+    pub fn custom_destination(recipient: &lazy_distributor::RecipientV0) -> Option<Pubkey> {
+        (recipient.destination != Pubkey::default()).then_some(recipient.destination)
+    }
+
+    /// The instruction setting the destination rewards of a compressed asset
+    /// are paid out to. The recipient of the asset has to exist. The proof
+    /// accounts needed for the tree of the asset are included.
+    pub async fn update_destination_instruction<C: AsRef<SolanaRpcClient>>(
+        client: &C,
+        token: ClaimableToken,
+        kta: &helium_entity_manager::KeyToAssetV0,
+        asset: &asset::Asset,
+        asset_proof: &asset::AssetProof,
+        destination: &Pubkey,
+    ) -> Result<Instruction, Error> {
+        let mut accounts = lazy_distributor::accounts::UpdateCompressionDestinationV0 {
+            recipient: token.receipient_key_from_kta(kta),
+            owner: asset.ownership.owner,
+            destination: *destination,
+            merkle_tree: asset.compression.tree,
+            compression_program: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+        }
+        .to_account_metas(None);
+        accounts.extend_from_slice(
+            &asset_proof
+                .proof_for_tree(client, &asset.compression.tree)
+                .await?,
+        );
+
+        Ok(Instruction {
+            program_id: lazy_distributor::id(),
+            accounts,
+            data: lazy_distributor::instruction::UpdateCompressionDestinationV0 {
+                _args: lazy_distributor::UpdateCompressionDestinationArgsV0 {
+                    data_hash: asset.compression.data_hash,
+                    creator_hash: asset.compression.creator_hash,
+                    root: asset_proof.root.to_bytes(),
+                    index: asset.compression.leaf_id()?,
+                },
+            }
+            .data(),
+        })
+    }
+
+    /// Pack updates of the reward destination of the given entities, all
+    /// owned by the given owner, into as few transactions as possible.
+    ///
+    /// Entities whose rewards already go to the given destination are left
+    /// out, so a set of updates that was interrupted can be completed by
+    /// packing it again. Recipients that do not exist yet are initialized in the same
+    /// transaction as their update. How many updates fit in a transaction
+    /// depends on the proof each asset needs.
+    pub async fn destination_batches<E: AsEntityKey, C: AsRef<SolanaRpcClient> + GetAsset>(
+        client: &C,
+        token: ClaimableToken,
+        entity_keys: &[E],
+        destination: &Pubkey,
+        owner: &Pubkey,
+        opts: &TransactionOpts,
+    ) -> Result<Vec<message::Batch>, Error> {
+        let ktas = kta::for_entity_keys(entity_keys).await?;
+        let recipients = for_ktas(client, token, &ktas).await?;
+        let lut_accounts = message::get_lut_accounts(client, &opts.lut_addresses).await?;
+        let mut items = vec![];
+        let mut indices = vec![];
+        for (index, (kta, recipient)) in ktas.iter().zip(recipients).enumerate() {
+            let current = recipient.as_ref().and_then(custom_destination);
+            if current.unwrap_or(*owner) == *destination {
+                continue;
+            }
+            let (asset, asset_proof) = asset::for_kta_with_proof(client, kta).await?;
+            if asset.ownership.owner != *owner {
+                return Err(RewardError::NotOwned {
+                    asset: asset.id,
+                    owner: *owner,
+                }
+                .into());
+            }
+            let mut ixs = vec![];
+            let mut budget = DESTINATION_INSTRUCTION_BUDGET;
+            if recipient.is_none() {
+                ixs.push(init_instruction(token, kta, &asset, &asset_proof, owner).await?);
+                budget += INIT_INSTRUCTION_BUDGET;
+            }
+            ixs.push(
+                update_destination_instruction(
+                    client,
+                    token,
+                    kta,
+                    &asset,
+                    &asset_proof,
+                    destination,
+                )
+                .await?,
+            );
+            items.push((ixs, budget));
+            indices.push(index);
+        }
+        Ok(with_indices(
+            message::pack(items, &lut_accounts, owner),
+            &indices,
+        ))
+    }
 }
 
 fn value_to_token_amount(
@@ -656,14 +2124,514 @@ fn value_to_token_amount(
     value: serde_json::Value,
 ) -> Result<TokenAmount, Error> {
     let value = match value {
-        serde_json::Value::String(s) => s
-            .parse::<u64>()
-            .map_err(|_| DecodeError::other(format!("invalid reward value {s}")))?,
+        serde_json::Value::String(s) => {
+            s.parse::<u64>().map_err(|_| RewardError::InvalidValue(s))?
+        }
         serde_json::Value::Number(n) => n
             .as_u64()
-            .ok_or_else(|| DecodeError::other(format!("invalid reward value {n}")))?,
-        _ => return Err(DecodeError::other(format!("invalid reward value {value}")).into()),
+            .ok_or_else(|| RewardError::InvalidValue(n.to_string()))?,
+        _ => return Err(RewardError::InvalidValue(value.to_string()).into()),
     };
 
     Ok(TokenAmount::from_u64(token.into(), value))
 }
+
+pub mod cost {
+    use super::*;
+    use crate::simulation::LAMPORTS_PER_SIGNATURE;
+
+    /// Estimated SOL a wallet needs to pay for a number of claims
+    #[derive(Debug, Clone, Serialize)]
+    pub struct ClaimCost {
+        pub token: ClaimableToken,
+        pub claims: u64,
+        /// Claims whose recipient account is created by the claim
+        pub recipients: u64,
+        /// Compute unit price, in micro lamports, the estimate uses
+        pub priority_fee: u64,
+        /// Signature and priority fees of a claim
+        pub fee_per_claim: TokenAmount,
+        /// Rent of a recipient account, paid by the claim that creates it
+        pub recipient_rent: TokenAmount,
+        pub total: TokenAmount,
+    }
+
+    /// Size of a recipient account as allocated on chain by the lazy
+    /// distributor, read from the given existing recipient. Without one the
+    /// allocation for the given number of oracles is assumed.
+    async fn recipient_size<C: AsRef<SolanaRpcClient>>(
+        client: &C,
+        existing: Option<&Pubkey>,
+        oracles: usize,
+    ) -> Result<usize, Error> {
+        if let Some(recipient_key) = existing {
+            return Ok(client.as_ref().get_account_data(recipient_key).await?.len());
+        }
+        Ok(60
+            + std::mem::size_of::<lazy_distributor::RecipientV0>()
+            + std::mem::size_of::<Option<u64>>() * oracles)
+    }
+
+    /// Priority fee, in lamports, of a transaction with the given compute
+    /// limit at the given compute unit price in micro lamports
+    fn priority_fee_lamports(compute_limit: u32, price: u64) -> u64 {
+        (u64::from(compute_limit) * price).div_ceil(1_000_000)
+    }
+
+    /// Estimate the SOL needed to pay for a claim for each of the given
+    /// assets at the current priority fee and rent, including the rent of
+    /// the recipient accounts the claims create for assets that were never
+    /// claimed. Splits and the creation of reward token accounts are not
+    /// included.
+    pub async fn estimate<E, C>(
+        client: &C,
+        token: ClaimableToken,
+        entity_keys: &[E],
+        opts: &TransactionOpts,
+    ) -> Result<ClaimCost, Error>
+    where
+        E: AsEntityKey,
+        C: AsRef<SolanaRpcClient> + GetAnchorAccount,
+    {
+        let lazy_distributor = super::lazy_distributor(client, token).await?;
+        let ktas = kta::for_entity_keys(entity_keys).await?;
+        let recipients = recipient::for_ktas(client, token, &ktas).await?;
+        let claims = entity_keys.len() as u64;
+        let existing = ktas.iter().zip(&recipients).find_map(|(kta, recipient)| {
+            recipient
+                .as_ref()
+                .map(|_| token.receipient_key_from_kta(kta))
+        });
+        let recipients = recipients
+            .iter()
+            .filter(|recipient| recipient.is_none())
+            .count() as u64;
+
+        // Every oracle signs a claim along with the payer
+        let signatures = 1 + lazy_distributor.oracles.len() as u64;
+        let accounts = vec![AccountMeta::new(token.lazy_distributor_key(), false)];
+        let priority_fee = priority_fee::get_estimate(client, &accounts, opts.fee_range()).await?;
+        let recipient_size =
+            recipient_size(client, existing.as_ref(), lazy_distributor.oracles.len()).await?;
+        let recipient_rent = client
+            .as_ref()
+            .get_minimum_balance_for_rent_exemption(recipient_size)
+            .await?;
+
+        let fee_per_claim =
+            signatures * LAMPORTS_PER_SIGNATURE + priority_fee_lamports(CLAIM_BUDGET, priority_fee);
+        let init_fee = priority_fee_lamports(recipient::INIT_INSTRUCTION_BUDGET, priority_fee)
+            + recipient_rent;
+        let total = fee_per_claim * claims + init_fee * recipients;
+        Ok(ClaimCost {
+            token,
+            claims,
+            recipients,
+            priority_fee,
+            fee_per_claim: Token::Sol.amount(fee_per_claim),
+            recipient_rent: Token::Sol.amount(recipient_rent),
+            total: Token::Sol.amount(total),
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn priority_fee() {
+            assert_eq!(priority_fee_lamports(200_000, 0), 0);
+            assert_eq!(priority_fee_lamports(200_000, 1), 1);
+            assert_eq!(priority_fee_lamports(200_000, 5_000), 1_000);
+        }
+    }
+}
+
+/// Predicting the size of claim transactions without building them, so the
+/// number of claims packed in a transaction can be chosen up front instead of
+/// by trial and error
+pub mod size {
+    use super::*;
+    use crate::solana_sdk::{address_lookup_table::AddressLookupTableAccount, packet};
+
+    /// Proof nodes a claim passes, as the claim builders cap them
+    pub const CLAIM_PROOF_LEN: usize = 3;
+
+    /// The parts of claim transactions that determine their size
+    #[derive(Debug, Clone, Copy)]
+    pub struct ClaimShape {
+        pub token: ClaimableToken,
+        /// Number of claims, one per asset, in the transaction
+        pub tickets: usize,
+        /// Whether the claims initialize the recipient accounts of their
+        /// assets
+        pub init_recipient: bool,
+        /// Whether the rewards are paid to a custom destination rather than
+        /// the asset owner
+        pub custom_destination: bool,
+    }
+
+    /// A compressed asset owned by the given owner, alone in its own merkle
+    /// tree, with its key to asset account
+    fn placeholder_asset(
+        owner: &Pubkey,
+    ) -> Result<(Pubkey, helium_entity_manager::KeyToAssetV0, asset::Asset), Error> {
+        let id = Pubkey::new_unique();
+        let kta = helium_entity_manager::KeyToAssetV0 {
+            dao: Dao::Hnt.key(),
+            asset: id,
+            entity_key: Pubkey::new_unique().to_bytes().to_vec(),
+            bump_seed: 255,
+            key_serialization: helium_entity_manager::KeySerialization::B58,
+        };
+        let asset = asset::Asset {
+            id,
+            compression: asset::AssetCompression {
+                data_hash: [0; 32],
+                creator_hash: [0; 32],
+                leaf_id: 0,
+                tree: Pubkey::new_unique(),
+            },
+            creators: vec![],
+            ownership: asset::AssetOwnership {
+                owner: *owner,
+                delegate: None,
+            },
+            content: asset::AssetContent {
+                metadata: asset::AssetMetadata {
+                    attributes: vec![],
+                    name: String::new(),
+                    symbol: String::new(),
+                },
+                json_uri: url::Url::parse("https://entities.nft.helium.io/")
+                    .map_err(DecodeError::from)?,
+            },
+            grouping: vec![],
+            burnt: false,
+        };
+        Ok((Pubkey::new_unique(), kta, asset))
+    }
+
+    /// The instructions of a claim transaction of the given shape, built by
+    /// the claim builders for placeholder assets. The accounts every claim
+    /// of the token shares are the real ones from its lazy distributor, so
+    /// lookup tables holding them shrink the prediction like they would the
+    /// transaction. Assets are taken to be owned by the payer, each in its
+    /// own merkle tree, with proofs as long as claims pass.
+    pub fn placeholder_instructions(
+        shape: &ClaimShape,
+        ld_account: &lazy_distributor::LazyDistributorV0,
+        payer: &Pubkey,
+    ) -> Result<Vec<Instruction>, Error> {
+        let token = shape.token;
+        let tickets = u32::try_from(shape.tickets).unwrap_or(u32::MAX);
+        let init_budget = if shape.init_recipient {
+            recipient::INIT_INSTRUCTION_BUDGET
+        } else {
+            0
+        };
+        let reward = OracleReward {
+            oracle: Oracle {
+                key: ld_account
+                    .oracles
+                    .first()
+                    .map(|oracle| oracle.oracle)
+                    .unwrap_or_default(),
+                url: String::new(),
+            },
+            index: 0,
+            reward: Token::from(token).amount(0),
+        };
+        let mut ixs = vec![
+            priority_fee::compute_budget_instruction(
+                (CLAIM_BUDGET + init_budget).saturating_mul(tickets),
+            ),
+            priority_fee::compute_price_instruction(0),
+        ];
+        for _ in 0..shape.tickets {
+            let (kta_key, kta, asset) = placeholder_asset(payer)?;
+            let asset_proof = asset::AssetProof {
+                proof: (0..CLAIM_PROOF_LEN)
+                    .map(|_| Pubkey::new_unique().to_string())
+                    .collect(),
+                root: Pubkey::new_unique(),
+                tree_id: asset.compression.tree,
+            };
+            let destination = shape.custom_destination.then(Pubkey::new_unique);
+            ixs.extend(claim_instructions(
+                token,
+                ld_account,
+                kta_key,
+                &kta,
+                &reward,
+                &asset,
+                &asset_proof,
+                shape.init_recipient,
+                destination,
+                payer,
+            )?);
+        }
+        Ok(ixs)
+    }
+
+    /// Predicted size in bytes of a claim transaction of the given shape,
+    /// including the payer and oracle signatures
+    pub fn transaction_size(
+        shape: &ClaimShape,
+        ld_account: &lazy_distributor::LazyDistributorV0,
+        lut_accounts: &[AddressLookupTableAccount],
+        payer: &Pubkey,
+    ) -> Option<u64> {
+        let ixs = placeholder_instructions(shape, ld_account, payer).ok()?;
+        message::size(&ixs, lut_accounts, payer)
+    }
+
+    /// Whether a claim transaction of the given shape fits in a single
+    /// transaction
+    pub fn fits(
+        shape: &ClaimShape,
+        ld_account: &lazy_distributor::LazyDistributorV0,
+        lut_accounts: &[AddressLookupTableAccount],
+        payer: &Pubkey,
+    ) -> bool {
+        transaction_size(shape, ld_account, lut_accounts, payer)
+            .is_some_and(|size| size <= packet::PACKET_DATA_SIZE as u64)
+    }
+
+    /// The most claims of the given shape, ignoring its ticket count, that
+    /// fit in a single transaction
+    pub fn max_tickets(
+        shape: &ClaimShape,
+        ld_account: &lazy_distributor::LazyDistributorV0,
+        lut_accounts: &[AddressLookupTableAccount],
+        payer: &Pubkey,
+    ) -> usize {
+        let with = |tickets| ClaimShape { tickets, ..*shape };
+        (1..)
+            .take_while(|tickets| fits(&with(*tickets), ld_account, lut_accounts, payer))
+            .last()
+            .unwrap_or(0)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn ld_account() -> lazy_distributor::LazyDistributorV0 {
+            lazy_distributor::LazyDistributorV0 {
+                rewards_escrow: Pubkey::new_unique(),
+                oracles: vec![lazy_distributor::OracleConfigV0 {
+                    oracle: Pubkey::new_unique(),
+                    url: "https://iot-oracle.oracle.test-helium.com".to_string(),
+                }],
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn claim_sizes() {
+            let payer = Pubkey::new_unique();
+            let ld_account = ld_account();
+            let shape = ClaimShape {
+                token: ClaimableToken::Iot,
+                tickets: 1,
+                init_recipient: false,
+                custom_destination: false,
+            };
+            assert!(fits(&shape, &ld_account, &[], &payer));
+            let max = max_tickets(&shape, &ld_account, &[], &payer);
+            assert!(max >= 1);
+            assert!(!fits(
+                &ClaimShape {
+                    tickets: max + 1,
+                    ..shape
+                },
+                &ld_account,
+                &[],
+                &payer
+            ));
+
+            let init = ClaimShape {
+                init_recipient: true,
+                ..shape
+            };
+            assert!(
+                transaction_size(&init, &ld_account, &[], &payer)
+                    > transaction_size(&shape, &ld_account, &[], &payer)
+            );
+            assert!(max_tickets(&init, &ld_account, &[], &payer) <= max);
+        }
+
+        #[test]
+        fn predicts_claim() {
+            let payer = Pubkey::new_unique();
+            let ld_account = ld_account();
+            let token = ClaimableToken::Iot;
+            let asset_id = Pubkey::new_unique();
+            let asset: asset::Asset = serde_json::from_value(serde_json::json!({
+                "id": asset_id.to_string(),
+                "compression": {
+                    "data_hash": Pubkey::new_unique().to_string(),
+                    "creator_hash": Pubkey::new_unique().to_string(),
+                    "leaf_id": 42,
+                    "tree": Pubkey::new_unique().to_string(),
+                },
+                "creators": [],
+                "ownership": {
+                    "owner": payer.to_string(),
+                    "delegate": null,
+                },
+                "content": {
+                    "metadata": {"name": "test", "symbol": "IOT OPS"},
+                    "json_uri": "https://entities.nft.helium.io/test",
+                },
+                "grouping": [],
+            }))
+            .expect("asset");
+            // Claims pass fewer proof nodes than the asset proof holds
+            let asset_proof = asset::AssetProof {
+                proof: (0..10).map(|_| Pubkey::new_unique().to_string()).collect(),
+                root: Pubkey::new_unique(),
+                tree_id: asset.compression.tree,
+            };
+            let kta = helium_entity_manager::KeyToAssetV0 {
+                dao: Dao::Hnt.key(),
+                asset: asset_id,
+                entity_key: vec![13; 33],
+                bump_seed: 255,
+                key_serialization: helium_entity_manager::KeySerialization::B58,
+            };
+            let reward = OracleReward {
+                oracle: ld_account.oracles[0].clone().into(),
+                index: 0,
+                reward: Token::Iot.amount(1_000_000),
+            };
+
+            for (init_recipient, custom_destination) in
+                [(false, false), (true, false), (false, true)]
+            {
+                let mut claim = vec![
+                    priority_fee::compute_budget_instruction(CLAIM_BUDGET),
+                    priority_fee::compute_price_instruction(1_000),
+                ];
+                claim.extend(
+                    claim_instructions(
+                        token,
+                        &ld_account,
+                        Pubkey::new_unique(),
+                        &kta,
+                        &reward,
+                        &asset,
+                        &asset_proof,
+                        init_recipient,
+                        custom_destination.then(Pubkey::new_unique),
+                        &payer,
+                    )
+                    .expect("claim"),
+                );
+                let shape = ClaimShape {
+                    token,
+                    tickets: 1,
+                    init_recipient,
+                    custom_destination,
+                };
+                assert_eq!(
+                    transaction_size(&shape, &ld_account, &[], &payer),
+                    message::size(&claim, &[], &payer),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_transfers() {
+        let host = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let share = |destination, basis_points| RewardShare {
+            destination,
+            basis_points,
+        };
+        let split = RewardSplit::new(vec![share(host, 2_000), share(other, 1)]).expect("split");
+        let transfers = split.transfers(Token::Iot, 1_000_001);
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers[0], (host, Token::Iot.amount(200_000)));
+        assert_eq!(transfers[1], (other, Token::Iot.amount(100)));
+        assert_eq!(split.transfers(Token::Iot, 100).len(), 1);
+
+        assert!(RewardSplit::new(vec![share(host, 8_000), share(other, 2_001)]).is_err());
+        assert!(RewardSplit::new(vec![share(host, 10), share(host, 10)]).is_err());
+        assert!(RewardSplit::new(vec![share(host, 0)]).is_err());
+    }
+
+    #[test]
+    fn decode_claims() {
+        use crate::solana_sdk::{hash::Hash, message::Message, system_instruction};
+
+        let owner = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let destination_account = Pubkey::new_unique();
+        let mut accounts = vec![AccountMeta::new(owner, true); 3];
+        accounts[2] = AccountMeta::new(recipient, false);
+        let set_current = Instruction {
+            program_id: rewards_oracle::id(),
+            accounts: accounts.clone(),
+            data: rewards_oracle::instruction::SetCurrentRewardsWrapperV1 {
+                _args: rewards_oracle::SetCurrentRewardsWrapperArgsV1 {
+                    current_rewards: 42,
+                    oracle_index: 0,
+                },
+            }
+            .data(),
+        };
+        let mut accounts = vec![AccountMeta::new(owner, true); distribute_destination_index() + 1];
+        accounts[distribute_destination_index()] = AccountMeta::new(destination_account, false);
+        let distribute = Instruction {
+            program_id: lazy_distributor::id(),
+            accounts,
+            data: lazy_distributor::instruction::DistributeCompressionRewardsV0 {
+                _args: lazy_distributor::DistributeCompressionRewardsArgsV0 {
+                    data_hash: [0; 32],
+                    creator_hash: [0; 32],
+                    root: [0; 32],
+                    index: 0,
+                },
+            }
+            .data(),
+        };
+        let message = |ixs: &[Instruction]| {
+            message::VersionedMessage::Legacy(Message::new_with_blockhash(
+                ixs,
+                Some(&owner),
+                &Hash::new_unique(),
+            ))
+        };
+        let claim = vec![
+            system_instruction::advance_nonce_account(&nonce_account, &owner),
+            priority_fee::compute_budget_instruction(CLAIM_BUDGET),
+            set_current,
+            distribute,
+        ];
+
+        let summary = decode_claim(&message(&claim)).expect("claim");
+        assert_eq!(summary.recipient, recipient);
+        assert_eq!(summary.lifetime_rewards, 42);
+        assert_eq!(summary.destination_account, destination_account);
+        assert_eq!(summary.nonce_account, Some(nonce_account));
+        assert!(!summary.init_recipient);
+
+        let mut drain = claim.clone();
+        drain.push(system_instruction::transfer(
+            &owner,
+            &Pubkey::new_unique(),
+            1,
+        ));
+        assert!(decode_claim(&message(&drain)).is_err());
+        assert!(decode_claim(&message(&claim[..3])).is_err());
+    }
+}
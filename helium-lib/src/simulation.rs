@@ -0,0 +1,168 @@
+use crate::{
+    anchor_lang::AccountDeserialize,
+    anchor_spl,
+    client::SolanaRpcClient,
+    error::Error,
+    keypair::{serde_pubkey, Pubkey},
+    solana_client::rpc_config::{
+        RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig,
+    },
+    solana_sdk::{
+        borsh1::try_from_slice_unchecked, compute_budget, compute_budget::ComputeBudgetInstruction,
+        message::VersionedMessage, transaction::VersionedTransaction,
+    },
+    token::{Token, TokenAmount},
+};
+use itertools::Itertools;
+use serde::Serialize;
+use solana_account_decoder::UiAccountEncoding;
+
+/// Lamports charged per required transaction signature
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5000;
+
+/// The change in balance of a token account caused by a transaction
+#[derive(Debug, Serialize, Clone)]
+pub struct TokenDelta {
+    #[serde(with = "serde_pubkey")]
+    pub address: Pubkey,
+    #[serde(with = "serde_pubkey")]
+    pub owner: Pubkey,
+    pub pre: TokenAmount,
+    pub post: TokenAmount,
+}
+
+impl TokenDelta {
+    pub fn token(&self) -> Token {
+        self.post.token
+    }
+
+    pub fn delta(&self) -> i128 {
+        i128::from(self.post.amount) - i128::from(self.pre.amount)
+    }
+}
+
+/// The expected outcome and cost of a transaction
+#[derive(Debug, Serialize, Clone)]
+pub struct Simulation {
+    pub err: Option<String>,
+    pub logs: Vec<String>,
+    /// Compute units consumed by the simulation
+    pub compute_units: Option<u64>,
+    /// Compute unit limit requested by the transaction
+    pub compute_unit_limit: Option<u32>,
+    /// Compute unit price in micro lamports requested by the transaction
+    pub compute_unit_price: Option<u64>,
+    /// Expected fee in lamports, including the priority fee
+    pub fee: u64,
+    /// Changes in token balances of the writable accounts of the transaction
+    pub token_deltas: Vec<TokenDelta>,
+}
+
+/// Compute budget (limit, price) requested by the given message, if any
+pub fn compute_budget(message: &VersionedMessage) -> (Option<u32>, Option<u64>) {
+    let account_keys = message.static_account_keys();
+    message
+        .instructions()
+        .iter()
+        .filter(|ix| {
+            account_keys.get(usize::from(ix.program_id_index)) == Some(&compute_budget::id())
+        })
+        .filter_map(|ix| try_from_slice_unchecked::<ComputeBudgetInstruction>(&ix.data).ok())
+        .fold((None, None), |(limit, price), ix| match ix {
+            ComputeBudgetInstruction::SetComputeUnitLimit(units) => (Some(units), price),
+            ComputeBudgetInstruction::SetComputeUnitPrice(micro_lamports) => {
+                (limit, Some(micro_lamports))
+            }
+            _ => (limit, price),
+        })
+}
+
+/// Expected fee in lamports for the given message
+pub fn fee(message: &VersionedMessage) -> u64 {
+    let signature_fee =
+        u64::from(message.header().num_required_signatures) * LAMPORTS_PER_SIGNATURE;
+    let priority_fee = match compute_budget(message) {
+        (Some(limit), Some(price)) => {
+            (u128::from(limit) * u128::from(price)).div_ceil(1_000_000) as u64
+        }
+        _ => 0,
+    };
+    signature_fee + priority_fee
+}
+
+fn token_account(data: &[u8]) -> Option<(Pubkey, Token, u64)> {
+    let account = anchor_spl::token::TokenAccount::try_deserialize(&mut &data[..]).ok()?;
+    let token = Token::from_mint(account.mint)?;
+    Some((account.owner, token, account.amount))
+}
+
+/// Simulate the given transaction and report its expected outcome, including
+/// the token balance changes of its writable accounts.
+pub async fn simulate<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    txn: &VersionedTransaction,
+) -> Result<Simulation, Error> {
+    let message = &txn.message;
+    let writable_keys = message
+        .static_account_keys()
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| message.is_maybe_writable(*index))
+        .map(|(_, key)| *key)
+        .unique()
+        .collect_vec();
+
+    let pre_accounts = client
+        .as_ref()
+        .get_multiple_accounts(&writable_keys)
+        .await?;
+    let config = RpcSimulateTransactionConfig {
+        accounts: Some(RpcSimulateTransactionAccountsConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            addresses: writable_keys.iter().map(ToString::to_string).collect(),
+        }),
+        ..Default::default()
+    };
+    let result = client
+        .as_ref()
+        .simulate_transaction_with_config(txn, config)
+        .await?
+        .value;
+
+    let post_accounts = result.accounts.unwrap_or_default();
+    let token_deltas = writable_keys
+        .iter()
+        .enumerate()
+        .filter_map(|(index, address)| {
+            let pre = pre_accounts
+                .get(index)
+                .and_then(Option::as_ref)
+                .and_then(|account| token_account(&account.data));
+            let post = post_accounts
+                .get(index)
+                .and_then(Option::as_ref)
+                .and_then(|account| account.data.decode())
+                .and_then(|data| token_account(&data));
+            let (owner, token, _) = post.or(pre)?;
+            let pre_amount = pre.map(|(_, _, amount)| amount).unwrap_or_default();
+            let post_amount = post.map(|(_, _, amount)| amount).unwrap_or_default();
+            (pre_amount != post_amount).then(|| TokenDelta {
+                address: *address,
+                owner,
+                pre: TokenAmount::from_u64(token, pre_amount),
+                post: TokenAmount::from_u64(token, post_amount),
+            })
+        })
+        .collect();
+
+    let (compute_unit_limit, compute_unit_price) = compute_budget(message);
+    Ok(Simulation {
+        err: result.err.map(|err| err.to_string()),
+        logs: result.logs.unwrap_or_default(),
+        compute_units: result.units_consumed,
+        compute_unit_limit,
+        compute_unit_price,
+        fee: fee(message),
+        token_deltas,
+    })
+}
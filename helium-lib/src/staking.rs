@@ -38,7 +38,7 @@ impl Client {
     /// timeout value.  The library will use absolute paths based on
     /// the given base_url.
     pub fn new_with_timeout(base_url: String, timeout: u64) -> Self {
-        let client = reqwest::Client::builder()
+        let client = crate::network::apply(reqwest::Client::builder())
             .gzip(true)
             .user_agent(USER_AGENT)
             .timeout(Duration::from_secs(timeout))
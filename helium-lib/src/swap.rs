@@ -0,0 +1,321 @@
+//! Token swaps through the [Jupiter](https://station.jup.ag/docs/apis/swap-api)
+//! aggregator.
+//!
+//! Jupiter quotes a route and returns the instructions for it. The swap
+//! transaction is built here from those instructions so it uses the priority
+//! fee and lookup tables of the given [`TransactionOpts`]. Only instructions
+//! of the Jupiter, token, associated token account and system programs are
+//! accepted from the API, so a compromised or misbehaving API can not have
+//! the wallet sign anything else.
+use crate::{
+    anchor_spl,
+    client::SolanaRpcClient,
+    error::{DecodeError, Error},
+    keypair::{pubkey, Keypair, Pubkey},
+    message, priority_fee,
+    solana_sdk::{
+        compute_budget,
+        instruction::{AccountMeta, Instruction},
+        signer::Signer,
+        system_program,
+        transaction::VersionedTransaction,
+    },
+    token::{Token, TokenAmount},
+    TransactionOpts,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+pub const JUPITER_URL: &str = "https://lite-api.jup.ag/swap/v1";
+pub const JUPITER_PROGRAM_ID: Pubkey = pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
+/// Environment variable to use a different Jupiter API, for example a self
+/// hosted one
+pub const JUPITER_URL_ENV: &str = "HELIUM_JUPITER_URL";
+/// Compute limit used when Jupiter does not report one for the route
+pub const SWAP_COMPUTE_LIMIT: u32 = 400_000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SwapError {
+    #[error("token can not be swapped: {0}")]
+    InvalidToken(Token),
+    #[error("can not swap {0} for itself")]
+    SameToken(Token),
+    #[error("jupiter: {0}")]
+    Api(String),
+    #[error("swap uses unexpected program {0}")]
+    UnexpectedProgram(Pubkey),
+}
+
+fn jupiter_url() -> String {
+    std::env::var(JUPITER_URL_ENV).unwrap_or_else(|_| JUPITER_URL.to_string())
+}
+
+/// The mint Jupiter knows a token by. SOL is swapped as wrapped SOL, which
+/// Jupiter wraps and unwraps in the swap transaction.
+fn swap_mint(token: Token) -> Result<Pubkey, SwapError> {
+    match token {
+        Token::Sol => Ok(anchor_spl::token::spl_token::native_mint::ID),
+        Token::Dc => Err(SwapError::InvalidToken(token)),
+        token => Ok(*token.mint()),
+    }
+}
+
+/// Programs the instructions of a swap transaction may call
+const ALLOWED_PROGRAMS: [Pubkey; 5] = [
+    JUPITER_PROGRAM_ID,
+    anchor_spl::token::ID,
+    spl_associated_token_account::ID,
+    compute_budget::ID,
+    system_program::ID,
+];
+
+/// Check that the given swap instructions only call allowed programs
+fn check_programs(ixs: &[Instruction]) -> Result<(), SwapError> {
+    match ixs
+        .iter()
+        .find(|ix| !ALLOWED_PROGRAMS.contains(&ix.program_id))
+    {
+        Some(ix) => Err(SwapError::UnexpectedProgram(ix.program_id)),
+        None => Ok(()),
+    }
+}
+
+/// A quoted swap route
+#[derive(Debug, Clone, Serialize)]
+pub struct Quote {
+    pub input: TokenAmount,
+    /// Expected output of the swap
+    pub output: TokenAmount,
+    /// Least output the swap accepts with the slippage of the quote
+    pub minimum_output: TokenAmount,
+    pub slippage_bps: u16,
+    /// Price impact of the swap in percent
+    pub price_impact: f64,
+    /// The quote as returned by Jupiter, which is passed back as is to get
+    /// the swap instructions
+    #[serde(skip)]
+    response: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuoteResponse {
+    out_amount: String,
+    other_amount_threshold: String,
+    #[serde(default)]
+    price_impact_pct: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiInstruction {
+    program_id: String,
+    accounts: Vec<ApiAccount>,
+    data: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiAccount {
+    pubkey: String,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+impl TryFrom<ApiInstruction> for Instruction {
+    type Error = DecodeError;
+    fn try_from(value: ApiInstruction) -> Result<Self, Self::Error> {
+        let accounts = value
+            .accounts
+            .into_iter()
+            .map(|account| {
+                Ok(AccountMeta {
+                    pubkey: Pubkey::from_str(&account.pubkey)?,
+                    is_signer: account.is_signer,
+                    is_writable: account.is_writable,
+                })
+            })
+            .collect::<Result<Vec<_>, DecodeError>>()?;
+        Ok(Instruction {
+            program_id: Pubkey::from_str(&value.program_id)?,
+            accounts,
+            data: STANDARD.decode(value.data)?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SwapInstructions {
+    #[serde(default)]
+    setup_instructions: Vec<ApiInstruction>,
+    swap_instruction: ApiInstruction,
+    cleanup_instruction: Option<ApiInstruction>,
+    #[serde(default)]
+    address_lookup_table_addresses: Vec<String>,
+    compute_unit_limit: Option<u32>,
+}
+
+/// Parse a Jupiter response, turning error responses into a [`SwapError`]
+async fn parse_response<T: serde::de::DeserializeOwned>(
+    response: reqwest::Response,
+) -> Result<T, Error> {
+    if response.status().is_success() {
+        return Ok(response.json().await?);
+    }
+    let status = response.status();
+    let message = match response.json::<ErrorResponse>().await {
+        Ok(ErrorResponse { error }) => error,
+        Err(_) => status.to_string(),
+    };
+    Err(SwapError::Api(message).into())
+}
+
+fn parse_amount(token: Token, amount: &str) -> Result<TokenAmount, DecodeError> {
+    amount
+        .parse()
+        .map(|amount| TokenAmount::from_u64(token, amount))
+        .map_err(|_| DecodeError::other(format!("invalid amount {amount}")))
+}
+
+/// Quote swapping the given amount for a token with the given maximum
+/// slippage in basis points
+pub async fn quote(input: TokenAmount, output: Token, slippage_bps: u16) -> Result<Quote, Error> {
+    if input.token == output {
+        return Err(SwapError::SameToken(output).into());
+    }
+    let (input_mint, output_mint) = (swap_mint(input.token)?, swap_mint(output)?);
+    let response: serde_json::Value = parse_response(
+        crate::client::http_client()
+            .get(format!("{}/quote", jupiter_url()))
+            .query(&[
+                ("inputMint", input_mint.to_string()),
+                ("outputMint", output_mint.to_string()),
+                ("amount", input.amount.to_string()),
+                ("slippageBps", slippage_bps.to_string()),
+            ])
+            .send()
+            .await?,
+    )
+    .await?;
+    let QuoteResponse {
+        out_amount,
+        other_amount_threshold,
+        price_impact_pct,
+    } = serde_json::from_value(response.clone())
+        .map_err(|err| DecodeError::other(format!("invalid quote: {err}")))?;
+    Ok(Quote {
+        input,
+        output: parse_amount(output, &out_amount)?,
+        minimum_output: parse_amount(output, &other_amount_threshold)?,
+        slippage_bps,
+        price_impact: price_impact_pct.parse::<f64>().unwrap_or_default() * 100.0,
+        response,
+    })
+}
+
+pub async fn swap_message<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    quote: &Quote,
+    owner: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<(message::VersionedMessage, u64), Error> {
+    let swap: SwapInstructions = parse_response(
+        crate::client::http_client()
+            .post(format!("{}/swap-instructions", jupiter_url()))
+            .json(&serde_json::json!({
+                "quoteResponse": quote.response,
+                "userPublicKey": owner.to_string(),
+                "wrapAndUnwrapSol": true,
+                "dynamicComputeUnitLimit": true,
+            }))
+            .send()
+            .await?,
+    )
+    .await?;
+
+    let swap_ix = Instruction::try_from(swap.swap_instruction)?;
+    let mut ixs = vec![
+        priority_fee::compute_budget_instruction(
+            swap.compute_unit_limit.unwrap_or(SWAP_COMPUTE_LIMIT),
+        ),
+        priority_fee::compute_price_instruction_for_accounts(
+            client,
+            &swap_ix.accounts,
+            opts.fee_range(),
+        )
+        .await?,
+    ];
+    for ix in swap.setup_instructions {
+        ixs.push(ix.try_into()?);
+    }
+    ixs.push(swap_ix);
+    if let Some(ix) = swap.cleanup_instruction {
+        ixs.push(ix.try_into()?);
+    }
+    check_programs(&ixs)?;
+
+    let mut lut_addresses = opts.lut_addresses.clone();
+    for address in swap.address_lookup_table_addresses {
+        lut_addresses.push(Pubkey::from_str(&address).map_err(DecodeError::from)?);
+    }
+    message::mk_message(client, &ixs, &lut_addresses, owner).await
+}
+
+pub async fn swap<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    quote: &Quote,
+    keypair: &Keypair,
+    opts: &TransactionOpts,
+) -> Result<(VersionedTransaction, u64), Error> {
+    let (msg, block_height) = swap_message(client, quote, &keypair.pubkey(), opts).await?;
+    let txn = VersionedTransaction::try_new(msg, &[keypair])?;
+    Ok((txn, block_height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_instruction() {
+        let ix: ApiInstruction = serde_json::from_value(serde_json::json!({
+            "programId": "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4",
+            "accounts": [{
+                "pubkey": "hntyVP6YFm1Hg25TN9WGLqM12b8TQmcknKrdu1oxWux",
+                "isSigner": false,
+                "isWritable": true,
+            }],
+            "data": "AQID",
+        }))
+        .expect("instruction");
+        let ix = Instruction::try_from(ix).expect("decoded");
+        assert_eq!(ix.data, vec![1, 2, 3]);
+        assert_eq!(ix.accounts[0].pubkey, *Token::Hnt.mint());
+        assert!(ix.accounts[0].is_writable);
+        assert!(swap_mint(Token::Dc).is_err());
+    }
+
+    #[test]
+    fn allowed_programs() {
+        let ix = |program_id| Instruction::new_with_bytes(program_id, &[], vec![]);
+        assert!(check_programs(&[
+            ix(compute_budget::ID),
+            ix(spl_associated_token_account::ID),
+            ix(JUPITER_PROGRAM_ID),
+            ix(anchor_spl::token::ID),
+        ])
+        .is_ok());
+        let other = Pubkey::new_unique();
+        assert!(matches!(
+            check_programs(&[ix(JUPITER_PROGRAM_ID), ix(other)]),
+            Err(SwapError::UnexpectedProgram(program)) if program == other
+        ));
+    }
+}
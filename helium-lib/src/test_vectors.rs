@@ -0,0 +1,124 @@
+//! Deterministic test vectors for the instruction builders.
+//!
+//! Each vector is the instruction produced by a builder for fixed inputs,
+//! stored as JSON with its program id, account metas and base64 encoded data.
+//! The checked-in fixtures in `test_vectors/` are compared against freshly
+//! built instructions, so changes in the generated program bindings that alter
+//! accounts or instruction data show up as test failures rather than as
+//! rejected transactions.
+//!
+//! A vector without a checked-in fixture fails the tests. Run the tests with
+//! `UPDATE_TEST_VECTORS` set to write missing fixtures, or to regenerate all
+//! fixtures after an intended change, and review the fixture diff before
+//! committing it.
+use crate::{
+    b64, error::Error, keypair::serde_pubkey, keypair::Pubkey, memo, priority_fee,
+    solana_sdk::instruction::Instruction, token,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountVector {
+    #[serde(with = "serde_pubkey")]
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionVector {
+    pub name: String,
+    #[serde(with = "serde_pubkey")]
+    pub program_id: Pubkey,
+    pub accounts: Vec<AccountVector>,
+    /// Base64 encoded instruction data
+    pub data: String,
+}
+
+impl InstructionVector {
+    pub fn new(name: &str, ix: &Instruction) -> Self {
+        Self {
+            name: name.to_string(),
+            program_id: ix.program_id,
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| AccountVector {
+                    pubkey: meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: b64::encode(&ix.data),
+        }
+    }
+}
+
+const OWNER: Pubkey = Pubkey::new_from_array([1; 32]);
+const PAYEE: Pubkey = Pubkey::new_from_array([2; 32]);
+const REFERENCE: Pubkey = Pubkey::new_from_array([3; 32]);
+
+/// Build the test vectors for all instruction builders using fixed inputs
+pub async fn generate() -> Result<Vec<InstructionVector>, Error> {
+    let mut vectors = vec![
+        InstructionVector::new(
+            "compute_budget",
+            &priority_fee::compute_budget_instruction(200_000),
+        ),
+        InstructionVector::new(
+            "compute_price",
+            &priority_fee::compute_price_instruction(1_000),
+        ),
+        InstructionVector::new("memo", &memo::memo_instruction("helium", &OWNER)),
+        InstructionVector::new(
+            "token_burn_iot",
+            &token::burn_instruction(&token::Token::Iot.amount(1_000_000), &OWNER)?,
+        ),
+    ];
+
+    let transfers = [
+        ("transfer_sol", token::Token::Sol.amount(1_000_000)),
+        ("transfer_hnt", token::Token::Hnt.amount(100_000_000)),
+    ];
+    for (name, amount) in transfers {
+        let ixs = token::transfer_instructions(&[(PAYEE, amount)], None, &[REFERENCE], &OWNER)?;
+        vectors.extend(
+            ixs.iter()
+                .enumerate()
+                .map(|(index, ix)| InstructionVector::new(&format!("{name}_{index}"), ix)),
+        );
+    }
+
+    Ok(vectors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::{fs, path::Path};
+
+    #[test]
+    fn instruction_vectors() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_vectors");
+        let update = std::env::var_os("UPDATE_TEST_VECTORS").is_some();
+        for vector in block_on(generate()).expect("test vectors") {
+            let path = dir.join(format!("{}.json", vector.name));
+            if update {
+                fs::create_dir_all(&dir).expect("test vector directory");
+                let json = serde_json::to_string_pretty(&vector).expect("test vector json");
+                fs::write(&path, json + "\n").expect("test vector write");
+                continue;
+            }
+            assert!(
+                path.exists(),
+                "missing fixture for test vector {}, run the tests with UPDATE_TEST_VECTORS set to write it",
+                vector.name
+            );
+            let file = fs::File::open(&path).expect("test vector fixture");
+            let expected: InstructionVector =
+                serde_json::from_reader(file).expect("test vector fixture json");
+            assert_eq!(expected, vector, "test vector {} changed", vector.name);
+        }
+    }
+}
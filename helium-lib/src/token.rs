@@ -5,8 +5,16 @@ use crate::{
     error::{DecodeError, Error},
     keypair::{serde_pubkey, Keypair, Pubkey},
     message,
+    programs::TOKEN_METADATA_PROGRAM_ID,
+    solana_client::{
+        rpc_request::RpcRequest,
+        rpc_response::{Response as RpcResponse, RpcKeyedAccount},
+    },
     solana_sdk::{
-        commitment_config::CommitmentConfig, signer::Signer, system_instruction,
+        commitment_config::CommitmentConfig,
+        instruction::{AccountMeta, Instruction},
+        signer::Signer,
+        system_instruction,
         transaction::VersionedTransaction,
     },
     TransactionOpts,
@@ -14,12 +22,23 @@ use crate::{
 use chrono::{DateTime, Duration, Utc};
 use futures::stream::{self, StreamExt, TryStreamExt};
 use helium_anchor_gen::circuit_breaker;
-use std::{collections::HashMap, result::Result as StdResult, str::FromStr};
+use itertools::Itertools;
+use std::{collections::HashMap, ops::Range, result::Result as StdResult, str::FromStr};
 
 #[derive(Debug, thiserror::Error)]
 pub enum TokenError {
     #[error("Invalid token type: {0}")]
     InvalidToken(String),
+    #[error("Invalid token amount: {0}")]
+    InvalidAmount(String),
+    #[error("Expected an amount of {expected}, got {got}")]
+    UnexpectedToken { expected: Token, got: Token },
+    #[error("{0} is not a token mint")]
+    NotMint(Pubkey),
+    #[error("{0} is not a token account")]
+    NotTokenAccount(Pubkey),
+    #[error("{0} not supported for SOL")]
+    NativeUnsupported(&'static str),
 }
 
 lazy_static::lazy_static! {
@@ -36,95 +55,401 @@ lazy_static::lazy_static! {
     static ref IOT_PRICE_FEED: price::FeedId = price::feed_from_hex("6b701e292e0836d18a5904a08fe94534f9ab5c3d4ff37dc02c74dd0f4901944d").unwrap();
 
     static ref DC_MINT: Pubkey = Pubkey::from_str("dcuc8Amr83Wz27ZkQ2K9NS6r8zRpf1J6cvArEBDZDmm").unwrap();
+
     static ref SOL_MINT: Pubkey = solana_sdk::system_program::ID;
+    static ref SOL_PRICE_KEY: Pubkey = Pubkey::from_str("7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE").unwrap();
+    static ref SOL_PRICE_FEED: price::FeedId = price::feed_from_hex("ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d").unwrap();
+
+    static ref USDC_MINT: Pubkey = Pubkey::from_str("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").unwrap();
+    static ref USDC_PRICE_KEY: Pubkey = Pubkey::from_str("Dpw1EAVrSB1ibxiDQyTAW6Zip3J4Btk2x4SgApQCeFbX").unwrap();
+    static ref USDC_PRICE_FEED: price::FeedId = price::feed_from_hex("eaa020c61cc479712813461ce153894a96a6c00b21ed0cfc2798d1f9a9e9c94a").unwrap();
 }
 
-pub async fn burn_message<C: AsRef<SolanaRpcClient>>(
-    client: &C,
-    token_amount: &TokenAmount,
-    payer: &Pubkey,
-    opts: &TransactionOpts,
-) -> Result<(message::VersionedMessage, u64), Error> {
+/// Whether the given program is the classic SPL token program or the
+/// Token-2022 program
+pub fn is_token_program(program: &Pubkey) -> bool {
+    *program == anchor_spl::token::spl_token::id()
+        || *program == anchor_spl::token_2022::spl_token_2022::id()
+}
+
+/// The token program owning the given mint account, failing for accounts
+/// that are not owned by a token program.
+///
+/// Mints owned by the Token-2022 program have their associated token accounts
+/// derived with, and their transfers executed by, that program instead of the
+/// classic SPL token program.
+fn mint_program(mint: &Pubkey, account: &solana_sdk::account::Account) -> Result<Pubkey, Error> {
+    if !is_token_program(&account.owner) {
+        return Err(TokenError::NotMint(*mint).into());
+    }
+    Ok(account.owner)
+}
+
+/// The associated token account of the given owner for a mint owned by the
+/// given token program
+pub fn associated_token_address_with_program(
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Pubkey {
+    spl_associated_token_account::get_associated_token_address_with_program_id(
+        owner,
+        mint,
+        token_program,
+    )
+}
+
+/// Read an SPL token from its mint account. Token-2022 mints with the
+/// transfer fee extension carry the fee in effect in the given epoch.
+fn spl_token_from_account(
+    mint: &Pubkey,
+    account: &solana_sdk::account::Account,
+    epoch: u64,
+) -> Result<SplToken, Error> {
+    use anchor_spl::token_2022::spl_token_2022::{
+        extension::{
+            transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions,
+        },
+        state::Mint,
+    };
+    let program = mint_program(mint, account)?;
+    if program != anchor_spl::token_2022::spl_token_2022::id() {
+        let mint_account =
+            anchor_spl::token_interface::Mint::try_deserialize(&mut account.data.as_slice())?;
+        return Ok(SplToken {
+            mint: *mint,
+            decimals: mint_account.decimals,
+            program,
+            transfer_fee: None,
+        });
+    }
+    let state = StateWithExtensions::<Mint>::unpack(&account.data)?;
+    let transfer_fee = state
+        .get_extension::<TransferFeeConfig>()
+        .ok()
+        .map(|config| {
+            let fee = config.get_epoch_fee(epoch);
+            TransferFee {
+                basis_points: u16::from(fee.transfer_fee_basis_points),
+                maximum_fee: u64::from(fee.maximum_fee),
+            }
+        })
+        .filter(|fee| fee.basis_points > 0 && fee.maximum_fee > 0);
+    Ok(SplToken {
+        mint: *mint,
+        decimals: state.base.decimals,
+        program,
+        transfer_fee,
+    })
+}
+
+/// Read the name and symbol from the token metadata extension of a
+/// Token-2022 mint account, if it has one
+fn token_2022_metadata(account: &solana_sdk::account::Account) -> Option<(String, String)> {
+    use anchor_spl::token_2022::spl_token_2022::{
+        extension::{BaseStateWithExtensions, StateWithExtensions},
+        state::Mint,
+    };
+    use spl_token_metadata_interface::state::TokenMetadata;
+    if account.owner != anchor_spl::token_2022::spl_token_2022::id() {
+        return None;
+    }
+    let state = StateWithExtensions::<Mint>::unpack(&account.data).ok()?;
+    let metadata = state.get_variable_len_extension::<TokenMetadata>().ok()?;
+    Some((metadata.name, metadata.symbol))
+}
+
+/// Build a checked transfer instruction for the given token program
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_checked_instruction(
+    token_program: &Pubkey,
+    source: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Result<Instruction, Error> {
+    let ix = if *token_program == anchor_spl::token_2022::spl_token_2022::id() {
+        anchor_spl::token_2022::spl_token_2022::instruction::transfer_checked(
+            token_program,
+            source,
+            mint,
+            destination,
+            authority,
+            &[],
+            amount,
+            decimals,
+        )?
+    } else {
+        anchor_spl::token::spl_token::instruction::transfer_checked(
+            token_program,
+            source,
+            mint,
+            destination,
+            authority,
+            &[],
+            amount,
+            decimals,
+        )?
+    };
+    Ok(ix)
+}
+
+/// Build the instruction burning the given token amount held by the payer
+pub fn burn_instruction(token_amount: &TokenAmount, payer: &Pubkey) -> Result<Instruction, Error> {
     let ix = match token_amount.token.mint() {
         spl_mint if spl_mint == Token::Sol.mint() => {
-            return Err(DecodeError::other("native token burn not supported").into());
+            return Err(TokenError::NativeUnsupported("burn").into());
         }
         spl_mint => {
+            let token_program = token_amount.token.token_program();
             let token_account = token_amount.token.associated_token_adress(payer);
-            anchor_spl::token::spl_token::instruction::burn_checked(
-                &anchor_spl::token::spl_token::id(),
-                &token_account,
-                spl_mint,
-                payer,
-                &[payer],
-                token_amount.amount,
-                token_amount.token.decimals(),
-            )?
+            if token_program == anchor_spl::token_2022::spl_token_2022::id() {
+                anchor_spl::token_2022::spl_token_2022::instruction::burn_checked(
+                    &token_program,
+                    &token_account,
+                    spl_mint,
+                    payer,
+                    &[],
+                    token_amount.amount,
+                    token_amount.token.decimals(),
+                )?
+            } else {
+                anchor_spl::token::spl_token::instruction::burn_checked(
+                    &token_program,
+                    &token_account,
+                    spl_mint,
+                    payer,
+                    &[],
+                    token_amount.amount,
+                    token_amount.token.decimals(),
+                )?
+            }
         }
     };
+    Ok(ix)
+}
 
-    message::mk_message(client, &[ix], &opts.lut_addresses, payer).await
+/// Build the instructions burning the given token amount held by the payer,
+/// with an optional memo ahead of the burn
+pub fn burn_instructions(
+    token_amount: &TokenAmount,
+    memo: Option<&str>,
+    payer: &Pubkey,
+) -> Result<Vec<Instruction>, Error> {
+    let mut ixs = vec![];
+    if let Some(memo) = memo {
+        ixs.push(crate::memo::memo_instruction(memo, payer));
+    }
+    ixs.push(burn_instruction(token_amount, payer)?);
+    Ok(ixs)
+}
+
+pub async fn burn_message<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    token_amount: &TokenAmount,
+    memo: Option<&str>,
+    payer: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<(message::VersionedMessage, u64), Error> {
+    let ixs = burn_instructions(token_amount, memo, payer)?;
+    message::mk_message(client, &ixs, &opts.lut_addresses, payer).await
 }
 
 pub async fn burn<C: AsRef<SolanaRpcClient>>(
     client: &C,
     token_amount: &TokenAmount,
+    memo: Option<&str>,
     keypair: &Keypair,
     opts: &TransactionOpts,
 ) -> Result<(VersionedTransaction, u64), Error> {
-    let (msg, block_height) = burn_message(client, token_amount, &keypair.pubkey(), opts).await?;
+    let (msg, block_height) =
+        burn_message(client, token_amount, memo, &keypair.pubkey(), opts).await?;
     let txn = VersionedTransaction::try_new(msg, &[keypair])?;
     Ok((txn, block_height))
 }
 
-pub async fn transfer_message<C: AsRef<SolanaRpcClient>>(
-    client: &C,
+/// Build the instructions transferring the given token amounts to their
+/// payees.
+///
+/// An optional memo is added as a memo instruction ahead of the transfers, and
+/// any given reference keys are added as read-only accounts to each transfer
+/// instruction so the payment can be located by reference, as described by
+/// the Solana Pay specification.
+pub fn transfer_instructions(
     transfers: &[(Pubkey, TokenAmount)],
+    memo: Option<&str>,
+    references: &[Pubkey],
     payer: &Pubkey,
-    opts: &TransactionOpts,
-) -> Result<(message::VersionedMessage, u64), Error> {
+) -> Result<Vec<Instruction>, Error> {
+    let reference_metas: Vec<AccountMeta> = references
+        .iter()
+        .map(|reference| AccountMeta::new_readonly(*reference, false))
+        .collect();
     let mut ixs = vec![];
+    if let Some(memo) = memo {
+        ixs.push(spl_memo::build_memo(memo.as_bytes(), &[payer]));
+    }
     for (payee, token_amount) in transfers {
         match token_amount.token.mint() {
             spl_mint if spl_mint == Token::Sol.mint() => {
-                let ix = system_instruction::transfer(payer, payee, token_amount.amount);
+                let mut ix = system_instruction::transfer(payer, payee, token_amount.amount);
+                ix.accounts.extend_from_slice(&reference_metas);
                 ixs.push(ix);
             }
             spl_mint => {
                 let source_pubkey = token_amount.token.associated_token_adress(payer);
                 let destination_pubkey = token_amount.token.associated_token_adress(payee);
+                let token_program = token_amount.token.token_program();
                 let ix = spl_associated_token_account::instruction::create_associated_token_account_idempotent(
                     payer,
                     payee,
                     spl_mint,
-                    &anchor_spl::token::spl_token::id(),
+                    &token_program,
                 );
                 ixs.push(ix);
 
-                let ix = anchor_spl::token::spl_token::instruction::transfer_checked(
-                    &anchor_spl::token::spl_token::id(),
-                    &source_pubkey,
-                    token_amount.token.mint(),
-                    &destination_pubkey,
-                    payer,
-                    &[],
-                    token_amount.amount,
-                    token_amount.token.decimals(),
-                )?;
+                let mut ix = match token_amount.token.transfer_fee() {
+                    // The fee is stated so the transfer fails rather than
+                    // withholding more than expected if the mint fee changes
+                    Some(fee) => anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::instruction::transfer_checked_with_fee(
+                        &token_program,
+                        &source_pubkey,
+                        spl_mint,
+                        &destination_pubkey,
+                        payer,
+                        &[],
+                        token_amount.amount,
+                        token_amount.token.decimals(),
+                        fee.calculate(token_amount.amount),
+                    )?,
+                    None => transfer_checked_instruction(
+                        &token_program,
+                        &source_pubkey,
+                        spl_mint,
+                        &destination_pubkey,
+                        payer,
+                        token_amount.amount,
+                        token_amount.token.decimals(),
+                    )?,
+                };
+                ix.accounts.extend_from_slice(&reference_metas);
                 ixs.push(ix);
             }
         }
     }
+    Ok(ixs)
+}
+
+/// Construct a message transferring the given token amounts to their payees.
+///
+/// See [`transfer_instructions`] for how the memo and references are added.
+pub async fn transfer_message<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    transfers: &[(Pubkey, TokenAmount)],
+    memo: Option<&str>,
+    references: &[Pubkey],
+    payer: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<(message::VersionedMessage, u64), Error> {
+    let ixs = transfer_instructions(transfers, memo, references, payer)?;
     message::mk_message(client, &ixs, &opts.lut_addresses, payer).await
 }
 
 pub async fn transfer<C: AsRef<SolanaRpcClient>>(
     client: &C,
     transfers: &[(Pubkey, TokenAmount)],
+    memo: Option<&str>,
+    references: &[Pubkey],
+    keypair: &Keypair,
+    opts: &TransactionOpts,
+) -> Result<(VersionedTransaction, u64), Error> {
+    let (msg, block_height) =
+        transfer_message(client, transfers, memo, references, &keypair.pubkey(), opts).await?;
+    let txn = VersionedTransaction::try_new(msg, &[keypair])?;
+    Ok((txn, block_height))
+}
+
+/// A single payment of a batch, with its own memo
+#[derive(Debug, Clone)]
+pub struct Payment {
+    pub payee: Pubkey,
+    pub amount: TokenAmount,
+    pub memo: Option<String>,
+}
+
+impl Payment {
+    pub fn instructions(
+        &self,
+        references: &[Pubkey],
+        payer: &Pubkey,
+    ) -> Result<Vec<Instruction>, Error> {
+        transfer_instructions(
+            &[(self.payee, self.amount)],
+            self.memo.as_deref(),
+            references,
+            payer,
+        )
+    }
+}
+
+/// Group the given payments, in order, into as few transactions as
+/// possible. Each returned range of payments fits in a single transaction
+/// with the given lookup tables. A payment that does not fit in a
+/// transaction on its own gets a range by itself and fails to build.
+pub async fn pack_payments<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    payments: &[Payment],
+    references: &[Pubkey],
+    payer: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<Vec<Range<usize>>, Error> {
+    let lut_accounts = message::get_lut_accounts(client, &opts.lut_addresses).await?;
+    let mut ranges = vec![];
+    let mut start = 0;
+    let mut ixs = vec![];
+    for (index, payment) in payments.iter().enumerate() {
+        let payment_ixs = payment.instructions(references, payer)?;
+        let mut candidate = ixs.clone();
+        candidate.extend_from_slice(&payment_ixs);
+        if index == start || message::fits(&candidate, &lut_accounts, payer) {
+            ixs = candidate;
+            continue;
+        }
+        ranges.push(start..index);
+        start = index;
+        ixs = payment_ixs;
+    }
+    if start < payments.len() {
+        ranges.push(start..payments.len());
+    }
+    Ok(ranges)
+}
+
+pub async fn transfer_payments_message<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    payments: &[Payment],
+    references: &[Pubkey],
+    payer: &Pubkey,
+    opts: &TransactionOpts,
+) -> Result<(message::VersionedMessage, u64), Error> {
+    let mut ixs = vec![];
+    for payment in payments {
+        ixs.extend(payment.instructions(references, payer)?);
+    }
+    message::mk_message(client, &ixs, &opts.lut_addresses, payer).await
+}
+
+pub async fn transfer_payments<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    payments: &[Payment],
+    references: &[Pubkey],
     keypair: &Keypair,
     opts: &TransactionOpts,
 ) -> Result<(VersionedTransaction, u64), Error> {
-    let (msg, block_height) = transfer_message(client, transfers, &keypair.pubkey(), opts).await?;
+    let (msg, block_height) =
+        transfer_payments_message(client, payments, references, &keypair.pubkey(), opts).await?;
     let txn = VersionedTransaction::try_new(msg, &[keypair])?;
     Ok((txn, block_height))
 }
@@ -142,13 +467,14 @@ pub async fn balance_for_address<C: AsRef<SolanaRpcClient>>(
         Some(account) if account.owner == solana_sdk::system_program::ID => {
             Ok(Some(Token::Sol.to_balance(*pubkey, account.lamports)))
         }
-        Some(account) => {
-            let token_account =
-                anchor_spl::token::TokenAccount::try_deserialize(&mut account.data.as_slice())?;
-            let token = Token::from_mint(token_account.mint)
-                .ok_or_else(|| DecodeError::other("Invalid mint"))?;
+        Some(account) if is_token_program(&account.owner) => {
+            let token_account = anchor_spl::token_interface::TokenAccount::try_deserialize(
+                &mut account.data.as_slice(),
+            )?;
+            let token = Token::for_mint(client, &token_account.mint).await?;
             Ok(Some(token.to_balance(*pubkey, token_account.amount)))
         }
+        Some(_) => Err(TokenError::NotTokenAccount(*pubkey).into()),
         None => Ok(None),
     }
 }
@@ -165,6 +491,230 @@ pub async fn balance_for_addresses<C: AsRef<SolanaRpcClient>>(
         .await
 }
 
+/// A token account of an owner, with the name and symbol from the token
+/// metadata of its mint if it has any
+#[derive(Debug, serde::Serialize)]
+pub struct TokenAccountBalance {
+    #[serde(with = "serde_pubkey")]
+    pub address: Pubkey,
+    #[serde(with = "serde_pubkey")]
+    pub mint: Pubkey,
+    pub symbol: Option<String>,
+    pub name: Option<String>,
+    pub decimals: u8,
+    #[serde(serialize_with = "crate::token::serde_amount_value")]
+    pub amount: TokenAmount,
+}
+
+/// Read a borsh encoded string from the front of the given data
+fn read_borsh_string(data: &mut &[u8]) -> Option<String> {
+    let len = u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize;
+    let bytes = data.get(4..4 + len)?;
+    let str = String::from_utf8_lossy(bytes)
+        .trim_end_matches('\0')
+        .to_string();
+    *data = &data[4 + len..];
+    Some(str)
+}
+
+/// Read the name and symbol of a token metadata account. The key, update
+/// authority and mint of the account come before them.
+fn parse_token_metadata(data: &[u8]) -> Option<(String, String)> {
+    let mut data = data.get(1 + 32 + 32..)?;
+    let name = read_borsh_string(&mut data)?;
+    let symbol = read_borsh_string(&mut data)?;
+    Some((name, symbol))
+}
+
+fn token_metadata_key(mint: &Pubkey) -> Pubkey {
+    let (metadata, _bump) = Pubkey::find_program_address(
+        &[
+            b"metadata",
+            TOKEN_METADATA_PROGRAM_ID.as_ref(),
+            mint.as_ref(),
+        ],
+        &TOKEN_METADATA_PROGRAM_ID,
+    );
+    metadata
+}
+
+/// Get every token account held by the given owner, of both the classic SPL
+/// token program and the Token-2022 program, including tokens that are not
+/// built in
+pub async fn token_accounts_for_owner<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    owner: &Pubkey,
+) -> Result<Vec<TokenAccountBalance>, Error> {
+    let solana_client = client.as_ref();
+    let mut token_accounts = vec![];
+    for program in [
+        anchor_spl::token::spl_token::id(),
+        anchor_spl::token_2022::spl_token_2022::id(),
+    ] {
+        let response: RpcResponse<Vec<RpcKeyedAccount>> = solana_client
+            .send(
+                RpcRequest::GetTokenAccountsByOwner,
+                serde_json::json!([
+                    owner.to_string(),
+                    { "programId": program.to_string() },
+                    { "encoding": "base64", "commitment": "confirmed" },
+                ]),
+            )
+            .await?;
+        for keyed_account in response.value {
+            let Some(account) = keyed_account
+                .account
+                .decode::<solana_sdk::account::Account>()
+            else {
+                continue;
+            };
+            let token_account = anchor_spl::token_interface::TokenAccount::try_deserialize(
+                &mut account.data.as_slice(),
+            )?;
+            let address = Pubkey::from_str(&keyed_account.pubkey)
+                .map_err(|_| DecodeError::other("invalid token account address"))?;
+            token_accounts.push((address, token_account, program));
+        }
+    }
+
+    let mints: Vec<Pubkey> = token_accounts
+        .iter()
+        .map(|(_, token_account, _)| token_account.mint)
+        .unique()
+        .collect();
+    let metadata_keys: Vec<Pubkey> = mints.iter().map(token_metadata_key).collect();
+    let mut mint_accounts = vec![];
+    let mut metadata_accounts = vec![];
+    for (mint_chunk, metadata_chunk) in mints.chunks(100).zip(metadata_keys.chunks(100)) {
+        mint_accounts.extend(solana_client.get_multiple_accounts(mint_chunk).await?);
+        metadata_accounts.extend(solana_client.get_multiple_accounts(metadata_chunk).await?);
+    }
+    // Transfer fees of Token-2022 mints depend on the epoch
+    let epoch = if token_accounts
+        .iter()
+        .any(|(_, _, program)| *program == anchor_spl::token_2022::spl_token_2022::id())
+    {
+        solana_client.get_epoch_info().await?.epoch
+    } else {
+        0
+    };
+    let mut mint_info = HashMap::new();
+    for ((mint, mint_account), metadata_account) in
+        mints.iter().zip(mint_accounts).zip(metadata_accounts)
+    {
+        let Some(mint_account) = mint_account else {
+            continue;
+        };
+        let spl = spl_token_from_account(mint, &mint_account, epoch)?;
+        // Token-2022 mints can carry their metadata in an extension of the
+        // mint itself rather than in a token metadata account
+        let metadata = token_2022_metadata(&mint_account)
+            .or_else(|| metadata_account.and_then(|account| parse_token_metadata(&account.data)))
+            .filter(|(name, symbol)| !name.is_empty() || !symbol.is_empty());
+        mint_info.insert(*mint, (spl, metadata));
+    }
+
+    Ok(token_accounts
+        .into_iter()
+        .filter_map(|(address, token_account, _)| {
+            let (spl, metadata) = mint_info.get(&token_account.mint)?;
+            let token = Token::from_mint(token_account.mint).unwrap_or(Token::Spl(*spl));
+            let (name, symbol) = metadata.clone().unzip();
+            Some(TokenAccountBalance {
+                address,
+                mint: token_account.mint,
+                symbol,
+                name,
+                decimals: spl.decimals,
+                amount: token.amount(token_account.amount),
+            })
+        })
+        .collect())
+}
+
+/// Most token accounts returned by the RPC largest accounts query
+pub const MAX_HOLDERS: usize = 20;
+
+#[derive(Debug, serde::Serialize)]
+pub struct TokenHolder {
+    #[serde(with = "serde_pubkey")]
+    pub address: Pubkey,
+    #[serde(with = "crate::keypair::serde_opt_pubkey")]
+    pub owner: Option<Pubkey>,
+    #[serde(serialize_with = "crate::token::serde_amount_value")]
+    pub amount: TokenAmount,
+    /// Fraction of the current supply held by the account
+    pub supply_share: f64,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct TokenHolders {
+    #[serde(serialize_with = "crate::token::serde_amount_value")]
+    pub supply: TokenAmount,
+    pub holders: Vec<TokenHolder>,
+}
+
+fn parse_ui_amount(token: Token, amount: &str) -> Result<TokenAmount, Error> {
+    amount
+        .parse()
+        .map(|amount| token.amount(amount))
+        .map_err(|_| TokenError::InvalidAmount(amount.to_string()).into())
+}
+
+/// Get the largest token accounts for the given token with their owners and
+/// share of the supply, largest first.
+///
+/// The RPC query used returns at most [`MAX_HOLDERS`] accounts. Holders are
+/// token accounts, so a wallet with several accounts for the mint may show up
+/// more than once.
+pub async fn holders<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    token: Token,
+    limit: usize,
+) -> Result<TokenHolders, Error> {
+    if token == Token::Sol {
+        return Err(TokenError::NativeUnsupported("token holders").into());
+    }
+    let solana_client = client.as_ref();
+    let supply = solana_client.get_token_supply(token.mint()).await?;
+    let supply = parse_ui_amount(token, &supply.amount)?;
+    let largest = solana_client
+        .get_token_largest_accounts(token.mint())
+        .await?;
+
+    let mut holders = vec![];
+    for balance in largest.into_iter().take(limit.min(MAX_HOLDERS)) {
+        let address = Pubkey::from_str(&balance.address).map_err(DecodeError::from)?;
+        let amount = parse_ui_amount(token, &balance.amount.amount)?;
+        let supply_share = if supply.amount == 0 {
+            0.0
+        } else {
+            amount.amount as f64 / supply.amount as f64
+        };
+        holders.push(TokenHolder {
+            address,
+            owner: None,
+            amount,
+            supply_share,
+        });
+    }
+
+    let addresses: Vec<Pubkey> = holders.iter().map(|holder| holder.address).collect();
+    let accounts = solana_client.get_multiple_accounts(&addresses).await?;
+    for (holder, account) in holders.iter_mut().zip(accounts) {
+        holder.owner = account
+            .and_then(|account| {
+                anchor_spl::token_interface::TokenAccount::try_deserialize(
+                    &mut account.data.as_slice(),
+                )
+                .ok()
+            })
+            .map(|token_account| token_account.owner);
+    }
+
+    Ok(TokenHolders { supply, holders })
+}
+
 pub mod price {
     use super::*;
     use pyth_solana_receiver_sdk::price_update::{self, PriceUpdateV2};
@@ -187,6 +737,21 @@ pub mod price {
         InvalidTimestamp(i64),
         #[error("unsupported positive price exponent")]
         PositiveExponent,
+        #[error("price interval must be at least a minute")]
+        InvalidInterval,
+        #[error("no price found for {0}")]
+        NoData(DateTime<Utc>),
+        #[error("invalid price source: {0}")]
+        InvalidSource(String),
+        #[error("no price source available: {}", format_skipped(.0))]
+        NoSource(Vec<SkippedSource>),
+    }
+
+    fn format_skipped(skipped: &[SkippedSource]) -> String {
+        skipped
+            .iter()
+            .map(|skipped| format!("{}: {}", skipped.source, skipped.error))
+            .join(", ")
     }
 
     #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -202,45 +767,95 @@ pub mod price {
         Ok(feed_id)
     }
 
-    pub async fn get_with_max_age<C: AsRef<SolanaRpcClient>>(
+    /// A pyth price feed for a token
+    #[derive(Debug, Clone, serde::Serialize)]
+    pub struct Feed {
+        pub token: super::Token,
+        /// The price update account of the feed
+        #[serde(with = "serde_pubkey")]
+        pub account: Pubkey,
+        /// The hex encoded pyth feed id
+        pub feed_id: String,
+    }
+
+    /// The latest spot and EMA prices reported by a pyth price feed
+    #[derive(Debug, Clone, serde::Serialize)]
+    pub struct FeedInfo {
+        #[serde(flatten)]
+        pub feed: Feed,
+        pub publish_time: DateTime<Utc>,
+        pub price: Decimal,
+        pub confidence: Decimal,
+        pub ema_price: Decimal,
+        pub ema_confidence: Decimal,
+    }
+
+    /// All tokens with a known pyth price feed
+    pub fn feeds() -> Vec<Feed> {
+        Token::all()
+            .into_iter()
+            .filter_map(|token| token.price_feed_info())
+            .collect()
+    }
+
+    pub async fn get_feed_info<C: AsRef<SolanaRpcClient>>(
         client: &C,
         token: Token,
-        max_age: Duration,
-    ) -> Result<Price, Error> {
+    ) -> Result<FeedInfo, Error> {
         use helium_anchor_gen::anchor_lang::AccountDeserialize;
-        let price_key = token.price_key().ok_or(PriceError::InvalidToken(token))?;
+        let feed = token
+            .price_feed_info()
+            .ok_or(PriceError::InvalidToken(token))?;
         let price_feed = token.price_feed().ok_or(PriceError::InvalidToken(token))?;
-        let account = client.as_ref().get_account(price_key).await?;
+        let account = client.as_ref().get_account(&feed.account).await?;
         let PriceUpdateV2 { price_message, .. } =
             PriceUpdateV2::try_deserialize(&mut account.data.as_slice())?;
 
-        if (price_message
+        if price_message.exponent > 0 {
+            return Err(PriceError::PositiveExponent.into());
+        }
+        if price_message.feed_id != *price_feed {
+            return Err(PriceError::InvalidFeed.into());
+        }
+        let scale = price_message.exponent.unsigned_abs();
+        let publish_time = DateTime::from_timestamp(price_message.publish_time, 0)
+            .ok_or(PriceError::InvalidTimestamp(price_message.publish_time))?;
+
+        Ok(FeedInfo {
+            feed,
+            publish_time,
+            price: Decimal::new(price_message.price, scale),
+            confidence: Decimal::new(price_message.conf as i64, scale),
+            ema_price: Decimal::new(price_message.ema_price, scale),
+            ema_confidence: Decimal::new(price_message.ema_conf as i64, scale),
+        })
+    }
+
+    pub async fn get_with_max_age<C: AsRef<SolanaRpcClient>>(
+        client: &C,
+        token: Token,
+        max_age: Duration,
+    ) -> Result<Price, Error> {
+        let info = get_feed_info(client, token).await?;
+
+        if (info
             .publish_time
+            .timestamp()
             .saturating_add(max_age.num_seconds()))
             < Utc::now().timestamp()
         {
             return Err(PriceError::TooOld.into());
         }
-        if price_message.ema_price < 0 {
+        if info.ema_price.is_sign_negative() {
             return Err(PriceError::Negative.into());
         }
-        if price_message.exponent > 0 {
-            return Err(PriceError::PositiveExponent.into());
-        }
-        if price_message.feed_id != *price_feed {
-            return Err(PriceError::InvalidFeed.into());
-        }
-        let scale = price_message.exponent.unsigned_abs();
         // Remove the confidence interval from the price to get the most optimistic price:
-        let mut price = Decimal::new(price_message.ema_price, scale)
-            + Decimal::new(price_message.ema_conf as i64, scale) * Decimal::new(2, 0);
+        let mut price = info.ema_price + info.ema_confidence * Decimal::new(2, 0);
         // ensure we use only up to 6 decimals, this rounds using `MidpointAwayFromZero`
         price.rescale(6);
-        let timestamp = DateTime::from_timestamp(price_message.publish_time, 0)
-            .ok_or(PriceError::InvalidTimestamp(price_message.publish_time))?;
 
         Ok(Price {
-            timestamp,
+            timestamp: info.publish_time,
             price,
             token,
         })
@@ -249,19 +864,502 @@ pub mod price {
     pub async fn get<C: AsRef<SolanaRpcClient>>(client: &C, token: Token) -> Result<Price, Error> {
         get_with_max_age(client, token, Duration::minutes(10)).await
     }
+
+    pub const PYTH_BENCHMARKS_URL: &str = "https://benchmarks.pyth.network";
+    pub const COINGECKO_URL: &str = "https://api.coingecko.com/api/v3";
+
+    /// Switchboard crossbar service simulating on-demand feeds
+    pub const SWITCHBOARD_CROSSBAR_URL: &str = "https://crossbar.switchboard.xyz";
+
+    /// Sources of current USD prices, tried in order by [`get_with_fallback`]
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum LiveSource {
+        /// The EMA price of the on-chain pyth feed, as used by the programs
+        Pyth,
+        /// A switchboard on-demand feed, identified by its hex feed hash and
+        /// simulated by the crossbar service. There are no canonical
+        /// switchboard feeds for Helium tokens, so the feed must be given.
+        Switchboard { feed: String },
+        /// The simple price API of CoinGecko
+        Coingecko,
+    }
+
+    impl std::fmt::Display for LiveSource {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Pyth => f.write_str("pyth"),
+                Self::Switchboard { feed } => write!(f, "switchboard:{feed}"),
+                Self::Coingecko => f.write_str("coingecko"),
+            }
+        }
+    }
+
+    impl std::str::FromStr for LiveSource {
+        type Err = PriceError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.split_once(':') {
+                None if s == "pyth" => Ok(Self::Pyth),
+                None if s == "coingecko" => Ok(Self::Coingecko),
+                Some(("switchboard", feed)) => {
+                    let feed = feed.trim_start_matches("0x");
+                    if feed.is_empty() || hex::decode(feed).is_err() {
+                        return Err(PriceError::InvalidSource(s.to_string()));
+                    }
+                    Ok(Self::Switchboard {
+                        feed: feed.to_string(),
+                    })
+                }
+                _ => Err(PriceError::InvalidSource(s.to_string())),
+            }
+        }
+    }
+
+    impl serde::Serialize for LiveSource {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    /// A source skipped by [`get_with_fallback`] and why
+    #[derive(Debug, Clone, serde::Serialize)]
+    pub struct SkippedSource {
+        pub source: LiveSource,
+        pub error: String,
+    }
+
+    /// A current price with the source it came from
+    #[derive(Debug, Clone, serde::Serialize)]
+    pub struct SourcedPrice {
+        #[serde(flatten)]
+        pub price: Price,
+        pub source: LiveSource,
+        /// Earlier sources of the chain that could not provide a price
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub skipped: Vec<SkippedSource>,
+    }
+
+    impl SourcedPrice {
+        pub fn is_fallback(&self) -> bool {
+            self.source != LiveSource::Pyth
+        }
+    }
+
+    #[derive(serde::Deserialize)]
+    struct CoingeckoPrice {
+        usd: f64,
+        last_updated_at: Option<i64>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct SwitchboardSimulation {
+        results: Vec<serde_json::Value>,
+    }
+
+    fn decimal_from_json(value: &serde_json::Value) -> Option<Decimal> {
+        match value {
+            serde_json::Value::Number(number) => Decimal::from_f64(number.as_f64()?),
+            serde_json::Value::String(string) => string.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The current USD price of a token from the given source. Pyth prices
+    /// older than the given maximum age are rejected. The other sources
+    /// report no reliable publish time, so their prices are timestamped when
+    /// they are fetched unless the source says otherwise.
+    pub async fn get_from_source<C: AsRef<SolanaRpcClient>>(
+        client: &C,
+        source: &LiveSource,
+        token: Token,
+        max_age: Duration,
+    ) -> Result<Price, Error> {
+        let http = crate::client::http_client();
+        let (timestamp, price) = match source {
+            LiveSource::Pyth => return get_with_max_age(client, token, max_age).await,
+            LiveSource::Switchboard { feed } => {
+                let simulations: Vec<SwitchboardSimulation> = http
+                    .get(format!(
+                        "{SWITCHBOARD_CROSSBAR_URL}/simulate/solana/mainnet/{feed}"
+                    ))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                let price = simulations
+                    .first()
+                    .and_then(|simulation| simulation.results.first())
+                    .and_then(decimal_from_json)
+                    .ok_or(PriceError::InvalidFeed)?;
+                (Utc::now(), price)
+            }
+            LiveSource::Coingecko => {
+                let symbol = PriceSource::Coingecko
+                    .symbol(token)
+                    .ok_or(PriceError::InvalidToken(token))?;
+                let mut prices: HashMap<String, CoingeckoPrice> = http
+                    .get(format!("{COINGECKO_URL}/simple/price"))
+                    .query(&[
+                        ("ids", symbol),
+                        ("vs_currencies", "usd"),
+                        ("include_last_updated_at", "true"),
+                    ])
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                let CoingeckoPrice {
+                    usd,
+                    last_updated_at,
+                } = prices.remove(symbol).ok_or(PriceError::InvalidFeed)?;
+                let timestamp = match last_updated_at {
+                    Some(timestamp) => DateTime::from_timestamp(timestamp, 0)
+                        .ok_or(PriceError::InvalidTimestamp(timestamp))?,
+                    None => Utc::now(),
+                };
+                (
+                    timestamp,
+                    Decimal::from_f64(usd).ok_or(PriceError::InvalidFeed)?,
+                )
+            }
+        };
+        if price.is_sign_negative() {
+            return Err(PriceError::Negative.into());
+        }
+        if timestamp.timestamp().saturating_add(max_age.num_seconds()) < Utc::now().timestamp() {
+            return Err(PriceError::TooOld.into());
+        }
+        let mut price = price;
+        price.rescale(6);
+        Ok(Price {
+            timestamp,
+            price,
+            token,
+        })
+    }
+
+    /// The current USD price of a token from the first of the given sources
+    /// that has a price no older than the given maximum age. The result
+    /// records which source was used and why the sources before it were
+    /// skipped.
+    pub async fn get_with_fallback<C: AsRef<SolanaRpcClient>>(
+        client: &C,
+        token: Token,
+        sources: &[LiveSource],
+        max_age: Duration,
+    ) -> Result<SourcedPrice, Error> {
+        let mut skipped = vec![];
+        for source in sources {
+            match get_from_source(client, source, token, max_age).await {
+                Ok(price) => {
+                    return Ok(SourcedPrice {
+                        price,
+                        source: source.clone(),
+                        skipped,
+                    })
+                }
+                Err(err) => skipped.push(SkippedSource {
+                    source: source.clone(),
+                    error: err.to_string(),
+                }),
+            }
+        }
+        Err(PriceError::NoSource(skipped).into())
+    }
+
+    /// Sources of historical USD prices
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    #[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+    #[serde(rename_all = "lowercase")]
+    pub enum PriceSource {
+        /// Daily closing prices of the pyth benchmarks service
+        #[default]
+        Pyth,
+        /// Daily prices of the public CoinGecko API, which only covers the
+        /// last year
+        Coingecko,
+    }
+
+    impl PriceSource {
+        fn symbol(&self, token: Token) -> Option<&'static str> {
+            let symbol = match (self, token) {
+                (Self::Pyth, Token::Hnt) => "Crypto.HNT/USD",
+                (Self::Pyth, Token::Mobile) => "Crypto.MOBILE/USD",
+                (Self::Pyth, Token::Iot) => "Crypto.IOT/USD",
+                (Self::Pyth, Token::Sol) => "Crypto.SOL/USD",
+                (Self::Pyth, Token::Usdc) => "Crypto.USDC/USD",
+                (Self::Coingecko, Token::Hnt) => "helium",
+                (Self::Coingecko, Token::Mobile) => "helium-mobile",
+                (Self::Coingecko, Token::Iot) => "helium-iot",
+                (Self::Coingecko, Token::Sol) => "solana",
+                (Self::Coingecko, Token::Usdc) => "usd-coin",
+                (_, Token::Dc | Token::Spl(_)) => return None,
+            };
+            Some(symbol)
+        }
+    }
+
+    #[derive(serde::Deserialize)]
+    struct PythHistory {
+        s: String,
+        #[serde(default)]
+        t: Vec<i64>,
+        #[serde(default)]
+        c: Vec<f64>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct CoingeckoHistory {
+        prices: Vec<(f64, f64)>,
+    }
+
+    /// Daily USD prices of a token between two times from the given source,
+    /// oldest first. Each price is timestamped with the start of its day.
+    pub async fn history(
+        source: PriceSource,
+        token: Token,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<Price>, Error> {
+        history_with_interval(source, token, since, until, Duration::days(1)).await
+    }
+
+    /// Resolutions, in minutes, the pyth benchmarks service reports prices
+    /// at below a day
+    const PYTH_RESOLUTIONS: [i64; 10] = [1, 2, 5, 15, 30, 60, 120, 240, 360, 720];
+
+    /// The coarsest pyth resolution that evenly divides the given interval
+    fn pyth_resolution(interval: Duration) -> String {
+        let minutes = interval.num_minutes();
+        if minutes % (24 * 60) == 0 {
+            return "1D".to_string();
+        }
+        PYTH_RESOLUTIONS
+            .iter()
+            .rev()
+            .find(|resolution| minutes % *resolution == 0)
+            .unwrap_or(&1)
+            .to_string()
+    }
+
+    /// Keep the last of the given prices, ordered oldest first, in every
+    /// interval. Each kept price is timestamped with the start of its
+    /// interval.
+    fn close_prices(prices: Vec<(i64, f64)>, interval: Duration) -> Vec<(i64, f64)> {
+        let seconds = interval.num_seconds();
+        let mut closes: Vec<(i64, f64)> = vec![];
+        for (timestamp, price) in prices {
+            let start = timestamp - timestamp.rem_euclid(seconds);
+            match closes.last_mut() {
+                Some(last) if last.0 == start => last.1 = price,
+                _ => closes.push((start, price)),
+            }
+        }
+        closes
+    }
+
+    /// USD prices of a token between two times from the given source at the
+    /// given interval, oldest first. Each price is the last one reported in
+    /// its interval, timestamped with the start of the interval. Intervals
+    /// are aligned to the unix epoch.
+    pub async fn history_with_interval(
+        source: PriceSource,
+        token: Token,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        interval: Duration,
+    ) -> Result<Vec<Price>, Error> {
+        if interval < Duration::minutes(1) {
+            return Err(PriceError::InvalidInterval.into());
+        }
+        let symbol = source
+            .symbol(token)
+            .ok_or(PriceError::InvalidToken(token))?;
+        let client = crate::client::http_client();
+        let (from, to) = (since.timestamp().to_string(), until.timestamp().to_string());
+        let mut prices: Vec<(i64, f64)> = match source {
+            PriceSource::Pyth => {
+                let history: PythHistory = client
+                    .get(format!(
+                        "{PYTH_BENCHMARKS_URL}/v1/shims/tradingview/history"
+                    ))
+                    .query(&[
+                        ("symbol", symbol),
+                        ("resolution", &pyth_resolution(interval)),
+                        ("from", &from),
+                        ("to", &to),
+                    ])
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                if history.s != "ok" && history.s != "no_data" {
+                    return Err(PriceError::InvalidFeed.into());
+                }
+                history.t.into_iter().zip(history.c).collect()
+            }
+            // The granularity of CoinGecko prices depends on the length of
+            // the range, from minutes for a day to daily beyond 90 days
+            PriceSource::Coingecko => {
+                let history: CoingeckoHistory = client
+                    .get(format!("{COINGECKO_URL}/coins/{symbol}/market_chart/range"))
+                    .query(&[("vs_currency", "usd"), ("from", &from), ("to", &to)])
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                history
+                    .prices
+                    .into_iter()
+                    .map(|(millis, price)| ((millis / 1000.0) as i64, price))
+                    .collect()
+            }
+        };
+        prices.sort_by_key(|(timestamp, _)| *timestamp);
+        close_prices(prices, interval)
+            .into_iter()
+            .map(|(timestamp, price)| {
+                Ok(Price {
+                    timestamp: DateTime::from_timestamp(timestamp, 0)
+                        .ok_or(PriceError::InvalidTimestamp(timestamp))?,
+                    price: Decimal::from_f64(price).ok_or(PriceError::InvalidFeed)?,
+                    token,
+                })
+            })
+            .collect()
+    }
+
+    /// The USD price of a token at a past time from the given source. This is
+    /// the last minute price reported in the hour up to the time, or the
+    /// daily price of its day when the source has no finer prices for it.
+    pub async fn history_at(
+        source: PriceSource,
+        token: Token,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Price, Error> {
+        for (window, interval) in [
+            (Duration::hours(1), Duration::minutes(1)),
+            (Duration::days(7), Duration::days(1)),
+        ] {
+            let prices =
+                history_with_interval(source, token, timestamp - window, timestamp, interval)
+                    .await?;
+            if let Some(price) = at(&prices, timestamp) {
+                return Ok(price.clone());
+            }
+        }
+        Err(PriceError::NoData(timestamp).into())
+    }
+
+    /// The latest of the given prices, ordered oldest first, at or before the
+    /// given time
+    pub fn at(prices: &[Price], timestamp: DateTime<Utc>) -> Option<&Price> {
+        let index = prices.partition_point(|price| price.timestamp <= timestamp);
+        index.checked_sub(1).map(|index| &prices[index])
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn resolution() {
+            assert_eq!(pyth_resolution(Duration::hours(1)), "60");
+            assert_eq!(pyth_resolution(Duration::minutes(90)), "30");
+            assert_eq!(pyth_resolution(Duration::days(2)), "1D");
+            assert_eq!(pyth_resolution(Duration::minutes(7)), "1");
+        }
+
+        #[test]
+        fn live_source() {
+            for source in ["pyth", "coingecko", "switchboard:ab12"] {
+                assert_eq!(
+                    source.parse::<LiveSource>().expect("source").to_string(),
+                    source
+                );
+            }
+            assert_eq!(
+                "switchboard:0xAB12".parse::<LiveSource>().expect("source"),
+                LiveSource::Switchboard {
+                    feed: "AB12".to_string()
+                }
+            );
+            assert!("switchboard:xyz".parse::<LiveSource>().is_err());
+            assert!("chainlink".parse::<LiveSource>().is_err());
+            assert_eq!(
+                decimal_from_json(&serde_json::json!("1.25")),
+                Some(Decimal::new(125, 2))
+            );
+            assert_eq!(
+                decimal_from_json(&serde_json::json!(2.5)),
+                Some(Decimal::new(25, 1))
+            );
+            assert_eq!(decimal_from_json(&serde_json::json!(null)), None);
+        }
+
+        #[test]
+        fn closes() {
+            let prices = vec![(3600, 1.0), (3660, 2.0), (7300, 3.0), (10800, 4.0)];
+            assert_eq!(
+                close_prices(prices, Duration::hours(1)),
+                vec![(3600, 2.0), (7200, 3.0), (10800, 4.0)]
+            );
+        }
+    }
 }
 
-#[derive(
-    Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord,
-)]
-#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
-#[serde(rename_all = "lowercase")]
+/// A token held by Solana wallets. Tokens other than the built in ones are
+/// SPL tokens identified by their mint, whose decimals and token program are
+/// read from the mint account with [`Token::for_mint`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
 pub enum Token {
     Sol,
     Hnt,
     Mobile,
     Iot,
     Dc,
+    Usdc,
+    Spl(SplToken),
+}
+
+/// An SPL token outside the built in tokens
+#[derive(
+    Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+pub struct SplToken {
+    #[serde(with = "serde_pubkey")]
+    pub mint: Pubkey,
+    pub decimals: u8,
+    /// The token program owning the mint
+    #[serde(with = "serde_pubkey")]
+    pub program: Pubkey,
+    /// The fee withheld from transfers by Token-2022 mints with the transfer
+    /// fee extension
+    pub transfer_fee: Option<TransferFee>,
+}
+
+/// The transfer fee of a Token-2022 mint, as in effect in the epoch the mint
+/// was read in
+#[derive(
+    Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+pub struct TransferFee {
+    pub basis_points: u16,
+    /// Largest fee withheld from a single transfer, in base units
+    pub maximum_fee: u64,
+}
+
+impl TransferFee {
+    /// The fee withheld from a transfer of the given amount in base units,
+    /// rounded up like the token program does
+    pub fn calculate(&self, amount: u64) -> u64 {
+        let fee = (u128::from(amount) * u128::from(self.basis_points)).div_ceil(10_000);
+        u64::try_from(fee).map_or(self.maximum_fee, |fee| fee.min(self.maximum_fee))
+    }
 }
 
 impl std::fmt::Display for Token {
@@ -272,11 +1370,58 @@ impl std::fmt::Display for Token {
             Token::Mobile => "mobile",
             Token::Iot => "iot",
             Token::Dc => "dc",
+            Token::Usdc => "usdc",
+            Token::Spl(spl) => return write!(f, "{}", spl.mint),
         };
         f.write_str(str)
     }
 }
 
+/// Tokens are serialized by name, and SPL tokens by mint address. A mint
+/// address alone does not say what the decimals of a token are, so SPL tokens
+/// are deserialized from their full [`SplToken`] description instead.
+impl serde::Serialize for Token {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Token {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum TokenRepr {
+            Name(String),
+            Spl(SplToken),
+        }
+        match TokenRepr::deserialize(deserializer)? {
+            TokenRepr::Name(str) => Self::from_str(&str).map_err(serde::de::Error::custom),
+            TokenRepr::Spl(spl) => Ok(Self::from_mint(spl.mint).unwrap_or(Self::Spl(spl))),
+        }
+    }
+}
+
+#[cfg(feature = "clap")]
+impl clap::ValueEnum for Token {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            Self::Sol,
+            Self::Hnt,
+            Self::Mobile,
+            Self::Iot,
+            Self::Dc,
+            Self::Usdc,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            Self::Spl(_) => None,
+            token => Some(clap::builder::PossibleValue::new(token.to_string())),
+        }
+    }
+}
+
 impl FromStr for Token {
     type Err = TokenError;
     fn from_str(s: &str) -> StdResult<Self, Self::Err> {
@@ -286,12 +1431,14 @@ impl FromStr for Token {
             "mobile" => Ok(Token::Mobile),
             "iot" => Ok(Token::Iot),
             "dc" => Ok(Token::Dc),
+            "usdc" => Ok(Token::Usdc),
             _ => Err(TokenError::InvalidToken(s.to_string())),
         }
     }
 }
 
 impl Token {
+    /// The built in token with the given mint, if any
     pub fn from_mint(mint: Pubkey) -> Option<Self> {
         let token = match mint {
             mint if mint == *HNT_MINT => Token::Hnt,
@@ -299,14 +1446,42 @@ impl Token {
             mint if mint == *DC_MINT => Token::Dc,
             mint if mint == *MOBILE_MINT => Token::Mobile,
             mint if mint == *SOL_MINT => Token::Sol,
+            mint if mint == *USDC_MINT => Token::Usdc,
             _ => return None,
         };
 
         Some(token)
     }
 
+    /// The token with the given mint, looking up the decimals and token
+    /// program of mints that are not built in
+    pub async fn for_mint<C: AsRef<SolanaRpcClient>>(
+        client: &C,
+        mint: &Pubkey,
+    ) -> Result<Self, Error> {
+        if let Some(token) = Self::from_mint(*mint) {
+            return Ok(token);
+        }
+        let account = client.as_ref().get_account(mint).await?;
+        let program = mint_program(mint, &account)?;
+        let epoch = if program == anchor_spl::token_2022::spl_token_2022::id() {
+            client.as_ref().get_epoch_info().await?.epoch
+        } else {
+            0
+        };
+        Ok(Self::Spl(spl_token_from_account(mint, &account, epoch)?))
+    }
+
+    /// The built in tokens
     pub fn all() -> Vec<Self> {
-        vec![Self::Hnt, Self::Iot, Self::Mobile, Self::Dc, Self::Sol]
+        vec![
+            Self::Hnt,
+            Self::Iot,
+            Self::Mobile,
+            Self::Dc,
+            Self::Sol,
+            Self::Usdc,
+        ]
     }
 
     fn from_allowed(s: &str, allowed: &[Self]) -> StdResult<Self, TokenError> {
@@ -321,14 +1496,38 @@ impl Token {
         Self::from_allowed(s, &[Self::Iot, Self::Mobile, Self::Hnt, Self::Sol])
     }
 
-    pub fn pricekey_value_parser(s: &str) -> StdResult<Self, TokenError> {
+    pub fn burnable_value_parser(s: &str) -> StdResult<Self, TokenError> {
         Self::from_allowed(s, &[Self::Iot, Self::Mobile, Self::Hnt])
     }
 
+    pub fn pricekey_value_parser(s: &str) -> StdResult<Self, TokenError> {
+        Self::from_allowed(
+            s,
+            &[Self::Iot, Self::Mobile, Self::Hnt, Self::Sol, Self::Usdc],
+        )
+    }
+
+    /// The token program owning the mint of this token. All Helium token
+    /// mints and USDC are classic SPL token mints.
+    pub fn token_program(&self) -> Pubkey {
+        match self {
+            Self::Spl(spl) => spl.program,
+            _ => anchor_spl::token::spl_token::id(),
+        }
+    }
+
+    /// The transfer fee of Token-2022 tokens with the transfer fee extension
+    pub fn transfer_fee(&self) -> Option<TransferFee> {
+        match self {
+            Self::Spl(spl) => spl.transfer_fee,
+            _ => None,
+        }
+    }
+
     pub fn associated_token_adress(&self, address: &Pubkey) -> Pubkey {
         match self {
             Self::Sol => *address,
-            _ => spl_associated_token_account::get_associated_token_address(address, self.mint()),
+            _ => associated_token_address_with_program(address, self.mint(), &self.token_program()),
         }
     }
 
@@ -376,6 +1575,12 @@ pub struct TokenAmount {
     pub amount: u64,
 }
 
+impl From<(Token, u64)> for TokenAmount {
+    fn from((token, amount): (Token, u64)) -> Self {
+        Self { token, amount }
+    }
+}
+
 impl From<&TokenAmount> for f64 {
     fn from(value: &TokenAmount) -> Self {
         match value.token.decimals() {
@@ -431,15 +1636,46 @@ impl TokenAmount {
     pub fn from_u64(token: Token, amount: u64) -> Self {
         Self { token, amount }
     }
+
+    /// The amount in the smallest unit of the given token, or an error if
+    /// this is an amount of another token
+    pub fn amount_for(&self, token: Token) -> StdResult<u64, TokenError> {
+        if self.token != token {
+            return Err(TokenError::UnexpectedToken {
+                expected: token,
+                got: self.token,
+            });
+        }
+        Ok(self.amount)
+    }
+
+    /// Parse an amount in whole tokens, like "12.5", without the rounding
+    /// errors of going through a float. Amounts with more decimals than the
+    /// token has are rejected.
+    pub fn from_decimal_str(token: Token, amount: &str) -> StdResult<Self, TokenError> {
+        use rust_decimal::prelude::*;
+        let invalid = || TokenError::InvalidAmount(amount.to_string());
+        let value = Decimal::from_str(amount.trim()).map_err(|_| invalid())?;
+        if value.is_sign_negative() || value.normalize().scale() > u32::from(token.decimals()) {
+            return Err(invalid());
+        }
+        let amount = 10u64
+            .checked_pow(token.decimals().into())
+            .and_then(|scale| value.checked_mul(Decimal::from(scale)))
+            .and_then(|value| value.to_u64())
+            .ok_or_else(invalid)?;
+        Ok(Self { token, amount })
+    }
 }
 
 impl Token {
     pub fn decimals(&self) -> u8 {
         match self {
             Self::Hnt => 8,
-            Self::Iot | Self::Mobile => 6,
+            Self::Iot | Self::Mobile | Self::Usdc => 6,
             Self::Dc => 0,
             Self::Sol => 9,
+            Self::Spl(spl) => spl.decimals,
         }
     }
 
@@ -450,6 +1686,8 @@ impl Token {
             Self::Iot => &IOT_MINT,
             Self::Dc => &DC_MINT,
             Self::Sol => &SOL_MINT,
+            Self::Usdc => &USDC_MINT,
+            Self::Spl(spl) => &spl.mint,
         }
     }
 
@@ -458,7 +1696,9 @@ impl Token {
             Self::Hnt => Some(&HNT_PRICE_KEY),
             Self::Iot => Some(&IOT_PRICE_KEY),
             Self::Mobile => Some(&MOBILE_PRICE_KEY),
-            _ => None,
+            Self::Sol => Some(&SOL_PRICE_KEY),
+            Self::Usdc => Some(&USDC_PRICE_KEY),
+            Self::Dc | Self::Spl(_) => None,
         }
     }
 
@@ -467,10 +1707,20 @@ impl Token {
             Self::Hnt => Some(&HNT_PRICE_FEED),
             Self::Iot => Some(&IOT_PRICE_FEED),
             Self::Mobile => Some(&MOBILE_PRICE_FEED),
-            _ => None,
+            Self::Sol => Some(&SOL_PRICE_FEED),
+            Self::Usdc => Some(&USDC_PRICE_FEED),
+            Self::Dc | Self::Spl(_) => None,
         }
     }
 
+    pub fn price_feed_info(&self) -> Option<price::Feed> {
+        Some(price::Feed {
+            token: *self,
+            account: *self.price_key()?,
+            feed_id: hex::encode(self.price_feed()?),
+        })
+    }
+
     pub fn amount(self, amount: u64) -> TokenAmount {
         TokenAmount::from_u64(self, amount)
     }
@@ -482,3 +1732,430 @@ impl Token {
         }
     }
 }
+
+pub mod history {
+    use super::*;
+    use crate::{
+        keypair::Signature,
+        solana_client::{
+            rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_config::RpcTransactionConfig,
+        },
+    };
+    use solana_transaction_status::{
+        EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiMessage,
+        UiTransactionEncoding, UiTransactionTokenBalance,
+    };
+    use std::collections::BTreeMap;
+
+    const SIGNATURE_PAGE_SIZE: usize = 1000;
+    /// Number of transactions fetched at the same time
+    const FETCH_CONCURRENCY: usize = 5;
+
+    /// Change of the balance of a token of a wallet in a transaction
+    #[derive(Debug, serde::Serialize, Clone, PartialEq, Eq)]
+    pub struct Movement {
+        pub token: Token,
+        /// Signed change of the balance in the smallest unit of the token.
+        /// Transaction fees are not included in SOL movements
+        pub change: i128,
+        /// The wallets whose balance of the token changed the other way
+        pub counterparties: Vec<String>,
+    }
+
+    /// The balance changes of a wallet in a single transaction
+    #[derive(Debug, serde::Serialize, Clone)]
+    pub struct Activity {
+        pub timestamp: DateTime<Utc>,
+        pub signature: String,
+        /// Transaction fee in lamports paid by the wallet
+        pub fee: u64,
+        pub movements: Vec<Movement>,
+    }
+
+    /// Read back the balance changes of the given tokens for the given wallet
+    /// between two times.
+    ///
+    /// Transactions are found through the signatures of the wallet and of its
+    /// associated token accounts for the given tokens. Transactions that only
+    /// cost the wallet a fee are included without movements. Results are
+    /// ordered oldest first.
+    pub async fn for_address<C: AsRef<SolanaRpcClient>>(
+        client: &C,
+        address: &Pubkey,
+        tokens: &[Token],
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<Activity>, Error> {
+        let mut accounts = vec![*address];
+        accounts.extend(
+            tokens
+                .iter()
+                .filter(|token| **token != Token::Sol)
+                .map(|token| token.associated_token_adress(address)),
+        );
+        let mut signatures = BTreeMap::new();
+        for account in accounts {
+            for (signature, block_time) in
+                signatures_between(client, &account, since, until).await?
+            {
+                signatures.insert((block_time, signature.to_string()), signature);
+            }
+        }
+
+        let activities: Vec<Option<Activity>> = stream::iter(signatures.into_values())
+            .map(|signature| async move {
+                let txn = client
+                    .as_ref()
+                    .get_transaction_with_config(
+                        &signature,
+                        RpcTransactionConfig {
+                            encoding: Some(UiTransactionEncoding::JsonParsed),
+                            commitment: Some(CommitmentConfig::finalized()),
+                            max_supported_transaction_version: Some(0),
+                        },
+                    )
+                    .await?;
+                Ok::<_, Error>(Activity::from_transaction(txn, address, tokens)?)
+            })
+            .buffered(FETCH_CONCURRENCY)
+            .try_collect()
+            .await?;
+        Ok(activities.into_iter().flatten().collect())
+    }
+
+    /// Signatures of the given account with their block times between two
+    /// times
+    async fn signatures_between<C: AsRef<SolanaRpcClient>>(
+        client: &C,
+        account: &Pubkey,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<(Signature, DateTime<Utc>)>, Error> {
+        let mut signatures = vec![];
+        let mut before = None;
+        'pages: loop {
+            let page = client
+                .as_ref()
+                .get_signatures_for_address_with_config(
+                    account,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        limit: Some(SIGNATURE_PAGE_SIZE),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            let page_len = page.len();
+            for status in page {
+                let signature =
+                    Signature::from_str(&status.signature).map_err(DecodeError::from)?;
+                before = Some(signature);
+                let Some(block_time) = status
+                    .block_time
+                    .and_then(|block_time| DateTime::from_timestamp(block_time, 0))
+                else {
+                    continue;
+                };
+                if block_time < since {
+                    break 'pages;
+                }
+                if block_time < until {
+                    signatures.push((signature, block_time));
+                }
+            }
+            if page_len < SIGNATURE_PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(signatures)
+    }
+
+    impl Activity {
+        fn from_transaction(
+            txn: EncodedConfirmedTransactionWithStatusMeta,
+            address: &Pubkey,
+            tokens: &[Token],
+        ) -> Result<Option<Self>, DecodeError> {
+            let Some(meta) = txn.transaction.meta else {
+                return Ok(None);
+            };
+            let EncodedTransaction::Json(ui_txn) = txn.transaction.transaction else {
+                return Err(DecodeError::other("not a json encoded transaction"));
+            };
+            let UiMessage::Parsed(ui_msg) = ui_txn.message else {
+                return Err(DecodeError::other("not a parsed transaction message"));
+            };
+            let Some(timestamp) = txn
+                .block_time
+                .and_then(|block_time| DateTime::from_timestamp(block_time, 0))
+            else {
+                return Err(DecodeError::other("no valid block time found"));
+            };
+            let address = address.to_string();
+            let keys: Vec<&String> = ui_msg
+                .account_keys
+                .iter()
+                .map(|account| &account.pubkey)
+                .collect();
+            let fee_payer = keys.first().copied();
+            let fee = if fee_payer == Some(&address) {
+                meta.fee
+            } else {
+                0
+            };
+
+            let mut movements = vec![];
+            if tokens.contains(&Token::Sol) {
+                // The fee is reported separately, add it back to the balance
+                // change of the payer
+                let balances = |balances: &[u64], with_fee: bool| {
+                    keys.iter()
+                        .zip(balances)
+                        .enumerate()
+                        .map(|(index, (key, balance))| {
+                            let fee = if with_fee && index == 0 { meta.fee } else { 0 };
+                            (key.to_string(), balance + fee)
+                        })
+                        .collect::<Vec<_>>()
+                };
+                let changes = balance_changes(
+                    balances(&meta.pre_balances, false),
+                    balances(&meta.post_balances, true),
+                );
+                movements.extend(movement(Token::Sol, &changes, &address));
+            }
+            let pre_token_balances: Option<Vec<_>> = meta.pre_token_balances.into();
+            let post_token_balances: Option<Vec<_>> = meta.post_token_balances.into();
+            for token in tokens.iter().filter(|token| **token != Token::Sol) {
+                let changes = balance_changes(
+                    token_balances(pre_token_balances.as_deref(), token)?,
+                    token_balances(post_token_balances.as_deref(), token)?,
+                );
+                movements.extend(movement(*token, &changes, &address));
+            }
+
+            if movements.is_empty() && fee == 0 {
+                return Ok(None);
+            }
+            Ok(Some(Self {
+                timestamp,
+                signature: ui_txn.signatures[0].clone(),
+                fee,
+                movements,
+            }))
+        }
+    }
+
+    /// The balances of the given token by owner
+    fn token_balances(
+        balances: Option<&[UiTransactionTokenBalance]>,
+        token: &Token,
+    ) -> Result<Vec<(String, u64)>, DecodeError> {
+        let mint = token.mint().to_string();
+        balances
+            .unwrap_or_default()
+            .iter()
+            .filter(|balance| balance.mint == mint)
+            .filter_map(|balance| {
+                let owner = Option::<&String>::from(balance.owner.as_ref())?;
+                Some((owner, &balance.ui_token_amount.amount))
+            })
+            .map(|(owner, amount)| {
+                let amount = amount
+                    .parse::<u64>()
+                    .map_err(|err| DecodeError::other(format!("invalid token amount: {err}")))?;
+                Ok((owner.clone(), amount))
+            })
+            .collect()
+    }
+
+    /// The change of the balance of every holder between the given balances.
+    /// Holders without a change are left out
+    fn balance_changes(pre: Vec<(String, u64)>, post: Vec<(String, u64)>) -> HashMap<String, i128> {
+        let mut changes: HashMap<String, i128> = HashMap::new();
+        for (holder, balance) in pre {
+            *changes.entry(holder).or_default() -= i128::from(balance);
+        }
+        for (holder, balance) in post {
+            *changes.entry(holder).or_default() += i128::from(balance);
+        }
+        changes.retain(|_, change| *change != 0);
+        changes
+    }
+
+    /// The movement of the given token for the given address, if its balance
+    /// changed
+    fn movement(token: Token, changes: &HashMap<String, i128>, address: &str) -> Option<Movement> {
+        let change = *changes.get(address)?;
+        let mut counterparties: Vec<String> = changes
+            .iter()
+            .filter(|(holder, other)| {
+                holder.as_str() != address && other.signum() == -change.signum()
+            })
+            .map(|(holder, _)| holder.clone())
+            .collect();
+        counterparties.sort();
+        Some(Movement {
+            token,
+            change,
+            counterparties,
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn movements() {
+            let balances = |entries: &[(&str, u64)]| {
+                entries
+                    .iter()
+                    .map(|(holder, balance)| (holder.to_string(), *balance))
+                    .collect::<Vec<_>>()
+            };
+            let changes = balance_changes(
+                balances(&[("wallet", 100), ("alice", 5), ("bob", 7)]),
+                balances(&[("wallet", 40), ("alice", 65), ("bob", 7), ("carol", 0)]),
+            );
+            assert_eq!(
+                movement(Token::Hnt, &changes, "wallet"),
+                Some(Movement {
+                    token: Token::Hnt,
+                    change: -60,
+                    counterparties: vec!["alice".to_string()],
+                })
+            );
+            assert_eq!(movement(Token::Hnt, &changes, "bob"), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_metadata() {
+        let mut data = vec![4u8];
+        data.extend([0u8; 64]);
+        for (str, padded) in [("Helium Network Token", 32), ("HNT", 10)] {
+            data.extend((padded as u32).to_le_bytes());
+            let mut bytes = str.as_bytes().to_vec();
+            bytes.resize(padded, 0);
+            data.extend(bytes);
+        }
+        assert_eq!(
+            parse_token_metadata(&data),
+            Some(("Helium Network Token".to_string(), "HNT".to_string()))
+        );
+        assert_eq!(parse_token_metadata(&data[..80]), None);
+    }
+
+    #[test]
+    fn spl_token() {
+        let spl_token = SplToken {
+            mint: Pubkey::new_unique(),
+            decimals: 2,
+            program: anchor_spl::token::spl_token::id(),
+            transfer_fee: None,
+        };
+        let spl = Token::Spl(spl_token);
+        assert_eq!(
+            serde_json::to_value(spl).unwrap(),
+            serde_json::json!(spl.mint().to_string())
+        );
+        assert_eq!(TokenAmount::from_f64(spl, 1.5).amount, 150);
+        assert_eq!(
+            serde_json::from_str::<Token>("\"hnt\"").unwrap(),
+            Token::Hnt
+        );
+        assert!(serde_json::from_str::<Token>(&format!("\"{}\"", spl.mint())).is_err());
+        let described = serde_json::to_value(spl_token).unwrap();
+        assert_eq!(described["decimals"], 2);
+        assert_eq!(serde_json::from_value::<Token>(described).unwrap(), spl);
+        let fee = SplToken {
+            transfer_fee: Some(TransferFee {
+                basis_points: 50,
+                maximum_fee: 1_000,
+            }),
+            ..spl_token
+        };
+        assert_eq!(
+            serde_json::from_value::<Token>(serde_json::to_value(fee).unwrap()).unwrap(),
+            Token::Spl(fee)
+        );
+    }
+
+    #[test]
+    fn decimal_amounts() {
+        assert_eq!(
+            TokenAmount::from_decimal_str(Token::Hnt, "0.29")
+                .unwrap()
+                .amount,
+            29_000_000
+        );
+        assert_eq!(
+            TokenAmount::from_decimal_str(Token::Iot, "1000")
+                .unwrap()
+                .amount,
+            1_000_000_000
+        );
+        assert_eq!(
+            TokenAmount::from_decimal_str(Token::Mobile, "1.500000")
+                .unwrap()
+                .amount,
+            1_500_000
+        );
+        assert!(TokenAmount::from_decimal_str(Token::Mobile, "0.0000001").is_err());
+        assert!(TokenAmount::from_decimal_str(Token::Hnt, "-1").is_err());
+        assert!(TokenAmount::from_decimal_str(Token::Hnt, "one").is_err());
+    }
+
+    #[test]
+    fn typed_amounts() {
+        let amount = TokenAmount::from((Token::Dc, 100_000));
+        assert_eq!(amount.amount_for(Token::Dc).unwrap(), 100_000);
+        assert!(matches!(
+            amount.amount_for(Token::Hnt),
+            Err(TokenError::UnexpectedToken {
+                expected: Token::Hnt,
+                got: Token::Dc
+            })
+        ));
+    }
+
+    #[test]
+    fn burn_instructions() {
+        let payer = Pubkey::new_unique();
+        let token_2022 = Token::Spl(SplToken {
+            mint: Pubkey::new_unique(),
+            decimals: 6,
+            program: anchor_spl::token_2022::spl_token_2022::id(),
+            transfer_fee: None,
+        });
+        for token in [Token::Hnt, token_2022] {
+            let ix = burn_instruction(&token.amount(100), &payer).expect("burn");
+            assert_eq!(ix.program_id, token.token_program());
+            assert_eq!(ix.accounts[0].pubkey, token.associated_token_adress(&payer));
+            assert_eq!(ix.accounts[1].pubkey, *token.mint());
+            assert_eq!(ix.accounts[2].pubkey, payer);
+            assert!(ix.accounts[2].is_signer);
+        }
+        assert!(burn_instruction(&Token::Sol.amount(1), &payer).is_err());
+    }
+
+    #[test]
+    fn transfer_fee() {
+        let fee = TransferFee {
+            basis_points: 150,
+            maximum_fee: 5_000,
+        };
+        assert_eq!(fee.calculate(0), 0);
+        assert_eq!(fee.calculate(1), 1);
+        assert_eq!(fee.calculate(10_000), 150);
+        assert_eq!(fee.calculate(10_001), 151);
+        assert_eq!(fee.calculate(1_000_000), 5_000);
+        assert_eq!(fee.calculate(u64::MAX), 5_000);
+    }
+}
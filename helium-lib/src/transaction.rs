@@ -0,0 +1,254 @@
+//! Submission of transactions.
+//!
+//! [`send_with_retry`] is the one submit loop for transactions. It retries
+//! submissions that fail for transient reasons, like a node that is behind
+//! the cluster. A transaction is only accepted until the chain passes the
+//! last valid block height of its blockhash, so when retries are asked for
+//! it also tracks the block height until the transaction is processed and,
+//! when the blockhash expires first, rebuilds the message with a fresh
+//! blockhash, signs it again and resubmits it.
+use crate::{
+    client::SolanaRpcClient,
+    confirm::ConfirmError,
+    error::Error,
+    keypair::{Keypair, Signature, Signer},
+    solana_client::{
+        client_error::{ClientError, ClientErrorKind},
+        rpc_config::RpcSendTransactionConfig,
+        rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+        rpc_request::{RpcError, RpcResponseErrorData},
+    },
+    solana_sdk::{
+        commitment_config::CommitmentConfig,
+        transaction::{TransactionError, VersionedTransaction},
+    },
+};
+use std::time::Duration;
+
+/// Interval between status checks of a submitted transaction
+const STATUS_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+/// Number of times a transaction is submitted before giving up on transient
+/// errors
+const SUBMIT_ATTEMPTS: usize = 3;
+/// Delay before resubmitting a transaction after a transient error
+const SUBMIT_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SendOpts {
+    /// Number of times the transaction is rebuilt and resubmitted after its
+    /// blockhash expires. The transaction is waited for until it is processed
+    /// when this is not zero
+    pub max_retries: usize,
+    pub skip_preflight: bool,
+}
+
+/// A transaction that was submitted to the cluster
+#[derive(Debug, Clone)]
+pub struct Sent {
+    /// The transaction as it was submitted, with the blockhash it was last
+    /// signed with
+    pub transaction: VersionedTransaction,
+    pub signature: Signature,
+    /// Number of times the transaction was submitted
+    pub attempts: usize,
+}
+
+/// Submit errors that are expected to resolve themselves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TransientError {
+    /// The blockhash of the transaction is unknown to the node, either because
+    /// it expired or because the node has not yet seen it
+    BlockhashNotFound,
+    /// The transaction was already processed by an earlier submission
+    AlreadyProcessed,
+    /// The node is behind the cluster
+    NodeBehind,
+}
+
+impl TransientError {
+    fn classify(err: &ClientError) -> Option<Self> {
+        match err.get_transaction_error() {
+            Some(TransactionError::BlockhashNotFound) => return Some(Self::BlockhashNotFound),
+            Some(TransactionError::AlreadyProcessed) => return Some(Self::AlreadyProcessed),
+            _ => (),
+        }
+        match &err.kind {
+            ClientErrorKind::RpcError(RpcError::RpcResponseError { code, data, .. })
+                if matches!(data, RpcResponseErrorData::NodeUnhealthy { .. })
+                    || *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY =>
+            {
+                Some(Self::NodeBehind)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Submit a transaction, retrying transient submit errors.
+///
+/// With `max_retries` set the transaction is waited for until it is
+/// processed, and rebuilt with a fresh blockhash and signed again by
+/// `resigner` when its blockhash expires first. Without it the transaction is
+/// returned once the node accepted it. A transaction is only rebuilt when it
+/// is signed by the resigner alone, others fail once their blockhash expires.
+///
+/// The last valid block height of the original blockhash is used to detect
+/// expiry if known, otherwise the node is asked whether the blockhash is
+/// still valid. Every transaction signed again is passed to `on_resign`
+/// before it is submitted. Errors of the RPC node are returned as
+/// [`Error::Solana`], so callers can report the logs of a failed preflight.
+pub async fn send_with_retry<C, F, E>(
+    client: &C,
+    mut tx: VersionedTransaction,
+    mut last_valid_block_height: Option<u64>,
+    resigner: Option<&Keypair>,
+    opts: &SendOpts,
+    mut on_resign: F,
+) -> Result<Sent, E>
+where
+    C: AsRef<SolanaRpcClient>,
+    F: FnMut(&VersionedTransaction) -> Result<(), E>,
+    E: From<Error>,
+{
+    let solana_client = client.as_ref();
+    let resigner = resigner.filter(|keypair| {
+        tx.message.header().num_required_signatures == 1
+            && tx.message.static_account_keys().first() == Some(&keypair.pubkey())
+    });
+    let config = RpcSendTransactionConfig {
+        skip_preflight: opts.skip_preflight,
+        ..Default::default()
+    };
+    let mut attempts = 0;
+    let mut failed_submits = 0;
+    let mut rebuilds = 0;
+    loop {
+        attempts += 1;
+        let signature = tx.signatures[0];
+        // Whether the blockhash of the transaction expired before it was
+        // processed
+        let expired = match solana_client
+            .send_transaction_with_config(&tx, config)
+            .await
+        {
+            Ok(_) if opts.max_retries == 0 => false,
+            Ok(_) => !wait_processed(client, &tx, last_valid_block_height).await?,
+            Err(err) => {
+                let Some(transient) = TransientError::classify(&err) else {
+                    return Err(Error::from(err).into());
+                };
+                failed_submits += 1;
+                match transient {
+                    // An earlier submission landed, wait for it rather than
+                    // submitting again
+                    TransientError::AlreadyProcessed => {
+                        !wait_processed(client, &tx, last_valid_block_height).await?
+                    }
+                    _ if failed_submits >= SUBMIT_ATTEMPTS => {
+                        return Err(Error::from(err).into());
+                    }
+                    // The node never accepted the transaction, so rebuilding
+                    // it does not count against the retries for expiry
+                    TransientError::BlockhashNotFound if resigner.is_some() => {
+                        (tx, last_valid_block_height) = rebuild(client, &tx, resigner).await?;
+                        on_resign(&tx)?;
+                        continue;
+                    }
+                    _ => {
+                        tokio::time::sleep(SUBMIT_RETRY_DELAY).await;
+                        continue;
+                    }
+                }
+            }
+        };
+        if !expired {
+            return Ok(Sent {
+                transaction: tx,
+                signature,
+                attempts,
+            });
+        }
+        if resigner.is_none() || rebuilds >= opts.max_retries {
+            return Err(Error::from(ConfirmError::Expired(signature)).into());
+        }
+        rebuilds += 1;
+        (tx, last_valid_block_height) = rebuild(client, &tx, resigner).await?;
+        on_resign(&tx)?;
+    }
+}
+
+/// Sign the message of the given transaction again with a fresh blockhash,
+/// returning the new transaction and the last valid block height of its
+/// blockhash
+async fn rebuild<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    tx: &VersionedTransaction,
+    resigner: Option<&Keypair>,
+) -> Result<(VersionedTransaction, Option<u64>), Error> {
+    let Some(keypair) = resigner else {
+        return Err(ConfirmError::Expired(tx.signatures[0]).into());
+    };
+    let client = client.as_ref();
+    let (blockhash, block_height) = client
+        .get_latest_blockhash_with_commitment(client.commitment())
+        .await?;
+    let mut message = tx.message.clone();
+    message.set_recent_blockhash(blockhash);
+    let tx = VersionedTransaction::try_new(message, &[keypair])?;
+    Ok((tx, Some(block_height)))
+}
+
+/// Wait for a submitted transaction to be processed. Returns false if its
+/// blockhash expired before it was.
+async fn wait_processed<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    tx: &VersionedTransaction,
+    last_valid_block_height: Option<u64>,
+) -> Result<bool, Error> {
+    let signature = tx.signatures[0];
+    loop {
+        let expired = is_expired(client, tx, last_valid_block_height).await?;
+        let status = client
+            .as_ref()
+            .get_signature_statuses(&[signature])
+            .await?
+            .value
+            .into_iter()
+            .next()
+            .flatten();
+        if let Some(status) = status {
+            return match status.err {
+                Some(err) => Err(ConfirmError::Failed(signature, err).into()),
+                None => Ok(true),
+            };
+        }
+        // The status is checked after expiry so a transaction that landed
+        // right before its blockhash expired is not submitted again
+        if expired {
+            return Ok(false);
+        }
+        tokio::time::sleep(STATUS_CHECK_INTERVAL).await;
+    }
+}
+
+async fn is_expired<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    tx: &VersionedTransaction,
+    last_valid_block_height: Option<u64>,
+) -> Result<bool, ClientError> {
+    let client = client.as_ref();
+    match last_valid_block_height {
+        Some(last_valid_block_height) => {
+            let block_height = client
+                .get_block_height_with_commitment(CommitmentConfig::confirmed())
+                .await?;
+            Ok(block_height > last_valid_block_height)
+        }
+        None => {
+            let valid = client
+                .is_blockhash_valid(tx.message.recent_blockhash(), CommitmentConfig::processed())
+                .await?;
+            Ok(!valid)
+        }
+    }
+}
@@ -3,6 +3,8 @@ use lazy_static::lazy_static;
 use sha2::{Digest, Sha256};
 use std::ops::Index;
 
+pub mod slip10;
+
 #[derive(Debug, thiserror::Error)]
 pub enum MnmemonicError {
     #[error("no such word: {0}")]
@@ -13,6 +15,8 @@ pub enum MnmemonicError {
     InvalidChecksum,
     #[error("Incorrect entropy length: {0}")]
     InvalidEntropyLen(usize),
+    #[error("invalid derivation path: {0}, only hardened indexes are supported")]
+    InvalidDerivationPath(String),
 }
 
 lazy_static! {
@@ -118,6 +122,56 @@ pub fn mnemonic_to_entropy(words: &[&str]) -> Result<[u8; 32], MnmemonicError> {
     Ok(entropy_bytes)
 }
 
+/// Converts a 12 or 24 word mnemonic and a BIP39 passphrase to entropy that
+/// can be used to generate a keypair.
+///
+/// Without a passphrase this is the entropy of [`mnemonic_to_entropy`], which
+/// is how the Helium apps derive keys. With a passphrase it is the first half
+/// of the BIP39 seed, which is how the Solana CLI derives the key of a phrase
+/// with a passphrase.
+pub fn mnemonic_to_entropy_with_passphrase(
+    words: &[&str],
+    passphrase: &str,
+) -> Result<[u8; 32], MnmemonicError> {
+    if passphrase.is_empty() {
+        return mnemonic_to_entropy(words);
+    }
+    let seed = mnemonic_to_seed(words, passphrase)?;
+    let mut entropy = [0u8; 32];
+    entropy.copy_from_slice(&seed[..32]);
+    Ok(entropy)
+}
+
+/// Converts a 12 or 24 word mnemonic and optional passphrase to a BIP39 seed.
+///
+/// Words may be abbreviated to their first four letters like for
+/// [`mnemonic_to_entropy`], and are expanded before deriving the seed.
+pub fn mnemonic_to_seed(words: &[&str], passphrase: &str) -> Result<[u8; 64], MnmemonicError> {
+    const PBKDF2_ROUNDS: u32 = 2048;
+    // Validates word count, words and checksum
+    mnemonic_to_entropy(words)?;
+    let language = Language::English;
+    let phrase = words
+        .iter()
+        .map(|word| {
+            language
+                .find_word(word)
+                .map(|idx| &language[idx])
+                .ok_or(MnmemonicError::NoSuchWord(word.to_string()))
+        })
+        .collect::<Result<Vec<&str>, _>>()?
+        .join(" ");
+    let salt = format!("mnemonic{passphrase}");
+    let mut seed = [0u8; 64];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha512>(
+        phrase.as_bytes(),
+        salt.as_bytes(),
+        PBKDF2_ROUNDS,
+        &mut seed,
+    );
+    Ok(seed)
+}
+
 /// Given some entropy of the proper length, return a mnemonic phrase.
 /// Inspired by the bip39 crate. https://docs.rs/bip39/1.0.1/bip39/index.html
 pub fn entropy_to_mnemonic(entropy: &[u8]) -> Result<Vec<String>, MnmemonicError> {
@@ -330,4 +384,32 @@ mod tests {
         let words = entropy_to_mnemonic(&entropy).expect("mnemonic").join(" ");
         assert_eq!(expected_words, words);
     }
+
+    #[test]
+    fn bip39_seed() {
+        // Test vector from the BIP39 reference implementation
+        let words: Vec<&str> = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abou"
+            .split_whitespace()
+            .collect();
+        let seed = mnemonic_to_seed(&words, "TREZOR").expect("seed");
+        assert_eq!(
+            hex::encode(seed),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+    }
+
+    #[test]
+    fn passphrase_entropy() {
+        let words: Vec<&str> = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+            .split_whitespace()
+            .collect();
+        assert_eq!(
+            mnemonic_to_entropy_with_passphrase(&words, "").expect("entropy"),
+            mnemonic_to_entropy(&words).expect("entropy")
+        );
+        assert_eq!(
+            hex::encode(mnemonic_to_entropy_with_passphrase(&words, "TREZOR").expect("entropy")),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e5349553"
+        );
+    }
 }
@@ -0,0 +1,124 @@
+//! SLIP-0010 derivation of ed25519 keys from a BIP39 seed.
+//!
+//! This is the derivation used by most Solana wallets, like Phantom and
+//! Solflare, which derive their first account at `m/44'/501'/0'/0'`.
+use crate::MnmemonicError;
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+use std::str::FromStr;
+
+/// Derivation path of the first account of most Solana wallets
+pub const SOLANA_DERIVATION_PATH: &str = "m/44'/501'/0'/0'";
+
+const HARDENED_OFFSET: u32 = 0x8000_0000;
+const ED25519_CURVE: &[u8] = b"ed25519 seed";
+
+/// A derivation path of hardened indexes, the only kind ed25519 supports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl FromStr for DerivationPath {
+    type Err = MnmemonicError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || MnmemonicError::InvalidDerivationPath(s.to_string());
+        let mut components = s.trim().split('/');
+        if components.next() != Some("m") {
+            return Err(invalid());
+        }
+        let indexes = components
+            .map(|component| {
+                let index = component
+                    .strip_suffix('\'')
+                    .or_else(|| component.strip_suffix('h'))
+                    .ok_or_else(invalid)?;
+                index
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|index| *index < HARDENED_OFFSET)
+                    .ok_or_else(invalid)
+            })
+            .collect::<Result<Vec<u32>, _>>()?;
+        Ok(Self(indexes))
+    }
+}
+
+impl Default for DerivationPath {
+    fn default() -> Self {
+        // The default path is known to be valid
+        SOLANA_DERIVATION_PATH.parse().unwrap()
+    }
+}
+
+impl std::fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("m")?;
+        for index in &self.0 {
+            write!(f, "/{index}'")?;
+        }
+        Ok(())
+    }
+}
+
+fn hmac_sha512(key: &[u8], parts: &[&[u8]]) -> ([u8; 32], [u8; 32]) {
+    // HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("hmac key");
+    for part in parts {
+        mac.update(part);
+    }
+    let result = mac.finalize().into_bytes();
+    let mut key = [0u8; 32];
+    let mut chain_code = [0u8; 32];
+    key.copy_from_slice(&result[..32]);
+    chain_code.copy_from_slice(&result[32..]);
+    (key, chain_code)
+}
+
+/// Derive the ed25519 secret key at the given path from a BIP39 seed
+pub fn derive_ed25519(seed: &[u8], path: &DerivationPath) -> [u8; 32] {
+    let (mut key, mut chain_code) = hmac_sha512(ED25519_CURVE, &[seed]);
+    for index in &path.0 {
+        let index_bytes = (index | HARDENED_OFFSET).to_be_bytes();
+        (key, chain_code) = hmac_sha512(&chain_code, &[&[0], &key, &index_bytes]);
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_path() {
+        let path: DerivationPath = "m/44'/501'/0h/0'".parse().expect("path");
+        assert_eq!(path, DerivationPath(vec![44, 501, 0, 0]));
+        assert_eq!(path.to_string(), SOLANA_DERIVATION_PATH);
+        assert!("m/44'/501".parse::<DerivationPath>().is_err());
+        assert!("44'/501'".parse::<DerivationPath>().is_err());
+        assert!("m/2147483648'".parse::<DerivationPath>().is_err());
+    }
+
+    #[test]
+    fn slip10_vectors() {
+        // Test vector 1 for ed25519 from SLIP-0010
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").expect("seed");
+        let cases = [
+            (
+                "m",
+                "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7",
+            ),
+            (
+                "m/0'",
+                "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3",
+            ),
+            (
+                "m/0'/1'",
+                "b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2",
+            ),
+        ];
+        for (path, expected) in cases {
+            let path = path.parse().expect("path");
+            assert_eq!(hex::encode(derive_ed25519(&seed, &path)), expected);
+        }
+    }
+}
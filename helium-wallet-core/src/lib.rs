@@ -0,0 +1,10 @@
+//! The encrypted wallet file format of the `helium-wallet` CLI.
+//!
+//! Kept apart from the CLI so that other frontends, like the mobile bindings
+//! in `helium-lib-ffi`, read and write the same wallet files without pulling
+//! in the commands of the CLI.
+pub mod format;
+pub mod pwhash;
+pub mod read_write;
+pub mod result;
+pub mod wallet;
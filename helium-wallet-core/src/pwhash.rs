@@ -44,6 +44,15 @@ impl PwHash {
     pub fn argon2id13_default() -> Self {
         PwHash::Argon2id13(Argon2id13::default())
     }
+
+    /// Argon2id with the given number of passes over the given memory, in
+    /// MiB
+    pub fn argon2id13(passes: u32, memory_mib: u32) -> Self {
+        PwHash::Argon2id13(Argon2id13::with_limits(
+            argon2id13::OpsLimit(passes as usize),
+            argon2id13::MemLimit(memory_mib as usize * MIB),
+        ))
+    }
 }
 
 impl fmt::Display for PwHash {
@@ -70,6 +79,10 @@ impl Pbkdf2 {
         Self { salt, iterations }
     }
 
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
     pub fn pwhash(&self, password: &[u8], hash: &mut [u8]) -> Result {
         pbkdf2::pbkdf2::<Hmac<Sha256>>(password, &self.salt, self.iterations, hash)
             .map_err(|e| anyhow!("Failed to hash password: {e}"))
@@ -88,6 +101,21 @@ impl Pbkdf2 {
     }
 }
 
+const MIB: usize = 1024 * 1024;
+
+/// Memory used by the default Argon2id parameters, in MiB
+pub const ARGON2ID13_DEFAULT_MEMORY_MIB: u32 = (argon2id13::MEMLIMIT_SENSITIVE.0 / MIB) as u32;
+/// Passes over memory of the default Argon2id parameters
+pub const ARGON2ID13_DEFAULT_PASSES: u32 = argon2id13::OPSLIMIT_SENSITIVE.0 as u32;
+/// Largest memory that can be stored in a wallet file, in MiB
+pub const ARGON2ID13_MAX_MEMORY_MIB: u32 = 4095;
+/// Smallest memory new wallet files are hashed with, in MiB
+pub const ARGON2ID13_MIN_MEMORY_MIB: u32 = 8;
+/// Most passes over memory new wallet files are hashed with
+pub const ARGON2ID13_MAX_PASSES: u32 = 64;
+
+/// Argon2id as implemented by libsodium, which always hashes with a single
+/// lane. The salt, memory and number of passes are stored in the wallet.
 #[derive(Clone, Copy, Debug)]
 pub struct Argon2id13 {
     salt: argon2id13::Salt,
@@ -125,6 +153,16 @@ impl Argon2id13 {
         self.salt
     }
 
+    /// Memory used to hash, in bytes
+    pub fn mem_limit(&self) -> usize {
+        self.mem_limit.0
+    }
+
+    /// Number of passes over memory
+    pub fn ops_limit(&self) -> usize {
+        self.ops_limit.0
+    }
+
     pub fn pwhash(&self, password: &[u8], hash: &mut [u8]) -> Result {
         match argon2id13::derive_key(hash, password, &self.salt, self.ops_limit, self.mem_limit) {
             Ok(_) => Ok(()),
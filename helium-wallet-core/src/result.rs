@@ -0,0 +1,4 @@
+pub type Result<T = ()> = anyhow::Result<T>;
+pub type Error = anyhow::Error;
+pub use anyhow::Context;
+pub use anyhow::{anyhow, bail};
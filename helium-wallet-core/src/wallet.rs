@@ -130,6 +130,11 @@ impl Wallet {
         Ok(to_helium_pubkey(&self.public_key)?)
     }
 
+    /// Whether the wallet is stored in the latest version of its format
+    pub fn is_current_version(&self) -> bool {
+        matches!(self.kind, WALLET_KIND_BASIC_V3 | WALLET_KIND_SHARDED_V3)
+    }
+
     pub fn pwhash(&self) -> &PwHash {
         self.format.pwhash()
     }
@@ -373,7 +378,7 @@ impl Builder {
             Wallet::encrypt(&keypair, self.password.as_bytes(), Format::Sharded(format))?
         } else {
             let format = format::Basic {
-                pwhash: PwHash::argon2id13_default(),
+                pwhash: self.pwhash,
             };
             Wallet::encrypt(&keypair, self.password.as_bytes(), Format::Basic(format))?
         };
@@ -433,7 +438,6 @@ fn open_output_file(filename: &Path, create: bool) -> io::Result<fs::File> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::cmd::phrase_to_words;
 
     #[test]
     fn rountrip_basic() {
@@ -448,6 +452,30 @@ mod tests {
         assert_eq!(from_keypair, to_keypair);
     }
 
+    #[test]
+    fn argon2id13_params() {
+        let from_keypair: Arc<Keypair> = Keypair::default().into();
+        let format = format::Basic {
+            pwhash: PwHash::argon2id13(1, 8),
+        };
+        let password = b"passsword";
+        let wallet = Wallet::encrypt(&from_keypair, password, Format::Basic(format))
+            .expect("wallet creation");
+        let mut buffer = vec![];
+        wallet.write(&mut buffer).expect("wallet write");
+
+        let wallet = Wallet::read(&mut buffer.as_slice()).expect("wallet read");
+        let PwHash::Argon2id13(hasher) = wallet.pwhash() else {
+            panic!("expected argon2id13");
+        };
+        assert_eq!(
+            (hasher.ops_limit(), hasher.mem_limit()),
+            (1, 8 * 1024 * 1024)
+        );
+        let to_keypair = wallet.decrypt(password).expect("wallet to keypair");
+        assert_eq!(from_keypair, to_keypair);
+    }
+
     #[test]
     fn basic_from_builder() {
         use std::fs;
@@ -456,9 +484,10 @@ mod tests {
         let _ = fs::remove_file(&path);
 
         let password = String::from("password");
-        let seed_words = phrase_to_words(
-            "drill toddler tongue laundry access silly few faint glove birth crumble add",
-        );
+        let seed_words: Vec<&str> =
+            "drill toddler tongue laundry access silly few faint glove birth crumble add"
+                .split_whitespace()
+                .collect();
 
         let from_keypair = Keypair::from_words(&seed_words).expect("to generate a keypair");
         let entropy = helium_mnemonic::mnemonic_to_entropy(&seed_words)
@@ -493,8 +522,10 @@ mod tests {
             recovery_threshold: 2,
         };
 
-        let seed_words = phrase_to_words(
-            "moment case dirt ski tool dynamic sort ugly pluck drop kiwi knee jar easy verb canal nuclear survey before dwarf prosper cave pottery target");
+        let seed_words: Vec<&str> =
+            "moment case dirt ski tool dynamic sort ugly pluck drop kiwi knee jar easy verb canal nuclear survey before dwarf prosper cave pottery target"
+                .split_whitespace()
+                .collect();
         let from_keypair = Keypair::from_words(&seed_words).expect("to generate a keypair");
         let entropy = helium_mnemonic::mnemonic_to_entropy(&seed_words)
             .expect("entropy from mnemonic")
@@ -0,0 +1,336 @@
+use crate::result::{bail, Result};
+use chrono::{DateTime, Utc};
+use helium_lib::{
+    b64,
+    keypair::{Pubkey, Signature},
+    solana_sdk::transaction::VersionedTransaction,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+/// The previous hash used for the first entry in an audit log
+pub const GENESIS_HASH: [u8; 32] = [0; 32];
+
+static LOG: OnceLock<AuditLog> = OnceLock::new();
+
+/// Enable audit logging of all signing operations to the given file for the
+/// given command
+pub fn init(path: &Path, command: String) -> Result {
+    let _ = LOG.set(AuditLog::open(path, command)?);
+    Ok(())
+}
+
+/// Record a signing operation in the audit log, if one is configured
+pub fn record(signer: &Pubkey, msg: &[u8], signature: &[u8]) -> Result {
+    let Some(log) = LOG.get() else {
+        return Ok(());
+    };
+    log.append(signer, msg, signature)
+}
+
+/// Record every signature of a signed transaction in the audit log, if one is
+/// configured. Signatures still missing from a partially signed transaction
+/// are left out.
+pub fn record_transaction(txn: &VersionedTransaction) -> Result {
+    if LOG.get().is_none() {
+        return Ok(());
+    }
+    let msg = txn.message.serialize();
+    for (signer, signature) in transaction_signatures(txn) {
+        record(signer, &msg, signature.as_ref())?;
+    }
+    Ok(())
+}
+
+/// The signers of a transaction with the signatures they made, signers are
+/// the first accounts of the message in the order of the signatures
+fn transaction_signatures(txn: &VersionedTransaction) -> Vec<(&Pubkey, &Signature)> {
+    txn.message
+        .static_account_keys()
+        .iter()
+        .zip(&txn.signatures)
+        .filter(|(_, signature)| **signature != Signature::default())
+        .collect()
+}
+
+/// Placeholder for the values left out of the recorded command line
+const REDACTED: &str = "***";
+
+/// The command line to record for the given arguments of the given command.
+///
+/// Only subcommand and option names are kept. Option values and positional
+/// arguments are replaced, since secrets like passwords or seed phrases can
+/// be passed as either.
+pub fn redact_command<I: IntoIterator<Item = String>>(command: &clap::Command, args: I) -> String {
+    let mut current = command;
+    let mut options_ended = false;
+    let mut redacted = vec![];
+    for arg in args {
+        if options_ended {
+            redacted.push(REDACTED.to_string());
+        } else if arg == "--" {
+            options_ended = true;
+            redacted.push(arg);
+        } else if arg.starts_with('-') {
+            match arg.split_once('=') {
+                Some((name, _)) => redacted.push(format!("{name}={REDACTED}")),
+                None => redacted.push(arg),
+            }
+        } else if let Some(subcommand) = current.find_subcommand(&arg) {
+            current = subcommand;
+            redacted.push(arg);
+        } else {
+            redacted.push(REDACTED.to_string());
+        }
+    }
+    redacted.join(" ")
+}
+
+/// A single entry in the audit log. Each entry includes the hash of the
+/// previous entry, making the log tamper-evident.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub command: String,
+    #[serde(with = "helium_lib::keypair::serde_pubkey")]
+    pub signer: Pubkey,
+    /// Base64 encoded sha256 digest of the signed message
+    pub digest: String,
+    /// Base64 encoded signature
+    pub signature: String,
+    /// Base64 encoded hash of the previous entry
+    pub prev: String,
+    /// Base64 encoded hash of this entry
+    pub hash: String,
+}
+
+impl AuditEntry {
+    fn new(
+        prev: &[u8],
+        command: &str,
+        signer: &Pubkey,
+        msg: &[u8],
+        signature: &[u8],
+    ) -> Result<Self> {
+        let mut entry = Self {
+            timestamp: Utc::now(),
+            command: command.to_string(),
+            signer: *signer,
+            digest: b64::encode(Sha256::digest(msg)),
+            signature: b64::encode(signature),
+            prev: b64::encode(prev),
+            hash: String::new(),
+        };
+        entry.hash = b64::encode(entry.compute_hash()?);
+        Ok(entry)
+    }
+
+    /// Computes the hash of this entry over all fields except the hash itself
+    pub fn compute_hash(&self) -> Result<Vec<u8>> {
+        let mut unhashed = self.clone();
+        unhashed.hash = String::new();
+        let bytes = serde_json::to_vec(&unhashed)?;
+        Ok(Sha256::digest(bytes).to_vec())
+    }
+}
+
+struct AuditLog {
+    path: PathBuf,
+    command: String,
+    prev: Mutex<Vec<u8>>,
+}
+
+impl AuditLog {
+    fn open(path: &Path, command: String) -> Result<Self> {
+        let prev = match fs::File::open(path) {
+            Ok(file) => last_hash(io::BufReader::new(file))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => GENESIS_HASH.to_vec(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            command,
+            prev: Mutex::new(prev),
+        })
+    }
+
+    fn append(&self, signer: &Pubkey, msg: &[u8], signature: &[u8]) -> Result {
+        let mut prev = self.prev.lock().expect("audit log lock poisoned");
+        let entry = AuditEntry::new(&prev, &self.command, signer, msg, signature)?;
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        *prev = b64::decode(&entry.hash)?;
+        Ok(())
+    }
+}
+
+fn last_hash<R: BufRead>(reader: R) -> Result<Vec<u8>> {
+    let mut prev = GENESIS_HASH.to_vec();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: AuditEntry = serde_json::from_str(&line)?;
+        prev = b64::decode(&entry.hash)?;
+    }
+    Ok(prev)
+}
+
+/// Verifies the hash chain of the given audit log and returns the number of
+/// verified entries.
+pub fn verify<R: BufRead>(reader: R) -> Result<usize> {
+    let mut prev = GENESIS_HASH.to_vec();
+    let mut count = 0;
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: AuditEntry = serde_json::from_str(&line)?;
+        if b64::decode(&entry.prev)? != prev {
+            bail!("entry {} does not link to previous entry", index + 1);
+        }
+        let hash = entry.compute_hash()?;
+        if b64::decode(&entry.hash)? != hash {
+            bail!("entry {} hash mismatch", index + 1);
+        }
+        prev = hash;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helium_lib::keypair::{Keypair, Signer};
+
+    fn mk_log(count: usize) -> Vec<String> {
+        let keypair = Keypair::default();
+        let mut prev = GENESIS_HASH.to_vec();
+        let mut lines = vec![];
+        for i in 0..count {
+            let msg = format!("message {i}");
+            let signature = keypair.sign(msg.as_bytes()).expect("signature");
+            let entry = AuditEntry::new(
+                &prev,
+                "sign msg",
+                &keypair.pubkey(),
+                msg.as_bytes(),
+                signature.as_ref(),
+            )
+            .expect("entry");
+            prev = b64::decode(&entry.hash).expect("hash");
+            lines.push(serde_json::to_string(&entry).expect("json"));
+        }
+        lines
+    }
+
+    #[test]
+    fn verify_chain() {
+        let lines = mk_log(3);
+        let count = verify(io::Cursor::new(lines.join("\n"))).expect("verified");
+        assert_eq!(3, count);
+        let prev = last_hash(io::Cursor::new(lines.join("\n"))).expect("last hash");
+        let last: AuditEntry = serde_json::from_str(&lines[2]).expect("entry");
+        assert_eq!(b64::decode(&last.hash).expect("hash"), prev);
+    }
+
+    #[test]
+    fn verify_tampered_chain() {
+        let mut lines = mk_log(3);
+        let mut entry: AuditEntry = serde_json::from_str(&lines[1]).expect("entry");
+        entry.command = "transfer".to_string();
+        lines[1] = serde_json::to_string(&entry).expect("json");
+        verify(io::Cursor::new(lines.join("\n"))).expect_err("tampered entry");
+    }
+
+    #[test]
+    fn redacted_command() {
+        let command = clap::Command::new("helium-wallet")
+            .arg(clap::Arg::new("password").long("password"))
+            .subcommand(
+                clap::Command::new("transfer").subcommand(
+                    clap::Command::new("one")
+                        .arg(clap::Arg::new("address"))
+                        .arg(clap::Arg::new("memo").long("memo")),
+                ),
+            );
+        let args = [
+            "--password",
+            "secret",
+            "transfer",
+            "one",
+            "address",
+            "--memo=note",
+        ]
+        .map(str::to_string);
+        assert_eq!(
+            redact_command(&command, args),
+            "--password *** transfer one *** --memo=***"
+        );
+    }
+
+    #[test]
+    fn records_all_signatures() {
+        use helium_lib::solana_sdk::{
+            hash::Hash,
+            instruction::{AccountMeta, Instruction},
+            message::{v0, VersionedMessage},
+        };
+        let (payer, cosigner, missing) =
+            (Keypair::default(), Keypair::default(), Keypair::default());
+        let ix = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            vec![
+                AccountMeta::new_readonly(cosigner.pubkey(), true),
+                AccountMeta::new_readonly(missing.pubkey(), true),
+            ],
+        );
+        let msg = VersionedMessage::V0(
+            v0::Message::try_compile(&payer.pubkey(), &[ix], &[], Hash::default())
+                .expect("message"),
+        );
+        let data = msg.serialize();
+        let signatures = msg.static_account_keys()[..3]
+            .iter()
+            .map(|key| {
+                [&payer, &cosigner]
+                    .into_iter()
+                    .find(|keypair| keypair.pubkey() == *key)
+                    .map(|keypair| keypair.sign(&data).expect("signature"))
+                    .unwrap_or_default()
+            })
+            .collect();
+        let txn = VersionedTransaction {
+            signatures,
+            message: msg,
+        };
+        let mut signers: Vec<Pubkey> = transaction_signatures(&txn)
+            .into_iter()
+            .map(|(signer, _)| *signer)
+            .collect();
+        signers.sort();
+        let mut expected = vec![payer.pubkey(), cosigner.pubkey()];
+        expected.sort();
+        assert_eq!(signers, expected);
+    }
+
+    #[test]
+    fn verify_dropped_entry() {
+        let mut lines = mk_log(3);
+        lines.remove(1);
+        verify(io::Cursor::new(lines.join("\n"))).expect_err("dropped entry");
+    }
+}
@@ -1,33 +1,148 @@
 use crate::cmd::*;
-use helium_lib::{asset, dao, entity_key};
+use helium_lib::{
+    asset::{self, Asset, AssetFilter},
+    dao, entity_key,
+    keypair::Signer,
+};
 
 #[derive(Clone, Debug, clap::Args)]
-/// Burn a given asset (NFT)
+/// Burn a given asset (NFT), or all assets of the wallet matching filters.
+///
+/// With --filter the assets of the wallet matching every filter are burned,
+/// packed into as few transactions as possible, and the result of every asset
+/// is reported. Hotspots and other Helium entities are never selected by
+/// filters unless --include-hotspots is given.
 pub struct Cmd {
     /// Subdao for command
-    subdao: dao::SubDao,
+    #[arg(required_unless_present = "filters", conflicts_with = "filters")]
+    subdao: Option<dao::SubDao>,
     /// Entity key of asset to burn
-    #[clap(flatten)]
-    entity_key: entity_key::EncodedEntityKey,
+    #[arg(required_unless_present = "filters", conflicts_with = "filters")]
+    entity_key: Option<String>,
+    /// Encoding of the entity key
+    #[arg(long, default_value_t = entity_key::EntityKeyEncoding::UTF8)]
+    encoding: entity_key::EntityKeyEncoding,
+    /// Select assets to burn by symbol=<symbol>, collection=<address> or
+    /// creator=<address>. Can be repeated, assets must match every filter
+    #[arg(long = "filter")]
+    filters: Vec<AssetFilter>,
+    /// Also select Hotspots and other Helium entities with the filters
+    #[arg(long, requires = "filters")]
+    include_hotspots: bool,
+    /// Only list the assets the filters select
+    #[arg(long, requires = "filters")]
+    dry_run: bool,
     /// Commit the transaction
     #[command(flatten)]
     commit: CommitOpts,
 }
 
+fn asset_json(asset: &Asset) -> serde_json::Value {
+    json!({
+        "id": asset.id.to_string(),
+        "name": asset.content.metadata.name,
+        "symbol": asset.content.metadata.symbol,
+        "collection": asset.collection().map(|collection| collection.to_string()),
+    })
+}
+
 impl Cmd {
     pub async fn run(&self, opts: Opts) -> Result {
+        if !self.filters.is_empty() {
+            return self.run_filtered(opts).await;
+        }
+        let Some(entity_key) = &self.entity_key else {
+            bail!("an entity key or a filter is required");
+        };
+        let entity_key = entity_key::EncodedEntityKey {
+            encoding: self.encoding,
+            entity_key: entity_key.clone(),
+        };
         let client = opts.client()?;
-        let password = get_wallet_password(false)?;
-        let keypair = opts.load_keypair(password.as_bytes())?;
-        let asset = asset::for_entity_key(&client, &self.entity_key.as_entity_key()?).await?;
-        let (tx, _) = asset::burn(
-            &client,
-            &asset.id,
-            &keypair,
-            &self.commit.transaction_opts(&client),
+        let signer = opts.load_signer(&self.commit)?;
+        let asset = asset::for_entity_key(&client, &entity_key.as_entity_key()?).await?;
+        let (msg, _) =
+            asset::burn_message(&client, &asset.id, &self.commit.transaction_opts(&client)).await?;
+        let tx = signer.sign(msg)?;
+
+        print_json(
+            &self
+                .commit
+                .maybe_commit_with_signer(tx, &client, &signer)
+                .await?
+                .to_json(),
         )
-        .await?;
+    }
+
+    async fn run_filtered(&self, opts: Opts) -> Result {
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let wallet = keypair.pubkey();
+        let client = opts.client()?;
+
+        let selected: Vec<Asset> = asset::all_for_owner(&client, &wallet)
+            .await?
+            .into_iter()
+            .filter(|asset| !asset.burnt)
+            .filter(|asset| self.include_hotspots || !asset.is_hotspot())
+            .filter(|asset| self.filters.iter().all(|filter| filter.matches(asset)))
+            .collect();
+        if self.dry_run || selected.is_empty() {
+            return print_json(&json!({
+                "count": selected.len(),
+                "assets": selected.iter().map(asset_json).collect::<Vec<_>>(),
+            }));
+        }
+
+        let txn_opts = self.commit.transaction_opts(&client);
+        let ids: Vec<_> = selected.iter().map(|asset| asset.id).collect();
+        let batches = asset::burn_batches(&client, &ids, &wallet, &txn_opts).await?;
+        let mut results: Vec<serde_json::Value> = selected.iter().map(asset_json).collect();
+        let mut fee_cap_reached = false;
+        for (transaction, batch) in batches.iter().enumerate() {
+            let commit = if fee_cap_reached {
+                None
+            } else {
+                let commit = match batch.message(&client, &wallet, &txn_opts).await {
+                    Ok((msg, _)) => match VersionedTransaction::try_new(msg, &[&*keypair]) {
+                        Ok(tx) => {
+                            self.commit
+                                .maybe_commit_with_keypair(tx, &client, &keypair)
+                                .await
+                        }
+                        Err(err) => Err(err.into()),
+                    },
+                    Err(err) => Err(err.into()),
+                };
+                if FeeCapExceeded::is_cause(&commit) {
+                    warnings::warn(
+                        warnings::FEE_CAP_REACHED,
+                        format!(
+                            "maximum total fee reached, skipped burning from asset {}",
+                            selected[batch.items[0]].id
+                        ),
+                    );
+                    fee_cap_reached = true;
+                    None
+                } else {
+                    Some(commit)
+                }
+            };
+            let (status, result) = match &commit {
+                None => ("skipped", serde_json::Value::Null),
+                Some(commit) if commit.is_ok() => ("sent", commit.to_json()),
+                Some(commit) => ("failed", commit.to_json()),
+            };
+            for index in &batch.items {
+                results[*index]["status"] = status.into();
+                results[*index]["transaction"] = transaction.into();
+                results[*index]["result"] = result.clone();
+            }
+        }
 
-        print_json(&self.commit.maybe_commit(tx, &client).await?.to_json())
+        print_json(&json!({
+            "count": selected.len(),
+            "transactions": batches.len(),
+            "assets": results,
+        }))
     }
 }
@@ -1,5 +1,12 @@
 use crate::cmd::*;
-use helium_lib::{asset, dao::SubDao, entity_key, keypair};
+use helium_lib::{
+    asset::{
+        self,
+        tree::{self, ProofFreshness},
+    },
+    dao::SubDao,
+    entity_key, keypair,
+};
 
 #[derive(Clone, Debug, clap::Args)]
 /// Get details for a given asset
@@ -9,6 +16,13 @@ pub struct Cmd {
     /// Display raw asset data
     #[arg(long)]
     raw: bool,
+    /// Check the proof of the asset from the DAS indexer against the
+    /// on-chain tree and report the health of the tree.
+    ///
+    /// A stale proof means the indexer is behind the chain, which makes
+    /// transfers and claims of the asset fail until it catches up.
+    #[arg(long)]
+    diagnose: bool,
     /// Entity key of asset to look up
     #[clap(flatten)]
     entity_key: entity_key::EncodedEntityKey,
@@ -18,11 +32,56 @@ impl Cmd {
     pub async fn run(&self, opts: Opts) -> Result {
         let client = opts.client()?;
         let asset = asset::for_entity_key(&client, &self.entity_key.as_entity_key()?).await?;
-        if self.raw {
-            print_json(&asset)
+        let diagnostics = if self.diagnose {
+            Some(Diagnostics::for_asset(&client, &asset).await?)
+        } else {
+            None
+        };
+        let mut json = if self.raw {
+            serde_json::to_value(&asset)?
         } else {
-            print_json(&AssetInfo::from(asset))
+            serde_json::to_value(AssetInfo::from(asset))?
+        };
+        if let Some(diagnostics) = diagnostics {
+            json["diagnostics"] = serde_json::to_value(diagnostics)?;
+        }
+        print_json(&json)
+    }
+}
+
+#[derive(serde::Serialize)]
+struct Diagnostics {
+    tree: tree::TreeState,
+    #[serde(with = "keypair::serde_pubkey")]
+    proof_root: keypair::Pubkey,
+    proof_freshness: ProofFreshness,
+    /// Number of proof nodes given by the DAS indexer
+    proof_nodes: usize,
+    /// Number of proof nodes transactions for the asset need
+    required_proof_nodes: usize,
+}
+
+impl Diagnostics {
+    async fn for_asset(client: &client::Client, asset: &asset::Asset) -> Result<Self> {
+        let proof = asset::proof::get(client, &asset.id).await?;
+        let state = tree::state(client, &proof.tree_id).await?;
+        let proof_freshness = state.proof_freshness(&proof);
+        if proof_freshness == ProofFreshness::Stale {
+            warnings::warn(
+                warnings::STALE_PROOF,
+                format!(
+                    "the DAS proof for {} is older than the changes the tree accepts, transactions for it will fail until the indexer catches up",
+                    asset.id
+                ),
+            );
         }
+        Ok(Self {
+            proof_root: proof.root,
+            proof_freshness,
+            proof_nodes: proof.proof.len(),
+            required_proof_nodes: state.proof_len(),
+            tree: state,
+        })
     }
 }
 
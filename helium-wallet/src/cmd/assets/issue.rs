@@ -0,0 +1,59 @@
+use crate::cmd::{assets::add_issued, *};
+use helium_lib::hotspot::dataonly;
+
+#[derive(Clone, Debug, clap::Args)]
+/// Issue a data-only entity (NFT) for a given entity key to this wallet.
+///
+/// This can be used for entities other than Hotspots, like service provider
+/// keys. The entity key is the helium public key of the entity, since the
+/// data-only program records all entity keys in their b58 encoding, and the
+/// given message must be signed by that key. The ecc verifier checks the
+/// signature before co-signing the issue transaction.
+pub struct Cmd {
+    /// Helium public key of the entity to issue
+    entity_key: helium_crypto::PublicKey,
+
+    /// Base64 encoded message signed by the entity key
+    #[arg(long)]
+    msg: String,
+
+    /// Base64 encoded signature of the message by the entity key
+    #[arg(long)]
+    signature: String,
+
+    /// Optional url for the ecc signature verifier.
+    ///
+    /// If the main API URL is one of the shortcuts (like "m" or "d") the
+    /// default verifier for that network will be used.
+    #[arg(long)]
+    verifier: Option<String>,
+
+    /// Commit the transaction
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let msg = b64::decode(&self.msg)?;
+        let signature = b64::decode(&self.signature)?;
+        let client = opts.client()?;
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let (tx, _) = dataonly::issue_entity(
+            &client,
+            opts.verifier_url(&self.verifier),
+            &self.entity_key,
+            &msg,
+            &signature,
+            &keypair,
+            &self.commit.transaction_opts(&client),
+        )
+        .await?;
+
+        let mut json = self.commit.maybe_commit(tx, &client).await?.to_json();
+        if self.commit.commit {
+            add_issued(&mut json, &client, &self.entity_key, &opts).await;
+        }
+        print_json(&json)
+    }
+}
@@ -1,8 +1,14 @@
 use crate::cmd::*;
+use helium_crypto::PublicKey;
+use helium_lib::{asset, dao::Dao, keypair::Pubkey};
+use std::time::Duration;
 
 pub mod burn;
 pub mod info;
+pub mod issue;
 pub mod rewards;
+pub mod schedule;
+pub mod search;
 
 #[derive(Debug, clap::Args)]
 pub struct Cmd {
@@ -20,16 +26,62 @@ impl Cmd {
 /// Commands on assets
 pub enum AssetCommand {
     Rewards(rewards::Cmd),
+    Claim(schedule::Cmd),
     Info(info::Cmd),
     Burn(burn::Cmd),
+    Issue(issue::Cmd),
+    Search(search::Cmd),
 }
 
 impl AssetCommand {
     pub async fn run(&self, opts: Opts) -> Result {
         match self {
             Self::Rewards(cmd) => cmd.run(opts).await,
+            Self::Claim(cmd) => cmd.run(opts).await,
             Self::Info(cmd) => cmd.run(opts).await,
             Self::Burn(cmd) => cmd.run(opts).await,
+            Self::Issue(cmd) => cmd.run(opts).await,
+            Self::Search(cmd) => cmd.run(opts).await,
         }
     }
 }
+
+/// How long to wait for a newly issued asset to be indexed
+const ASSET_WAIT_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn explorer_url(address: &Pubkey, opts: &Opts) -> String {
+    let cluster = if opts.is_devnet() {
+        "?cluster=devnet"
+    } else {
+        ""
+    };
+    format!("https://explorer.solana.com/address/{address}{cluster}")
+}
+
+/// Add the asset and key to asset account of a newly issued entity to the
+/// given command output once the asset is indexed, so it can be looked up
+/// right away
+pub async fn add_issued(
+    json: &mut serde_json::Value,
+    client: &client::Client,
+    entity_key: &PublicKey,
+    opts: &Opts,
+) {
+    let kta = Dao::Hnt.entity_key_to_kta_key(entity_key);
+    let asset = match asset::wait_for_entity_key(client, entity_key, ASSET_WAIT_TIMEOUT).await {
+        Ok(asset) => Some(asset.id),
+        Err(err) => {
+            warnings::warn(
+                warnings::ASSET_NOT_INDEXED,
+                format!("asset of {entity_key} not found yet: {err}"),
+            );
+            None
+        }
+    };
+    json["asset"] = json!(asset.map(|asset| asset.to_string()));
+    json["kta"] = json!(kta.to_string());
+    json["explorer"] = json!({
+        "asset": asset.map(|asset| explorer_url(&asset, opts)),
+        "kta": explorer_url(&kta, opts),
+    });
+}
@@ -1,6 +1,23 @@
-use crate::cmd::*;
+use crate::{
+    audit,
+    cmd::*,
+    offline::{ClaimBundle, ClaimSignatures},
+    policy,
+};
 use anyhow::Context;
-use helium_lib::{entity_key, reward, reward::ClaimableToken, token::TokenAmount};
+use helium_lib::{
+    entity_key::{self, AsEntityKey},
+    hotspot,
+    keypair::{Keypair, Pubkey, Signer},
+    kta, reward,
+    reward::{ClaimRouting, ClaimableToken, RewardShare, RewardSplit},
+    token::{self, TokenAmount},
+    TransactionOpts,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+const SPLITS_FILE: &str = "reward-splits.json";
 
 #[derive(Debug, Clone, clap::Args)]
 pub struct Cmd {
@@ -17,18 +34,30 @@ impl Cmd {
 #[derive(Debug, Clone, clap::Subcommand)]
 pub enum RewardsCommand {
     Claim(ClaimCmd),
+    Bundle(BundleCmd),
     Pending(PendingCmd),
     Lifetime(LifetimeCmd),
     MaxClaim(MaxClaimCmd),
+    Oracles(OraclesCmd),
+    Split(SplitCmd),
+    Cost(CostCmd),
+    Fund(FundCmd),
+    Destination(DestinationCmd),
 }
 
 impl RewardsCommand {
     pub async fn run(&self, opts: Opts) -> Result {
         match self {
             Self::Claim(cmd) => cmd.run(opts).await,
+            Self::Bundle(cmd) => cmd.run(opts).await,
             Self::MaxClaim(cmd) => cmd.run(opts).await,
             Self::Pending(cmd) => cmd.run(opts).await,
             Self::Lifetime(cmd) => cmd.run(opts).await,
+            Self::Oracles(cmd) => cmd.run(opts).await,
+            Self::Split(cmd) => cmd.run(opts).await,
+            Self::Cost(cmd) => cmd.run(opts).await,
+            Self::Fund(cmd) => cmd.run(opts).await,
+            Self::Destination(cmd) => cmd.run(opts).await,
         }
     }
 }
@@ -45,34 +74,149 @@ pub struct ClaimCmd {
     /// If not specific the full pending amount is claimed, limited by the maximum
     /// claim amount for the subdao
     pub amount: Option<f64>,
+    /// Simulate the claim and report the expected token changes, compute
+    /// units and fees before asking to commit it
+    #[arg(long)]
+    pub simulate_detailed: bool,
+    /// Only pay for the claim, for assets owned by another wallet.
+    ///
+    /// Checks that the claim needs no signature from the asset owner and
+    /// pays out to the reward destination of the asset.
+    #[arg(long)]
+    pub payer_only: bool,
+    /// Ignore the saved reward split for the asset and keep the full claim
+    #[arg(long)]
+    pub no_split: bool,
     /// Commit the claim transaction.
     #[command(flatten)]
     pub commit: CommitOpts,
 }
 
+/// Check where a claim for the given asset pays out to, including the shares
+/// of the given split, against the address policy. The reward destination is
+/// only looked up when a policy is in force.
+pub(crate) async fn check_claim_policy(
+    client: &client::Client,
+    token: ClaimableToken,
+    encoded_entity_key: &entity_key::EncodedEntityKey,
+    split: &RewardSplit,
+    wallet: &Pubkey,
+) -> Result {
+    if !policy::is_active() {
+        return Ok(());
+    }
+    let routing = reward::claim_routing(client, token, encoded_entity_key).await?;
+    policy::check_external(wallet, &routing.destination, "reward destination")?;
+    for share in &split.shares {
+        policy::check_external(wallet, &share.destination, "reward split destination")?;
+    }
+    Ok(())
+}
+
+/// A claim built for the payer alone
+enum PayerOnlyClaim {
+    /// There is nothing to claim
+    Empty,
+    /// A claim the payer can submit without the asset owner
+    Claimable(VersionedTransaction),
+    /// The claim needs the asset owner to sign or does not pay out to the
+    /// reward destination of the asset, for the given reason
+    NotClaimable(String),
+}
+
+/// Build a claim that is paid for and signed by the payer alone, after
+/// checking that it needs no signature from the asset owner and pays out to
+/// the reward destination of the asset. Failures to look up or build the
+/// claim are returned as errors, they say nothing about whether the asset
+/// can be claimed without its owner.
+async fn payer_only_claim(
+    client: &client::Client,
+    token: ClaimableToken,
+    amount: Option<TokenAmount>,
+    encoded_entity_key: &entity_key::EncodedEntityKey,
+    keypair: &Keypair,
+    opts: &TransactionOpts,
+) -> Result<(PayerOnlyClaim, ClaimRouting)> {
+    let routing = reward::claim_routing(client, token, encoded_entity_key).await?;
+    let payer = keypair.pubkey();
+    policy::check_external(&payer, &routing.destination, "reward destination")?;
+    let Some((mut txn, _)) =
+        reward::claim_transaction(client, token, amount, encoded_entity_key, &payer, opts).await?
+    else {
+        return Ok((PayerOnlyClaim::Empty, routing));
+    };
+    if let Err(err) = routing.check_transaction(&txn, &payer) {
+        return Ok((PayerOnlyClaim::NotClaimable(err.to_string()), routing));
+    }
+    let blockhash = txn.message.recent_blockhash;
+    txn.try_sign(&[keypair], blockhash)?;
+    Ok((PayerOnlyClaim::Claimable(txn.into()), routing))
+}
+
 impl ClaimCmd {
     pub async fn run(&self, opts: Opts) -> Result {
-        let password = get_wallet_password(false)?;
-        let keypair = opts.load_keypair(password.as_bytes())?;
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
         let client = opts.client()?;
         let transaction_opts = self.commit.transaction_opts(&client);
 
         let token_amount = self
             .amount
-            .map(|amount| TokenAmount::from_f64(self.token.into(), amount).amount);
-        let Some((tx, _)) = reward::claim(
-            &client,
-            self.token,
-            token_amount,
-            &self.entity_key,
-            &keypair,
-            &transaction_opts,
-        )
-        .await?
-        else {
+            .map(|amount| TokenAmount::from_f64(self.token.into(), amount));
+        let tx = if self.payer_only {
+            match payer_only_claim(
+                &client,
+                self.token,
+                token_amount,
+                &self.entity_key,
+                &keypair,
+                &transaction_opts,
+            )
+            .await?
+            .0
+            {
+                PayerOnlyClaim::Empty => None,
+                PayerOnlyClaim::Claimable(tx) => Some(tx),
+                PayerOnlyClaim::NotClaimable(reason) => {
+                    bail!("not claimable without the asset owner: {reason}")
+                }
+            }
+        } else {
+            let split = if self.no_split {
+                RewardSplit::default()
+            } else {
+                SplitProfiles::load()?.get(&self.entity_key.entity_key)
+            };
+            check_claim_policy(
+                &client,
+                self.token,
+                &self.entity_key,
+                &split,
+                &keypair.pubkey(),
+            )
+            .await?;
+            reward::claim_split(
+                &client,
+                self.token,
+                token_amount,
+                &self.entity_key,
+                &split,
+                &keypair,
+                &transaction_opts,
+            )
+            .await?
+            .map(|(tx, _)| tx)
+        };
+        let Some(tx) = tx else {
             bail!("No rewards to claim")
         };
 
+        if self.simulate_detailed {
+            let success = print_detailed_simulation(&client, &[tx.clone()]).await?;
+            if !success || !self.commit.commit || !confirm("Commit claim transaction?")? {
+                return Ok(());
+            }
+        }
+
         let claim_response = self
             .commit
             .maybe_commit(tx, &client)
@@ -82,6 +226,777 @@ impl ClaimCmd {
     }
 }
 
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum QueueOrder {
+    /// Claim in the order the entity keys were given
+    Given,
+    /// Claim the entity keys with the largest pending rewards first
+    AmountDesc,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Claim rewards for a queue of assets, one transaction per asset
+///
+/// Assets without pending rewards are skipped. A failed claim is reported and
+/// does not stop the remaining claims. The queue does stop at the first claim
+/// that would exceed the maximum total fee, if one is given.
+pub struct QueueCmd {
+    /// Token for command
+    token: ClaimableToken,
+    /// Entity keys of the assets to claim rewards for
+    #[arg(required = true)]
+    entity_keys: Vec<String>,
+    /// Encoding of the given entity keys
+    #[arg(long, default_value_t = entity_key::EntityKeyEncoding::UTF8)]
+    encoding: entity_key::EntityKeyEncoding,
+    /// Order in which to claim the assets.
+    ///
+    /// Ordering by amount fetches the pending rewards of all assets first, so
+    /// that a limited claim window, like the maximum claim amount of a subdao,
+    /// is spent on the most valuable claims.
+    #[arg(long, value_enum, default_value_t = QueueOrder::Given)]
+    order_by: QueueOrder,
+    /// Only claim the first N assets of the queue with pending rewards
+    #[arg(long)]
+    top: Option<usize>,
+    /// Only pay for the claims, for assets owned by other wallets.
+    ///
+    /// Each claim is checked to need no signature from the asset owner and
+    /// to pay out to the reward destination of the asset. The reward routing
+    /// of every asset is reported, along with whether it could be claimed
+    /// without the owner.
+    #[arg(long)]
+    payer_only: bool,
+    /// Initialize the reward recipients missing for assets of the queue in
+    /// batches before claiming.
+    ///
+    /// A claim for an asset that was never claimed also initializes its
+    /// recipient. Doing that for many assets at once up front takes fewer
+    /// transactions than doing it as part of every claim. Each batch is
+    /// waited for to be confirmed, and assets whose recipient still is not
+    /// initialized are not claimed. Without --commit the claims include the
+    /// inits instead.
+    #[arg(long)]
+    init_recipients: bool,
+    /// Commit the claim transactions.
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+impl QueueCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let client = opts.client()?;
+        let transaction_opts = self.commit.transaction_opts(&client);
+
+        let mut queue: Vec<(String, Option<TokenAmount>)> = if self.top.is_none()
+            && matches!(self.order_by, QueueOrder::Given)
+        {
+            self.entity_keys
+                .iter()
+                .map(|entity_key| (entity_key.clone(), None))
+                .collect()
+        } else {
+            // Assets without pending rewards are left out before taking
+            // the top of the queue, so they do not take the place of
+            // claims
+            let mut pending =
+                reward::pending(&client, self.token, &self.entity_keys, self.encoding.into())
+                    .await?;
+            let mut queue: Vec<_> = self
+                .entity_keys
+                .iter()
+                .filter_map(|entity_key| {
+                    let reward = pending.remove(entity_key)?;
+                    (reward.reward.amount > 0).then(|| (entity_key.clone(), Some(reward.reward)))
+                })
+                .collect();
+            if let QueueOrder::AmountDesc = self.order_by {
+                queue.sort_by(|(_, a), (_, b)| b.cmp(a));
+            }
+            queue
+        };
+        if let Some(top) = self.top {
+            queue.truncate(top);
+        }
+
+        let splits = SplitProfiles::load()?;
+        let queue_len = queue.len();
+        let mut results = vec![];
+        let mut missing_recipients = HashSet::new();
+        if self.init_recipients {
+            let entity_keys = queue
+                .iter()
+                .map(|(entity_key, _)| entity_key::from_str(entity_key, self.encoding.into()))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let inits = init_recipients(
+                &client,
+                self.token,
+                &entity_keys,
+                &keypair,
+                &self.commit,
+                &transaction_opts,
+            )
+            .await?;
+            for (indices, response) in inits.batches {
+                let mut json = response.to_json();
+                json["initialized"] = indices
+                    .iter()
+                    .map(|index| queue[*index].0.clone())
+                    .collect::<Vec<_>>()
+                    .into();
+                results.push(json);
+            }
+            if inits.fee_cap_reached {
+                fee_cap_reached(0, queue_len);
+                return print_json(&results);
+            }
+            missing_recipients = inits.missing;
+        }
+        let initialized = results.len();
+        for (index, (entity_key, pending)) in queue.into_iter().enumerate() {
+            if missing_recipients.contains(&index) {
+                results.push(json!({
+                    "result": "error",
+                    "error": "reward recipient was not initialized",
+                    "entity_key": entity_key,
+                }));
+                continue;
+            }
+            let encoded_entity_key = entity_key::EncodedEntityKey {
+                encoding: self.encoding,
+                entity_key: entity_key.clone(),
+            };
+            let claimed = results.len() - initialized;
+            if self.payer_only {
+                let mut json = match payer_only_claim(
+                    &client,
+                    self.token,
+                    None,
+                    &encoded_entity_key,
+                    &keypair,
+                    &transaction_opts,
+                )
+                .await
+                {
+                    Ok((PayerOnlyClaim::Claimable(tx), routing)) => {
+                        let response = self.commit.maybe_commit(tx, &client).await;
+                        if FeeCapExceeded::is_cause(&response) {
+                            fee_cap_reached(claimed, queue_len);
+                            break;
+                        }
+                        let mut json = response.to_json();
+                        json["routing"] = serde_json::to_value(routing)?;
+                        json["claimable_without_owner"] = true.into();
+                        json
+                    }
+                    Ok((PayerOnlyClaim::Empty, _)) => continue,
+                    Ok((PayerOnlyClaim::NotClaimable(reason), routing)) => json!({
+                        "result": "error",
+                        "error": reason,
+                        "routing": routing,
+                        "claimable_without_owner": false,
+                    }),
+                    // Whether the asset is claimable without its owner is
+                    // unknown when the claim could not be looked up
+                    Err(err) => json!({
+                        "result": "error",
+                        "error": err.to_string(),
+                    }),
+                };
+                json["entity_key"] = entity_key.into();
+                if let Some(pending) = pending {
+                    json["pending"] = serde_json::to_value(pending)?;
+                }
+                results.push(json);
+                continue;
+            }
+            let split = splits.get(&entity_key);
+            let claim = async {
+                check_claim_policy(
+                    &client,
+                    self.token,
+                    &encoded_entity_key,
+                    &split,
+                    &keypair.pubkey(),
+                )
+                .await?;
+                Ok::<_, Error>(
+                    reward::claim_split(
+                        &client,
+                        self.token,
+                        None,
+                        &encoded_entity_key,
+                        &split,
+                        &keypair,
+                        &transaction_opts,
+                    )
+                    .await?,
+                )
+            };
+            let response = match claim.await {
+                Ok(Some((tx, _))) => self.commit.maybe_commit(tx, &client).await,
+                Ok(None) => continue,
+                Err(err) => Err(err),
+            };
+            if FeeCapExceeded::is_cause(&response) {
+                fee_cap_reached(claimed, queue_len);
+                break;
+            }
+            let mut json = response.to_json();
+            json["entity_key"] = entity_key.into();
+            if let Some(pending) = pending {
+                json["pending"] = serde_json::to_value(pending)?;
+            }
+            results.push(json);
+        }
+
+        print_json(&results)
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Approve claims with an owner key kept on an offline machine.
+///
+/// `export` prepares the claims of a list of assets, including the oracle
+/// signatures, as a bundle. `sign` signs every claim of the bundle with the
+/// owner key and needs no network access. `submit` imports the signatures
+/// into the bundle and broadcasts the claims. Signatures are only accepted
+/// for the exact claims they were made for.
+///
+/// Every claim is built on its own durable nonce account of the owner instead
+/// of a recent blockhash, so a bundle does not expire. `nonces` creates the
+/// nonce accounts to export with.
+pub struct BundleCmd {
+    #[command(subcommand)]
+    cmd: BundleCommand,
+}
+
+impl BundleCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        self.cmd.run(opts).await
+    }
+}
+
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum BundleCommand {
+    Nonces(BundleNoncesCmd),
+    Export(BundleExportCmd),
+    Sign(BundleSignCmd),
+    Submit(BundleSubmitCmd),
+}
+
+impl BundleCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::Nonces(cmd) => cmd.run(opts).await,
+            Self::Export(cmd) => cmd.run(opts).await,
+            Self::Sign(cmd) => cmd.run(opts).await,
+            Self::Submit(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Create durable nonce accounts for bundled claims, paid for by the wallet.
+///
+/// Every claim of a bundle needs a nonce account of its own. A nonce account
+/// is advanced by the claim built on it and can be exported with again once
+/// that claim was submitted.
+pub struct BundleNoncesCmd {
+    /// Number of nonce accounts to create
+    #[arg(long, default_value_t = 1)]
+    count: usize,
+    /// Owner that signs the bundled claims and is made the authority of the
+    /// nonce accounts. Defaults to the wallet address
+    #[arg(long)]
+    owner: Option<Pubkey>,
+    /// Commit the nonce account transactions.
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+impl BundleNoncesCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        use helium_lib::solana_sdk::{nonce, system_instruction};
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let owner = self.owner.unwrap_or_else(|| keypair.pubkey());
+        let client = opts.client()?;
+        let solana_client = client.solana_client.as_ref();
+        let lamports = solana_client
+            .get_minimum_balance_for_rent_exemption(nonce::State::size())
+            .await?;
+
+        let mut results = vec![];
+        for _ in 0..self.count {
+            let nonce_keypair = Keypair::generate();
+            let ixs = system_instruction::create_nonce_account(
+                &keypair.pubkey(),
+                &nonce_keypair.pubkey(),
+                &owner,
+                lamports,
+            );
+            let (mut txn, _) =
+                helium_lib::mk_transaction_with_blockhash(&client, &ixs, &keypair.pubkey()).await?;
+            txn.try_sign(&[&*keypair, &nonce_keypair], txn.message.recent_blockhash)?;
+            let mut json = self
+                .commit
+                .maybe_commit(txn.into(), &client)
+                .await
+                .to_json();
+            json["nonce_account"] = nonce_keypair.pubkey().to_string().into();
+            results.push(json);
+        }
+        print_json(&results)
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Prepare the claims for a list of assets as a bundle to be signed by the
+/// owner. Assets without pending rewards are left out.
+///
+/// Bundled claims pay out the rewards undivided, saved reward splits are not
+/// applied.
+pub struct BundleExportCmd {
+    /// Token for command
+    token: ClaimableToken,
+    /// Entity keys of the assets to claim rewards for
+    #[arg(required = true)]
+    entity_keys: Vec<String>,
+    /// Encoding of the given entity keys
+    #[arg(long, default_value_t = entity_key::EntityKeyEncoding::UTF8)]
+    encoding: entity_key::EntityKeyEncoding,
+    /// Owner that signs and pays for the claims. Defaults to the wallet
+    /// address
+    #[arg(long)]
+    owner: Option<Pubkey>,
+    /// Durable nonce accounts of the owner to build the claims on, one per
+    /// claim. Create them with "bundle nonces"
+    #[arg(long = "nonce", required = true)]
+    nonces: Vec<Pubkey>,
+    /// File to write the bundle to
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+impl BundleExportCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let owner = match self.owner {
+            Some(owner) => owner,
+            None => opts.load_wallet()?.public_key,
+        };
+        let client = opts.client()?;
+        let pending =
+            reward::pending(&client, self.token, &self.entity_keys, self.encoding.into()).await?;
+
+        let mut nonces = self.nonces.iter();
+        let mut claims = vec![];
+        for entity_key in &self.entity_keys {
+            let encoded_entity_key = entity_key::EncodedEntityKey {
+                encoding: self.encoding,
+                entity_key: entity_key.clone(),
+            };
+            let Some(pending) = pending.get(entity_key) else {
+                continue;
+            };
+            let Some(nonce_account) = nonces.next() else {
+                bail!(
+                    "not enough nonce accounts, {} given for more claims",
+                    self.nonces.len()
+                );
+            };
+            check_claim_policy(
+                &client,
+                self.token,
+                &encoded_entity_key,
+                &RewardSplit::default(),
+                &owner,
+            )
+            .await
+            .with_context(|| format!("while preparing the claim for {entity_key}"))?;
+            let Some(txn) = reward::claim_split_nonce_transaction(
+                &client,
+                self.token,
+                None,
+                &encoded_entity_key,
+                &RewardSplit::default(),
+                &owner,
+                nonce_account,
+                &TransactionOpts::default(),
+            )
+            .await
+            .with_context(|| format!("while preparing the claim for {entity_key}"))?
+            else {
+                continue;
+            };
+            claims.push((
+                entity_key.clone(),
+                Some(f64::from(&pending.reward)),
+                txn.into(),
+            ));
+        }
+        if claims.is_empty() {
+            bail!("No rewards to claim");
+        }
+
+        let bundle = ClaimBundle::new(self.token, owner, claims)?;
+        serde_json::to_writer_pretty(open_output_file(&self.output, true)?, &bundle)?;
+        print_json(&json!({
+            "owner": owner.to_string(),
+            "claims": bundle.claims.len(),
+            "bundle_hash": bundle.hash()?.to_string(),
+            "output": self.output,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Sign the claims of a bundle with the wallet, which must be the owner of
+/// the bundle.
+///
+/// Only plain reward claims built on a nonce account are signed. What each
+/// claim pays out and where to is shown before signing.
+pub struct BundleSignCmd {
+    /// Path to the bundle
+    input: PathBuf,
+    /// File to write the signatures to
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+impl BundleSignCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let bundle = ClaimBundle::read(&self.input)?;
+        let review = bundle.review()?;
+        let token = token::Token::from(bundle.token);
+        let owner_account = token.associated_token_adress(&bundle.owner);
+        let fees: u64 = review.iter().map(|(_, summary)| summary.fee).sum();
+        eprintln!(
+            "Claims: {} of {:?} for owner {}, fees up to {} SOL",
+            bundle.claims.len(),
+            bundle.token,
+            bundle.owner,
+            f64::from(&token::Token::Sol.amount(fees))
+        );
+        for (claim, (_, summary)) in bundle.claims.iter().zip(&review) {
+            let destination = if summary.destination_account == owner_account {
+                "owner".to_string()
+            } else {
+                format!("custom destination {}", summary.destination_account)
+            };
+            eprintln!(
+                "  {}: lifetime rewards {}, pending at export {}, paid to {destination}{}",
+                claim.entity_key,
+                f64::from(&token.amount(summary.lifetime_rewards)),
+                claim
+                    .pending
+                    .map(|pending| pending.to_string())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                if summary.init_recipient {
+                    ", initializes recipient"
+                } else {
+                    ""
+                },
+            );
+        }
+        if !confirm("Sign these claims?")? {
+            bail!("claims not signed");
+        }
+
+        let password = get_wallet_password(false)?;
+        let keypair = opts.load_keypair(password.as_bytes())?;
+        let signatures = bundle.sign(&keypair)?;
+        for txn in bundle.apply(&signatures)? {
+            audit::record_transaction(&txn)?;
+        }
+        serde_json::to_writer_pretty(open_output_file(&self.output, true)?, &signatures)?;
+        print_json(&json!({
+            "signer": signatures.signer.to_string(),
+            "claims": signatures.signatures.len(),
+            "bundle_hash": signatures.bundle_hash,
+            "output": self.output,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Add the owner signatures to the claims of a bundle and submit them
+///
+/// A failed claim is reported and does not stop the remaining claims.
+pub struct BundleSubmitCmd {
+    /// Path to the bundle
+    bundle: PathBuf,
+    /// Path to the signatures made with "bundle sign"
+    signatures: PathBuf,
+    /// Commit the claim transactions.
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+impl BundleSubmitCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let bundle = ClaimBundle::read(&self.bundle)?;
+        let signatures = ClaimSignatures::read(&self.signatures)?;
+        let txns = bundle.apply(&signatures)?;
+
+        let client = opts.client()?;
+        let mut results = vec![];
+        for (claim, txn) in bundle.claims.iter().zip(txns) {
+            let response = self.commit.maybe_commit(txn, &client).await;
+            if FeeCapExceeded::is_cause(&response) {
+                fee_cap_reached(results.len(), bundle.claims.len());
+                break;
+            }
+            let mut json = response.to_json();
+            json["entity_key"] = claim.entity_key.clone().into();
+            results.push(json);
+        }
+
+        print_json(&results)
+    }
+}
+
+/// Reward splits saved per entity key
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct SplitProfiles {
+    #[serde(flatten)]
+    splits: BTreeMap<String, RewardSplit>,
+}
+
+impl SplitProfiles {
+    fn path() -> PathBuf {
+        config_dir().join(SPLITS_FILE)
+    }
+
+    pub(crate) fn load() -> Result<Self> {
+        match fs::File::open(Self::path()) {
+            Ok(file) => Ok(serde_json::from_reader(file)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn save(&self) -> Result {
+        fs::create_dir_all(config_dir())?;
+        let file = fs::File::create(Self::path())?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    /// The split for the given entity key, empty if none is saved
+    pub(crate) fn get(&self, entity_key: &str) -> RewardSplit {
+        self.splits.get(entity_key).cloned().unwrap_or_default()
+    }
+}
+
+/// Parse a reward share given as `<address>=<percent>`
+fn parse_share(value: &str) -> Result<RewardShare> {
+    let (destination, percent) = value
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected <address>=<percent>, got \"{value}\""))?;
+    let percent: f64 = percent.parse()?;
+    if percent.is_nan() || percent <= 0.0 || percent > 100.0 {
+        bail!("share percentage must be above 0 and at most 100");
+    }
+    Ok(RewardShare {
+        destination: destination.parse()?,
+        basis_points: (percent * 100.0).round() as u16,
+    })
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Manage the saved reward splits of assets.
+///
+/// A split passes shares of every claim for an asset on to other wallets, for
+/// example to compensate the host of a Hotspot. The shares are transferred
+/// out of the claimed rewards in the claim transaction, so the claim has to be
+/// made by the wallet the rewards are paid out to. Payer only claims do not
+/// apply splits.
+pub struct SplitCmd {
+    #[command(subcommand)]
+    cmd: SplitCommand,
+}
+
+impl SplitCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        self.cmd.run(opts).await
+    }
+}
+
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum SplitCommand {
+    Set(SplitSetCmd),
+    Show(SplitShowCmd),
+    Clear(SplitClearCmd),
+}
+
+impl SplitCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::Set(cmd) => cmd.run(opts).await,
+            Self::Show(cmd) => cmd.run(opts).await,
+            Self::Clear(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Save the reward split for an asset, replacing any existing split
+pub struct SplitSetCmd {
+    /// Entity key of the asset, as used by the claim commands
+    entity_key: String,
+    /// Share of each claim to pass on, as `<address>=<percent>`. Can be given
+    /// multiple times, for example "--share <host>=20".
+    #[arg(long = "share", required = true, value_parser = parse_share)]
+    shares: Vec<RewardShare>,
+}
+
+impl SplitSetCmd {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        let split = RewardSplit::new(self.shares.clone())?;
+        for share in &split.shares {
+            policy::check(&share.destination, "reward split destination")?;
+        }
+        let mut profiles = SplitProfiles::load()?;
+        profiles
+            .splits
+            .insert(self.entity_key.clone(), split.clone());
+        profiles.save()?;
+        print_json(&json!({
+            "entity_key": self.entity_key,
+            "split": split,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Show the saved reward split for an asset, or all saved splits
+pub struct SplitShowCmd {
+    /// Entity key of the asset
+    entity_key: Option<String>,
+}
+
+impl SplitShowCmd {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        let profiles = SplitProfiles::load()?;
+        match &self.entity_key {
+            Some(entity_key) => print_json(&json!({
+                "entity_key": entity_key,
+                "split": profiles.get(entity_key),
+            })),
+            None => print_json(&profiles),
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Remove the saved reward split for an asset
+pub struct SplitClearCmd {
+    /// Entity key of the asset
+    entity_key: String,
+}
+
+impl SplitClearCmd {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        let mut profiles = SplitProfiles::load()?;
+        let removed = profiles.splits.remove(&self.entity_key).is_some();
+        if removed {
+            profiles.save()?;
+        }
+        print_json(&json!({
+            "entity_key": self.entity_key,
+            "removed": removed,
+        }))
+    }
+}
+
+/// The outcome of initializing reward recipients ahead of claims
+pub(crate) struct RecipientInits {
+    /// The result of every committed init batch, with the indices of the
+    /// entities it initializes
+    pub(crate) batches: Vec<(Vec<usize>, Result<CommitResponse>)>,
+    /// Indices of the entities whose recipient is still missing after the
+    /// inits, whose claims would initialize it again
+    pub(crate) missing: HashSet<usize>,
+    pub(crate) fee_cap_reached: bool,
+}
+
+/// Initialize the reward recipients missing for the given entities in
+/// batches, waiting for each batch to be confirmed, and read the recipients
+/// again to find the ones that are still missing.
+///
+/// Nothing is initialized unless the transactions are committed, since the
+/// claims then include the inits themselves.
+pub(crate) async fn init_recipients<E: AsEntityKey>(
+    client: &client::Client,
+    token: ClaimableToken,
+    entity_keys: &[E],
+    keypair: &Arc<Keypair>,
+    commit: &CommitOpts,
+    transaction_opts: &TransactionOpts,
+) -> Result<RecipientInits> {
+    let mut inits = RecipientInits {
+        batches: vec![],
+        missing: HashSet::new(),
+        fee_cap_reached: false,
+    };
+    if !commit.commit {
+        return Ok(inits);
+    }
+    let batches = reward::recipient::init_batches(
+        client,
+        token,
+        entity_keys,
+        &keypair.pubkey(),
+        transaction_opts,
+    )
+    .await?;
+    if batches.is_empty() {
+        return Ok(inits);
+    }
+    let commit = commit.confirming();
+    for batch in batches {
+        let (msg, _) = batch
+            .message(client, &keypair.pubkey(), transaction_opts)
+            .await?;
+        let tx = VersionedTransaction::try_new(msg, &[&**keypair])?;
+        let response = commit.maybe_commit_with_keypair(tx, client, keypair).await;
+        if FeeCapExceeded::is_cause(&response) {
+            inits.fee_cap_reached = true;
+            break;
+        }
+        inits.batches.push((batch.items, response));
+    }
+
+    let ktas = kta::for_entity_keys(entity_keys).await?;
+    let recipient_keys: Vec<Pubkey> = ktas
+        .iter()
+        .map(|kta| token.receipient_key_from_kta(kta))
+        .collect();
+    let accounts = client
+        .solana_client
+        .get_multiple_accounts_with_commitment(
+            &recipient_keys,
+            helium_lib::solana_sdk::commitment_config::CommitmentConfig::confirmed(),
+        )
+        .await?
+        .value;
+    inits.missing = accounts
+        .iter()
+        .enumerate()
+        .filter(|(_, account)| account.is_none())
+        .map(|(index, _)| index)
+        .collect();
+    Ok(inits)
+}
+
+pub(crate) fn fee_cap_reached(completed: usize, total: usize) {
+    warnings::warn(
+        warnings::FEE_CAP_REACHED,
+        format!("maximum total fee reached, stopped after {completed} of {total} claims"),
+    );
+}
+
 #[derive(Debug, Clone, clap::Args)]
 /// List the maximum claim amount for the given subdao
 ///
@@ -100,6 +1015,301 @@ impl MaxClaimCmd {
     }
 }
 
+/// Parse a number of claims, given as "500" or "500-claims"
+fn parse_claims(s: &str) -> Result<usize> {
+    s.strip_suffix("-claims")
+        .unwrap_or(s)
+        .parse()
+        .map_err(|_| anyhow!("invalid number of claims {s}, expected for example 500-claims"))
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct ClaimCostOpts {
+    /// Token of the claims
+    #[arg(long, default_value = "hnt")]
+    token: ClaimableToken,
+    /// Entity keys of the assets of the claim queue
+    #[arg(required = true)]
+    entity_keys: Vec<String>,
+    /// Encoding of the given entity keys
+    #[arg(long, default_value_t = entity_key::EntityKeyEncoding::UTF8)]
+    encoding: entity_key::EntityKeyEncoding,
+    /// Only pay for the claims of the first N assets, for example
+    /// "500-claims"
+    #[arg(long = "for", value_parser = parse_claims)]
+    claims: Option<usize>,
+}
+
+impl ClaimCostOpts {
+    async fn estimate(
+        &self,
+        client: &client::Client,
+        opts: &TransactionOpts,
+    ) -> Result<reward::cost::ClaimCost> {
+        let count = self.claims.unwrap_or(self.entity_keys.len());
+        let entity_keys = self
+            .entity_keys
+            .iter()
+            .take(count)
+            .map(|entity_key| entity_key::from_str(entity_key, self.encoding.into()))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(reward::cost::estimate(client, self.token, &entity_keys, opts).await?)
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Estimate the SOL a wallet needs to pay for the claims of a claim queue.
+///
+/// Uses the current priority fee and rent. Assets that were never claimed
+/// are looked up and include the rent of the recipient account their claim
+/// creates. The fees of initializing reward splits and reward token accounts
+/// are not included.
+pub struct CostCmd {
+    #[command(flatten)]
+    cost: ClaimCostOpts,
+    #[command(flatten)]
+    priority_fee: PriorityFeeOpts,
+}
+
+impl CostCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let client = opts.client()?;
+        let transaction_opts = self.priority_fee.transaction_opts(&client);
+        let cost = self.cost.estimate(&client, &transaction_opts).await?;
+        print_json(&cost)
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Fund the wallet paying for claims with the SOL a number of claims need.
+///
+/// Transfers the estimated cost of the claims of a claim queue, as reported
+/// by "cost", from this wallet to the given payer wallet, for example the
+/// wallet that runs "queue" with --payer-only.
+pub struct FundCmd {
+    /// Wallet paying for the claims
+    #[arg(long)]
+    payer: Pubkey,
+    #[command(flatten)]
+    cost: ClaimCostOpts,
+    /// Commit the transfer
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+impl FundCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let client = opts.client()?;
+        let transaction_opts = self.commit.transaction_opts(&client);
+        policy::check_external(&keypair.pubkey(), &self.payer, "claim payer")?;
+
+        let cost = self.cost.estimate(&client, &transaction_opts).await?;
+        let (tx, _) = token::transfer(
+            &client,
+            &[(self.payer, cost.total)],
+            None,
+            &[],
+            &keypair,
+            &transaction_opts,
+        )
+        .await?;
+        let mut json = self
+            .commit
+            .maybe_commit_with_keypair(tx, &client, &keypair)
+            .await?
+            .to_json();
+        json["payer"] = self.payer.to_string().into();
+        json["cost"] = serde_json::to_value(&cost)?;
+        print_json(&json)
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Manage the wallets rewards of assets are paid out to
+pub struct DestinationCmd {
+    #[command(subcommand)]
+    cmd: DestinationCommand,
+}
+
+impl DestinationCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        self.cmd.run(opts).await
+    }
+}
+
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum DestinationCommand {
+    SetAll(DestinationSetAllCmd),
+}
+
+impl DestinationCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::SetAll(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Pay the rewards of every Hotspot in the wallet out to another wallet
+///
+/// Sets the reward destination of every Hotspot owned by the wallet, for each
+/// token, packing as many updates into a transaction as their proofs allow.
+/// With --dry-run only the affected Hotspots and their current destinations
+/// are listed. Hotspots already paying out to the destination
+/// are skipped, so running the command again after it was interrupted, or
+/// stopped by the maximum total fee, completes the remaining updates.
+pub struct DestinationSetAllCmd {
+    /// Wallet to pay the rewards out to
+    #[arg(long)]
+    to: Pubkey,
+    /// Tokens to set the destination for, all of them by default
+    #[arg(long = "token", value_delimiter = ',')]
+    tokens: Vec<ClaimableToken>,
+    /// Only list the Hotspots whose destination would be set
+    #[arg(long)]
+    dry_run: bool,
+    /// Commit the update transactions
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+impl DestinationSetAllCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let wallet = keypair.pubkey();
+        policy::check_external(&wallet, &self.to, "reward destination")?;
+        let client = opts.client()?;
+        let transaction_opts = self.commit.transaction_opts(&client);
+        let tokens = if self.tokens.is_empty() {
+            vec![
+                ClaimableToken::Hnt,
+                ClaimableToken::Iot,
+                ClaimableToken::Mobile,
+            ]
+        } else {
+            self.tokens.clone()
+        };
+
+        let hotspots: Vec<hotspot::Hotspot> = hotspot::for_owner(&client, &wallet)
+            .await?
+            .into_iter()
+            .filter(|hotspot| !hotspot.burnt)
+            .collect();
+        let keys: Vec<helium_crypto::PublicKey> =
+            hotspots.iter().map(|hotspot| hotspot.key.clone()).collect();
+        let ktas = kta::for_entity_keys(&keys).await?;
+
+        let mut updates = vec![];
+        let mut current = 0;
+        for token in &tokens {
+            let recipients = reward::recipient::for_ktas(&client, *token, &ktas).await?;
+            for (hotspot, recipient) in hotspots.iter().zip(recipients) {
+                let destination = recipient
+                    .as_ref()
+                    .and_then(reward::recipient::custom_destination);
+                if destination.unwrap_or(wallet) == self.to {
+                    current += 1;
+                    continue;
+                }
+                updates.push(json!({
+                    "token": token,
+                    "hotspot": hotspot.key.to_string(),
+                    "name": hotspot.name,
+                    "asset": hotspot.asset.to_string(),
+                    "destination": destination.map(|destination| destination.to_string()),
+                }));
+            }
+        }
+        if self.dry_run || updates.is_empty() {
+            return print_json(&json!({
+                "to": self.to.to_string(),
+                "current": current,
+                "count": updates.len(),
+                "updates": updates,
+            }));
+        }
+
+        let mut results = vec![];
+        'tokens: for token in &tokens {
+            let batches = reward::recipient::destination_batches(
+                &client,
+                *token,
+                &keys,
+                &self.to,
+                &wallet,
+                &transaction_opts,
+            )
+            .await?;
+            for batch in batches {
+                let commit = match batch.message(&client, &wallet, &transaction_opts).await {
+                    Ok((msg, _)) => match VersionedTransaction::try_new(msg, &[&*keypair]) {
+                        Ok(tx) => {
+                            self.commit
+                                .maybe_commit_with_keypair(tx, &client, &keypair)
+                                .await
+                        }
+                        Err(err) => Err(err.into()),
+                    },
+                    Err(err) => Err(err.into()),
+                };
+                if FeeCapExceeded::is_cause(&commit) {
+                    warnings::warn(
+                        warnings::FEE_CAP_REACHED,
+                        format!(
+                            "maximum total fee reached, stopped before setting the destination of {}",
+                            keys[batch.items[0]]
+                        ),
+                    );
+                    break 'tokens;
+                }
+                let mut json = commit.to_json();
+                json["token"] = json!(token);
+                json["hotspots"] = batch
+                    .items
+                    .iter()
+                    .map(|index| keys[*index].to_string())
+                    .collect::<Vec<_>>()
+                    .into();
+                results.push(json);
+            }
+        }
+
+        print_json(&json!({
+            "to": self.to.to_string(),
+            "current": current,
+            "count": updates.len(),
+            "transactions": results,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// List the oracles of the rewards distributor for a token and probe their
+/// health.
+///
+/// Claims require every oracle to sign, so one unreachable oracle causes all
+/// claims for the token to fail.
+pub struct OraclesCmd {
+    /// Token for command
+    #[arg(long)]
+    token: ClaimableToken,
+    /// Only list the oracles without probing them
+    #[arg(long)]
+    no_probe: bool,
+}
+
+impl OraclesCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let client = opts.client()?;
+        if self.no_probe {
+            return print_json(&reward::oracles(&client, self.token).await?);
+        }
+        print_json(&reward::oracle_health(&client, self.token).await?)
+    }
+}
+
 #[derive(Debug, Clone, clap::Args)]
 /// List claimable pending rewards for a given asset
 pub struct PendingCmd {
@@ -0,0 +1,264 @@
+use crate::{
+    cmd::{
+        assets::rewards::{check_claim_policy, QueueCmd, SplitProfiles},
+        *,
+    },
+    policy::PolicyViolation,
+};
+use chrono::Utc;
+use futures::StreamExt;
+use helium_lib::{
+    entity_key::EncodedEntityKey,
+    keypair::{Pubkey, Signer},
+    payer::PayerPool,
+    reward::{self, ClaimableToken, OwnerReward},
+    TransactionOpts,
+};
+use std::time::Duration;
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct Cmd {
+    #[command(subcommand)]
+    cmd: ClaimCommand,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        self.cmd.run(opts).await
+    }
+}
+
+/// Claim rewards on a schedule or for a queue of assets
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum ClaimCommand {
+    Daemon(DaemonCmd),
+    Queue(QueueCmd),
+}
+
+impl ClaimCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::Daemon(cmd) => cmd.run(opts).await,
+            Self::Queue(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Claim the rewards of the wallet's Hotspots as they cross a threshold.
+///
+/// Checks the pending rewards of every Hotspot owned by the wallet at the
+/// given interval and claims those at or above the threshold, applying saved
+/// reward splits. Every check and claim is logged as a line of JSON with a
+/// timestamp and an "event" field. Claims paying out to an address the
+/// address policy does not allow are logged as "claim_blocked" and skipped.
+///
+/// A check that fails, for example because an RPC node is unavailable, is
+/// logged and retried at the next interval. The daemon stops once a claim
+/// would exceed the maximum total fee, if one is given. The wallet password
+/// is read once at startup, from HELIUM_WALLET_PASSWORD or --password-stdin
+/// when running unattended.
+pub struct DaemonCmd {
+    /// Tokens to claim
+    #[arg(long = "token", value_delimiter = ',', default_value = "hnt")]
+    tokens: Vec<ClaimableToken>,
+    /// Pending amount of a Hotspot, in the claimed token, at which its
+    /// rewards are claimed
+    #[arg(long, default_value_t = 0.0)]
+    threshold: f64,
+    /// Time between checks, for example "6h" or "1day"
+    #[arg(long, default_value = "1day", value_parser = humantime::parse_duration)]
+    interval: Duration,
+    /// Run a single check and exit, for scheduling with cron instead
+    #[arg(long)]
+    once: bool,
+    /// Only log the claims that would be made, without building or
+    /// submitting them
+    #[arg(long)]
+    dry_run: bool,
+    /// Wallet file, or name of a saved wallet, of a fee payer to rotate the
+    /// claims over. Repeat to add more payers. Payer wallets are unlocked
+    /// with the wallet password. Claims with a reward split are still paid
+    /// for by the wallet, which transfers the shares
+    #[arg(long = "payer")]
+    payers: Vec<PathBuf>,
+    /// SOL a payer has to hold to be rotated in. Payers holding less are
+    /// skipped until a later check finds them topped up
+    #[arg(long, default_value_t = 0.01)]
+    min_payer_balance: f64,
+    /// Commit the claim transactions.
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+/// Log an event as a line of JSON
+fn log_event(event: &str, mut fields: serde_json::Value) {
+    fields["timestamp"] = Utc::now().to_rfc3339().into();
+    fields["event"] = event.into();
+    println!("{fields}");
+}
+
+fn reward_fields(reward: &OwnerReward) -> serde_json::Value {
+    json!({
+        "token": reward.token,
+        "hotspot": reward.hotspot.to_string(),
+        "name": reward.name,
+        "pending": f64::from(&reward.pending),
+    })
+}
+
+/// Whether the daemon should keep running after a check
+enum Next {
+    Continue,
+    Stop,
+}
+
+impl DaemonCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        if !self.threshold.is_finite() || self.threshold < 0.0 {
+            bail!("threshold must not be negative");
+        }
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let payers = self.payer_pool()?;
+        let client = opts.client()?;
+        let transaction_opts = self.commit.transaction_opts(&client);
+        log_event(
+            "started",
+            json!({
+                "wallet": keypair.pubkey().to_string(),
+                "payers": self.payers.len(),
+                "tokens": self.tokens,
+                "threshold": self.threshold,
+                "interval": humantime::format_duration(self.interval).to_string(),
+                "dry_run": self.dry_run,
+            }),
+        );
+
+        loop {
+            match self
+                .check(&client, &keypair, payers.as_ref(), &transaction_opts)
+                .await
+            {
+                Ok(Next::Continue) => (),
+                Ok(Next::Stop) => break,
+                Err(err) => log_event("error", json!({ "error": format!("{err:#}") })),
+            }
+            if self.once {
+                break;
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+        log_event("stopped", json!({ "fees_spent": self.commit.fees_spent() }));
+        Ok(())
+    }
+
+    /// The pool of fee payers to rotate the claims over, if any
+    fn payer_pool(&self) -> Result<Option<PayerPool>> {
+        if self.payers.is_empty() {
+            return Ok(None);
+        }
+        let password = get_wallet_password(false)?;
+        let payers = self
+            .payers
+            .iter()
+            .map(|path| {
+                let wallet = Wallet::read(&mut fs::File::open(wallets::resolve(path)?)?)?;
+                wallet.decrypt(password.as_bytes())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let min_balance = sol_to_lamports(self.min_payer_balance);
+        Ok(Some(PayerPool::new(payers, min_balance)?))
+    }
+
+    async fn check(
+        &self,
+        client: &client::Client,
+        keypair: &Keypair,
+        payers: Option<&PayerPool>,
+        transaction_opts: &TransactionOpts,
+    ) -> Result<Next> {
+        let wallet: Pubkey = keypair.pubkey();
+        let rewards: Vec<OwnerReward> = reward::pending_for_owner(client, &wallet, &self.tokens)
+            .await?
+            .into_iter()
+            .filter(|reward| f64::from(&reward.pending) >= self.threshold)
+            .collect();
+        log_event("checked", json!({ "due": rewards.len() }));
+
+        // Loaded on every check so splits saved while running are applied
+        let splits = SplitProfiles::load()?;
+        // Claims paying out to an address the address policy forbids are
+        // skipped, the others are still claimed
+        let mut allowed = Vec::with_capacity(rewards.len());
+        for reward in rewards {
+            let checked = check_claim_policy(
+                client,
+                reward.token,
+                &EncodedEntityKey::from(&reward.hotspot),
+                &splits.get(&reward.hotspot.to_string()),
+                &wallet,
+            )
+            .await;
+            match checked {
+                Ok(()) => allowed.push(reward),
+                Err(err) => {
+                    let event = if err.is::<PolicyViolation>() {
+                        "claim_blocked"
+                    } else {
+                        "claim_failed"
+                    };
+                    let mut fields = reward_fields(&reward);
+                    fields["error"] = format!("{err:#}").into();
+                    log_event(event, fields);
+                }
+            }
+        }
+        let rewards = allowed;
+        if let Some(payers) = payers {
+            payers.refresh(client).await?;
+            log_event("payers", json!({ "payers": payers.balances() }));
+        }
+        if self.dry_run {
+            for reward in &rewards {
+                log_event("would_claim", reward_fields(reward));
+            }
+            return Ok(Next::Continue);
+        }
+
+        let mut claims = std::pin::pin!(reward::claim_for_owner(
+            client,
+            &rewards,
+            |hotspot| splits.get(&hotspot.to_string()),
+            keypair,
+            payers,
+            transaction_opts,
+        ));
+        while let Some((reward, claim)) = claims.next().await {
+            let response = match claim {
+                Ok(Some((tx, _))) => {
+                    let response = self.commit.maybe_commit(tx.clone(), client).await;
+                    if let Some(payers) = payers.filter(|_| response.is_ok()) {
+                        payers.record_transaction(&tx);
+                    }
+                    response
+                }
+                Ok(None) => continue,
+                Err(err) => Err(err.into()),
+            };
+            if FeeCapExceeded::is_cause(&response) {
+                let mut fields = reward_fields(reward);
+                fields["fees_spent"] = self.commit.fees_spent().into();
+                log_event("fee_cap_reached", fields);
+                return Ok(Next::Stop);
+            }
+            let mut fields = reward_fields(reward);
+            let event = match &response {
+                Ok(_) => "claimed",
+                Err(_) => "claim_failed",
+            };
+            fields["result"] = response.to_json();
+            log_event(event, fields);
+        }
+        Ok(Next::Continue)
+    }
+}
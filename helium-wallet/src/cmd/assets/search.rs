@@ -0,0 +1,57 @@
+use crate::cmd::*;
+use futures::TryStreamExt;
+use helium_lib::{asset, client::DasSearchAssetsParams, keypair::Pubkey};
+
+#[derive(Debug, Clone, clap::Args)]
+/// Search the compressed assets of the active or a given wallet.
+///
+/// Assets are fetched a page at a time. With --ndjson every asset is written
+/// on its own line as soon as its page arrives.
+pub struct Cmd {
+    /// The alternate wallet to search the assets of
+    #[arg(long, value_parser = parse_address)]
+    owner: Option<Pubkey>,
+    /// Only include assets with the given verified creator, like the Hotspot
+    /// creator
+    #[arg(long)]
+    creator: Option<Pubkey>,
+
+    #[command(flatten)]
+    output: OutputOpts,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let owner = match self.owner {
+            Some(owner) => owner,
+            None => opts.load_wallet()?.public_key,
+        };
+        let client = opts.client()?;
+        // Uncompressed assets, like staked positions, carry no compression
+        // data and are left out
+        let params = DasSearchAssetsParams {
+            owner_address: Some(owner),
+            creator_address: self.creator,
+            creator_verified: self.creator.is_some(),
+            compressed: true,
+            ..Default::default()
+        };
+        let mut sink = self.output.sink()?;
+        let mut pages = std::pin::pin!(asset::search_pages(&client, params));
+        let mut assets = vec![];
+        while let Some(page) = pages.try_next().await? {
+            if sink.is_ndjson() {
+                sink.write_items(page)?;
+            } else {
+                assets.extend(page);
+            }
+        }
+        if sink.is_ndjson() {
+            return Ok(());
+        }
+        sink.write_json(&json!({
+            "address": owner.to_string(),
+            "assets": assets,
+        }))
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{audit, cmd::*};
+use serde_json::json;
+
+#[derive(Debug, clap::Args)]
+pub struct Cmd {
+    #[command(subcommand)]
+    cmd: AuditCommand,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        self.cmd.run(opts).await
+    }
+}
+
+/// Commands for working with signing audit logs
+#[derive(Debug, clap::Subcommand)]
+pub enum AuditCommand {
+    Verify(VerifyCmd),
+}
+
+impl AuditCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::Verify(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+/// Verify the hash chain integrity of an audit log
+#[derive(Debug, clap::Args)]
+pub struct VerifyCmd {
+    /// Path to the audit log to verify
+    input: PathBuf,
+}
+
+impl VerifyCmd {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        let reader = io::BufReader::new(fs::File::open(&self.input)?);
+        match audit::verify(reader) {
+            Ok(entries) => print_json(&json!({
+                "result": "ok",
+                "entries": entries,
+            })),
+            Err(err) => {
+                print_json(&json!({
+                    "result": "error",
+                    "error": err.to_string(),
+                }))?;
+                bail!("Audit log verification failed");
+            }
+        }
+    }
+}
@@ -6,8 +6,10 @@ use helium_lib::{
 
 #[derive(Debug, clap::Args)]
 /// Get the balance for a wallet or a given public key. The balance is given for
-/// each of the Helium related holdings of a given Solana address
+/// each of the Helium related holdings of a given Solana address, followed by
+/// every token account of the address with the symbol of its token, if known
 pub struct Cmd {
+    #[arg(value_parser = parse_address)]
     address: Option<Pubkey>,
 }
 
@@ -24,9 +26,11 @@ impl Cmd {
         let balances =
             token::balance_for_addresses(&client, &Token::associated_token_adresses(&address))
                 .await?;
+        let tokens = token::token_accounts_for_owner(&client, &address).await?;
         let json = json!({
             "address": address.to_string(),
             "balance": token::TokenBalanceMap::from(balances),
+            "tokens": tokens,
         });
         print_json(&json)
     }
@@ -0,0 +1,99 @@
+use crate::cmd::*;
+use helium_lib::{boosting, keypair::Signer};
+
+/// Number of boost accounts closed per transaction
+const CLOSE_BATCH_SIZE: usize = 10;
+
+#[derive(Debug, clap::Args)]
+pub struct Cmd {
+    #[command(subcommand)]
+    cmd: BoostingCommand,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        self.cmd.run(opts).await
+    }
+}
+
+/// Operations on hex boosts
+#[derive(Debug, clap::Subcommand)]
+pub enum BoostingCommand {
+    Close(CloseCmd),
+}
+
+impl BoostingCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::Close(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+/// Close expired hex boosts and reclaim their rent.
+///
+/// Lists the expired mobile boosts the wallet can close, with the rent each
+/// returns. Only the rent reclaim authority of the boost config can close
+/// boosts, and unused boosted periods are not refunded by the program. Closing
+/// transactions are only submitted with --commit.
+pub struct CloseCmd {
+    /// Only close the boosts of these hexes
+    #[arg(long = "hex", value_delimiter = ',')]
+    hexes: Vec<h3o::CellIndex>,
+    /// Commit the close transactions
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+impl CloseCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let client = opts.client()?;
+
+        let closable: Vec<boosting::ClosableBoost> = boosting::closable(&client, &keypair.pubkey())
+            .await?
+            .into_iter()
+            .filter(|boost| {
+                self.hexes.is_empty() || self.hexes.iter().any(|hex| hex.to_string() == boost.hex)
+            })
+            .collect();
+        let rent: u64 = closable.iter().map(|boost| boost.rent).sum();
+
+        let transaction_opts = self.commit.transaction_opts(&client);
+        let mut results = vec![];
+        for batch in closable.chunks(CLOSE_BATCH_SIZE) {
+            let addresses: Vec<_> = batch.iter().map(|boost| boost.address).collect();
+            let close = boosting::close_boost(&client, &addresses, &keypair, &transaction_opts);
+            let commit = match close.await {
+                Ok((tx, _)) => {
+                    self.commit
+                        .maybe_commit_with_keypair(tx, &client, &keypair)
+                        .await
+                }
+                Err(err) => Err(err.into()),
+            };
+            if FeeCapExceeded::is_cause(&commit) {
+                warnings::warn(
+                    warnings::FEE_CAP_REACHED,
+                    "maximum total fee reached, skipped closing the remaining boosts",
+                );
+                break;
+            }
+            let mut json = commit.to_json();
+            json["boosts"] = addresses
+                .iter()
+                .map(|address| address.to_string())
+                .collect::<Vec<_>>()
+                .into();
+            results.push(json);
+        }
+
+        print_json(&json!({
+            "boosts": closable,
+            "rent": rent,
+            "rent_sol": helium_lib::solana_sdk::native_token::lamports_to_sol(rent),
+            "transactions": results,
+        }))
+    }
+}
@@ -1,13 +1,30 @@
 use crate::cmd::*;
-use helium_lib::{dao::SubDao, token};
+use helium_lib::{
+    dao::SubDao,
+    token::{self, Token},
+};
 
 #[derive(Debug, Clone, clap::Args)]
+#[command(allow_missing_positional = true)]
 /// Burn tokens
+///
+/// The token is given either as the subdao, as in "burn iot 10", or with
+/// --token, which also allows burning HNT. An optional memo is recorded in
+/// the burn transaction.
 pub struct Cmd {
     /// Subdao token to burn
-    subdao: SubDao,
-    /// Amount to burn
-    amount: f64,
+    #[arg(required_unless_present = "token", conflicts_with = "token")]
+    subdao: Option<SubDao>,
+    /// Amount to burn, in whole tokens
+    amount: String,
+    /// Token to burn
+    #[arg(long, value_parser = burnable_token)]
+    token: Option<Token>,
+    /// Memo to attach to the burn
+    #[arg(long)]
+    memo: Option<String>,
+    #[command(flatten)]
+    multisig: MultisigOpts,
     /// Commit the burn
     #[command(flatten)]
     commit: CommitOpts,
@@ -15,13 +32,35 @@ pub struct Cmd {
 
 impl Cmd {
     pub async fn run(&self, opts: Opts) -> Result {
-        let password = get_wallet_password(false)?;
-        let keypair = opts.load_keypair(password.as_bytes())?;
+        let token = match (self.token, self.subdao) {
+            (Some(token), _) => token,
+            (None, Some(subdao)) => subdao.token(),
+            (None, None) => bail!("no token specified"),
+        };
+        let token_amount = token::TokenAmount::from_decimal_str(token, &self.amount)?;
+        if token_amount.amount == 0 {
+            bail!("amount to burn must be positive");
+        }
+
+        let signer = opts.load_signer(&self.commit)?;
         let client = opts.client()?;
         let txn_opts = self.commit.transaction_opts(&client);
 
-        let token_amount = token::TokenAmount::from_f64(self.subdao.token(), self.amount);
-        let (tx, _) = token::burn(&client, &token_amount, &keypair, &txn_opts).await?;
-        print_json(&self.commit.maybe_commit(tx, &client).await?.to_json())
+        let multisig = self.multisig.resolve(&signer.pubkey())?;
+        let authority = multisig.authority(&signer.pubkey());
+        let (msg, _) = token::burn_message(
+            &client,
+            &token_amount,
+            self.memo.as_deref(),
+            &authority,
+            &txn_opts,
+        )
+        .await?;
+        let (tx, proposal) = multisig.sign(&client, msg, &signer, &txn_opts).await?;
+        let response = self
+            .commit
+            .maybe_commit_with_signer(tx, &client, &signer)
+            .await?;
+        print_json(&with_proposal(response.to_json(), proposal)?)
     }
 }
@@ -1,6 +1,10 @@
-use crate::{cmd::*, wallet::ShardConfig};
+use crate::{cmd::*, password, wallet::ShardConfig};
 use clap::builder::TypedValueParser as _;
-use helium_lib::{bs58, keypair};
+use helium_lib::{
+    bs58,
+    keypair::{self, Signer},
+};
+use helium_mnemonic::slip10::{self, DerivationPath, SOLANA_DERIVATION_PATH};
 
 #[derive(Debug, clap::Args)]
 pub struct Cmd {
@@ -20,6 +24,7 @@ pub enum CreateCommand {
     Basic(Basic),
     Sharded(Sharded),
     Keypair(Keypair),
+    Import(Import),
 }
 
 #[derive(Debug, clap::Args)]
@@ -33,13 +38,18 @@ pub struct Basic {
     /// Overwrite an existing file
     force: bool,
 
-    #[arg(long)]
-    /// Use a BIP39 or mobile app seed phrase to generate the wallet keys
-    seed: bool,
+    #[command(flatten)]
+    seed: SeedOpts,
 
     #[arg(long)]
     /// Use solana byte array or b58 encoded private key
     key: bool,
+
+    #[command(flatten)]
+    password: PasswordOpts,
+
+    #[command(flatten)]
+    pwhash: PwHashOpts,
 }
 
 #[derive(Debug, clap::Args)]
@@ -61,13 +71,124 @@ pub struct Sharded {
     /// Number of shards required to recover the key
     recovery_threshold: u8,
 
+    #[command(flatten)]
+    seed: SeedOpts,
+
+    #[arg(long)]
+    /// Use solana byte array or b58 encoded private key
+    key: bool,
+
+    #[command(flatten)]
+    password: PasswordOpts,
+
+    #[command(flatten)]
+    pwhash: PwHashOpts,
+}
+
+/// Formats of unencrypted keypair files of other tools
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum KeyFileFormat {
+    /// Solana CLI keypair file (id.json), a JSON array of the 64 bytes of
+    /// the secret and public key. Also used by Anchor and Phantom imports
+    Solana,
+}
+
+#[derive(Debug, clap::Args)]
+/// Create a new basic wallet from the keypair file or seed phrase of another
+/// tool
+pub struct Import {
+    #[arg(required_unless_present = "seed", conflicts_with = "seed")]
+    /// Keypair file to import, like ~/.config/solana/id.json
+    input: Option<PathBuf>,
+
+    #[arg(long, value_enum, default_value_t = KeyFileFormat::Solana)]
+    /// Format of the keypair file
+    format: KeyFileFormat,
+
+    #[command(flatten)]
+    seed: SeedOpts,
+
+    #[arg(long, value_enum, default_value_t = Derivation::Direct, requires = "seed")]
+    /// How the wallet key is derived from the seed phrase
+    derivation: Derivation,
+
+    #[arg(long, default_value = SOLANA_DERIVATION_PATH, requires = "seed")]
+    /// Derivation path to use with slip10 derivation
+    path: DerivationPath,
+
+    #[arg(short, long, default_value = "wallet.key")]
+    /// Output file to store the key in
+    output: PathBuf,
+
+    #[arg(long)]
+    /// Overwrite an existing file
+    force: bool,
+
+    #[command(flatten)]
+    password: PasswordOpts,
+
+    #[command(flatten)]
+    pwhash: PwHashOpts,
+}
+
+/// How wallet keys are derived from a seed phrase
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum Derivation {
+    /// Use the phrase entropy as the key, like the Helium apps
+    Direct,
+    /// Derive the key from the BIP39 seed along a SLIP-0010 path, like
+    /// Phantom, Solflare and other Solana wallets
+    Slip10,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SeedOpts {
     #[arg(long)]
     /// Use a BIP39 or mobile app seed phrase to generate the wallet keys
     seed: bool,
 
+    #[arg(long, requires = "seed")]
+    /// Ask for the BIP39 passphrase ("25th word") the seed phrase was created
+    /// with. Read from HELIUM_WALLET_SEED_PASSPHRASE if set
+    passphrase: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct PasswordOpts {
     #[arg(long)]
-    /// Use solana byte array or b58 encoded private key
-    key: bool,
+    /// Allow a password that is estimated to be easy to guess
+    allow_weak_password: bool,
+
+    #[arg(long)]
+    /// Generate a random passphrase for the wallet instead of asking for a
+    /// password. The passphrase is printed once and cannot be recovered
+    gen_password: bool,
+}
+
+impl PasswordOpts {
+    /// Get the password for a new wallet, rejecting weak passwords unless
+    /// allowed
+    fn get_password(&self) -> Result<Zeroizing<String>> {
+        if self.gen_password {
+            let passphrase = Zeroizing::new(password::generate_passphrase());
+            eprintln!("Wallet passphrase (store it safely, it is not shown again):");
+            eprintln!("{}", *passphrase);
+            return Ok(passphrase);
+        }
+        let password = get_wallet_password(true)?;
+        let strength = password::strength(&password);
+        if strength.is_weak() && !self.allow_weak_password {
+            let mut reason = format!("estimated {:.0} bits of entropy", strength.entropy);
+            for warning in &strength.warnings {
+                reason.push_str(", ");
+                reason.push_str(warning);
+            }
+            bail!(
+                "password too weak ({reason}), use a stronger password, --gen-password or --allow-weak-password"
+            );
+        }
+        Ok(password)
+    }
 }
 
 #[derive(Debug, clap::Args)]
@@ -87,18 +208,23 @@ impl CreateCommand {
             Self::Basic(cmd) => cmd.run(opts).await,
             Self::Sharded(cmd) => cmd.run(opts).await,
             Self::Keypair(cmd) => cmd.run(opts).await,
+            Self::Import(cmd) => cmd.run(opts).await,
         }
     }
 }
 
-fn get_entropy(seed: bool, key: bool) -> Result<Option<Vec<u8>>> {
+fn get_entropy(seed: &SeedOpts, key: bool) -> Result<Option<Vec<u8>>> {
     let key = if key {
         Some(get_secret_entropy()?)
     } else {
         None
     };
-    let seed = if key.is_none() && seed {
-        Some(get_seed_entropy()?)
+    let seed = if key.is_none() && seed.seed {
+        Some(get_seed_entropy(
+            seed,
+            Derivation::Direct,
+            &DerivationPath::default(),
+        )?)
     } else {
         None
     };
@@ -108,12 +234,13 @@ fn get_entropy(seed: bool, key: bool) -> Result<Option<Vec<u8>>> {
 
 impl Basic {
     pub async fn run(&self, _opts: Opts) -> Result {
-        let entropy = get_entropy(self.seed, self.key)?;
-        let password = get_wallet_password(true)?;
+        let entropy = get_entropy(&self.seed, self.key)?;
+        let password = self.password.get_password()?;
 
         let wallet = Wallet::builder()
             .output(&self.output)
             .password(&password)
+            .pwhash(self.pwhash.pwhash())
             .force(self.force)
             .entropy(entropy)
             .create()?;
@@ -124,8 +251,8 @@ impl Basic {
 
 impl Sharded {
     pub async fn run(&self, _opts: Opts) -> Result {
-        let entropy = get_entropy(self.seed, self.key)?;
-        let password = get_wallet_password(true)?;
+        let entropy = get_entropy(&self.seed, self.key)?;
+        let password = self.password.get_password()?;
 
         let shard_config = ShardConfig {
             key_share_count: self.key_share_count,
@@ -135,6 +262,7 @@ impl Sharded {
         let wallet = Wallet::builder()
             .output(&self.output)
             .password(&password)
+            .pwhash(self.pwhash.pwhash())
             .force(self.force)
             .shard(Some(shard_config))
             .entropy(entropy)
@@ -144,6 +272,42 @@ impl Sharded {
     }
 }
 
+impl Import {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        let secret = match &self.input {
+            Some(input) => match self.format {
+                KeyFileFormat::Solana => read_solana_keypair(&fs::read_to_string(input)?)?,
+            },
+            None => get_seed_entropy(&self.seed, self.derivation, &self.path)?,
+        };
+        let password = self.password.get_password()?;
+
+        let wallet = Wallet::builder()
+            .output(&self.output)
+            .password(&password)
+            .pwhash(self.pwhash.pwhash())
+            .force(self.force)
+            .entropy(Some(secret))
+            .create()?;
+
+        info::print_wallet(&wallet)
+    }
+}
+
+/// The secret of a Solana CLI keypair file, checked against the public key
+/// stored with it
+fn read_solana_keypair(data: &str) -> Result<Vec<u8>> {
+    let bytes: Vec<u8> = serde_json::from_str(data)?;
+    if bytes.len() != 64 {
+        bail!("expected 64 keypair bytes, found {}", bytes.len());
+    }
+    let keypair = keypair::Keypair::generate_from_entropy(&bytes)?;
+    if keypair.pubkey().as_ref() != &bytes[32..] {
+        bail!("public key does not match the secret key");
+    }
+    Ok(bytes)
+}
+
 impl Keypair {
     pub async fn run(&self, _opts: Opts) -> Result {
         let key_tag = helium_crypto::KeyTag {
@@ -173,12 +337,37 @@ impl Keypair {
     }
 }
 
-fn get_seed_entropy() -> Result<Vec<u8>> {
-    fn secret_from_phrase(s: &str) -> Result<Vec<u8>> {
-        let entropy = helium_mnemonic::mnemonic_to_entropy(&phrase_to_words(s))?.to_vec();
-        Ok(entropy)
+/// The key entropy for the given seed words and BIP39 passphrase, which is
+/// empty for phrases without one
+pub(crate) fn seed_words_entropy(
+    words: &[&str],
+    derivation: Derivation,
+    path: &DerivationPath,
+    passphrase: &str,
+) -> Result<Vec<u8>> {
+    let entropy = match derivation {
+        Derivation::Direct => {
+            helium_mnemonic::mnemonic_to_entropy_with_passphrase(words, passphrase)?.to_vec()
+        }
+        Derivation::Slip10 => {
+            let seed = helium_mnemonic::mnemonic_to_seed(words, passphrase)?;
+            slip10::derive_ed25519(&seed, path).to_vec()
+        }
+    };
+    Ok(entropy)
+}
+
+/// Get the BIP39 passphrase of a seed phrase
+pub(crate) fn get_seed_passphrase(confirm: bool) -> Result<Zeroizing<String>> {
+    match env::var("HELIUM_WALLET_SEED_PASSPHRASE") {
+        Ok(passphrase) => Ok(Zeroizing::new(passphrase)),
+        _ => Ok(get_password("BIP39 passphrase", confirm)?),
     }
+}
 
+/// Get seed words from HELIUM_WALLET_SEED_WORDS or by asking for them,
+/// checked with the given function
+pub(crate) fn get_seed_words<T>(secret_from_phrase: impl Fn(&str) -> Result<T>) -> Result<T> {
     match env::var("HELIUM_WALLET_SEED_WORDS") {
         Ok(word_string) => secret_from_phrase(&word_string),
         _ => {
@@ -192,6 +381,19 @@ fn get_seed_entropy() -> Result<Vec<u8>> {
     }
 }
 
+fn get_seed_entropy(
+    opts: &SeedOpts,
+    derivation: Derivation,
+    path: &DerivationPath,
+) -> Result<Vec<u8>> {
+    let passphrase = if opts.passphrase {
+        get_seed_passphrase(true)?
+    } else {
+        Zeroizing::default()
+    };
+    get_seed_words(|s| seed_words_entropy(&phrase_to_words(s), derivation, path, &passphrase))
+}
+
 fn get_secret_entropy() -> Result<Vec<u8>> {
     fn secret_from_str(s: &str) -> Result<Vec<u8>> {
         if s.starts_with('[') {
@@ -213,3 +415,56 @@ fn get_secret_entropy() -> Result<Vec<u8>> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solana_keypair_file() {
+        let keypair = keypair::Keypair::generate();
+        let secret = keypair.secret();
+        let file = serde_json::to_string(&secret).expect("json");
+        assert_eq!(read_solana_keypair(&file).expect("keypair"), secret);
+
+        let mut mismatched = secret.clone();
+        mismatched[40] ^= 1;
+        let file = serde_json::to_string(&mismatched).expect("json");
+        assert!(read_solana_keypair(&file).is_err());
+        assert!(read_solana_keypair("[1, 2, 3]").is_err());
+    }
+
+    #[test]
+    fn import_args() {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(subcommand)]
+            cmd: CreateCommand,
+        }
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from([
+            "create",
+            "import",
+            "--seed",
+            "--derivation",
+            "slip10",
+            "--path",
+            "m/44'/501'/1'/0'",
+        ])
+        .expect("seed import");
+        let CreateCommand::Import(import) = cli.cmd else {
+            panic!("expected import");
+        };
+        assert!(import.seed.seed);
+        assert!(import.input.is_none());
+        assert!(matches!(import.derivation, Derivation::Slip10));
+        assert_eq!(import.path.to_string(), "m/44'/501'/1'/0'");
+
+        assert!(Cli::try_parse_from(["create", "import"]).is_err());
+        assert!(Cli::try_parse_from(["create", "import", "id.json", "--seed"]).is_err());
+        assert!(
+            Cli::try_parse_from(["create", "basic", "--seed", "--derivation", "slip10"]).is_err()
+        );
+    }
+}
@@ -3,6 +3,12 @@ use helium_lib::dc;
 
 #[derive(Debug, Clone, clap::Args)]
 /// Burn Data Credits (DC) from this wallet into oblivion.
+///
+/// The burn is not attributed to any subDAO. Only DC burned through the
+/// delegated burn flow counts toward the dc_burned totals, and with that the
+/// utility score, of a subDAO. That burn can only be performed by the burn
+/// authority of the subDAO, which burns DC delegated with "dc delegate" as
+/// the traffic of its payer uses it.
 pub struct Cmd {
     /// Amount of DC to burn
     dc: u64,
@@ -14,12 +20,19 @@ pub struct Cmd {
 
 impl Cmd {
     pub async fn run(&self, opts: Opts) -> Result {
-        let password = get_wallet_password(false)?;
-        let keypair = opts.load_keypair(password.as_bytes())?;
+        let signer = opts.load_signer(&self.commit)?;
         let client = opts.client()?;
         let transaction_opts = self.commit.transaction_opts(&client);
 
-        let (tx, _) = dc::burn(&client, self.dc, &keypair, &transaction_opts).await?;
-        print_json(&self.commit.maybe_commit(tx, &client).await?.to_json())
+        let (msg, _) =
+            dc::burn_message(&client, self.dc, &signer.pubkey(), &transaction_opts).await?;
+        let tx = signer.sign(msg)?;
+        print_json(
+            &self
+                .commit
+                .maybe_commit_with_signer(tx, &client, &signer)
+                .await?
+                .to_json(),
+        )
     }
 }
@@ -0,0 +1,85 @@
+use crate::cmd::*;
+use helium_lib::{dao::SubDao, dc, keypair::Pubkey, token};
+
+#[derive(Debug, Clone, clap::Args)]
+/// Burn delegated Data Credits (DC) from the escrow account of a given router.
+///
+/// The wallet must be the DC burn authority of the given subdao. A structured
+/// receipt with the escrow balances before and after the burn is printed for
+/// settlement reconciliation. The balance after the burn is read from the
+/// escrow once the burn is finalized, without --commit the expected balance
+/// is given instead.
+pub struct Cmd {
+    /// Subdao the DC were delegated to
+    subdao: SubDao,
+
+    /// Public Helium router key to burn delegated DC for
+    router_key: String,
+
+    /// Amount of DC to burn. Defaults to the full escrow balance
+    dc: Option<u64>,
+
+    /// Commit the burn
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let client = opts.client()?;
+
+        let delegated_dc_key = self.subdao.delegated_dc_key(&self.router_key);
+        let escrow_key = self.subdao.escrow_key(&delegated_dc_key);
+        let pre_balance = escrow_balance(&client, &escrow_key).await?;
+        let amount = self.dc.unwrap_or(pre_balance);
+        if amount == 0 {
+            bail!("No delegated DC to burn");
+        }
+        if amount > pre_balance {
+            bail!("Escrow balance {pre_balance} is less than requested {amount} DC");
+        }
+
+        let transaction_opts = self.commit.transaction_opts(&client);
+        let (tx, _) = dc::burn_delegated(
+            &client,
+            self.subdao,
+            &keypair,
+            amount,
+            &self.router_key,
+            &transaction_opts,
+        )
+        .await?;
+        // The escrow is read back after the burn, which only sees it once
+        // finalized
+        let response = self
+            .commit
+            .finalizing()
+            .maybe_commit_with_keypair(tx, &client, &keypair)
+            .await?;
+        let committed = matches!(
+            response,
+            CommitResponse::Signature(_) | CommitResponse::Confirmed(_)
+        );
+        let mut json = response.to_json();
+        json["router"] = self.router_key.clone().into();
+        json["subdao"] = self.subdao.to_string().into();
+        json["delegated_dc_key"] = delegated_dc_key.to_string().into();
+        json["escrow_key"] = escrow_key.to_string().into();
+        json["amount"] = amount.into();
+        json["pre_balance"] = pre_balance.into();
+        if committed {
+            json["post_balance"] = escrow_balance(&client, &escrow_key).await?.into();
+        } else {
+            json["expected_post_balance"] = (pre_balance - amount).into();
+        }
+        print_json(&json)
+    }
+}
+
+async fn escrow_balance(client: &client::Client, escrow_key: &Pubkey) -> Result<u64> {
+    Ok(token::balance_for_address(client, escrow_key)
+        .await?
+        .map(|balance| balance.amount.amount)
+        .unwrap_or_default())
+}
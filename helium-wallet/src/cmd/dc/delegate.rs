@@ -3,6 +3,10 @@ use helium_lib::{dao::SubDao, dc};
 
 #[derive(Debug, Clone, clap::Args)]
 /// Delegate DC from this wallet to a given router
+///
+/// Delegated DC can not be undelegated, the data credits program has no way
+/// to return escrowed DC or close a delegation. Use "dc migrate-delegation"
+/// to delegate to another router key instead.
 pub struct Cmd {
     /// Subdao to delegate DC to
     subdao: SubDao,
@@ -20,20 +24,26 @@ pub struct Cmd {
 
 impl Cmd {
     pub async fn run(&self, opts: Opts) -> Result {
-        let password = get_wallet_password(false)?;
-        let keypair = opts.load_keypair(password.as_bytes())?;
+        let signer = opts.load_signer(&self.commit)?;
 
         let client = opts.client()?;
         let transaction_opts = self.commit.transaction_opts(&client);
-        let (tx, _) = dc::delegate(
+        let (msg, _) = dc::delegate_message(
             &client,
             self.subdao,
             &self.payer,
-            self.dc,
-            &keypair,
+            Token::Dc.amount(self.dc),
+            &signer.pubkey(),
             &transaction_opts,
         )
         .await?;
-        print_json(&self.commit.maybe_commit(tx, &client).await?.to_json())
+        let tx = signer.sign(msg)?;
+        print_json(
+            &self
+                .commit
+                .maybe_commit_with_signer(tx, &client, &signer)
+                .await?
+                .to_json(),
+        )
     }
 }
@@ -0,0 +1,182 @@
+use crate::cmd::*;
+use helium_lib::{
+    dao::SubDao,
+    dc::{self, Delegation},
+    keypair::{Pubkey, Signer},
+    token::{self, Token},
+};
+
+#[derive(Debug, Clone, clap::Args)]
+/// Move delegated Data Credits (DC) from one router key to another.
+///
+/// For routers that migrate their OUI to a new router key or switch subdaos,
+/// also available as `dc redelegate`. This does not undelegate, which is not
+/// possible: the data credits program has no way to return escrowed DC or
+/// close a delegation. Instead the wallet delegates the amount held in the old escrow
+/// account from its own DC to the new router key. The DC in the old escrow
+/// stay there and are reported as stranded, to be used by the traffic of the
+/// old router key or burned by the DC burn authority of the old subdao.
+///
+/// A wallet that is that burn authority can pass --burn to burn the same
+/// amount from the old escrow in the same transaction, leaving the total
+/// delegated DC unchanged.
+///
+/// A report with the escrow and wallet balances before and after the
+/// migration is printed for reconciliation. When committed, the balances
+/// after the migration are read once the transaction is finalized.
+pub struct Cmd {
+    /// Subdao the DC are delegated to
+    #[arg(long)]
+    from_subdao: SubDao,
+    /// Public Helium router key the DC are delegated to
+    #[arg(long, alias = "from-router")]
+    from_router_key: String,
+    /// Subdao to delegate the DC to. Defaults to the old subdao
+    #[arg(long)]
+    to_subdao: Option<SubDao>,
+    /// Public Helium router key to delegate the DC to. Defaults to the old
+    /// router key
+    #[arg(long, alias = "to-router")]
+    to_router_key: Option<String>,
+    /// Amount of DC to move. Defaults to the full balance of the old escrow
+    #[arg(long, alias = "amount")]
+    dc: Option<u64>,
+    /// Also burn the moved amount from the old escrow. Requires the wallet
+    /// to be the DC burn authority of the old subdao
+    #[arg(long)]
+    burn: bool,
+    /// Commit the migration
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+/// Balance of the escrow account of a delegation, zero if there is none
+async fn escrow_balance(client: &client::Client, delegation: Delegation<'_>) -> Result<u64> {
+    let delegated_dc_key = delegation.subdao.delegated_dc_key(delegation.router_key);
+    let escrow_key = delegation.subdao.escrow_key(&delegated_dc_key);
+    Ok(token::balance_for_address(client, &escrow_key)
+        .await?
+        .map(|balance| balance.amount.amount)
+        .unwrap_or_default())
+}
+
+fn delegation_json(delegation: Delegation<'_>, pre_balance: u64) -> serde_json::Value {
+    let delegated_dc_key = delegation.subdao.delegated_dc_key(delegation.router_key);
+    json!({
+        "subdao": delegation.subdao.to_string(),
+        "router": delegation.router_key,
+        "delegated_dc_key": delegated_dc_key.to_string(),
+        "escrow_key": delegation.subdao.escrow_key(&delegated_dc_key).to_string(),
+        "pre_balance": pre_balance,
+    })
+}
+
+async fn wallet_balance(client: &client::Client, wallet: &Pubkey) -> Result<u64> {
+    Ok(
+        token::balance_for_address(client, &Token::Dc.associated_token_adress(wallet))
+            .await?
+            .map(|balance| balance.amount.amount)
+            .unwrap_or_default(),
+    )
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let from = Delegation {
+            subdao: self.from_subdao,
+            router_key: &self.from_router_key,
+        };
+        let to = Delegation {
+            subdao: self.to_subdao.unwrap_or(self.from_subdao),
+            router_key: self
+                .to_router_key
+                .as_deref()
+                .unwrap_or(&self.from_router_key),
+        };
+        if from.subdao == to.subdao && from.router_key == to.router_key {
+            bail!("old and new delegation are the same");
+        }
+
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let wallet: Pubkey = keypair.pubkey();
+        let client = opts.client()?;
+
+        let from_balance = escrow_balance(&client, from).await?;
+        let to_balance = escrow_balance(&client, to).await?;
+        let amount = self.dc.unwrap_or(from_balance);
+        if amount == 0 {
+            bail!("No delegated DC to migrate");
+        }
+        if amount > from_balance {
+            bail!("Escrow balance {from_balance} is less than requested {amount} DC");
+        }
+        let wallet_balance = wallet_balance(&client, &wallet).await?;
+        if amount > wallet_balance {
+            bail!("Wallet balance {wallet_balance} is less than the {amount} DC to delegate");
+        }
+        if self.burn {
+            let (dc_burn_authority, _) = dc::burn_authority(&client, from.subdao).await?;
+            if dc_burn_authority != wallet {
+                bail!(
+                    "wallet is not the DC burn authority of {}, leave out --burn to only delegate",
+                    from.subdao
+                );
+            }
+        }
+
+        let transaction_opts = self.commit.transaction_opts(&client);
+        let (msg, _) = dc::migrate_delegation_message(
+            &client,
+            from,
+            to,
+            amount,
+            self.burn,
+            &wallet,
+            &transaction_opts,
+        )
+        .await?;
+        let tx = VersionedTransaction::try_new(msg, &[&*keypair])?;
+        let commit = self.commit.finalizing();
+        let response = commit
+            .maybe_commit_with_keypair(tx, &client, &keypair)
+            .await?;
+        let committed = matches!(
+            response,
+            CommitResponse::Signature(_) | CommitResponse::Confirmed(_)
+        );
+        let mut json = response.to_json();
+
+        let mut from_json = delegation_json(from, from_balance);
+        let mut to_json = delegation_json(to, to_balance);
+        let mut wallet_json = json!({
+            "address": wallet.to_string(),
+            "pre_balance": wallet_balance,
+        });
+        let from_post = if committed {
+            let from_post = escrow_balance(&client, from).await?;
+            from_json["post_balance"] = from_post.into();
+            to_json["post_balance"] = escrow_balance(&client, to).await?.into();
+            wallet_json["post_balance"] = self::wallet_balance(&client, &wallet).await?.into();
+            from_post
+        } else {
+            let from_post = if self.burn {
+                from_balance - amount
+            } else {
+                from_balance
+            };
+            from_json["expected_post_balance"] = from_post.into();
+            to_json["expected_post_balance"] = (to_balance + amount).into();
+            wallet_json["expected_post_balance"] = (wallet_balance - amount).into();
+            from_post
+        };
+        // DC left in the old escrow can only be used by the traffic of the
+        // old router key or burned by the burn authority
+        from_json["stranded"] = from_post.into();
+        json["amount"] = amount.into();
+        json["burned"] = self.burn.into();
+        json["from"] = from_json;
+        json["to"] = to_json;
+        json["wallet"] = wallet_json;
+        print_json(&json)
+    }
+}
@@ -1,15 +1,24 @@
-use crate::cmd::*;
+use crate::{
+    cmd::{price::LivePriceOpts, *},
+    policy,
+};
 use helium_lib::{
     dc,
     keypair::Pubkey,
-    token::{Token, TokenAmount},
+    token::{
+        price::{SourcedPrice, DC_PER_USD},
+        Token, TokenAmount,
+    },
 };
+use rust_decimal::prelude::*;
 
 #[derive(Debug, Clone, clap::Args)]
 /// Mint HNT to Data Credits (DC) from this wallet to a given payee's wallet.
 ///
 /// Either the amount of HNT to burn or the amount of DC expected after the burn
-/// can be specified.
+/// can be specified. The other amount is estimated from the current HNT price,
+/// which is reported along with the source it came from. The burn itself
+/// always uses the on-chain pyth price.
 pub struct Cmd {
     /// Account address to send the resulting DC to. Defaults to the active
     /// wallet.
@@ -24,11 +33,35 @@ pub struct Cmd {
     #[arg(long, conflicts_with = "hnt")]
     dc: Option<u64>,
 
+    #[command(flatten)]
+    price: LivePriceOpts,
+
     /// Commit the burn
     #[command(flatten)]
     commit: CommitOpts,
 }
 
+/// The amount of the other side of a mint at the given HNT price
+fn estimate(amount: &TokenAmount, price: &SourcedPrice) -> Option<serde_json::Value> {
+    let dc_per_hnt = price.price.price * Decimal::from(DC_PER_USD);
+    if dc_per_hnt.is_zero() {
+        return None;
+    }
+    let value = Decimal::new(
+        i64::try_from(amount.amount).ok()?,
+        amount.token.decimals().into(),
+    );
+    let estimate = match amount.token {
+        Token::Hnt => json!({ "dc": (value * dc_per_hnt).floor().to_u64()? }),
+        _ => json!({
+            "hnt": (value / dc_per_hnt)
+                .round_dp(Token::Hnt.decimals().into())
+                .to_f64()?
+        }),
+    };
+    Some(estimate)
+}
+
 impl Cmd {
     pub async fn run(&self, opts: Opts) -> Result {
         let password = get_wallet_password(false)?;
@@ -36,15 +69,35 @@ impl Cmd {
 
         let client = opts.client()?;
         let payee = self.payee.as_ref().unwrap_or(&wallet.public_key);
+        policy::check_external(&wallet.public_key, payee, "DC payee")?;
         let amount = match (self.hnt, self.dc) {
             (Some(hnt), None) => TokenAmount::from_f64(Token::Hnt, hnt),
             (None, Some(dc)) => TokenAmount::from_u64(Token::Dc, dc),
             _ => return Err(anyhow!("Must specify either HNT or DC")),
         };
+        // Without --require-pyth a missing price only leaves out the estimate,
+        // the program checks the oracle itself
+        let price = match self.price.get(&client, Token::Hnt).await {
+            Ok(price) => Some(price),
+            Err(err) if !self.price.requires_pyth() => {
+                warnings::warn(warnings::MISSING_PRICE, format!("no HNT price: {err}"));
+                None
+            }
+            Err(err) => return Err(err),
+        };
         let transaction_opts = self.commit.transaction_opts(&client);
 
         let keypair = wallet.decrypt(password.as_bytes())?;
         let (tx, _) = dc::mint(&client, amount, payee, &keypair, &transaction_opts).await?;
-        print_json(&self.commit.maybe_commit(tx, &client).await?.to_json())
+        let mut json = self
+            .commit
+            .maybe_commit_with_keypair(tx, &client, &keypair)
+            .await?
+            .to_json();
+        if let Some(price) = price {
+            json["estimate"] = estimate(&amount, &price).into();
+            json["price"] = serde_json::to_value(price)?;
+        }
+        print_json(&json)
     }
 }
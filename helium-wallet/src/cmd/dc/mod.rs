@@ -1,7 +1,9 @@
 use crate::cmd::*;
 
 mod burn;
+mod burn_delegated;
 mod delegate;
+mod migrate_delegation;
 mod mint;
 mod price;
 
@@ -24,6 +26,9 @@ pub enum DcCommand {
     Mint(mint::Cmd),
     Delegate(delegate::Cmd),
     Burn(burn::Cmd),
+    BurnDelegated(burn_delegated::Cmd),
+    #[command(alias = "redelegate")]
+    MigrateDelegation(migrate_delegation::Cmd),
 }
 
 impl DcCommand {
@@ -33,6 +38,8 @@ impl DcCommand {
             Self::Mint(cmd) => cmd.run(opts).await,
             Self::Delegate(cmd) => cmd.run(opts).await,
             Self::Burn(cmd) => cmd.run(opts).await,
+            Self::BurnDelegated(cmd) => cmd.run(opts).await,
+            Self::MigrateDelegation(cmd) => cmd.run(opts).await,
         }
     }
 }
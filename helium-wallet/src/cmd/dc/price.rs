@@ -1,22 +1,27 @@
-use crate::cmd::*;
+use crate::cmd::{price::LivePriceOpts, *};
 use helium_lib::token::{self, Token};
 use rust_decimal::prelude::*;
 use serde_json::json;
 
 #[derive(Clone, Debug, clap::Args)]
 /// Get the amount of HNT needed to buy a given number of USD worth of Data
-/// Credits
+/// Credits.
+///
+/// The HNT price falls back to secondary sources when the pyth price is stale
+/// or unavailable, and the source used is reported.
 pub struct Cmd {
     /// The USD value of the Data Credits to convert to HNT amount.
     usd: f64,
+    #[command(flatten)]
+    price: LivePriceOpts,
 }
 
 impl Cmd {
     pub async fn run(&self, opts: Opts) -> Result {
         let client = opts.client()?;
-        let price = token::price::get(&client, Token::Hnt).await?;
+        let price = self.price.get(&client, Token::Hnt).await?;
 
-        let hnt_price = price.price;
+        let hnt_price = price.price.price;
         let usd_amount =
             Decimal::from_f64(self.usd).ok_or_else(|| anyhow!("Invalid USD amount"))?;
         let dc_amount = (usd_amount * Decimal::new(token::price::DC_PER_USD, 0))
@@ -29,7 +34,8 @@ impl Cmd {
                 "hnt": hnt_amount,
                 "dc": dc_amount,
                 "hnt_price": hnt_price,
-                "timestamp": price.timestamp,
+                "timestamp": price.price.timestamp,
+                "source": price.source,
         });
         print_json(&json)
     }
@@ -0,0 +1,104 @@
+use crate::cmd::*;
+use helium_lib::{asset, kta, reward};
+
+#[derive(Debug, clap::Args)]
+pub struct Cmd {
+    #[command(subcommand)]
+    cmd: DebugCommand,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        self.cmd.run(opts).await
+    }
+}
+
+/// Commands to diagnose the wallet itself
+#[derive(Debug, clap::Subcommand)]
+pub enum DebugCommand {
+    #[command(subcommand)]
+    Cache(CacheCommand),
+}
+
+impl DebugCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::Cache(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+/// Inspect and clear the caches kept on disk across commands.
+///
+/// Oracle reward responses are cached on disk unless '--no-cache' is given.
+/// Key to asset accounts and assets fetched from the DAS provider are only
+/// cached in memory for the duration of a single command. Run any command
+/// with '--log-level helium_wallet=debug' to log the statistics of those
+/// caches when it finishes.
+#[derive(Debug, clap::Subcommand)]
+pub enum CacheCommand {
+    Stats(StatsCmd),
+    Clear(ClearCmd),
+}
+
+impl CacheCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::Stats(cmd) => cmd.run(opts).await,
+            Self::Clear(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+/// Report the entries and size of the on-disk caches
+#[derive(Debug, clap::Args)]
+pub struct StatsCmd {}
+
+impl StatsCmd {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        print_json(&json!({
+            "oracle_rewards": reward::cache::stats(),
+        }))
+    }
+}
+
+/// Empty the on-disk caches
+#[derive(Debug, clap::Args)]
+pub struct ClearCmd {}
+
+impl ClearCmd {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        let oracle_rewards = reward::cache::stats().map(|stats| stats.path);
+        let removed = reward::cache::clear()?;
+        print_json(&json!({
+            "oracle_rewards": oracle_rewards.map(|path| json!({
+                "path": path,
+                "removed": removed,
+            })),
+        }))
+    }
+}
+
+/// Statistics of the in-memory caches of this command
+pub fn command_stats_json() -> serde_json::Value {
+    let kta = kta::stats();
+    let das_assets = asset::cache::stats();
+    json!({
+        "kta": {
+            "entries": kta.entries,
+            "bytes": kta.bytes,
+            "hits": kta.hits,
+            "misses": kta.misses,
+            "hit_rate": kta.hit_rate(),
+        },
+        "das_assets": {
+            "entries": das_assets.entries,
+            "capacity": das_assets.capacity,
+            "ttl_secs": das_assets.ttl_secs,
+            "hits": das_assets.hits,
+            "misses": das_assets.misses,
+            "evictions": das_assets.evictions,
+            "hit_rate": das_assets.hit_rate(),
+        },
+    })
+}
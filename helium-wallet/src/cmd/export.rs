@@ -1,5 +1,15 @@
-use crate::{cmd::*, pwhash::*};
+use crate::{
+    cmd::{
+        create::{get_seed_passphrase, get_seed_words, seed_words_entropy, Derivation},
+        *,
+    },
+    pwhash::*,
+};
 use helium_lib::keypair::Signer;
+use helium_mnemonic::{
+    slip10::{DerivationPath, SOLANA_DERIVATION_PATH},
+    Language,
+};
 use qr2term::print_qr;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -12,7 +22,8 @@ const ARGON_MEM_LIMIT: pwhash::MemLimit = pwhash::MEMLIMIT_MODERATE;
 
 #[derive(Debug, Clone, clap::ValueEnum)]
 pub enum OutputFormat {
-    /// Export the raw secret key, compatible with the solana CLI wallet
+    /// Export the raw secret key as a Solana CLI keypair file (id.json)
+    #[value(alias = "solana")]
     Key,
     /// Export the seed phrase
     Seed,
@@ -24,8 +35,28 @@ pub enum OutputFormat {
 #[derive(Debug, clap::Args)]
 pub struct Cmd {
     /// Output format to use
-    #[arg(long, default_value = "seed")]
+    #[arg(long, alias = "format", default_value = "seed")]
     output: OutputFormat,
+    /// File to write a key or seed export to instead of stdout. The file is
+    /// not encrypted, must not exist yet and is only readable by the current
+    /// user
+    #[arg(long, conflicts_with = "passphrase")]
+    out: Option<PathBuf>,
+    /// The wallet was created from a seed phrase with a BIP39 passphrase.
+    ///
+    /// The key of such a wallet cannot be turned back into its seed phrase,
+    /// so the phrase and passphrase are asked for instead and the phrase is
+    /// only exported once they are checked to reproduce the wallet key.
+    /// Read from HELIUM_WALLET_SEED_WORDS and HELIUM_WALLET_SEED_PASSPHRASE
+    /// if set
+    #[arg(long)]
+    passphrase: bool,
+    /// How the wallet key was derived from the seed phrase
+    #[arg(long, value_enum, default_value_t = Derivation::Direct, requires = "passphrase")]
+    derivation: Derivation,
+    /// Derivation path used with slip10 derivation
+    #[arg(long, default_value = SOLANA_DERIVATION_PATH, requires = "passphrase")]
+    path: DerivationPath,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -38,6 +69,9 @@ pub struct EncryptedSeed {
 
 impl Cmd {
     pub async fn run(&self, opts: Opts) -> Result {
+        if self.passphrase {
+            return self.export_passphrase_seed(opts);
+        }
         let password = get_wallet_password(false)?;
         let wallet = opts.load_wallet()?;
         let keypair = wallet.decrypt(password.as_bytes())?;
@@ -52,18 +86,60 @@ impl Cmd {
                 print_qr(json.to_string())?;
                 Ok(())
             }
-            OutputFormat::Key => {
-                println!("{}", &serde_json::to_string(&keypair.secret())?);
-                Ok(())
-            }
-            OutputFormat::Seed => {
-                println!("{}", &keypair.phrase()?);
-                Ok(())
-            }
+            OutputFormat::Key => self.write_secret(&serde_json::to_string(&keypair.secret())?),
+            OutputFormat::Seed => self.write_secret(&keypair.phrase()?),
         }
     }
 }
 
+impl Cmd {
+    /// Write an exported secret to the output file, or stdout if none is
+    /// given
+    fn write_secret(&self, secret: &str) -> Result {
+        let Some(path) = &self.out else {
+            println!("{secret}");
+            return Ok(());
+        };
+        // The file is created readable by the owner only, rather than
+        // restricted after the secret could already be read
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(path)?;
+        io::Write::write_all(&mut file, format!("{secret}\n").as_bytes())?;
+        Ok(())
+    }
+
+    /// Export the seed phrase of a wallet created with a BIP39 passphrase,
+    /// after checking that the phrase and passphrase reproduce its key
+    fn export_passphrase_seed(&self, opts: Opts) -> Result {
+        if !matches!(self.output, OutputFormat::Seed) {
+            bail!("--passphrase only applies to seed output");
+        }
+        let address = opts.load_wallet()?.public_key;
+        let passphrase = get_seed_passphrase(false)?;
+        let phrase = get_seed_words(|s| {
+            let words = phrase_to_words(s);
+            let entropy = seed_words_entropy(&words, self.derivation, &self.path, &passphrase)?;
+            if Keypair::generate_from_entropy(&entropy)?.pubkey() != address {
+                bail!("seed phrase and passphrase do not reproduce wallet {address}");
+            }
+            let language = Language::English;
+            let words: Vec<&str> = words
+                .iter()
+                .filter_map(|word| language.find_word(word).map(|index| &language[index]))
+                .collect();
+            Ok(words.join(" "))
+        })?;
+        println!("{phrase}");
+        Ok(())
+    }
+}
+
 /// Encrypted seeds V1:
 ///  1) Given the user entered password, generate an encryption key using the same pwhash
 ///     algorithm (Argong2id13) as the existing wallet.
@@ -0,0 +1,425 @@
+use crate::cmd::*;
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use helium_lib::{
+    keypair::Pubkey,
+    reward::{self, ClaimableToken},
+    token::{
+        self,
+        history::Activity,
+        price::{self, PriceSource},
+        Token, TokenAmount,
+    },
+};
+use rust_decimal::prelude::*;
+
+#[derive(Debug, clap::Args)]
+pub struct Cmd {
+    #[command(subcommand)]
+    cmd: HistoryCommand,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        self.cmd.run(opts).await
+    }
+}
+
+/// Reports on the past activity of a wallet
+#[derive(Debug, clap::Subcommand)]
+pub enum HistoryCommand {
+    RewardsReport(RewardsReportCmd),
+    Export(ExportCmd),
+}
+
+impl HistoryCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::RewardsReport(cmd) => cmd.run(opts).await,
+            Self::Export(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+/// Currencies to value rewards in
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum Currency {
+    Usd,
+}
+
+impl Currency {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Usd => "USD",
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+/// Report the rewards claimed by a wallet in a calendar year with their value
+/// at the time of each claim.
+///
+/// Claims are read back from the chain. The report is a CSV with a row per
+/// claim in the Koinly universal format, which most tax tools can import,
+/// labeled as rewards with the claimed value as the cost basis. The value
+/// uses the daily price of the token on the day of the claim.
+pub struct RewardsReportCmd {
+    /// Calendar year to report, in UTC
+    #[arg(long)]
+    year: i32,
+    /// Currency to value claims in
+    #[arg(long, value_enum, default_value_t = Currency::Usd)]
+    currency: Currency,
+    /// Source of historical prices
+    #[arg(long, value_enum, default_value_t)]
+    price_source: PriceSource,
+    /// Only report claims of the given token
+    #[arg(long)]
+    token: Option<ClaimableToken>,
+    /// Address to report claims for instead of the wallet
+    #[arg(long, value_parser = parse_address)]
+    address: Option<Pubkey>,
+    /// File to write the CSV report to instead of stdout
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+impl RewardsReportCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let owner = match self.address {
+            Some(address) => address,
+            None => opts.load_wallet()?.public_key,
+        };
+        let (since, until) = year_range(self.year)?;
+        let tokens = match self.token {
+            Some(token) => vec![token],
+            None => vec![
+                ClaimableToken::Hnt,
+                ClaimableToken::Iot,
+                ClaimableToken::Mobile,
+            ],
+        };
+
+        let client = opts.client()?;
+        let mut rows = vec![];
+        for token in tokens {
+            let claims =
+                reward::history::claims_for_owner(&client, token, &owner, since, until).await?;
+            if claims.is_empty() {
+                continue;
+            }
+            // Include the day before the year so claims at its start are
+            // priced even if the first daily price is late
+            let prices = price::history(
+                self.price_source,
+                token.into(),
+                since - Duration::days(1),
+                until,
+            )
+            .await?;
+            for claim in claims {
+                let price = price::at(&prices, claim.timestamp).map(|price| price.price);
+                if price.is_none() {
+                    warnings::warn(
+                        warnings::MISSING_PRICE,
+                        format!("no price found for the claim in {}", claim.signature),
+                    );
+                }
+                rows.push(ReportRow {
+                    timestamp: claim.timestamp,
+                    amount: claim.amount,
+                    price,
+                    signature: claim.signature,
+                });
+            }
+        }
+        rows.sort_by_key(|row| row.timestamp);
+
+        let writer: Box<dyn io::Write> = match &self.out {
+            Some(path) => Box::new(io::BufWriter::new(open_output_file(path, false)?)),
+            None => Box::new(io::stdout().lock()),
+        };
+        write_report(writer, self.currency, &rows)
+    }
+}
+
+/// The start of the given year and of the year after it
+fn year_range(year: i32) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let start = |year: i32| {
+        Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0)
+            .single()
+            .ok_or_else(|| anyhow!("invalid year: {year}"))
+    };
+    Ok((start(year)?, start(year + 1)?))
+}
+
+struct ReportRow {
+    timestamp: DateTime<Utc>,
+    amount: TokenAmount,
+    /// Price per token in the report currency
+    price: Option<Decimal>,
+    signature: String,
+}
+
+impl ReportRow {
+    fn quantity(&self) -> Decimal {
+        Decimal::new(
+            self.amount.amount as i64,
+            self.amount.token.decimals().into(),
+        )
+    }
+
+    fn value(&self) -> Option<Decimal> {
+        self.price
+            .map(|price| (self.quantity() * price).round_dp(2))
+    }
+}
+
+const REPORT_HEADER: [&str; 12] = [
+    "Date",
+    "Sent Amount",
+    "Sent Currency",
+    "Received Amount",
+    "Received Currency",
+    "Fee Amount",
+    "Fee Currency",
+    "Net Worth Amount",
+    "Net Worth Currency",
+    "Label",
+    "Description",
+    "TxHash",
+];
+
+fn write_report<W: io::Write>(writer: W, currency: Currency, rows: &[ReportRow]) -> Result {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(REPORT_HEADER)?;
+    for row in rows {
+        let token = row.amount.token.to_string().to_uppercase();
+        let description = match row.price {
+            Some(price) => format!(
+                "Helium rewards claim at {price} {}/{token}",
+                currency.code()
+            ),
+            None => "Helium rewards claim".to_string(),
+        };
+        writer.write_record([
+            row.timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+            String::new(),
+            String::new(),
+            row.quantity().normalize().to_string(),
+            token,
+            String::new(),
+            String::new(),
+            row.value()
+                .map(|value| value.to_string())
+                .unwrap_or_default(),
+            currency.code().to_string(),
+            "reward".to_string(),
+            description,
+            row.signature.clone(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, clap::Args)]
+/// Export the balance changes of a wallet for accounting.
+///
+/// Transactions of the wallet and its token accounts are read back from the
+/// chain and every change of a balance of the selected tokens is reported
+/// with the wallets on the other side of it and the transaction fee paid by
+/// the wallet. The CSV has a row per balance change, with the fee on the
+/// first row of each transaction so it is counted once.
+pub struct ExportCmd {
+    /// Tokens to report, comma separated. Defaults to hnt, iot, mobile, dc
+    /// and sol
+    #[arg(long, value_enum, value_delimiter = ',')]
+    token: Vec<Token>,
+    /// First day to report, as YYYY-MM-DD in UTC
+    #[arg(long)]
+    since: Option<NaiveDate>,
+    /// Day to stop reporting at, as YYYY-MM-DD in UTC. Not included in the
+    /// report
+    #[arg(long)]
+    until: Option<NaiveDate>,
+    /// Address to export instead of the wallet
+    #[arg(long, value_parser = parse_address)]
+    address: Option<Pubkey>,
+    /// Output format. --ndjson writes JSON, one balance change per line
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    format: ExportFormat,
+
+    #[command(flatten)]
+    output: OutputOpts,
+}
+
+impl ExportCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let address = match self.address {
+            Some(address) => address,
+            None => opts.load_wallet()?.public_key,
+        };
+        let tokens = if self.token.is_empty() {
+            vec![Token::Hnt, Token::Iot, Token::Mobile, Token::Dc, Token::Sol]
+        } else {
+            self.token.clone()
+        };
+        let day_start = |day: NaiveDate| day.and_hms_opt(0, 0, 0).map(|time| time.and_utc());
+        let since = self
+            .since
+            .and_then(day_start)
+            .unwrap_or(DateTime::UNIX_EPOCH);
+        let until = self.until.and_then(day_start).unwrap_or_else(Utc::now);
+
+        let client = opts.client()?;
+        let activities =
+            token::history::for_address(&client, &address, &tokens, since, until).await?;
+
+        let mut sink = self.output.sink()?;
+        if sink.is_ndjson() {
+            return sink.write_items(activities.iter().map(activity_json));
+        }
+        match self.format {
+            ExportFormat::Csv => write_export_csv(sink.writer(), &activities),
+            ExportFormat::Json => {
+                let json: Vec<serde_json::Value> = activities.iter().map(activity_json).collect();
+                sink.write_json(&json)
+            }
+        }
+    }
+}
+
+/// The given amount in the smallest unit of the given token as a decimal
+fn token_decimal(token: Token, amount: i128) -> Decimal {
+    Decimal::from_i128_with_scale(amount, token.decimals().into()).normalize()
+}
+
+fn activity_json(activity: &Activity) -> serde_json::Value {
+    json!({
+        "timestamp": activity.timestamp,
+        "signature": activity.signature,
+        "fee": token_decimal(Token::Sol, activity.fee.into()).to_string(),
+        "movements": activity
+            .movements
+            .iter()
+            .map(|movement| json!({
+                "token": movement.token,
+                "amount": token_decimal(movement.token, movement.change).to_string(),
+                "counterparties": movement.counterparties,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+const EXPORT_HEADER: [&str; 6] = [
+    "timestamp",
+    "signature",
+    "token",
+    "amount",
+    "counterparties",
+    "fee_sol",
+];
+
+fn write_export_csv<W: io::Write>(writer: W, activities: &[Activity]) -> Result {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(EXPORT_HEADER)?;
+    for activity in activities {
+        let timestamp = activity.timestamp.to_rfc3339();
+        let fee = token_decimal(Token::Sol, activity.fee.into()).to_string();
+        if activity.movements.is_empty() {
+            writer.write_record([
+                timestamp.as_str(),
+                activity.signature.as_str(),
+                "",
+                "",
+                "",
+                fee.as_str(),
+            ])?;
+            continue;
+        }
+        for (index, movement) in activity.movements.iter().enumerate() {
+            let fee = if index == 0 {
+                fee.clone()
+            } else {
+                String::new()
+            };
+            writer.write_record([
+                timestamp.clone(),
+                activity.signature.clone(),
+                movement.token.to_string(),
+                token_decimal(movement.token, movement.change).to_string(),
+                movement.counterparties.join(" "),
+                fee,
+            ])?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helium_lib::token::history::Movement;
+
+    #[test]
+    fn report_rows() {
+        let (since, until) = year_range(2024).expect("year");
+        assert_eq!(until - since, Duration::days(366));
+
+        let rows = [ReportRow {
+            timestamp: since + Duration::hours(36),
+            amount: Token::Hnt.amount(150_000_000),
+            price: Some(Decimal::new(512, 2)),
+            signature: "sig".to_string(),
+        }];
+        let mut output = vec![];
+        write_report(&mut output, Currency::Usd, &rows).expect("report");
+        let output = String::from_utf8(output).expect("utf8");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[1],
+            "2024-01-02 12:00:00 UTC,,,1.5,HNT,,,7.68,USD,reward,Helium rewards claim at 5.12 USD/HNT,sig"
+        );
+    }
+
+    #[test]
+    fn export_rows() {
+        let activities = [Activity {
+            timestamp: Utc.with_ymd_and_hms(2024, 3, 1, 8, 0, 0).unwrap(),
+            signature: "sig".to_string(),
+            fee: 5000,
+            movements: vec![
+                Movement {
+                    token: Token::Hnt,
+                    change: -250_000_000,
+                    counterparties: vec!["alice".to_string()],
+                },
+                Movement {
+                    token: Token::Sol,
+                    change: 2_039_280,
+                    counterparties: vec![],
+                },
+            ],
+        }];
+        let mut output = vec![];
+        write_export_csv(&mut output, &activities).expect("export");
+        let output = String::from_utf8(output).expect("utf8");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "timestamp,signature,token,amount,counterparties,fee_sol",
+                "2024-03-01T08:00:00+00:00,sig,hnt,-2.5,alice,0.000005",
+                "2024-03-01T08:00:00+00:00,sig,sol,0.00203928,,",
+            ]
+        );
+    }
+}
@@ -1,15 +1,25 @@
-use crate::{cmd::*, txn_envelope::TxnEnvelope};
+use crate::{
+    cmd::{
+        assets,
+        hotspots::{check_gain, onboarding_url, parse_region, IotConfigOpts},
+        *,
+    },
+    result::Context,
+    txn_envelope::TxnEnvelope,
+};
 use chrono::{DateTime, Utc};
 use helium_crypto::{KeyTag, PublicKey};
 use helium_lib::{
-    asset,
-    client::{VERIFIER_URL_DEVNET, VERIFIER_URL_MAINNET},
+    asset, client,
     dao::SubDao,
     hotspot::{self, cert, HotspotInfoUpdate},
+    keypair::{Keypair, Signer},
+    onboarding,
+    solana_sdk::transaction::Transaction as SolanaTransaction,
 };
-use helium_proto::BlockchainTxnAddGatewayV1;
+use helium_proto::{BlockchainTxnAddGatewayV1, Message, Region};
 use rand::rngs::OsRng;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::{fs::File, io::Write};
 
 #[derive(Debug, Clone, clap::Args)]
@@ -42,7 +52,11 @@ impl AddCmd {
 /// Add an IOT Hotspot to the blockchain.
 ///
 /// The required transaction is created by a
-/// Hotspot and supplied here for owner signing.
+/// Hotspot and supplied here for owner signing, either directly or as the JSON
+/// output of the `helium_gateway add` command.
+///
+/// Once a committed add lands, the command waits for the Hotspot asset to be
+/// indexed and prints its asset id and key to asset account.
 #[derive(Clone, Debug, clap::Args)]
 struct IotCmd {
     /// Latitude of Hotspot location to assert.
@@ -74,8 +88,33 @@ struct IotCmd {
     #[arg(long)]
     elevation: Option<i32>,
 
+    /// The LoRa region of the asserted location, like "US915".
+    ///
+    /// The antenna gain is checked against the maximum EIRP for the region.
+    /// Defaults to the region of the currently asserted location as known to
+    /// the IoT config service.
+    #[arg(long, value_parser = parse_region)]
+    region: Option<Region>,
+
+    #[command(flatten)]
+    iot_config: IotConfigOpts,
+
     /// Base64 encoded Hotspot transaction.
-    txn: Transaction,
+    #[arg(required_unless_present = "gateway_json")]
+    txn: Option<Transaction>,
+
+    /// Path to the JSON output of the `helium_gateway add` command.
+    ///
+    /// The transaction in the file is checked against the address, owner and
+    /// payer listed in the file.
+    #[arg(long, conflicts_with = "txn")]
+    gateway_json: Option<PathBuf>,
+
+    /// The expected maker (payer) of the Hotspot transaction.
+    ///
+    /// The add is rejected if the transaction is not paid for by this maker.
+    #[arg(long)]
+    maker: Option<PublicKey>,
 
     /// Optional url for the ecc signature verifier.
     ///
@@ -84,11 +123,58 @@ struct IotCmd {
     #[arg(long)]
     verifier: Option<String>,
 
+    /// Issue and onboard the Hotspot through the given onboarding server.
+    ///
+    /// The maker of the Hotspot pays the onboarding fees, and needs an
+    /// onboarding record for the Hotspot on the server. The shortcuts "m" and
+    /// "d" select the default onboarding server for mainnet and devnet.
+    #[arg(long, conflicts_with = "verifier")]
+    onboarding: Option<String>,
+
     /// Commit the Hotspot add.
     #[command(flatten)]
     commit: CommitOpts,
 }
 
+/// The JSON output of the `helium_gateway add` command
+#[derive(Debug, Deserialize)]
+struct GatewayAddJson {
+    address: String,
+    owner: String,
+    payer: String,
+    txn: String,
+}
+
+impl GatewayAddJson {
+    fn read(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("opening gateway json {}", path.display()))?;
+        let json = serde_json::from_reader(file)
+            .with_context(|| format!("parsing gateway json {}", path.display()))?;
+        Ok(json)
+    }
+
+    /// Returns the add gateway transaction after checking that it matches the
+    /// keys listed alongside it
+    fn add_gateway_txn(&self) -> Result<BlockchainTxnAddGatewayV1> {
+        let envelope: Transaction = self.txn.parse()?;
+        let txn = BlockchainTxnAddGatewayV1::from_envelope(&envelope)?;
+        for (field, expected, actual) in [
+            ("address", &self.address, &txn.gateway),
+            ("owner", &self.owner, &txn.owner),
+            ("payer", &self.payer, &txn.payer),
+        ] {
+            let expected: PublicKey = expected
+                .parse()
+                .with_context(|| format!("invalid {field} in gateway json"))?;
+            if expected.to_vec() != *actual {
+                bail!("{field} in gateway json does not match transaction");
+            }
+        }
+        Ok(txn)
+    }
+}
+
 async fn perform_add(
     subdao: SubDao,
     mut txn: BlockchainTxnAddGatewayV1,
@@ -97,17 +183,11 @@ async fn perform_add(
     commit: &CommitOpts,
     opts: &Opts,
 ) -> Result {
-    let password = get_wallet_password(false)?;
-    let keypair = opts.load_keypair(password.as_bytes())?;
+    let keypair = opts.load_signer(commit)?.keypair()?;
     let gateway = helium_crypto::PublicKey::from_bytes(&txn.gateway)?;
     let client = opts.client()?;
-    let hotspot_issued = asset::for_entity_key(&client, &gateway).await.is_ok();
-    let verifier_key = verifier.as_ref().unwrap_or(&opts.url);
-    let verifier = match verifier_key.as_str() {
-        "m" | "mainnet-beta" => VERIFIER_URL_MAINNET,
-        "d" | "devnet" => VERIFIER_URL_DEVNET,
-        url => url,
-    };
+    let hotspot_issued = is_issued(&client, &gateway).await?;
+    let verifier = opts.verifier_url(verifier);
     let transaction_opts = &commit.transaction_opts(&client);
 
     if !hotspot_issued {
@@ -131,19 +211,121 @@ async fn perform_add(
             transaction_opts,
         )
         .await?;
-        print_json(&commit.maybe_commit(tx, &client).await?.to_json())
+        let mut json = commit.maybe_commit(tx, &client).await?.to_json();
+        if commit.commit {
+            assets::add_issued(&mut json, &client, &gateway, opts).await;
+        }
+        print_json(&json)
     } else {
         Ok(())
     }
 }
 
+/// Partially sign the given transaction if the wallet is one of its signers
+fn sign_if_signer(tx: &mut SolanaTransaction, keypair: &Keypair) -> Result {
+    let signers =
+        &tx.message.account_keys[..usize::from(tx.message.header.num_required_signatures)];
+    if signers.contains(&keypair.pubkey()) {
+        tx.try_partial_sign(&[keypair], tx.message.recent_blockhash)?;
+    }
+    Ok(())
+}
+
+/// Whether the asset of the given Hotspot was issued. Failures other than
+/// the asset not being found are returned.
+async fn is_issued(client: &client::Client, gateway: &PublicKey) -> Result<bool> {
+    match asset::for_entity_key(client, gateway).await {
+        Ok(_) => Ok(true),
+        Err(err) if err.is_account_not_found() => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Issue and onboard a Hotspot with transactions from the onboarding server,
+/// paid for by the maker of the Hotspot
+async fn perform_onboarding_add(
+    onboarding_client: &onboarding::Client,
+    txn: BlockchainTxnAddGatewayV1,
+    update: HotspotInfoUpdate,
+    commit: &CommitOpts,
+    opts: &Opts,
+) -> Result {
+    let keypair = opts.load_signer(commit)?.keypair()?;
+    let gateway = helium_crypto::PublicKey::from_bytes(&txn.gateway)?;
+    let client = opts.client()?;
+
+    if !is_issued(&client, &gateway).await? {
+        let txns = onboarding_client
+            .get_create_hotspot_txns(&txn.in_envelope().encode_to_vec())
+            .await?;
+        for mut tx in txns {
+            sign_if_signer(&mut tx, &keypair)?;
+            let response = commit.maybe_commit(tx, &client).await?;
+            print_json(&response.to_json())?;
+            // The onboard transaction needs the accounts the issue
+            // transactions create, so those have to land before onboarding
+            // is requested
+            if let CommitResponse::Signature(signature) = response {
+                onboarding::wait_for_transaction(
+                    &client,
+                    &signature,
+                    CommitmentLevel::Confirmed,
+                    onboarding::TRANSACTION_TIMEOUT,
+                )
+                .await?;
+            }
+        }
+        if !commit.commit {
+            return Ok(());
+        }
+    }
+    onboarding::preflight(&client, onboarding_client, &gateway, &update).await?;
+    let mut tx = onboarding_client.get_onboard_txn(&gateway, update).await?;
+    sign_if_signer(&mut tx, &keypair)?;
+    let mut json = commit.maybe_commit(tx, &client).await?.to_json();
+    if commit.commit {
+        assets::add_issued(&mut json, &client, &gateway, opts).await;
+    }
+    print_json(&json)
+}
+
 impl IotCmd {
+    fn add_gateway_txn(&self) -> Result<BlockchainTxnAddGatewayV1> {
+        let txn = match (&self.txn, &self.gateway_json) {
+            (Some(txn), _) => BlockchainTxnAddGatewayV1::from_envelope(txn)?,
+            (None, Some(path)) => GatewayAddJson::read(path)?.add_gateway_txn()?,
+            (None, None) => bail!("no transaction or gateway json specified"),
+        };
+        if let Some(maker) = &self.maker {
+            let payer = PublicKey::from_bytes(&txn.payer)
+                .map_err(|_| anyhow!("transaction has no valid payer"))?;
+            if payer != *maker {
+                bail!("transaction payer {payer} does not match expected maker {maker}");
+            }
+        }
+        Ok(txn)
+    }
+
     pub async fn run(&self, opts: Opts) -> Result {
-        let txn = BlockchainTxnAddGatewayV1::from_envelope(&self.txn)?;
+        let txn = self.add_gateway_txn()?;
         let update = HotspotInfoUpdate::for_subdao(SubDao::Iot)
             .set_gain(self.gain)
             .set_elevation(self.elevation)
             .set_geo(self.lat, self.lon)?;
+        let gateway = PublicKey::from_bytes(&txn.gateway)?;
+        check_gain(
+            &opts.client()?,
+            &mut self.iot_config.client()?,
+            &gateway,
+            &update,
+            self.region,
+        )
+        .await?;
+        if let Some(server) = &self.onboarding {
+            let onboarding_client = onboarding::Client::new(&onboarding_url(server));
+            return perform_onboarding_add(&onboarding_client, txn, update, &self.commit, &opts)
+                .await;
+        }
         perform_add(
             SubDao::Iot,
             txn,
@@ -204,6 +386,9 @@ impl MobileToken {
 ///
 /// Issues the mobile Hotspot NFT and onboards it given the created data-only transaction,
 /// and Location details
+///
+/// Once committed, the asset id and key to asset account of the Hotspot are
+/// printed when the asset is indexed.
 #[derive(Debug, Clone, clap::Args)]
 struct MobileOnboard {
     /// Latitude of Hotspot location to assert.
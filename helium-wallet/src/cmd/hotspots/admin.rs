@@ -0,0 +1,69 @@
+use crate::cmd::*;
+use helium_lib::hotspot::dataonly;
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct Cmd {
+    #[command(subcommand)]
+    cmd: AdminCommand,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        self.cmd.run(opts).await
+    }
+}
+
+#[derive(Debug, Clone, clap::Subcommand)]
+/// Maintenance commands for data-only Hotspot infrastructure
+pub enum AdminCommand {
+    CreateTree(CreateTreeCmd),
+}
+
+impl AdminCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::CreateTree(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Replace the merkle tree data-only Hotspots are issued into with a new,
+/// empty tree.
+///
+/// The size of the new tree is set by the data-only config. The wallet pays
+/// the rent of the new tree account, which can be a large amount of SOL.
+/// Initializing the data-only config itself requires the DAO authority and is
+/// not supported here.
+pub struct CreateTreeCmd {
+    /// Create a new tree even if the current tree has capacity left
+    #[arg(long)]
+    force: bool,
+
+    /// Commit the new tree.
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+impl CreateTreeCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let client = opts.client()?;
+        let capacity = dataonly::tree_capacity(&client).await?;
+        if capacity.remaining() > 0 && !self.force {
+            bail!(
+                "current tree {} has capacity for {} more hotspots, use --force to replace it",
+                capacity.merkle_tree,
+                capacity.remaining()
+            );
+        }
+
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let transaction_opts = self.commit.transaction_opts(&client);
+        let (tx, _, new_merkle_tree) =
+            dataonly::create_tree(&client, &keypair, &transaction_opts).await?;
+        let mut json = self.commit.maybe_commit(tx, &client).await.to_json();
+        json["previous_tree"] = serde_json::to_value(capacity)?;
+        json["merkle_tree"] = new_merkle_tree.to_string().into();
+        print_json(&json)
+    }
+}
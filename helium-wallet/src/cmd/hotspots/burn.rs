@@ -16,16 +16,21 @@ pub struct Cmd {
 impl Cmd {
     pub async fn run(&self, opts: Opts) -> Result {
         let client = opts.client()?;
-        let password = get_wallet_password(false)?;
-        let keypair = opts.load_keypair(password.as_bytes())?;
-        let (tx, _) = hotspot::burn(
+        let signer = opts.load_signer(&self.commit)?;
+        let (msg, _) = hotspot::burn_message(
             &client,
             &self.address,
-            &keypair,
             &self.commit.transaction_opts(&client),
         )
         .await?;
+        let tx = signer.sign(msg)?;
 
-        print_json(&self.commit.maybe_commit(tx, &client).await?.to_json())
+        print_json(
+            &self
+                .commit
+                .maybe_commit_with_signer(tx, &client, &signer)
+                .await?
+                .to_json(),
+        )
     }
 }
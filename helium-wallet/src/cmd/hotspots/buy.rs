@@ -0,0 +1,38 @@
+use crate::cmd::*;
+use helium_lib::hotspot::escrow;
+
+/// Default maximum priority fee, in lamports, accepted in an offer
+const MAX_PRIORITY_FEE: u64 = 10_000_000;
+
+#[derive(Clone, Debug, clap::Args)]
+/// Accept an offer to buy a Hotspot.
+///
+/// The offer is written by the seller with the "hotspots sell" command. Its
+/// transaction is checked to only transfer the Hotspot to this wallet and pay
+/// the offered price to the seller before it is signed. Both happen in the
+/// same transaction, or neither does.
+pub struct Cmd {
+    /// Offer file to accept
+    offer: PathBuf,
+    /// Maximum priority fee in lamports to accept in the offer
+    #[arg(long, default_value_t = MAX_PRIORITY_FEE)]
+    max_priority_fee: u64,
+    /// Commit the purchase
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let offer: escrow::SaleOffer = serde_json::from_reader(fs::File::open(&self.offer)?)?;
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let client = opts.client()?;
+        let tx = escrow::accept(&client, &offer, self.max_priority_fee, &keypair).await?;
+        print_json(&json!({
+            "hotspot": offer.hotspot,
+            "seller": offer.seller.to_string(),
+            "price": offer.price(),
+            "result": self.commit.maybe_commit(tx, &client).await?.to_json(),
+        }))
+    }
+}
@@ -1,8 +1,15 @@
 use crate::cmd::*;
-use helium_lib::{dao::SubDao, hotspot};
+use helium_lib::{
+    boosting,
+    dao::SubDao,
+    hotspot::{self, HotspotInfo},
+};
 
 #[derive(Clone, Debug, clap::Args)]
 /// Get details for a given Hotspot
+///
+/// For mobile Hotspots with an asserted location the boosts of the hex the
+/// Hotspot is in are included.
 pub struct Cmd {
     address: helium_crypto::PublicKey,
 }
@@ -11,6 +18,21 @@ impl Cmd {
     pub async fn run(&self, opts: Opts) -> Result {
         let client = opts.client()?;
         let hotspot = hotspot::get_with_info(&client, &SubDao::all(), &self.address).await?;
-        print_json(&hotspot)
+        let mobile_location = hotspot
+            .info
+            .as_ref()
+            .and_then(|info| info.get(&SubDao::Mobile))
+            .and_then(|info| match info {
+                HotspotInfo::Mobile { location, .. } => location.as_ref(),
+                _ => None,
+            })
+            .map(|location| location.location);
+        let Some(location) = mobile_location else {
+            return print_json(&hotspot);
+        };
+        let hex_boosts = boosting::for_location(&client, location).await?;
+        let mut json = serde_json::to_value(&hotspot)?;
+        json["hex_boosts"] = serde_json::to_value(hex_boosts)?;
+        print_json(&json)
     }
 }
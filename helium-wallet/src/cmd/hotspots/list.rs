@@ -1,11 +1,22 @@
 use crate::cmd::*;
-use helium_lib::{hotspot, keypair::Pubkey};
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt, TryStreamExt};
+use helium_lib::{dao::SubDao, hotspot, keypair::Pubkey};
 
 #[derive(Clone, Debug, clap::Args)]
 /// Get the list of Hotspots for the active or a given wallet
 pub struct Cmd {
     /// The alternate wallet to get the list of Hotspots for
     wallet: Option<Pubkey>,
+
+    /// Include the time of the last asserted info update of every Hotspot
+    /// and the days since then. Hotspots that were never asserted have no
+    /// last assert time
+    #[arg(long)]
+    activity: bool,
+
+    #[command(flatten)]
+    output: OutputOpts,
 }
 
 impl Cmd {
@@ -17,11 +28,57 @@ impl Cmd {
             wallet.public_key
         };
         let client = opts.client()?;
+        let mut sink = self.output.sink()?;
+        if sink.is_ndjson() {
+            let mut pages = std::pin::pin!(hotspot::for_owner_pages(&client, &owner));
+            while let Some(hotspots) = pages.try_next().await? {
+                if self.activity {
+                    sink.write_items(with_activity(&client, hotspots).await?)?;
+                } else {
+                    sink.write_items(hotspots)?;
+                }
+            }
+            return Ok(());
+        }
         let hotspots = hotspot::for_owner(&client, &owner).await?;
+        let hotspots = if self.activity {
+            serde_json::to_value(with_activity(&client, hotspots).await?)?
+        } else {
+            serde_json::to_value(hotspots)?
+        };
         let json = json!( {
             "address": owner.to_string(),
             "hotspots": hotspots,
         });
-        print_json(&json)
+        sink.write_json(&json)
     }
 }
+
+/// The given Hotspots with the time of their latest info update across
+/// subdaos and the days since then
+async fn with_activity(
+    client: &client::Client,
+    hotspots: Vec<hotspot::Hotspot>,
+) -> Result<Vec<serde_json::Value>> {
+    stream::iter(hotspots)
+        .map(|hotspot| async move {
+            let mut last_update: Option<(SubDao, DateTime<Utc>)> = None;
+            for subdao in SubDao::all() {
+                let info_key = subdao.info_key(&hotspot.key);
+                if let Some(update) = hotspot::info::last_update(client, &info_key).await? {
+                    if last_update.is_none_or(|(_, last)| update.timestamp > last) {
+                        last_update = Some((subdao, update.timestamp));
+                    }
+                }
+            }
+            let mut json = serde_json::to_value(&hotspot)?;
+            json["last_assert"] = json!(last_update.map(|(_, timestamp)| timestamp));
+            json["last_assert_subdao"] = json!(last_update.map(|(subdao, _)| subdao));
+            json["days_since_activity"] =
+                json!(last_update.map(|(_, timestamp)| (Utc::now() - timestamp).num_days()));
+            Ok::<_, Error>(json)
+        })
+        .buffered(5)
+        .try_collect()
+        .await
+}
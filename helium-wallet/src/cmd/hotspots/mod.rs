@@ -1,13 +1,37 @@
 use crate::cmd::*;
+use helium_crypto::KeyTag;
+use helium_lib::{
+    client::{
+        config::iot, IOT_CONFIG_KEY_MAINNET, IOT_CONFIG_URL_MAINNET, ONBOARDING_URL_DEVNET,
+        ONBOARDING_URL_MAINNET,
+    },
+    hotspot::{region, HotspotInfoUpdate},
+};
+use helium_proto::Region;
+use rand::rngs::OsRng;
+use rust_decimal::Decimal;
 
 mod add;
+mod admin;
 mod burn;
+mod buy;
 mod info;
 mod list;
+mod near;
+mod onboarding;
+mod resolve;
 mod rewards;
+mod sell;
 mod transfer;
+mod transfer_all;
 mod update;
+mod update_batch;
 mod updates;
+mod validate_add_txn;
+mod watch;
+mod wizard;
+
+pub use rewards::ThresholdExceeded;
 
 #[derive(Debug, clap::Args)]
 pub struct Cmd {
@@ -25,26 +49,124 @@ impl Cmd {
 /// Commands on Hotspots
 pub enum HotspotCommand {
     Update(update::Cmd),
+    UpdateBatch(update_batch::Cmd),
     Add(Box<add::Cmd>),
+    ValidateAddTxn(validate_add_txn::Cmd),
     List(list::Cmd),
+    Near(near::Cmd),
+    Onboarding(onboarding::Cmd),
     Info(info::Cmd),
+    Resolve(resolve::Cmd),
     Updates(updates::Cmd),
+    Watch(watch::Cmd),
     Rewards(rewards::Cmd),
     Transfer(transfer::Cmd),
+    TransferAll(transfer_all::Cmd),
+    Sell(sell::Cmd),
+    Buy(buy::Cmd),
     Burn(burn::Cmd),
+    Admin(admin::Cmd),
 }
 
 impl HotspotCommand {
     pub async fn run(&self, opts: Opts) -> Result {
         match self {
             Self::Update(cmd) => cmd.run(opts).await,
+            Self::UpdateBatch(cmd) => cmd.run(opts).await,
             Self::Add(cmd) => cmd.run(opts).await,
+            Self::ValidateAddTxn(cmd) => cmd.run(opts).await,
             Self::List(cmd) => cmd.run(opts).await,
+            Self::Near(cmd) => cmd.run(opts).await,
+            Self::Onboarding(cmd) => cmd.run(opts).await,
             Self::Info(cmd) => cmd.run(opts).await,
+            Self::Resolve(cmd) => cmd.run(opts).await,
             Self::Updates(cmd) => cmd.run(opts).await,
+            Self::Watch(cmd) => cmd.run(opts).await,
             Self::Rewards(cmd) => cmd.run(opts).await,
             Self::Transfer(cmd) => cmd.run(opts).await,
+            Self::TransferAll(cmd) => cmd.run(opts).await,
+            Self::Sell(cmd) => cmd.run(opts).await,
+            Self::Buy(cmd) => cmd.run(opts).await,
             Self::Burn(cmd) => cmd.run(opts).await,
+            Self::Admin(cmd) => cmd.run(opts).await,
         }
     }
 }
+
+/// Resolve an onboarding server option to its URL. The shortcuts "m" and "d"
+/// select the default onboarding server for mainnet and devnet.
+fn onboarding_url(value: &str) -> String {
+    match value {
+        "m" | "mainnet-beta" => ONBOARDING_URL_MAINNET,
+        "d" | "devnet" => ONBOARDING_URL_DEVNET,
+        url => url,
+    }
+    .to_string()
+}
+
+fn parse_region(s: &str) -> Result<Region> {
+    Ok(region::region_from_str(s)?)
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct IotConfigOpts {
+    /// URL of the IoT config service to look up asserted regions and their
+    /// maximum EIRP with
+    #[arg(long, default_value = IOT_CONFIG_URL_MAINNET)]
+    iot_config: String,
+    /// Public key the IoT config service signs its responses with
+    #[arg(long, default_value = IOT_CONFIG_KEY_MAINNET)]
+    iot_config_key: helium_crypto::PublicKey,
+}
+
+impl IotConfigOpts {
+    /// A config service client signing its requests with a new key
+    pub fn client(&self) -> Result<iot::Client> {
+        let signer = helium_crypto::Keypair::generate(KeyTag::default(), &mut OsRng);
+        Ok(iot::Client::new(
+            &self.iot_config,
+            self.iot_config_key.clone(),
+            Arc::new(signer),
+        )?)
+    }
+}
+
+/// The given region with its maximum EIRP in dBm, if the config service has
+/// parameters for it
+async fn max_eirp(
+    config: &mut iot::Client,
+    region: Option<Region>,
+) -> Result<Option<(Region, Decimal)>> {
+    let Some(region) = region else {
+        return Ok(None);
+    };
+    Ok(region::max_eirp(config, region)
+        .await?
+        .map(|max_eirp| (region, max_eirp)))
+}
+
+/// Refuse IoT antenna gains outside the range accepted by the IoT subdao or
+/// at or above the maximum EIRP of the region of the Hotspot. The region is
+/// the given one or else the region of the asserted location of the Hotspot.
+async fn check_gain(
+    client: &client::Client,
+    config: &mut iot::Client,
+    gateway: &helium_crypto::PublicKey,
+    update: &HotspotInfoUpdate,
+    region: Option<Region>,
+) -> Result {
+    let HotspotInfoUpdate::Iot {
+        gain: Some(gain), ..
+    } = update
+    else {
+        return Ok(());
+    };
+    let limits = region::gain_limits(client).await?;
+    let region = match region {
+        Some(region) => Some(region),
+        None => config.region(gateway).await?,
+    };
+    let max_eirp = max_eirp(config, region).await?;
+    region::check_gain(*gain, &limits, max_eirp)?;
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use crate::cmd::*;
+use futures::{stream, StreamExt, TryStreamExt};
+use helium_lib::{
+    asset,
+    hotspot::{self, info::LOCATION_RESOLUTION},
+};
+
+#[derive(Clone, Debug, clap::Args)]
+/// Find the Hotspots of all subdaos asserted within a distance of a location.
+///
+/// The search covers the H3 cells around the location at a resolution that
+/// fits the radius, and keeps the Hotspots within the radius. Large radii
+/// search many accounts and may be refused by the RPC provider.
+pub struct Cmd {
+    /// Latitude of the center of the search. For negative values use '=',
+    /// for example: "--lat=-xx.xxxxxxx".
+    #[arg(long)]
+    lat: f64,
+
+    /// Longitude of the center of the search. For negative values use '=',
+    /// for example: "--lon=-xx.xxxxxxx".
+    #[arg(long, alias = "lng")]
+    lon: f64,
+
+    /// Search radius in kilometers
+    #[arg(long, default_value_t = 1.0)]
+    radius_km: f64,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        if self.radius_km.is_nan() || self.radius_km <= 0.0 {
+            bail!("radius must be positive");
+        }
+        let center = h3o::LatLng::new(self.lat, self.lon)?;
+        // The finest resolution whose cells, together with their neighbors,
+        // still cover the radius around any point in the center cell
+        let resolution = (0..=u8::from(LOCATION_RESOLUTION))
+            .rev()
+            .filter_map(|r| h3o::Resolution::try_from(r).ok())
+            .find(|r| r.edge_length_km() >= self.radius_km)
+            .unwrap_or(h3o::Resolution::Zero);
+        let cells: Vec<h3o::CellIndex> = center.to_cell(resolution).grid_disk(1);
+
+        let client = opts.client()?;
+        let mut found = vec![];
+        for cell in cells {
+            for hotspot in hotspot::in_cell(&client, cell, resolution).await? {
+                let Some(location) = hotspot.info.location() else {
+                    continue;
+                };
+                let distance_km = center.distance_km(h3o::LatLng::from(location.location));
+                if distance_km <= self.radius_km {
+                    found.push((distance_km, hotspot));
+                }
+            }
+        }
+        found.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        let hotspots: Vec<serde_json::Value> = stream::iter(found)
+            .map(|(distance_km, found)| {
+                let client = &client;
+                async move {
+                    let asset = asset::get(client, &found.asset).await?;
+                    let hotspot = hotspot::Hotspot::from_asset(asset).await?;
+                    Ok::<_, Error>(json!({
+                        "subdao": found.subdao,
+                        "key": hotspot.key,
+                        "name": hotspot.name,
+                        "asset": hotspot.asset.to_string(),
+                        "owner": hotspot.owner.to_string(),
+                        "distance_km": distance_km,
+                        "info": found.info,
+                    }))
+                }
+            })
+            .buffered(10)
+            .try_collect()
+            .await?;
+
+        print_json(&json!({
+            "lat": self.lat,
+            "lon": self.lon,
+            "radius_km": self.radius_km,
+            "hotspots": hotspots,
+        }))
+    }
+}
@@ -0,0 +1,169 @@
+use crate::cmd::{hotspots::onboarding_url, *};
+use helium_lib::{
+    client::{ONBOARDING_URL_DEVNET, ONBOARDING_URL_MAINNET},
+    onboarding,
+};
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct Cmd {
+    #[command(subcommand)]
+    cmd: OnboardingCommand,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        self.cmd.run(opts).await
+    }
+}
+
+#[derive(Debug, Clone, clap::Subcommand)]
+/// Commands on the onboarding server
+pub enum OnboardingCommand {
+    Record(RecordCmd),
+    List(ListCmd),
+    Create(CreateCmd),
+}
+
+impl OnboardingCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::Record(cmd) => cmd.run(opts).await,
+            Self::List(cmd) => cmd.run(opts).await,
+            Self::Create(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Look up the onboarding record of a Hotspot.
+///
+/// Reports the maker and payer of the Hotspot and when it was registered with
+/// the onboarding server, along with the subdaos it has been onboarded to.
+pub struct RecordCmd {
+    /// Helium address of the Hotspot
+    gateway: helium_crypto::PublicKey,
+
+    /// The onboarding server to query.
+    ///
+    /// Defaults to the onboarding server for the network of the API URL. If
+    /// specified with a shortcut like "m" or "d", the default onboarding
+    /// server for that network will be used.
+    #[arg(long)]
+    onboarding: Option<String>,
+}
+
+/// The url of the given onboarding server option, defaulting to the
+/// onboarding server for the network of the API URL
+pub(super) fn server_url(onboarding: Option<&str>, opts: &Opts) -> String {
+    match onboarding {
+        Some(value) => onboarding_url(value),
+        None if opts.is_devnet() => ONBOARDING_URL_DEVNET.to_string(),
+        None => ONBOARDING_URL_MAINNET.to_string(),
+    }
+}
+
+impl RecordCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let client = opts.client()?;
+        let onboarding_client =
+            onboarding::Client::new(&server_url(self.onboarding.as_deref(), &opts));
+        let record = onboarding::record(&client, &onboarding_client, &self.gateway).await?;
+        print_json(&record)
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// List the onboarding records on the onboarding server.
+pub struct ListCmd {
+    /// Only list the records of the maker with this id
+    #[arg(long)]
+    maker_id: Option<u32>,
+
+    /// Page of records to list, starting at 1
+    #[arg(long, default_value_t = 1)]
+    page: u32,
+
+    /// The onboarding server to query.
+    ///
+    /// Defaults to the onboarding server for the network of the API URL. If
+    /// specified with a shortcut like "m" or "d", the default onboarding
+    /// server for that network will be used.
+    #[arg(long)]
+    onboarding: Option<String>,
+}
+
+impl ListCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let onboarding_client =
+            onboarding::Client::new(&server_url(self.onboarding.as_deref(), &opts));
+        let hotspots = onboarding_client
+            .get_hotspots(self.maker_id, self.page)
+            .await?;
+        print_json(&json!({
+            "page": self.page,
+            "hotspots": hotspots,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+/// Create the onboarding record of a Hotspot for a maker.
+///
+/// The record allows the Hotspot to be added through the onboarding server
+/// with `hotspots add iot --onboarding`, with the maker paying the onboarding
+/// fees. Creating records requires the api key of the maker.
+pub struct CreateCmd {
+    /// Onboarding key of the Hotspot, usually its Helium address
+    onboarding_key: helium_crypto::PublicKey,
+
+    /// Maker api key for the onboarding server
+    #[arg(long, env = "HELIUM_ONBOARDING_API_KEY", hide_env_values = true)]
+    api_key: String,
+
+    /// MAC address of the wlan0 interface of the Hotspot
+    #[arg(long)]
+    mac_wlan0: Option<String>,
+
+    /// MAC address of the eth0 interface of the Hotspot
+    #[arg(long)]
+    mac_eth0: Option<String>,
+
+    /// Serial number of the Raspberry Pi of the Hotspot
+    #[arg(long)]
+    rpi_serial: Option<String>,
+
+    /// Helium serial number of the Hotspot
+    #[arg(long)]
+    helium_serial: Option<String>,
+
+    /// Manufacturing batch of the Hotspot
+    #[arg(long)]
+    batch: Option<String>,
+
+    /// The onboarding server to create the record on.
+    ///
+    /// Defaults to the onboarding server for the network of the API URL. If
+    /// specified with a shortcut like "m" or "d", the default onboarding
+    /// server for that network will be used.
+    #[arg(long)]
+    onboarding: Option<String>,
+}
+
+impl CreateCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let onboarding_client =
+            onboarding::Client::new(&server_url(self.onboarding.as_deref(), &opts))
+                .with_api_key(&self.api_key);
+        let hotspot = onboarding_client
+            .create_hotspot(&onboarding::NewHotspot {
+                onboarding_key: self.onboarding_key.clone(),
+                mac_wlan0: self.mac_wlan0.clone(),
+                mac_eth0: self.mac_eth0.clone(),
+                rpi_serial: self.rpi_serial.clone(),
+                helium_serial: self.helium_serial.clone(),
+                batch: self.batch.clone(),
+            })
+            .await?;
+        print_json(&hotspot)
+    }
+}
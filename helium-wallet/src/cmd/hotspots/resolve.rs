@@ -0,0 +1,54 @@
+use crate::cmd::*;
+use helium_lib::{hotspot, keypair::Pubkey};
+
+#[derive(Clone, Debug, clap::Args)]
+/// Find Hotspots by their animal name, like "tall-cinnamon-dolphin"
+///
+/// By default only the Hotspots of the active wallet are searched.
+pub struct Cmd {
+    /// The animal name of the Hotspot
+    name: String,
+    /// The alternate wallet to search the Hotspots of
+    #[arg(long, conflicts_with = "network")]
+    owner: Option<Pubkey>,
+    /// Search all Hotspots in the network.
+    ///
+    /// This pages through the Hotspot assets, up to --max-pages pages of
+    /// them, and can take a long time
+    #[arg(long)]
+    network: bool,
+    /// Maximum number of pages of Hotspot assets to search with --network
+    #[arg(
+        long,
+        default_value_t = 100,
+        requires = "network",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    max_pages: u32,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let owner = if self.network {
+            None
+        } else if let Some(owner) = self.owner {
+            Some(owner)
+        } else {
+            Some(opts.load_wallet()?.public_key)
+        };
+        let client = opts.client()?;
+        let max_pages = self.network.then_some(self.max_pages);
+        let hotspots = hotspot::for_name(&client, &self.name, owner.as_ref(), max_pages).await?;
+        if hotspots.is_empty() && self.network {
+            bail!(
+                "no hotspot found named {} in the first {} pages of Hotspots, raise --max-pages to search further",
+                self.name,
+                self.max_pages
+            );
+        }
+        if hotspots.is_empty() {
+            bail!("no hotspot found named {}", self.name);
+        }
+        print_json(&hotspots)
+    }
+}
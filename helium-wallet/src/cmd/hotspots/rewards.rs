@@ -1,11 +1,16 @@
-use crate::cmd::*;
+use crate::cmd::{
+    assets::rewards::{check_claim_policy, fee_cap_reached, init_recipients, SplitProfiles},
+    *,
+};
 use client::DasClient;
+use futures::StreamExt;
 use helium_lib::{
     entity_key::{EncodedEntityKey, KeySerialization},
     hotspot,
-    keypair::Pubkey,
+    keypair::{Pubkey, Signer},
     reward,
 };
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone, clap::Args)]
 pub struct Cmd {
@@ -23,7 +28,9 @@ impl Cmd {
 pub enum RewardsCommand {
     Pending(PendingCmd),
     Lifetime(LifetimeCmd),
+    History(HistoryCmd),
     Claim(ClaimCmd),
+    ClaimAll(ClaimAllCmd),
 }
 
 impl RewardsCommand {
@@ -31,7 +38,9 @@ impl RewardsCommand {
         match self {
             Self::Pending(cmd) => cmd.run(opts).await,
             Self::Lifetime(cmd) => cmd.run(opts).await,
+            Self::History(cmd) => cmd.run(opts).await,
             Self::Claim(cmd) => cmd.run(opts).await,
+            Self::ClaimAll(cmd) => cmd.run(opts).await,
         }
     }
 }
@@ -57,6 +66,10 @@ async fn collect_hotspots<C: AsRef<DasClient>>(
 
 #[derive(Clone, Debug, clap::Args)]
 /// List pending rewards for given Hotspots
+///
+/// With --threshold the command exits with status 2 when the pending rewards
+/// of any Hotspot exceed the threshold, so it can be used for alerting from
+/// cron. Add --quiet to only print a line per Hotspot over the threshold.
 pub struct PendingCmd {
     /// Token for command
     token: reward::ClaimableToken,
@@ -65,10 +78,73 @@ pub struct PendingCmd {
     /// Wallet to look up hotspots for
     #[arg(long)]
     owner: Option<Pubkey>,
+    /// Pending amount to alert on, for example "5" or "5hnt". A token suffix
+    /// has to match the token of the command
+    #[arg(long, value_parser = parse_threshold)]
+    threshold: Option<Threshold>,
+    /// Only print the Hotspots over the threshold, as "name key amount"
+    #[arg(long, requires = "threshold")]
+    quiet: bool,
+}
+
+/// The pending rewards of Hotspots exceed the alert threshold. The wallet
+/// exits with [`ThresholdExceeded::EXIT_STATUS`] on this error.
+#[derive(Debug)]
+pub struct ThresholdExceeded {
+    pub hotspots: usize,
+    pub threshold: f64,
+}
+
+impl ThresholdExceeded {
+    pub const EXIT_STATUS: i32 = 2;
+}
+
+impl std::fmt::Display for ThresholdExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pending rewards of {} Hotspot(s) exceed the threshold of {}",
+            self.hotspots, self.threshold
+        )
+    }
+}
+
+impl std::error::Error for ThresholdExceeded {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Threshold {
+    amount: f64,
+    token: Option<reward::ClaimableToken>,
+}
+
+fn parse_threshold(s: &str) -> Result<Threshold> {
+    use clap::ValueEnum;
+    let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let (amount, token) = s.split_at(split);
+    let amount: f64 = amount
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid threshold amount: {s}"))?;
+    if !amount.is_finite() || amount < 0.0 {
+        bail!("invalid threshold amount: {s}");
+    }
+    let token = match token.trim() {
+        "" => None,
+        token => Some(
+            reward::ClaimableToken::from_str(token, true)
+                .map_err(|_| anyhow!("invalid threshold token: {token}"))?,
+        ),
+    };
+    Ok(Threshold { amount, token })
 }
 
 impl PendingCmd {
     pub async fn run(&self, opts: Opts) -> Result {
+        if let Some(token) = self.threshold.and_then(|threshold| threshold.token) {
+            if token != self.token {
+                bail!("threshold token does not match the token of the command");
+            }
+        }
         let client = opts.client()?;
         let wallet = opts.load_wallet()?;
         let hotspots = collect_hotspots(
@@ -86,7 +162,33 @@ impl PendingCmd {
         )
         .await?;
 
-        print_json(&pending)
+        let Some(threshold) = self.threshold else {
+            return print_json(&pending);
+        };
+        let mut exceeded: Vec<(&helium_crypto::PublicKey, f64)> = hotspots
+            .iter()
+            .filter_map(|hotspot| {
+                let reward = pending.get(&hotspot.to_string())?;
+                let amount = f64::from(&reward.reward);
+                (amount > threshold.amount).then_some((hotspot, amount))
+            })
+            .collect();
+        exceeded.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        if self.quiet {
+            for (hotspot, amount) in &exceeded {
+                println!("{} {hotspot} {amount}", hotspot::name(hotspot));
+            }
+        } else {
+            print_json(&pending)?;
+        }
+        if !exceeded.is_empty() {
+            return Err(ThresholdExceeded {
+                hotspots: exceeded.len(),
+                threshold: threshold.amount,
+            }
+            .into());
+        }
+        Ok(())
     }
 }
 
@@ -122,7 +224,69 @@ impl LifetimeCmd {
 }
 
 #[derive(Clone, Debug, clap::Args)]
-/// Claim rewards for one or all Hotspots in a wallet
+/// Report the rewards distributed to a Hotspot per epoch as CSV
+///
+/// Rewards are reconstructed from the on-chain reward distributions of the
+/// Hotspot. Rewards accumulate until claimed, so each row covers the rewards
+/// earned since the previous claim and is reported in the epoch of the claim.
+/// Unclaimed rewards are not included, see the `pending` command for those.
+pub struct HistoryCmd {
+    /// Hotspot to report rewards for
+    hotspot: helium_crypto::PublicKey,
+    /// Number of most recent epochs to report
+    #[arg(long, default_value_t = 30)]
+    epochs: u64,
+    /// Only report rewards for the given token
+    #[arg(long)]
+    token: Option<reward::ClaimableToken>,
+    /// File to write the CSV report to instead of stdout
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+impl HistoryCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        use std::io::Write;
+        let client = opts.client()?;
+        let tokens = match self.token {
+            Some(token) => vec![token],
+            None => vec![
+                reward::ClaimableToken::Hnt,
+                reward::ClaimableToken::Iot,
+                reward::ClaimableToken::Mobile,
+            ],
+        };
+        let mut rows = vec![];
+        for token in tokens {
+            rows.extend(
+                reward::history::for_entity_key(&client, token, &self.hotspot, self.epochs).await?,
+            );
+        }
+        rows.sort_by_key(|row| row.timestamp);
+
+        let mut writer: Box<dyn io::Write> = match &self.out {
+            Some(path) => Box::new(io::BufWriter::new(open_output_file(path, false)?)),
+            None => Box::new(io::stdout().lock()),
+        };
+        writeln!(writer, "epoch,timestamp,token,amount,signature")?;
+        for row in rows {
+            writeln!(
+                writer,
+                "{},{},{},{},{}",
+                row.epoch,
+                row.timestamp.to_rfc3339(),
+                row.amount.token,
+                f64::from(&row.amount),
+                row.signature
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, clap::Args)]
+/// Claim rewards for a Hotspot
 pub struct ClaimCmd {
     /// Token for command
     token: reward::ClaimableToken,
@@ -133,6 +297,13 @@ pub struct ClaimCmd {
     /// If not specific the full pending amount is claimed, limited by the maximum
     /// claim amount for the subdao
     pub amount: Option<f64>,
+    /// Simulate the claim and report the expected token changes, compute
+    /// units and fees before asking to commit it
+    #[arg(long)]
+    simulate_detailed: bool,
+    /// Ignore the saved reward split for the Hotspot and keep the full claim
+    #[arg(long)]
+    no_split: bool,
     /// Commit the claim transaction.
     #[command(flatten)]
     commit: CommitOpts,
@@ -144,6 +315,9 @@ impl From<&ClaimCmd> for crate::cmd::assets::rewards::ClaimCmd {
             token: value.token,
             entity_key: EncodedEntityKey::from(&value.hotspot),
             amount: value.amount,
+            simulate_detailed: value.simulate_detailed,
+            payer_only: false,
+            no_split: value.no_split,
             commit: value.commit.clone(),
         }
     }
@@ -156,9 +330,201 @@ impl ClaimCmd {
     }
 }
 
+#[derive(Clone, Debug, clap::Args)]
+/// Claim the pending rewards of every Hotspot in the wallet
+///
+/// Finds the Hotspots owned by the wallet and their pending rewards, then
+/// initializes the missing reward recipients in batches and claims the
+/// rewards of each Hotspot, largest first. Hotspots whose recipient failed to
+/// initialize are not claimed. Without --commit the claims include the
+/// inits instead. Saved reward splits are applied.
+/// A failed claim is reported and does not stop the remaining claims, the
+/// claims do stop at the first one that would exceed the maximum total fee,
+/// if one is given.
+pub struct ClaimAllCmd {
+    /// Tokens to claim, all of them by default
+    #[arg(long = "token", value_delimiter = ',')]
+    tokens: Vec<reward::ClaimableToken>,
+    /// Commit the claim transactions.
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+/// Claims made for a token
+#[derive(Debug, Default, serde::Serialize)]
+struct ClaimSummary {
+    sent: usize,
+    failed: usize,
+    /// Total pending rewards of the sent claims
+    amount: f64,
+}
+
+impl ClaimAllCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let wallet = keypair.pubkey();
+        let client = opts.client()?;
+        let transaction_opts = self.commit.transaction_opts(&client);
+        let tokens = if self.tokens.is_empty() {
+            vec![
+                reward::ClaimableToken::Hnt,
+                reward::ClaimableToken::Iot,
+                reward::ClaimableToken::Mobile,
+            ]
+        } else {
+            self.tokens.clone()
+        };
+
+        let rewards = reward::pending_for_owner(&client, &wallet, &tokens).await?;
+        let mut initialized = vec![];
+        let mut claims = vec![];
+        let mut summary: HashMap<reward::ClaimableToken, ClaimSummary> = HashMap::new();
+        let mut missing_recipients = HashSet::new();
+        let report = |initialized, claims, summary| {
+            print_json(&json!({
+                "initialized": initialized,
+                "claims": claims,
+                "summary": summary,
+            }))
+        };
+
+        for token in &tokens {
+            let hotspots: Vec<helium_crypto::PublicKey> = rewards
+                .iter()
+                .filter(|reward| reward.token == *token)
+                .map(|reward| reward.hotspot.clone())
+                .collect();
+            if hotspots.is_empty() {
+                continue;
+            }
+            let inits = init_recipients(
+                &client,
+                *token,
+                &hotspots,
+                &keypair,
+                &self.commit,
+                &transaction_opts,
+            )
+            .await?;
+            for (indices, response) in inits.batches {
+                let mut json = response.to_json();
+                json["token"] = json!(token);
+                json["hotspots"] = indices
+                    .iter()
+                    .map(|index| hotspots[*index].to_string())
+                    .collect::<Vec<_>>()
+                    .into();
+                initialized.push(json);
+            }
+            if inits.fee_cap_reached {
+                fee_cap_reached(0, rewards.len());
+                return report(initialized, claims, summary);
+            }
+            missing_recipients.extend(
+                inits
+                    .missing
+                    .into_iter()
+                    .map(|index| (*token, hotspots[index].to_string())),
+            );
+        }
+
+        // Claims whose recipient failed to initialize are not built, they
+        // would pay for the init again
+        let (rewards, uninitialized): (Vec<_>, Vec<_>) = rewards.into_iter().partition(|reward| {
+            !missing_recipients.contains(&(reward.token, reward.hotspot.to_string()))
+        });
+        for reward in uninitialized {
+            summary.entry(reward.token).or_default().failed += 1;
+            claims.push(json!({
+                "result": "error",
+                "error": "reward recipient was not initialized",
+                "token": reward.token,
+                "hotspot": reward.hotspot.to_string(),
+                "name": reward.name,
+                "pending": reward.pending,
+            }));
+        }
+
+        let splits = SplitProfiles::load()?;
+        let mut stream = std::pin::pin!(reward::claim_for_owner(
+            &client,
+            &rewards,
+            |hotspot| splits.get(&hotspot.to_string()),
+            &keypair,
+            None,
+            &transaction_opts,
+        ));
+        while let Some((reward, claim)) = stream.next().await {
+            let response = match claim {
+                Ok(Some((tx, _))) => {
+                    let checked = check_claim_policy(
+                        &client,
+                        reward.token,
+                        &EncodedEntityKey::from(&reward.hotspot),
+                        &splits.get(&reward.hotspot.to_string()),
+                        &wallet,
+                    );
+                    match checked.await {
+                        Ok(()) => self.commit.maybe_commit(tx, &client).await,
+                        Err(err) => Err(err),
+                    }
+                }
+                Ok(None) => continue,
+                Err(err) => Err(err.into()),
+            };
+            if FeeCapExceeded::is_cause(&response) {
+                fee_cap_reached(claims.len(), rewards.len());
+                break;
+            }
+            let token_summary = summary.entry(reward.token).or_default();
+            if response.is_ok() {
+                token_summary.sent += 1;
+                token_summary.amount += f64::from(&reward.pending);
+            } else {
+                token_summary.failed += 1;
+            }
+            let mut json = response.to_json();
+            json["token"] = json!(reward.token);
+            json["hotspot"] = reward.hotspot.to_string().into();
+            json["name"] = reward.name.clone().into();
+            json["pending"] = serde_json::to_value(reward.pending)?;
+            claims.push(json);
+        }
+
+        report(initialized, claims, summary)
+    }
+}
+
 fn hotspots_to_entity_key_strings(public_keys: &[helium_crypto::PublicKey]) -> Vec<String> {
     public_keys
         .iter()
         .map(|key| key.to_string())
         .collect::<Vec<String>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threshold() {
+        assert_eq!(
+            parse_threshold("5hnt").expect("threshold"),
+            Threshold {
+                amount: 5.0,
+                token: Some(reward::ClaimableToken::Hnt)
+            }
+        );
+        assert_eq!(
+            parse_threshold("0.5 IOT").expect("threshold"),
+            Threshold {
+                amount: 0.5,
+                token: Some(reward::ClaimableToken::Iot)
+            }
+        );
+        assert_eq!(parse_threshold("12").expect("threshold").token, None);
+        assert!(parse_threshold("5sol").is_err());
+        assert!(parse_threshold("-1").is_err());
+        assert!(parse_threshold("hnt").is_err());
+    }
+}
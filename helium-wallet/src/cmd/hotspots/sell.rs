@@ -0,0 +1,82 @@
+use crate::cmd::*;
+use helium_lib::{
+    hotspot::escrow,
+    keypair::{Pubkey, Signer},
+    token::{Token, TokenAmount},
+};
+
+#[derive(Clone, Debug, clap::Args)]
+/// Offer a Hotspot for sale to a given buyer.
+///
+/// Writes an offer with a transaction that transfers the Hotspot to the buyer
+/// and pays the price to this wallet in the same transaction, signed by this
+/// wallet. The buyer accepts the offer with the "hotspots buy" command, which
+/// checks the transaction and submits it. Neither side has to go first.
+///
+/// The buyer pays the transaction fees. Offers expire with the blockhash of
+/// their transaction, so the buyer has to accept the offer within a minute or
+/// two of it being written.
+pub struct Cmd {
+    /// Key of Hotspot to sell
+    address: helium_crypto::PublicKey,
+    /// Solana address of the buyer
+    #[arg(long)]
+    buyer: Pubkey,
+    /// Price of the Hotspot
+    #[arg(long)]
+    price: f64,
+    /// Token the price is paid in
+    #[arg(long, default_value_t = Token::Hnt, value_parser = parse_sale_token)]
+    token: Token,
+    /// File to write the offer to
+    #[arg(long)]
+    out: PathBuf,
+    /// Overwrite an existing offer file
+    #[arg(long)]
+    force: bool,
+    /// Priority fee options for the sale transaction. The offer is never
+    /// submitted by this command.
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+fn parse_sale_token(s: &str) -> Result<Token> {
+    let token: Token = s.parse()?;
+    if !escrow::SALE_TOKENS.contains(&token) {
+        bail!("hotspots can only be sold for hnt or usdc");
+    }
+    Ok(token)
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        if self.price <= 0.0 {
+            bail!("price must be positive");
+        }
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        if keypair.pubkey() == self.buyer {
+            bail!("buyer already owner of hotspot");
+        }
+        let client = opts.client()?;
+        let transaction_opts = self.commit.transaction_opts(&client);
+        let offer = escrow::offer(
+            &client,
+            &self.address,
+            &self.buyer,
+            TokenAmount::from_f64(self.token, self.price),
+            &keypair,
+            &transaction_opts,
+        )
+        .await?;
+        let file = open_output_file(&self.out, !self.force)?;
+        serde_json::to_writer_pretty(file, &offer)?;
+
+        print_json(&json!({
+            "out": self.out,
+            "hotspot": offer.hotspot,
+            "buyer": offer.buyer.to_string(),
+            "price": offer.price(),
+            "last_valid_block_height": offer.last_valid_block_height,
+        }))
+    }
+}
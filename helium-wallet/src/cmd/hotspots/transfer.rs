@@ -1,8 +1,5 @@
-use crate::cmd::*;
-use helium_lib::{
-    hotspot,
-    keypair::{Pubkey, Signer},
-};
+use crate::{cmd::*, policy};
+use helium_lib::{hotspot, keypair::Pubkey};
 
 #[derive(Clone, Debug, clap::Args)]
 /// Transfer a Hotspot to another owner
@@ -18,21 +15,23 @@ pub struct Cmd {
 
 impl Cmd {
     pub async fn run(&self, opts: Opts) -> Result {
-        let password = get_wallet_password(false)?;
-        let keypair = opts.load_keypair(password.as_bytes())?;
-        if keypair.pubkey() == self.recipient {
+        let signer = opts.load_signer(&self.commit)?;
+        if signer.pubkey() == self.recipient {
             bail!("recipient already owner of hotspot");
         }
+        policy::check(&self.recipient, "hotspot recipient")?;
         let client = opts.client()?;
         let transaction_opts = self.commit.transaction_opts(&client);
-        let (tx, _) = hotspot::transfer(
-            &client,
-            &self.address,
-            &self.recipient,
-            &keypair,
-            &transaction_opts,
+        let (msg, _) =
+            hotspot::transfer_message(&client, &self.address, &self.recipient, &transaction_opts)
+                .await?;
+        let tx = signer.sign(msg)?;
+        print_json(
+            &self
+                .commit
+                .maybe_commit_with_signer(tx, &client, &signer)
+                .await?
+                .to_json(),
         )
-        .await?;
-        print_json(&self.commit.maybe_commit(tx, &client).await?.to_json())
     }
 }
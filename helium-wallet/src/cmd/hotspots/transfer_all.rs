@@ -0,0 +1,164 @@
+use crate::{cmd::*, policy};
+use helium_lib::{
+    asset, hotspot,
+    keypair::{Pubkey, Signer},
+};
+
+#[derive(Clone, Debug, clap::Args)]
+/// Transfer all Hotspots of the wallet to another owner.
+///
+/// The transfers are packed into as few transactions as possible. The
+/// result of every Hotspot is reported, a failed transaction does not stop
+/// the other transfers. With a maximum total fee, the Hotspots after the
+/// first transaction that would exceed it are skipped.
+pub struct Cmd {
+    /// Solana address of the recipient of the Hotspots
+    #[arg(long)]
+    to: Pubkey,
+    /// Transfer only the Hotspots listed in the given file, one key per
+    /// line. Blank lines and lines starting with '#' are ignored
+    #[arg(long)]
+    from_file: Option<PathBuf>,
+    /// Commit the transfers
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let wallet = keypair.pubkey();
+        if wallet == self.to {
+            bail!("recipient already owner of the hotspots");
+        }
+        policy::check(&self.to, "hotspot recipient")?;
+        let client = opts.client()?;
+        let txn_opts = self.commit.transaction_opts(&client);
+
+        // Every listed Hotspot, with the Hotspot found for it or the reason
+        // it can not be transferred
+        let listed: Vec<(String, std::result::Result<hotspot::Hotspot, String>)> =
+            match &self.from_file {
+                Some(path) => {
+                    let mut listed = vec![];
+                    for key in read_keys(&fs::read_to_string(path)?) {
+                        let hotspot = match key.parse::<helium_crypto::PublicKey>() {
+                            Ok(key) => hotspot::get(&client, &key)
+                                .await
+                                .map_err(|err| err.to_string())
+                                .and_then(|hotspot| {
+                                    if hotspot.owner == wallet {
+                                        Ok(hotspot)
+                                    } else {
+                                        Err(format!("owned by {}", hotspot.owner))
+                                    }
+                                }),
+                            Err(_) => Err("invalid hotspot key".to_string()),
+                        };
+                        listed.push((key, hotspot));
+                    }
+                    listed
+                }
+                None => hotspot::for_owner(&client, &wallet)
+                    .await?
+                    .into_iter()
+                    .map(|hotspot| (hotspot.key.to_string(), Ok(hotspot)))
+                    .collect(),
+            };
+
+        let (indexes, assets): (Vec<usize>, Vec<Pubkey>) = listed
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (_, hotspot))| {
+                hotspot.as_ref().ok().map(|hotspot| (index, hotspot.asset))
+            })
+            .unzip();
+        let mut results: Vec<Option<serde_json::Value>> = listed
+            .iter()
+            .map(|(_, hotspot)| {
+                hotspot.as_ref().err().map(
+                    |err| json!({"status": "failed", "result": {"result": "error", "error": err}}),
+                )
+            })
+            .collect();
+
+        let batches =
+            asset::transfer_batches(&client, &assets, &wallet, &self.to, &txn_opts).await?;
+        let mut fee_cap_reached = false;
+        for (transaction, batch) in batches.iter().enumerate() {
+            let commit = if fee_cap_reached {
+                None
+            } else {
+                let commit = match batch.message(&client, &wallet, &txn_opts).await {
+                    Ok((msg, _)) => match VersionedTransaction::try_new(msg, &[&*keypair]) {
+                        Ok(tx) => {
+                            self.commit
+                                .maybe_commit_with_keypair(tx, &client, &keypair)
+                                .await
+                        }
+                        Err(err) => Err(err.into()),
+                    },
+                    Err(err) => Err(err.into()),
+                };
+                if FeeCapExceeded::is_cause(&commit) {
+                    warnings::warn(
+                        warnings::FEE_CAP_REACHED,
+                        format!(
+                            "maximum total fee reached, skipped transfers from hotspot {}",
+                            listed[indexes[batch.items[0]]].0
+                        ),
+                    );
+                    fee_cap_reached = true;
+                    None
+                } else {
+                    Some(commit)
+                }
+            };
+            let (status, result) = match &commit {
+                None => ("skipped", serde_json::Value::Null),
+                Some(commit) if commit.is_ok() => ("sent", commit.to_json()),
+                Some(commit) => ("failed", commit.to_json()),
+            };
+            for index in &batch.items {
+                results[indexes[*index]] = Some(json!({
+                    "status": status,
+                    "transaction": transaction,
+                    "result": result,
+                }));
+            }
+        }
+
+        let report: Vec<serde_json::Value> = listed
+            .iter()
+            .zip(results)
+            .map(|((key, hotspot), result)| {
+                let mut entry = json!({ "key": key });
+                if let Ok(hotspot) = hotspot {
+                    entry["name"] = hotspot.name.clone().into();
+                    entry["asset"] = hotspot.asset.to_string().into();
+                }
+                if let (Some(entry), Some(serde_json::Value::Object(result))) =
+                    (entry.as_object_mut(), result)
+                {
+                    entry.extend(result);
+                }
+                entry
+            })
+            .collect();
+        print_json(&json!({
+            "recipient": self.to.to_string(),
+            "transactions": batches.len(),
+            "hotspots": report,
+        }))
+    }
+}
+
+/// The Hotspot keys listed in a file, one per line
+fn read_keys(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
@@ -1,9 +1,13 @@
-use crate::cmd::*;
+use crate::cmd::{
+    hotspots::{check_gain, onboarding_url, parse_region, wizard, IotConfigOpts},
+    *,
+};
 use helium_lib::{
-    client::{ONBOARDING_URL_DEVNET, ONBOARDING_URL_MAINNET},
     dao::SubDao,
     hotspot::{self, HotspotInfoUpdate},
+    onboarding,
 };
+use helium_proto::Region;
 
 #[derive(Debug, Clone, clap::Args)]
 /// Assert a Hotspot location on the blockchain.
@@ -13,10 +17,24 @@ use helium_lib::{
 /// onboarding key to get the transaction signed by the DeWi staking server.
 pub struct Cmd {
     /// The subdao to assert the Hotspot on
-    subdao: SubDao,
+    #[arg(required_unless_present = "interactive")]
+    subdao: Option<SubDao>,
 
     /// Helium address of Hotspot to assert
-    gateway: helium_crypto::PublicKey,
+    #[arg(required_unless_present = "interactive")]
+    gateway: Option<helium_crypto::PublicKey>,
+
+    /// Pick a Hotspot of the wallet and enter the update step by step.
+    ///
+    /// The expected cost of the update is shown before asking to submit it,
+    /// so the commit option is not needed. A location entered as a street
+    /// address is looked up with the public OpenStreetMap geocoding service,
+    /// after asking whether to send the address there.
+    #[arg(
+        long,
+        conflicts_with_all = ["subdao", "gateway", "lat", "lon", "gain", "elevation", "region"]
+    )]
+    interactive: bool,
 
     /// Latitude of Hotspot location to assert.
     ///
@@ -47,6 +65,17 @@ pub struct Cmd {
     #[arg(long)]
     elevation: Option<i32>,
 
+    /// The LoRa region of the asserted location, like "US915".
+    ///
+    /// The antenna gain is checked against the maximum EIRP for the region.
+    /// Defaults to the region of the currently asserted location as known to
+    /// the IoT config service.
+    #[arg(long, value_parser = parse_region)]
+    region: Option<Region>,
+
+    #[command(flatten)]
+    iot_config: IotConfigOpts,
+
     /// The onboarding server to use for asserting the hotspot.
     ///
     /// If the API URL is specified with a shortcut like "m" or "d", the
@@ -54,6 +83,11 @@ pub struct Cmd {
     #[arg(long)]
     onboarding: Option<String>,
 
+    /// Update a Hotspot owned by a multisig vault. Not supported with an
+    /// onboarding server or --interactive
+    #[command(flatten)]
+    multisig: MultisigOpts,
+
     /// Commit the assertion.
     #[command(flatten)]
     commit: CommitOpts,
@@ -61,35 +95,79 @@ pub struct Cmd {
 
 impl Cmd {
     pub async fn run(&self, opts: Opts) -> Result {
-        let password = get_wallet_password(false)?;
-        let keypair = opts.load_keypair(password.as_bytes())?;
-
-        let server = self.onboarding.as_ref().map(|value| {
-            match value.as_str() {
-                "m" | "mainnet-beta" => ONBOARDING_URL_MAINNET,
-                "d" | "devnet" => ONBOARDING_URL_DEVNET,
-                url => url,
-            }
-            .to_string()
-        });
+        let signer = opts.load_signer(&self.commit)?;
+        let wallet = signer.pubkey();
+        let multisig = self.multisig.resolve(&wallet)?;
+        if multisig.multisig.is_some() && (self.onboarding.is_some() || self.interactive) {
+            bail!("multisig updates are not supported with an onboarding server or --interactive");
+        }
 
-        let update = HotspotInfoUpdate::for_subdao(self.subdao)
-            .set_gain(self.gain)
-            .set_elevation(self.elevation)
-            .set_geo(self.lat, self.lon)?;
+        let server = self.onboarding.as_deref().map(onboarding_url);
 
         let client = opts.client()?;
+        let mut config = self.iot_config.client()?;
+        let (gateway, update, region) = if self.interactive {
+            let answers = wizard::run(&client, &mut config, &wallet).await?;
+            (answers.gateway, answers.update, answers.region)
+        } else {
+            let (Some(subdao), Some(gateway)) = (self.subdao, self.gateway.clone()) else {
+                bail!("a subdao and gateway are required");
+            };
+            let update = HotspotInfoUpdate::for_subdao(subdao)
+                .set_gain(self.gain)
+                .set_elevation(self.elevation)
+                .set_geo(self.lat, self.lon)?;
+            (gateway, update, self.region)
+        };
+
+        check_gain(&client, &mut config, &gateway, &update, region).await?;
+        if let Some(server) = &server {
+            let onboarding_client = onboarding::Client::new(server);
+            onboarding::preflight(&client, &onboarding_client, &gateway, &update).await?;
+        }
         let transaction_opts = self.commit.transaction_opts(&client);
-        let tx = hotspot::update(
-            &client,
-            server,
-            &self.gateway,
-            update,
-            &keypair,
-            &transaction_opts,
-        )
-        .await?;
-
-        print_json(&self.commit.maybe_commit(tx, &client).await.to_json())
+        let tx = match server {
+            Some(server) => {
+                hotspot::update(
+                    &client,
+                    Some(server),
+                    &gateway,
+                    update,
+                    &signer.keypair()?,
+                    &transaction_opts,
+                )
+                .await?
+            }
+            None => {
+                let owner = multisig.authority(&wallet);
+                let (msg, _) = hotspot::direct_update_message(
+                    &client,
+                    &gateway,
+                    update,
+                    &owner,
+                    &transaction_opts,
+                )
+                .await?;
+                let (tx, proposal) = multisig
+                    .sign(&client, msg, &signer, &transaction_opts)
+                    .await?;
+                if proposal.is_some() {
+                    let response = self.commit.maybe_commit(tx, &client).await;
+                    return print_json(&with_proposal(response.to_json(), proposal)?);
+                }
+                tx
+            }
+        };
+
+        if !self.interactive {
+            return print_json(&self.commit.maybe_commit(tx, &client).await.to_json());
+        }
+        let success = print_detailed_simulation(&client, &[tx.clone()]).await?;
+        if !success || !confirm("Submit the update?")? {
+            return Ok(());
+        }
+        let mut commit = self.commit.clone();
+        commit.commit = true;
+        print_json(&commit.maybe_commit(tx, &client).await.to_json())
     }
 }
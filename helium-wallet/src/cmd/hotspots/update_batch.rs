@@ -0,0 +1,136 @@
+use crate::cmd::{
+    hotspots::{check_gain, onboarding_url, parse_region, IotConfigOpts},
+    *,
+};
+use helium_lib::{
+    client::config::iot,
+    dao::SubDao,
+    hotspot::{self, HotspotInfoUpdate},
+};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, clap::Args)]
+/// Update the location, gain or elevation of many Hotspots.
+///
+/// The input is a JSON array of updates, each with a "subdao", a "gateway"
+/// and any of "lat", "lon", "gain", "elevation" and "region", which work like
+/// the options of the update command.
+///
+/// Updates that are already reflected on-chain are skipped, so a batch that
+/// was only partially confirmed can be run again to complete it. Failed
+/// updates do not stop the batch and are reported with their error. With a
+/// maximum total fee the batch stops at the first update that would exceed
+/// it.
+pub struct Cmd {
+    /// JSON file with the updates to apply
+    input: PathBuf,
+
+    /// The onboarding server to use for asserting the hotspots.
+    ///
+    /// If the API URL is specified with a shortcut like "m" or "d", the
+    /// default onboarding server for that network will be used.
+    #[arg(long)]
+    onboarding: Option<String>,
+
+    #[command(flatten)]
+    iot_config: IotConfigOpts,
+
+    /// Commit the updates.
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchUpdate {
+    subdao: SubDao,
+    gateway: String,
+    lat: Option<f64>,
+    lon: Option<f64>,
+    gain: Option<f64>,
+    elevation: Option<i32>,
+    region: Option<String>,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let updates: Vec<BatchUpdate> = serde_json::from_reader(fs::File::open(&self.input)?)?;
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+
+        let server = self.onboarding.as_deref().map(onboarding_url);
+
+        let client = opts.client()?;
+        let mut config = self.iot_config.client()?;
+        let transaction_opts = self.commit.transaction_opts(&client);
+        let mut results = vec![];
+        for entry in &updates {
+            let result = self
+                .apply(
+                    &client,
+                    &mut config,
+                    entry,
+                    server.clone(),
+                    &keypair,
+                    &transaction_opts,
+                )
+                .await;
+            if matches!(&result, Ok(Some(commit)) if FeeCapExceeded::is_cause(commit)) {
+                warnings::warn(
+                    warnings::FEE_CAP_REACHED,
+                    format!(
+                        "maximum total fee reached, stopped after {} of {} updates",
+                        results.len(),
+                        updates.len()
+                    ),
+                );
+                break;
+            }
+            let (status, result) = match result {
+                Ok(None) => ("applied", json!({"result": "ok"})),
+                Ok(Some(commit)) if commit.is_ok() => ("updated", commit.to_json()),
+                Ok(Some(commit)) => ("failed", commit.to_json()),
+                Err(err) => (
+                    "failed",
+                    json!({"result": "error", "error": err.to_string()}),
+                ),
+            };
+            results.push(json!({
+                "gateway": entry.gateway,
+                "subdao": entry.subdao,
+                "status": status,
+                "result": result,
+            }));
+        }
+
+        print_json(&results)
+    }
+
+    /// Apply a single update, returning None if it is already reflected
+    /// on-chain
+    async fn apply(
+        &self,
+        client: &client::Client,
+        config: &mut iot::Client,
+        entry: &BatchUpdate,
+        server: Option<String>,
+        keypair: &Keypair,
+        transaction_opts: &helium_lib::TransactionOpts,
+    ) -> Result<Option<Result<CommitResponse>>> {
+        let gateway: helium_crypto::PublicKey = entry.gateway.parse()?;
+        let update = HotspotInfoUpdate::for_subdao(entry.subdao)
+            .set_gain(entry.gain)
+            .set_elevation(entry.elevation)
+            .set_geo(entry.lat, entry.lon)?;
+        let region = entry.region.as_deref().map(parse_region).transpose()?;
+
+        let info_key = entry.subdao.info_key(&gateway);
+        let info = hotspot::info::get(client, entry.subdao, &info_key).await?;
+        if info.is_some_and(|info| update.is_applied(&info)) {
+            return Ok(None);
+        }
+
+        check_gain(client, config, &gateway, &update, region).await?;
+        let tx =
+            hotspot::update(client, server, &gateway, update, keypair, transaction_opts).await?;
+        Ok(Some(self.commit.maybe_commit(tx, client).await))
+    }
+}
@@ -1,4 +1,5 @@
 use crate::cmd::*;
+use futures::TryStreamExt;
 use helium_lib::{dao::SubDao, hotspot, keypair::Signature};
 
 #[derive(Clone, Debug, clap::Args)]
@@ -17,6 +18,9 @@ pub struct Cmd {
     /// The signature to look backwards up to
     #[arg(long)]
     until: Option<Signature>,
+
+    #[command(flatten)]
+    output: OutputOpts,
 }
 
 impl Cmd {
@@ -28,7 +32,16 @@ impl Cmd {
             ..Default::default()
         };
         let info_key = self.subdao.info_key(&self.address);
+        let mut sink = self.output.sink()?;
+        if sink.is_ndjson() {
+            let mut updates =
+                std::pin::pin!(hotspot::info::updates_stream(&client, &info_key, params).await?);
+            while let Some(update) = updates.try_next().await? {
+                sink.write_items([update])?;
+            }
+            return Ok(());
+        }
         let txns = hotspot::info::updates(&client, &info_key, params).await?;
-        print_json(&txns)
+        sink.write_json(&txns)
     }
 }
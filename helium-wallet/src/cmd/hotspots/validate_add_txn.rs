@@ -0,0 +1,170 @@
+use crate::{cmd::*, txn_envelope::TxnEnvelope};
+use helium_crypto::{PublicKey, Verify};
+use helium_lib::{asset, dao::SubDao, hotspot, keypair};
+use helium_proto::{BlockchainTxnAddGatewayV1, Message};
+use serde::Serialize;
+
+#[derive(Clone, Debug, clap::Args)]
+/// Check an add Hotspot transaction before it is submitted.
+///
+/// Decodes the transaction, checks the signatures it carries and whether the
+/// Hotspot was already issued or onboarded, and reports the maker and fee
+/// fields. Nothing is signed or submitted. Exits with an error when the
+/// transaction would not be accepted.
+pub struct Cmd {
+    /// Base64 encoded Hotspot transaction
+    txn: Transaction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum SignatureStatus {
+    Valid,
+    Invalid,
+    Missing,
+}
+
+/// Check a signature of the transaction. All signatures are made over the
+/// transaction with the signature fields cleared.
+fn signature_status(
+    txn: &BlockchainTxnAddGatewayV1,
+    key: &PublicKey,
+    signature: &[u8],
+) -> SignatureStatus {
+    if signature.is_empty() {
+        return SignatureStatus::Missing;
+    }
+    let unsigned = BlockchainTxnAddGatewayV1 {
+        gateway_signature: vec![],
+        owner_signature: vec![],
+        payer_signature: vec![],
+        ..txn.clone()
+    };
+    match key.verify(&unsigned.encode_to_vec(), signature) {
+        Ok(()) => SignatureStatus::Valid,
+        Err(_) => SignatureStatus::Invalid,
+    }
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let txn = BlockchainTxnAddGatewayV1::from_envelope(&self.txn)?;
+        let gateway = PublicKey::from_bytes(&txn.gateway)
+            .map_err(|_| anyhow!("transaction has no valid gateway key"))?;
+        let mut problems = vec![];
+
+        let gateway_signature = signature_status(&txn, &gateway, &txn.gateway_signature);
+        match gateway_signature {
+            SignatureStatus::Valid => (),
+            SignatureStatus::Invalid => problems.push("gateway signature is invalid".to_string()),
+            SignatureStatus::Missing => problems.push("gateway signature is missing".to_string()),
+        }
+        let mut signers = serde_json::Map::new();
+        for (field, key, signature) in [
+            ("owner", &txn.owner, &txn.owner_signature),
+            ("payer", &txn.payer, &txn.payer_signature),
+        ] {
+            let json = match PublicKey::from_bytes(key) {
+                Ok(key) => {
+                    // Owner and payer signatures are optional, the wallet
+                    // signs the Solana transactions as the owner instead
+                    let status = signature_status(&txn, &key, signature);
+                    if status == SignatureStatus::Invalid {
+                        problems.push(format!("{field} signature is invalid"));
+                    }
+                    json!({
+                        "key": key.to_string(),
+                        "address": keypair::to_pubkey(&key)?.to_string(),
+                        "signature": status,
+                    })
+                }
+                Err(_) if key.is_empty() => serde_json::Value::Null,
+                Err(_) => {
+                    problems.push(format!("{field} key is invalid"));
+                    serde_json::Value::Null
+                }
+            };
+            signers.insert(field.to_string(), json);
+        }
+
+        let client = opts.client()?;
+        let issued = match asset::for_entity_key(&client, &gateway).await {
+            Ok(_) => true,
+            Err(err) if err.is_account_not_found() => false,
+            Err(err) => return Err(err.into()),
+        };
+        let onboarded: Vec<SubDao> = if issued {
+            hotspot::info::for_entity_key(&client, &SubDao::all(), &gateway)
+                .await?
+                .into_keys()
+                .collect()
+        } else {
+            vec![]
+        };
+        if !onboarded.is_empty() {
+            let subdaos: Vec<String> = onboarded.iter().map(SubDao::to_string).collect();
+            problems.push(format!(
+                "hotspot already onboarded to {}",
+                subdaos.join(", ")
+            ));
+        }
+
+        print_json(&json!({
+            "address": gateway.to_string(),
+            "name": hotspot::name(&gateway),
+            "gateway_signature": gateway_signature,
+            "owner": signers["owner"],
+            "maker": signers["payer"],
+            "fee": txn.fee,
+            "staking_fee": txn.staking_fee,
+            "issued": issued,
+            "onboarded": onboarded,
+            "problems": problems,
+        }))?;
+        if !problems.is_empty() {
+            bail!(
+                "add hotspot transaction is not valid: {}",
+                problems.join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helium_crypto::{KeyTag, Keypair, Sign};
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn signatures() {
+        let gateway = Keypair::generate(KeyTag::default(), &mut OsRng);
+        let owner = Keypair::generate(KeyTag::default(), &mut OsRng);
+        let mut txn = BlockchainTxnAddGatewayV1 {
+            gateway: gateway.public_key().to_vec(),
+            owner: owner.public_key().to_vec(),
+            ..Default::default()
+        };
+        let unsigned = txn.encode_to_vec();
+        txn.gateway_signature = gateway.sign(&unsigned).expect("signature");
+
+        let check = |txn: &BlockchainTxnAddGatewayV1| {
+            signature_status(txn, gateway.public_key(), &txn.gateway_signature)
+        };
+        assert_eq!(check(&txn), SignatureStatus::Valid);
+        assert_eq!(
+            signature_status(&txn, owner.public_key(), &txn.owner_signature),
+            SignatureStatus::Missing
+        );
+        // Adding the owner signature does not invalidate the gateway's
+        txn.owner_signature = owner.sign(&unsigned).expect("signature");
+        assert_eq!(check(&txn), SignatureStatus::Valid);
+        assert_eq!(
+            signature_status(&txn, owner.public_key(), &txn.owner_signature),
+            SignatureStatus::Valid
+        );
+        txn.fee = 1;
+        assert_eq!(check(&txn), SignatureStatus::Invalid);
+    }
+}
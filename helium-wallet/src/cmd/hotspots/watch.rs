@@ -0,0 +1,223 @@
+use crate::cmd::*;
+use futures::TryStreamExt;
+use helium_crypto::{KeyTag, PublicKey};
+use helium_lib::{client::config, dao::SubDao, hotspot, keypair::Pubkey};
+use rand::rngs::OsRng;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+#[derive(Clone, Debug, clap::Args)]
+/// Watch the config service of a subdao for changes to Hotspots.
+///
+/// The config service is polled at the given interval and a line of JSON is
+/// printed for every Hotspot that was added, changed or removed since the
+/// previous poll. The first poll only records the current state unless
+/// --snapshot is given.
+///
+/// With --owned-only only the Hotspots owned by the wallet are requested from
+/// the config service, instead of streaming all Hotspots of the subdao. The
+/// owned Hotspots are looked up again on every poll so transfers are picked
+/// up.
+///
+/// Polls failing on transient RPC or config service errors are noted as a
+/// warning and retried at the next interval.
+pub struct Cmd {
+    /// Subdao to watch Hotspots in
+    subdao: SubDao,
+    /// URL of the config service of the subdao
+    #[arg(long)]
+    config: String,
+    /// Public key the config service signs its responses with
+    #[arg(long)]
+    config_key: PublicKey,
+    /// Helium keypair file to sign config service requests with. A new key
+    /// is generated if neither this nor --gateway-key is given, which some
+    /// config services may refuse
+    #[arg(long, conflicts_with = "gateway_key")]
+    signer: Option<PathBuf>,
+    /// Name of a gateway key stored with the wallet to sign config service
+    /// requests with, see "wallets gateway-key"
+    #[arg(long)]
+    gateway_key: Option<String>,
+    /// Only watch the Hotspots owned by the wallet
+    #[arg(long)]
+    owned_only: bool,
+    /// Watch the Hotspots of the given owner instead of the wallet. Implies
+    /// --owned-only
+    #[arg(long)]
+    owner: Option<Pubkey>,
+    /// Time between polls, for example "30s" or "5m"
+    #[arg(long, default_value = "5m", value_parser = humantime::parse_duration)]
+    interval: Duration,
+    /// Print the state of all watched Hotspots on the first poll
+    #[arg(long)]
+    snapshot: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Event {
+    Snapshot,
+    Added,
+    Changed,
+    Removed,
+}
+
+type Infos = HashMap<PublicKey, serde_json::Value>;
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let signer = match (&self.signer, &self.gateway_key) {
+            (Some(path), _) => helium_crypto::Keypair::try_from(fs::read(path)?.as_slice())?,
+            (None, Some(name)) => {
+                let password = get_wallet_password(false)?;
+                opts.load_gateway_key(name, password.as_bytes())?
+            }
+            (None, None) => helium_crypto::Keypair::generate(KeyTag::default(), &mut OsRng),
+        };
+        let owner = match self.owner {
+            Some(owner) => Some(owner),
+            None if self.owned_only => Some(opts.load_wallet()?.public_key),
+            None => None,
+        };
+        let client = opts.client()?;
+        let mut config = config::Client::for_subdao(
+            self.subdao,
+            &self.config,
+            self.config_key.clone(),
+            Arc::new(signer),
+        )?;
+
+        let mut previous: Option<Infos> = None;
+        loop {
+            let current = match poll(&client, &mut config, owner).await {
+                Ok(infos) => to_infos(infos)?,
+                // A failed poll is retried at the next interval rather than
+                // ending the watch
+                Err(err) if err.is_retryable() => {
+                    warnings::warn(
+                        warnings::POLL_FAILED,
+                        format!("failed to poll hotspots, retrying: {err}"),
+                    );
+                    tokio::time::sleep(self.interval).await;
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let events = match &previous {
+                Some(previous) => changes(previous, &current),
+                None if self.snapshot => current
+                    .keys()
+                    .map(|address| (Event::Snapshot, address.clone()))
+                    .collect(),
+                None => vec![],
+            };
+            for (event, address) in events {
+                println!(
+                    "{}",
+                    json!({
+                        "event": event,
+                        "subdao": self.subdao,
+                        "address": address.to_string(),
+                        "name": hotspot::name(&address),
+                        "info": current.get(&address),
+                    })
+                );
+            }
+            previous = Some(current);
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
+
+/// Get the info of the watched Hotspots, those of the given owner or all of
+/// the subdao
+async fn poll(
+    client: &client::Client,
+    config: &mut config::Client,
+    owner: Option<Pubkey>,
+) -> std::result::Result<HashMap<PublicKey, hotspot::HotspotInfo>, helium_lib::error::Error> {
+    match owner {
+        Some(owner) => {
+            let owned: Vec<PublicKey> = hotspot::for_owner(client, &owner)
+                .await?
+                .into_iter()
+                .map(|hotspot| hotspot.key)
+                .collect();
+            config.batch_info(&owned).await
+        }
+        None => {
+            let mut infos = HashMap::new();
+            let mut stream = config.stream_info().await?;
+            while let Some((address, info)) = stream.try_next().await? {
+                if let Some(info) = info {
+                    infos.insert(address, info);
+                }
+            }
+            Ok(infos)
+        }
+    }
+}
+
+fn to_infos(infos: HashMap<PublicKey, hotspot::HotspotInfo>) -> Result<Infos> {
+    infos
+        .into_iter()
+        .map(|(address, info)| Ok((address, serde_json::to_value(info)?)))
+        .collect()
+}
+
+/// The Hotspots added, changed or removed between two polls
+fn changes(previous: &Infos, current: &Infos) -> Vec<(Event, PublicKey)> {
+    let mut events: Vec<(Event, PublicKey)> = current
+        .iter()
+        .filter_map(|(address, info)| match previous.get(address) {
+            None => Some((Event::Added, address.clone())),
+            Some(previous) if previous != info => Some((Event::Changed, address.clone())),
+            Some(_) => None,
+        })
+        .collect();
+    events.extend(
+        previous
+            .keys()
+            .filter(|address| !current.contains_key(address))
+            .map(|address| (Event::Removed, address.clone())),
+    );
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn info_changes() {
+        let key = || {
+            helium_crypto::Keypair::generate(KeyTag::default(), &mut OsRng)
+                .public_key()
+                .clone()
+        };
+        let (kept, moved, added, removed) = (key(), key(), key(), key());
+        let previous: Infos = [
+            (kept.clone(), json!({"gain": 12})),
+            (moved.clone(), json!({"location": "a"})),
+            (removed.clone(), json!({})),
+        ]
+        .into();
+        let current: Infos = [
+            (kept, json!({"gain": 12})),
+            (moved.clone(), json!({"location": "b"})),
+            (added.clone(), json!({})),
+        ]
+        .into();
+
+        let mut events = changes(&previous, &current);
+        events.sort_by_key(|(event, _)| *event as u8);
+        assert_eq!(
+            events,
+            vec![
+                (Event::Added, added),
+                (Event::Changed, moved),
+                (Event::Removed, removed)
+            ]
+        );
+    }
+}
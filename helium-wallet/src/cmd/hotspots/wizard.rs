@@ -0,0 +1,321 @@
+//! Interactive prompts collecting a Hotspot update.
+use crate::cmd::*;
+use dialoguer::{Input, Select};
+use helium_lib::{
+    client::config::iot,
+    dao::SubDao,
+    hotspot::{self, region, Hotspot, HotspotInfo, HotspotInfoUpdate},
+    keypair::Pubkey,
+};
+use helium_proto::Region;
+use rust_decimal::{prelude::FromPrimitive, Decimal};
+use serde::Deserialize;
+
+/// Geocoding service used to look up street addresses
+const GEOCODER_URL: &str = "https://nominatim.openstreetmap.org/search";
+/// Number of geocoding results offered to pick from
+const GEOCODER_LIMIT: &str = "5";
+/// Time to wait for the geocoding service before giving up on a lookup
+const GEOCODER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// The update collected from the prompts
+pub struct Answers {
+    pub gateway: helium_crypto::PublicKey,
+    pub update: HotspotInfoUpdate,
+    pub region: Option<Region>,
+}
+
+/// Walk through picking one of the Hotspots of the given owner and the
+/// location, gain and elevation to assert for it. Empty answers keep the
+/// currently asserted values.
+pub async fn run(
+    client: &client::Client,
+    config: &mut iot::Client,
+    owner: &Pubkey,
+) -> Result<Answers> {
+    let hotspots = hotspot::for_owner(client, owner).await?;
+    if hotspots.is_empty() {
+        bail!("wallet {owner} owns no hotspots");
+    }
+    let hotspot = select_hotspot(&hotspots)?;
+
+    let mut infos = vec![];
+    for subdao in SubDao::all() {
+        let info_key = subdao.info_key(&hotspot.key);
+        if let Some(info) = hotspot::info::get(client, subdao, &info_key).await? {
+            infos.push(info);
+        }
+    }
+    let info = match infos.len() {
+        0 => bail!("{} is not onboarded to any subdao", hotspot.name),
+        1 => &infos[0],
+        _ => {
+            let subdaos: Vec<String> = infos.iter().map(|info| info.subdao().to_string()).collect();
+            let index = Select::new()
+                .with_prompt("Subdao to update")
+                .items(&subdaos)
+                .default(0)
+                .interact()?;
+            &infos[index]
+        }
+    };
+
+    let mut update = HotspotInfoUpdate::for_subdao(info.subdao());
+    if let Some(location) = prompt_location(info).await? {
+        update = update.set_location(Some(location));
+    }
+    let mut region = None;
+    if info.subdao() == SubDao::Iot {
+        region = prompt_region(config.region(&hotspot.key).await?)?;
+        let limits = region::gain_limits(client).await?;
+        let max_eirp = super::max_eirp(config, region).await?;
+        let current_gain = info
+            .gain()
+            .as_ref()
+            .map(|gain| gain.to_string())
+            .unwrap_or_default();
+        let gain = Input::<String>::new()
+            .with_prompt(format!(
+                "Antenna gain in dBi, {} to {} (empty to keep {current_gain})",
+                limits.min, limits.max
+            ))
+            .allow_empty(true)
+            .validate_with(|value: &String| -> Result {
+                if let Some(gain) = parse_gain(value)? {
+                    region::check_gain(gain, &limits, max_eirp)?;
+                }
+                Ok(())
+            })
+            .interact()?;
+        update = update.set_gain(parse_gain(&gain)?.and_then(|gain| gain.to_f64()));
+
+        let current_elevation = info
+            .elevation()
+            .as_ref()
+            .map(|elevation| elevation.to_string())
+            .unwrap_or_default();
+        let elevation = Input::<String>::new()
+            .with_prompt(format!(
+                "Elevation in meters above ground (empty to keep {current_elevation})"
+            ))
+            .allow_empty(true)
+            .validate_with(|value: &String| parse_optional::<i32>(value).map(|_| ()))
+            .interact()?;
+        update = update.set_elevation(parse_optional(&elevation)?);
+    }
+
+    Ok(Answers {
+        gateway: hotspot.key.clone(),
+        update,
+        region,
+    })
+}
+
+/// Pick a Hotspot by searching for words of its animal name
+fn select_hotspot(hotspots: &[Hotspot]) -> Result<&Hotspot> {
+    loop {
+        let query = Input::<String>::new()
+            .with_prompt("Search Hotspot name (empty for all)")
+            .allow_empty(true)
+            .interact()?;
+        let matches: Vec<&Hotspot> = hotspots
+            .iter()
+            .filter(|hotspot| name_matches(&hotspot.name, &query))
+            .collect();
+        if matches.is_empty() {
+            eprintln!("No Hotspot name matches \"{query}\"");
+            continue;
+        }
+        let names: Vec<&str> = matches
+            .iter()
+            .map(|hotspot| hotspot.name.as_str())
+            .collect();
+        let index = Select::new()
+            .with_prompt("Hotspot to update")
+            .items(&names)
+            .default(0)
+            .interact()?;
+        return Ok(matches[index]);
+    }
+}
+
+/// Whether every word of the query is a prefix of a word of the name, so
+/// "tal dol" matches "Tall Cinnamon Dolphin".
+fn name_matches(name: &str, query: &str) -> bool {
+    let name = name.to_lowercase();
+    let name_words: Vec<&str> = name.split(['-', ' ', '_']).collect();
+    query.to_lowercase().split_whitespace().all(|word| {
+        name_words
+            .iter()
+            .any(|name_word| name_word.starts_with(word))
+    })
+}
+
+async fn prompt_location(info: &HotspotInfo) -> Result<Option<h3o::CellIndex>> {
+    let current = info
+        .location()
+        .as_ref()
+        .map(|location| {
+            let geo = h3o::LatLng::from(location.location);
+            format!("keep {:.6}, {:.6}", geo.lat(), geo.lng())
+        })
+        .unwrap_or_else(|| "keep unasserted".to_string());
+    let choices = [
+        current.as_str(),
+        "street address (looked up with OpenStreetMap)",
+        "latitude and longitude",
+        "H3 cell",
+    ];
+    let location = loop {
+        let choice = Select::new()
+            .with_prompt("Location")
+            .items(&choices)
+            .default(0)
+            .interact()?;
+        match choice {
+            0 => return Ok(None),
+            1 => match prompt_address().await? {
+                Some(location) => break location,
+                // Declined to send the address, pick another way to enter
+                // the location
+                None => continue,
+            },
+            2 => {
+                let lat: f64 = Input::new().with_prompt("Latitude").interact()?;
+                let lon: f64 = Input::new().with_prompt("Longitude").interact()?;
+                break h3o::LatLng::new(lat, lon)?.to_cell(h3o::Resolution::Twelve);
+            }
+            _ => {
+                let cell = Input::<String>::new()
+                    .with_prompt("H3 cell index")
+                    .validate_with(|value: &String| value.parse::<h3o::CellIndex>().map(|_| ()))
+                    .interact()?;
+                let cell: h3o::CellIndex = cell.parse()?;
+                // Coarser cells are asserted at their center
+                break cell
+                    .center_child(h3o::Resolution::Twelve)
+                    .or_else(|| cell.parent(h3o::Resolution::Twelve))
+                    .ok_or_else(|| anyhow!("invalid cell {cell}"))?;
+            }
+        }
+    };
+    Ok(Some(location))
+}
+
+#[derive(Debug, Deserialize)]
+struct GeocodeResult {
+    display_name: String,
+    lat: String,
+    lon: String,
+}
+
+/// Look up a street address with the geocoding service and pick one of the
+/// places found. The address is only sent to the public geocoding service
+/// once the user agrees to it, `None` is returned otherwise.
+async fn prompt_address() -> Result<Option<h3o::CellIndex>> {
+    if !dialoguer::Confirm::new()
+        .with_prompt(format!(
+            "The address is sent to the public OpenStreetMap geocoding service at \
+             {GEOCODER_URL} to look it up. Continue?"
+        ))
+        .default(false)
+        .interact()?
+    {
+        return Ok(None);
+    }
+    let client = helium_lib::network::apply(reqwest::Client::builder())
+        .timeout(GEOCODER_TIMEOUT)
+        .build()?;
+    loop {
+        let address = Input::<String>::new()
+            .with_prompt("Street address")
+            .interact()?;
+        let body = client
+            .get(GEOCODER_URL)
+            .query(&[
+                ("q", address.as_str()),
+                ("format", "json"),
+                ("limit", GEOCODER_LIMIT),
+            ])
+            .header(reqwest::header::USER_AGENT, "helium-wallet")
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let results: Vec<GeocodeResult> = serde_json::from_str(&body)?;
+        if results.is_empty() {
+            eprintln!("No places found for \"{address}\"");
+            continue;
+        }
+        let names: Vec<&str> = results
+            .iter()
+            .map(|result| result.display_name.as_str())
+            .collect();
+        let index = Select::new()
+            .with_prompt("Place")
+            .items(&names)
+            .default(0)
+            .interact()?;
+        let result = &results[index];
+        let geo = h3o::LatLng::new(result.lat.parse()?, result.lon.parse()?)?;
+        return Ok(Some(geo.to_cell(h3o::Resolution::Twelve)));
+    }
+}
+
+/// Ask for the region to check the gain against, offering the region of the
+/// asserted location if known
+fn prompt_region(asserted: Option<Region>) -> Result<Option<Region>> {
+    let prompt = "LoRa region, like US915, to check the gain against";
+    let prompt = match asserted {
+        Some(asserted) => format!("{prompt} (empty for {})", asserted.as_str_name()),
+        None => format!("{prompt} (empty to skip)"),
+    };
+    let region = Input::<String>::new()
+        .with_prompt(prompt)
+        .allow_empty(true)
+        .validate_with(|value: &String| -> Result {
+            if !value.trim().is_empty() {
+                region::region_from_str(value.trim())?;
+            }
+            Ok(())
+        })
+        .interact()?;
+    if region.trim().is_empty() {
+        return Ok(asserted);
+    }
+    Ok(Some(region::region_from_str(region.trim())?))
+}
+
+fn parse_gain(value: &str) -> Result<Option<Decimal>> {
+    parse_optional::<f64>(value)?
+        .map(|gain| {
+            Decimal::from_f64(gain)
+                .map(|gain| gain.trunc_with_scale(1))
+                .ok_or_else(|| anyhow!("invalid gain {gain}"))
+        })
+        .transpose()
+}
+
+fn parse_optional<T: std::str::FromStr>(value: &str) -> Result<Option<T>>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(value.parse()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hotspot_name_search() {
+        assert!(name_matches("Tall Cinnamon Dolphin", "tal dol"));
+        assert!(name_matches("tall-cinnamon-dolphin", ""));
+        assert!(!name_matches("Tall Cinnamon Dolphin", "tall whale"));
+    }
+}
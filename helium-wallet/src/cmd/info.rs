@@ -1,21 +1,36 @@
 use crate::{
     cmd::{print_json, Opts},
-    result::{Error, Result},
+    pwhash::PwHash,
+    result::{Context, Error, Result},
     wallet::Wallet,
 };
+use helium_lib::{entity_key, kta};
 use qr2term::print_qr;
 use serde_json::json;
+use std::{fs, path::PathBuf};
 
 /// Get wallet information
 #[derive(Debug, clap::Args)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Cmd {
     /// Display QR code for a given single wallet.
     #[arg(long)]
     qr: bool,
+
+    #[command(subcommand)]
+    cmd: Option<InfoCommand>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum InfoCommand {
+    AssetsForKeys(AssetsForKeysCmd),
 }
 
 impl Cmd {
     pub async fn run(&self, opts: Opts) -> Result {
+        if let Some(InfoCommand::AssetsForKeys(cmd)) = &self.cmd {
+            return cmd.run(opts).await;
+        }
         let wallet = opts.load_wallet()?;
         if self.qr {
             print_qr(wallet.public_key.to_string()).map_err(Error::from)
@@ -31,6 +46,7 @@ pub(crate) fn print_wallet(wallet: &Wallet) -> Result {
     let json = json!({
         "sharded": wallet.is_sharded(),
         "pwhash": wallet.pwhash().to_string(),
+        "pwhash_params": pwhash_params(wallet.pwhash()),
         "address": {
             "solana": address,
             "helium": helium_address,
@@ -38,3 +54,55 @@ pub(crate) fn print_wallet(wallet: &Wallet) -> Result {
     });
     print_json(&json)
 }
+
+fn pwhash_params(pwhash: &PwHash) -> serde_json::Value {
+    match pwhash {
+        PwHash::Pbkdf2(hasher) => json!({ "iterations": hasher.iterations() }),
+        PwHash::Argon2id13(hasher) => json!({
+            "memory_mib": hasher.mem_limit() / (1024 * 1024),
+            "passes": hasher.ops_limit(),
+        }),
+    }
+}
+
+/// Resolve the asset ids for a list of entity keys.
+///
+/// The input file contains one entity key per line. Blank lines are ignored.
+/// Entity keys that do not have an asset are reported with a null asset.
+#[derive(Debug, clap::Args)]
+pub struct AssetsForKeysCmd {
+    /// File with one entity key per line
+    #[arg(long)]
+    input: PathBuf,
+    /// The encoding of the entity keys in the input file
+    #[arg(long, default_value_t = entity_key::EntityKeyEncoding::UTF8)]
+    encoding: entity_key::EntityKeyEncoding,
+}
+
+impl AssetsForKeysCmd {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        let input = fs::read_to_string(&self.input)
+            .with_context(|| format!("reading {}", self.input.display()))?;
+        let entity_key_strings: Vec<&str> = input
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let entity_keys = entity_key_strings
+            .iter()
+            .map(|entity_key| entity_key::from_str(entity_key, self.encoding.into()))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let asset_ids = kta::asset_ids_for_entity_keys(&entity_keys).await?;
+        let json = entity_key_strings
+            .iter()
+            .zip(asset_ids)
+            .map(|(entity_key, asset)| {
+                json!({
+                    "entity_key": entity_key,
+                    "asset": asset.map(|asset| asset.to_string()),
+                })
+            })
+            .collect::<Vec<_>>();
+        print_json(&json)
+    }
+}
@@ -21,6 +21,12 @@ impl Cmd {
         let transaction_opts = self.commit.transaction_opts(&client);
         let (tx, _) =
             helium_lib::memo::memo(&client, &self.message, &keypair, &transaction_opts).await?;
-        print_json(&self.commit.maybe_commit(tx, &client).await?.to_json())
+        print_json(
+            &self
+                .commit
+                .maybe_commit_with_keypair(tx, &client, &keypair)
+                .await?
+                .to_json(),
+        )
     }
 }
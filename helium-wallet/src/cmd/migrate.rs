@@ -0,0 +1,286 @@
+use crate::cmd::*;
+use helium_lib::{
+    asset, hotspot,
+    keypair::{Pubkey, Signer},
+    solana_sdk::commitment_config::CommitmentLevel,
+    token::{self, Token, TokenAmount},
+};
+
+/// Tokens moved to the vault. SOL stays with the wallet, it pays the fees of
+/// the proposals the wallet makes after the migration.
+const MIGRATED_TOKENS: [Token; 4] = [Token::Hnt, Token::Iot, Token::Mobile, Token::Usdc];
+
+#[derive(Debug, Clone, clap::Args)]
+/// Move the wallet into the custody of a new Squads multisig.
+///
+/// Creates a multisig with the given members, then transfers the HNT, IOT,
+/// MOBILE and USDC balances and all Hotspots of the wallet to the vault of
+/// the multisig. Tokens and Hotspots are packed into as few transactions as
+/// fit. SOL stays with the wallet to pay for proposals.
+///
+/// The multisig is recorded for the wallet in the configuration directory
+/// before its creation is sent, so a creation that lands while its
+/// confirmation fails is picked up by the next run. Commands that support
+/// multisig proposals propose to it by default, pass --no-multisig to sign
+/// for the wallet itself.
+///
+/// The migration stops at the first failed transaction. Run it again with
+/// --resume to move what is left to the recorded multisig.
+pub struct Cmd {
+    /// Members of the multisig. Every member can propose, vote on and
+    /// execute transactions. The wallet has to be one of them
+    #[arg(
+        long,
+        value_delimiter = ',',
+        required_unless_present = "resume",
+        conflicts_with = "resume"
+    )]
+    members: Vec<Pubkey>,
+    /// Number of member approvals a transaction needs
+    #[arg(long, required_unless_present = "resume", conflicts_with = "resume")]
+    threshold: Option<u16>,
+    /// Move the remaining tokens and Hotspots to the multisig the wallet was
+    /// already migrated to
+    #[arg(long)]
+    resume: bool,
+    /// Commit the migration
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+/// A transaction of the migration
+enum Batch {
+    Create {
+        members: Vec<Pubkey>,
+        threshold: u16,
+    },
+    Tokens(Vec<TokenAmount>),
+    Hotspots {
+        hotspots: Vec<hotspot::Hotspot>,
+        batch: message::Batch,
+    },
+}
+
+impl Batch {
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Create { members, threshold } => json!({
+                "create": {
+                    "members": members.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+                    "threshold": threshold,
+                },
+            }),
+            Self::Tokens(amounts) => {
+                let tokens: serde_json::Map<String, serde_json::Value> = amounts
+                    .iter()
+                    .map(|amount| (amount.token.to_string(), json!(f64::from(amount))))
+                    .collect();
+                json!({ "tokens": tokens })
+            }
+            Self::Hotspots { hotspots, .. } => json!({
+                "hotspots": hotspots
+                    .iter()
+                    .map(|hotspot| json!({
+                        "key": hotspot.key.to_string(),
+                        "name": hotspot.name,
+                    }))
+                    .collect::<Vec<_>>(),
+            }),
+        }
+    }
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        if self.commit.sign_only {
+            bail!("a migration can not be signed offline");
+        }
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let wallet = keypair.pubkey();
+        let client = opts.client()?;
+        let txn_opts = self.commit.transaction_opts(&client);
+
+        let mut config = MultisigConfig::load()?;
+        let migrated = match config.wallets.get(&wallet.to_string()).cloned() {
+            // A creation that was sent without being confirmed either landed,
+            // and the migration resumes with it, or did not, and the multisig
+            // is created afresh
+            Some(migrated) if migrated.pending => {
+                match multisig::transaction_index(&client, &migrated.multisig).await {
+                    Ok(_) => {
+                        let migrated = MigratedMultisig {
+                            pending: false,
+                            ..migrated
+                        };
+                        config.wallets.insert(wallet.to_string(), migrated.clone());
+                        config.save()?;
+                        Some(migrated)
+                    }
+                    Err(err) if err.is_account_not_found() => None,
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            migrated => migrated,
+        };
+        let create_key = Keypair::generate();
+        let mut batches = vec![];
+        let migrated = match (migrated, self.resume) {
+            (Some(migrated), true) => {
+                // Fails unless the multisig exists, so nothing is sent to the
+                // vault of a multisig whose creation did not land
+                multisig::transaction_index(&client, &migrated.multisig).await?;
+                migrated
+            }
+            (None, true) => bail!("wallet {wallet} was not migrated to a multisig"),
+            (Some(migrated), false) => bail!(
+                "wallet {wallet} was already migrated to multisig {}, use --resume",
+                migrated.multisig
+            ),
+            (None, false) => {
+                if !self.members.contains(&wallet) {
+                    bail!("wallet {wallet} has to be a member of the multisig");
+                }
+                batches.push(Batch::Create {
+                    members: self.members.clone(),
+                    threshold: self.threshold.unwrap_or_default(),
+                });
+                MigratedMultisig {
+                    multisig: multisig::multisig_key(&create_key.pubkey()),
+                    vault_index: 0,
+                    pending: false,
+                }
+            }
+        };
+        let vault = multisig::vault_key(&migrated.multisig, migrated.vault_index);
+        policy::check_external(&wallet, &vault, "multisig vault")?;
+
+        let balances = token::balance_for_addresses(
+            &client,
+            &MIGRATED_TOKENS.map(|token| token.associated_token_adress(&wallet)),
+        )
+        .await?;
+        let amounts: Vec<TokenAmount> = balances
+            .into_iter()
+            .map(|balance| balance.amount)
+            .filter(|amount| amount.amount > 0)
+            .collect();
+        let lut_accounts = message::get_lut_accounts(&client, &txn_opts.lut_addresses).await?;
+        let mut tokens: Vec<TokenAmount> = vec![];
+        for amount in amounts {
+            let mut candidate = tokens.clone();
+            candidate.push(amount);
+            let ixs =
+                token::transfer_instructions(&transfers(&candidate, &vault), None, &[], &wallet)?;
+            if !tokens.is_empty() && !message::fits(&ixs, &lut_accounts, &wallet) {
+                batches.push(Batch::Tokens(std::mem::take(&mut tokens)));
+            }
+            tokens.push(amount);
+        }
+        if !tokens.is_empty() {
+            batches.push(Batch::Tokens(tokens));
+        }
+        let hotspots = hotspot::for_owner(&client, &wallet).await?;
+        let assets: Vec<Pubkey> = hotspots.iter().map(|hotspot| hotspot.asset).collect();
+        batches.extend(
+            asset::transfer_batches(&client, &assets, &wallet, &vault, &txn_opts)
+                .await?
+                .into_iter()
+                .map(|batch| Batch::Hotspots {
+                    hotspots: batch
+                        .items
+                        .iter()
+                        .map(|index| hotspots[*index].clone())
+                        .collect(),
+                    batch,
+                }),
+        );
+
+        if self.commit.commit
+            && !confirm(&format!(
+                "Move the wallet to multisig vault {vault} in {} transactions?",
+                batches.len()
+            ))?
+        {
+            return Ok(());
+        }
+
+        let mut results = vec![];
+        for batch in &batches {
+            let response = match batch {
+                Batch::Create { members, threshold } => {
+                    let (msg, _) = multisig::create_message(
+                        &client,
+                        &create_key.pubkey(),
+                        members,
+                        *threshold,
+                        &wallet,
+                        &txn_opts,
+                    )
+                    .await?;
+                    let tx = VersionedTransaction::try_new(msg, &[&*keypair, &create_key])?;
+                    // Tokens and Hotspots only go to the vault once the
+                    // multisig that controls it is known to exist
+                    let mut commit = self.commit.clone();
+                    if commit.commit {
+                        commit.wait = Some(CommitmentLevel::Confirmed);
+                        let pending = MigratedMultisig {
+                            pending: true,
+                            ..migrated.clone()
+                        };
+                        config.wallets.insert(wallet.to_string(), pending);
+                        config.save()?;
+                    }
+                    let response = commit.maybe_commit(tx, &client).await;
+                    if response.is_ok() && self.commit.commit {
+                        config.wallets.insert(wallet.to_string(), migrated.clone());
+                        config.save()?;
+                    }
+                    response
+                }
+                Batch::Tokens(amounts) => {
+                    let (msg, _) = token::transfer_message(
+                        &client,
+                        &transfers(amounts, &vault),
+                        None,
+                        &[],
+                        &wallet,
+                        &txn_opts,
+                    )
+                    .await?;
+                    let tx = VersionedTransaction::try_new(msg, &[&*keypair])?;
+                    self.commit
+                        .maybe_commit_with_keypair(tx, &client, &keypair)
+                        .await
+                }
+                Batch::Hotspots { batch, .. } => {
+                    let (msg, _) = batch.message(&client, &wallet, &txn_opts).await?;
+                    let tx = VersionedTransaction::try_new(msg, &[&*keypair])?;
+                    self.commit
+                        .maybe_commit_with_keypair(tx, &client, &keypair)
+                        .await
+                }
+            };
+            let failed = response.is_err();
+            let mut json = batch.to_json();
+            json["response"] = response.to_json();
+            results.push(json);
+            // Later batches depend on the multisig existing, and a partial
+            // migration is resumed from the wallet's remaining holdings
+            if failed && self.commit.commit {
+                break;
+            }
+        }
+
+        print_json(&json!({
+            "multisig": migrated.multisig.to_string(),
+            "vault_index": migrated.vault_index,
+            "vault": vault.to_string(),
+            "planned": batches.len(),
+            "batches": results,
+        }))
+    }
+}
+
+fn transfers(amounts: &[TokenAmount], vault: &Pubkey) -> Vec<(Pubkey, TokenAmount)> {
+    amounts.iter().map(|amount| (*vault, *amount)).collect()
+}
@@ -1,17 +1,35 @@
 use crate::{
-    result::{anyhow, bail, Error, Result},
+    format::Format,
+    offline::TransactionEnvelope,
+    policy::{self, AddressPolicy},
+    pwhash::{
+        PwHash, ARGON2ID13_DEFAULT_MEMORY_MIB, ARGON2ID13_DEFAULT_PASSES,
+        ARGON2ID13_MAX_MEMORY_MIB, ARGON2ID13_MAX_PASSES, ARGON2ID13_MIN_MEMORY_MIB,
+        PBKDF2_DEFAULT_ITERATIONS,
+    },
+    result::{anyhow, bail, Context, Error, Result},
     wallet::Wallet,
+    warnings,
 };
 use helium_lib::{
     b64,
     client::{self, SolanaRpcClient},
-    keypair::Keypair,
-    message, priority_fee,
+    confirm::{self, Confirmation},
+    keypair::{Keypair, Signer},
+    message, multisig,
+    preflight::{self, Preflight},
+    priority_fee, simulation,
     solana_client::{
-        self, rpc_config::RpcSendTransactionConfig, rpc_request::RpcResponseErrorData,
-        rpc_response::RpcSimulateTransactionResult,
+        self, rpc_request::RpcResponseErrorData, rpc_response::RpcSimulateTransactionResult,
+    },
+    solana_sdk::{
+        commitment_config::CommitmentLevel,
+        message::VersionedMessage,
+        native_token::{lamports_to_sol, sol_to_lamports},
+        transaction::VersionedTransaction,
     },
-    solana_sdk::transaction::VersionedTransaction,
+    token::{self, Token},
+    transaction::{self, SendOpts},
     TransactionOpts,
 };
 use serde_json::json;
@@ -19,97 +37,837 @@ use std::{
     env, fs, io,
     ops::Deref,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
 };
+use zeroize::Zeroizing;
 
 pub mod assets;
+pub mod audit;
 pub mod balance;
+pub mod boosting;
 pub mod burn;
 pub mod create;
 pub mod dc;
+pub mod debug;
 pub mod export;
+pub mod history;
 pub mod hotspots;
 pub mod info;
 pub mod memo;
+pub mod migrate;
 pub mod price;
 pub mod router;
+pub mod shards;
 pub mod sign;
+pub mod state;
+pub mod submit;
+pub mod summary;
+pub mod swap;
+pub mod token;
 pub mod transfer;
 pub mod upgrade;
+pub mod wallets;
+
+/// Wallet file used when no other file is given
+pub const DEFAULT_WALLET_FILE: &str = "wallet.key";
 
 /// Common options for most wallet commands
 #[derive(Debug, clap::Args, Clone)]
 pub struct Opts {
-    /// File(s) to use
+    /// File(s) or wallet name(s) to use
     #[arg(
         short = 'f',
         long = "file",
         number_of_values(1),
-        default_value = "wallet.key"
+        default_value = DEFAULT_WALLET_FILE
     )]
     files: Vec<PathBuf>,
 
     /// Solana RPC URL to use.
     #[arg(long, default_value = "m")]
     url: String,
+
+    /// Append an entry for every signing operation to the given audit log
+    #[arg(long)]
+    audit_log: Option<PathBuf>,
+
+    /// POST the signature and context of every submitted transaction to the
+    /// given indexer url. Failures to reach the indexer are ignored.
+    #[arg(long, env = "HELIUM_WALLET_TRACE_URL")]
+    trace_url: Option<String>,
+
+    /// Do not use or update the on-disk cache of reward oracle responses
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Log filter, a level like "debug" or levels per module like
+    /// "warn,helium_lib::client=debug". Nothing is logged by default
+    #[arg(long, env = "HELIUM_WALLET_LOG")]
+    log_level: Option<String>,
+
+    /// Write logs to the given file instead of stderr, rotated daily. The
+    /// date of the day is appended to the file name, so "wallet.log" is
+    /// written as "wallet.log.2024-06-01". Logs at info level unless
+    /// --log-level is given
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+
+    /// Address policy file listing the addresses funds may or may not be sent
+    /// to. Defaults to address-policy.json in the configuration directory,
+    /// if it exists
+    #[arg(long, env = "HELIUM_WALLET_ADDRESS_POLICY")]
+    address_policy: Option<PathBuf>,
+
+    /// Read the wallet password from the first line of stdin instead of
+    /// prompting for it
+    #[arg(long)]
+    password_stdin: bool,
+
+    /// Timeout for each network request, for example "30s" or "2m"
+    #[arg(long, value_parser = humantime::parse_duration)]
+    timeout: Option<std::time::Duration>,
+
+    /// Number of assets to request per asset search. Lower this for DAS
+    /// providers that cap pages below 1000 assets
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=client::DAS_MAX_PAGE_SIZE as i64))]
+    page_size: Option<u32>,
+
+    /// Maximum number of DAS requests in flight at once
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    das_concurrency: Option<u32>,
+
+    /// Maximum number of DAS requests started per second, to stay below the
+    /// rate limit of the DAS provider
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    das_rate: Option<u32>,
+
+    /// Time fetched assets are reused for before they are fetched again, for
+    /// example "30s". Use "0s" to disable caching assets
+    #[arg(long, value_parser = humantime::parse_duration, default_value = "60s")]
+    das_cache_ttl: std::time::Duration,
+
+    /// Proxy to send all http requests through, for example
+    /// "http://proxy.example.com:3128". Defaults to the proxy in network.json
+    /// in the configuration directory, then to the HTTPS_PROXY environment
+    /// variable
+    #[arg(long, env = "HELIUM_WALLET_PROXY")]
+    proxy: Option<String>,
+
+    /// PEM file of root certificates to trust in addition to the built in
+    /// ones. Defaults to the bundle in network.json in the configuration
+    /// directory
+    #[arg(long, env = "HELIUM_WALLET_CA_BUNDLE")]
+    ca_bundle: Option<PathBuf>,
+
+    /// Sign with a keypair derived from the given seed instead of the
+    /// wallet, without asking for a password. Only the signing key is
+    /// deterministic: blockhashes, fees and accounts still come from the
+    /// live RPC, so transactions differ between runs until RPC responses can
+    /// be recorded and replayed. The secret of the keypair is not secret at
+    /// all, so it is only accepted with a devnet or local RPC url
+    #[arg(long, env = "HELIUM_WALLET_TEST_SEED", hide = true)]
+    test_seed: Option<String>,
+}
+
+/// Network settings read from the configuration directory, used when not
+/// given on the command line
+#[derive(Debug, Default, serde::Deserialize)]
+struct NetworkConfig {
+    proxy: Option<String>,
+    ca_bundle: Option<PathBuf>,
+}
+
+impl NetworkConfig {
+    const FILE: &'static str = "network.json";
+
+    fn load() -> Result<Self> {
+        let path = config_dir().join(Self::FILE);
+        match fs::File::open(&path) {
+            Ok(file) => serde_json::from_reader(file)
+                .map_err(|err| anyhow!("invalid {}: {err}", path.display())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
 }
 
 impl Opts {
     pub fn load_wallet(&self) -> Result<Wallet> {
+        if let Some(keypair) = self.test_keypair()? {
+            // Cheap hashing parameters, the wallet is never written
+            let format = Format::basic(PwHash::argon2id13(1, 8));
+            return Wallet::encrypt(&keypair, b"", format);
+        }
         let mut files_iter = self.files.iter();
         let mut first_wallet = match files_iter.next() {
             Some(path) => {
-                let mut reader = fs::File::open(path)?;
+                let mut reader = fs::File::open(wallets::resolve(path)?)?;
                 Wallet::read(&mut reader)?
             }
             None => bail!("At least one wallet file expected"),
         };
 
         for path in files_iter {
-            let mut reader = fs::File::open(path)?;
+            let mut reader = fs::File::open(wallets::resolve(path)?)?;
             let w = Wallet::read(&mut reader)?;
             first_wallet.absorb_shard(&w)?;
         }
 
+        if !first_wallet.is_current_version() {
+            warnings::warn(
+                warnings::LEGACY_WALLET_FORMAT,
+                "the wallet uses an older format version, use the upgrade command to convert it",
+            );
+        }
+        if let PwHash::Pbkdf2(pbkdf2) = first_wallet.pwhash() {
+            if pbkdf2.iterations() < PBKDF2_DEFAULT_ITERATIONS {
+                warnings::warn(
+                    warnings::LOW_ITERATIONS,
+                    format!(
+                        "the wallet password is hashed with {} iterations, fewer than the default of {PBKDF2_DEFAULT_ITERATIONS}",
+                        pbkdf2.iterations()
+                    ),
+                );
+            }
+        }
         Ok(first_wallet)
     }
 
     pub fn load_keypair(&self, password: &[u8]) -> Result<Arc<Keypair>> {
         let wallet = self.load_wallet()?;
+        if self.is_devnet() {
+            warnings::warn_once(
+                warnings::DEVNET_URL,
+                "signing with a wallet against a devnet url, transactions will not reach mainnet",
+            );
+        }
+        crate::offline::set_wallet(wallet.public_key);
+        if let Some(keypair) = self.test_keypair()? {
+            return Ok(keypair);
+        }
         wallet.decrypt(password)
     }
 
+    /// Load the wallet to sign the transactions of a command with. With
+    /// --sign-only only the public key of the wallet is read, without asking
+    /// for the password, so the wallet is never decrypted on the machine
+    /// that builds the transactions.
+    pub fn load_signer(&self, commit: &CommitOpts) -> Result<WalletSigner> {
+        if commit.sign_only {
+            let wallet = self.load_wallet()?;
+            crate::offline::set_wallet(wallet.public_key);
+            return Ok(WalletSigner::PublicKey(wallet.public_key));
+        }
+        let password = get_wallet_password(false)?;
+        let keypair = self.load_keypair(password.as_bytes())?;
+        let _ = commit.wallet_keypair.set(keypair.clone());
+        Ok(WalletSigner::Keypair(keypair))
+    }
+
+    /// Path of the named gateway keys of the wallet, next to its first file
+    pub fn gateway_keys_path(&self) -> Result<PathBuf> {
+        let Some(file) = self.files.first() else {
+            bail!("At least one wallet file expected");
+        };
+        Ok(crate::gateway_keys::path(&wallets::resolve(file)?))
+    }
+
+    /// Decrypt the named gateway key of the wallet with the wallet password
+    pub fn load_gateway_key(&self, name: &str, password: &[u8]) -> Result<helium_crypto::Keypair> {
+        crate::gateway_keys::GatewayKeys::load(&self.gateway_keys_path()?)?.get(name, password)
+    }
+
+    /// The keypair derived from the test seed, if one is given
+    fn test_keypair(&self) -> Result<Option<Arc<Keypair>>> {
+        use sha2::{Digest, Sha256};
+        let Some(seed) = &self.test_seed else {
+            return Ok(None);
+        };
+        if !self.is_devnet() && !client::is_localhost(&self.url) {
+            bail!("a test seed can only be used with a devnet or local RPC url");
+        }
+        let entropy = Sha256::digest(seed.as_bytes());
+        Ok(Some(Arc::new(Keypair::generate_from_entropy(&entropy)?)))
+    }
+
+    /// Whether the configured RPC url is a devnet url
+    pub fn is_devnet(&self) -> bool {
+        client::is_devnet(&self.url)
+    }
+
     pub fn client(&self) -> Result<client::Client> {
+        if let Some(timeout) = self.timeout {
+            client::set_timeout(timeout);
+        }
+        if let Some(page_size) = self.page_size {
+            client::set_das_page_size(page_size);
+        }
+        // Commands run briefly, so unlike the library the wallet reuses
+        // fetched assets by default
+        let defaults = client::DasLimits::default();
+        client::set_das_limits(client::DasLimits {
+            concurrency: self
+                .das_concurrency
+                .map_or(defaults.concurrency, |concurrency| concurrency as usize),
+            rate: self.das_rate,
+            cache_ttl: self.das_cache_ttl,
+            ..defaults
+        });
+        self.init_network()?;
         Ok(client::Client::try_from(self.url.as_str())?)
     }
+
+    fn init_network(&self) -> Result {
+        let config = if self.proxy.is_none() || self.ca_bundle.is_none() {
+            NetworkConfig::load()?
+        } else {
+            NetworkConfig::default()
+        };
+        let proxy = self.proxy.as_ref().or(config.proxy.as_ref());
+        let ca_bundle = self.ca_bundle.as_ref().or(config.ca_bundle.as_ref());
+        helium_lib::network::set(proxy.map(String::as_str), ca_bundle.map(PathBuf::as_path))?;
+        Ok(())
+    }
+
+    /// Resolve the ecc verifier url to use. Defaults to the verifier for the
+    /// network of the configured RPC url when no verifier is given.
+    pub fn verifier_url<'a>(&'a self, verifier: &'a Option<String>) -> &'a str {
+        match verifier.as_ref().unwrap_or(&self.url).as_str() {
+            "m" | "mainnet-beta" => client::VERIFIER_URL_MAINNET,
+            "d" | "devnet" => client::VERIFIER_URL_DEVNET,
+            url => url,
+        }
+    }
+
+    /// Start the audit log, recording the arguments the given command line
+    /// interface was run with without their values
+    pub fn init_audit_log(&self, cli: &clap::Command) -> Result {
+        let Some(path) = &self.audit_log else {
+            return Ok(());
+        };
+        let command = crate::audit::redact_command(cli, env::args().skip(1));
+        crate::audit::init(path, command)
+    }
+
+    pub fn init_logging(&self) -> Result {
+        if self.log_level.is_none() && self.log_file.is_none() {
+            return Ok(());
+        }
+        let filter = self
+            .log_level
+            .as_deref()
+            .unwrap_or(crate::logging::DEFAULT_LEVEL);
+        crate::logging::init(filter, self.log_file.as_deref())
+    }
+
+    pub fn init_policy(&self) -> Result {
+        let policy = match &self.address_policy {
+            Some(path) => AddressPolicy::load(path, true)?,
+            None => AddressPolicy::load(&config_dir().join(policy::FILE), false)?,
+        };
+        policy::init(policy);
+        Ok(())
+    }
+
+    pub fn init_trace(&self) {
+        let Some(url) = &self.trace_url else {
+            return;
+        };
+        let command = env::args().skip(1).collect::<Vec<String>>().join(" ");
+        crate::trace::init(url, command)
+    }
+
+    pub fn init_password(&self) -> Result {
+        if self.test_seed.is_some() {
+            let _ = STDIN_PASSWORD.set(Zeroizing::default());
+            return Ok(());
+        }
+        // Stdin is only read when asked to, it may carry other input
+        if !self.password_stdin {
+            return Ok(());
+        }
+        let mut line = Zeroizing::new(String::new());
+        io::stdin().read_line(&mut line)?;
+        let password = Zeroizing::new(line.trim_end_matches(['\n', '\r']).to_string());
+        if password.is_empty() {
+            bail!("no password read from stdin");
+        }
+        let _ = STDIN_PASSWORD.set(password);
+        Ok(())
+    }
+
+    pub fn init_cache(&self) -> Result {
+        if self.no_cache {
+            return Ok(());
+        }
+        helium_lib::reward::cache::init(
+            cache_dir().join("oracle-rewards.json"),
+            helium_lib::reward::cache::DEFAULT_TTL,
+        )?;
+        Ok(())
+    }
 }
 
+/// Directory for cached data, following the XDG base directory convention
+pub fn cache_dir() -> PathBuf {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(env::temp_dir);
+    base.join("helium-wallet")
+}
+
+/// Directory for configuration, following the XDG base directory convention
+pub fn config_dir() -> PathBuf {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join("helium-wallet")
+}
+
+/// Password hashing parameters for new wallet files. Raising them makes
+/// guessing the password of a stolen wallet file slower, at the cost of
+/// slower unlocking.
 #[derive(Debug, Clone, clap::Args)]
-pub struct CommitOpts {
-    /// Skip pre-flight
+pub struct PwHashOpts {
+    /// Memory used to hash the wallet password with Argon2id, in MiB
+    #[arg(
+        long,
+        default_value_t = ARGON2ID13_DEFAULT_MEMORY_MIB,
+        value_parser = clap::value_parser!(u32)
+            .range(ARGON2ID13_MIN_MEMORY_MIB as i64..=ARGON2ID13_MAX_MEMORY_MIB as i64)
+    )]
+    pub argon2_memory: u32,
+
+    /// Number of Argon2id passes over memory when hashing the wallet
+    /// password
+    #[arg(
+        long,
+        default_value_t = ARGON2ID13_DEFAULT_PASSES,
+        value_parser = clap::value_parser!(u32).range(1..=ARGON2ID13_MAX_PASSES as i64)
+    )]
+    pub argon2_passes: u32,
+}
+
+impl PwHashOpts {
+    pub fn pwhash(&self) -> PwHash {
+        PwHash::argon2id13(self.argon2_passes, self.argon2_memory)
+    }
+}
+
+/// Options to propose a transaction to a Squads multisig instead of signing
+/// it for the wallet
+#[derive(Debug, Clone, clap::Args)]
+pub struct MultisigOpts {
+    /// Squads multisig to propose the transaction to. The wallet has to be a
+    /// member of the multisig, and the transaction acts on behalf of its
+    /// vault. The proposal is created and approved by the wallet, the other
+    /// members approve and execute it in the Squads app.
+    ///
+    /// Defaults to the multisig the wallet was migrated to with
+    /// "migrate-to-multisig", if any
     #[arg(long)]
-    skip_preflight: bool,
+    pub multisig: Option<helium_lib::keypair::Pubkey>,
+
+    /// Index of the vault of the multisig to act on behalf of
+    #[arg(long, default_value_t = 0, requires = "multisig")]
+    pub vault_index: u8,
+
+    /// Sign for the wallet itself, even if it was migrated to a multisig
+    #[arg(long, conflicts_with = "multisig")]
+    pub no_multisig: bool,
+}
+
+impl MultisigOpts {
+    /// These options with the multisig the given wallet was migrated to
+    /// filled in, unless a multisig was given or --no-multisig was passed
+    pub fn resolve(&self, wallet: &helium_lib::keypair::Pubkey) -> Result<Self> {
+        if self.multisig.is_some() || self.no_multisig {
+            return Ok(self.clone());
+        }
+        let config = MultisigConfig::load()?;
+        let Some(migrated) = config
+            .wallets
+            .get(&wallet.to_string())
+            .filter(|migrated| !migrated.pending)
+        else {
+            return Ok(self.clone());
+        };
+        Ok(Self {
+            multisig: Some(migrated.multisig),
+            vault_index: migrated.vault_index,
+            no_multisig: false,
+        })
+    }
+
+    /// The account the transaction acts on behalf of, the vault of the
+    /// multisig if one is given and the wallet otherwise
+    pub fn authority(&self, wallet: &helium_lib::keypair::Pubkey) -> helium_lib::keypair::Pubkey {
+        match &self.multisig {
+            Some(multisig) => multisig::vault_key(multisig, self.vault_index),
+            None => *wallet,
+        }
+    }
+
+    /// Sign the given message for the wallet, or wrap it in a multisig
+    /// proposal signed by the wallet if a multisig is given
+    pub async fn sign<C: AsRef<SolanaRpcClient>>(
+        &self,
+        client: &C,
+        msg: message::VersionedMessage,
+        signer: &WalletSigner,
+        opts: &TransactionOpts,
+    ) -> Result<(VersionedTransaction, Option<multisig::Proposal>)> {
+        let Some(multisig) = &self.multisig else {
+            return Ok((signer.sign(msg)?, None));
+        };
+        let (msg, _, proposal) = multisig::proposal_message(
+            client,
+            multisig,
+            self.vault_index,
+            &msg,
+            &signer.pubkey(),
+            opts,
+        )
+        .await?;
+        Ok((signer.sign(msg)?, Some(proposal)))
+    }
+}
+
+/// The wallet signing the transactions of a command, or only its public key
+/// when the transactions are built to be signed offline
+pub enum WalletSigner {
+    Keypair(Arc<Keypair>),
+    PublicKey(helium_lib::keypair::Pubkey),
+}
+
+impl WalletSigner {
+    pub fn pubkey(&self) -> helium_lib::keypair::Pubkey {
+        match self {
+            Self::Keypair(keypair) => keypair.pubkey(),
+            Self::PublicKey(pubkey) => *pubkey,
+        }
+    }
+
+    /// The decrypted wallet, for commands that can only build their
+    /// transactions with it
+    pub fn keypair(&self) -> Result<Arc<Keypair>> {
+        match self {
+            Self::Keypair(keypair) => Ok(keypair.clone()),
+            Self::PublicKey(_) => {
+                bail!("this command can not build its transactions with --sign-only")
+            }
+        }
+    }
+
+    /// Sign the given message, or leave every signature empty when only the
+    /// public key of the wallet is known
+    pub fn sign(&self, msg: message::VersionedMessage) -> Result<VersionedTransaction> {
+        match self {
+            Self::Keypair(keypair) => Ok(VersionedTransaction::try_new(msg, &[&**keypair])?),
+            Self::PublicKey(_) => Ok(VersionedTransaction {
+                signatures: vec![
+                    helium_lib::keypair::Signature::default();
+                    usize::from(msg.header().num_required_signatures)
+                ],
+                message: msg,
+            }),
+        }
+    }
+}
+
+/// A multisig a wallet was migrated to
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MigratedMultisig {
+    #[serde(with = "helium_lib::keypair::serde_pubkey")]
+    pub multisig: helium_lib::keypair::Pubkey,
+    pub vault_index: u8,
+    /// Set from before the creation of the multisig is sent until it is
+    /// confirmed, so a creation that lands unnoticed is not lost
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pending: bool,
+}
+
+/// The multisigs wallets were migrated to, by wallet address. Commands that
+/// take multisig options propose to the multisig of their wallet by default.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct MultisigConfig {
+    #[serde(flatten)]
+    pub wallets: std::collections::BTreeMap<String, MigratedMultisig>,
+}
+
+impl MultisigConfig {
+    const FILE: &'static str = "multisig.json";
+
+    pub fn load() -> Result<Self> {
+        let path = config_dir().join(Self::FILE);
+        match fs::File::open(&path) {
+            Ok(file) => serde_json::from_reader(file)
+                .map_err(|err| anyhow!("invalid {}: {err}", path.display())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self) -> Result {
+        fs::create_dir_all(config_dir())?;
+        let file = fs::File::create(config_dir().join(Self::FILE))?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+/// Add the multisig proposal a transaction made, if any, to its output
+pub fn with_proposal(
+    mut json: serde_json::Value,
+    proposal: Option<multisig::Proposal>,
+) -> Result<serde_json::Value> {
+    if let Some(proposal) = proposal {
+        json["proposal"] = serde_json::to_value(proposal)?;
+    }
+    Ok(json)
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct PriorityFeeOpts {
     /// Minimum priority fee in micro lamports
     #[arg(long, default_value_t = priority_fee::MIN_PRIORITY_FEE)]
     min_priority_fee: u64,
     /// Maximum priority fee in micro lamports
     #[arg(long, default_value_t = priority_fee::MAX_PRIORITY_FEE)]
     max_priority_fee: u64,
+    /// Exact priority fee in micro lamports. Skips priority fee estimation
+    #[arg(long, conflicts_with_all = ["min_priority_fee", "max_priority_fee"])]
+    priority_fee_exact: Option<u64>,
+}
+
+impl PriorityFeeOpts {
+    pub fn transaction_opts<C: AsRef<SolanaRpcClient>>(&self, client: &C) -> TransactionOpts {
+        TransactionOpts {
+            min_priority_fee: self.min_priority_fee,
+            max_priority_fee: self.max_priority_fee,
+            exact_priority_fee: self.priority_fee_exact,
+            lut_addresses: if client::is_devnet(&client.as_ref().url()) {
+                vec![message::COMMON_LUT_DEVNET]
+            } else {
+                vec![message::COMMON_LUT]
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct CommitOpts {
+    /// Skip pre-flight
+    #[arg(long)]
+    skip_preflight: bool,
+    #[command(flatten)]
+    priority_fee: PriorityFeeOpts,
+    /// Maximum total fee in SOL, including priority fees, to spend on
+    /// committed transactions.
+    ///
+    /// Commands that submit many transactions stop once the next transaction
+    /// would exceed this total and report the transactions completed so far.
+    #[arg(long)]
+    max_total_fee: Option<f64>,
     /// Commit the transaction
     #[arg(long)]
     commit: bool,
+    /// Output the transaction without the wallet signature, to be signed
+    /// with "sign transaction" and broadcast with "submit". Only the public
+    /// key of the wallet is read, no password is asked for
+    #[arg(long, conflicts_with = "commit")]
+    sign_only: bool,
+    /// Wait for committed transactions to reach the given commitment, one of
+    /// "processed", "confirmed" or "finalized", and report the slot they
+    /// landed in
+    #[arg(
+        long,
+        num_args = 0..=1,
+        default_missing_value = "finalized",
+        requires = "commit"
+    )]
+    wait: Option<CommitmentLevel>,
+    /// Number of times a transaction signed by the wallet alone is rebuilt
+    /// with a fresh blockhash and resubmitted when it expires before it is
+    /// processed. Transactions are waited for until processed when set, and
+    /// others fail once their blockhash expires
+    #[arg(long, default_value_t = 0)]
+    max_retries: usize,
+    /// Maximum time to wait for a transaction with --wait, for example "90s"
+    #[arg(long, default_value = "90s", value_parser = humantime::parse_duration)]
+    wait_timeout: std::time::Duration,
+    /// Wallet file, or name of a saved wallet, to top up the fee payer from
+    /// when it holds too little SOL for a committed transaction. Without it
+    /// such a transaction is refused before it is submitted. Its password is
+    /// read from HELIUM_WALLET_TOPUP_PASSWORD, or from the second line of
+    /// stdin with --password-stdin
+    #[arg(long, requires = "commit")]
+    auto_topup_from: Option<PathBuf>,
+    /// Fees in lamports of the transactions submitted with these options,
+    /// shared between clones
+    #[arg(skip)]
+    fees_spent: Arc<AtomicU64>,
+    /// Keypair of the top up wallet once unlocked, shared between clones so
+    /// its password is asked for once
+    #[arg(skip)]
+    topup_keypair: Arc<Mutex<Option<Arc<Keypair>>>>,
+    /// Keypair of the wallet once loaded for these options, shared between
+    /// clones, so expired transactions it signed alone can be rebuilt
+    #[arg(skip)]
+    wallet_keypair: Arc<OnceLock<Arc<Keypair>>>,
+}
+
+/// A transaction was not submitted because the fee payer holds too little SOL
+/// for it
+#[derive(Debug)]
+pub struct InsufficientSol(pub Preflight);
+
+impl std::fmt::Display for InsufficientSol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let preflight = &self.0;
+        write!(
+            f,
+            "wallet {} holds {} SOL but the transaction needs {} SOL ({} SOL in fees, {} SOL rent for new token accounts, {} SOL transferred), add at least {} SOL or pass --auto-topup-from",
+            preflight.payer,
+            lamports_to_sol(preflight.balance),
+            lamports_to_sol(preflight.required()),
+            lamports_to_sol(preflight.fee),
+            lamports_to_sol(preflight.rent),
+            lamports_to_sol(preflight.transfers),
+            lamports_to_sol(preflight.shortfall()),
+        )
+    }
+}
+
+impl std::error::Error for InsufficientSol {}
+
+/// A transaction was not submitted because its fee would exceed the maximum
+/// total fee of the command
+#[derive(Debug)]
+pub struct FeeCapExceeded {
+    pub cap: u64,
+    pub spent: u64,
+    pub fee: u64,
+}
+
+impl std::fmt::Display for FeeCapExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "fee of {} lamports would exceed the maximum total fee of {} lamports, {} lamports spent",
+            self.fee, self.cap, self.spent
+        )
+    }
+}
+
+impl std::error::Error for FeeCapExceeded {}
+
+impl FeeCapExceeded {
+    /// Whether the given commit result was refused for exceeding the fee cap
+    pub fn is_cause<T>(result: &Result<T>) -> bool {
+        result
+            .as_ref()
+            .is_err_and(|err| err.downcast_ref::<Self>().is_some())
+    }
 }
 
 impl CommitOpts {
+    /// These options, waiting for committed transactions to be at least
+    /// confirmed when --wait is not given. Used where later transactions
+    /// depend on the accounts earlier ones create.
+    pub fn confirming(&self) -> Self {
+        Self {
+            wait: self.wait.or(Some(CommitmentLevel::Confirmed)),
+            ..self.clone()
+        }
+    }
+
+    /// These options, waiting for committed transactions to be finalized.
+    /// Used where accounts are read back after the commit, since reads see
+    /// finalized state.
+    pub fn finalizing(&self) -> Self {
+        Self {
+            wait: Some(CommitmentLevel::Finalized),
+            ..self.clone()
+        }
+    }
+
+    /// Simulate, commit or output the given transaction. With --max-retries a
+    /// transaction signed by the wallet alone is rebuilt with a fresh
+    /// blockhash when it expires, once the wallet was loaded with these
+    /// options.
     pub async fn maybe_commit<C: AsRef<client::SolanaRpcClient>, T: Into<VersionedTransaction>>(
         &self,
         tx: T,
         client: &C,
     ) -> Result<CommitResponse> {
-        fn context_err(client_err: solana_client::client_error::ClientError) -> Error {
-            let mut captured_logs: Option<Vec<String>> = None;
-            let mut error_message: Option<String> = None;
-            if let solana_client::client_error::ClientErrorKind::RpcError(
+        let tx = tx.into();
+        let message = tx.message.clone();
+        let resigner = self.wallet_keypair.get().map(Arc::as_ref);
+        match self.commit_transaction(tx, resigner, client).await {
+            Ok(response) => Ok(response),
+            Err(err) => Err(decode_program_error(client, err, &message, &[]).await),
+        }
+    }
+
+    /// Like maybe_commit but re-signs the transaction with the given keypair
+    /// and a fresh blockhash if the original blockhash has expired by the time
+    /// it is submitted. Only usable for transactions signed by the given
+    /// keypair alone.
+    pub async fn maybe_commit_with_keypair<
+        C: AsRef<client::SolanaRpcClient>,
+        T: Into<VersionedTransaction>,
+    >(
+        &self,
+        tx: T,
+        client: &C,
+        keypair: &Keypair,
+    ) -> Result<CommitResponse> {
+        let tx = tx.into();
+        let message = tx.message.clone();
+        match self.commit_transaction(tx, Some(keypair), client).await {
+            Ok(response) => Ok(response),
+            Err(err) => Err(decode_program_error(client, err, &message, &[]).await),
+        }
+    }
+
+    /// Commit a transaction signed by the given wallet, re-signing it with
+    /// the wallet keypair like maybe_commit_with_keypair when it is known
+    pub async fn maybe_commit_with_signer<
+        C: AsRef<client::SolanaRpcClient>,
+        T: Into<VersionedTransaction>,
+    >(
+        &self,
+        tx: T,
+        client: &C,
+        signer: &WalletSigner,
+    ) -> Result<CommitResponse> {
+        match signer {
+            WalletSigner::Keypair(keypair) => {
+                self.maybe_commit_with_keypair(tx, client, keypair).await
+            }
+            WalletSigner::PublicKey(_) => self.maybe_commit(tx, client).await,
+        }
+    }
+
+    async fn commit_transaction<C: AsRef<client::SolanaRpcClient>>(
+        &self,
+        versioned_tx: VersionedTransaction,
+        resigner: Option<&Keypair>,
+        client: &C,
+    ) -> Result<CommitResponse> {
+        /// Add the message and logs of a failed preflight to the error
+        fn context_err(err: Error) -> Error {
+            let client_err = match err.downcast_ref::<helium_lib::error::Error>() {
+                Some(helium_lib::error::Error::Solana(client_err)) => Some(client_err.as_ref()),
+                _ => err.downcast_ref::<solana_client::client_error::ClientError>(),
+            };
+            let Some(solana_client::client_error::ClientErrorKind::RpcError(
                 solana_client::rpc_request::RpcError::RpcResponseError {
                     data:
                         RpcResponseErrorData::SendTransactionPreflightFailure(
@@ -118,16 +876,13 @@ impl CommitOpts {
                     message,
                     ..
                 },
-            ) = &client_err.kind
-            {
-                logs.clone_into(&mut captured_logs);
-                error_message = Some(message.clone());
-            }
-            let mut mapped = Error::from(client_err);
-            if let Some(message) = error_message {
-                mapped = mapped.context(message);
-            }
-            if let Some(logs) = captured_logs.as_ref() {
+            )) = client_err.map(|client_err| &client_err.kind)
+            else {
+                return err;
+            };
+            let (message, logs) = (message.clone(), logs.clone());
+            let mut mapped = err.context(message);
+            if let Some(logs) = logs.as_ref() {
                 if let Ok(serialized_logs) = serde_json::to_string(logs) {
                     mapped = mapped.context(serialized_logs);
                 }
@@ -135,39 +890,219 @@ impl CommitOpts {
             mapped
         }
 
-        let versioned_tx = tx.into();
-        if self.commit {
-            let config = RpcSendTransactionConfig {
-                skip_preflight: self.skip_preflight,
-                ..Default::default()
-            };
-            client
-                .as_ref()
-                .send_transaction_with_config(&versioned_tx, config)
-                .await
-                .map(Into::into)
-                .map_err(context_err)
-        } else {
-            client
+        if self.sign_only {
+            return Ok(CommitResponse::Unsigned(TransactionEnvelope::unsigned(
+                versioned_tx,
+            )?));
+        }
+        crate::audit::record_transaction(&versioned_tx)?;
+        if !self.commit {
+            let simulated = client
                 .as_ref()
                 .simulate_transaction(&versioned_tx)
                 .await
-                .map_err(context_err)?
-                .value
-                .try_into()
+                .map_err(|err| context_err(err.into()))?
+                .value;
+            let logs = simulated.logs.clone().unwrap_or_default();
+            return match CommitResponse::try_from(simulated) {
+                Ok(response) => Ok(response),
+                Err(err) => {
+                    Err(decode_program_error(client, err, &versioned_tx.message, &logs).await)
+                }
+            };
+        }
+
+        let fee = simulation::fee(&versioned_tx.message);
+        self.check_fee_cap(fee)?;
+        if !self.skip_preflight {
+            self.check_balance(client, &versioned_tx).await?;
+        }
+        let opts = SendOpts {
+            max_retries: self.max_retries,
+            skip_preflight: self.skip_preflight,
+        };
+        let sent = transaction::send_with_retry(
+            client,
+            versioned_tx,
+            None,
+            resigner,
+            &opts,
+            crate::audit::record_transaction,
+        )
+        .await
+        .map_err(context_err)?;
+        self.fees_spent.fetch_add(fee, Ordering::Relaxed);
+        crate::trace::submitted(&sent.signature, &sent.transaction, &client.as_ref().url());
+        match self.wait {
+            Some(commitment) => self.confirm(client, &sent.transaction, commitment).await,
+            None => Ok(sent.signature.into()),
+        }
+    }
+
+    async fn confirm<C: AsRef<client::SolanaRpcClient>>(
+        &self,
+        client: &C,
+        tx: &VersionedTransaction,
+        commitment: CommitmentLevel,
+    ) -> Result<CommitResponse> {
+        let confirmation = confirm::wait(client, tx, commitment, self.wait_timeout).await?;
+        Ok(CommitResponse::Confirmed(confirmation))
+    }
+
+    /// Make sure the fee payer of the given transaction holds the SOL it
+    /// needs, topping it up from the top up wallet if one is given
+    async fn check_balance<C: AsRef<client::SolanaRpcClient>>(
+        &self,
+        client: &C,
+        tx: &VersionedTransaction,
+    ) -> Result {
+        let preflight = preflight::check(client, &tx.message).await?;
+        let shortfall = preflight.shortfall();
+        if shortfall == 0 {
+            return Ok(());
         }
+        let Some(path) = &self.auto_topup_from else {
+            return Err(InsufficientSol(preflight).into());
+        };
+        let keypair = self.topup_keypair(path)?;
+        let (topup, _) = token::transfer(
+            client,
+            &[(preflight.payer, Token::Sol.amount(shortfall))],
+            None,
+            &[],
+            &keypair,
+            &self.transaction_opts(client),
+        )
+        .await?;
+        crate::audit::record_transaction(&topup)?;
+        let fee = simulation::fee(&topup.message);
+        self.check_fee_cap(fee)?;
+        client
+            .as_ref()
+            .send_and_confirm_transaction(&topup)
+            .await
+            .with_context(|| format!("failed to top up {} with SOL", preflight.payer))?;
+        self.fees_spent.fetch_add(fee, Ordering::Relaxed);
+        warnings::warn(
+            warnings::SOL_TOPUP,
+            format!(
+                "topped up {} with {} SOL from {}",
+                preflight.payer,
+                lamports_to_sol(shortfall),
+                keypair.pubkey()
+            ),
+        );
+        Ok(())
+    }
+
+    fn topup_keypair(&self, path: &Path) -> Result<Arc<Keypair>> {
+        let mut topup_keypair = self
+            .topup_keypair
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        if let Some(keypair) = topup_keypair.as_ref() {
+            return Ok(keypair.clone());
+        }
+        let mut reader = fs::File::open(wallets::resolve(path)?)?;
+        let wallet = Wallet::read(&mut reader)?;
+        let password = get_topup_password()?;
+        let keypair = wallet.decrypt(password.as_bytes())?;
+        *topup_keypair = Some(keypair.clone());
+        Ok(keypair)
+    }
+
+    /// Fees in lamports of the transactions committed so far
+    pub fn fees_spent(&self) -> u64 {
+        self.fees_spent.load(Ordering::Relaxed)
+    }
+
+    fn check_fee_cap(&self, fee: u64) -> Result {
+        let Some(max_total_fee) = self.max_total_fee else {
+            return Ok(());
+        };
+        let cap = sol_to_lamports(max_total_fee);
+        let spent = self.fees_spent();
+        if spent.saturating_add(fee) > cap {
+            return Err(FeeCapExceeded { cap, spent, fee }.into());
+        }
+        Ok(())
     }
 
     pub fn transaction_opts<C: AsRef<SolanaRpcClient>>(&self, client: &C) -> TransactionOpts {
-        TransactionOpts {
-            min_priority_fee: self.min_priority_fee,
-            max_priority_fee: self.max_priority_fee,
-            lut_addresses: if client::is_devnet(&client.as_ref().url()) {
-                vec![message::COMMON_LUT_DEVNET]
-            } else {
-                vec![message::COMMON_LUT]
-            },
+        self.priority_fee.transaction_opts(client)
+    }
+}
+
+/// Add the decoded custom program error, if any, that a transaction with the
+/// given message failed with to the error. The program logs carried by the
+/// error are used to name errors the program's IDL does not list, or the
+/// given logs if it has none.
+async fn decode_program_error<C: AsRef<client::SolanaRpcClient>>(
+    client: &C,
+    err: Error,
+    message: &VersionedMessage,
+    logs: &[String],
+) -> Error {
+    use helium_lib::{program_error, solana_sdk::transaction::TransactionError};
+    fn client_failure(
+        err: &solana_client::client_error::ClientError,
+    ) -> Option<(TransactionError, Vec<String>)> {
+        let tx_err = err.get_transaction_error()?;
+        let logs = match &err.kind {
+            solana_client::client_error::ClientErrorKind::RpcError(
+                solana_client::rpc_request::RpcError::RpcResponseError {
+                    data:
+                        RpcResponseErrorData::SendTransactionPreflightFailure(
+                            RpcSimulateTransactionResult { logs, .. },
+                        ),
+                    ..
+                },
+            ) => logs.clone().unwrap_or_default(),
+            _ => vec![],
+        };
+        Some((tx_err, logs))
+    }
+
+    if err.downcast_ref::<program_error::DecodedError>().is_some() {
+        return err;
+    }
+    let failure = err.chain().find_map(|cause| {
+        if let Some(client_err) = cause.downcast_ref::<solana_client::client_error::ClientError>() {
+            return client_failure(client_err);
+        }
+        match cause.downcast_ref::<helium_lib::error::Error>() {
+            Some(helium_lib::error::Error::Solana(client_err)) => {
+                return client_failure(client_err)
+            }
+            Some(helium_lib::error::Error::Confirm(confirm::ConfirmError::Failed(_, tx_err))) => {
+                return Some((tx_err.clone(), vec![]))
+            }
+            _ => (),
         }
+        cause
+            .downcast_ref::<TransactionError>()
+            .map(|tx_err| (tx_err.clone(), vec![]))
+    });
+    let Some((tx_err, failure_logs)) = failure else {
+        return err;
+    };
+    let logs = if failure_logs.is_empty() {
+        logs
+    } else {
+        failure_logs.as_slice()
+    };
+    // The logs still name the error when the IDL can not be fetched
+    let errors = match program_error::failed_program(message, &tx_err) {
+        Some(program_id) => program_error::program_errors(client, &program_id)
+            .await
+            .ok()
+            .flatten(),
+        None => None,
+    };
+    let decoded = program_error::decode(message, &tx_err, errors.as_deref(), logs);
+    match decoded {
+        Some(decoded) => err.context(decoded),
+        None => err,
     }
 }
 
@@ -189,21 +1124,60 @@ impl std::str::FromStr for Transaction {
     }
 }
 
-fn get_wallet_password(confirm: bool) -> std::io::Result<String> {
+/// Password read from stdin when `--password-stdin` is given
+static STDIN_PASSWORD: OnceLock<Zeroizing<String>> = OnceLock::new();
+
+fn get_wallet_password(confirm: bool) -> std::io::Result<Zeroizing<String>> {
+    if let Some(password) = STDIN_PASSWORD.get() {
+        return Ok(password.clone());
+    }
     match env::var("HELIUM_WALLET_PASSWORD") {
-        Ok(str) => Ok(str),
+        Ok(str) => Ok(Zeroizing::new(str)),
         _ => get_password("Wallet Password", confirm),
     }
 }
 
-fn get_password(prompt: &str, confirm: bool) -> std::io::Result<String> {
+/// Password of the wallet given with --auto-topup-from. It is read from
+/// HELIUM_WALLET_TOPUP_PASSWORD, else from the line after the wallet password
+/// with --password-stdin, else prompted for on the terminal.
+fn get_topup_password() -> std::io::Result<Zeroizing<String>> {
+    if let Ok(password) = env::var("HELIUM_WALLET_TOPUP_PASSWORD") {
+        return Ok(Zeroizing::new(password));
+    }
+    if STDIN_PASSWORD
+        .get()
+        .is_some_and(|password| !password.is_empty())
+    {
+        let mut line = Zeroizing::new(String::new());
+        io::stdin().read_line(&mut line)?;
+        let password = Zeroizing::new(line.trim_end_matches(['\n', '\r']).to_string());
+        if password.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "no top up wallet password read from stdin",
+            ));
+        }
+        return Ok(password);
+    }
+    get_password("Top Up Wallet Password", false)
+}
+
+/// Prompt for a password on the controlling terminal, without echoing it.
+/// The prompt reads from the terminal rather than stdin, so it works with
+/// redirected input as long as there is a terminal.
+fn get_password(prompt: &str, confirm: bool) -> std::io::Result<Zeroizing<String>> {
     use dialoguer::Password;
     let mut builder = Password::new();
     builder.with_prompt(prompt);
     if confirm {
         builder.with_confirmation("Confirm password", "Passwords do not match");
     };
-    builder.interact()
+    builder.interact().map(Zeroizing::new).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("prompting for a password: {err}, use --password-stdin or set HELIUM_WALLET_PASSWORD without a terminal"),
+        )
+    })
 }
 
 pub fn open_output_file(filename: &Path, create: bool) -> io::Result<fs::File> {
@@ -225,14 +1199,102 @@ pub fn get_file_extension(filename: &Path) -> String {
         .to_string()
 }
 
+/// Output options for listing commands
+#[derive(Debug, Clone, clap::Args)]
+pub struct OutputOpts {
+    /// Write the output to the given file instead of stdout
+    #[arg(long)]
+    out: Option<PathBuf>,
+    /// Write newline delimited JSON, one result per line as results arrive,
+    /// instead of a single JSON document once all results are in
+    #[arg(long)]
+    ndjson: bool,
+}
+
+impl OutputOpts {
+    pub fn sink(&self) -> Result<OutputSink> {
+        let writer: Box<dyn io::Write> = match &self.out {
+            Some(path) => Box::new(io::BufWriter::new(fs::File::create(path)?)),
+            None => Box::new(io::stdout()),
+        };
+        Ok(OutputSink {
+            writer,
+            ndjson: self.ndjson,
+        })
+    }
+}
+
+/// Destination for the output of a listing command
+pub struct OutputSink {
+    writer: Box<dyn io::Write>,
+    ndjson: bool,
+}
+
+impl OutputSink {
+    pub fn is_ndjson(&self) -> bool {
+        self.ndjson
+    }
+
+    /// The destination itself, for output that is not JSON
+    pub fn writer(&mut self) -> &mut dyn io::Write {
+        &mut self.writer
+    }
+
+    /// Write a complete JSON document, pretty printed unless newline
+    /// delimited JSON was requested
+    pub fn write_json<T: ?Sized + serde::Serialize>(&mut self, value: &T) -> Result {
+        if crate::warnings::get().is_empty() {
+            self.write_document(value)
+        } else {
+            let value = crate::warnings::attach(serde_json::to_value(value)?);
+            self.write_document(&value)
+        }
+    }
+
+    fn write_document<T: ?Sized + serde::Serialize>(&mut self, value: &T) -> Result {
+        use std::io::Write;
+        if self.ndjson {
+            serde_json::to_writer(&mut self.writer, value)?;
+        } else {
+            serde_json::to_writer_pretty(&mut self.writer, value)?;
+        }
+        writeln!(self.writer)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Write each of the given results on its own line
+    pub fn write_items<T: serde::Serialize>(
+        &mut self,
+        items: impl IntoIterator<Item = T>,
+    ) -> Result {
+        use std::io::Write;
+        for item in items {
+            serde_json::to_writer(&mut self.writer, &item)?;
+            writeln!(self.writer)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Print the given value as pretty printed JSON, with any warnings raised by
+/// the command attached
 pub fn print_json<T: ?Sized + serde::Serialize>(value: &T) -> Result {
-    println!("{}", serde_json::to_string_pretty(value)?);
+    if crate::warnings::get().is_empty() {
+        println!("{}", serde_json::to_string_pretty(value)?);
+    } else {
+        let value = crate::warnings::attach(serde_json::to_value(value)?);
+        println!("{}", serde_json::to_string_pretty(&value)?);
+    }
     Ok(())
 }
 
 #[derive(Debug, serde::Serialize)]
 pub enum CommitResponse {
     Signature(helium_lib::keypair::Signature),
+    Confirmed(Confirmation),
+    Unsigned(TransactionEnvelope),
     None,
 }
 
@@ -262,6 +1324,17 @@ impl ToJson for CommitResponse {
                 "result": "ok",
                 "txid": signature.to_string(),
             }),
+            Self::Confirmed(confirmation) => json!({
+                "result": "ok",
+                "txid": confirmation.signature,
+                "slot": confirmation.slot,
+                "commitment": confirmation.commitment,
+                "finalized": confirmation.is_finalized(),
+            }),
+            Self::Unsigned(envelope) => json!({
+                "result": "unsigned",
+                "envelope": envelope,
+            }),
             Self::None => json!({"result": "ok"}),
         }
     }
@@ -271,10 +1344,17 @@ impl ToJson for Result<CommitResponse> {
     fn to_json(&self) -> serde_json::Value {
         match self {
             Ok(response) => response.to_json(),
-            Err(err) => json!({
-                "result": "error",
-                "error": err.to_string()
-            }),
+            Err(err) => {
+                let mut json = json!({
+                    "result": "error",
+                    "error": err.to_string()
+                });
+                if let Some(decoded) = err.downcast_ref::<helium_lib::program_error::DecodedError>()
+                {
+                    json["program_error"] = json!(decoded);
+                }
+                json
+            }
         }
     }
 }
@@ -291,6 +1371,95 @@ pub fn print_simulation_response(
     }))
 }
 
+/// Simulate the given transactions and print a report of the expected token
+/// balance changes, compute units and fees of each transaction, aggregated
+/// across all transactions. Returns whether all simulations succeeded.
+pub async fn print_detailed_simulation<C: AsRef<SolanaRpcClient>>(
+    client: &C,
+    txns: &[VersionedTransaction],
+) -> Result<bool> {
+    use helium_lib::simulation;
+    use std::collections::BTreeMap;
+
+    let mut simulations = Vec::with_capacity(txns.len());
+    for txn in txns {
+        simulations.push(simulation::simulate(client, txn).await?);
+    }
+
+    let mut deltas: BTreeMap<(String, String), i128> = BTreeMap::new();
+    for delta in simulations.iter().flat_map(|sim| &sim.token_deltas) {
+        *deltas
+            .entry((delta.owner.to_string(), delta.token().to_string()))
+            .or_default() += delta.delta();
+    }
+    let success = simulations.iter().all(|sim| sim.err.is_none());
+    let total = json!({
+        "success": success,
+        "transactions": simulations.len(),
+        "compute_units": simulations.iter().filter_map(|sim| sim.compute_units).sum::<u64>(),
+        "fee": simulations.iter().map(|sim| sim.fee).sum::<u64>(),
+        "token_deltas": deltas
+            .into_iter()
+            .map(|((owner, token), delta)| json!({
+                "owner": owner,
+                "token": token,
+                "delta": delta.to_string(),
+            }))
+            .collect::<Vec<_>>(),
+    });
+    print_json(&json!({
+        "simulations": simulations,
+        "total": total,
+    }))?;
+    Ok(success)
+}
+
+/// Parse a Solana address. The Helium address of an ed25519 key is accepted
+/// as the Solana address of the same key, which is deprecated.
+pub fn parse_address(s: &str) -> Result<helium_lib::keypair::Pubkey> {
+    if let Ok(address) = s.parse() {
+        return Ok(address);
+    }
+    let helium_address: helium_crypto::PublicKey =
+        s.parse().map_err(|_| anyhow!("invalid address: {s}"))?;
+    let address = helium_lib::keypair::to_pubkey(&helium_address)?;
+    warnings::warn(
+        warnings::LEGACY_ADDRESS,
+        format!("Helium address {s} is deprecated, use its Solana address {address}"),
+    );
+    Ok(address)
+}
+
+/// The lower case name of a token. Upper case names like "HNT" are accepted
+/// but deprecated.
+fn token_name(s: &str) -> String {
+    let name = s.to_lowercase();
+    if name != s {
+        warnings::warn(
+            warnings::DEPRECATED_TOKEN_NAME,
+            format!("token name \"{s}\" is deprecated, use \"{name}\""),
+        );
+    }
+    name
+}
+
+pub fn transferrable_token(s: &str) -> std::result::Result<Token, token::TokenError> {
+    Token::transferrable_value_parser(&token_name(s))
+}
+
+pub fn burnable_token(s: &str) -> std::result::Result<Token, token::TokenError> {
+    Token::burnable_value_parser(&token_name(s))
+}
+
+pub fn pricekey_token(s: &str) -> std::result::Result<Token, token::TokenError> {
+    Token::pricekey_value_parser(&token_name(s))
+}
+
+/// Ask for confirmation on the terminal
+pub fn confirm(prompt: &str) -> Result<bool> {
+    Ok(dialoguer::Confirm::new().with_prompt(prompt).interact()?)
+}
+
 pub fn phrase_to_words(phrase: &str) -> Vec<&str> {
     phrase.split_whitespace().collect()
 }
@@ -1,19 +1,216 @@
-use crate::cmd::*;
-use helium_lib::token;
+use crate::cmd::{history::ExportFormat, *};
+use chrono::{DateTime, NaiveDate, Utc};
+use helium_lib::token::{
+    self,
+    price::{self, LiveSource, PriceSource, SourcedPrice},
+    Token,
+};
+use std::time::Duration;
 
 #[derive(Clone, Debug, clap::Args)]
-/// Get the current price from the pyth price feed for the given token
+/// Get the current price from the pyth price feed for the given token.
+///
+/// When the pyth price is stale or unavailable the price is taken from the
+/// first fallback source that has one, and the source used is reported. With
+/// --at or --range, historical USD prices are looked up from the given price
+/// source instead.
 pub struct Cmd {
     /// Token to look up
-    #[arg(value_parser = token::Token::pricekey_value_parser)]
-    token: token::Token,
+    #[arg(
+        value_parser = pricekey_token,
+        required_unless_present = "list"
+    )]
+    token: Option<token::Token>,
+    /// List the available price feeds
+    #[arg(long, conflicts_with = "token")]
+    list: bool,
+    /// Display the feed account, publish time, confidence and the spot and
+    /// EMA prices reported by the price feed
+    #[arg(long, conflicts_with_all = ["at", "range"])]
+    feed: bool,
+    /// Get the price at a past time, as an RFC 3339 timestamp or YYYY-MM-DD
+    /// in UTC
+    #[arg(long, value_parser = parse_time, conflicts_with = "range")]
+    at: Option<DateTime<Utc>>,
+    /// Get the prices in a past range of time, as "start..end" with each end
+    /// an RFC 3339 timestamp or YYYY-MM-DD in UTC
+    #[arg(long, value_parser = parse_range)]
+    range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    /// Time between the prices of a range, for example "1h" or "1day"
+    #[arg(long, default_value = "1h", value_parser = humantime::parse_duration, requires = "range")]
+    interval: Duration,
+    /// Source of historical prices
+    #[arg(long, value_enum, default_value_t)]
+    source: PriceSource,
+    /// Output format of a range of prices
+    #[arg(long, value_enum, default_value_t = ExportFormat::Json, requires = "range")]
+    format: ExportFormat,
+    #[command(flatten)]
+    live: LivePriceOpts,
+}
+
+/// Options for looking up a current price
+#[derive(Clone, Debug, clap::Args)]
+pub struct LivePriceOpts {
+    /// Sources to try, in order, when the pyth price is stale or unavailable:
+    /// "coingecko" or "switchboard:<feed hash>"
+    #[arg(long = "fallback", value_delimiter = ',', default_value = "coingecko")]
+    fallbacks: Vec<LiveSource>,
+    /// Fail instead of falling back when the pyth price is stale or
+    /// unavailable
+    #[arg(long)]
+    require_pyth: bool,
+}
+
+impl LivePriceOpts {
+    /// Whether only pyth prices are accepted
+    pub fn requires_pyth(&self) -> bool {
+        self.require_pyth
+    }
+
+    /// The sources to try in order, always starting with pyth
+    pub fn sources(&self) -> Vec<LiveSource> {
+        let mut sources = vec![LiveSource::Pyth];
+        if !self.require_pyth {
+            sources.extend(
+                self.fallbacks
+                    .iter()
+                    .filter(|source| **source != LiveSource::Pyth)
+                    .cloned(),
+            );
+        }
+        sources
+    }
+
+    /// The current price of a token from the first source that has one.
+    /// Falling back to a secondary source raises a warning.
+    pub async fn get<C: AsRef<SolanaRpcClient>>(
+        &self,
+        client: &C,
+        token: Token,
+    ) -> Result<SourcedPrice> {
+        let price = price::get_with_fallback(
+            client,
+            token,
+            &self.sources(),
+            chrono::Duration::minutes(10),
+        )
+        .await?;
+        if price.is_fallback() {
+            warnings::warn(
+                warnings::FALLBACK_PRICE,
+                format!("{token} price from {} since pyth had none", price.source),
+            );
+        }
+        Ok(price)
+    }
 }
 
 impl Cmd {
     pub async fn run(&self, opts: Opts) -> Result {
+        if self.list {
+            return print_json(&token::price::feeds());
+        }
+        let Some(token) = self.token else {
+            bail!("no token specified");
+        };
+        if let Some(at) = self.at {
+            let price = price::history_at(self.source, token, at).await?;
+            return print_json(&price);
+        }
+        if let Some((since, until)) = self.range {
+            let interval = chrono::Duration::from_std(self.interval)?;
+            let prices =
+                price::history_with_interval(self.source, token, since, until, interval).await?;
+            return match self.format {
+                ExportFormat::Json => print_json(&prices),
+                ExportFormat::Csv => write_csv(io::stdout().lock(), &prices),
+            };
+        }
         let client = opts.client()?;
-        let price = token::price::get(&client, self.token).await?;
+        if self.feed {
+            let info = token::price::get_feed_info(&client, token).await?;
+            return print_json(&info);
+        }
+        let price = self.live.get(&client, token).await?;
 
         print_json(&price)
     }
 }
+
+fn write_csv<W: io::Write>(writer: W, prices: &[price::Price]) -> Result {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(["timestamp", "token", "price"])?;
+    for price in prices {
+        writer.write_record([
+            price.timestamp.to_rfc3339(),
+            price.token.to_string(),
+            price.price.to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Parse an RFC 3339 timestamp or a YYYY-MM-DD date, taken as the start of
+/// the day in UTC
+fn parse_time(s: &str) -> Result<DateTime<Utc>> {
+    if let Ok(day) = s.parse::<NaiveDate>() {
+        if let Some(time) = day.and_hms_opt(0, 0, 0) {
+            return Ok(time.and_utc());
+        }
+    }
+    DateTime::parse_from_rfc3339(s)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| anyhow!("invalid time {s}, expected an RFC 3339 timestamp or YYYY-MM-DD"))
+}
+
+fn parse_range(s: &str) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let (start, end) = s
+        .split_once("..")
+        .ok_or_else(|| anyhow!("invalid range {s}, expected start..end"))?;
+    let (start, end) = (parse_time(start)?, parse_time(end)?);
+    if start >= end {
+        bail!("range start must be before its end");
+    }
+    Ok((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range() {
+        let (start, end) = parse_range("2024-01-01..2024-01-02T12:00:00Z").expect("range");
+        assert_eq!(start.to_rfc3339(), "2024-01-01T00:00:00+00:00");
+        assert_eq!(end.to_rfc3339(), "2024-01-02T12:00:00+00:00");
+        assert!(parse_range("2024-01-02..2024-01-01").is_err());
+        assert!(parse_range("2024-01-01").is_err());
+    }
+
+    #[test]
+    fn live_sources() {
+        let opts = LivePriceOpts {
+            fallbacks: vec![
+                "switchboard:0xab12".parse().expect("source"),
+                LiveSource::Coingecko,
+            ],
+            require_pyth: false,
+        };
+        assert_eq!(
+            opts.sources()
+                .iter()
+                .map(|source| source.to_string())
+                .collect::<Vec<_>>(),
+            ["pyth", "switchboard:ab12", "coingecko"]
+        );
+        let strict = LivePriceOpts {
+            require_pyth: true,
+            ..opts
+        };
+        assert_eq!(strict.sources(), [LiveSource::Pyth]);
+        assert!("switchboard:".parse::<LiveSource>().is_err());
+        assert!("binance".parse::<LiveSource>().is_err());
+    }
+}
@@ -1,13 +1,45 @@
 use crate::cmd::*;
-use helium_lib::{dao::SubDao, token};
+use helium_lib::{
+    dao::SubDao,
+    keypair::Pubkey,
+    token::{self, TokenAmount},
+};
+use std::time::Duration;
+
+/// Time allowed for the webhook to accept an alert
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Debug, clap::Args)]
 /// Get the Delegated DC balance for a given router key. The balance is in Data
 /// Credits (DC)
+///
+/// With --threshold the command exits with status 2 when the balance is below
+/// the threshold, or posts an alert to the given --webhook instead.
+///
+/// With --watch the escrow account is polled at the given interval and a line
+/// of JSON is printed for every poll. Without a webhook the command exits with
+/// status 2 as soon as the balance falls below the threshold. With one, an
+/// alert is posted once every time the balance falls below the threshold and
+/// watching continues. Polls that fail with an error that may go away, like
+/// an unavailable RPC node, are warned about and retried at the next
+/// interval.
 pub struct Cmd {
     #[arg(value_enum)]
     pub subdao: SubDao,
     pub router_key: String,
+    /// Alert when the balance is below this many DC
+    #[arg(long)]
+    pub threshold: Option<u64>,
+    /// POST a JSON alert to this url when the balance is below the threshold
+    /// instead of exiting with an error status
+    #[arg(long, requires = "threshold")]
+    pub webhook: Option<String>,
+    /// Keep polling the balance
+    #[arg(long)]
+    pub watch: bool,
+    /// Time between polls, for example "30s" or "5m"
+    #[arg(long, default_value = "5m", value_parser = humantime::parse_duration, requires = "watch")]
+    pub interval: Duration,
 }
 
 impl Cmd {
@@ -15,15 +47,127 @@ impl Cmd {
         let delegated_dc_key = self.subdao.delegated_dc_key(&self.router_key);
         let escrow_key = self.subdao.escrow_key(&delegated_dc_key);
         let client = opts.client()?;
-        let balance = token::balance_for_address(&client, &escrow_key)
-            .await?
-            .map(|balance| balance.amount);
-        let json = json!({
+
+        let mut alerted = false;
+        loop {
+            let balance = match token::balance_for_address(&client, &escrow_key).await {
+                Ok(balance) => balance.map(|balance| balance.amount).unwrap_or_default(),
+                Err(err) if self.watch && err.is_retryable() => {
+                    warnings::warn(
+                        warnings::POLL_FAILED,
+                        format!("failed to get router balance, retrying: {err}"),
+                    );
+                    tokio::time::sleep(self.interval).await;
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let below = self
+                .threshold
+                .is_some_and(|threshold| balance.amount < threshold);
+            let json = json!({
+                "router": self.router_key,
+                "delegated_dc_key": delegated_dc_key.to_string(),
+                "escrow_key": escrow_key.to_string(),
+                "balance": balance,
+                "threshold": self.threshold,
+                "below_threshold": self.threshold.map(|_| below),
+            });
+            if self.watch {
+                println!("{json}");
+            } else {
+                print_json(&json)?;
+            }
+
+            match (&self.webhook, self.threshold) {
+                // Alert again only after the balance recovered, or when
+                // posting the alert failed
+                _ if !below => alerted = false,
+                (Some(url), _) if !alerted => {
+                    alerted = self
+                        .alert(url, &delegated_dc_key, &escrow_key, balance)
+                        .await
+                }
+                (None, Some(threshold)) => {
+                    return Err(BelowThreshold {
+                        router: self.router_key.clone(),
+                        balance: balance.amount,
+                        threshold,
+                    }
+                    .into())
+                }
+                _ => (),
+            }
+
+            if !self.watch {
+                return Ok(());
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    /// Post a low balance alert to the webhook, returning whether it was
+    /// accepted. A webhook that can not be reached is a warning so watching
+    /// continues.
+    async fn alert(
+        &self,
+        url: &str,
+        delegated_dc_key: &Pubkey,
+        escrow_key: &Pubkey,
+        balance: TokenAmount,
+    ) -> bool {
+        let alert = json!({
+            "timestamp": chrono::Utc::now(),
+            "subdao": self.subdao,
             "router": self.router_key,
             "delegated_dc_key": delegated_dc_key.to_string(),
             "escrow_key": escrow_key.to_string(),
-            "balance": balance.unwrap_or_default(),
+            "balance": balance,
+            "threshold": self.threshold,
         });
-        print_json(&json)
+        let sent = async {
+            helium_lib::network::apply(reqwest::Client::builder())
+                .timeout(WEBHOOK_TIMEOUT)
+                .build()?
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(alert.to_string())
+                .send()
+                .await?
+                .error_for_status()
+        };
+        if let Err(err) = sent.await {
+            warnings::warn(
+                warnings::WEBHOOK_FAILED,
+                format!("failed to post low balance alert: {err}"),
+            );
+            return false;
+        }
+        true
+    }
+}
+
+/// The delegated DC balance of a router is below the alert threshold. The
+/// wallet exits with [`BelowThreshold::EXIT_STATUS`] on this error.
+#[derive(Debug)]
+pub struct BelowThreshold {
+    pub router: String,
+    pub balance: u64,
+    pub threshold: u64,
+}
+
+impl BelowThreshold {
+    pub const EXIT_STATUS: i32 = 2;
+}
+
+impl std::fmt::Display for BelowThreshold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "delegated DC balance {} of router {} is below the threshold of {}",
+            self.balance, self.router, self.threshold
+        )
     }
 }
+
+impl std::error::Error for BelowThreshold {}
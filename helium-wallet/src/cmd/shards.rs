@@ -0,0 +1,153 @@
+use crate::{
+    cmd::*,
+    pairing::{self, Channel, Role},
+    wallet::Wallet,
+};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Debug, clap::Args)]
+pub struct Cmd {
+    #[command(subcommand)]
+    cmd: ShardsCommand,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        self.cmd.run(opts).await
+    }
+}
+
+/// Move wallet shards between machines.
+///
+/// The receiving side prints a pairing code which is entered on the sending
+/// side. The shard is sent over a connection encrypted with a key agreed on
+/// with the code, so it is never exposed to the network in plain text. A
+/// receiver accepts a single attempt, run it again after a failed one.
+#[derive(Debug, clap::Subcommand)]
+pub enum ShardsCommand {
+    Send(SendCmd),
+    Receive(ReceiveCmd),
+}
+
+impl ShardsCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::Send(cmd) => cmd.run(opts).await,
+            Self::Receive(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+/// A shard file as sent to the receiver
+#[derive(Debug, Serialize, Deserialize)]
+struct ShardTransfer {
+    name: String,
+    data: String,
+}
+
+const ACK: &[u8] = b"received";
+
+/// Send a shard file to a receiver
+#[derive(Debug, clap::Args)]
+pub struct SendCmd {
+    /// The shard file to send
+    input: PathBuf,
+    /// Address of the receiver, as host:port
+    to: String,
+    /// The pairing code shown by the receiver. Prompted for if not given
+    #[arg(long)]
+    code: Option<String>,
+}
+
+impl SendCmd {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        let data = fs::read(&self.input)?;
+        check_shard(&data)?;
+        let name = self
+            .input
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("invalid shard file name"))?
+            .to_string();
+        let code = match &self.code {
+            Some(code) => code.clone(),
+            None => dialoguer::Input::<String>::new()
+                .with_prompt("Pairing code")
+                .interact()?,
+        };
+
+        let stream = TcpStream::connect(&self.to).await?;
+        let mut channel = Channel::pair(stream, &code, Role::Sender).await?;
+        let transfer = ShardTransfer {
+            name,
+            data: b64::encode(&data),
+        };
+        channel.send(&serde_json::to_vec(&transfer)?).await?;
+        if channel.recv().await? != ACK {
+            bail!("receiver did not confirm the shard");
+        }
+        print_json(&json!({
+            "result": "ok",
+            "sent": self.input,
+        }))
+    }
+}
+
+/// Wait for a shard from a sender
+#[derive(Debug, clap::Args)]
+pub struct ReceiveCmd {
+    /// Address to listen on
+    #[arg(long, default_value_t = SocketAddr::from(([0, 0, 0, 0], pairing::DEFAULT_PORT)))]
+    listen: SocketAddr,
+    /// File to write the shard to. Defaults to the name of the sent file in
+    /// the current directory
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Overwrite an existing file
+    #[arg(long)]
+    force: bool,
+}
+
+impl ReceiveCmd {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        let code = pairing::generate_code();
+        let listener = TcpListener::bind(self.listen).await?;
+        eprintln!("Pairing code: {code}");
+        eprintln!("Waiting for a shard on {}", listener.local_addr()?);
+
+        let (stream, peer) = listener.accept().await?;
+        let mut channel = Channel::pair(stream, &code, Role::Receiver).await?;
+        let transfer: ShardTransfer = serde_json::from_slice(&channel.recv().await?)?;
+        let data = b64::decode(&transfer.data)?;
+        let wallet = check_shard(&data)?;
+        let output = match &self.output {
+            Some(output) => output.clone(),
+            None => PathBuf::from(
+                Path::new(&transfer.name)
+                    .file_name()
+                    .ok_or_else(|| anyhow!("invalid shard file name"))?,
+            ),
+        };
+        let mut file = open_output_file(&output, !self.force)?;
+        io::Write::write_all(&mut file, &data)?;
+        channel.send(ACK).await?;
+
+        print_json(&json!({
+            "result": "ok",
+            "from": peer.to_string(),
+            "output": output,
+            "address": wallet.address()?,
+        }))
+    }
+}
+
+/// Check that the given data is a wallet shard
+fn check_shard(data: &[u8]) -> Result<Wallet> {
+    let wallet = Wallet::read(&mut &data[..])?;
+    if !wallet.is_sharded() {
+        bail!("not a wallet shard");
+    }
+    Ok(wallet)
+}
@@ -1,5 +1,12 @@
-use crate::cmd::*;
+use crate::{audit, cmd::*, jcs, offline::TransactionEnvelope};
+use helium_lib::keypair::{Pubkey, Signature, Signer};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use sha2::{Digest, Sha256};
+
+/// Prefix of the signed digest of a JSON payload. Keeps signatures over JSON
+/// payloads distinct from signatures over transactions or plain messages.
+const JSON_SIGNING_PREFIX: &[u8] = b"\x19Helium Signed JSON:\n";
 
 #[derive(Debug, clap::Args)]
 pub struct Cmd {
@@ -18,6 +25,8 @@ impl Cmd {
 pub enum SubCmd {
     File(File),
     Msg(Msg),
+    Json(Json),
+    Transaction(Transaction),
     Verify(VerifyCmd),
 }
 
@@ -26,6 +35,8 @@ impl SubCmd {
         match self {
             Self::File(cmd) => cmd.run(opts).await,
             Self::Msg(cmd) => cmd.run(opts).await,
+            Self::Json(cmd) => cmd.run(opts).await,
+            Self::Transaction(cmd) => cmd.run(opts).await,
             Self::Verify(cmd) => cmd.run(opts).await,
         }
     }
@@ -48,6 +59,7 @@ impl File {
         fs::File::open(&self.input)?.read_to_end(&mut data)?;
 
         let signature = keypair.sign(&data)?;
+        audit::record(&keypair.pubkey(), &data, signature.as_ref())?;
         print_signature(&wallet, signature.as_ref())
     }
 }
@@ -65,10 +77,124 @@ impl Msg {
         let wallet = opts.load_wallet()?;
         let keypair = wallet.decrypt(password.as_bytes())?;
         let signature = keypair.sign(self.msg.as_bytes())?;
+        audit::record(&keypair.pubkey(), self.msg.as_bytes(), signature.as_ref())?;
         print_signature(&wallet, signature.as_ref())
     }
 }
 
+/// Sign a JSON payload for a given schema.
+///
+/// The payload is canonicalized (RFC 8785) and the SHA-256 digest of the
+/// schema id and canonical payload is signed. The output is an envelope with
+/// the schema id, payload, public key and signature, which can be checked
+/// with `sign verify json`.
+#[derive(Debug, clap::Args)]
+pub struct Json {
+    /// Path to the JSON file to sign
+    #[arg(long)]
+    file: PathBuf,
+    /// Schema id of the payload, for example "helium/claim-attestation-v1"
+    #[arg(long)]
+    schema: String,
+}
+
+impl Json {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let payload: serde_json::Value = serde_json::from_reader(fs::File::open(&self.file)?)?;
+        let digest = json_digest(&self.schema, &payload)?;
+        let password = get_wallet_password(false)?;
+        let keypair = opts.load_keypair(password.as_bytes())?;
+        let signature = keypair.sign(&digest)?;
+        audit::record(&keypair.pubkey(), &digest, signature.as_ref())?;
+        print_json(&JsonEnvelope {
+            schema: self.schema.clone(),
+            payload,
+            public_key: keypair.pubkey().to_string(),
+            signature: b64::encode(signature),
+        })
+    }
+}
+
+/// A signed JSON payload
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonEnvelope {
+    schema: String,
+    payload: serde_json::Value,
+    /// Solana address of the signer
+    public_key: String,
+    /// Base64 encoded signature of the payload digest
+    signature: String,
+}
+
+fn json_digest(schema: &str, payload: &serde_json::Value) -> Result<Vec<u8>> {
+    if schema.contains('\n') {
+        bail!("schema id can not contain newlines");
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(JSON_SIGNING_PREFIX);
+    hasher.update(schema.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(jcs::canonicalize(payload)?.as_bytes());
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Sign a transaction envelope output by a command run with "--sign-only".
+///
+/// Signing needs no network access, so it can be done on an offline machine.
+/// The programs the transaction calls are listed for review. Broadcast the
+/// signed envelope with the "submit" command.
+#[derive(Debug, clap::Args)]
+pub struct Transaction {
+    /// Path to the envelope, or to the output of the sign-only command
+    input: PathBuf,
+    /// File to write the signed envelope to instead of printing it
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+impl Transaction {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let envelope = TransactionEnvelope::read(&self.input)?;
+        let txn = envelope.transaction()?;
+        let keys = txn.message.static_account_keys();
+        let mut programs: Vec<String> = vec![];
+        for ix in txn.message.instructions() {
+            let Some(program) = keys.get(usize::from(ix.program_id_index)) else {
+                continue;
+            };
+            let program = program.to_string();
+            if !programs.contains(&program) {
+                programs.push(program);
+            }
+        }
+        eprintln!("Programs called: {}", programs.join(", "));
+        if !confirm("Sign this transaction?")? {
+            bail!("transaction not signed");
+        }
+
+        let password = get_wallet_password(false)?;
+        let keypair = opts.load_keypair(password.as_bytes())?;
+        let signed = envelope.sign(&keypair)?;
+        audit::record_transaction(&signed.transaction()?)?;
+        let json = json!({
+            "envelope": signed,
+            "programs": programs,
+            "missing_signers": signed
+                .missing_signers()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+        });
+        match &self.output {
+            Some(path) => {
+                serde_json::to_writer_pretty(open_output_file(path, true)?, &json)?;
+                Ok(())
+            }
+            None => print_json(&json),
+        }
+    }
+}
+
 /// Verify a file or message with a given signature
 #[derive(clap::Args, Debug)]
 pub struct VerifyCmd {
@@ -86,6 +212,7 @@ impl VerifyCmd {
 pub enum Verify {
     File(VerifyFile),
     Msg(VerifyMsg),
+    Json(VerifyJson),
 }
 
 impl Verify {
@@ -93,6 +220,7 @@ impl Verify {
         match self {
             Self::File(cmd) => cmd.run(opts).await,
             Self::Msg(cmd) => cmd.run(opts).await,
+            Self::Json(cmd) => cmd.run(opts).await,
         }
     }
 }
@@ -145,6 +273,53 @@ impl VerifyMsg {
     }
 }
 
+/// Verify a signed JSON envelope.
+///
+/// The signature is checked against the public key in the envelope, so no
+/// wallet is needed.
+#[derive(clap::Args, Debug)]
+pub struct VerifyJson {
+    /// Path to the signed JSON envelope
+    #[arg(long)]
+    file: PathBuf,
+
+    /// Schema id the envelope is expected to have
+    #[arg(long)]
+    schema: Option<String>,
+
+    /// Solana address the envelope is expected to be signed by
+    #[arg(long)]
+    address: Option<Pubkey>,
+}
+
+impl VerifyJson {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        let envelope: JsonEnvelope = serde_json::from_reader(fs::File::open(&self.file)?)?;
+        if let Some(schema) = self
+            .schema
+            .as_ref()
+            .filter(|schema| **schema != envelope.schema)
+        {
+            bail!(
+                "envelope schema {} does not match {schema}",
+                envelope.schema
+            );
+        }
+        let public_key: Pubkey = envelope.public_key.parse()?;
+        if let Some(address) = self.address.filter(|address| *address != public_key) {
+            bail!("envelope is signed by {public_key}, not {address}");
+        }
+        let signature = Signature::try_from(b64::decode(&envelope.signature)?.as_slice())?;
+        let digest = json_digest(&envelope.schema, &envelope.payload)?;
+        let verified = signature.verify(public_key.as_ref(), &digest);
+        print_json(&json!({
+            "address": public_key.to_string(),
+            "schema": envelope.schema,
+            "verified": verified,
+        }))
+    }
+}
+
 fn json_address(wallet: &Wallet) -> Result<serde_json::Value> {
     let helium_address = wallet.helium_address()?;
     let address = wallet.address()?;
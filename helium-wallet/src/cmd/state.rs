@@ -0,0 +1,245 @@
+use crate::cmd::*;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::path::Component;
+
+/// Version of the state archive layout. Bumped when the layout changes in a
+/// way older versions can not import.
+const STATE_FORMAT: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+const CONFIG_PREFIX: &str = "config";
+const CACHE_PREFIX: &str = "cache";
+
+#[derive(Debug, clap::Args)]
+pub struct Cmd {
+    #[command(subcommand)]
+    cmd: StateCommand,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        self.cmd.run(opts).await
+    }
+}
+
+/// Export or import the configuration and cached state of the wallet CLI.
+///
+/// This moves the configuration directory, including the default wallet
+/// setting, and the cache directory to a new machine. Named wallets are only
+/// included when asked for. Wallet files are stored as they are on disk,
+/// encrypted with their own password.
+#[derive(Debug, clap::Subcommand)]
+pub enum StateCommand {
+    Export(ExportCmd),
+    Import(ImportCmd),
+}
+
+impl StateCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::Export(cmd) => cmd.run(opts).await,
+            Self::Import(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    format: u32,
+    version: String,
+    created: chrono::DateTime<chrono::Utc>,
+    wallets: bool,
+    cache: bool,
+}
+
+/// Write the CLI state to a gzipped tar archive
+#[derive(Debug, clap::Args)]
+pub struct ExportCmd {
+    /// File to write the archive to
+    #[arg(long)]
+    out: PathBuf,
+    /// Include the named wallets in the archive
+    #[arg(long)]
+    wallets: bool,
+    /// Leave the cache directory out of the archive
+    #[arg(long)]
+    no_cache: bool,
+    /// Overwrite an existing output file
+    #[arg(long)]
+    force: bool,
+}
+
+impl ExportCmd {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        let manifest = Manifest {
+            format: STATE_FORMAT,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            created: chrono::Utc::now(),
+            wallets: self.wallets,
+            cache: !self.no_cache,
+        };
+        let file = open_output_file(&self.out, !self.force)?;
+        let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+        let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest_json.len() as u64);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, MANIFEST_FILE, manifest_json.as_slice())?;
+
+        let wallets_dir = wallets::wallets_dir();
+        let mut files = vec![];
+        for path in collect_files(&config_dir())? {
+            if !self.wallets && path.starts_with(&wallets_dir) {
+                continue;
+            }
+            let name = Path::new(CONFIG_PREFIX).join(path.strip_prefix(config_dir())?);
+            builder.append_path_with_name(&path, &name)?;
+            files.push(name);
+        }
+        if !self.no_cache {
+            for path in collect_files(&cache_dir())? {
+                let name = Path::new(CACHE_PREFIX).join(path.strip_prefix(cache_dir())?);
+                builder.append_path_with_name(&path, &name)?;
+                files.push(name);
+            }
+        }
+        builder.into_inner()?.finish()?;
+
+        print_json(&json!({
+            "out": self.out,
+            "manifest": manifest,
+            "files": files,
+        }))
+    }
+}
+
+/// Restore CLI state from an archive written by export
+#[derive(Debug, clap::Args)]
+pub struct ImportCmd {
+    /// Archive to import
+    file: PathBuf,
+    /// Overwrite existing files. Existing files are skipped otherwise
+    #[arg(long)]
+    force: bool,
+}
+
+impl ImportCmd {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        let file = fs::File::open(&self.file)?;
+        let mut archive = tar::Archive::new(GzDecoder::new(file));
+        let mut entries = archive.entries()?;
+
+        let manifest: Manifest = match entries.next() {
+            Some(entry) => {
+                let entry = entry?;
+                if entry.path()?.as_ref() != Path::new(MANIFEST_FILE) {
+                    bail!("not a state archive, missing {MANIFEST_FILE}");
+                }
+                serde_json::from_reader(entry)?
+            }
+            None => bail!("empty state archive"),
+        };
+        if manifest.format > STATE_FORMAT {
+            bail!(
+                "state archive format {} written by version {} is not supported by this version",
+                manifest.format,
+                manifest.version
+            );
+        }
+
+        let mut imported = vec![];
+        let mut skipped = vec![];
+        for entry in entries {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = entry.path()?.into_owned();
+            let Some(target) = target_path(&name, &config_dir(), &cache_dir()) else {
+                bail!("invalid path in state archive: {}", name.display());
+            };
+            if target.exists() && !self.force {
+                skipped.push(target);
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            entry.unpack(&target)?;
+            imported.push(target);
+        }
+
+        print_json(&json!({
+            "manifest": manifest,
+            "imported": imported,
+            "skipped": skipped,
+        }))
+    }
+}
+
+/// All files below the given directory, which may not exist
+fn collect_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let read_dir = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(files),
+        Err(err) => return Err(err.into()),
+    };
+    for entry in read_dir {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(collect_files(&path)?);
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Map a path in a state archive to its location on this machine. Returns
+/// None for paths outside the config and cache directories.
+fn target_path(name: &Path, config: &Path, cache: &Path) -> Option<PathBuf> {
+    let mut components = name.components();
+    let root = match components.next()? {
+        Component::Normal(prefix) if prefix == CONFIG_PREFIX => config,
+        Component::Normal(prefix) if prefix == CACHE_PREFIX => cache,
+        _ => return None,
+    };
+    let rest = components.as_path();
+    if rest.as_os_str().is_empty()
+        || !rest
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+    Some(root.join(rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_target_path() {
+        let config = Path::new("/home/user/.config/helium-wallet");
+        let cache = Path::new("/home/user/.cache/helium-wallet");
+        assert_eq!(
+            target_path(Path::new("config/wallets/main.key"), config, cache),
+            Some(config.join("wallets/main.key"))
+        );
+        assert_eq!(
+            target_path(Path::new("cache/oracle-rewards.json"), config, cache),
+            Some(cache.join("oracle-rewards.json"))
+        );
+        assert_eq!(target_path(Path::new("config"), config, cache), None);
+        assert_eq!(
+            target_path(Path::new("config/../.bashrc"), config, cache),
+            None
+        );
+        assert_eq!(target_path(Path::new("/etc/passwd"), config, cache), None);
+        assert_eq!(target_path(Path::new("other/file"), config, cache), None);
+    }
+}
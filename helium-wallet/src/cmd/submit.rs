@@ -0,0 +1,33 @@
+use crate::{cmd::*, offline::TransactionEnvelope};
+
+#[derive(Debug, Clone, clap::Args)]
+/// Broadcast a transaction envelope signed with "sign transaction".
+///
+/// The envelope has to be signed by all of its signers and be submitted
+/// before its blockhash expires.
+pub struct Cmd {
+    /// Path to the signed envelope
+    input: PathBuf,
+
+    /// Commit the transaction.
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let envelope = TransactionEnvelope::read(&self.input)?;
+        let missing = envelope.missing_signers();
+        if !missing.is_empty() {
+            let missing: Vec<String> = missing.iter().map(ToString::to_string).collect();
+            bail!("transaction is not signed by {}", missing.join(", "));
+        }
+        let txn = envelope.transaction()?;
+        if !txn.verify_with_results().iter().all(|verified| *verified) {
+            bail!("transaction has an invalid signature");
+        }
+
+        let client = opts.client()?;
+        print_json(&self.commit.maybe_commit(txn, &client).await.to_json())
+    }
+}
@@ -0,0 +1,271 @@
+use crate::cmd::*;
+use helium_lib::{
+    dao::{Dao, SubDao},
+    entity_key::KeySerialization,
+    hotspot::{self, HotspotMode},
+    keypair::Pubkey,
+    programs::VOTER_STAKE_REGISTRY_PROGRAM_ID,
+    reward::{self, ClaimableToken},
+    solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config,
+    token::{self, Token, TokenAmount},
+};
+use std::collections::HashMap;
+
+/// SOL balance, in lamports, below which transaction fees may not be covered
+const LOW_SOL_BALANCE: u64 = 10_000_000;
+/// Number of recent transactions included in the summary
+const RECENT_TRANSACTIONS: usize = 10;
+
+#[derive(Debug, clap::Args)]
+/// Summarize a wallet or a given public key.
+///
+/// Includes token balances, Hotspot counts by subdao and mode, pending
+/// reward totals, staking positions, recent transactions and warnings for
+/// things that need attention, like a low SOL balance or Hotspots without an
+/// asserted location.
+///
+/// Delegated DC is not included since it is held per router key rather than
+/// per wallet.
+pub struct Cmd {
+    #[arg(value_parser = parse_address)]
+    address: Option<Pubkey>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    format: SummaryFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub enum SummaryFormat {
+    #[default]
+    Json,
+    Table,
+}
+
+#[derive(Debug, Default, serde::Serialize)]
+struct HotspotCounts {
+    full: usize,
+    data_only: usize,
+    unasserted: usize,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let address = if let Some(address) = self.address {
+            address
+        } else {
+            let wallet = opts.load_wallet()?;
+            wallet.public_key
+        };
+        let client = opts.client()?;
+
+        let balances =
+            token::balance_for_addresses(&client, &Token::associated_token_adresses(&address))
+                .await?;
+        let sol_balance = balances
+            .iter()
+            .find(|balance| balance.amount.token == Token::Sol)
+            .map(|balance| balance.amount.amount)
+            .unwrap_or_default();
+        if sol_balance < LOW_SOL_BALANCE {
+            warnings::warn(
+                warnings::LOW_SOL_BALANCE,
+                format!(
+                    "low SOL balance of {sol_balance} lamports, transactions may fail to pay fees"
+                ),
+            );
+        }
+
+        let hotspots = hotspot::for_owner(&client, &address).await?;
+        let mut hotspot_counts = HashMap::new();
+        for subdao in SubDao::all() {
+            let info_keys: Vec<Pubkey> = hotspots
+                .iter()
+                .map(|hotspot| subdao.info_key(&hotspot.key))
+                .collect();
+            let mut counts = HotspotCounts::default();
+            for info in hotspot::info::get_many(&client, subdao, &info_keys)
+                .await?
+                .into_iter()
+                .flatten()
+            {
+                match info.mode() {
+                    HotspotMode::Full => counts.full += 1,
+                    HotspotMode::DataOnly => counts.data_only += 1,
+                }
+                if info.location().is_none() {
+                    counts.unasserted += 1;
+                }
+            }
+            if counts.unasserted > 0 {
+                warnings::warn(
+                    warnings::UNASSERTED_HOTSPOTS,
+                    format!(
+                        "{} {subdao} Hotspot(s) without an asserted location",
+                        counts.unasserted
+                    ),
+                );
+            }
+            hotspot_counts.insert(subdao, counts);
+        }
+
+        let mut pending_rewards = HashMap::new();
+        if !hotspots.is_empty() {
+            let entity_keys: Vec<String> = hotspots
+                .iter()
+                .map(|hotspot| hotspot.key.to_string())
+                .collect();
+            for token in [
+                ClaimableToken::Iot,
+                ClaimableToken::Mobile,
+                ClaimableToken::Hnt,
+            ] {
+                let pending =
+                    reward::pending(&client, token, &entity_keys, KeySerialization::B58).await?;
+                let total = pending
+                    .values()
+                    .map(|reward| reward.reward.amount)
+                    .sum::<u64>();
+                pending_rewards.insert(token, TokenAmount::from_u64(token.into(), total));
+            }
+        }
+
+        let positions = positions_for_owner(&client, &address).await?;
+
+        let config = GetConfirmedSignaturesForAddress2Config {
+            limit: Some(RECENT_TRANSACTIONS),
+            ..Default::default()
+        };
+        let recent_transactions: Vec<serde_json::Value> = client
+            .solana_client
+            .get_signatures_for_address_with_config(&address, config)
+            .await?
+            .into_iter()
+            .map(|status| {
+                json!({
+                    "signature": status.signature,
+                    "slot": status.slot,
+                    "block_time": status.block_time,
+                    "failed": status.err.is_some(),
+                    "memo": status.memo,
+                })
+            })
+            .collect();
+
+        let json = json!({
+            "address": address.to_string(),
+            "balance": token::TokenBalanceMap::from(balances),
+            "hotspots": {
+                "total": hotspots.len(),
+                "by_subdao": hotspot_counts,
+            },
+            "pending_rewards": pending_rewards,
+            "positions": positions
+                .iter()
+                .map(Pubkey::to_string)
+                .collect::<Vec<_>>(),
+            "recent_transactions": recent_transactions,
+            "warnings": [],
+        });
+        match self.format {
+            SummaryFormat::Json => print_json(&json),
+            SummaryFormat::Table => {
+                print_table(&json);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Staking positions of the given owner. Positions are held as NFTs, every
+/// NFT of the owner is checked for a position account.
+async fn positions_for_owner(client: &client::Client, owner: &Pubkey) -> Result<Vec<Pubkey>> {
+    let position_keys: Vec<Pubkey> = token::token_accounts_for_owner(client, owner)
+        .await?
+        .into_iter()
+        .filter(|account| account.decimals == 0 && account.amount.amount == 1)
+        .map(|account| Dao::position_key(&account.mint))
+        .collect();
+    let mut positions = vec![];
+    for chunk in position_keys.chunks(100) {
+        let accounts = client.solana_client.get_multiple_accounts(chunk).await?;
+        positions.extend(
+            chunk
+                .iter()
+                .zip(accounts)
+                .filter(|(_, account)| {
+                    account
+                        .as_ref()
+                        .is_some_and(|account| account.owner == VOTER_STAKE_REGISTRY_PROGRAM_ID)
+                })
+                .map(|(key, _)| *key),
+        );
+    }
+    Ok(positions)
+}
+
+fn print_table(json: &serde_json::Value) {
+    let row = |label: &str, value: &dyn std::fmt::Display| println!("{label:<24}{value}");
+    let text = |value: &serde_json::Value| match value {
+        serde_json::Value::String(value) => value.clone(),
+        serde_json::Value::Null => "-".to_string(),
+        value => value.to_string(),
+    };
+
+    row("Address", &text(&json["address"]));
+    println!();
+    println!("Balances");
+    if let Some(balances) = json["balance"].as_object() {
+        for (token, balance) in balances {
+            row(&format!("  {token}"), &text(&balance["amount"]));
+        }
+    }
+    println!();
+    println!("Hotspots");
+    row("  total", &text(&json["hotspots"]["total"]));
+    if let Some(subdaos) = json["hotspots"]["by_subdao"].as_object() {
+        for (subdao, counts) in subdaos {
+            row(
+                &format!("  {subdao}"),
+                &format!(
+                    "full {}, data only {}, unasserted {}",
+                    counts["full"], counts["data_only"], counts["unasserted"]
+                ),
+            );
+        }
+    }
+    if let Some(pending) = json["pending_rewards"].as_object() {
+        println!();
+        println!("Pending rewards");
+        for (token, amount) in pending {
+            row(&format!("  {token}"), &text(&amount["amount"]));
+        }
+    }
+    println!();
+    println!("Positions");
+    for position in json["positions"].as_array().into_iter().flatten() {
+        println!("  {}", text(position));
+    }
+    println!();
+    println!("Recent transactions");
+    for transaction in json["recent_transactions"].as_array().into_iter().flatten() {
+        let status = if transaction["failed"] == true {
+            "failed"
+        } else {
+            "ok"
+        };
+        println!(
+            "  {:<8}{:<12}{}",
+            status,
+            text(&transaction["slot"]),
+            text(&transaction["signature"])
+        );
+    }
+    let warnings = warnings::get();
+    if !warnings.is_empty() {
+        println!();
+        println!("Warnings");
+        for warning in warnings {
+            println!("  {}", warning.message);
+        }
+    }
+}
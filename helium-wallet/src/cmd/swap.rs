@@ -0,0 +1,51 @@
+use crate::cmd::*;
+use helium_lib::{
+    swap,
+    token::{Token, TokenAmount},
+};
+
+#[derive(Debug, Clone, clap::Args)]
+/// Swap tokens through the Jupiter aggregator.
+///
+/// Swaps between HNT, IOT, MOBILE, SOL and USDC. The quote for the swap is
+/// printed with the result. The swap is not submitted unless the '--commit'
+/// option is given.
+pub struct Cmd {
+    /// Token to swap from
+    #[arg(value_enum)]
+    from: Token,
+    /// Amount of the token to swap
+    amount: f64,
+    /// Token to swap to
+    #[arg(value_enum)]
+    to: Token,
+    /// Maximum slippage of the swap in basis points
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u16).range(1..=10_000))]
+    slippage_bps: u16,
+    /// Commit the swap
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let keypair = opts.load_signer(&self.commit)?.keypair()?;
+        let client = opts.client()?;
+        let txn_opts = self.commit.transaction_opts(&client);
+
+        let quote = swap::quote(
+            TokenAmount::from_f64(self.from, self.amount),
+            self.to,
+            self.slippage_bps,
+        )
+        .await?;
+        let (tx, _) = swap::swap(&client, &quote, &keypair, &txn_opts).await?;
+        let mut json = self
+            .commit
+            .maybe_commit_with_keypair(tx, &client, &keypair)
+            .await?
+            .to_json();
+        json["quote"] = serde_json::to_value(&quote)?;
+        print_json(&json)
+    }
+}
@@ -0,0 +1,57 @@
+use crate::cmd::*;
+use helium_lib::token::{self, Token};
+
+#[derive(Debug, clap::Args)]
+pub struct Cmd {
+    #[command(subcommand)]
+    cmd: TokenCommand,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        self.cmd.run(opts).await
+    }
+}
+
+/// Commands on the Helium token mints
+#[derive(Debug, clap::Subcommand)]
+pub enum TokenCommand {
+    Holders(HoldersCmd),
+}
+
+impl TokenCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::Holders(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+/// List the largest holders of a token and their share of the supply.
+///
+/// Holders are token accounts as reported by the RPC provider, which returns
+/// at most 20 of them. Accounts owned by programs, like the treasuries of the
+/// subdaos, are included.
+#[derive(Debug, clap::Args)]
+pub struct HoldersCmd {
+    /// Token to list the holders of
+    #[arg(long, default_value_t = Token::Hnt)]
+    token: Token,
+    /// Number of holders to list
+    #[arg(
+        long,
+        default_value_t = token::MAX_HOLDERS as u8,
+        value_parser = clap::value_parser!(u8).range(1..=token::MAX_HOLDERS as i64)
+    )]
+    top: u8,
+}
+
+impl HoldersCmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let client = opts.client()?;
+        let holders = token::holders(&client, self.token, self.top.into()).await?;
+        let mut json = serde_json::to_value(&holders)?;
+        json["token"] = json!(self.token);
+        print_json(&json)
+    }
+}
@@ -1,27 +1,35 @@
-use crate::cmd::*;
+use crate::{cmd::*, policy};
 use helium_lib::{
-    keypair::{serde_pubkey, Pubkey},
-    token::{self, Token, TokenAmount},
+    keypair::{serde_opt_pubkey, serde_pubkey, Pubkey},
+    token::{self, Payment, Token, TokenAmount},
 };
 use serde::Deserialize;
 
 #[derive(Debug, clap::Args)]
+#[command(args_conflicts_with_subcommands = true)]
+/// Send one (or more) payments to given addresses.
+///
+/// Payments listed in a CSV file are made with --input instead of a
+/// subcommand. The payment is not submitted to the system unless the
+/// '--commit' option is given.
 pub struct Cmd {
     #[command(subcommand)]
-    cmd: PayCmd,
+    cmd: Option<PayCmd>,
+    #[command(flatten)]
+    csv: Csv,
 }
 
 impl Cmd {
     pub async fn run(&self, opts: Opts) -> Result {
-        self.cmd.run(opts).await
+        match (&self.cmd, &self.csv.input) {
+            (Some(cmd), _) => cmd.run(opts).await,
+            (None, Some(input)) => self.csv.run(input, opts).await,
+            (None, None) => bail!("either a payment subcommand or --input is required"),
+        }
     }
 }
 
 #[derive(Debug, clap::Subcommand)]
-/// Send one (or more) payments to given addresses.
-///
-/// The payment is not submitted to the system unless the '--commit' option is
-/// given.
 pub enum PayCmd {
     /// Pay a single payee.
     ///
@@ -36,6 +44,10 @@ pub enum PayCmd {
 pub struct One {
     #[command(flatten)]
     payee: Payee,
+    #[command(flatten)]
+    reference: ReferenceOpts,
+    #[command(flatten)]
+    multisig: MultisigOpts,
     /// Commit the payment to the API
     #[command(flatten)]
     commit: CommitOpts,
@@ -50,6 +62,7 @@ pub struct One {
 ///   "amount" is required. It must be a number or the string "max". When "max"
 ///            the entire balance (minus fees) will be sent.
 ///   "token" is optional and defaults to "hnt".
+///   "mint" is optional, the mint of an SPL token to send instead of "token".
 ///   "memo" is optional.
 ///
 /// For example:
@@ -76,6 +89,37 @@ pub struct One {
 pub struct Multi {
     /// File to read multiple payments from.
     path: PathBuf,
+    #[command(flatten)]
+    reference: ReferenceOpts,
+    #[command(flatten)]
+    multisig: MultisigOpts,
+    /// Commit the payments
+    #[command(flatten)]
+    commit: CommitOpts,
+}
+
+/// Payments from a CSV file, made with --input instead of a subcommand
+#[derive(Debug, clap::Args)]
+pub struct Csv {
+    /// CSV file to pay the payees listed in, in as few transactions as
+    /// possible.
+    ///
+    /// The file has a header row and an "address", "amount", "token" and
+    /// "memo" column. "token" is optional and defaults to "hnt", "memo" is
+    /// optional and defaults to the --memo option. For example:
+    ///
+    ///     address,token,amount,memo
+    ///     <address1>,hnt,1.6,invoice 1
+    ///     <address2>,mobile,3,
+    ///
+    /// Rows that can not be parsed or whose transaction fails are reported
+    /// and do not stop the other payments. With a maximum total fee, the
+    /// payments after the first transaction that would exceed it are
+    /// skipped.
+    #[arg(long, verbatim_doc_comment)]
+    input: Option<PathBuf>,
+    #[command(flatten)]
+    reference: ReferenceOpts,
     /// Commit the payments
     #[command(flatten)]
     commit: CommitOpts,
@@ -83,29 +127,80 @@ pub struct Multi {
 
 impl PayCmd {
     pub async fn run(&self, opts: Opts) -> Result {
-        let payments = self.collect_payments()?;
-        let password = get_wallet_password(false)?;
-        let keypair = opts.load_keypair(password.as_bytes())?;
         let client = opts.client()?;
+        let payments = self.collect_payments(&client).await?;
+        let signer = opts.load_signer(self.commit())?;
+        let wallet = signer.pubkey();
+        for (payee, _) in &payments {
+            policy::check_external(&wallet, payee, "payee")?;
+        }
         let txn_opts = self.commit().transaction_opts(&client);
 
-        let (tx, _) = token::transfer(&client, &payments, &keypair, &txn_opts).await?;
+        let reference = self.reference();
+        let multisig = self.multisig().resolve(&wallet)?;
+        let (msg, _) = token::transfer_message(
+            &client,
+            &payments,
+            reference.memo.as_deref(),
+            &reference.references,
+            &multisig.authority(&wallet),
+            &txn_opts,
+        )
+        .await?;
+        let (tx, proposal) = multisig.sign(&client, msg, &signer, &txn_opts).await?;
 
-        print_json(&self.commit().maybe_commit(tx, &client).await?.to_json())
+        let response = self
+            .commit()
+            .maybe_commit_with_signer(tx, &client, &signer)
+            .await?;
+        print_json(&with_proposal(response.to_json(), proposal)?)
     }
 
-    fn collect_payments(&self) -> Result<Vec<(Pubkey, TokenAmount)>> {
-        match &self {
-            Self::One(one) => Ok(vec![(one.payee.address, one.payee.token_amount())]),
+    async fn collect_payments(
+        &self,
+        client: &client::Client,
+    ) -> Result<Vec<(Pubkey, TokenAmount)>> {
+        let multi_payees: Vec<Payee>;
+        let payees = match &self {
+            Self::One(one) => std::slice::from_ref(&one.payee),
             Self::Multi(multi) => {
                 let file = std::fs::File::open(multi.path.clone())?;
-                let payees: Vec<Payee> = serde_json::from_reader(file)?;
-                let payments = payees
-                    .iter()
-                    .map(|p| (p.address, p.token_amount()))
-                    .collect();
-                Ok(payments)
+                multi_payees = serde_json::from_reader(file)?;
+                &multi_payees
+            }
+        };
+        let mut payments = vec![];
+        for payee in payees {
+            let amount = payee.resolve_token_amount(client).await?;
+            if let Some(fee) = amount.token.transfer_fee() {
+                let fee = TokenAmount::from_u64(amount.token, fee.calculate(amount.amount));
+                warnings::warn(
+                    warnings::TRANSFER_FEE,
+                    format!(
+                        "the mint of {} withholds {} of the {} sent to {}",
+                        amount.token,
+                        f64::from(&fee),
+                        f64::from(&amount),
+                        payee.address
+                    ),
+                );
             }
+            payments.push((payee.address, amount));
+        }
+        Ok(payments)
+    }
+
+    fn reference(&self) -> &ReferenceOpts {
+        match &self {
+            Self::One(one) => &one.reference,
+            Self::Multi(multi) => &multi.reference,
+        }
+    }
+
+    fn multisig(&self) -> &MultisigOpts {
+        match &self {
+            Self::One(one) => &one.multisig,
+            Self::Multi(multi) => &multi.multisig,
         }
     }
 
@@ -117,21 +212,220 @@ impl PayCmd {
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct CsvRow {
+    address: String,
+    amount: String,
+    #[serde(default)]
+    token: Option<String>,
+    #[serde(default)]
+    memo: Option<String>,
+}
+
+impl CsvRow {
+    fn payment(&self, default_memo: Option<&str>) -> Result<Payment> {
+        let payee = parse_address(&self.address)?;
+        let token = match self.token.as_deref().filter(|token| !token.is_empty()) {
+            Some(token) => transferrable_token(token)?,
+            None => Token::Hnt,
+        };
+        let amount: f64 = self
+            .amount
+            .parse()
+            .map_err(|_| anyhow!("invalid amount: {}", self.amount))?;
+        if !amount.is_finite() || amount <= 0.0 {
+            bail!("amount must be positive: {}", self.amount);
+        }
+        let memo = self
+            .memo
+            .as_deref()
+            .filter(|memo| !memo.is_empty())
+            .or(default_memo)
+            .map(str::to_string);
+        Ok(Payment {
+            payee,
+            amount: TokenAmount::from_f64(token, amount),
+            memo,
+        })
+    }
+}
+
+/// Read the rows of a payments CSV, keeping rows that fail to parse with
+/// their error
+fn read_csv<R: io::Read>(
+    reader: R,
+    default_memo: Option<&str>,
+) -> Vec<std::result::Result<Payment, String>> {
+    csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(reader)
+        .deserialize::<CsvRow>()
+        .map(|row| {
+            row.map_err(Error::from)
+                .and_then(|row| row.payment(default_memo))
+                .map_err(|err| err.to_string())
+        })
+        .collect()
+}
+
+impl Csv {
+    pub async fn run(&self, input: &Path, opts: Opts) -> Result {
+        let rows = read_csv(fs::File::open(input)?, self.reference.memo.as_deref());
+        let signer = opts.load_signer(&self.commit)?;
+        let wallet = signer.pubkey();
+        // Payments to addresses the address policy refuses fail like rows
+        // that can not be parsed
+        let rows: Vec<_> = rows
+            .into_iter()
+            .map(|row| {
+                row.and_then(|payment| {
+                    policy::check_external(&wallet, &payment.payee, "payee")
+                        .map(|_| payment)
+                        .map_err(|err| err.to_string())
+                })
+            })
+            .collect();
+        let client = opts.client()?;
+        let txn_opts = self.commit.transaction_opts(&client);
+        let references = &self.reference.references;
+
+        // The payments to pack with the index of their row
+        let (row_indexes, payments): (Vec<usize>, Vec<Payment>) = rows
+            .iter()
+            .enumerate()
+            .filter_map(|(index, row)| row.as_ref().ok().map(|payment| (index, payment.clone())))
+            .unzip();
+        let mut results: Vec<Option<serde_json::Value>> = rows
+            .iter()
+            .map(|row| {
+                row.as_ref().err().map(
+                    |err| json!({"status": "failed", "result": {"result": "error", "error": err}}),
+                )
+            })
+            .collect();
+
+        let batches =
+            token::pack_payments(&client, &payments, references, &wallet, &txn_opts).await?;
+        let mut fee_cap_reached = false;
+        for (transaction, batch) in batches.into_iter().enumerate() {
+            let commit = if fee_cap_reached {
+                None
+            } else {
+                let commit = match token::transfer_payments_message(
+                    &client,
+                    &payments[batch.clone()],
+                    references,
+                    &wallet,
+                    &txn_opts,
+                )
+                .await
+                {
+                    Ok((msg, _)) => match signer.sign(msg) {
+                        Ok(tx) => {
+                            self.commit
+                                .maybe_commit_with_signer(tx, &client, &signer)
+                                .await
+                        }
+                        Err(err) => Err(err),
+                    },
+                    Err(err) => Err(err.into()),
+                };
+                if FeeCapExceeded::is_cause(&commit) {
+                    warnings::warn(
+                        warnings::FEE_CAP_REACHED,
+                        format!(
+                            "maximum total fee reached, skipped payments from row {}",
+                            row_indexes[batch.start] + 1
+                        ),
+                    );
+                    fee_cap_reached = true;
+                    None
+                } else {
+                    Some(commit)
+                }
+            };
+            let (status, result) = match &commit {
+                None => ("skipped", serde_json::Value::Null),
+                Some(commit) if commit.is_ok() => ("sent", commit.to_json()),
+                Some(commit) => ("failed", commit.to_json()),
+            };
+            for index in batch {
+                results[row_indexes[index]] = Some(json!({
+                    "status": status,
+                    "transaction": transaction,
+                    "result": result,
+                }));
+            }
+        }
+
+        let report: Vec<serde_json::Value> = rows
+            .iter()
+            .zip(results)
+            .enumerate()
+            .map(|(index, (row, result))| {
+                let mut entry = json!({ "row": index + 1 });
+                if let Ok(payment) = row {
+                    entry["address"] = payment.payee.to_string().into();
+                    entry["token"] = json!(payment.amount.token);
+                    entry["amount"] = payment.amount.amount.into();
+                    entry["memo"] = json!(payment.memo);
+                }
+                if let (Some(entry), Some(serde_json::Value::Object(result))) =
+                    (entry.as_object_mut(), result)
+                {
+                    entry.extend(result);
+                }
+                entry
+            })
+            .collect();
+        print_json(&report)
+    }
+}
+
+/// Options to help payees reconcile a payment
+#[derive(Debug, clap::Args)]
+pub struct ReferenceOpts {
+    /// Memo to attach to the payment transaction
+    #[arg(long)]
+    memo: Option<String>,
+    /// Reference key to attach to the payment, allowing it to be located by
+    /// reference. Can be given multiple times
+    #[arg(long = "reference", number_of_values(1))]
+    references: Vec<Pubkey>,
+}
+
 #[derive(Debug, Deserialize, clap::Args)]
 pub struct Payee {
     /// Address to send the tokens to.
+    #[arg(value_parser = parse_address)]
     #[serde(with = "serde_pubkey")]
     address: Pubkey,
     /// Amount of token to send
     amount: f64,
-    /// Type of token to send
-    #[arg(value_parser = Token::transferrable_value_parser)]
-    token: Token,
+    /// Type of token to send. Defaults to HNT
+    #[arg(value_parser = transferrable_token)]
+    #[serde(default)]
+    token: Option<Token>,
+    /// Mint of an SPL token to send instead of one of the named tokens
+    #[arg(long, conflicts_with = "token")]
+    #[serde(default, with = "serde_opt_pubkey")]
+    mint: Option<Pubkey>,
 }
 
 impl Payee {
     pub fn token_amount(&self) -> TokenAmount {
-        TokenAmount::from_f64(self.token, self.amount)
+        TokenAmount::from_f64(self.token.unwrap_or(Token::Hnt), self.amount)
+    }
+
+    /// The amount to send, looking up the decimals of a token given by mint
+    pub async fn resolve_token_amount(&self, client: &client::Client) -> Result<TokenAmount> {
+        match &self.mint {
+            Some(mint) => Ok(TokenAmount::from_f64(
+                Token::for_mint(client, mint).await?,
+                self.amount,
+            )),
+            None => Ok(self.token_amount()),
+        }
     }
 }
 
@@ -175,6 +469,92 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_json_mint_input() {
+        let json_mint_input = "{\
+            \"address\": \"JBjajLx1b2MsugerDALTffjh9dVdNx5XTvgJd8SpwUPf\",\
+            \"amount\": 2,\
+            \"mint\": \"EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v\"\
+        }";
+
+        let payee: Payee = serde_json::from_str(json_mint_input).expect("payee");
+        assert_eq!(payee.token, None);
+        assert_eq!(payee.mint, Some(*Token::Usdc.mint()),);
+    }
+
+    #[test]
+    fn test_csv_input() {
+        let input = "address,token,amount,memo\n\
+            JBjajLx1b2MsugerDALTffjh9dVdNx5XTvgJd8SpwUPf, mobile, 0.5, invoice 1\n\
+            JBjajLx1b2MsugerDALTffjh9dVdNx5XTvgJd8SpwUPf,,1.6,\n\
+            not-an-address,hnt,1,\n\
+            JBjajLx1b2MsugerDALTffjh9dVdNx5XTvgJd8SpwUPf,dc,1,\n";
+
+        let rows = read_csv(input.as_bytes(), Some("default"));
+        assert_eq!(rows.len(), 4);
+        let first = rows[0].as_ref().expect("first row");
+        assert_eq!(first.amount, Token::Mobile.amount(500_000));
+        assert_eq!(first.memo.as_deref(), Some("invoice 1"));
+        let second = rows[1].as_ref().expect("second row");
+        assert_eq!(second.amount, Token::Hnt.amount(160_000_000));
+        assert_eq!(second.memo.as_deref(), Some("default"));
+        assert!(rows[2].is_err());
+        assert!(rows[3].is_err());
+    }
+
+    #[test]
+    fn test_csv_deprecated_input() {
+        use helium_crypto::{KeyTag, KeyType, Network};
+        let helium_key = helium_crypto::Keypair::generate(
+            KeyTag {
+                network: Network::MainNet,
+                key_type: KeyType::Ed25519,
+            },
+            &mut rand::rngs::OsRng,
+        );
+        let input = format!("address,token,amount\n{},HNT,1\n", helium_key.public_key());
+
+        let rows = read_csv(input.as_bytes(), None);
+        let payment = rows[0].as_ref().expect("payment");
+        assert_eq!(
+            payment.payee,
+            helium_lib::keypair::to_pubkey(helium_key.public_key()).expect("solana key")
+        );
+        assert_eq!(payment.amount, Token::Hnt.amount(100_000_000));
+        let codes: Vec<String> = warnings::get()
+            .into_iter()
+            .map(|warning| warning.code)
+            .collect();
+        assert!(codes.contains(&warnings::LEGACY_ADDRESS.to_string()));
+        assert!(codes.contains(&warnings::DEPRECATED_TOKEN_NAME.to_string()));
+    }
+
+    #[test]
+    fn test_csv_args() {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(flatten)]
+            cmd: Cmd,
+        }
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["transfer", "--input", "payments.csv", "--commit"])
+            .expect("csv transfer");
+        assert!(cli.cmd.cmd.is_none());
+        assert_eq!(cli.cmd.csv.input, Some(PathBuf::from("payments.csv")));
+        let cli = Cli::try_parse_from([
+            "transfer",
+            "one",
+            "JBjajLx1b2MsugerDALTffjh9dVdNx5XTvgJd8SpwUPf",
+            "1",
+        ])
+        .expect("single transfer");
+        assert!(matches!(cli.cmd.cmd, Some(PayCmd::One(_))));
+        assert!(
+            Cli::try_parse_from(["transfer", "--input", "payments.csv", "multi", "x"]).is_err()
+        );
+    }
+
     #[test]
     fn test_json_bad_amount() {
         let json_hnt_input = "{\
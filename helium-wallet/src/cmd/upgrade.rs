@@ -1,9 +1,12 @@
 use crate::{
     cmd::*,
     format::{self, Format},
-    pwhash::PwHash,
 };
 use clap::Parser;
+use helium_lib::keypair::to_pubkey;
+use helium_proto::{BlockchainTxn, Txn};
+use serde::Deserialize;
+use std::collections::BTreeMap;
 
 #[derive(Debug, Parser)]
 pub struct Cmd {
@@ -20,10 +23,15 @@ impl Cmd {
 /// Upgrade a wallet to the latest supported version of the given
 /// format. The same password is used to decrypt the old and encrypt
 /// the new wallet.
+///
+/// The new wallet hashes its password with Argon2id. Upgrading a current
+/// wallet re-encrypts it, which can be used to change the Argon2id
+/// parameters.
 #[derive(Debug, clap::Subcommand)]
 pub enum UpgradeCmd {
     Basic(Basic),
     Sharded(Sharded),
+    Legacy(Legacy),
 }
 
 #[derive(Debug, clap::Args)]
@@ -36,6 +44,9 @@ pub struct Basic {
     #[arg(long)]
     /// Overwrite an existing file
     force: bool,
+
+    #[command(flatten)]
+    pwhash: PwHashOpts,
 }
 
 #[derive(Debug, clap::Args)]
@@ -56,6 +67,9 @@ pub struct Sharded {
     #[arg(short = 'k', long = "required-shards", default_value = "3")]
     /// Number of shards required to recover the key
     recovery_threshold: u8,
+
+    #[command(flatten)]
+    pwhash: PwHashOpts,
 }
 
 impl UpgradeCmd {
@@ -63,6 +77,7 @@ impl UpgradeCmd {
         match self {
             Self::Basic(cmd) => cmd.run(opts).await,
             Self::Sharded(cmd) => cmd.run(opts).await,
+            Self::Legacy(cmd) => cmd.run(opts).await,
         }
     }
 }
@@ -74,7 +89,7 @@ impl Basic {
         let keypair = wallet.decrypt(password.as_bytes())?;
 
         let format = format::Basic {
-            pwhash: PwHash::argon2id13_default(),
+            pwhash: self.pwhash.pwhash(),
         };
         let new_wallet = Wallet::encrypt(&keypair, password.as_bytes(), Format::Basic(format))?;
         let mut writer = open_output_file(&self.output, !self.force)?;
@@ -92,7 +107,7 @@ impl Sharded {
         let format = format::Sharded {
             key_share_count: self.key_share_count,
             recovery_threshold: self.recovery_threshold,
-            pwhash: PwHash::argon2id13_default(),
+            pwhash: self.pwhash.pwhash(),
             key_shares: vec![],
         };
         let new_wallet = Wallet::encrypt(&keypair, password.as_bytes(), Format::Sharded(format))?;
@@ -108,3 +123,240 @@ impl Sharded {
         info::print_wallet(&wallet)
     }
 }
+
+#[derive(Debug, clap::Args)]
+/// Migrate legacy Helium L1 artifacts.
+///
+/// Each given file is detected as one of:
+///
+/// * a wallet file: older basic wallet versions are converted to the latest
+///   basic format, using the same password, and written to the output
+///   directory under the same file name.
+///
+/// * exported legacy transactions: base64 encoded `BlockchainTxn`s, one per
+///   line. The accounts they refer to are reported.
+///
+/// * an address book: a JSON list of `{"name", "address"}` entries or a JSON
+///   object mapping names to addresses.
+///
+/// The Helium and corresponding Solana addresses found in every artifact are
+/// printed.
+pub struct Legacy {
+    /// Legacy files to migrate
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    #[arg(short, long, default_value = ".")]
+    /// Directory to write converted wallets to
+    output_dir: PathBuf,
+
+    #[arg(long)]
+    /// Overwrite existing files
+    force: bool,
+
+    #[command(flatten)]
+    pwhash: PwHashOpts,
+}
+
+/// A Helium address and its Solana equivalent, if it has one
+#[derive(Debug, serde::Serialize)]
+struct AddressMapping {
+    name: String,
+    helium: String,
+    solana: Option<String>,
+}
+
+impl AddressMapping {
+    fn new(name: &str, helium: &helium_crypto::PublicKey) -> Self {
+        Self {
+            name: name.to_string(),
+            helium: helium.to_string(),
+            solana: to_pubkey(helium).ok().map(|key| key.to_string()),
+        }
+    }
+
+    fn from_bytes(name: &str, bytes: &[u8]) -> Option<Self> {
+        helium_crypto::PublicKey::try_from(bytes)
+            .ok()
+            .map(|helium| Self::new(name, &helium))
+    }
+
+    fn from_str(name: &str, address: &str) -> Result<Self> {
+        let helium = address
+            .parse::<helium_crypto::PublicKey>()
+            .map_err(|_| anyhow!("invalid helium address {address} for {name}"))?;
+        Ok(Self::new(name, &helium))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum AddressBook {
+    List(Vec<AddressBookEntry>),
+    Map(BTreeMap<String, String>),
+}
+
+#[derive(Debug, Deserialize)]
+struct AddressBookEntry {
+    name: String,
+    address: String,
+}
+
+impl AddressBook {
+    fn mappings(self) -> Result<Vec<AddressMapping>> {
+        match self {
+            Self::List(entries) => entries
+                .iter()
+                .map(|entry| AddressMapping::from_str(&entry.name, &entry.address))
+                .collect(),
+            Self::Map(entries) => entries
+                .iter()
+                .map(|(name, address)| AddressMapping::from_str(name, address))
+                .collect(),
+        }
+    }
+}
+
+/// The accounts referred to by a legacy transaction, by role
+fn txn_mappings(txn: &BlockchainTxn) -> Result<Vec<AddressMapping>> {
+    let accounts: Vec<(&str, &[u8])> = match &txn.txn {
+        Some(Txn::Payment(txn)) => vec![
+            ("payer", txn.payer.as_slice()),
+            ("payee", txn.payee.as_slice()),
+        ],
+        Some(Txn::PaymentV2(txn)) => std::iter::once(("payer", txn.payer.as_slice()))
+            .chain(
+                txn.payments
+                    .iter()
+                    .map(|payment| ("payee", payment.payee.as_slice())),
+            )
+            .collect(),
+        Some(Txn::AddGateway(txn)) => vec![
+            ("gateway", txn.gateway.as_slice()),
+            ("owner", txn.owner.as_slice()),
+            ("payer", txn.payer.as_slice()),
+        ],
+        Some(Txn::AssertLocation(txn)) => vec![
+            ("gateway", txn.gateway.as_slice()),
+            ("owner", txn.owner.as_slice()),
+            ("payer", txn.payer.as_slice()),
+        ],
+        Some(Txn::AssertLocationV2(txn)) => vec![
+            ("gateway", txn.gateway.as_slice()),
+            ("owner", txn.owner.as_slice()),
+            ("payer", txn.payer.as_slice()),
+        ],
+        Some(Txn::TransferHotspot(txn)) => vec![
+            ("gateway", txn.gateway.as_slice()),
+            ("seller", txn.seller.as_slice()),
+            ("buyer", txn.buyer.as_slice()),
+        ],
+        Some(Txn::TransferHotspotV2(txn)) => vec![
+            ("gateway", txn.gateway.as_slice()),
+            ("owner", txn.owner.as_slice()),
+            ("new_owner", txn.new_owner.as_slice()),
+        ],
+        _ => bail!("unsupported legacy transaction"),
+    };
+    Ok(accounts
+        .into_iter()
+        .filter(|(_, bytes)| !bytes.is_empty())
+        .filter_map(|(name, bytes)| AddressMapping::from_bytes(name, bytes))
+        .collect())
+}
+
+impl Legacy {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        let mut password: Option<Zeroizing<String>> = None;
+        let mut results = vec![];
+        for path in &self.files {
+            let data = fs::read(path)?;
+            let result = if let Ok(wallet) = Wallet::read(&mut data.as_slice()) {
+                self.upgrade_wallet(path, wallet, &mut password)?
+            } else {
+                Self::map_text(path, &data)?
+            };
+            results.push(result);
+        }
+        print_json(&results)
+    }
+
+    fn upgrade_wallet(
+        &self,
+        path: &Path,
+        wallet: Wallet,
+        password: &mut Option<Zeroizing<String>>,
+    ) -> Result<serde_json::Value> {
+        let addresses = vec![AddressMapping::new("wallet", &wallet.helium_pubkey()?)];
+        if wallet.is_current_version() {
+            return Ok(json!({
+                "file": path,
+                "kind": "wallet",
+                "upgraded": false,
+                "addresses": addresses,
+            }));
+        }
+        if wallet.is_sharded() {
+            bail!(
+                "{} is a legacy wallet shard, use \"upgrade sharded\" with all shards",
+                path.display()
+            );
+        }
+        if password.is_none() {
+            *password = Some(get_wallet_password(false)?);
+        }
+        let password = password.as_deref().map_or("", String::as_str);
+        let keypair = wallet.decrypt(password.as_bytes())?;
+        let format = format::Basic {
+            pwhash: self.pwhash.pwhash(),
+        };
+        let new_wallet = Wallet::encrypt(&keypair, password.as_bytes(), Format::Basic(format))?;
+        let output = self
+            .output_dir
+            .join(path.file_name().unwrap_or(path.as_os_str()));
+        if output == path && !self.force {
+            bail!(
+                "refusing to replace {} in place without --force",
+                path.display()
+            );
+        }
+        let mut writer = open_output_file(&output, !self.force)?;
+        new_wallet.write(&mut writer)?;
+        Ok(json!({
+            "file": path,
+            "kind": "wallet",
+            "upgraded": true,
+            "output": output,
+            "addresses": addresses,
+        }))
+    }
+
+    fn map_text(path: &Path, data: &[u8]) -> Result<serde_json::Value> {
+        let unrecognized = || anyhow!("{}: unrecognized legacy file", path.display());
+        let text = std::str::from_utf8(data).map_err(|_| unrecognized())?;
+        if let Ok(address_book) = serde_json::from_str::<AddressBook>(text) {
+            return Ok(json!({
+                "file": path,
+                "kind": "address_book",
+                "addresses": address_book.mappings()?,
+            }));
+        }
+        let transactions = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let txn: BlockchainTxn = b64::decode_message(line).map_err(|_| unrecognized())?;
+                txn_mappings(&txn).map(|addresses| json!({ "addresses": addresses }))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if transactions.is_empty() {
+            return Err(unrecognized());
+        }
+        Ok(json!({
+            "file": path,
+            "kind": "transactions",
+            "transactions": transactions,
+        }))
+    }
+}
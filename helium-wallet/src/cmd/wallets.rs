@@ -0,0 +1,394 @@
+use crate::{
+    backup::{self, Provider},
+    cmd::*,
+    gateway_keys::GatewayKeys,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const WALLET_EXTENSION: &str = "key";
+const CONFIG_FILE: &str = "wallets.json";
+
+/// Directory holding named wallets
+pub fn wallets_dir() -> PathBuf {
+    config_dir().join("wallets")
+}
+
+/// Path of the wallet file for a given wallet name
+pub fn wallet_path(name: &str) -> PathBuf {
+    wallets_dir().join(format!("{name}.{WALLET_EXTENSION}"))
+}
+
+/// Resolve a wallet file argument to a path.
+///
+/// Existing paths are used as given. Otherwise the argument is treated as the
+/// name of a wallet in the wallets directory. The default wallet file name
+/// resolves to the configured default wallet when there is no such file in
+/// the current directory.
+pub fn resolve(file: &Path) -> Result<PathBuf> {
+    if file.exists() {
+        return Ok(file.to_path_buf());
+    }
+    if let Some(name) = file.to_str() {
+        let path = wallet_path(name);
+        if path.exists() {
+            return Ok(path);
+        }
+        if name == DEFAULT_WALLET_FILE {
+            if let Some(default) = WalletsConfig::load()?.default {
+                return Ok(wallet_path(&default));
+            }
+        }
+    }
+    Ok(file.to_path_buf())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct WalletsConfig {
+    default: Option<String>,
+}
+
+impl WalletsConfig {
+    fn path() -> PathBuf {
+        config_dir().join(CONFIG_FILE)
+    }
+
+    fn load() -> Result<Self> {
+        match fs::File::open(Self::path()) {
+            Ok(file) => Ok(serde_json::from_reader(file)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn save(&self) -> Result {
+        fs::create_dir_all(config_dir())?;
+        let file = fs::File::create(Self::path())?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct Cmd {
+    #[command(subcommand)]
+    cmd: WalletsCommand,
+}
+
+impl Cmd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        self.cmd.run(opts).await
+    }
+}
+
+/// Manage named wallets in the wallet configuration directory.
+///
+/// Named wallets can be passed to the global '-f' option by name instead of by
+/// path.
+#[derive(Debug, clap::Subcommand)]
+pub enum WalletsCommand {
+    List(List),
+    Label(Label),
+    SetDefault(SetDefault),
+    Backup(Backup),
+    Restore(Restore),
+    #[command(subcommand)]
+    GatewayKey(GatewayKeyCommand),
+}
+
+impl WalletsCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::List(cmd) => cmd.run(opts).await,
+            Self::Label(cmd) => cmd.run(opts).await,
+            Self::SetDefault(cmd) => cmd.run(opts).await,
+            Self::Backup(cmd) => cmd.run(opts).await,
+            Self::Restore(cmd) => cmd.run(opts).await,
+            Self::GatewayKey(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+/// List all named wallets
+#[derive(Debug, clap::Args)]
+pub struct List {}
+
+impl List {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        let config = WalletsConfig::load()?;
+        let mut names = match fs::read_dir(wallets_dir()) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| get_file_extension(path) == WALLET_EXTENSION)
+                .filter_map(|path| {
+                    path.file_stem()
+                        .and_then(|stem| stem.to_str())
+                        .map(str::to_string)
+                })
+                .collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => vec![],
+            Err(err) => return Err(err.into()),
+        };
+        names.sort();
+
+        let wallets = names
+            .into_iter()
+            .map(|name| {
+                let mut reader = fs::File::open(wallet_path(&name))?;
+                let wallet = Wallet::read(&mut reader)?;
+                Ok(json!({
+                    "default": config.default.as_ref() == Some(&name),
+                    "address": wallet.address()?,
+                    "sharded": wallet.is_sharded(),
+                    "name": name,
+                }))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        print_json(&wallets)
+    }
+}
+
+/// Give a wallet a name.
+///
+/// A wallet file is copied into the wallets directory under the given name. An
+/// already named wallet is renamed.
+#[derive(Debug, clap::Args)]
+pub struct Label {
+    /// Path or name of the wallet to label
+    wallet: PathBuf,
+    /// Name for the wallet
+    name: String,
+    /// Overwrite an existing wallet with the same name
+    #[arg(long)]
+    force: bool,
+}
+
+impl Label {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        if self.name.is_empty() || self.name.contains(std::path::is_separator) {
+            bail!("Invalid wallet name: {}", self.name);
+        }
+        let source = resolve(&self.wallet)?;
+        // Make sure the source is a readable wallet
+        let wallet = Wallet::read(&mut fs::File::open(&source)?)?;
+        let destination = wallet_path(&self.name);
+        if destination.exists() && !self.force {
+            bail!("Wallet {} already exists", self.name);
+        }
+        fs::create_dir_all(wallets_dir())?;
+        if source.starts_with(wallets_dir()) {
+            fs::rename(&source, &destination)?;
+            let mut config = WalletsConfig::load()?;
+            let source_name = source.file_stem().and_then(|stem| stem.to_str());
+            if config.default.is_some() && config.default.as_deref() == source_name {
+                config.default = Some(self.name.clone());
+                config.save()?;
+            }
+        } else {
+            fs::copy(&source, &destination)?;
+        }
+        print_json(&json!({
+            "name": self.name,
+            "address": wallet.address()?,
+            "path": destination,
+        }))
+    }
+}
+
+/// Set the wallet to use when no '-f' option is given
+#[derive(Debug, clap::Args)]
+pub struct SetDefault {
+    /// Name of the wallet to use by default
+    name: String,
+}
+
+impl SetDefault {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        if !wallet_path(&self.name).exists() {
+            bail!("No wallet named {}", self.name);
+        }
+        let mut config = WalletsConfig::load()?;
+        config.default = Some(self.name.clone());
+        config.save()?;
+        print_json(&json!({ "default": self.name }))
+    }
+}
+
+/// Back up the wallet file to remote storage.
+///
+/// The wallet file, already encrypted with the wallet password, is encrypted
+/// again with a separate backup passphrase before it is uploaded. The upload
+/// is read back and decrypted to verify it.
+///
+/// S3 credentials and region are read from the standard AWS_ACCESS_KEY_ID,
+/// AWS_SECRET_ACCESS_KEY, AWS_SESSION_TOKEN and AWS_REGION environment
+/// variables. AWS_ENDPOINT_URL selects an S3 compatible service instead of
+/// AWS.
+#[derive(Debug, clap::Args)]
+pub struct Backup {
+    /// Location to store the backup at, s3://bucket/path or file:///path
+    #[arg(long)]
+    provider: Provider,
+}
+
+impl Backup {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let [file] = opts.files.as_slice() else {
+            bail!("back up the shards of a sharded wallet one at a time");
+        };
+        let path = resolve(file)?;
+        let contents = fs::read(&path)?;
+        // Make sure what is backed up is a readable wallet
+        let wallet = Wallet::read(&mut contents.as_slice())?;
+        let passphrase = get_password("Backup Passphrase", true)?;
+        let envelope = backup::seal(&contents, passphrase.as_bytes())?;
+        self.provider.put(&envelope).await?;
+
+        let uploaded = self.provider.get().await?;
+        if uploaded != envelope {
+            bail!(
+                "backup at {} does not match what was uploaded",
+                self.provider
+            );
+        }
+        if backup::open(&uploaded, passphrase.as_bytes())? != contents {
+            bail!("backup at {} does not decrypt to the wallet", self.provider);
+        }
+        print_json(&json!({
+            "address": wallet.address()?,
+            "path": path,
+            "provider": self.provider.to_string(),
+            "sha256": backup::hex(&Sha256::digest(&envelope)),
+            "verified": true,
+        }))
+    }
+}
+
+/// Restore a wallet file from a backup made with the backup command
+#[derive(Debug, clap::Args)]
+pub struct Restore {
+    /// Location of the backup, s3://bucket/path or file:///path
+    #[arg(long)]
+    provider: Provider,
+    /// File to write the restored wallet to
+    #[arg(long, short)]
+    output: PathBuf,
+    /// Overwrite an existing output file
+    #[arg(long)]
+    force: bool,
+}
+
+impl Restore {
+    pub async fn run(&self, _opts: Opts) -> Result {
+        let envelope = self.provider.get().await?;
+        let passphrase = get_password("Backup Passphrase", false)?;
+        let contents = backup::open(&envelope, passphrase.as_bytes())?;
+        let wallet = Wallet::read(&mut contents.as_slice())?;
+        let mut file = open_output_file(&self.output, !self.force)?;
+        io::Write::write_all(&mut file, &contents)?;
+        print_json(&json!({
+            "address": wallet.address()?,
+            "path": self.output,
+            "provider": self.provider.to_string(),
+        }))
+    }
+}
+
+/// Manage named Helium keypairs stored with the wallet.
+///
+/// Gateway keys, for example the keys used to sign config service requests,
+/// are encrypted with the wallet password and kept in a file next to the
+/// wallet file. Commands that take a '--gateway-key' option refer to them by
+/// name.
+#[derive(Debug, clap::Subcommand)]
+pub enum GatewayKeyCommand {
+    Add(GatewayKeyAdd),
+    List(GatewayKeyList),
+    Remove(GatewayKeyRemove),
+}
+
+impl GatewayKeyCommand {
+    pub async fn run(&self, opts: Opts) -> Result {
+        match self {
+            Self::Add(cmd) => cmd.run(opts).await,
+            Self::List(cmd) => cmd.run(opts).await,
+            Self::Remove(cmd) => cmd.run(opts).await,
+        }
+    }
+}
+
+/// Store a gateway key under a name, importing a keypair file or generating
+/// a new key
+#[derive(Debug, clap::Args)]
+pub struct GatewayKeyAdd {
+    /// Name for the key
+    name: String,
+    /// Helium keypair file to import. A new ed25519 key is generated if not
+    /// given
+    #[arg(long)]
+    file: Option<PathBuf>,
+    /// Replace an existing key with the same name
+    #[arg(long)]
+    force: bool,
+}
+
+impl GatewayKeyAdd {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let path = opts.gateway_keys_path()?;
+        let mut keys = GatewayKeys::load(&path)?;
+        if keys.contains(&self.name) && !self.force {
+            bail!("Gateway key {} already exists", self.name);
+        }
+        let keypair = match &self.file {
+            Some(file) => helium_crypto::Keypair::try_from(fs::read(file)?.as_slice())?,
+            None => helium_crypto::Keypair::generate(
+                helium_crypto::KeyTag::default(),
+                &mut rand::rngs::OsRng,
+            ),
+        };
+        let password = get_wallet_password(false)?;
+        // Make sure the key is encrypted with the wallet password
+        opts.load_keypair(password.as_bytes())?;
+        keys.insert(&self.name, &keypair, password.as_bytes())?;
+        keys.save(&path)?;
+        print_json(&json!({
+            "name": self.name,
+            "public_key": keypair.public_key().to_string(),
+            "path": path,
+        }))
+    }
+}
+
+/// List the gateway keys stored with the wallet
+#[derive(Debug, clap::Args)]
+pub struct GatewayKeyList {}
+
+impl GatewayKeyList {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let keys = GatewayKeys::load(&opts.gateway_keys_path()?)?;
+        let list: Vec<_> = keys
+            .list()
+            .map(|(name, public_key)| json!({ "name": name, "public_key": public_key }))
+            .collect();
+        print_json(&list)
+    }
+}
+
+/// Remove a gateway key stored with the wallet
+#[derive(Debug, clap::Args)]
+pub struct GatewayKeyRemove {
+    /// Name of the key to remove
+    name: String,
+}
+
+impl GatewayKeyRemove {
+    pub async fn run(&self, opts: Opts) -> Result {
+        let path = opts.gateway_keys_path()?;
+        let mut keys = GatewayKeys::load(&path)?;
+        if !keys.remove(&self.name) {
+            bail!("No gateway key named {}", self.name);
+        }
+        keys.save(&path)?;
+        print_json(&json!({ "removed": self.name }))
+    }
+}
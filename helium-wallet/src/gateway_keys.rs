@@ -0,0 +1,184 @@
+//! Named Helium keypairs kept alongside a wallet.
+//!
+//! Gateway keys, for example the keys config service requests are signed
+//! with, are stored in a `<wallet file>.gateway-keys.json` file next to the
+//! wallet and referred to by name. Each key is encrypted with the wallet
+//! password, using its own Argon2id parameters and XChaCha20-Poly1305, with
+//! the public key of the entry authenticated as well.
+use crate::{
+    pwhash::Argon2id13,
+    result::{anyhow, bail, Result},
+};
+use helium_lib::b64;
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::aead::xchacha20poly1305_ietf as aead;
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const EXTENSION: &str = "gateway-keys.json";
+
+/// Path of the gateway key file of the given wallet file
+pub fn path(wallet: &Path) -> PathBuf {
+    let mut path = OsString::from(wallet.as_os_str());
+    path.push(".");
+    path.push(EXTENSION);
+    PathBuf::from(path)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredKey {
+    public_key: String,
+    /// Salt, memory and passes of the key derivation
+    pwhash: String,
+    nonce: String,
+    encrypted: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GatewayKeys {
+    #[serde(flatten)]
+    keys: BTreeMap<String, StoredKey>,
+}
+
+impl GatewayKeys {
+    /// Read the keys in the given file. A missing file has no keys.
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::File::open(path) {
+            Ok(file) => serde_json::from_reader(file)
+                .map_err(|err| anyhow!("invalid {}: {err}", path.display())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result {
+        let file = fs::File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    /// Names and helium public keys of the stored keys
+    pub fn list(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.keys
+            .iter()
+            .map(|(name, key)| (name.as_str(), key.public_key.as_str()))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.keys.contains_key(name)
+    }
+
+    /// Encrypt the given keypair with the password and store it under the
+    /// given name, replacing any key with the same name
+    pub fn insert(
+        &mut self,
+        name: &str,
+        keypair: &helium_crypto::Keypair,
+        password: &[u8],
+    ) -> Result {
+        self.insert_with(name, keypair, password, Argon2id13::default())
+    }
+
+    fn insert_with(
+        &mut self,
+        name: &str,
+        keypair: &helium_crypto::Keypair,
+        password: &[u8],
+        pwhash: Argon2id13,
+    ) -> Result {
+        if name.is_empty() {
+            bail!("gateway key name can not be empty");
+        }
+        let public_key = keypair.public_key().to_string();
+        let key = derive_key(&pwhash, password)?;
+        let nonce = aead::gen_nonce();
+        let encrypted = aead::seal(&keypair.to_vec(), Some(public_key.as_bytes()), &nonce, &key);
+        let mut pwhash_bytes = vec![];
+        pwhash.write(&mut pwhash_bytes)?;
+        self.keys.insert(
+            name.to_string(),
+            StoredKey {
+                public_key,
+                pwhash: b64::encode(pwhash_bytes),
+                nonce: b64::encode(nonce.0),
+                encrypted: b64::encode(encrypted),
+            },
+        );
+        Ok(())
+    }
+
+    /// Remove the key with the given name, returning whether it existed
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.keys.remove(name).is_some()
+    }
+
+    /// Decrypt the key with the given name
+    pub fn get(&self, name: &str, password: &[u8]) -> Result<helium_crypto::Keypair> {
+        let stored = self
+            .keys
+            .get(name)
+            .ok_or_else(|| anyhow!("no gateway key named {name}"))?;
+        let mut pwhash = Argon2id13::default();
+        pwhash.read(&mut b64::decode(&stored.pwhash)?.as_slice())?;
+        let nonce = aead::Nonce::from_slice(&b64::decode(&stored.nonce)?)
+            .ok_or_else(|| anyhow!("invalid nonce for gateway key {name}"))?;
+        let key = derive_key(&pwhash, password)?;
+        let decrypted = aead::open(
+            &b64::decode(&stored.encrypted)?,
+            Some(stored.public_key.as_bytes()),
+            &nonce,
+            &key,
+        )
+        .map_err(|_| anyhow!("invalid password or corrupted gateway key {name}"))?;
+        let keypair = helium_crypto::Keypair::try_from(decrypted.as_slice())?;
+        if keypair.public_key().to_string() != stored.public_key {
+            bail!("gateway key {name} does not match its public key");
+        }
+        Ok(keypair)
+    }
+}
+
+fn derive_key(pwhash: &Argon2id13, password: &[u8]) -> Result<aead::Key> {
+    let mut key = [0u8; aead::KEYBYTES];
+    pwhash.pwhash(password, &mut key)?;
+    Ok(aead::Key(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helium_crypto::KeyTag;
+    use sodiumoxide::crypto::pwhash::argon2id13;
+
+    #[test]
+    fn roundtrip() {
+        let pwhash = Argon2id13::with_limits(
+            argon2id13::OpsLimit(1),
+            argon2id13::MemLimit(8 * 1024 * 1024),
+        );
+        let keypair = helium_crypto::Keypair::generate(KeyTag::default(), &mut rand::rngs::OsRng);
+        let mut keys = GatewayKeys::default();
+        keys.insert_with("field-router-1", &keypair, b"password", pwhash)
+            .expect("insert");
+
+        let json = serde_json::to_string(&keys).expect("serialize");
+        let keys: GatewayKeys = serde_json::from_str(&json).expect("deserialize");
+        let decrypted = keys.get("field-router-1", b"password").expect("get");
+        assert_eq!(decrypted.public_key(), keypair.public_key());
+        assert!(keys.get("field-router-1", b"wrong").is_err());
+        assert!(keys.get("other", b"password").is_err());
+        assert_eq!(
+            keys.list().collect::<Vec<_>>(),
+            vec![("field-router-1", keypair.public_key().to_string().as_str())]
+        );
+
+        assert_eq!(
+            path(Path::new("wallets/main.key")),
+            PathBuf::from("wallets/main.key.gateway-keys.json")
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! JSON canonicalization following RFC 8785 (JCS).
+//!
+//! Canonical JSON has no insignificant whitespace, object members sorted by
+//! the UTF-16 code units of their names, and strings and numbers serialized
+//! the way ECMAScript `JSON.stringify` does. Two parties holding the same
+//! JSON value produce the same bytes, which makes the output suitable for
+//! signing.
+use crate::result::{bail, Result};
+use serde_json::{Number, Value};
+
+/// Serialize the given value to its canonical form
+pub fn canonicalize(value: &Value) -> Result<String> {
+    let mut out = String::new();
+    write_value(&mut out, value)?;
+    Ok(out)
+}
+
+fn write_value(out: &mut String, value: &Value) -> Result {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => write_number(out, n)?,
+        Value::String(s) => out.push_str(&serde_json::to_string(s)?),
+        Value::Array(items) => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_value(out, item)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut members: Vec<(&String, &Value)> = map.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (index, (key, item)) in members.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::to_string(key)?);
+                out.push(':');
+                write_value(out, item)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+fn write_number(out: &mut String, n: &Number) -> Result {
+    if let Some(i) = n.as_i64() {
+        if i.unsigned_abs() <= MAX_SAFE_INTEGER {
+            out.push_str(&i.to_string());
+            return Ok(());
+        }
+    } else if let Some(u) = n.as_u64() {
+        if u <= MAX_SAFE_INTEGER {
+            out.push_str(&u.to_string());
+            return Ok(());
+        }
+    }
+    let Some(f) = n.as_f64().filter(|f| f.is_finite()) else {
+        bail!("number {n} can not be canonicalized");
+    };
+    out.push_str(&format_f64(f));
+    Ok(())
+}
+
+/// Integers beyond this lose precision as an IEEE 754 double
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Format a double the way ECMAScript does: the shortest round trip digits,
+/// in positional notation for exponents from -7 to 20 and scientific notation
+/// otherwise.
+fn format_f64(f: f64) -> String {
+    if f == 0.0 {
+        return "0".to_string();
+    }
+    let sign = if f < 0.0 { "-" } else { "" };
+    // Rust's exponent formatting gives the shortest round trip digits
+    let scientific = format!("{:e}", f.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    let exponent: i32 = exponent.parse().unwrap_or_default();
+    let k = digits.len() as i32;
+    let n = exponent + 1;
+
+    let formatted = if k <= n && n <= 21 {
+        format!("{digits}{}", "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        let (int, frac) = digits.split_at(n as usize);
+        format!("{int}.{frac}")
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{digits}", "0".repeat(-n as usize))
+    } else {
+        let (first, rest) = digits.split_at(1);
+        let fraction = if rest.is_empty() {
+            String::new()
+        } else {
+            format!(".{rest}")
+        };
+        let exponent_sign = if n - 1 < 0 { "-" } else { "+" };
+        format!("{first}{fraction}e{exponent_sign}{}", (n - 1).abs())
+    };
+    format!("{sign}{formatted}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn canonical_order_and_whitespace() {
+        let value: Value = serde_json::from_str(
+            r#"{ "b": [1, 2, {"z": null, "a": true}], "a": "x\n€", "é": 1, "😀": 2, "ﬁ": 3 }"#,
+        )
+        .expect("json");
+        assert_eq!(
+            canonicalize(&value).expect("canonical"),
+            "{\"a\":\"x\\n\u{20ac}\",\"b\":[1,2,{\"a\":true,\"z\":null}],\"\u{e9}\":1,\"\u{1f600}\":2,\"\u{fb01}\":3}"
+        );
+    }
+
+    #[test]
+    fn canonical_numbers() {
+        let cases = [
+            (json!(0), "0"),
+            (json!(-0.0), "0"),
+            (json!(1.0), "1"),
+            (json!(-1.5), "-1.5"),
+            (json!(1e20), "100000000000000000000"),
+            (json!(1e21), "1e+21"),
+            (json!(0.000001), "0.000001"),
+            (json!(1e-7), "1e-7"),
+            (json!(123.456e-10), "1.23456e-8"),
+            (json!(9007199254740993u64), "9007199254740992"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(canonicalize(&value).expect("canonical"), expected);
+        }
+    }
+}
@@ -1,7 +1,16 @@
+pub mod audit;
+pub mod backup;
 pub mod cmd;
-pub mod format;
-pub mod pwhash;
-pub mod read_write;
+pub mod gateway_keys;
+pub mod jcs;
+pub mod logging;
+pub mod offline;
+pub mod pairing;
+pub mod password;
+pub mod policy;
 pub mod result;
+pub mod trace;
 pub mod txn_envelope;
-pub mod wallet;
+pub mod warnings;
+
+pub use helium_wallet_core::{format, pwhash, read_write, wallet};
@@ -0,0 +1,126 @@
+//! Diagnostic logging of the wallet and the libraries it uses.
+//!
+//! Logs are off unless a level or a log file is given. Log files are rotated
+//! daily, so a log file `wallet.log` is written as `wallet.log.YYYY-MM-DD`
+//! for the current day, and only the most recent ones are kept. Fields that may hold
+//! secrets are written as `[redacted]`, and api keys in urls are masked, so
+//! a log can be attached to a bug report as is.
+use crate::result::{anyhow, Result};
+use std::{io, path::Path};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{
+    field::MakeExt,
+    fmt::{format, FormatFields},
+    EnvFilter,
+};
+
+/// Level used when only a log file is given
+pub const DEFAULT_LEVEL: &str = "info";
+/// Number of rotated log files kept
+const MAX_LOG_FILES: usize = 7;
+
+/// Field names that hold secrets, matched case insensitively anywhere in the
+/// name
+const SENSITIVE_FIELDS: &[&str] = &[
+    "password",
+    "passphrase",
+    "secret",
+    "seed",
+    "mnemonic",
+    "words",
+    "keypair",
+    "private",
+    "authorization",
+];
+/// Url query parameters that carry credentials
+const SENSITIVE_PARAMS: &[&str] = &["api-key=", "api_key=", "apikey=", "token="];
+const REDACTED: &str = "[redacted]";
+
+fn is_sensitive(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SENSITIVE_FIELDS.iter().any(|field| name.contains(field))
+}
+
+/// Mask the values of credential parameters in urls in the given text
+pub fn redact(text: &str) -> String {
+    let mut redacted = text.to_string();
+    for param in SENSITIVE_PARAMS {
+        let mut from = 0;
+        while let Some(found) = redacted[from..].to_ascii_lowercase().find(param) {
+            let start = from + found + param.len();
+            let end = redacted[start..]
+                .find(|c: char| c == '&' || c == '"' || c == '\'' || c.is_whitespace())
+                .map_or(redacted.len(), |end| start + end);
+            redacted.replace_range(start..end, REDACTED);
+            from = start + REDACTED.len();
+        }
+    }
+    redacted
+}
+
+fn fields() -> impl for<'writer> FormatFields<'writer> + Send + Sync + 'static {
+    format::debug_fn(|writer, field, value| {
+        let name = field.name();
+        if is_sensitive(name) {
+            return write!(writer, "{name}={REDACTED}");
+        }
+        let value = redact(&format!("{value:?}"));
+        if name == "message" {
+            write!(writer, "{value}")
+        } else {
+            write!(writer, "{name}={value}")
+        }
+    })
+    .delimited(" ")
+}
+
+/// Install the logger with the given filter, in the format of `RUST_LOG`,
+/// for example "warn,helium_lib::client=debug". Logs go to stderr unless a
+/// file is given, which gets the date of the day appended.
+pub fn init(filter: &str, file: Option<&Path>) -> Result {
+    let filter = EnvFilter::try_new(filter).map_err(|err| anyhow!("invalid log level: {err}"))?;
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .fmt_fields(fields());
+    let installed = match file {
+        Some(path) => {
+            let Some(prefix) = path.file_name() else {
+                return Err(anyhow!("invalid log file {}", path.display()));
+            };
+            let directory = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            let appender = RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix(prefix.to_string_lossy())
+                .max_log_files(MAX_LOG_FILES)
+                .build(directory)?;
+            builder.with_ansi(false).with_writer(appender).try_init()
+        }
+        None => builder.with_writer(io::stderr).try_init(),
+    };
+    installed.map_err(|err| anyhow!("failed to install logger: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redaction() {
+        assert!(is_sensitive("password"));
+        assert!(is_sensitive("backup_passphrase"));
+        assert!(is_sensitive("Keypair"));
+        assert!(!is_sensitive("url"));
+        assert_eq!(
+            redact("GET https://rpc.example.com/?api-key=abc123&x=1 failed"),
+            "GET https://rpc.example.com/?api-key=[redacted]&x=1 failed"
+        );
+        assert_eq!(
+            redact(r#"url="https://rpc.example.com/?API-KEY=abc""#),
+            r#"url="https://rpc.example.com/?API-KEY=[redacted]""#
+        );
+        assert_eq!(redact("no secrets"), "no secrets");
+    }
+}
@@ -1,10 +1,12 @@
-use clap::Parser;
+use clap::{CommandFactory, Parser};
+use helium_lib::error::Service;
 use helium_wallet::{
     cmd::{
-        assets, balance, burn, create, dc, export, hotspots, info, memo, price, router, sign,
-        transfer, upgrade, Opts,
+        assets, audit, balance, boosting, burn, create, dc, debug, export, history, hotspots, info,
+        memo, migrate, price, router, shards, sign, state, submit, summary, swap, token, transfer,
+        upgrade, wallets, Opts,
     },
-    result::Result,
+    result::{Error, Result},
 };
 
 static START: std::sync::Once = std::sync::Once::new();
@@ -28,18 +30,31 @@ pub struct Cli {
 pub enum Cmd {
     Info(info::Cmd),
     Balance(balance::Cmd),
+    Summary(summary::Cmd),
     Upgrade(upgrade::Cmd),
     Router(router::Cmd),
     Create(create::Cmd),
     Hotspots(Box<hotspots::Cmd>),
+    History(history::Cmd),
+    ExportHistory(history::ExportCmd),
     Dc(dc::Cmd),
     Price(price::Cmd),
+    Token(token::Cmd),
     Transfer(transfer::Cmd),
     Burn(burn::Cmd),
+    Boosting(boosting::Cmd),
+    Swap(swap::Cmd),
     Export(export::Cmd),
     Sign(sign::Cmd),
+    Submit(submit::Cmd),
     Memo(memo::Cmd),
     Assets(assets::Cmd),
+    Audit(audit::Cmd),
+    State(state::Cmd),
+    Wallets(wallets::Cmd),
+    Shards(shards::Cmd),
+    MigrateToMultisig(migrate::Cmd),
+    Debug(debug::Cmd),
 }
 
 #[allow(clippy::needless_return)]
@@ -47,28 +62,84 @@ pub enum Cmd {
 async fn main() -> Result {
     init();
     let cli = Cli::parse();
-    cli.run().await
+    match cli.run().await {
+        Err(err) if err.is::<router::balance::BelowThreshold>() => {
+            eprintln!("Error: {err}");
+            std::process::exit(router::balance::BelowThreshold::EXIT_STATUS)
+        }
+        Err(err) if err.is::<hotspots::ThresholdExceeded>() => {
+            eprintln!("Error: {err}");
+            std::process::exit(hotspots::ThresholdExceeded::EXIT_STATUS)
+        }
+        result => result.map_err(service_context),
+    }
+}
+
+/// Adds which service failed, and whether trying again may help, to errors
+/// caused by a failed request to a remote service
+fn service_context(err: Error) -> Error {
+    let Some(lib_err) = err
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<helium_lib::error::Error>())
+    else {
+        return err;
+    };
+    let service = lib_err.service();
+    if service == Service::Local {
+        return err;
+    }
+    let endpoint = lib_err
+        .endpoint()
+        .map(|endpoint| format!(" ({endpoint})"))
+        .unwrap_or_default();
+    let context = if lib_err.is_retryable() {
+        format!("{service}{endpoint} is unavailable, try again later")
+    } else {
+        format!("request rejected by {service}{endpoint}")
+    };
+    err.context(context)
 }
 
 impl Cli {
     async fn run(self) -> Result {
+        self.opts.init_logging()?;
         let client = self.opts.client()?;
         helium_lib::init(client.solana_client)?;
-        match self.cmd {
+        self.opts.init_password()?;
+        self.opts.init_audit_log(&Cli::command())?;
+        self.opts.init_policy()?;
+        self.opts.init_trace();
+        self.opts.init_cache()?;
+        let result = match self.cmd {
             Cmd::Info(cmd) => cmd.run(self.opts).await,
             Cmd::Balance(cmd) => cmd.run(self.opts).await,
+            Cmd::Summary(cmd) => cmd.run(self.opts).await,
             Cmd::Upgrade(cmd) => cmd.run(self.opts).await,
             Cmd::Router(cmd) => cmd.run(self.opts).await,
             Cmd::Create(cmd) => cmd.run(self.opts).await,
             Cmd::Hotspots(cmd) => cmd.run(self.opts).await,
+            Cmd::History(cmd) => cmd.run(self.opts).await,
+            Cmd::ExportHistory(cmd) => cmd.run(self.opts).await,
             Cmd::Dc(cmd) => cmd.run(self.opts).await,
             Cmd::Price(cmd) => cmd.run(self.opts).await,
+            Cmd::Token(cmd) => cmd.run(self.opts).await,
             Cmd::Transfer(cmd) => cmd.run(self.opts).await,
             Cmd::Burn(cmd) => cmd.run(self.opts).await,
+            Cmd::Boosting(cmd) => cmd.run(self.opts).await,
+            Cmd::Swap(cmd) => cmd.run(self.opts).await,
             Cmd::Export(cmd) => cmd.run(self.opts).await,
             Cmd::Sign(cmd) => cmd.run(self.opts).await,
+            Cmd::Submit(cmd) => cmd.run(self.opts).await,
             Cmd::Memo(cmd) => cmd.run(self.opts).await,
             Cmd::Assets(cmd) => cmd.run(self.opts).await,
-        }
+            Cmd::Audit(cmd) => cmd.run(self.opts).await,
+            Cmd::State(cmd) => cmd.run(self.opts).await,
+            Cmd::Wallets(cmd) => cmd.run(self.opts).await,
+            Cmd::Shards(cmd) => cmd.run(self.opts).await,
+            Cmd::MigrateToMultisig(cmd) => cmd.run(self.opts).await,
+            Cmd::Debug(cmd) => cmd.run(self.opts).await,
+        };
+        tracing::debug!(caches = %debug::command_stats_json(), "cache statistics");
+        result
     }
 }
@@ -0,0 +1,549 @@
+//! Transactions signed away from the machine that builds them.
+//!
+//! A command run with `--sign-only` outputs its transaction in a
+//! [`TransactionEnvelope`] without the signature of the wallet, keeping the
+//! signatures of any other signers, like reward oracles, that were added
+//! while building it. The envelope is then signed with `sign transaction`,
+//! which needs no network access, and broadcast with `submit`.
+//!
+//! Transactions carry a recent blockhash and expire about a minute after they
+//! are built, so the envelope has to be signed and submitted within that time.
+//!
+//! Reward claims that need the signature of the asset owner can be approved
+//! in bulk with a [`ClaimBundle`]. The bundle holds the prepared claims,
+//! including the signatures of the reward oracles, and is signed on the
+//! offline machine into [`ClaimSignatures`], which are imported back into the
+//! bundle for submission. Bundled claims are built on durable nonce accounts
+//! of the owner instead of a recent blockhash, so they do not expire, and
+//! only claims that do nothing but pay out rewards are signed.
+use crate::result::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use helium_lib::{
+    b64,
+    keypair::{serde_pubkey, Keypair, Pubkey, Signature, Signer},
+    reward::{self, ClaimSummary, ClaimableToken},
+    solana_sdk::{
+        hash::{hashv, Hash},
+        transaction::VersionedTransaction,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, sync::OnceLock};
+
+/// Version of the envelope format
+pub const ENVELOPE_VERSION: u8 = 1;
+/// Version of the claim bundle and claim signatures formats
+pub const CLAIM_BUNDLE_VERSION: u8 = 2;
+
+static WALLET: OnceLock<Pubkey> = OnceLock::new();
+
+/// Note the address of the wallet used by the command, whose signatures are
+/// left out of sign-only envelopes
+pub fn set_wallet(pubkey: Pubkey) {
+    let _ = WALLET.set(pubkey);
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionEnvelope {
+    pub version: u8,
+    /// Base64 encoded, bincode serialized versioned transaction
+    pub transaction: String,
+    pub recent_blockhash: String,
+    /// Address lookup tables the transaction loads accounts from
+    pub address_lookup_tables: Vec<String>,
+    /// The required signers of the transaction, in signature order
+    pub signers: Vec<EnvelopeSigner>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvelopeSigner {
+    #[serde(with = "serde_pubkey")]
+    pub address: Pubkey,
+    pub signed: bool,
+}
+
+impl TransactionEnvelope {
+    /// Wrap the given transaction, removing the signature of the wallet of
+    /// the command
+    pub fn unsigned(mut txn: VersionedTransaction) -> Result<Self> {
+        if let Some(wallet) = WALLET.get() {
+            if let Some(index) = signer_index(&txn, wallet) {
+                txn.signatures[index] = Signature::default();
+            }
+        }
+        Self::from_transaction(&txn)
+    }
+
+    pub fn from_transaction(txn: &VersionedTransaction) -> Result<Self> {
+        let signers = required_signers(txn)
+            .iter()
+            .zip(&txn.signatures)
+            .map(|(address, signature)| EnvelopeSigner {
+                address: *address,
+                signed: *signature != Signature::default(),
+            })
+            .collect();
+        let address_lookup_tables = txn
+            .message
+            .address_table_lookups()
+            .unwrap_or_default()
+            .iter()
+            .map(|lookup| lookup.account_key.to_string())
+            .collect();
+        Ok(Self {
+            version: ENVELOPE_VERSION,
+            transaction: b64::encode(bincode::serialize(txn)?),
+            recent_blockhash: txn.message.recent_blockhash().to_string(),
+            address_lookup_tables,
+            signers,
+        })
+    }
+
+    /// Read an envelope from a JSON file holding either the envelope itself
+    /// or the output of a sign-only command, which has it in an `envelope`
+    /// field
+    pub fn read(path: &std::path::Path) -> Result<Self> {
+        let mut value: serde_json::Value = serde_json::from_reader(std::fs::File::open(path)?)?;
+        if let Some(envelope) = value.get_mut("envelope") {
+            value = envelope.take();
+        }
+        let envelope: Self = serde_json::from_value(value)?;
+        if envelope.version != ENVELOPE_VERSION {
+            bail!("unsupported envelope version {}", envelope.version);
+        }
+        Ok(envelope)
+    }
+
+    pub fn transaction(&self) -> Result<VersionedTransaction> {
+        Ok(bincode::deserialize(&b64::decode(&self.transaction)?)?)
+    }
+
+    /// Add the signature of the given keypair, which must be a required
+    /// signer of the transaction
+    pub fn sign(&self, keypair: &Keypair) -> Result<Self> {
+        let mut txn = self.transaction()?;
+        let Some(index) = signer_index(&txn, &keypair.pubkey()) else {
+            bail!("{} is not a signer of the transaction", keypair.pubkey());
+        };
+        txn.signatures[index] = keypair.sign(&txn.message.serialize())?;
+        Self::from_transaction(&txn)
+    }
+
+    /// The signers that have not signed yet
+    pub fn missing_signers(&self) -> Vec<Pubkey> {
+        self.signers
+            .iter()
+            .filter(|signer| !signer.signed)
+            .map(|signer| signer.address)
+            .collect()
+    }
+}
+
+/// A batch of prepared reward claims awaiting the signature of the owner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimBundle {
+    pub version: u8,
+    pub token: ClaimableToken,
+    /// The wallet that has to sign every claim
+    #[serde(with = "serde_pubkey")]
+    pub owner: Pubkey,
+    pub created_at: DateTime<Utc>,
+    pub claims: Vec<BundledClaim>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledClaim {
+    pub entity_key: String,
+    /// Pending rewards of the asset when the claim was prepared
+    pub pending: Option<f64>,
+    /// Hash of the serialized transaction message
+    pub message_hash: String,
+    /// The claim transaction, signed by the reward oracles
+    pub envelope: TransactionEnvelope,
+}
+
+/// The owner signatures for the claims of a bundle, in claim order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimSignatures {
+    pub version: u8,
+    /// Hash of the message hashes of all claims of the signed bundle
+    pub bundle_hash: String,
+    #[serde(with = "serde_pubkey")]
+    pub signer: Pubkey,
+    pub signatures: Vec<ClaimSignature>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimSignature {
+    pub message_hash: String,
+    pub signature: String,
+}
+
+fn message_hash(txn: &VersionedTransaction) -> Hash {
+    hashv(&[&txn.message.serialize()])
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &std::path::Path) -> Result<T> {
+    Ok(serde_json::from_reader(std::fs::File::open(path)?)?)
+}
+
+impl ClaimBundle {
+    /// Bundle the given claims of assets, with their pending rewards, for the
+    /// given owner to sign
+    pub fn new(
+        token: ClaimableToken,
+        owner: Pubkey,
+        claims: Vec<(String, Option<f64>, VersionedTransaction)>,
+    ) -> Result<Self> {
+        let claims = claims
+            .into_iter()
+            .map(|(entity_key, pending, txn)| {
+                if signer_index(&txn, &owner).is_none() {
+                    bail!("{owner} is not a signer of the claim for {entity_key}");
+                }
+                Ok(BundledClaim {
+                    entity_key,
+                    pending,
+                    message_hash: message_hash(&txn).to_string(),
+                    envelope: TransactionEnvelope::from_transaction(&txn)?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            version: CLAIM_BUNDLE_VERSION,
+            token,
+            owner,
+            created_at: Utc::now(),
+            claims,
+        })
+    }
+
+    pub fn read(path: &std::path::Path) -> Result<Self> {
+        let bundle: Self = read_json(path)?;
+        if bundle.version != CLAIM_BUNDLE_VERSION {
+            bail!("unsupported claim bundle version {}", bundle.version);
+        }
+        bundle.verify()?;
+        Ok(bundle)
+    }
+
+    /// The hash binding signatures to the exact claims of this bundle
+    pub fn hash(&self) -> Result<Hash> {
+        let hashes = self
+            .claims
+            .iter()
+            .map(|claim| Ok(claim.message_hash.parse::<Hash>()?))
+            .collect::<Result<Vec<_>>>()?;
+        let hashes: Vec<&[u8]> = hashes.iter().map(|hash| hash.as_ref()).collect();
+        Ok(hashv(&hashes))
+    }
+
+    /// The claim transactions, checked against their recorded message
+    /// hashes
+    pub fn transactions(&self) -> Result<Vec<VersionedTransaction>> {
+        Ok(self.review()?.into_iter().map(|(txn, _)| txn).collect())
+    }
+
+    /// The claim transactions with what they pay out. Fails unless every
+    /// claim matches its recorded message hash, is a plain reward claim and
+    /// is built on a durable nonce account of the owner that no other claim
+    /// of the bundle uses.
+    pub fn review(&self) -> Result<Vec<(VersionedTransaction, ClaimSummary)>> {
+        let mut nonce_accounts = HashSet::new();
+        self.claims
+            .iter()
+            .map(|claim| {
+                let txn = claim.envelope.transaction()?;
+                if message_hash(&txn).to_string() != claim.message_hash {
+                    bail!("claim for {} does not match its hash", claim.entity_key);
+                }
+                if signer_index(&txn, &self.owner).is_none() {
+                    bail!(
+                        "{} is not a signer of the claim for {}",
+                        self.owner,
+                        claim.entity_key
+                    );
+                }
+                let summary = reward::decode_claim(&txn.message)
+                    .map_err(|err| anyhow!("claim for {}: {err}", claim.entity_key))?;
+                let Some(nonce_account) = summary.nonce_account else {
+                    bail!(
+                        "claim for {} is not built on a nonce account",
+                        claim.entity_key
+                    );
+                };
+                if !nonce_accounts.insert(nonce_account) {
+                    bail!(
+                        "claim for {} reuses nonce account {nonce_account}",
+                        claim.entity_key
+                    );
+                }
+                Ok((txn, summary))
+            })
+            .collect()
+    }
+
+    fn verify(&self) -> Result {
+        self.review().map(|_| ())
+    }
+
+    /// Sign every claim of the bundle with the given owner keypair
+    pub fn sign(&self, keypair: &Keypair) -> Result<ClaimSignatures> {
+        if keypair.pubkey() != self.owner {
+            bail!("bundle has to be signed by {}", self.owner);
+        }
+        let signatures = self
+            .transactions()?
+            .iter()
+            .map(|txn| {
+                Ok(ClaimSignature {
+                    message_hash: message_hash(txn).to_string(),
+                    signature: keypair.sign(&txn.message.serialize())?.to_string(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(ClaimSignatures {
+            version: CLAIM_BUNDLE_VERSION,
+            bundle_hash: self.hash()?.to_string(),
+            signer: keypair.pubkey(),
+            signatures,
+        })
+    }
+
+    /// Add the given owner signatures to the claims of the bundle. Fails
+    /// unless every signature was made by the owner over the exact message
+    /// of its claim.
+    pub fn apply(&self, signatures: &ClaimSignatures) -> Result<Vec<VersionedTransaction>> {
+        if signatures.version != CLAIM_BUNDLE_VERSION {
+            bail!(
+                "unsupported claim signatures version {}",
+                signatures.version
+            );
+        }
+        if signatures.signer != self.owner {
+            bail!("signatures are not made by the bundle owner {}", self.owner);
+        }
+        if signatures.bundle_hash != self.hash()?.to_string() {
+            bail!("signatures were made for a different bundle");
+        }
+        let txns = self.transactions()?;
+        if signatures.signatures.len() != txns.len() {
+            bail!(
+                "expected {} signatures, found {}",
+                txns.len(),
+                signatures.signatures.len()
+            );
+        }
+        txns.into_iter()
+            .zip(&self.claims)
+            .zip(&signatures.signatures)
+            .map(|((mut txn, claim), signed)| {
+                if signed.message_hash != claim.message_hash {
+                    bail!(
+                        "signature for {} is for a different claim",
+                        claim.entity_key
+                    );
+                }
+                let signature: Signature = signed
+                    .signature
+                    .parse()
+                    .map_err(|_| anyhow!("invalid signature for {}", claim.entity_key))?;
+                if !signature.verify(self.owner.as_ref(), &txn.message.serialize()) {
+                    bail!("invalid signature for {}", claim.entity_key);
+                }
+                let index = signer_index(&txn, &self.owner)
+                    .ok_or_else(|| anyhow!("{} is not a signer", self.owner))?;
+                txn.signatures[index] = signature;
+                Ok(txn)
+            })
+            .collect()
+    }
+}
+
+impl ClaimSignatures {
+    pub fn read(path: &std::path::Path) -> Result<Self> {
+        read_json(path)
+    }
+}
+
+fn required_signers(txn: &VersionedTransaction) -> &[Pubkey] {
+    let keys = txn.message.static_account_keys();
+    let count = usize::from(txn.message.header().num_required_signatures).min(keys.len());
+    &keys[..count]
+}
+
+fn signer_index(txn: &VersionedTransaction, signer: &Pubkey) -> Option<usize> {
+    required_signers(txn).iter().position(|key| key == signer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helium_lib::solana_sdk::{message::VersionedMessage, system_instruction};
+
+    #[test]
+    fn sign_envelope() {
+        let payer = Keypair::generate();
+        let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+        let message = helium_lib::solana_sdk::message::Message::new_with_blockhash(
+            &[ix],
+            Some(&payer.pubkey()),
+            &Hash::new_unique(),
+        );
+        let txn = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::Legacy(message),
+        };
+        let envelope = TransactionEnvelope::from_transaction(&txn).expect("envelope");
+        assert_eq!(envelope.missing_signers(), vec![payer.pubkey()]);
+
+        let signed = envelope.sign(&payer).expect("signed");
+        assert!(signed.missing_signers().is_empty());
+        assert!(signed
+            .transaction()
+            .expect("transaction")
+            .verify_with_results()
+            .iter()
+            .all(|verified| *verified));
+        assert!(envelope.sign(&Keypair::generate()).is_err());
+    }
+
+    fn unsigned_transfer(payer: &Keypair) -> VersionedTransaction {
+        let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+        let message = helium_lib::solana_sdk::message::Message::new_with_blockhash(
+            &[ix],
+            Some(&payer.pubkey()),
+            &Hash::new_unique(),
+        );
+        VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::Legacy(message),
+        }
+    }
+
+    fn unsigned_claim(owner: &Keypair, nonce_account: &Pubkey) -> VersionedTransaction {
+        use helium_lib::{
+            anchor_lang::{InstructionData, ToAccountMetas},
+            lazy_distributor, rewards_oracle,
+            solana_sdk::instruction::{AccountMeta, Instruction},
+        };
+        let payer = owner.pubkey();
+        let set_current = Instruction {
+            program_id: rewards_oracle::id(),
+            accounts: vec![AccountMeta::new(Pubkey::new_unique(), false); 3],
+            data: rewards_oracle::instruction::SetCurrentRewardsWrapperV1 {
+                _args: rewards_oracle::SetCurrentRewardsWrapperArgsV1 {
+                    current_rewards: 100,
+                    oracle_index: 0,
+                },
+            }
+            .data(),
+        };
+        let distribute = Instruction {
+            program_id: lazy_distributor::id(),
+            accounts: lazy_distributor::accounts::DistributeCompressionRewardsV0Common {
+                payer,
+                lazy_distributor: Pubkey::new_unique(),
+                associated_token_program: Pubkey::new_unique(),
+                rewards_mint: Pubkey::new_unique(),
+                rewards_escrow: Pubkey::new_unique(),
+                system_program: Pubkey::new_unique(),
+                token_program: Pubkey::new_unique(),
+                circuit_breaker_program: Pubkey::new_unique(),
+                owner: payer,
+                circuit_breaker: Pubkey::new_unique(),
+                recipient: Pubkey::new_unique(),
+                destination_account: Pubkey::new_unique(),
+            }
+            .to_account_metas(None),
+            data: lazy_distributor::instruction::DistributeCompressionRewardsV0 {
+                _args: lazy_distributor::DistributeCompressionRewardsArgsV0 {
+                    data_hash: [0; 32],
+                    creator_hash: [0; 32],
+                    root: [0; 32],
+                    index: 0,
+                },
+            }
+            .data(),
+        };
+        let ixs = [
+            system_instruction::advance_nonce_account(nonce_account, &payer),
+            set_current,
+            distribute,
+        ];
+        let message = helium_lib::solana_sdk::message::Message::new_with_blockhash(
+            &ixs,
+            Some(&payer),
+            &Hash::new_unique(),
+        );
+        VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::Legacy(message),
+        }
+    }
+
+    #[test]
+    fn claim_bundle() {
+        let owner = Keypair::generate();
+        let claims = vec![
+            (
+                "a".to_string(),
+                Some(1.5),
+                unsigned_claim(&owner, &Pubkey::new_unique()),
+            ),
+            (
+                "b".to_string(),
+                None,
+                unsigned_claim(&owner, &Pubkey::new_unique()),
+            ),
+        ];
+        let bundle = ClaimBundle::new(ClaimableToken::Hnt, owner.pubkey(), claims).expect("bundle");
+        assert!(bundle.sign(&Keypair::generate()).is_err());
+
+        let signatures = bundle.sign(&owner).expect("signatures");
+        let txns = bundle.apply(&signatures).expect("applied");
+        assert!(txns
+            .iter()
+            .all(|txn| txn.verify_with_results().iter().all(|verified| *verified)));
+
+        // Signatures are bound to the exact claims they were made for
+        let mut reordered = bundle.clone();
+        reordered.claims.reverse();
+        assert!(reordered.apply(&signatures).is_err());
+        let mut swapped = signatures.clone();
+        swapped.signatures.swap(0, 1);
+        assert!(bundle.apply(&swapped).is_err());
+        let mut tampered = bundle.clone();
+        tampered.claims[0].envelope =
+            TransactionEnvelope::from_transaction(&unsigned_transfer(&owner)).expect("envelope");
+        assert!(tampered.transactions().is_err());
+    }
+
+    #[test]
+    fn claim_bundle_allow_list() {
+        let owner = Keypair::generate();
+        let bundle = |claims: Vec<VersionedTransaction>| {
+            let claims = claims
+                .into_iter()
+                .enumerate()
+                .map(|(index, txn)| (index.to_string(), None, txn))
+                .collect();
+            ClaimBundle::new(ClaimableToken::Hnt, owner.pubkey(), claims).expect("bundle")
+        };
+        // Transactions other than plain claims are never signed
+        let transfer = bundle(vec![unsigned_transfer(&owner)]);
+        assert!(transfer.sign(&owner).is_err());
+        // Claims have to be built on distinct nonce accounts
+        let nonce_account = Pubkey::new_unique();
+        let reused = bundle(vec![
+            unsigned_claim(&owner, &nonce_account),
+            unsigned_claim(&owner, &nonce_account),
+        ]);
+        assert!(reused.sign(&owner).is_err());
+        let review = bundle(vec![unsigned_claim(&owner, &nonce_account)])
+            .review()
+            .expect("review");
+        assert_eq!(review[0].1.nonce_account, Some(nonce_account));
+        assert_eq!(review[0].1.lifetime_rewards, 100);
+    }
+}
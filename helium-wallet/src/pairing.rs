@@ -0,0 +1,179 @@
+//! Encrypted transfer of secrets between two machines.
+//!
+//! Both sides run SPAKE2 with a short pairing code as the password. This
+//! gives them the same key only if they used the same code, and neither an
+//! eavesdropper nor the other side learns anything that allows guessing the
+//! code offline. Every message is then encrypted with AES-256-GCM under a key
+//! derived for its direction, so a wrong code shows up as a message that
+//! fails to decrypt.
+//!
+//! Messages are framed with a big endian u32 length.
+use crate::result::{anyhow, bail, Result};
+use aes_gcm::{
+    aead::{generic_array::GenericArray, Aead},
+    Aes256Gcm, KeyInit,
+};
+use rand::{rngs::OsRng, Rng, RngCore};
+use sha2::{Digest, Sha256};
+use spake2::{Ed25519Group, Identity, Password, Spake2};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Port receivers listen on by default
+pub const DEFAULT_PORT: u16 = 7420;
+/// Number of digits in a pairing code
+const CODE_DIGITS: usize = 8;
+const NONCE_LEN: usize = 12;
+/// Largest message accepted from the other side
+const MAX_FRAME_LEN: u32 = 1 << 20;
+
+const SENDER_ID: &[u8] = b"helium-wallet pairing sender";
+const RECEIVER_ID: &[u8] = b"helium-wallet pairing receiver";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Sender,
+    Receiver,
+}
+
+impl Role {
+    fn label(&self) -> &'static [u8] {
+        match self {
+            Self::Sender => SENDER_ID,
+            Self::Receiver => RECEIVER_ID,
+        }
+    }
+
+    fn peer(&self) -> Self {
+        match self {
+            Self::Sender => Self::Receiver,
+            Self::Receiver => Self::Sender,
+        }
+    }
+}
+
+/// Generate a random pairing code of digits, grouped by four for reading
+/// aloud
+pub fn generate_code() -> String {
+    let digits: Vec<String> = (0..CODE_DIGITS)
+        .map(|_| OsRng.gen_range(0..10).to_string())
+        .collect();
+    digits
+        .chunks(4)
+        .map(|chunk| chunk.concat())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// A pairing code with separators and surrounding whitespace removed
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// An encrypted channel to the other side of a pairing
+pub struct Channel<S> {
+    stream: S,
+    outbound: Aes256Gcm,
+    inbound: Aes256Gcm,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Channel<S> {
+    /// Run the key exchange with the given pairing code over the given
+    /// stream
+    pub async fn pair(mut stream: S, code: &str, role: Role) -> Result<Self> {
+        let password = Password::new(normalize_code(code).as_bytes());
+        let (sender, receiver) = (Identity::new(SENDER_ID), Identity::new(RECEIVER_ID));
+        let (spake, outbound_msg) = match role {
+            Role::Sender => Spake2::<Ed25519Group>::start_a(&password, &sender, &receiver),
+            Role::Receiver => Spake2::<Ed25519Group>::start_b(&password, &sender, &receiver),
+        };
+        write_frame(&mut stream, &outbound_msg).await?;
+        let inbound_msg = read_frame(&mut stream).await?;
+        let key = spake
+            .finish(&inbound_msg)
+            .map_err(|_| anyhow!("invalid pairing message"))?;
+        Ok(Self {
+            stream,
+            outbound: direction_cipher(&key, role),
+            inbound: direction_cipher(&key, role.peer()),
+        })
+    }
+
+    pub async fn send(&mut self, data: &[u8]) -> Result {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let encrypted = self
+            .outbound
+            .encrypt(GenericArray::from_slice(&nonce), data)
+            .map_err(|_| anyhow!("failed to encrypt message"))?;
+        write_frame(&mut self.stream, &[nonce.as_slice(), &encrypted].concat()).await
+    }
+
+    pub async fn recv(&mut self) -> Result<Vec<u8>> {
+        let frame = read_frame(&mut self.stream).await?;
+        if frame.len() < NONCE_LEN {
+            bail!("invalid message");
+        }
+        let (nonce, encrypted) = frame.split_at(NONCE_LEN);
+        self.inbound
+            .decrypt(GenericArray::from_slice(nonce), encrypted)
+            .map_err(|_| anyhow!("pairing failed, check that both sides use the same code"))
+    }
+}
+
+/// The cipher for messages sent by the given role
+fn direction_cipher(key: &[u8], role: Role) -> Aes256Gcm {
+    let mut hasher = Sha256::new();
+    hasher.update(key);
+    hasher.update(role.label());
+    Aes256Gcm::new(&hasher.finalize())
+}
+
+async fn write_frame<S: AsyncWrite + Unpin>(stream: &mut S, data: &[u8]) -> Result {
+    let len = u32::try_from(data.len())?;
+    stream.write_u32(len).await?;
+    stream.write_all(data).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+async fn read_frame<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Vec<u8>> {
+    let len = stream.read_u32().await?;
+    if len > MAX_FRAME_LEN {
+        bail!("message of {len} bytes is too large");
+    }
+    let mut data = vec![0u8; len as usize];
+    stream.read_exact(&mut data).await?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn exchange(sender_code: &str, receiver_code: &str) -> Result<Vec<u8>> {
+        let (sender_stream, receiver_stream) = tokio::io::duplex(4096);
+        let sender = async {
+            let mut channel = Channel::pair(sender_stream, sender_code, Role::Sender).await?;
+            channel.send(b"shard").await
+        };
+        let receiver = async {
+            let mut channel = Channel::pair(receiver_stream, receiver_code, Role::Receiver).await?;
+            channel.recv().await
+        };
+        let (sent, received) = tokio::join!(sender, receiver);
+        sent?;
+        received
+    }
+
+    #[tokio::test]
+    async fn pairing() {
+        let code = generate_code();
+        assert_eq!(code.len(), CODE_DIGITS + 1);
+        let spaced = format!(" {} ", code.replace('-', " "));
+        assert_eq!(exchange(&code, &spaced).await.expect("paired"), b"shard");
+        assert!(exchange("1234-5678", "1234-5679").await.is_err());
+    }
+}
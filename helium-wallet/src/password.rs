@@ -0,0 +1,179 @@
+use helium_mnemonic::Language;
+use rand::{rngs::OsRng, Rng};
+use serde::Serialize;
+
+/// Number of words in a generated passphrase. Each word carries 11 bits of
+/// entropy.
+pub const PASSPHRASE_WORDS: usize = 6;
+
+/// Minimum strength score a wallet password needs unless weak passwords are
+/// explicitly allowed
+pub const MIN_SCORE: u8 = 2;
+
+/// Frequently used passwords and fragments, which add next to no strength
+/// regardless of the characters they use
+const COMMON: &[&str] = &[
+    "password", "passw0rd", "123456", "12345678", "qwerty", "abc123", "letmein", "welcome",
+    "monkey", "dragon", "iloveyou", "admin", "login", "master", "sunshine", "princess", "football",
+    "baseball", "shadow", "trustno1", "superman", "starwars", "whatever", "helium", "hotspot",
+    "wallet", "crypto", "bitcoin", "solana", "secret", "changeme", "default",
+];
+
+/// Keyboard and alphabet runs that are easy to guess
+const SEQUENCES: &[&str] = &[
+    "abcdefghijklmnopqrstuvwxyz",
+    "01234567890",
+    "qwertyuiop",
+    "asdfghjkl",
+    "zxcvbnm",
+];
+
+/// Estimated strength of a password
+#[derive(Debug, Clone, Serialize)]
+pub struct Strength {
+    /// Estimated entropy in bits, after penalties for guessable patterns
+    pub entropy: f64,
+    /// Score from 0 (trivially guessable) to 4 (very strong)
+    pub score: u8,
+    /// Guessable patterns found in the password
+    pub warnings: Vec<String>,
+}
+
+impl Strength {
+    pub fn is_weak(&self) -> bool {
+        self.score < MIN_SCORE
+    }
+}
+
+fn pool_size(password: &str) -> f64 {
+    let mut pool = 0;
+    if password.chars().any(|c| c.is_ascii_lowercase()) {
+        pool += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_uppercase()) {
+        pool += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_digit()) {
+        pool += 10;
+    }
+    if password
+        .chars()
+        .any(|c| c.is_ascii_punctuation() || c == ' ')
+    {
+        pool += 33;
+    }
+    if password.chars().any(|c| !c.is_ascii()) {
+        pool += 100;
+    }
+    f64::from(pool.max(1))
+}
+
+/// The number of characters of the password that are part of a run of the
+/// same character or of a well known sequence
+fn patterned_len(lower: &str) -> usize {
+    let chars: Vec<char> = lower.chars().collect();
+    let mut patterned = vec![false; chars.len()];
+    for window in 1..chars.len() {
+        if chars[window] == chars[window - 1] {
+            patterned[window] = true;
+        }
+    }
+    for sequence in SEQUENCES {
+        let reversed: String = sequence.chars().rev().collect();
+        for run in [*sequence, reversed.as_str()] {
+            for len in (3..=run.len()).rev() {
+                for start in 0..=run.len() - len {
+                    let fragment = &run[start..start + len];
+                    for (index, _) in lower.match_indices(fragment) {
+                        let first = lower[..index].chars().count();
+                        patterned[first + 1..first + len]
+                            .iter_mut()
+                            .for_each(|p| *p = true);
+                    }
+                }
+            }
+        }
+    }
+    patterned.into_iter().filter(|p| *p).count()
+}
+
+/// Estimate the strength of the given password.
+///
+/// This is a simple estimator in the spirit of zxcvbn: it starts from the
+/// brute force entropy of the character classes used and discounts common
+/// passwords, repeated characters and keyboard or alphabet sequences.
+pub fn strength(password: &str) -> Strength {
+    let lower = password.to_lowercase();
+    let len = password.chars().count();
+    let mut warnings = vec![];
+
+    let mut guessable = 0;
+    for common in COMMON {
+        if lower.contains(common) {
+            warnings.push(format!("contains the common password \"{common}\""));
+            guessable = guessable.max(common.chars().count());
+        }
+    }
+    let patterned = patterned_len(&lower);
+    if patterned > 0 {
+        warnings.push("contains repeated characters or sequences".to_string());
+    }
+    // A common password counts as a single guess among the common list
+    let effective_len = len.saturating_sub(guessable).saturating_sub(patterned);
+    let mut entropy = effective_len as f64 * pool_size(password).log2();
+    if guessable > 0 {
+        entropy += (COMMON.len() as f64).log2();
+    }
+    if len < 8 {
+        warnings.push("shorter than 8 characters".to_string());
+    }
+    let score = match entropy {
+        e if e < 28.0 => 0,
+        e if e < 36.0 => 1,
+        e if e < 60.0 => 2,
+        e if e < 80.0 => 3,
+        _ => 4,
+    };
+    Strength {
+        entropy,
+        score,
+        warnings,
+    }
+}
+
+/// Generate a random passphrase of BIP39 English words separated by dashes
+pub fn generate_passphrase() -> String {
+    let mut rng = OsRng;
+    (0..PASSPHRASE_WORDS)
+        .map(|_| &Language::English[rng.gen_range(0..2048)])
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weak_passwords() {
+        for password in [
+            "password123",
+            "12345678",
+            "aaaaaaaaaaaa",
+            "qwerty2024",
+            "abc",
+        ] {
+            assert!(strength(password).is_weak(), "{password} should be weak");
+        }
+    }
+
+    #[test]
+    fn strong_passwords() {
+        for password in ["correct-horse-battery-staple", "x7#Lp2!vQz9@Rm4s"] {
+            assert!(!strength(password).is_weak(), "{password} should be strong");
+        }
+        let passphrase = generate_passphrase();
+        assert_eq!(PASSPHRASE_WORDS, passphrase.split('-').count());
+        assert!(strength(&passphrase).score >= 3);
+    }
+}
@@ -0,0 +1,173 @@
+//! Address policy restricting where the wallet sends funds.
+//!
+//! A policy file lists addresses funds may go to, addresses they must never
+//! go to, or both. Every external recipient of a payment or asset transfer,
+//! and every destination of claimed rewards, is checked against it before a
+//! transaction is built. The wallet itself is always allowed.
+//!
+//! For example:
+//!
+//! ```json
+//! {
+//!     "allow": ["<treasury address>", "<payroll address>"],
+//!     "deny": ["<compromised address>"]
+//! }
+//! ```
+//!
+//! Without an "allow" list every address that is not denied is allowed. With
+//! one, even an empty one, only the listed addresses are.
+use crate::result::{anyhow, Result};
+use helium_lib::keypair::Pubkey;
+use serde::Deserialize;
+use std::{collections::HashSet, fs, io, path::Path, sync::OnceLock};
+
+/// Name of the policy file in the configuration directory
+pub const FILE: &str = "address-policy.json";
+
+static POLICY: OnceLock<AddressPolicy> = OnceLock::new();
+
+#[derive(Debug, Default, Deserialize)]
+struct PolicyFile {
+    allow: Option<Vec<String>>,
+    #[serde(default)]
+    deny: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct AddressPolicy {
+    allow: Option<HashSet<Pubkey>>,
+    deny: HashSet<Pubkey>,
+}
+
+fn parse_addresses(path: &Path, addresses: Vec<String>) -> Result<HashSet<Pubkey>> {
+    addresses
+        .into_iter()
+        .map(|address| {
+            address
+                .parse()
+                .map_err(|_| anyhow!("invalid address {address} in {}", path.display()))
+        })
+        .collect()
+}
+
+impl AddressPolicy {
+    /// Read the policy in the given file. A missing file is an empty policy
+    /// unless it is required.
+    pub fn load(path: &Path, required: bool) -> Result<Self> {
+        let file = match fs::File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !required => {
+                return Ok(Self::default())
+            }
+            Err(err) => return Err(anyhow!("opening {}: {err}", path.display())),
+        };
+        let PolicyFile { allow, deny } = serde_json::from_reader(file)
+            .map_err(|err| anyhow!("invalid {}: {err}", path.display()))?;
+        Ok(Self {
+            allow: allow
+                .map(|allow| parse_addresses(path, allow))
+                .transpose()?,
+            deny: parse_addresses(path, deny)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_none() && self.deny.is_empty()
+    }
+
+    pub fn check(&self, address: &Pubkey, role: &str) -> std::result::Result<(), PolicyViolation> {
+        let violation = |reason| PolicyViolation {
+            address: *address,
+            role: role.to_string(),
+            reason,
+        };
+        if self.deny.contains(address) {
+            return Err(violation("is on the deny list"));
+        }
+        match &self.allow {
+            Some(allow) if !allow.contains(address) => Err(violation("is not on the allow list")),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Enforce the given policy for the rest of the command
+pub fn init(policy: AddressPolicy) {
+    let _ = POLICY.set(policy);
+}
+
+/// Whether a non empty policy is in force. Lets callers skip lookups that are
+/// only needed for checks.
+pub fn is_active() -> bool {
+    POLICY.get().is_some_and(|policy| !policy.is_empty())
+}
+
+/// Check that funds may be sent to the given address, acting as the given
+/// role, for example "payee" or "reward destination"
+pub fn check(address: &Pubkey, role: &str) -> Result {
+    match POLICY.get() {
+        Some(policy) => Ok(policy.check(address, role)?),
+        None => Ok(()),
+    }
+}
+
+/// Like [`check`], but always allows the given wallet
+pub fn check_external(wallet: &Pubkey, address: &Pubkey, role: &str) -> Result {
+    if address == wallet {
+        return Ok(());
+    }
+    check(address, role)
+}
+
+/// Funds were about to go to an address the address policy does not allow
+#[derive(Debug)]
+pub struct PolicyViolation {
+    pub address: Pubkey,
+    pub role: String,
+    pub reason: &'static str,
+}
+
+impl std::fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "address policy violation: {} {} {}",
+            self.role, self.address, self.reason
+        )
+    }
+}
+
+impl std::error::Error for PolicyViolation {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allow_and_deny() {
+        let allowed = Pubkey::new_unique();
+        let denied = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        let deny_only = AddressPolicy {
+            allow: None,
+            deny: HashSet::from([denied]),
+        };
+        assert!(deny_only.check(&other, "payee").is_ok());
+        assert!(deny_only.check(&denied, "payee").is_err());
+
+        let both = AddressPolicy {
+            allow: Some(HashSet::from([allowed, denied])),
+            deny: HashSet::from([denied]),
+        };
+        assert!(both.check(&allowed, "payee").is_ok());
+        assert!(both.check(&denied, "payee").is_err());
+        let violation = both.check(&other, "payee").unwrap_err();
+        assert_eq!(
+            violation.to_string(),
+            format!("address policy violation: payee {other} is not on the allow list")
+        );
+
+        assert!(AddressPolicy::default().is_empty());
+    }
+}
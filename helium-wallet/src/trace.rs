@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use helium_lib::{
+    keypair::{Pubkey, Signature},
+    solana_sdk::transaction::VersionedTransaction,
+};
+use serde::Serialize;
+use std::{sync::OnceLock, time::Duration};
+
+/// Time allowed for the indexer to accept a submitted transaction before the
+/// request is abandoned
+const TRACE_TIMEOUT: Duration = Duration::from_secs(2);
+
+static TRACER: OnceLock<Tracer> = OnceLock::new();
+
+/// Enable reporting of all submitted transactions to the indexer at the given
+/// url for the given command
+pub fn init(url: &str, command: String) {
+    let _ = TRACER.set(Tracer::new(url, command));
+}
+
+/// Report a submitted transaction to the indexer, if one is configured.
+///
+/// The report is sent in the background so it never delays confirming the
+/// transaction. Failures to reach the indexer are ignored and never fail the
+/// command that submitted the transaction, and reports still in flight when
+/// the command exits are dropped.
+pub fn submitted(signature: &Signature, txn: &VersionedTransaction, cluster: &str) {
+    let Some(tracer) = TRACER.get() else {
+        return;
+    };
+    let event = TraceEvent {
+        timestamp: Utc::now(),
+        command: &tracer.command,
+        cluster: cluster.to_string(),
+        signature: signature.to_string(),
+        fee_payer: txn.message.static_account_keys().first().copied(),
+    };
+    tokio::spawn(async move { tracer.post(&event).await });
+}
+
+/// The context sent to the indexer for a submitted transaction
+#[derive(Debug, Serialize)]
+struct TraceEvent<'a> {
+    timestamp: DateTime<Utc>,
+    command: &'a str,
+    cluster: String,
+    signature: String,
+    #[serde(with = "helium_lib::keypair::serde_opt_pubkey")]
+    fee_payer: Option<Pubkey>,
+}
+
+struct Tracer {
+    url: String,
+    command: String,
+    client: reqwest::Client,
+}
+
+impl Tracer {
+    fn new(url: &str, command: String) -> Self {
+        let client = helium_lib::network::apply(reqwest::Client::builder())
+            .timeout(TRACE_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            url: url.to_string(),
+            command,
+            client,
+        }
+    }
+
+    async fn post(&self, event: &TraceEvent<'_>) {
+        let Ok(body) = serde_json::to_vec(event) else {
+            return;
+        };
+        let _ = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await;
+    }
+}
@@ -0,0 +1,154 @@
+//! Warnings raised while running a command.
+//!
+//! Warnings are noted on stderr as they are raised and collected so the JSON
+//! output of the command can include them in a `warnings` list. Each warning
+//! has a stable code that scripts can match on, independent of the wording of
+//! its message.
+use serde::Serialize;
+use std::sync::Mutex;
+
+/// The wallet file uses an older version of its format
+pub const LEGACY_WALLET_FORMAT: &str = "legacy-wallet-format";
+/// The wallet password is hashed with fewer iterations than the default
+pub const LOW_ITERATIONS: &str = "low-iterations";
+/// A wallet is used to sign against a devnet RPC url
+pub const DEVNET_URL: &str = "devnet-url";
+/// A token was named in upper case, token names are lower case
+pub const DEPRECATED_TOKEN_NAME: &str = "deprecated-token-name";
+/// An address was given as a Helium address instead of a Solana address
+pub const LEGACY_ADDRESS: &str = "legacy-address";
+/// The SOL balance may not cover transaction fees
+pub const LOW_SOL_BALANCE: &str = "low-sol-balance";
+/// The fee payer was topped up with SOL from another wallet
+pub const SOL_TOPUP: &str = "sol-topup";
+/// Hotspots have no asserted location
+pub const UNASSERTED_HOTSPOTS: &str = "unasserted-hotspots";
+/// A command stopped submitting transactions at its maximum total fee
+pub const FEE_CAP_REACHED: &str = "fee-cap-reached";
+/// No historical price was found to value a transaction
+pub const MISSING_PRICE: &str = "missing-price";
+/// A newly added asset was not found before the wait for it timed out
+pub const ASSET_NOT_INDEXED: &str = "asset-not-indexed";
+/// A Token-2022 mint withholds a fee from the amount sent
+pub const TRANSFER_FEE: &str = "transfer-fee";
+/// A current price came from a secondary source because pyth had none
+pub const FALLBACK_PRICE: &str = "fallback-price";
+/// The DAS proof of an asset is for a root its tree no longer accepts
+pub const STALE_PROOF: &str = "stale-proof";
+/// An alert could not be posted to a webhook
+pub const WEBHOOK_FAILED: &str = "webhook-failed";
+/// A poll of a watching command failed and is retried at the next interval
+pub const POLL_FAILED: &str = "poll-failed";
+
+static WARNINGS: Mutex<Vec<Warning>> = Mutex::new(vec![]);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Warning {
+    pub code: String,
+    pub message: String,
+}
+
+/// Raise a warning with the given code. Repeated warnings with the same code
+/// and message are only noted once.
+pub fn warn<S: ToString>(code: &str, message: S) {
+    let warning = Warning {
+        code: code.to_string(),
+        message: message.to_string(),
+    };
+    let mut warnings = WARNINGS.lock().unwrap_or_else(|err| err.into_inner());
+    if warnings.contains(&warning) {
+        return;
+    }
+    eprintln!("warning: {}", warning.message);
+    warnings.push(warning);
+}
+
+/// Raise a warning with the given code unless one with the same code was
+/// raised before, whatever its message. Used for warnings about the
+/// configuration of the whole process, which apply to every operation alike.
+pub fn warn_once<S: ToString>(code: &str, message: S) {
+    let raised = WARNINGS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .iter()
+        .any(|warning| warning.code == code);
+    if !raised {
+        warn(code, message);
+    }
+}
+
+/// The warnings raised so far
+pub fn get() -> Vec<Warning> {
+    WARNINGS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone()
+}
+
+/// Add the raised warnings to the given JSON output. Warnings are added to a
+/// `warnings` list in JSON objects, extending one that is already there.
+/// Other values are returned unchanged, their warnings are only noted on
+/// stderr.
+pub fn attach(mut value: serde_json::Value) -> serde_json::Value {
+    let warnings = get();
+    if warnings.is_empty() {
+        return value;
+    }
+    let Some(object) = value.as_object_mut() else {
+        return value;
+    };
+    let warnings = warnings
+        .into_iter()
+        .filter_map(|warning| serde_json::to_value(warning).ok());
+    match object.get_mut("warnings") {
+        Some(serde_json::Value::Array(existing)) => existing.extend(warnings),
+        _ => {
+            object.insert("warnings".to_string(), warnings.collect());
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Warnings are global to the process and other tests raise their own, so
+    // tests only check for the warnings they raise themselves
+    fn has_code(warnings: &serde_json::Value, code: &str) -> bool {
+        warnings
+            .as_array()
+            .is_some_and(|warnings| warnings.iter().any(|warning| warning["code"] == code))
+    }
+
+    #[test]
+    fn attach_warnings() {
+        warn(LOW_ITERATIONS, "few iterations");
+        warn(LOW_ITERATIONS, "few iterations");
+        let raised = get()
+            .into_iter()
+            .filter(|warning| warning.code == LOW_ITERATIONS)
+            .count();
+        assert_eq!(raised, 1);
+
+        let value = attach(json!({"address": "a"}));
+        assert!(has_code(&value["warnings"], LOW_ITERATIONS));
+        let value = attach(json!({"warnings": [{"code": "other", "message": "m"}]}));
+        assert_eq!(value["warnings"][0]["code"], "other");
+        assert!(has_code(&value["warnings"], LOW_ITERATIONS));
+        assert_eq!(attach(json!([1, 2])), json!([1, 2]));
+    }
+
+    #[test]
+    fn warn_once_per_code() {
+        warn_once(DEVNET_URL, "first");
+        warn_once(DEVNET_URL, "second");
+        let raised: Vec<Warning> = get()
+            .into_iter()
+            .filter(|warning| warning.code == DEVNET_URL)
+            .collect();
+        assert_eq!(raised.len(), 1);
+        assert_eq!(raised[0].message, "first");
+    }
+}